@@ -0,0 +1,37 @@
+(method_declaration
+  body: (_) @function.inside) @function.around
+
+(constructor_declaration
+  body: (_) @function.inside) @function.around
+
+(lambda_expression
+  body: (_) @function.inside) @function.around
+
+(class_declaration
+  body: (_) @class.inside) @class.around
+
+(interface_declaration
+  body: (_) @class.inside) @class.around
+
+(enum_declaration
+  body: (_) @class.inside) @class.around
+
+(record_declaration
+  body: (_) @class.inside) @class.around
+
+(formal_parameters
+  (_) @parameter.inside)
+
+(type_parameters
+  (_) @parameter.inside)
+
+(type_arguments
+  (_) @parameter.inside)
+
+(argument_list
+  (_) @parameter.inside)
+
+[
+  (line_comment)
+  (block_comment)
+] @comment.inside @comment.around
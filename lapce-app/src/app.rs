@@ -33,11 +33,10 @@
         style_helpers::{self, auto, fr},
         Line,
     },
-    text::{Style as FontStyle, Weight},
+    text::{Attrs, AttrsList, FamilyOwned, Style as FontStyle, TextLayout, Weight},
     unit::PxPctAuto,
     views::{
         clip, container, drag_resize_window_area, drag_window_area, dyn_stack,
-        editor::{core::register::Clipboard, text::SystemClipboard},
         empty, label, rich_text,
         scroll::{scroll, PropagatePointerWheel, VerticalScrollAsHorizontal},
         stack, svg, tab, text, tooltip, virtual_stack, Decorators, VirtualDirection,
@@ -57,14 +56,14 @@
     file::PathObject,
     RpcMessage,
 };
-use lsp_types::{CompletionItemKind, MessageType, ShowMessageParams};
+use lsp_types::{CompletionItemKind, Url};
 use notify::Watcher;
 use serde::{Deserialize, Serialize};
 use tracing_subscriber::{filter::Targets, reload::Handle};
 
 use crate::{
     about, alert,
-    code_action::CodeActionStatus,
+    code_action::{edit_preview_lines, CodeActionStatus, FileChange, PreviewLine},
     command::{
         CommandKind, InternalCommand, LapceCommand, LapceWorkbenchCommand,
         WindowCommand,
@@ -82,32 +81,38 @@
     },
     editor_tab::{EditorTabChild, EditorTabData},
     focus_text::focus_text,
+    hunk_peek,
     id::{EditorTabId, SplitId},
+    image_viewer::image_viewer_view,
     keymap::keymap_view,
     keypress::keymap::KeyMap,
     listener::Listener,
     main_split::{
         SplitContent, SplitData, SplitDirection, SplitMoveDirection, TabCloseKind,
     },
-    markdown::MarkdownContent,
+    markdown::{self, MarkdownContent},
+    markdown_preview::markdown_preview_view,
+    notification::{notification_history, notification_toasts},
     palette::{
         item::{PaletteItem, PaletteItemContent},
         PaletteStatus,
     },
     panel::{position::PanelContainerPosition, view::panel_container_view},
+    peek, perf,
     plugin::{plugin_info_view, PluginData},
     settings::{settings_view, theme_color_settings_view},
+    signature,
     status::status,
     text_input::TextInputBuilder,
     title::{title, window_controls_view},
     tracing::*,
     update::ReleaseInfo,
-    window::{TabsInfo, WindowData, WindowInfo},
+    window::{window_raiser, TabsInfo, WindowData, WindowInfo},
     window_tab::{Focus, WindowTabData},
     workspace::{LapceWorkspace, LapceWorkspaceType},
 };
 
-mod grammars;
+pub(crate) mod grammars;
 mod logging;
 
 #[derive(Parser)]
@@ -479,10 +484,12 @@ fn app_view(&self, window_id: WindowId, info: WindowInfo) -> impl View {
         let config = window_data.config;
         // The KeyDown and PointerDown event handlers both need ownership of a WindowData object.
         let key_down_window_data = window_data.clone();
+        let key_up_window_data = window_data.clone();
         let view =
             stack((
                 workspace_tab_header(window_data.clone()),
                 window(window_data.clone()),
+                window_raiser(window_data.raiser_id),
                 stack((
                     drag_resize_window_area(ResizeDirection::West, empty())
                         .style(|s| s.absolute().width(4.0).height_full()),
@@ -572,6 +579,7 @@ fn app_view(&self, window_id: WindowId, info: WindowInfo) -> impl View {
             .keyboard_navigatable()
             .on_event(EventListener::KeyDown, move |event| {
                 if let Event::KeyDown(key_event) = event {
+                    key_down_window_data.update_link_modifier(key_event, true);
                     if key_down_window_data.key_down(key_event) {
                         view_id.request_focus();
                     }
@@ -580,6 +588,11 @@ fn app_view(&self, window_id: WindowId, info: WindowInfo) -> impl View {
                     EventPropagation::Continue
                 }
             })
+            .on_event_cont(EventListener::KeyUp, move |event| {
+                if let Event::KeyUp(key_event) = event {
+                    key_up_window_data.update_link_modifier(key_event, false);
+                }
+            })
             .on_event(EventListener::PointerDown, {
                 let window_data = window_data.clone();
                 move |event| {
@@ -649,6 +662,7 @@ fn editor_tab_header(
     let config = window_tab_data.common.config;
     let internal_command = window_tab_data.common.internal_command;
     let workbench_command = window_tab_data.common.workbench_command;
+    let zen_mode = window_tab_data.zen_mode;
     let editor_tab_id =
         editor_tab.with_untracked(|editor_tab| editor_tab.editor_tab_id);
 
@@ -684,6 +698,7 @@ fn editor_tab_header(
         let child_for_close = child.clone();
         let child_for_mouse_close = child.clone();
         let child_for_mouse_close_2 = child.clone();
+        let child_for_pin = child.clone();
         let main_split = main_split.clone();
         let plugin = plugin.clone();
         let child_view = {
@@ -731,9 +746,14 @@ fn editor_tab_header(
                 },
             );
 
+            let is_pinned = create_memo(move |_| {
+                editor_tab.with(|t| t.is_pinned(&child_for_pin))
+            });
             let tab_close_button = clickable_icon(
                 move || {
-                    if hovered.get() || info.with(|info| info.is_pristine) {
+                    if is_pinned.get() {
+                        LapceIcons::PINNED
+                    } else if hovered.get() || info.with(|info| info.is_pristine) {
                         LapceIcons::CLOSE
                     } else {
                         LapceIcons::UNSAVED
@@ -742,14 +762,25 @@ fn editor_tab_header(
                 move || {
                     let editor_tab_id =
                         editor_tab.with_untracked(|t| t.editor_tab_id);
-                    internal_command.send(InternalCommand::EditorTabChildClose {
-                        editor_tab_id,
-                        child: child_for_close.clone(),
-                    });
+                    // The pin of a pinned tab unpins it rather than closing it.
+                    if is_pinned.get_untracked() {
+                        internal_command.send(InternalCommand::EditorTabChildPin {
+                            editor_tab_id,
+                            child: child_for_close.clone(),
+                            pinned: false,
+                        });
+                    } else {
+                        internal_command.send(
+                            InternalCommand::EditorTabChildClose {
+                                editor_tab_id,
+                                child: child_for_close.clone(),
+                            },
+                        );
+                    }
                 },
                 || false,
                 || false,
-                || "Close",
+                move || if is_pinned.get() { "Unpin" } else { "Close" },
                 config,
             )
             .on_event_stop(EventListener::PointerDown, |_| {})
@@ -859,10 +890,13 @@ fn editor_tab_header(
                     let editor_tab_id =
                         editor_tab.with_untracked(|t| t.editor_tab_id);
 
+                    let pinned = editor_tab
+                        .with_untracked(|t| t.is_pinned(&child_for_mouse_close_2));
                     tab_secondary_click(
                         internal_command,
                         editor_tab_id,
                         child_for_mouse_close_2.clone(),
+                        pinned,
                     );
                 })
                 .on_event_stop(EventListener::DragStart, move |_| {
@@ -988,6 +1022,7 @@ fn editor_tab_header(
 
     let content_size = create_rw_signal(Size::ZERO);
     let scroll_offset = create_rw_signal(Rect::ZERO);
+    let drag_over_bar = create_rw_signal(false);
     stack((
         stack({
             let size = create_rw_signal(Size::ZERO);
@@ -1072,7 +1107,49 @@ fn editor_tab_header(
                     .size_full()
             }),
         )
-        .style(|s| s.height_full().flex_grow(1.0).flex_basis(0.).min_width(10.))
+        .on_event_stop(EventListener::DragOver, move |_| {
+            if dragging.with_untracked(|dragging| dragging.is_some())
+                && !drag_over_bar.get_untracked()
+            {
+                drag_over_bar.set(true);
+            }
+        })
+        .on_event_stop(EventListener::DragLeave, move |_| {
+            drag_over_bar.set(false);
+        })
+        .on_event(EventListener::Drop, {
+            let main_split = main_split.clone();
+            move |_| {
+                drag_over_bar.set(false);
+                if let Some((from_index, from_editor_tab_id)) =
+                    dragging.get_untracked()
+                {
+                    // Dropped past the last tab, so it goes at the end.
+                    let to_index = editor_tab
+                        .with_untracked(|editor_tab| editor_tab.children.len());
+                    main_split.move_editor_tab_child(
+                        from_editor_tab_id,
+                        editor_tab_id,
+                        from_index.get_untracked(),
+                        to_index,
+                    );
+                    EventPropagation::Stop
+                } else {
+                    EventPropagation::Continue
+                }
+            }
+        })
+        .style(move |s| {
+            s.height_full()
+                .flex_grow(1.0)
+                .flex_basis(0.)
+                .min_width(10.)
+                .apply_if(drag_over_bar.get(), |s| {
+                    s.background(
+                        config.get().color(LapceColor::EDITOR_DRAG_DROP_BACKGROUND),
+                    )
+                })
+        })
         .debug_name("Tab scroll"),
         stack({
             let size = create_rw_signal(Size::ZERO);
@@ -1161,6 +1238,7 @@ fn editor_tab_header(
             .border_color(config.color(LapceColor::LAPCE_BORDER))
             .background(config.color(LapceColor::PANEL_BACKGROUND))
             .height(config.ui.header_height() as i32)
+            .apply_if(zen_mode.with(Option::is_some), |s| s.hide())
     })
     .debug_name("Editor Tab Header")
 }
@@ -1176,6 +1254,7 @@ fn editor_tab_content(
     let workspace = common.workspace.clone();
     let editors = main_split.editors;
     let diff_editors = main_split.diff_editors;
+    let image_viewers = main_split.image_viewers;
     let config = common.config;
     let focus = common.focus;
     let items = move || {
@@ -1262,8 +1341,14 @@ fn editor_tab_content(
                     let left_scroll_to = diff_editor_data.left.scroll_to();
                     let right_viewport = diff_editor_data.right.viewport();
                     let right_scroll_to = diff_editor_data.right.scroll_to();
+                    let inline = diff_editor_data.inline;
+                    // The sides aren't aligned line by line when the diff is
+                    // inline, and the left one is hidden.
                     create_effect(move |_| {
                         let left_viewport = left_viewport.get();
+                        if inline.get_untracked() {
+                            return;
+                        }
                         if right_viewport.get_untracked() != left_viewport {
                             right_scroll_to
                                 .set(Some(left_viewport.origin().to_vec2()));
@@ -1271,6 +1356,9 @@ fn editor_tab_content(
                     });
                     create_effect(move |_| {
                         let right_viewport = right_viewport.get();
+                        if inline.get() {
+                            return;
+                        }
                         if left_viewport.get_untracked() != right_viewport {
                             left_scroll_to
                                 .set(Some(right_viewport.origin().to_vec2()));
@@ -1308,6 +1396,7 @@ fn editor_tab_content(
                                 .border_color(
                                     config.get().color(LapceColor::LAPCE_BORDER),
                                 )
+                                .apply_if(inline.get(), |s| s.hide())
                         }),
                         container(
                             editor_container_view(
@@ -1353,6 +1442,20 @@ fn editor_tab_content(
             EditorTabChild::Volt(_, id) => {
                 plugin_info_view(plugin.clone(), id).into_any()
             }
+            EditorTabChild::Image(image_view_id, _) => {
+                if let Some(image_viewer) =
+                    image_viewers.with_untracked(|image_viewers| {
+                        image_viewers.get(&image_view_id).cloned()
+                    })
+                {
+                    image_viewer_view(image_viewer, config).into_any()
+                } else {
+                    text("empty image viewer").into_any()
+                }
+            }
+            EditorTabChild::MarkdownPreview(_, path) => {
+                markdown_preview_view(main_split.clone(), path).into_any()
+            }
         };
         child.style(|s| s.size_full())
     };
@@ -1964,6 +2067,7 @@ fn main_split(window_tab_data: Rc<WindowTabData>) -> impl View {
     let config = window_tab_data.main_split.common.config;
     let panel = window_tab_data.panel.clone();
     let plugin = window_tab_data.plugin.clone();
+    let zen_mode = window_tab_data.zen_mode;
     let dragging: RwSignal<Option<(RwSignal<usize>, EditorTabId)>> =
         create_rw_signal(None);
     split_list(
@@ -1976,12 +2080,18 @@ fn main_split(window_tab_data: Rc<WindowTabData>) -> impl View {
         let config = config.get();
         let is_hidden = panel.panel_bottom_maximized(true)
             && panel.is_container_shown(&PanelContainerPosition::Bottom, true);
+        let is_centered =
+            config.ui.zen_mode_centered && zen_mode.with(Option::is_some);
         s.border_color(config.color(LapceColor::LAPCE_BORDER))
             .background(config.color(LapceColor::EDITOR_BACKGROUND))
             .apply_if(is_hidden, |s| s.display(Display::None))
             .width_full()
             .flex_grow(1.0)
             .flex_basis(0.0)
+            .apply_if(is_centered, |s| {
+                s.max_width(config.ui.zen_mode_width() as f32)
+                    .margin_horiz(PxPctAuto::Auto)
+            })
     })
     .debug_name("Main Split")
 }
@@ -2134,7 +2244,14 @@ fn workbench(window_tab_data: Rc<WindowTabData>) -> impl View {
             .style(|s| s.flex_col().flex_grow(1.0))
         },
         panel_container_view(window_tab_data.clone(), PanelContainerPosition::Right),
-        window_message_view(window_tab_data.messages, window_tab_data.common.config),
+        notification_toasts(
+            window_tab_data.common.notifications.clone(),
+            window_tab_data.common.config,
+        ),
+        notification_history(
+            window_tab_data.common.notifications.clone(),
+            window_tab_data.common.config,
+        ),
     ))
     .on_resize(move |rect| {
         let size = rect.size();
@@ -2499,14 +2616,47 @@ fn palette_item(
                 .style(|s| s.width_full().items_center()),
             )
         }
+        PaletteItemContent::FileTemplate { template } => {
+            let text = item.filter_text;
+            let indices = item.indices;
+            let description = template.description.clone();
+            container(
+                stack((
+                    focus_text(
+                        move || text.clone(),
+                        move || indices.clone(),
+                        move || config.get().color(LapceColor::EDITOR_FOCUS),
+                    )
+                    .style(|s| s.margin_right(6.0).max_width_full()),
+                    label(move || description.clone()).style(move |s| {
+                        s.color(config.get().color(LapceColor::EDITOR_DIM))
+                            .min_width(0.0)
+                            .flex_grow(1.0)
+                            .flex_basis(0.0)
+                    }),
+                ))
+                .style(|s| s.align_items(Some(AlignItems::Center)).max_width_full()),
+            )
+        }
         PaletteItemContent::Line { .. }
         | PaletteItemContent::Workspace { .. }
         | PaletteItemContent::SshHost { .. }
         | PaletteItemContent::Language { .. }
         | PaletteItemContent::LineEnding { .. }
+        | PaletteItemContent::Encoding { .. }
+        | PaletteItemContent::SurroundPair { .. }
         | PaletteItemContent::ColorTheme { .. }
         | PaletteItemContent::SCMReference { .. }
         | PaletteItemContent::TerminalProfile { .. }
+        | PaletteItemContent::DirtyFile { .. }
+        | PaletteItemContent::SaveAllDirtyFiles { .. }
+        | PaletteItemContent::LocalVersion { .. }
+        | PaletteItemContent::Macro { .. }
+        | PaletteItemContent::Window { .. }
+        | PaletteItemContent::Terminal { .. }
+        | PaletteItemContent::TerminalLayout { .. }
+        | PaletteItemContent::Bookmark { .. }
+        | PaletteItemContent::Task { .. }
         | PaletteItemContent::IconTheme { .. } => {
             let text = item.filter_text;
             let indices = item.indices;
@@ -2776,122 +2926,6 @@ fn palette(window_tab_data: Rc<WindowTabData>) -> impl View {
     .debug_name("Pallete Layer")
 }
 
-fn window_message_view(
-    messages: RwSignal<Vec<(String, ShowMessageParams)>>,
-    config: ReadSignal<Arc<LapceConfig>>,
-) -> impl View {
-    let view_fn =
-        move |(i, (title, message)): (usize, (String, ShowMessageParams))| {
-            stack((
-                svg(move || {
-                    if let MessageType::ERROR = message.typ {
-                        config.get().ui_svg(LapceIcons::ERROR)
-                    } else {
-                        config.get().ui_svg(LapceIcons::WARNING)
-                    }
-                })
-                .style(move |s| {
-                    let config = config.get();
-                    let size = config.ui.icon_size() as f32;
-                    let color = if let MessageType::ERROR = message.typ {
-                        config.color(LapceColor::LAPCE_ERROR)
-                    } else {
-                        config.color(LapceColor::LAPCE_WARN)
-                    };
-                    s.min_width(size)
-                        .size(size, size)
-                        .margin_right(10.0)
-                        .margin_top(4.0)
-                        .color(color)
-                }),
-                stack((
-                    text(title.clone()).style(|s| {
-                        s.min_width(0.0).line_height(1.8).font_weight(Weight::BOLD)
-                    }),
-                    text(message.message.clone()).style(|s| {
-                        s.min_width(0.0).line_height(1.8).margin_top(5.0)
-                    }),
-                ))
-                .style(move |s| {
-                    s.flex_col().min_width(0.0).flex_basis(0.0).flex_grow(1.0)
-                }),
-                clickable_icon(
-                    || LapceIcons::CLOSE,
-                    move || {
-                        messages.update(|messages| {
-                            messages.remove(i);
-                        });
-                    },
-                    || false,
-                    || false,
-                    || "Close",
-                    config,
-                )
-                .style(|s| s.margin_left(6.0)),
-            ))
-            .on_double_click_stop(move |_| {
-                messages.update(|messages| {
-                    messages.remove(i);
-                });
-            })
-            .on_secondary_click_stop({
-                let message = message.message.clone();
-                move |_| {
-                    let mut clipboard = SystemClipboard::new();
-                    if !message.is_empty() {
-                        clipboard.put_string(&message);
-                    }
-                }
-            })
-            .on_event_stop(EventListener::PointerDown, |_| {})
-            .style(move |s| {
-                let config = config.get();
-                s.width_full()
-                    .items_start()
-                    .padding(10.0)
-                    .border(1.0)
-                    .border_radius(6.0)
-                    .border_color(config.color(LapceColor::LAPCE_BORDER))
-                    .background(config.color(LapceColor::PANEL_BACKGROUND))
-                    .apply_if(i > 0, |s| s.margin_top(10.0))
-            })
-        };
-
-    let id = AtomicU64::new(0);
-    container(
-        container(
-            container(
-                scroll(
-                    dyn_stack(
-                        move || messages.get().into_iter().enumerate(),
-                        move |_| {
-                            id.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
-                        },
-                        view_fn,
-                    )
-                    .style(|s| s.flex_col().width_full()),
-                )
-                .style(|s| {
-                    s.absolute()
-                        .width_full()
-                        .min_height(0.0)
-                        .max_height_full()
-                        .set(PropagatePointerWheel, false)
-                }),
-            )
-            .style(|s| s.size_full()),
-        )
-        .style(|s| {
-            s.width(360.0)
-                .max_width_pct(80.0)
-                .padding(10.0)
-                .height_full()
-        }),
-    )
-    .style(|s| s.absolute().size_full().justify_end())
-    .debug_name("Window Message View")
-}
-
 struct VectorItems<V>(im::Vector<V>);
 
 impl<V: Clone + 'static> VirtualVector<(usize, V)> for VectorItems<V> {
@@ -2934,17 +2968,56 @@ fn hover(window_tab_data: Rc<WindowTabData>) -> impl View {
     let config = window_tab_data.common.config;
     let id = AtomicU64::new(0);
     let layout_rect = window_tab_data.common.hover.layout_rect;
+    let internal_command = window_tab_data.common.internal_command;
 
     scroll(
         dyn_stack(
             move || hover_data.content.get(),
             move |_| id.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
             move |content| match content {
-                MarkdownContent::Text(text_layout) => container(
-                    rich_text(move || text_layout.clone())
-                        .style(|s| s.max_width(600.0)),
-                )
-                .style(|s| s.max_width_full()),
+                MarkdownContent::Text(text_layout, links) => {
+                    let width = create_rw_signal(0.0);
+                    let layout = text_layout.clone();
+                    container(
+                        rich_text(move || text_layout.clone())
+                            .on_resize(move |rect| width.set(rect.width()))
+                            .on_click_stop(move |event| {
+                                let Some(point) = event.point() else {
+                                    return;
+                                };
+                                // Lay the text out as wide as it's shown to find
+                                // what was clicked.
+                                let mut layout = layout.clone();
+                                layout.set_size(
+                                    width.get_untracked() as f32,
+                                    f32::MAX,
+                                );
+                                let hit = layout.hit_point(point);
+                                if let Some(url) = hit
+                                    .is_inside
+                                    .then(|| markdown::link_at(&links, hit.index))
+                                    .flatten()
+                                {
+                                    if markdown::is_external_link(
+                                        url,
+                                        markdown::WEB_LINK_SCHEMES,
+                                    ) {
+                                        internal_command.send(
+                                            InternalCommand::OpenWebUri {
+                                                uri: url.to_string(),
+                                            },
+                                        );
+                                    } else {
+                                        tracing::warn!(
+                                            "Ignored the hover link to {url}"
+                                        );
+                                    }
+                                }
+                            })
+                            .style(|s| s.max_width(600.0)),
+                    )
+                    .style(|s| s.max_width_full())
+                }
                 MarkdownContent::Image { .. } => container(empty()),
                 MarkdownContent::Separator => container(empty().style(move |s| {
                     s.width_full()
@@ -2985,6 +3058,105 @@ fn hover(window_tab_data: Rc<WindowTabData>) -> impl View {
     .debug_name("Hover Layer")
 }
 
+fn signature(window_tab_data: Rc<WindowTabData>) -> impl View {
+    let signature_data = window_tab_data.common.signature.clone();
+    let config = window_tab_data.common.config;
+    let editors = window_tab_data.main_split.editors;
+    let layout_rect = signature_data.layout_rect;
+    let signatures = signature_data.signatures;
+    let active = signature_data.active;
+
+    stack((
+        rich_text(move || {
+            let config = config.get();
+            let active_parameter = signature_data.active_parameter.get();
+            let (label, parameter) = signatures.with(|signatures| {
+                signatures
+                    .get(active.get())
+                    .map(|signature| {
+                        (
+                            signature.label.clone(),
+                            signature::parameter_range(signature, active_parameter),
+                        )
+                    })
+                    .unwrap_or_default()
+            });
+
+            let family: Vec<FamilyOwned> =
+                FamilyOwned::parse_list(&config.editor.font_family).collect();
+            let attrs = Attrs::new()
+                .family(&family)
+                .font_size(config.editor.font_size() as f32)
+                .color(config.color(LapceColor::EDITOR_FOREGROUND));
+            let mut attrs_list = AttrsList::new(attrs);
+            if config.editor.signature_label_code_block {
+                let language = editors
+                    .editor_untracked(signature_data.editor_id.get_untracked())
+                    .map(|editor| {
+                        editor.doc().syntax().with_untracked(|s| s.language)
+                    });
+                markdown::highlight_as_code(
+                    &mut attrs_list,
+                    attrs,
+                    language,
+                    &label,
+                    0,
+                    &config,
+                );
+            }
+            if let Some(parameter) = parameter {
+                attrs_list.add_span(
+                    parameter,
+                    attrs
+                        .weight(Weight::BOLD)
+                        .color(config.color(LapceColor::EDITOR_FOCUS)),
+                );
+            }
+            let mut text_layout = TextLayout::new();
+            text_layout.set_text(&label, attrs_list);
+            text_layout
+        })
+        .style(|s| s.max_width(600.0)),
+        label(move || {
+            format!(
+                "{}/{}",
+                active.get() + 1,
+                signatures.with(|signatures| signatures.len())
+            )
+        })
+        .style(move |s| {
+            s.margin_left(10.0)
+                .color(config.get().color(LapceColor::EDITOR_DIM))
+                .apply_if(signatures.with(|signatures| signatures.len() < 2), |s| {
+                    s.hide()
+                })
+        }),
+    ))
+    .on_resize(move |rect| {
+        layout_rect.set(rect);
+    })
+    .on_event_stop(EventListener::PointerMove, |_| {})
+    .on_event_stop(EventListener::PointerDown, |_| {})
+    .style(move |s| {
+        let config = config.get();
+        match window_tab_data.signature_origin() {
+            Some(origin) => s
+                .absolute()
+                .margin_left(origin.x as f32)
+                .margin_top(origin.y as f32)
+                .items_center()
+                .padding_horiz(10.0)
+                .padding_vert(5.0)
+                .border(1.0)
+                .border_radius(6.0)
+                .border_color(config.color(LapceColor::LAPCE_BORDER))
+                .background(config.color(LapceColor::PANEL_BACKGROUND)),
+            None => s.hide(),
+        }
+    })
+    .debug_name("Signature Layer")
+}
+
 fn completion(window_tab_data: Rc<WindowTabData>) -> impl View {
     let completion_data = window_tab_data.common.completion;
     let active_editor = window_tab_data.main_split.active_editor;
@@ -3119,7 +3291,106 @@ fn code_action(window_tab_data: Rc<WindowTabData>) -> impl View {
         .with_untracked(|code_action| (code_action.status, code_action.active));
     let request_id =
         move || code_action.with_untracked(|code_action| code_action.request_id);
-    scroll(
+    let workspace = window_tab_data.workspace.clone();
+    let docs = window_tab_data.main_split.docs;
+    let preview = move || {
+        let config = config.get();
+        let relative = |uri: &Url| {
+            let path = uri.to_file_path().unwrap_or_default();
+            workspace
+                .path
+                .as_ref()
+                .and_then(|workspace| path.strip_prefix(workspace).ok())
+                .unwrap_or(&path)
+                .display()
+                .to_string()
+        };
+        let mut lines = Vec::new();
+        for (uri, change) in
+            code_action.with(|code_action| code_action.active_preview())
+        {
+            let path = relative(&uri);
+            match change {
+                FileChange::Edits(edits) => {
+                    lines.push((PreviewLine::File, path));
+                    let doc = uri.to_file_path().ok().and_then(|path| {
+                        docs.with_untracked(|docs| docs.get(&path).cloned())
+                    });
+                    match doc.filter(|doc| doc.loaded()) {
+                        Some(doc) => {
+                            let text =
+                                doc.buffer.with(|buffer| buffer.text().clone());
+                            lines.extend(edit_preview_lines(&text, &edits));
+                        }
+                        // The text of a file that isn't open isn't known, so
+                        // only the text the edits insert is shown.
+                        None => {
+                            for edit in edits {
+                                lines.push((
+                                    PreviewLine::Context,
+                                    format!("line {}", edit.range.start.line + 1),
+                                ));
+                                lines.extend(edit.new_text.lines().map(|line| {
+                                    (PreviewLine::Added, line.to_string())
+                                }));
+                            }
+                        }
+                    }
+                }
+                FileChange::Create => {
+                    lines.push((PreviewLine::File, format!("{path} (created)")));
+                }
+                FileChange::Rename(new_uri) => {
+                    lines.push((
+                        PreviewLine::File,
+                        format!("{path} → {}", relative(&new_uri)),
+                    ));
+                }
+                FileChange::Delete => {
+                    lines.push((PreviewLine::File, format!("{path} (deleted)")));
+                }
+            }
+        }
+
+        let family: Vec<FamilyOwned> =
+            FamilyOwned::parse_list(&config.editor.font_family).collect();
+        let attrs = Attrs::new()
+            .family(&family)
+            .font_size(config.editor.font_size() as f32)
+            .color(config.color(LapceColor::EDITOR_DIM));
+        let mut attrs_list = AttrsList::new(attrs);
+        let mut text = String::new();
+        for (i, (kind, line)) in lines.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+            }
+            let (prefix, line_attrs) = match kind {
+                PreviewLine::File => (
+                    "",
+                    attrs
+                        .weight(Weight::BOLD)
+                        .color(config.color(LapceColor::EDITOR_FOREGROUND)),
+                ),
+                PreviewLine::Context => ("  ", attrs),
+                PreviewLine::Removed => (
+                    "- ",
+                    attrs.color(config.color(LapceColor::SOURCE_CONTROL_REMOVED)),
+                ),
+                PreviewLine::Added => (
+                    "+ ",
+                    attrs.color(config.color(LapceColor::SOURCE_CONTROL_ADDED)),
+                ),
+            };
+            let start = text.len();
+            text.push_str(prefix);
+            text.push_str(line);
+            attrs_list.add_span(start..text.len(), line_attrs);
+        }
+        let mut text_layout = TextLayout::new();
+        text_layout.set_text(&text, attrs_list);
+        text_layout
+    };
+    let list = scroll(
         container(
             dyn_stack(
                 move || {
@@ -3176,27 +3447,48 @@ fn code_action(window_tab_data: Rc<WindowTabData>) -> impl View {
                 active as f64 * config.editor.line_height() as f64,
             ))
     })
-    .on_resize(move |rect| {
-        code_action.update(|c| {
-            c.layout_rect = rect;
-        });
-    })
-    .on_event_stop(EventListener::PointerMove, |_| {})
-    .style(move |s| {
-        let origin = window_tab_data.code_action_origin();
-        s.display(match status.get() {
-            CodeActionStatus::Inactive => Display::None,
-            CodeActionStatus::Active => Display::Flex,
+    .style(|s| s.width_full().min_height(0.0).flex_grow(1.0));
+
+    // The changes the workspace edit of the selected action makes.
+    let preview = scroll(rich_text(preview).style(|s| s.padding_horiz(10.0))).style(
+        move |s| {
+            let config = config.get();
+            s.width_full()
+                .max_height(200.0)
+                .padding_vert(4.0)
+                .border_top(1.0)
+                .border_color(config.color(LapceColor::LAPCE_BORDER))
+                .apply_if(
+                    code_action
+                        .with(|code_action| code_action.active_preview().is_empty()),
+                    |s| s.hide(),
+                )
+        },
+    );
+
+    stack((list, preview))
+        .on_resize(move |rect| {
+            code_action.update(|c| {
+                c.layout_rect = rect;
+            });
         })
-        .position(Position::Absolute)
-        .width(400.0)
-        .max_height(400.0)
-        .margin_left(origin.x as f32)
-        .margin_top(origin.y as f32)
-        .background(config.get().color(LapceColor::COMPLETION_BACKGROUND))
-        .border_radius(6.0)
-    })
-    .debug_name("Code Action Layer")
+        .on_event_stop(EventListener::PointerMove, |_| {})
+        .style(move |s| {
+            let origin = window_tab_data.code_action_origin();
+            s.display(match status.get() {
+                CodeActionStatus::Inactive => Display::None,
+                CodeActionStatus::Active => Display::Flex,
+            })
+            .flex_col()
+            .position(Position::Absolute)
+            .width(400.0)
+            .max_height(400.0)
+            .margin_left(origin.x as f32)
+            .margin_top(origin.y as f32)
+            .background(config.get().color(LapceColor::COMPLETION_BACKGROUND))
+            .border_radius(6.0)
+        })
+        .debug_name("Code Action Layer")
 }
 
 fn rename(window_tab_data: Rc<WindowTabData>) -> impl View {
@@ -3248,6 +3540,7 @@ fn window_tab(window_tab_data: Rc<WindowTabData>) -> impl View {
     let workbench_command = window_tab_data.common.workbench_command;
     let window_tab_scope = window_tab_data.scope;
     let hover_active = window_tab_data.common.hover.active;
+    let text_drag = window_tab_data.common.text_drag;
     let status_height = window_tab_data.status_height;
 
     let view = stack((
@@ -3272,11 +3565,15 @@ fn window_tab(window_tab_data: Rc<WindowTabData>) -> impl View {
         .debug_name("Base Layer"),
         completion(window_tab_data.clone()),
         hover(window_tab_data.clone()),
+        signature(window_tab_data.clone()),
         code_action(window_tab_data.clone()),
         rename(window_tab_data.clone()),
         palette(window_tab_data.clone()),
         about::about_popup(window_tab_data.clone()),
+        hunk_peek::hunk_peek(window_tab_data.hunk_peek.clone()),
+        peek::peek(window_tab_data.clone()),
         alert::alert_box(window_tab_data.alert_data.clone()),
+        perf::perf_overlay(window_tab_data.clone()),
     ))
     .on_cleanup(move || {
         window_tab_scope.dispose();
@@ -3285,6 +3582,21 @@ fn window_tab(window_tab_data: Rc<WindowTabData>) -> impl View {
         if hover_active.get_untracked() {
             hover_active.set(false);
         }
+        // Text dragged out of the editors has nowhere to be dropped.
+        if text_drag.with_untracked(|drag| {
+            drag.as_ref().is_some_and(|drag| drag.target.is_some())
+        }) {
+            text_drag.update(|drag| {
+                if let Some(drag) = drag {
+                    drag.target = None;
+                }
+            });
+        }
+    })
+    .on_event_cont(EventListener::PointerUp, move |_| {
+        if text_drag.with_untracked(Option::is_some) {
+            text_drag.set(None);
+        }
     })
     .style(move |s| {
         let config = config.get();
@@ -3615,12 +3927,8 @@ fn window(window_data: WindowData) -> impl View {
     .window_title(move || {
         let active = active();
         let window_tabs = window_tabs.get();
-        let workspace = window_tabs
-            .get(active)
-            .or_else(|| window_tabs.last())
-            .and_then(|(_, window_tab)| window_tab.workspace.display());
-        match workspace {
-            Some(workspace) => format!("{workspace} - Lapce"),
+        match window_tabs.get(active).or_else(|| window_tabs.last()) {
+            Some((_, window_tab)) => window_tab.window_title(),
             None => "Lapce".to_string(),
         }
     })
@@ -3794,6 +4102,11 @@ pub fn launch() {
             tracing::error!("{:?}", err);
         }
     }
+    if let Some(path) = Directory::snippets_directory() {
+        if let Err(err) = watcher.watch(&path, notify::RecursiveMode::Recursive) {
+            tracing::error!("{:?}", err);
+        }
+    }
     if let Some(path) = LapceConfig::keymaps_file() {
         if let Err(err) = watcher.watch(&path, notify::RecursiveMode::Recursive) {
             tracing::error!("{:?}", err);
@@ -3825,6 +4138,7 @@ pub fn launch() {
         plugin_paths,
     };
 
+    provide_context(app_data.clone());
     let app = app_data.create_windows(db.clone(), cli.paths);
 
     {
@@ -4216,11 +4530,13 @@ fn tab_secondary_click(
     internal_command: Listener<InternalCommand>,
     editor_tab_id: EditorTabId,
     child: EditorTabChild,
+    pinned: bool,
 ) {
     let mut menu = Menu::new("");
     let child_other = child.clone();
     let child_right = child.clone();
     let child_left = child.clone();
+    let child_pin = child.clone();
     menu = menu
         .entry(MenuItem::new("Close").action(move || {
             internal_command.send(InternalCommand::EditorTabChildClose {
@@ -4251,6 +4567,16 @@ fn tab_secondary_click(
                 child: child_left.clone(),
                 kind: TabCloseKind::CloseToLeft,
             });
-        }));
+        }))
+        .separator()
+        .entry(MenuItem::new(if pinned { "Unpin" } else { "Pin" }).action(
+            move || {
+                internal_command.send(InternalCommand::EditorTabChildPin {
+                    editor_tab_id,
+                    child: child_pin.clone(),
+                    pinned: !pinned,
+                });
+            },
+        ));
     show_context_menu(menu, None);
 }
@@ -1,13 +1,14 @@
 use std::{
     env,
     fs::{self},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::Command,
 };
 
 use anyhow::{anyhow, Context, Result};
-use lapce_core::directory::Directory;
+use lapce_core::{directory::Directory, language::LapceLanguage};
 
-use crate::{tracing::*, update::ReleaseInfo};
+use crate::{config::core::GrammarSource, tracing::*, update::ReleaseInfo};
 
 fn get_github_api(url: &str) -> Result<String> {
     let user_agent = format!("Lapce/{}", lapce_core::meta::VERSION);
@@ -139,3 +140,166 @@ fn download_release(
     }
     Ok(true)
 }
+
+/// Install the grammar of `language` and its queries from `source`, cloning
+/// it when it's a git repository and compiling the parser unless the source
+/// has a built library already.
+pub fn install_grammar(
+    language: LapceLanguage,
+    source: &GrammarSource,
+) -> Result<()> {
+    let grammars_dir = Directory::grammars_directory()
+        .ok_or_else(|| anyhow!("can't get grammars directory"))?;
+    let queries_dir = Directory::queries_directory()
+        .ok_or_else(|| anyhow!("can't get queries directory"))?;
+    let grammar_name = language.grammar_name();
+
+    let local = PathBuf::from(&source.source);
+    // Kept until the grammar is built, as it's removed when dropped
+    let mut clone_dir = None;
+    let root = if local.is_dir() {
+        local
+    } else {
+        let dir = tempfile::tempdir()?;
+        let mut git = Command::new("git");
+        git.args(["clone", "--depth", "1"]);
+        if let Some(rev) = source.rev.as_ref() {
+            git.arg(format!("--branch={rev}"));
+        }
+        // The source is never taken for an option, even if it starts with `-`.
+        let output = git.arg("--").arg(&source.source).arg(dir.path()).output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "failed to clone {}: {}",
+                source.source,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let root = dir.path().to_path_buf();
+        clone_dir = Some(dir);
+        root
+    };
+    let root = match source.subpath.as_ref() {
+        Some(subpath) => root.join(subpath),
+        None => root,
+    };
+
+    let mut library_path =
+        grammars_dir.join(format!("libtree-sitter-{grammar_name}"));
+    library_path.set_extension(env::consts::DLL_EXTENSION);
+    let mut built = root.join(format!("libtree-sitter-{grammar_name}"));
+    built.set_extension(env::consts::DLL_EXTENSION);
+    // The library may be loaded already, and overwriting it in place would
+    // crash the editor, so it's replaced by renaming a new file over it.
+    let library = tempfile::NamedTempFile::new_in(&grammars_dir)?;
+    if built.exists() {
+        fs::copy(&built, library.path())?;
+    } else {
+        compile_grammar(&root.join("src"), library.path())?;
+    }
+    library.persist(&library_path)?;
+
+    let query_dir = queries_dir.join(language.query_name());
+    fs::create_dir_all(&query_dir)?;
+    for file in [
+        LapceLanguage::HIGHLIGHTS_QUERIES_FILE_NAME,
+        LapceLanguage::HIGHLIGHTS_INJECTIONS_FILE_NAME,
+        LapceLanguage::TEXTOBJECTS_QUERIES_FILE_NAME,
+    ] {
+        let query = root.join("queries").join(file);
+        if query.exists() {
+            fs::copy(&query, query_dir.join(file))?;
+        }
+    }
+
+    drop(clone_dir);
+    trace!(TraceLevel::INFO, "Installed the grammar {grammar_name}");
+    Ok(())
+}
+
+/// Compile the parser and scanner in `src` into the library at `output`,
+/// the C sources with the C compiler, `CC`, and a C++ scanner with the C++
+/// one, `CXX`, which then links them.
+fn compile_grammar(src: &Path, output: &Path) -> Result<()> {
+    let parser = src.join("parser.c");
+    if !parser.exists() {
+        return Err(anyhow!("no parser.c found in {}", src.display()));
+    }
+    let mut sources = vec![(parser, false)];
+    let cpp_scanner = src.join("scanner.cc");
+    let c_scanner = src.join("scanner.c");
+    if cpp_scanner.exists() {
+        sources.push((cpp_scanner, true));
+    } else if c_scanner.exists() {
+        sources.push((c_scanner, false));
+    }
+    let cpp = sources.iter().any(|(_, cpp)| *cpp);
+
+    let mut build = cc::Build::new();
+    build
+        .opt_level(2)
+        .cargo_metadata(false)
+        .host(lapce_core::meta::TARGET)
+        .target(lapce_core::meta::TARGET);
+    let c_compiler = build.get_compiler();
+    let cpp_compiler = build.cpp(true).get_compiler();
+    let msvc = c_compiler.is_like_msvc();
+
+    let objects_dir = tempfile::tempdir()?;
+    let mut objects = Vec::new();
+    for (i, (source, cpp)) in sources.iter().enumerate() {
+        let compiler = if *cpp { &cpp_compiler } else { &c_compiler };
+        let object = objects_dir.path().join(format!("{i}.o"));
+        let mut command = compiler.to_command();
+        if msvc {
+            command
+                .args(["/nologo", "/c", "/utf-8", "/I"])
+                .arg(src)
+                .arg(source)
+                .arg(format!("/Fo{}", object.display()));
+        } else {
+            command
+                .args(["-c", "-fPIC", "-I"])
+                .arg(src)
+                .arg(source)
+                .arg("-o")
+                .arg(&object);
+        }
+        run_compiler(command, compiler)?;
+        objects.push(object);
+    }
+
+    // Linked by the C++ compiler with a C++ scanner, for its runtime.
+    let linker = if cpp { &cpp_compiler } else { &c_compiler };
+    let mut command = linker.to_command();
+    if msvc {
+        command
+            .args(["/nologo", "/LD"])
+            .args(&objects)
+            .arg("/link")
+            .arg(format!("/out:{}", output.display()));
+    } else {
+        command.arg("-shared").args(&objects);
+        if cfg!(target_os = "macos") {
+            command.args(["-undefined", "dynamic_lookup"]);
+        }
+        command.arg("-o").arg(output);
+    }
+    run_compiler(command, linker)
+}
+
+fn run_compiler(mut command: Command, compiler: &cc::Tool) -> Result<()> {
+    let output = command.output().with_context(|| {
+        format!(
+            "failed to run {} to compile the grammar",
+            compiler.path().display()
+        )
+    })?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to compile the grammar: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,183 @@
+//! Bookmarks of lines, kept with the workspace so they can be jumped between
+//! across files.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub line: usize,
+    pub label: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bookmarks {
+    files: BTreeMap<PathBuf, BTreeMap<usize, Bookmark>>,
+}
+
+impl Bookmarks {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The bookmarks of `path`, by line.
+    pub fn get(&self, path: &Path) -> Option<&BTreeMap<usize, Bookmark>> {
+        self.files.get(path)
+    }
+
+    /// Bookmark `line` of `path`, or remove the bookmark it has. Returns
+    /// whether the line is bookmarked now.
+    pub fn toggle(&mut self, path: &Path, line: usize) -> bool {
+        let added = match self.files.get_mut(path) {
+            Some(bookmarks) if bookmarks.contains_key(&line) => {
+                bookmarks.remove(&line);
+                false
+            }
+            _ => {
+                self.set(path, line, None);
+                true
+            }
+        };
+        self.files.retain(|_, bookmarks| !bookmarks.is_empty());
+        added
+    }
+
+    /// Bookmark `line` of `path` with `label`, replacing the bookmark it has.
+    pub fn set(&mut self, path: &Path, line: usize, label: Option<String>) {
+        self.files
+            .entry(path.to_path_buf())
+            .or_default()
+            .insert(line, Bookmark { line, label });
+    }
+
+    pub fn remove(&mut self, path: &Path, line: usize) {
+        if let Some(bookmarks) = self.files.get_mut(path) {
+            bookmarks.remove(&line);
+            if bookmarks.is_empty() {
+                self.files.remove(path);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+
+    /// All the bookmarks, ordered by path and then by line.
+    pub fn all(&self) -> Vec<(PathBuf, Bookmark)> {
+        self.files
+            .iter()
+            .flat_map(|(path, bookmarks)| {
+                bookmarks
+                    .values()
+                    .map(move |bookmark| (path.clone(), bookmark.clone()))
+            })
+            .collect()
+    }
+
+    /// The bookmark after `line` of `path`, or before it when not `forward`.
+    /// The search goes on through the bookmarks of the other files and wraps
+    /// around at the ends.
+    pub fn next(
+        &self,
+        path: &Path,
+        line: usize,
+        forward: bool,
+    ) -> Option<(PathBuf, usize)> {
+        let all = self.all();
+        let key = (path, line);
+        let found = if forward {
+            all.iter()
+                .find(|(p, b)| (p.as_path(), b.line) > key)
+                .or_else(|| all.first())
+        } else {
+            all.iter()
+                .rev()
+                .find(|(p, b)| (p.as_path(), b.line) < key)
+                .or_else(|| all.last())
+        };
+        found.map(|(path, bookmark)| (path.clone(), bookmark.line))
+    }
+
+    /// Move the bookmarks of `path` to the lines `new_line` gives for their
+    /// lines, after the file was edited. Bookmarks ending up on the same line
+    /// are merged.
+    pub fn update_lines(
+        &mut self,
+        path: &Path,
+        mut new_line: impl FnMut(usize) -> usize,
+    ) {
+        if let Some(bookmarks) = self.files.get_mut(path) {
+            *bookmarks = std::mem::take(bookmarks)
+                .into_values()
+                .map(|mut bookmark| {
+                    bookmark.line = new_line(bookmark.line);
+                    (bookmark.line, bookmark)
+                })
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::Bookmarks;
+
+    #[test]
+    fn test_toggle() {
+        let mut bookmarks = Bookmarks::default();
+        let path = Path::new("/a.rs");
+        assert!(bookmarks.toggle(path, 3));
+        bookmarks.set(path, 5, Some("five".to_string()));
+        assert_eq!(bookmarks.get(path).unwrap().len(), 2);
+        assert!(!bookmarks.toggle(path, 3));
+        assert!(!bookmarks.toggle(path, 5));
+        assert!(bookmarks.is_empty());
+    }
+
+    #[test]
+    fn test_next() {
+        let mut bookmarks = Bookmarks::default();
+        let a = Path::new("/a.rs");
+        let b = Path::new("/b.rs");
+        bookmarks.toggle(a, 10);
+        bookmarks.toggle(a, 20);
+        bookmarks.toggle(b, 5);
+
+        assert_eq!(bookmarks.next(a, 0, true), Some((PathBuf::from(a), 10)));
+        assert_eq!(bookmarks.next(a, 10, true), Some((PathBuf::from(a), 20)));
+        assert_eq!(bookmarks.next(a, 20, true), Some((PathBuf::from(b), 5)));
+        assert_eq!(bookmarks.next(b, 5, true), Some((PathBuf::from(a), 10)));
+
+        assert_eq!(bookmarks.next(a, 15, false), Some((PathBuf::from(a), 10)));
+        assert_eq!(bookmarks.next(a, 10, false), Some((PathBuf::from(b), 5)));
+        assert_eq!(bookmarks.next(b, 0, false), Some((PathBuf::from(a), 20)));
+
+        assert_eq!(Bookmarks::default().next(a, 0, true), None);
+    }
+
+    #[test]
+    fn test_update_lines() {
+        let mut bookmarks = Bookmarks::default();
+        let path = Path::new("/a.rs");
+        bookmarks.toggle(path, 1);
+        bookmarks.set(path, 4, Some("four".to_string()));
+        bookmarks.toggle(path, 5);
+
+        // Deleting lines 1 to 4 joins the bookmarks on them.
+        bookmarks.update_lines(path, |line| if line < 5 { 1 } else { line - 3 });
+        let lines: Vec<_> = bookmarks.get(path).unwrap().keys().copied().collect();
+        assert_eq!(lines, vec![1, 2]);
+        assert_eq!(
+            bookmarks.get(path).unwrap()[&1].label.as_deref(),
+            Some("four")
+        );
+    }
+}
@@ -0,0 +1,87 @@
+#[cfg(target_os = "linux")]
+use std::sync::Mutex;
+
+#[cfg(target_os = "linux")]
+use arboard::{GetExtLinux, LinuxClipboardKind, SetExtLinux};
+use floem::views::editor::core::register::Clipboard;
+
+/// The primary selection on Linux, which holds the last selected text and is
+/// pasted with a middle-click, as opposed to the clipboard used by
+/// [`SystemClipboard`](floem::views::editor::text::SystemClipboard).
+///
+/// It's available on X11, and on Wayland compositors supporting the data
+/// control protocol. Other platforms have no primary selection, so nothing
+/// is stored and nothing can be pasted from it.
+#[derive(Default)]
+pub struct PrimaryClipboard;
+
+/// The clipboard is kept around, as the selection is only served to other
+/// applications for as long as it lives.
+#[cfg(target_os = "linux")]
+static PRIMARY: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+impl PrimaryClipboard {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Read the primary selection on another thread, as that waits for the
+    /// application owning it to answer, and pass it to `f` there.
+    pub fn get_string_async(f: impl FnOnce(String) + Send + 'static) {
+        std::thread::Builder::new()
+            .name("PrimarySelection".to_owned())
+            .spawn(move || {
+                if let Some(content) = Self::new().get_string() {
+                    f(content);
+                }
+            })
+            .ok();
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Clipboard for PrimaryClipboard {
+    fn get_string(&mut self) -> Option<String> {
+        // A clipboard of its own, so a slow read doesn't hold up setting the
+        // selection.
+        let mut clipboard = arboard::Clipboard::new().ok()?;
+        clipboard
+            .get()
+            .clipboard(LinuxClipboardKind::Primary)
+            .text()
+            .ok()
+    }
+
+    fn put_string(&mut self, s: impl AsRef<str>) {
+        let Ok(mut primary) = PRIMARY.lock() else {
+            return;
+        };
+        if primary.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => *primary = Some(clipboard),
+                Err(err) => {
+                    tracing::error!("failed to open the primary selection: {err}");
+                    return;
+                }
+            }
+        }
+        if let Some(primary) = primary.as_mut() {
+            if let Err(err) = primary
+                .set()
+                .clipboard(LinuxClipboardKind::Primary)
+                .text(s.as_ref())
+            {
+                tracing::error!("failed to set the primary selection: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Clipboard for PrimaryClipboard {
+    fn get_string(&mut self) -> Option<String> {
+        None
+    }
+
+    fn put_string(&mut self, _s: impl AsRef<str>) {}
+}
@@ -5,9 +5,19 @@
     peniko::kurbo::Rect,
     reactive::{RwSignal, Scope, SignalGet, SignalUpdate},
 };
-use lapce_core::{command::FocusCommand, mode::Mode, movement::Movement};
+use lapce_core::{
+    buffer::rope_text::{RopeText, RopeTextRef},
+    command::FocusCommand,
+    mode::Mode,
+    movement::Movement,
+    rope_text_pos::RopeTextPosition,
+};
 use lapce_rpc::plugin::PluginId;
-use lsp_types::CodeActionOrCommand;
+use lapce_xi_rope::Rope;
+use lsp_types::{
+    AnnotatedTextEdit, CodeActionOrCommand, DocumentChangeOperation,
+    DocumentChanges, OneOf, ResourceOp, TextEdit, Url,
+};
 
 use crate::{
     command::{CommandExecuted, CommandKind, InternalCommand},
@@ -166,8 +176,7 @@ pub fn previous_page(&self) {
 
     pub fn show(
         &mut self,
-        plugin_id: PluginId,
-        code_actions: im::Vector<CodeActionOrCommand>,
+        code_actions: im::Vector<(PluginId, CodeActionOrCommand)>,
         offset: usize,
         mouse_click: bool,
     ) {
@@ -176,9 +185,12 @@ pub fn show(
         self.offset = offset;
         self.mouse_click = mouse_click;
         self.request_id += 1;
+        // The preferred actions, such as the fix for a diagnostic, come first.
+        let mut code_actions: Vec<_> = code_actions.into_iter().collect();
+        code_actions.sort_by_key(|(_, code_action)| !is_preferred(code_action));
         self.items = code_actions
             .into_iter()
-            .map(|code_action| ScoredCodeActionItem {
+            .map(|(plugin_id, code_action)| ScoredCodeActionItem {
                 item: code_action,
                 plugin_id,
                 score: 0,
@@ -189,6 +201,14 @@ pub fn show(
         self.common.focus.set(Focus::CodeAction);
     }
 
+    /// The edits of the active code action, for previewing it.
+    pub fn active_preview(&self) -> Vec<(Url, FileChange)> {
+        self.filtered_items
+            .get(self.active.get())
+            .map(|item| edit_preview(&item.item))
+            .unwrap_or_default()
+    }
+
     fn cancel(&self) {
         self.status.set(CodeActionStatus::Inactive);
         self.common.focus.set(Focus::Workbench);
@@ -231,3 +251,340 @@ fn run_focus_command(&self, cmd: &FocusCommand) -> CommandExecuted {
         CommandExecuted::Yes
     }
 }
+
+fn is_preferred(item: &CodeActionOrCommand) -> bool {
+    match item {
+        CodeActionOrCommand::CodeAction(action) => {
+            action.is_preferred.unwrap_or(false)
+        }
+        CodeActionOrCommand::Command(_) => false,
+    }
+}
+
+/// A change the workspace edit of a code action makes to a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FileChange {
+    Edits(Vec<TextEdit>),
+    Create,
+    Rename(Url),
+    Delete,
+}
+
+/// The kinds of the lines of the preview of the edits of a code action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreviewLine {
+    /// The file the following lines are in.
+    File,
+    Context,
+    Removed,
+    Added,
+}
+
+/// The unchanged lines shown above and below the lines an edit changes.
+const PREVIEW_CONTEXT_LINES: usize = 2;
+
+/// The files the workspace edit of a code action changes, with what it does
+/// to each of them, in the order the edit gives them.
+pub fn edit_preview(item: &CodeActionOrCommand) -> Vec<(Url, FileChange)> {
+    let CodeActionOrCommand::CodeAction(action) = item else {
+        return Vec::new();
+    };
+    let Some(edit) = action.edit.as_ref() else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<(Url, FileChange)> = Vec::new();
+    let mut add_edits = |uri: &Url, edits: Vec<TextEdit>| {
+        let existing = files.iter_mut().find_map(|(u, change)| match change {
+            FileChange::Edits(existing) if u == uri => Some(existing),
+            _ => None,
+        });
+        if let Some(existing) = existing {
+            existing.extend(edits);
+        } else {
+            files.push((uri.clone(), FileChange::Edits(edits)));
+        }
+    };
+    let text_edits = |edits: &[OneOf<TextEdit, AnnotatedTextEdit>]| {
+        edits
+            .iter()
+            .map(|edit| match edit {
+                OneOf::Left(edit) => edit.clone(),
+                OneOf::Right(edit) => edit.text_edit.clone(),
+            })
+            .collect::<Vec<_>>()
+    };
+    if let Some(changes) = edit.changes.as_ref() {
+        let mut changes: Vec<_> = changes.iter().collect();
+        changes.sort_by(|a, b| a.0.cmp(b.0));
+        for (uri, edits) in changes {
+            add_edits(uri, edits.clone());
+        }
+    }
+    match edit.document_changes.as_ref() {
+        Some(DocumentChanges::Edits(edits)) => {
+            for edit in edits {
+                add_edits(&edit.text_document.uri, text_edits(&edit.edits));
+            }
+        }
+        Some(DocumentChanges::Operations(operations)) => {
+            for operation in operations {
+                match operation {
+                    DocumentChangeOperation::Edit(edit) => {
+                        add_edits(&edit.text_document.uri, text_edits(&edit.edits));
+                    }
+                    DocumentChangeOperation::Op(ResourceOp::Create(op)) => {
+                        files.push((op.uri.clone(), FileChange::Create));
+                    }
+                    DocumentChangeOperation::Op(ResourceOp::Rename(op)) => {
+                        files.push((
+                            op.old_uri.clone(),
+                            FileChange::Rename(op.new_uri.clone()),
+                        ));
+                    }
+                    DocumentChangeOperation::Op(ResourceOp::Delete(op)) => {
+                        files.push((op.uri.clone(), FileChange::Delete));
+                    }
+                }
+            }
+        }
+        None => {}
+    }
+    files
+}
+
+/// The lines of a preview of `edits` made to `text`: the lines they change,
+/// then the lines replacing them, with a few unchanged lines around them.
+/// The edits close enough for their context to meet are shown together.
+pub fn edit_preview_lines(
+    text: &Rope,
+    edits: &[TextEdit],
+) -> Vec<(PreviewLine, String)> {
+    let text = RopeTextRef::new(text);
+    let last_line = text.last_line();
+    // The empty line after the last line break isn't shown as context.
+    let last_context_line =
+        if last_line > 0 && text.line_content(last_line).is_empty() {
+            last_line - 1
+        } else {
+            last_line
+        };
+    let mut edits: Vec<(usize, usize, &str)> = edits
+        .iter()
+        .map(|edit| {
+            let start = text.offset_of_position(&edit.range.start);
+            let end = text.offset_of_position(&edit.range.end).max(start);
+            (start, end, edit.new_text.as_str())
+        })
+        .collect();
+    edits.sort_by_key(|(start, end, _)| (*start, *end));
+
+    // The first and the last lines of each group of edits, with its edits.
+    let mut hunks: Vec<(usize, usize, Vec<(usize, usize, &str)>)> = Vec::new();
+    for edit in edits {
+        let first = text.line_of_offset(edit.0);
+        let mut last = text.line_of_offset(edit.1);
+        // An edit ending at the start of a line leaves that line as it is.
+        if last > first && edit.1 == text.offset_of_line(last) {
+            last -= 1;
+        }
+        match hunks.last_mut() {
+            Some((_, hunk_last, hunk_edits))
+                if first <= *hunk_last + 2 * PREVIEW_CONTEXT_LINES =>
+            {
+                *hunk_last = (*hunk_last).max(last);
+                hunk_edits.push(edit);
+            }
+            _ => hunks.push((first, last, vec![edit])),
+        }
+    }
+
+    let line = |line: usize| {
+        text.line_content(line)
+            .trim_end_matches(['\n', '\r'])
+            .to_string()
+    };
+    let mut lines = Vec::new();
+    for (i, (first, last, hunk_edits)) in hunks.into_iter().enumerate() {
+        if i > 0 {
+            lines.push((PreviewLine::Context, "...".to_string()));
+        }
+        for l in first.saturating_sub(PREVIEW_CONTEXT_LINES)..first {
+            lines.push((PreviewLine::Context, line(l)));
+        }
+        for l in first..=last {
+            lines.push((PreviewLine::Removed, line(l)));
+        }
+
+        let end = if last < last_line {
+            text.offset_of_line(last + 1)
+        } else {
+            text.len()
+        };
+        let mut offset = text.offset_of_line(first);
+        let mut replaced = String::new();
+        for (start, edit_end, new_text) in hunk_edits {
+            if start >= offset {
+                replaced.push_str(&text.slice_to_cow(offset..start));
+            }
+            replaced.push_str(new_text);
+            offset = offset.max(edit_end);
+        }
+        replaced.push_str(&text.slice_to_cow(offset..end));
+        for l in replaced.lines() {
+            lines.push((PreviewLine::Added, l.to_string()));
+        }
+
+        for l in last + 1..=(last + PREVIEW_CONTEXT_LINES).min(last_context_line) {
+            lines.push((PreviewLine::Context, line(l)));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lsp_types::{
+        CodeAction, Command, OneOf, OptionalVersionedTextDocumentIdentifier,
+        Position, Range, TextDocumentEdit, TextEdit, WorkspaceEdit,
+    };
+
+    use super::*;
+
+    fn text_edit() -> TextEdit {
+        TextEdit::new(
+            Range::new(Position::new(0, 0), Position::new(0, 1)),
+            "a".into(),
+        )
+    }
+
+    fn action(edit: WorkspaceEdit) -> CodeActionOrCommand {
+        CodeActionOrCommand::CodeAction(CodeAction {
+            title: "fix".to_string(),
+            edit: Some(edit),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_edit_preview() {
+        let a = Url::parse("file:///a.rs").unwrap();
+        let b = Url::parse("file:///b.rs").unwrap();
+
+        let changes = action(WorkspaceEdit::new(HashMap::from([
+            (b.clone(), vec![text_edit()]),
+            (a.clone(), vec![text_edit(), text_edit()]),
+        ])));
+        assert_eq!(
+            edit_preview(&changes),
+            vec![
+                (a.clone(), FileChange::Edits(vec![text_edit(), text_edit()])),
+                (b.clone(), FileChange::Edits(vec![text_edit()]))
+            ]
+        );
+
+        let document_changes = action(WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Edits(vec![
+                TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier {
+                        uri: b.clone(),
+                        version: None,
+                    },
+                    edits: vec![OneOf::Left(text_edit())],
+                },
+                TextDocumentEdit {
+                    text_document: OptionalVersionedTextDocumentIdentifier {
+                        uri: b.clone(),
+                        version: None,
+                    },
+                    edits: vec![OneOf::Left(text_edit())],
+                },
+            ])),
+            ..Default::default()
+        });
+        assert_eq!(
+            edit_preview(&document_changes),
+            vec![(b, FileChange::Edits(vec![text_edit(), text_edit()]))]
+        );
+
+        let command = CodeActionOrCommand::Command(Command::new(
+            "fix".to_string(),
+            "fix".to_string(),
+            None,
+        ));
+        assert!(edit_preview(&command).is_empty());
+    }
+
+    fn edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> TextEdit {
+        TextEdit::new(
+            Range::new(Position::new(start.0, start.1), Position::new(end.0, end.1)),
+            new_text.into(),
+        )
+    }
+
+    #[test]
+    fn test_edit_preview_lines() {
+        use PreviewLine::*;
+
+        let text = Rope::from("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n");
+        let line = |kind, text: &str| (kind, text.to_string());
+
+        // A line changed, with the lines around it
+        let lines = edit_preview_lines(&text, &[edit((3, 0), (3, 1), "D")]);
+        assert_eq!(
+            lines,
+            vec![
+                line(Context, "b"),
+                line(Context, "c"),
+                line(Removed, "d"),
+                line(Added, "D"),
+                line(Context, "e"),
+                line(Context, "f"),
+            ]
+        );
+
+        // Edits far apart are shown apart, and a line inserted at the start
+        let lines = edit_preview_lines(
+            &text,
+            &[edit((9, 0), (10, 0), ""), edit((0, 0), (0, 0), "use x;\n")],
+        );
+        assert_eq!(
+            lines,
+            vec![
+                line(Removed, "a"),
+                line(Added, "use x;"),
+                line(Added, "a"),
+                line(Context, "b"),
+                line(Context, "c"),
+                line(Context, "..."),
+                line(Context, "h"),
+                line(Context, "i"),
+                line(Removed, "j"),
+            ]
+        );
+
+        // Edits close together are shown together
+        let lines = edit_preview_lines(
+            &text,
+            &[edit((1, 0), (1, 1), "B"), edit((4, 0), (4, 1), "E")],
+        );
+        assert_eq!(
+            lines,
+            vec![
+                line(Context, "a"),
+                line(Removed, "b"),
+                line(Removed, "c"),
+                line(Removed, "d"),
+                line(Removed, "e"),
+                line(Added, "B"),
+                line(Added, "c"),
+                line(Added, "d"),
+                line(Added, "E"),
+                line(Context, "f"),
+                line(Context, "g"),
+            ]
+        );
+    }
+}
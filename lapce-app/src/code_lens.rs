@@ -87,6 +87,9 @@ fn get_rust_command_config(
                 cwd: None,
                 env: None,
                 prelaunch: None,
+                request: Default::default(),
+                adapter: None,
+                arguments: Default::default(),
                 debug_command: None,
                 dap_id: Default::default(),
                 tracing_output: mode == RunDebugMode::Debug,
@@ -14,6 +14,7 @@
     dap_types::{DapId, RunDebugConfig},
     plugin::{PluginId, VoltID},
     proxy::ProxyStatus,
+    task::{TaskConfig, TaskOutputLink},
     terminal::{TermId, TerminalProfile},
 };
 use lsp_types::{CodeActionOrCommand, Position, WorkspaceEdit};
@@ -27,6 +28,7 @@
     doc::Doc,
     editor::location::EditorLocation,
     editor_tab::EditorTabChild,
+    file_template::FileTemplate,
     id::EditorTabId,
     main_split::{SplitDirection, SplitMoveDirection, TabCloseKind},
     workspace::LapceWorkspace,
@@ -171,6 +173,18 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Disable Modal Editing")]
     DisableModal,
 
+    #[strum(serialize = "vim.select_register")]
+    SelectRegister,
+
+    #[strum(serialize = "create_mark")]
+    CreateMark,
+
+    #[strum(serialize = "go_to_mark")]
+    GoToMark,
+
+    #[strum(serialize = "go_to_mark_position")]
+    GoToMarkPosition,
+
     #[strum(serialize = "open_folder")]
     #[strum(message = "Open Folder")]
     OpenFolder,
@@ -179,6 +193,10 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Close Folder")]
     CloseFolder,
 
+    #[strum(serialize = "add_folder_to_workspace")]
+    #[strum(message = "Add Folder to Workspace")]
+    AddFolderToWorkspace,
+
     #[strum(serialize = "open_file")]
     #[strum(message = "Open File")]
     OpenFile,
@@ -195,6 +213,14 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Go to Implementation")]
     GoToImplementation,
 
+    #[strum(serialize = "peek_definition")]
+    #[strum(message = "Peek Definition")]
+    PeekDefinition,
+
+    #[strum(serialize = "peek_references")]
+    #[strum(message = "Peek References")]
+    PeekReferences,
+
     #[strum(serialize = "reveal_in_panel")]
     #[strum(message = "Reveal in Panel")]
     RevealInPanel,
@@ -225,6 +251,30 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Open Internal UI Inspector")]
     OpenUIInspector,
 
+    #[strum(serialize = "format_selection")]
+    #[strum(message = "Format Selection")]
+    FormatSelection,
+
+    #[strum(serialize = "block_select_up")]
+    #[strum(message = "Column Select Up")]
+    BlockSelectUp,
+
+    #[strum(serialize = "block_select_down")]
+    #[strum(message = "Column Select Down")]
+    BlockSelectDown,
+
+    #[strum(serialize = "block_select_left")]
+    #[strum(message = "Column Select Left")]
+    BlockSelectLeft,
+
+    #[strum(serialize = "block_select_right")]
+    #[strum(message = "Column Select Right")]
+    BlockSelectRight,
+
+    #[strum(serialize = "toggle_performance_overlay")]
+    #[strum(message = "Developer: Toggle Performance Overlay")]
+    TogglePerformanceOverlay,
+
     #[strum(serialize = "show_env")]
     #[strum(message = "Show Environment")]
     ShowEnvironment,
@@ -277,6 +327,10 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Open Themes Directory")]
     OpenThemesDirectory,
 
+    #[strum(serialize = "open_snippets_directory")]
+    #[strum(message = "Open Snippets Directory")]
+    OpenSnippetsDirectory,
+
     #[strum(serialize = "open_plugins_directory")]
     #[strum(message = "Open Plugins Directory")]
     OpenPluginsDirectory,
@@ -289,6 +343,10 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Open Queries Directory")]
     OpenQueriesDirectory,
 
+    #[strum(serialize = "install_grammar")]
+    #[strum(message = "Install Grammar for Current Language")]
+    InstallGrammar,
+
     #[strum(serialize = "zoom_in")]
     #[strum(message = "Zoom In")]
     ZoomIn,
@@ -301,6 +359,18 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Reset Zoom")]
     ZoomReset,
 
+    #[strum(serialize = "toggle_error_lens")]
+    #[strum(message = "Toggle Inline Diagnostics")]
+    ToggleErrorLens,
+
+    #[strum(serialize = "toggle_centered_layout")]
+    #[strum(message = "Toggle Centered Layout")]
+    ToggleCenteredLayout,
+
+    #[strum(serialize = "markdown_preview_to_side")]
+    #[strum(message = "Open Preview to the Side")]
+    MarkdownPreviewToSide,
+
     #[strum(serialize = "close_window_tab")]
     #[strum(message = "Close Current Window Tab")]
     CloseWindowTab,
@@ -325,6 +395,50 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Previous Terminal Tab")]
     PreviousTerminalTab,
 
+    #[strum(serialize = "terminal_split_resize_left")]
+    #[strum(message = "Resize Terminal Split Left")]
+    TerminalSplitResizeLeft,
+
+    #[strum(serialize = "terminal_split_resize_right")]
+    #[strum(message = "Resize Terminal Split Right")]
+    TerminalSplitResizeRight,
+
+    #[strum(serialize = "terminal_split_resize_up")]
+    #[strum(message = "Resize Terminal Split Up")]
+    TerminalSplitResizeUp,
+
+    #[strum(serialize = "terminal_split_resize_down")]
+    #[strum(message = "Resize Terminal Split Down")]
+    TerminalSplitResizeDown,
+
+    #[strum(serialize = "terminal_previous_prompt")]
+    #[strum(message = "Scroll Terminal to Previous Prompt")]
+    TerminalPreviousPrompt,
+
+    #[strum(serialize = "terminal_next_prompt")]
+    #[strum(message = "Scroll Terminal to Next Prompt")]
+    TerminalNextPrompt,
+
+    #[strum(serialize = "terminal_start_logging")]
+    #[strum(message = "Start Logging Terminal Output")]
+    StartTerminalLogging,
+
+    #[strum(serialize = "terminal_stop_logging")]
+    #[strum(message = "Stop Logging Terminal Output")]
+    StopTerminalLogging,
+
+    #[strum(serialize = "terminal_show_environment")]
+    #[strum(message = "Show Terminal Environment")]
+    ShowTerminalEnvironment,
+
+    #[strum(serialize = "terminal_save_layout")]
+    #[strum(message = "Save Terminal Layout")]
+    SaveTerminalLayout,
+
+    #[strum(serialize = "terminal_restore_layout")]
+    #[strum(message = "Restore Terminal Layout")]
+    RestoreTerminalLayout,
+
     #[strum(serialize = "next_window_tab")]
     #[strum(message = "Go To Next Window Tab")]
     NextWindowTab,
@@ -349,6 +463,10 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "new_file")]
     NewFile,
 
+    #[strum(message = "New File from Template")]
+    #[strum(serialize = "new_file_from_template")]
+    NewFileFromTemplate,
+
     #[strum(serialize = "connect_ssh_host")]
     #[strum(message = "Connect to SSH Host")]
     ConnectSshHost,
@@ -378,10 +496,20 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "palette.workspace_symbol")]
     PaletteWorkspaceSymbol,
 
+    #[strum(message = "Go to Anything")]
+    #[strum(serialize = "palette.anything")]
+    PaletteAnything,
+
     #[strum(message = "Command Palette")]
     #[strum(serialize = "palette.command")]
     PaletteCommand,
 
+    /// The command palette, running its input as a vim ex command when it's
+    /// one.
+    #[strum(message = "Vim Ex Command")]
+    #[strum(serialize = "palette.ex_command")]
+    PaletteExCommand,
+
     #[strum(message = "Open Recent Workspace")]
     #[strum(serialize = "palette.workspace")]
     PaletteWorkspace,
@@ -394,6 +522,22 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "palette.scm_references")]
     PaletteSCMReferences,
 
+    #[strum(message = "Dirty Files")]
+    #[strum(serialize = "palette.dirty_files")]
+    PaletteDirtyFiles,
+
+    #[strum(message = "Local History: Show Saved Versions of the File")]
+    #[strum(serialize = "palette.local_history")]
+    PaletteLocalHistory,
+
+    #[strum(message = "Replay Recorded Macro...")]
+    #[strum(serialize = "palette.macros")]
+    PaletteMacros,
+
+    #[strum(message = "Switch Window")]
+    #[strum(serialize = "switch_window")]
+    SwitchWindow,
+
     #[strum(message = "List Palette Types")]
     #[strum(serialize = "palette.palette_help")]
     PaletteHelp,
@@ -413,9 +557,14 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "source_control.checkout_reference")]
     CheckoutReference,
 
+    #[strum(message = "Toggle Maximized Panel")]
     #[strum(serialize = "toggle_maximized_panel")]
     ToggleMaximizedPanel,
 
+    #[strum(message = "Toggle Zen Mode")]
+    #[strum(serialize = "toggle_zen_mode")]
+    ToggleZenMode,
+
     #[strum(serialize = "hide_panel")]
     HidePanel,
 
@@ -466,6 +615,10 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "toggle_search_focus")]
     ToggleSearchFocus,
 
+    #[strum(message = "Toggle Outline Focus")]
+    #[strum(serialize = "toggle_outline_focus")]
+    ToggleOutlineFocus,
+
     // Visual toggle commands
     #[strum(serialize = "toggle_terminal_visual")]
     ToggleTerminalVisual,
@@ -488,12 +641,84 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "toggle_search_visual")]
     ToggleSearchVisual,
 
+    #[strum(serialize = "toggle_outline_visual")]
+    ToggleOutlineVisual,
+
+    #[strum(message = "Toggle Undo Tree")]
+    #[strum(serialize = "toggle_undo_tree_visual")]
+    ToggleUndoTreeVisual,
+
+    #[strum(message = "Undo Tree: Go to Older State")]
+    #[strum(serialize = "undo_tree_older")]
+    UndoTreeOlder,
+
+    #[strum(message = "Undo Tree: Go to Newer State")]
+    #[strum(serialize = "undo_tree_newer")]
+    UndoTreeNewer,
+
+    #[strum(message = "Toggle Bookmarks")]
+    #[strum(serialize = "toggle_bookmarks_visual")]
+    ToggleBookmarksVisual,
+
+    #[strum(message = "Toggle Bookmark")]
+    #[strum(serialize = "toggle_bookmark")]
+    ToggleBookmark,
+
+    #[strum(message = "Add Labeled Bookmark")]
+    #[strum(serialize = "add_labeled_bookmark")]
+    AddLabeledBookmark,
+
+    #[strum(message = "Go to Next Bookmark")]
+    #[strum(serialize = "next_bookmark")]
+    NextBookmark,
+
+    #[strum(message = "Go to Previous Bookmark")]
+    #[strum(serialize = "previous_bookmark")]
+    PreviousBookmark,
+
+    #[strum(message = "Clear All Bookmarks")]
+    #[strum(serialize = "clear_bookmarks")]
+    ClearBookmarks,
+
+    #[strum(message = "Go to Bookmark")]
+    #[strum(serialize = "palette.bookmarks")]
+    PaletteBookmarks,
+
+    #[strum(message = "Start/Stop Recording Macro")]
+    #[strum(serialize = "toggle_macro_recording")]
+    ToggleMacroRecording,
+
+    #[strum(message = "Replay Macro")]
+    #[strum(serialize = "replay_macro")]
+    ReplayMacro,
+
+    #[strum(message = "Vim: Record Macro into Register")]
+    #[strum(serialize = "vim.record_macro")]
+    RecordMacroRegister,
+
+    #[strum(message = "Vim: Replay Macro from Register")]
+    #[strum(serialize = "vim.replay_macro")]
+    ReplayMacroRegister,
+
     #[strum(serialize = "focus_editor")]
     FocusEditor,
 
     #[strum(serialize = "focus_terminal")]
     FocusTerminal,
 
+    #[strum(message = "Focus Next Panel")]
+    #[strum(serialize = "focus_next_panel")]
+    FocusNextPanel,
+
+    #[strum(message = "Focus Previous Panel")]
+    #[strum(serialize = "focus_previous_panel")]
+    FocusPreviousPanel,
+
+    /// Open the context menu of the selected item in the focused panel
+    #[strum(message = "Show Context Menu")]
+    #[strum(serialize = "show_context_menu")]
+    ShowContextMenu,
+
     #[strum(message = "Source Control: Init")]
     #[strum(serialize = "source_control_init")]
     SourceControlInit,
@@ -529,9 +754,231 @@ pub enum LapceWorkbenchCommand {
     ChangeFileLanguage,
 
     #[strum(serialize = "change_file_line_ending")]
-    #[strum(message = "Change current file line ending")]
+    #[strum(message = "Change End of Line Sequence")]
     ChangeFileLineEnding,
 
+    #[strum(serialize = "reopen_with_encoding")]
+    #[strum(message = "Reopen with Encoding")]
+    ReopenWithEncoding,
+
+    #[strum(serialize = "save_with_encoding")]
+    #[strum(message = "Save with Encoding")]
+    SaveWithEncoding,
+
+    #[strum(serialize = "toggle_block_comment")]
+    #[strum(message = "Toggle Block Comment")]
+    ToggleBlockComment,
+
+    #[strum(serialize = "surround_with")]
+    #[strum(message = "Surround With...")]
+    SurroundWith,
+
+    #[strum(serialize = "remove_surrounding_pair")]
+    #[strum(message = "Remove Surrounding Pair")]
+    RemoveSurroundingPair,
+
+    #[strum(serialize = "expand_selection")]
+    #[strum(message = "Expand Selection")]
+    ExpandSelection,
+
+    #[strum(serialize = "shrink_selection")]
+    #[strum(message = "Shrink Selection")]
+    ShrinkSelection,
+
+    #[strum(serialize = "show_refactor_actions")]
+    #[strum(message = "Refactor...")]
+    ShowRefactorActions,
+
+    #[strum(serialize = "show_source_actions")]
+    #[strum(message = "Source Action...")]
+    ShowSourceActions,
+
+    #[strum(serialize = "undo_workspace_file_operations")]
+    #[strum(message = "Undo File Operations of Workspace Edit")]
+    UndoWorkspaceFileOperations,
+
+    #[strum(serialize = "inline_completion.accept_word")]
+    #[strum(message = "Accept Next Word of Inline Completion")]
+    AcceptInlineCompletionWord,
+
+    #[strum(serialize = "paste_without_formatting")]
+    #[strum(message = "Paste Without Formatting")]
+    PasteWithoutFormatting,
+
+    #[strum(serialize = "go_to_next_function")]
+    #[strum(message = "Go to Next Function")]
+    GoToNextFunction,
+
+    #[strum(serialize = "go_to_previous_function")]
+    #[strum(message = "Go to Previous Function")]
+    GoToPreviousFunction,
+
+    #[strum(serialize = "go_to_next_class")]
+    #[strum(message = "Go to Next Class")]
+    GoToNextClass,
+
+    #[strum(serialize = "go_to_previous_class")]
+    #[strum(message = "Go to Previous Class")]
+    GoToPreviousClass,
+
+    #[strum(serialize = "toggle_output_visual")]
+    #[strum(message = "Toggle Output")]
+    ToggleOutputVisual,
+
+    #[strum(serialize = "restart_language_server")]
+    #[strum(message = "Restart Language Server")]
+    RestartLanguageServer,
+
+    #[strum(serialize = "stop_language_server")]
+    #[strum(message = "Stop Language Server")]
+    StopLanguageServer,
+
+    #[strum(serialize = "toggle_tasks_visual")]
+    #[strum(message = "Toggle Tasks")]
+    ToggleTasksVisual,
+
+    #[strum(message = "Run Task")]
+    #[strum(serialize = "palette.tasks")]
+    PaletteTasks,
+
+    #[strum(serialize = "rerun_last_task")]
+    #[strum(message = "Rerun Last Task")]
+    RerunLastTask,
+
+    #[strum(serialize = "stop_last_task")]
+    #[strum(message = "Stop Last Task")]
+    StopLastTask,
+
+    #[strum(serialize = "toggle_notifications")]
+    #[strum(message = "Toggle Notifications")]
+    ToggleNotifications,
+
+    #[strum(serialize = "clear_notifications")]
+    #[strum(message = "Clear Notifications")]
+    ClearNotifications,
+
+    #[strum(serialize = "select_inside_function")]
+    #[strum(message = "Select Inside Function")]
+    SelectInsideFunction,
+
+    #[strum(serialize = "select_inside_class")]
+    #[strum(message = "Select Inside Class")]
+    SelectInsideClass,
+
+    #[strum(serialize = "select_inside_parameter")]
+    #[strum(message = "Select Inside Parameter")]
+    SelectInsideParameter,
+
+    #[strum(serialize = "select_inside_comment")]
+    #[strum(message = "Select Inside Comment")]
+    SelectInsideComment,
+
+    #[strum(serialize = "select_around_function")]
+    #[strum(message = "Select Around Function")]
+    SelectAroundFunction,
+
+    #[strum(serialize = "select_around_class")]
+    #[strum(message = "Select Around Class")]
+    SelectAroundClass,
+
+    #[strum(serialize = "select_around_parameter")]
+    #[strum(message = "Select Around Parameter")]
+    SelectAroundParameter,
+
+    #[strum(serialize = "select_around_comment")]
+    #[strum(message = "Select Around Comment")]
+    SelectAroundComment,
+
+    #[strum(serialize = "change_inside_function")]
+    #[strum(message = "Change Inside Function")]
+    ChangeInsideFunction,
+
+    #[strum(serialize = "change_inside_class")]
+    #[strum(message = "Change Inside Class")]
+    ChangeInsideClass,
+
+    #[strum(serialize = "change_inside_parameter")]
+    #[strum(message = "Change Inside Parameter")]
+    ChangeInsideParameter,
+
+    #[strum(serialize = "change_inside_comment")]
+    #[strum(message = "Change Inside Comment")]
+    ChangeInsideComment,
+
+    #[strum(serialize = "change_around_function")]
+    #[strum(message = "Change Around Function")]
+    ChangeAroundFunction,
+
+    #[strum(serialize = "change_around_class")]
+    #[strum(message = "Change Around Class")]
+    ChangeAroundClass,
+
+    #[strum(serialize = "change_around_parameter")]
+    #[strum(message = "Change Around Parameter")]
+    ChangeAroundParameter,
+
+    #[strum(serialize = "change_around_comment")]
+    #[strum(message = "Change Around Comment")]
+    ChangeAroundComment,
+
+    #[strum(serialize = "swap_parameter_next")]
+    #[strum(message = "Swap Parameter with the Next One")]
+    SwapParameterNext,
+
+    #[strum(serialize = "swap_parameter_previous")]
+    #[strum(message = "Swap Parameter with the Previous One")]
+    SwapParameterPrevious,
+
+    #[strum(serialize = "sort_lines_ascending")]
+    #[strum(message = "Sort Lines Ascending")]
+    SortLinesAscending,
+
+    #[strum(serialize = "sort_lines_descending")]
+    #[strum(message = "Sort Lines Descending")]
+    SortLinesDescending,
+
+    #[strum(serialize = "sort_lines_natural")]
+    #[strum(message = "Sort Lines Naturally")]
+    SortLinesNatural,
+
+    #[strum(serialize = "sort_lines_case_insensitive")]
+    #[strum(message = "Sort Lines Case Insensitive")]
+    SortLinesCaseInsensitive,
+
+    #[strum(serialize = "delete_duplicate_lines")]
+    #[strum(message = "Delete Duplicate Lines")]
+    DeleteDuplicateLines,
+
+    #[strum(serialize = "join_selected_lines")]
+    #[strum(message = "Join Selected Lines")]
+    JoinSelectedLines,
+
+    #[strum(serialize = "transform_to_uppercase")]
+    #[strum(message = "Transform to Uppercase")]
+    TransformToUppercase,
+
+    #[strum(serialize = "transform_to_lowercase")]
+    #[strum(message = "Transform to Lowercase")]
+    TransformToLowercase,
+
+    #[strum(serialize = "transform_to_title_case")]
+    #[strum(message = "Transform to Title Case")]
+    TransformToTitleCase,
+
+    #[strum(serialize = "transform_to_snake_case")]
+    #[strum(message = "Transform to Snake Case")]
+    TransformToSnakeCase,
+
+    #[strum(serialize = "transform_to_camel_case")]
+    #[strum(message = "Transform to Camel Case")]
+    TransformToCamelCase,
+
+    #[strum(serialize = "signature_help.next")]
+    NextSignature,
+
+    #[strum(serialize = "signature_help.previous")]
+    PreviousSignature,
+
     #[strum(serialize = "next_editor_tab")]
     #[strum(message = "Next Editor Tab")]
     NextEditorTab,
@@ -540,6 +987,18 @@ pub enum LapceWorkbenchCommand {
     #[strum(message = "Previous Editor Tab")]
     PreviousEditorTab,
 
+    #[strum(serialize = "pin_editor_tab")]
+    #[strum(message = "Pin Tab")]
+    PinEditorTab,
+
+    #[strum(serialize = "unpin_editor_tab")]
+    #[strum(message = "Unpin Tab")]
+    UnpinEditorTab,
+
+    #[strum(serialize = "keep_editor_tab_open")]
+    #[strum(message = "Keep Tab Open")]
+    KeepEditorTabOpen,
+
     #[strum(serialize = "toggle_inlay_hints")]
     #[strum(message = "Toggle Inlay Hints")]
     ToggleInlayHints,
@@ -566,9 +1025,11 @@ pub enum LapceWorkbenchCommand {
     UninstallFromPATH,
 
     #[strum(serialize = "jump_location_backward")]
+    #[strum(message = "Go Back")]
     JumpLocationBackward,
 
     #[strum(serialize = "jump_location_forward")]
+    #[strum(message = "Go Forward")]
     JumpLocationForward,
 
     #[strum(serialize = "jump_location_backward_local")]
@@ -589,6 +1050,26 @@ pub enum LapceWorkbenchCommand {
     #[strum(serialize = "diff_files")]
     DiffFiles,
 
+    #[strum(message = "Compare Active File With...")]
+    #[strum(serialize = "compare_active_file_with")]
+    CompareActiveFileWith,
+
+    #[strum(message = "Compare Active File with Clipboard")]
+    #[strum(serialize = "compare_active_file_with_clipboard")]
+    CompareActiveFileWithClipboard,
+
+    #[strum(message = "Go to Next Change")]
+    #[strum(serialize = "next_diff_change")]
+    NextDiffChange,
+
+    #[strum(message = "Go to Previous Change")]
+    #[strum(serialize = "previous_diff_change")]
+    PreviousDiffChange,
+
+    #[strum(message = "Toggle Inline Diff")]
+    #[strum(serialize = "toggle_inline_diff")]
+    ToggleInlineDiff,
+
     #[strum(serialize = "quit")]
     #[strum(message = "Quit Editor")]
     Quit,
@@ -663,6 +1144,18 @@ pub enum InternalCommand {
     NewTerminal {
         profile: Option<TerminalProfile>,
     },
+    /// Show the terminal panel with the given terminal active and focused.
+    FocusTerminal {
+        term_id: TermId,
+    },
+    /// Save the current arrangement of the terminals as the layout `name`,
+    /// replacing any layout with the same name.
+    SaveTerminalLayout {
+        name: String,
+    },
+    RestoreTerminalLayout {
+        name: String,
+    },
     SplitTerminal {
         term_id: TermId,
     },
@@ -687,11 +1180,15 @@ pub enum InternalCommand {
         child: EditorTabChild,
         kind: TabCloseKind,
     },
+    EditorTabChildPin {
+        editor_tab_id: EditorTabId,
+        child: EditorTabChild,
+        pinned: bool,
+    },
     ShowCodeActions {
         offset: usize,
         mouse_click: bool,
-        plugin_id: PluginId,
-        code_actions: im::Vector<CodeActionOrCommand>,
+        code_actions: im::Vector<(PluginId, CodeActionOrCommand)>,
     },
     RunCodeAction {
         plugin_id: PluginId,
@@ -779,6 +1276,65 @@ pub enum InternalCommand {
         left_path: PathBuf,
         right_path: PathBuf,
     },
+    NewFileFromTemplate {
+        path: PathBuf,
+        template: FileTemplate,
+    },
+    FindInFolder {
+        path: PathBuf,
+    },
+    RemoveWorkspaceFolder {
+        path: PathBuf,
+    },
+    SaveFiles {
+        paths: Vec<PathBuf>,
+    },
+    ReopenWithEncoding {
+        path: PathBuf,
+        encoding: String,
+    },
+    SaveWithEncoding {
+        path: PathBuf,
+        encoding: String,
+    },
+    RevertFile {
+        path: PathBuf,
+    },
+    /// Compare the file with its version kept by the local history.
+    OpenLocalVersion {
+        path: PathBuf,
+        timestamp: u64,
+    },
+    /// Replace the content of the file with its version kept by the local
+    /// history, as an edit that can be undone.
+    RestoreLocalVersion {
+        path: PathBuf,
+        timestamp: u64,
+    },
+    /// Run the task, stopping the previous run of it.
+    RunTask {
+        task: TaskConfig,
+    },
+    /// Show the lines of the output of a task a problem was found in.
+    RevealTaskOutput {
+        link: TaskOutputLink,
+    },
+    /// Bookmark the line of the cursor in the active editor with `label`.
+    AddBookmark {
+        label: String,
+    },
+    /// Run the vim ex command typed in the command palette after `:`.
+    RunExCommand {
+        command: String,
+    },
+    /// Replay the recorded macro `name`, or the last one, `count` times.
+    ReplayMacro {
+        name: Option<String>,
+        count: usize,
+    },
+    OpenCommit {
+        rev: String,
+    },
     ExecuteProcess {
         program: String,
         arguments: Vec<String>,
@@ -797,6 +1353,9 @@ pub enum InternalCommand {
     RestartTerminal {
         term_id: TermId,
     },
+    DuplicateTerminal {
+        term_id: TermId,
+    },
 }
 
 #[derive(Clone)]
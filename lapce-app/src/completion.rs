@@ -28,6 +28,8 @@ pub enum CompletionStatus {
 pub struct ScoredCompletionItem {
     pub item: CompletionItem,
     pub plugin_id: PluginId,
+    /// The priority of the server of the item for completion.
+    pub priority: i64,
     pub score: u32,
     pub label_score: u32,
     pub indices: Vec<usize>,
@@ -52,6 +54,8 @@ pub struct CompletionData {
     pub input: String,
     /// `(Input, CompletionItems)`
     pub input_items: im::HashMap<String, im::Vector<ScoredCompletionItem>>,
+    /// The user snippets of the document's language, offered along the items of the LSP
+    pub snippet_items: im::Vector<ScoredCompletionItem>,
     /// The filtered items that are being displayed to the user
     pub filtered_items: im::Vector<ScoredCompletionItem>,
     /// The size of the completion element.  
@@ -78,6 +82,7 @@ pub fn new(cx: Scope, config: ReadSignal<Arc<LapceConfig>>) -> Self {
             active,
             input: "".to_string(),
             input_items: im::HashMap::new(),
+            snippet_items: im::Vector::new(),
             filtered_items: im::Vector::new(),
             layout_rect: Rect::ZERO,
             matcher: cx
@@ -94,6 +99,7 @@ pub fn receive(
         input: &str,
         resp: &CompletionResponse,
         plugin_id: PluginId,
+        priority: i64,
     ) {
         // If we've been canceled or the request id is old, ignore the response.
         if self.status == CompletionStatus::Inactive || self.request_id != request_id
@@ -111,12 +117,15 @@ pub fn receive(
             .map(|i| ScoredCompletionItem {
                 item: i.to_owned(),
                 plugin_id,
+                priority,
                 score: 0,
                 label_score: 0,
                 indices: Vec::new(),
             })
             .collect();
-        self.input_items.insert(input.to_string(), items);
+        // The servers of the language each respond with their own items.
+        let input_items = self.input_items.entry(input.to_string()).or_default();
+        *input_items = merge_items(input_items, plugin_id, items);
         self.filter_items();
     }
 
@@ -145,6 +154,7 @@ pub fn cancel(&mut self) {
         self.active.set(0);
         self.input.clear();
         self.input_items.clear();
+        self.snippet_items.clear();
         self.filtered_items.clear();
     }
 
@@ -162,13 +172,16 @@ pub fn update_input(&mut self, input: String) {
     }
 
     fn all_items(&self) -> im::Vector<ScoredCompletionItem> {
-        self.input_items
+        let mut items = self
+            .input_items
             .get(&self.input)
             .cloned()
             .filter(|items| !items.is_empty())
             .unwrap_or_else(move || {
                 self.input_items.get("").cloned().unwrap_or_default()
-            })
+            });
+        items.append(self.snippet_items.clone());
+        items
     }
 
     pub fn filter_items(&mut self) {
@@ -231,13 +244,7 @@ pub fn filter_items(&mut self) {
                     .collect()
             })
             .unwrap();
-        // Sort all the items by their score, then their label score, then their length.
-        items.sort_by(|a, b| {
-            b.score
-                .cmp(&a.score)
-                .then_with(|| b.label_score.cmp(&a.label_score))
-                .then_with(|| a.item.label.len().cmp(&b.item.label.len()))
-        });
+        items.sort_by(compare_filtered_items);
         self.filtered_items = items;
     }
 
@@ -339,6 +346,38 @@ pub fn update_document_completion(
     }
 }
 
+/// Replace the items of the server `plugin_id` in `items` with `new_items`,
+/// keeping the items of the servers with higher priorities first.
+fn merge_items(
+    items: &im::Vector<ScoredCompletionItem>,
+    plugin_id: PluginId,
+    new_items: im::Vector<ScoredCompletionItem>,
+) -> im::Vector<ScoredCompletionItem> {
+    let mut items: Vec<ScoredCompletionItem> = items
+        .iter()
+        .filter(|item| item.plugin_id != plugin_id)
+        .cloned()
+        .chain(new_items)
+        .collect();
+    // A stable sort keeps the order of the items of each server.
+    items.sort_by_key(|item| std::cmp::Reverse(item.priority));
+    items.into_iter().collect()
+}
+
+/// Order the filtered items by their score, then the priority of their
+/// server, then their label score, then their length, so a better match from
+/// a server of lower priority still comes first.
+fn compare_filtered_items(
+    a: &ScoredCompletionItem,
+    b: &ScoredCompletionItem,
+) -> std::cmp::Ordering {
+    b.score
+        .cmp(&a.score)
+        .then_with(|| b.priority.cmp(&a.priority))
+        .then_with(|| b.label_score.cmp(&a.label_score))
+        .then_with(|| a.item.label.len().cmp(&b.item.label.len()))
+}
+
 /// Get the text of the completion lens for the given completion item.  
 /// Returns `None` if the completion lens should be hidden.
 /// Returns `Some(None)` if the completion lens should be shown, but not changed.
@@ -415,3 +454,68 @@ fn completion_lens_text(
         Some(Some(item.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use lapce_rpc::plugin::PluginId;
+    use lsp_types::CompletionItem;
+
+    use super::{compare_filtered_items, merge_items, ScoredCompletionItem};
+
+    fn item(label: &str, plugin_id: u64, priority: i64) -> ScoredCompletionItem {
+        ScoredCompletionItem {
+            item: CompletionItem {
+                label: label.to_string(),
+                ..Default::default()
+            },
+            plugin_id: PluginId(plugin_id),
+            priority,
+            score: 0,
+            label_score: 0,
+            indices: Vec::new(),
+        }
+    }
+
+    fn labels(items: &im::Vector<ScoredCompletionItem>) -> Vec<&str> {
+        items.iter().map(|item| item.item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_merge_items() {
+        let items = merge_items(
+            &im::Vector::new(),
+            PluginId(1),
+            im::vector![item("a", 1, 0), item("b", 1, 0)],
+        );
+        assert_eq!(labels(&items), ["a", "b"]);
+
+        // The items of a server with a higher priority come first, whenever
+        // it responds.
+        let items = merge_items(&items, PluginId(2), im::vector![item("c", 2, 1)]);
+        assert_eq!(labels(&items), ["c", "a", "b"]);
+
+        let items = merge_items(&items, PluginId(3), im::vector![item("d", 3, 0)]);
+        assert_eq!(labels(&items), ["c", "a", "b", "d"]);
+
+        // A new response of a server replaces its items.
+        let items = merge_items(&items, PluginId(1), im::vector![item("e", 1, 0)]);
+        assert_eq!(labels(&items), ["c", "d", "e"]);
+    }
+
+    #[test]
+    fn test_compare_filtered_items() {
+        let scored = |label: &str, priority: i64, score: u32| ScoredCompletionItem {
+            score,
+            ..item(label, 1, priority)
+        };
+        let mut items = im::vector![
+            scored("ab", 1, 10),
+            scored("abc", 0, 20),
+            scored("a", 0, 10),
+            scored("b", 0, 10),
+        ];
+        items.sort_by(compare_filtered_items);
+        // The priority only breaks the ties of the scores.
+        assert_eq!(labels(&items), ["abc", "ab", "a", "b"]);
+    }
+}
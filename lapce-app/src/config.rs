@@ -7,7 +7,7 @@
 use ::core::slice;
 use floem::peniko::Color;
 use itertools::Itertools;
-use lapce_core::directory::Directory;
+use lapce_core::{directory::Directory, language::LapceLanguage};
 use lapce_proxy::plugin::wasi::find_all_volts;
 use lapce_rpc::plugin::VoltID;
 use lsp_types::{CompletionItemKind, SymbolKind};
@@ -15,13 +15,16 @@
 use parking_lot::RwLock;
 use serde::Deserialize;
 use strum::VariantNames;
-use tracing::error;
+use tracing::{error, warn};
 
 use self::{
     color::LapceColor,
     color_theme::{ColorThemeConfig, ThemeColor, ThemeColorPreference},
     core::CoreConfig,
-    editor::{EditorConfig, WrapStyle, SCALE_OR_SIZE_LIMIT},
+    editor::{
+        DefaultLineEnding, EditorConfig, LanguageEditorConfig, RenderWhitespace,
+        WrapIndent, WrapStyle, SCALE_OR_SIZE_LIMIT,
+    },
     icon::LapceIcons,
     icon_theme::IconThemeConfig,
     svg::SvgStore,
@@ -104,8 +107,17 @@ pub struct LapceConfig {
     pub color_theme: ColorThemeConfig,
     #[serde(default)]
     pub icon_theme: IconThemeConfig,
+    /// The settings of the `[languages.<name>]` blocks, by language name,
+    /// which are only deserialized when resolving them so that an invalid
+    /// block doesn't fail the whole config.
+    #[serde(default)]
+    pub languages: HashMap<String, serde_json::Value>,
     #[serde(flatten)]
     pub plugins: HashMap<String, HashMap<String, serde_json::Value>>,
+    /// The editor settings of the languages with a `[languages.<name>]`
+    /// block, with its settings overriding those of the editor.
+    #[serde(skip)]
+    language_editors: HashMap<LapceLanguage, EditorConfig>,
     #[serde(skip)]
     pub color: ThemeColor,
     #[serde(skip)]
@@ -143,6 +155,18 @@ pub fn load(
             }
         };
 
+        LapceLanguage::set_file_associations(&lapce_config.editor.file_associations);
+        for (name, source) in &lapce_config.core.grammars {
+            if !source.extensions.is_empty() || !source.files.is_empty() {
+                LapceLanguage::register(
+                    name,
+                    &source.extensions,
+                    &source.files,
+                    source.comment.as_deref(),
+                );
+            }
+        }
+
         lapce_config.available_color_themes =
             Self::load_color_themes(disabled_volts, extra_plugin_paths);
         lapce_config.available_icon_themes =
@@ -168,7 +192,7 @@ pub fn load(
         lapce_config.wrap_style_list = im::vector![
             WrapStyle::None.to_string(),
             WrapStyle::EditorWidth.to_string(),
-            // TODO: WrapStyle::WrapColumn.to_string(),
+            WrapStyle::WrapColumn.to_string(),
             WrapStyle::WrapWidth.to_string()
         ];
 
@@ -213,13 +237,21 @@ fn merge_config(
 
         match workspace.kind {
             LapceWorkspaceType::Local => {
-                if let Some(path) = workspace.path.as_ref() {
-                    let path = path.join("./.lapce/settings.toml");
+                if let Some(settings) = workspace
+                    .path
+                    .as_ref()
+                    .and_then(|path| {
+                        std::fs::read_to_string(path.join("./.lapce/settings.toml"))
+                            .ok()
+                    })
+                    .and_then(|settings| Self::workspace_settings(&settings))
+                {
                     config = config::Config::builder()
                         .add_source(config.clone())
-                        .add_source(
-                            config::File::from(path.as_path()).required(false),
-                        )
+                        .add_source(config::File::from_str(
+                            &settings,
+                            config::FileFormat::Toml,
+                        ))
                         .build()
                         .unwrap_or_else(|_| config.clone());
                 }
@@ -232,6 +264,36 @@ fn merge_config(
         config
     }
 
+    /// The settings of a workspace, without the external formatters and the
+    /// grammar sources. Formatters run commands on opening a document and
+    /// grammars are compiled or loaded as libraries, which a cloned
+    /// repository must not be able to pick, so they're only taken from the
+    /// user's settings.
+    fn workspace_settings(settings: &str) -> Option<String> {
+        let mut document: toml_edit::Document = match settings.parse() {
+            Ok(document) => document,
+            Err(err) => {
+                error!("Failed to parse the workspace settings: {err}");
+                return None;
+            }
+        };
+        for (table, key) in [
+            ("editor", "formatters"),
+            ("core", "grammars"),
+            ("core", "grammar-registry"),
+        ] {
+            if let Some(table) = document
+                .get_mut(table)
+                .and_then(|table| table.as_table_like_mut())
+            {
+                if table.remove(key).is_some() {
+                    warn!("Ignored the {key} of the workspace settings");
+                }
+            }
+        }
+        Some(document.to_string())
+    }
+
     fn update_id(&mut self) {
         self.id = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -296,8 +358,10 @@ fn resolve_theme(&mut self, workspace: &LapceWorkspace) {
             if let Some(icon_theme_path) = icon_theme_path {
                 self.icon_theme.path = icon_theme_path.clone().unwrap_or_default();
             }
+            self.languages = new.languages;
             self.plugins = new.plugins;
         }
+        self.resolve_language_editors();
         self.resolve_colors(Some(&default_lapce_config));
         self.update_id();
     }
@@ -324,6 +388,54 @@ fn load_color_themes(
         themes
     }
 
+    /// Resolve the editor settings of the languages from their
+    /// `[languages.<name>]` blocks.
+    fn resolve_language_editors(&mut self) {
+        // `[editor.format-on-save-languages]` predates the blocks, so its
+        // entries are moved into them, without replacing what they set.
+        for (name, format_on_save) in
+            std::mem::take(&mut self.editor.format_on_save_languages)
+        {
+            let settings = self
+                .languages
+                .entry(name)
+                .or_insert_with(|| serde_json::Value::Object(Default::default()));
+            if let serde_json::Value::Object(settings) = settings {
+                settings
+                    .entry("format-on-save")
+                    .or_insert(serde_json::Value::Bool(format_on_save));
+            }
+        }
+        self.language_editors = self
+            .languages
+            .iter()
+            .filter_map(|(name, settings)| {
+                let Some(language) = LapceLanguage::from_name(name) else {
+                    warn!("Ignored the settings of the unknown language {name}");
+                    return None;
+                };
+                match serde_json::from_value::<LanguageEditorConfig>(
+                    settings.clone(),
+                ) {
+                    Ok(overrides) => {
+                        Some((language, self.editor.with_overrides(&overrides)))
+                    }
+                    Err(err) => {
+                        error!(
+                            "Failed to deserialize the [languages.{name}] settings: {err}"
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+    }
+
+    /// The editor settings for the documents of `language`.
+    pub fn editor_config(&self, language: LapceLanguage) -> &EditorConfig {
+        self.language_editors.get(&language).unwrap_or(&self.editor)
+    }
+
     pub fn default_color_theme(&self) -> &ColorThemeConfig {
         &DEFAULT_DARK_THEME_COLOR_CONFIG
     }
@@ -361,6 +473,15 @@ pub fn color(&self, name: &str) -> Color {
         }
     }
 
+    /// The names of the colors brackets rotate through by nesting depth, which are
+    /// `bracket.color.1` and onwards for as many as the theme defines.
+    pub fn bracket_colors(&self) -> Vec<String> {
+        (1..)
+            .map(|i| format!("bracket.color.{i}"))
+            .take_while(|name| self.color.syntax.contains_key(name))
+            .collect()
+    }
+
     /// Retrieve a color value whose key starts with "style."
     pub fn style_color(&self, name: &str) -> Option<Color> {
         self.color.syntax.get(name).copied()
@@ -714,11 +835,12 @@ pub fn terminal_font_family(&self) -> &str {
     }
 
     pub fn terminal_font_size(&self) -> usize {
-        if self.terminal.font_size > 0 {
+        let font_size = if self.terminal.font_size > 0 {
             self.terminal.font_size
         } else {
             self.editor.font_size()
-        }
+        };
+        (font_size as i64 + self.terminal.font_size_zoom).max(1) as usize
     }
 
     pub fn terminal_line_height(&self) -> usize {
@@ -910,6 +1032,24 @@ pub fn get_dropdown_info(&self, kind: &str, key: &str) -> Option<DropdownInfo> {
                     .unwrap_or(0),
                 items: self.wrap_style_list.clone(),
             }),
+            ("editor", "default-line-ending") => Some(DropdownInfo {
+                active_index: self.editor.default_line_ending as usize,
+                items: DefaultLineEnding::VARIANTS
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            }),
+            ("editor", "wrap-indent") => Some(DropdownInfo {
+                active_index: self.editor.wrap_indent as usize,
+                items: WrapIndent::VARIANTS.iter().map(|s| s.to_string()).collect(),
+            }),
+            ("editor", "render-whitespace") => Some(DropdownInfo {
+                active_index: self.editor.render_whitespace as usize,
+                items: RenderWhitespace::VARIANTS
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            }),
             ("ui", "tab-close-button") => Some(DropdownInfo {
                 active_index: self.ui.tab_close_button as usize,
                 items: ui::TabCloseButton::VARIANTS
@@ -926,6 +1066,14 @@ pub fn get_dropdown_info(&self, kind: &str, key: &str) -> Option<DropdownInfo> {
                     .sorted()
                     .collect(),
             }),
+            ("ui", "zoom-scope") => Some(DropdownInfo {
+                active_index: self.ui.zoom_scope as usize,
+                items: ui::ZoomScope::VARIANTS
+                    .iter()
+                    .map(|s| s.to_string())
+                    .sorted()
+                    .collect(),
+            }),
             ("terminal", "default-profile") => Some(DropdownInfo {
                 active_index: self
                     .terminal
@@ -1018,3 +1166,141 @@ pub fn update_file(
         Some(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lapce_core::language::LapceLanguage;
+    use serde_json::json;
+
+    use super::{LapceConfig, DEFAULT_LAPCE_CONFIG, DEFAULT_SETTINGS};
+
+    #[test]
+    fn test_workspace_settings_formatters() {
+        let settings = r#"
+[editor]
+font-size = 14
+
+[editor.formatters.rust]
+command = "rustfmt"
+"#;
+        let settings = LapceConfig::workspace_settings(settings).unwrap();
+        assert!(settings.contains("font-size = 14"));
+        assert!(!settings.contains("rustfmt"));
+
+        let settings = LapceConfig::workspace_settings(
+            "editor.formatters.rust.command = \"rustfmt\"\n",
+        )
+        .unwrap();
+        assert!(!settings.contains("rustfmt"));
+
+        assert!(LapceConfig::workspace_settings("[editor").is_none());
+    }
+
+    #[test]
+    fn test_workspace_settings_grammars() {
+        let settings = r#"
+[core]
+color-theme = "Lapce Dark"
+grammar-registry = "https://example.com/{name}"
+
+[core.grammars.rust]
+source = "https://example.com/tree-sitter-rust"
+"#;
+        let settings = LapceConfig::workspace_settings(settings).unwrap();
+        assert!(settings.contains("color-theme"));
+        assert!(!settings.contains("example.com"));
+
+        let settings = LapceConfig::workspace_settings(
+            "core.grammars.rust.source = \"/tmp/grammar\"\n",
+        )
+        .unwrap();
+        assert!(!settings.contains("/tmp"));
+
+        let settings = LapceConfig::workspace_settings(
+            "core.grammar-registry = \"/tmp/{name}\"\n",
+        )
+        .unwrap();
+        assert!(!settings.contains("/tmp"));
+    }
+
+    #[test]
+    fn test_terminal_font_size_zoom() {
+        let mut config = DEFAULT_LAPCE_CONFIG.clone();
+        config.editor.font_size_zoom = 2;
+        // A terminal following the editor font size follows its zoom too.
+        assert_eq!(config.terminal_font_size(), 15);
+
+        config.terminal.font_size_zoom = -1;
+        assert_eq!(config.terminal_font_size(), 14);
+
+        config.terminal.font_size = 18;
+        assert_eq!(config.terminal_font_size(), 17);
+        config.terminal.font_size_zoom = 0;
+        assert_eq!(config.terminal_font_size(), 18);
+    }
+
+    #[test]
+    fn test_resolve_language_editors() {
+        let mut config = LapceConfig::default();
+        config.editor.tab_width = 4;
+        config.editor.font_size = 13;
+        config.editor.format_on_save_languages =
+            HashMap::from([("rust".to_string(), true), ("go".to_string(), true)]);
+        config.languages.insert(
+            "rust".to_string(),
+            json!({ "tab-width": 2, "font-size": 20, "format-on-save": false }),
+        );
+        config
+            .languages
+            .insert("python".to_string(), json!({ "tab-width": "two" }));
+        config
+            .languages
+            .insert("unknown".to_string(), json!({ "tab-width": 8 }));
+        // Top level blocks are the settings of plugins rather than languages.
+        config.plugins.insert(
+            "markdown".to_string(),
+            HashMap::from([("tab-width".to_string(), json!(8))]),
+        );
+        config.resolve_language_editors();
+
+        let rust = config.editor_config(LapceLanguage::Rust);
+        assert_eq!(rust.tab_width, 2);
+        assert_eq!(rust.font_size(), 20);
+        assert_eq!(rust.line_height(), 20);
+        // The block takes precedence over the former per language table.
+        assert!(!rust.format_on_save);
+        assert!(config.editor_config(LapceLanguage::Go).format_on_save);
+
+        // Invalid settings and the blocks of plugins leave the editor ones.
+        for language in [
+            LapceLanguage::Python,
+            LapceLanguage::Markdown,
+            LapceLanguage::PlainText,
+        ] {
+            let editor = config.editor_config(language);
+            assert_eq!(editor.tab_width, 4);
+            assert_eq!(editor.line_height(), 13);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_languages() {
+        let config: LapceConfig = config::Config::builder()
+            .add_source(config::File::from_str(
+                DEFAULT_SETTINGS,
+                config::FileFormat::Toml,
+            ))
+            .add_source(config::File::from_str(
+                "[languages.rust]\ntab-width = 2\n",
+                config::FileFormat::Toml,
+            ))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(config.languages["rust"], json!({ "tab-width": 2 }));
+        assert!(!config.plugins.contains_key("languages"));
+    }
+}
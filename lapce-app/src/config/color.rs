@@ -71,8 +71,12 @@ impl LapceColor {
     pub const EDITOR_DEBUG_BREAK_LINE: &'static str = "editor.debug_break_line";
     pub const EDITOR_CURRENT_LINE: &'static str = "editor.current_line";
     pub const EDITOR_LINK: &'static str = "editor.link";
+    pub const EDITOR_MISSPELLING: &'static str = "editor.misspelling";
     pub const EDITOR_VISIBLE_WHITESPACE: &'static str = "editor.visible_whitespace";
+    pub const EDITOR_TRAILING_WHITESPACE: &'static str =
+        "editor.trailing_whitespace";
     pub const EDITOR_INDENT_GUIDE: &'static str = "editor.indent_guide";
+    pub const EDITOR_RULER: &'static str = "editor.ruler";
     pub const EDITOR_DRAG_DROP_BACKGROUND: &'static str =
         "editor.drag_drop_background";
     pub const EDITOR_STICKY_HEADER_BACKGROUND: &'static str =
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use structdesc::FieldNames;
 
@@ -14,6 +16,10 @@ pub struct CoreConfig {
         desc = "Enable customised titlebar and disable OS native one (Linux, BSD, Windows)"
     )]
     pub custom_titlebar: bool,
+    #[field_names(
+        desc = "The title of the window. ${workspace}, ${remote}, ${file} and ${dirty} are replaced with the workspace name, the remote host, the active file name and a marker for unsaved changes"
+    )]
+    pub window_title: String,
     #[field_names(
         desc = "Only allow double-click to open files in the file explorer"
     )]
@@ -22,4 +28,62 @@ pub struct CoreConfig {
         desc = "Enable auto-reload for the plugin when its configuration changes."
     )]
     pub auto_reload_plugin: bool,
+    #[field_names(
+        desc = "The git repository grammars are installed from when a language has no source in [core.grammars]. {name} is replaced with the name of the grammar"
+    )]
+    pub grammar_registry: String,
+    /// Where the grammar of the languages listed is installed from, keyed by
+    /// their lowercase name. The languages that aren't built in are
+    /// registered under that name for the files they list.
+    #[field_names(skip)]
+    pub grammars: HashMap<String, GrammarSource>,
+}
+
+/// Where a tree-sitter grammar is installed from.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct GrammarSource {
+    /// The url of a git repository, or the path of a local directory.
+    pub source: String,
+    /// The branch or tag of the repository to check out.
+    #[serde(default)]
+    pub rev: Option<String>,
+    /// The directory of the grammar within the source, for repositories
+    /// holding several grammars.
+    #[serde(default)]
+    pub subpath: Option<String>,
+    /// The file name extensions of a language that isn't built in.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// The file names of a language that isn't built in.
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// The single line comment token of a language that isn't built in.
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+impl CoreConfig {
+    /// The source of the grammar of `language`, falling back to the
+    /// grammar registry.
+    pub fn grammar_source(
+        &self,
+        language: &str,
+        grammar_name: &str,
+    ) -> Option<GrammarSource> {
+        if let Some(source) = self.grammars.get(&language.to_lowercase()) {
+            return Some(source.clone());
+        }
+        if self.grammar_registry.is_empty() {
+            return None;
+        }
+        Some(GrammarSource {
+            source: self.grammar_registry.replace("{name}", grammar_name),
+            rev: None,
+            subpath: None,
+            extensions: Vec::new(),
+            files: Vec::new(),
+            comment: None,
+        })
+    }
 }
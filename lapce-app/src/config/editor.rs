@@ -1,6 +1,15 @@
-use floem::views::editor::text::RenderWhitespace;
+use std::{collections::HashMap, time::Duration};
+
+use floem::{
+    text::{Attrs, AttrsList, FamilyOwned, TextLayout},
+    views::editor::text::RenderWhitespace as FloemRenderWhitespace,
+};
+use lapce_core::line_ending::LineEnding;
+use lapce_rpc::proxy::ExternalFormatter;
+use lsp_types::CodeActionKind;
 use serde::{Deserialize, Serialize};
 use structdesc::FieldNames;
+use unicode_width::UnicodeWidthChar;
 
 pub const SCALE_OR_SIZE_LIMIT: f64 = 5.0;
 
@@ -23,8 +32,8 @@ pub enum WrapStyle {
     /// Wrap at the editor width
     #[default]
     EditorWidth,
-    // /// Wrap at the wrap-column
-    // WrapColumn,
+    /// Wrap at the wrap-column
+    WrapColumn,
     /// Wrap at a specific width
     WrapWidth,
 }
@@ -33,7 +42,7 @@ pub fn as_str(&self) -> &'static str {
         match self {
             WrapStyle::None => "none",
             WrapStyle::EditorWidth => "editor-width",
-            // WrapStyle::WrapColumn => "wrap-column",
+            WrapStyle::WrapColumn => "wrap-column",
             WrapStyle::WrapWidth => "wrap-width",
         }
     }
@@ -42,7 +51,7 @@ pub fn try_from_str(s: &str) -> Option<Self> {
         match s {
             "none" => Some(WrapStyle::None),
             "editor-width" => Some(WrapStyle::EditorWidth),
-            // "wrap-column" => Some(WrapStyle::WrapColumn),
+            "wrap-column" => Some(WrapStyle::WrapColumn),
             "wrap-width" => Some(WrapStyle::WrapWidth),
             _ => None,
         }
@@ -57,6 +66,201 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     }
 }
 
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    Default,
+    PartialEq,
+    Eq,
+    strum_macros::VariantNames,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum DefaultLineEnding {
+    /// The line ending of the platform
+    #[default]
+    Auto,
+    Lf,
+    Crlf,
+}
+
+impl DefaultLineEnding {
+    pub fn line_ending(self) -> LineEnding {
+        match self {
+            DefaultLineEnding::Auto => {
+                if cfg!(windows) {
+                    LineEnding::CrLf
+                } else {
+                    LineEnding::Lf
+                }
+            }
+            DefaultLineEnding::Lf => LineEnding::Lf,
+            DefaultLineEnding::Crlf => LineEnding::CrLf,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    Default,
+    PartialEq,
+    Eq,
+    strum_macros::VariantNames,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum RenderWhitespace {
+    #[default]
+    None,
+    /// Whitespace other than single spaces between words
+    Boundary,
+    /// Whitespace within the selections
+    Selection,
+    /// Whitespace at the end of lines
+    Trailing,
+    All,
+}
+
+impl RenderWhitespace {
+    /// The mode floem renders whitespace in. Whitespace within the selections is
+    /// painted by the editor view itself, so floem renders none for it.
+    pub fn floem_mode(self) -> FloemRenderWhitespace {
+        match self {
+            RenderWhitespace::None | RenderWhitespace::Selection => {
+                FloemRenderWhitespace::None
+            }
+            RenderWhitespace::Boundary => FloemRenderWhitespace::Boundary,
+            RenderWhitespace::Trailing => FloemRenderWhitespace::Trailing,
+            RenderWhitespace::All => FloemRenderWhitespace::All,
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    Default,
+    PartialEq,
+    Eq,
+    strum_macros::VariantNames,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum WrapIndent {
+    /// Wrapped parts of a line start at the first column
+    #[default]
+    None,
+    /// Wrapped parts of a line start at the line's indentation
+    Same,
+    /// Wrapped parts of a line start one indent level past the line's indentation
+    IndentPlusOne,
+}
+
+impl WrapIndent {
+    /// The number of columns the wrapped parts of `line` are indented by.
+    pub fn width(self, line: &str, tab_width: usize) -> usize {
+        let leading = || {
+            line.chars()
+                .take_while(|c| *c == ' ' || *c == '\t')
+                .map(|c| if c == '\t' { tab_width } else { 1 })
+                .sum::<usize>()
+        };
+        match self {
+            WrapIndent::None => 0,
+            WrapIndent::Same => leading(),
+            WrapIndent::IndentPlusOne => leading() + tab_width,
+        }
+    }
+
+    /// The byte columns of `line` at which its wrapped parts start, when it is
+    /// wrapped at `wrap_columns` columns with those parts indented by `indent`
+    /// columns.  
+    /// This mirrors the word wrapping of the text layout for a monospaced font:
+    /// lines break after whitespace, which may hang past the wrap column, and
+    /// words that don't fit on a line of their own are broken between characters.
+    pub fn wrap_columns(
+        line: &str,
+        indent: usize,
+        wrap_columns: usize,
+        tab_width: usize,
+    ) -> Vec<usize> {
+        let mut breaks = Vec::new();
+        // Leave the indented lines room for at least a character
+        if indent == 0 || indent >= wrap_columns {
+            return breaks;
+        }
+
+        let char_width = |c: char| {
+            if c == '\t' {
+                tab_width
+            } else {
+                c.width().unwrap_or(0)
+            }
+        };
+
+        let mut line_start = 0;
+        let mut x = 0;
+        let mut chars = line.char_indices().peekable();
+        while let Some(&(word_start, _)) = chars.peek() {
+            let mut word = Vec::new();
+            while let Some(&(col, c)) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push((col, char_width(c)));
+                chars.next();
+            }
+            let mut space_width = 0;
+            while let Some(&(_, c)) = chars.peek() {
+                if !c.is_whitespace() || c == '\n' || c == '\r' {
+                    break;
+                }
+                space_width += char_width(c);
+                chars.next();
+            }
+
+            let word_width: usize = word.iter().map(|(_, w)| w).sum();
+            if !word.is_empty()
+                && x + word_width > wrap_columns
+                && word_start != line_start
+            {
+                breaks.push(word_start);
+                line_start = word_start;
+                x = indent;
+            }
+            if x + word_width > wrap_columns {
+                for &(col, width) in &word {
+                    if x + width > wrap_columns && col != line_start {
+                        breaks.push(col);
+                        line_start = col;
+                        x = indent;
+                    }
+                    x += width;
+                }
+            } else {
+                x += word_width;
+            }
+            x += space_width;
+
+            if chars.peek().is_some_and(|(_, c)| *c == '\n' || *c == '\r') {
+                break;
+            }
+        }
+
+        breaks
+    }
+}
+
 #[derive(FieldNames, Debug, Clone, Deserialize, Serialize, Default)]
 #[serde(rename_all = "kebab-case")]
 pub struct EditorConfig {
@@ -64,6 +268,11 @@ pub struct EditorConfig {
     pub font_family: String,
     #[field_names(desc = "Set the editor font size")]
     font_size: usize,
+    /// The steps Zoom In and Zoom Out have moved the font size away from
+    /// `font-size`, so that Reset Zoom can go back to it.
+    #[field_names(skip)]
+    #[serde(default)]
+    pub font_size_zoom: i64,
     #[field_names(desc = "Set the font size in the code glance")]
     pub code_glance_font_size: usize,
     #[field_names(
@@ -86,16 +295,42 @@ pub struct EditorConfig {
         desc = "Set the minimum number of visible lines above and below the cursor"
     )]
     pub cursor_surrounding_lines: usize,
+    #[field_names(
+        desc = "Set the minimum number of visible columns to the left and right of the cursor"
+    )]
+    pub cursor_surrounding_columns: usize,
     #[field_names(desc = "The kind of wrapping to perform")]
     pub wrap_style: WrapStyle,
-    // #[field_names(desc = "The number of columns to wrap at")]
-    // pub wrap_column: usize,
+    #[field_names(desc = "The number of columns to wrap at")]
+    pub wrap_column: usize,
     #[field_names(desc = "The number of pixels to wrap at")]
     pub wrap_width: usize,
+    #[field_names(
+        desc = "How far the wrapped parts of a line are indented: none, the same as the line, or one more level"
+    )]
+    pub wrap_indent: WrapIndent,
+    #[field_names(
+        desc = "The columns to draw vertical rulers at, separated by commas (e.g. 80,100)"
+    )]
+    pub rulers: String,
+    #[field_names(
+        desc = "Center the editor text, keeping it within the centered layout max width"
+    )]
+    pub centered_layout: bool,
+    #[field_names(
+        desc = "The maximum width in pixels of the editor text in the centered layout"
+    )]
+    pub centered_layout_max_width: usize,
     #[field_names(
         desc = "Show code context like functions and classes at the top of editor when scroll"
     )]
     pub sticky_header: bool,
+    #[field_names(desc = "Fold the blocks of imports when a file is opened")]
+    pub auto_fold_imports: bool,
+    #[field_names(
+        desc = "Fold the regions between `region` and `endregion` marker comments when a file is opened"
+    )]
+    pub auto_fold_regions: bool,
     #[field_names(desc = "The number of pixels to show completion")]
     pub completion_width: usize,
     #[field_names(
@@ -122,6 +357,26 @@ pub struct EditorConfig {
         desc = "Whether the editor should automatically surround selected text when typing quotes or brackets"
     )]
     pub auto_surround: bool,
+    #[field_names(
+        desc = "Whether pressing Enter in a line comment continues the comment on the new line"
+    )]
+    pub continue_comments: bool,
+    #[field_names(
+        desc = "Whether pressing Tab after an Emmet abbreviation, like `ul>li*3`, expands it in HTML, JSX and CSS documents"
+    )]
+    pub emmet: bool,
+    #[field_names(
+        desc = "Whether lines pasted from the clipboard are re-indented to match the indentation where they are pasted"
+    )]
+    pub reindent_on_paste: bool,
+    #[field_names(
+        desc = "Whether editing a range the language server links to others, like the opening tag of an HTML element, edits them along with it"
+    )]
+    pub linked_editing: bool,
+    #[field_names(
+        desc = "Whether selected text can be moved by dragging it with the mouse, or copied while holding Ctrl"
+    )]
+    pub drag_and_drop: bool,
     #[field_names(
         desc = "How long (in ms) it should take before the hover information appears"
     )]
@@ -134,11 +389,34 @@ pub struct EditorConfig {
         desc = "Whether it should format the document on save (if there is an available formatter)"
     )]
     pub format_on_save: bool,
+    /// The former `[editor.format-on-save-languages]` table, keyed by the
+    /// lowercase name of a language, which is moved into the
+    /// `format-on-save` of the `[languages.<name>]` blocks when the config is
+    /// loaded.
+    #[field_names(skip)]
+    #[serde(default)]
+    pub format_on_save_languages: HashMap<String, bool>,
+    #[field_names(
+        desc = "How long (in ms) to wait for the formatter, or for each code action on save, before saving without it"
+    )]
+    pub format_timeout: u64,
+    /// The commands formatting the documents of the languages listed, keyed
+    /// by their lowercase name, in place of or after the language servers.
+    #[field_names(skip)]
+    pub formatters: HashMap<String, ExternalFormatter>,
+    #[field_names(
+        desc = "The kinds of code actions to run on save before formatting, separated by commas (e.g. source.organizeImports)"
+    )]
+    pub code_actions_on_save: String,
 
     #[field_names(
         desc = "Whether newlines should be automatically converted to the current line ending"
     )]
     pub normalize_line_endings: bool,
+    #[field_names(
+        desc = "The line ending of new files and of files without any line break: auto (the platform's), lf or crlf. Other files keep the one they have."
+    )]
+    pub default_line_ending: DefaultLineEnding,
 
     #[field_names(desc = "If matching brackets are highlighted")]
     pub highlight_matching_brackets: bool,
@@ -182,6 +460,14 @@ pub struct EditorConfig {
         desc = "Set the error lens font size. If 0 it uses the inlay hint font size."
     )]
     pub error_lens_font_size: usize,
+    #[field_names(
+        desc = "If misspelled words in comments, strings and text files should be underlined"
+    )]
+    pub enable_spell_check: bool,
+    #[field_names(
+        desc = "The hunspell dictionary used for spell checking, looked up as `<name>.dic` in the dictionaries directory and then in the system's hunspell directories"
+    )]
+    pub spell_check_dictionary: String,
     #[field_names(
         desc = "If the editor should display the completion item as phantom text"
     )]
@@ -209,9 +495,13 @@ pub struct EditorConfig {
     )]
     pub multicursor_whole_words: bool,
     #[field_names(
-        desc = "How the editor should render whitespace characters.\nOptions: none, all, boundary, trailing."
+        desc = "How the editor should render whitespace characters.\nOptions: none, boundary, selection, trailing, all."
     )]
     pub render_whitespace: RenderWhitespace,
+    #[field_names(desc = "Whether the editor highlights trailing whitespace.")]
+    pub highlight_trailing_whitespace: bool,
+    #[field_names(desc = "Whether trailing whitespace is removed when saving.")]
+    pub trim_trailing_whitespace_on_save: bool,
     #[field_names(desc = "Whether the editor show indent guide.")]
     pub show_indent_guide: bool,
     #[field_names(
@@ -244,27 +534,119 @@ pub struct EditorConfig {
         desc = "Glob patterns for excluding files and folders (in file explorer)"
     )]
     pub files_exclude: String,
+    /// The languages of the files matching the glob patterns listed, by the
+    /// name of the language, e.g. `"*.inc" = "php"`. The patterns without a
+    /// `/` are matched against the file name.
+    #[field_names(skip)]
+    pub file_associations: HashMap<String, String>,
+}
+
+/// The settings of a `[languages.<name>]` block, e.g. `[languages.rust]`,
+/// overriding those of the editor for the documents of that language.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct LanguageEditorConfig {
+    pub tab_width: Option<usize>,
+    pub wrap_style: Option<WrapStyle>,
+    pub wrap_column: Option<usize>,
+    pub wrap_width: Option<usize>,
+    pub rulers: Option<String>,
+    pub format_on_save: Option<bool>,
+    pub font_family: Option<String>,
+    pub font_size: Option<usize>,
 }
 
 impl EditorConfig {
+    /// These settings with the ones set in `overrides` replaced.
+    pub fn with_overrides(&self, overrides: &LanguageEditorConfig) -> EditorConfig {
+        let mut config = self.clone();
+        if let Some(tab_width) = overrides.tab_width {
+            config.tab_width = tab_width;
+        }
+        if let Some(wrap_style) = overrides.wrap_style {
+            config.wrap_style = wrap_style;
+        }
+        if let Some(wrap_column) = overrides.wrap_column {
+            config.wrap_column = wrap_column;
+        }
+        if let Some(wrap_width) = overrides.wrap_width {
+            config.wrap_width = wrap_width;
+        }
+        if let Some(rulers) = overrides.rulers.as_ref() {
+            config.rulers.clone_from(rulers);
+        }
+        if let Some(format_on_save) = overrides.format_on_save {
+            config.format_on_save = format_on_save;
+        }
+        if let Some(font_family) = overrides.font_family.as_ref() {
+            config.font_family.clone_from(font_family);
+        }
+        if let Some(font_size) = overrides.font_size {
+            config.font_size = font_size;
+        }
+        config
+    }
+
+    /// The external formatter of documents of `language`, if one is set.
+    pub fn formatter(&self, language: &str) -> Option<ExternalFormatter> {
+        self.formatters
+            .get(&language.to_lowercase())
+            .filter(|formatter| !formatter.command.is_empty())
+            .cloned()
+    }
+
+    pub fn format_timeout(&self) -> Duration {
+        Duration::from_millis(self.format_timeout)
+    }
+
+    pub fn code_actions_on_save(&self) -> Vec<CodeActionKind> {
+        self.code_actions_on_save
+            .split(',')
+            .map(str::trim)
+            .filter(|kind| !kind.is_empty())
+            .map(|kind| CodeActionKind::from(kind.to_string()))
+            .collect()
+    }
+
+    /// The columns to draw the rulers at.
+    pub fn rulers(&self) -> Vec<usize> {
+        self.rulers
+            .split(',')
+            .filter_map(|column| column.trim().parse().ok())
+            .collect()
+    }
+
     pub fn font_size(&self) -> usize {
-        self.font_size.clamp(6, 32)
+        (self.font_size as i64 + self.font_size_zoom).clamp(6, 32) as usize
+    }
+
+    /// The width of a character of the editor font, which is assumed to be
+    /// monospaced.
+    pub fn char_width(&self) -> f64 {
+        let family: Vec<FamilyOwned> =
+            FamilyOwned::parse_list(&self.font_family).collect();
+        let attrs = Attrs::new()
+            .family(&family)
+            .font_size(self.font_size() as f32);
+        let mut text_layout = TextLayout::new();
+        text_layout.set_text("W", AttrsList::new(attrs));
+        text_layout.size().width
     }
 
     pub fn line_height(&self) -> usize {
         let line_height = if self.line_height < SCALE_OR_SIZE_LIMIT {
-            self.line_height * self.font_size as f64
+            self.line_height * self.font_size() as f64
         } else {
             self.line_height
         };
 
         // Prevent overlapping lines
-        (line_height.round() as usize).max(self.font_size)
+        (line_height.round() as usize).max(self.font_size())
     }
 
     pub fn inlay_hint_font_size(&self) -> usize {
         if self.inlay_hint_font_size < 5
-            || self.inlay_hint_font_size > self.font_size
+            || self.inlay_hint_font_size > self.font_size()
         {
             self.font_size()
         } else {
@@ -304,3 +686,120 @@ pub fn blink_interval(&self) -> u64 {
         self.blink_interval.max(200)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{EditorConfig, LanguageEditorConfig, WrapIndent, WrapStyle};
+
+    #[test]
+    fn test_with_overrides() {
+        let config = EditorConfig {
+            tab_width: 4,
+            font_family: "monospace".to_string(),
+            font_size: 13,
+            line_height: 1.5,
+            rulers: "80".to_string(),
+            format_on_save: false,
+            ..Default::default()
+        };
+
+        let overridden = config.with_overrides(&LanguageEditorConfig::default());
+        assert_eq!(overridden.tab_width, 4);
+        assert_eq!(overridden.font_size, 13);
+        assert_eq!(overridden.rulers(), vec![80]);
+
+        let overridden = config.with_overrides(&LanguageEditorConfig {
+            tab_width: Some(2),
+            wrap_style: Some(WrapStyle::WrapColumn),
+            rulers: Some("72, 100".to_string()),
+            format_on_save: Some(true),
+            font_size: Some(20),
+            ..Default::default()
+        });
+        assert_eq!(overridden.tab_width, 2);
+        assert_eq!(overridden.wrap_style, WrapStyle::WrapColumn);
+        assert_eq!(overridden.rulers(), vec![72, 100]);
+        assert!(overridden.format_on_save);
+        assert_eq!(overridden.font_family, "monospace");
+        assert_eq!(overridden.font_size(), 20);
+        // The line height scales with the overridden font size.
+        assert_eq!(config.line_height(), 20);
+        assert_eq!(overridden.line_height(), 30);
+    }
+
+    #[test]
+    fn test_font_size_zoom() {
+        let config = EditorConfig {
+            font_size: 13,
+            line_height: 1.5,
+            font_size_zoom: 3,
+            ..Default::default()
+        };
+        assert_eq!(config.font_size(), 16);
+        assert_eq!(config.line_height(), 24);
+
+        // A language override keeps the zoom on top of its own size.
+        let overridden = config.with_overrides(&LanguageEditorConfig {
+            font_size: Some(20),
+            ..Default::default()
+        });
+        assert_eq!(overridden.font_size(), 23);
+
+        let config = EditorConfig {
+            font_size: 13,
+            font_size_zoom: -20,
+            ..Default::default()
+        };
+        assert_eq!(config.font_size(), 6);
+    }
+
+    #[test]
+    fn test_wrap_style_from_str() {
+        for style in [
+            WrapStyle::None,
+            WrapStyle::EditorWidth,
+            WrapStyle::WrapColumn,
+            WrapStyle::WrapWidth,
+        ] {
+            assert_eq!(WrapStyle::try_from_str(style.as_str()), Some(style));
+        }
+        assert_eq!(WrapStyle::try_from_str("column"), None);
+    }
+
+    #[test]
+    fn test_wrap_indent_width() {
+        assert_eq!(WrapIndent::None.width("    let a = 1;", 4), 0);
+        assert_eq!(WrapIndent::Same.width("    let a = 1;", 4), 4);
+        assert_eq!(WrapIndent::Same.width("\t  let a = 1;", 4), 6);
+        assert_eq!(WrapIndent::IndentPlusOne.width("  let a = 1;", 4), 6);
+        assert_eq!(WrapIndent::IndentPlusOne.width("let a = 1;", 2), 2);
+    }
+
+    #[test]
+    fn test_wrap_indent_columns() {
+        // Wrapped words start on an indented line, with the whitespace before
+        // them left hanging on the previous one.
+        assert_eq!(
+            WrapIndent::wrap_columns("  aaa bbb ccc ddd\n", 2, 8, 4),
+            vec![6, 10, 14]
+        );
+        // Whitespace may hang past the wrap column.
+        assert_eq!(
+            WrapIndent::wrap_columns("aaaa bbbb      cc\n", 2, 10, 4),
+            vec![15]
+        );
+        // Words too long for a line are broken between characters.
+        assert_eq!(
+            WrapIndent::wrap_columns("aaaaaaaaaaaaaaaa", 2, 8, 4),
+            vec![8, 14]
+        );
+        assert_eq!(
+            WrapIndent::wrap_columns("a bbbbbbbbbbbb", 2, 8, 4),
+            vec![2, 8]
+        );
+        // Lines that fit, or that can't be given an indent, aren't changed.
+        assert!(WrapIndent::wrap_columns("aaa bbb\n", 2, 10, 4).is_empty());
+        assert!(WrapIndent::wrap_columns("aaa bbb ccc", 0, 4, 4).is_empty());
+        assert!(WrapIndent::wrap_columns("aaa bbb ccc", 4, 4, 4).is_empty());
+    }
+}
@@ -30,7 +30,9 @@ impl LapceIcons {
     pub const START: &'static str = "start";
     pub const RUN_ERRORS: &'static str = "run_errors";
     pub const UNSAVED: &'static str = "unsaved";
+    pub const PINNED: &'static str = "pinned";
     pub const WARNING: &'static str = "warning";
+    pub const INFO: &'static str = "info";
     pub const TERMINAL: &'static str = "terminal";
     pub const SETTINGS: &'static str = "settings";
     pub const LIGHTBULB: &'static str = "lightbulb";
@@ -100,6 +102,14 @@ impl LapceIcons {
     pub const SEARCH_REGEX: &'static str = "search.regex";
     pub const SEARCH_REPLACE: &'static str = "search.replace";
     pub const SEARCH_REPLACE_ALL: &'static str = "search.replace_all";
+    pub const SEARCH_PRESERVE_CASE: &'static str = "search.preserve_case";
+    pub const SEARCH_IN_SELECTION: &'static str = "search.in_selection";
+
+    pub const IMAGE_ZOOM_IN: &'static str = "image.zoom_in";
+    pub const IMAGE_ZOOM_OUT: &'static str = "image.zoom_out";
+    pub const IMAGE_FIT: &'static str = "image.fit";
+
+    pub const MARKDOWN_PREVIEW: &'static str = "markdown.preview";
 
     pub const FILE_TYPE_CODE: &'static str = "file-code";
     pub const FILE_TYPE_MEDIA: &'static str = "file-media";
@@ -115,6 +125,16 @@ impl LapceIcons {
 
     pub const IMPLEMENTATION: &'static str = "document_symbol";
 
+    pub const UNDO_TREE: &'static str = "undo_tree";
+
+    pub const BOOKMARK: &'static str = "bookmark";
+
+    pub const OUTPUT: &'static str = "output";
+
+    pub const TASKS: &'static str = "tasks";
+
+    pub const NOTIFICATION: &'static str = "notification";
+
     pub const SYMBOL_KIND_ARRAY: &'static str = "symbol_kind.array";
     pub const SYMBOL_KIND_BOOLEAN: &'static str = "symbol_kind.boolean";
     pub const SYMBOL_KIND_CLASS: &'static str = "symbol_kind.class";
@@ -4,6 +4,7 @@
 };
 
 use indexmap::IndexMap;
+use lapce_core::language::LapceLanguage;
 use serde::{Deserialize, Serialize};
 
 /// Returns the first item yielded from `items` if at least one item is yielded, all yielded items
@@ -37,13 +38,26 @@ impl IconThemeConfig {
     /// extension), and there is an icon associated with that file type, returns the path of the
     /// icon.
     pub fn resolve_path_to_icon(&self, paths: &[&Path]) -> Option<PathBuf> {
+        // The files set to a language by the `file-associations` setting
+        // take the icon of its extensions
+        let languages = paths
+            .iter()
+            .map(|path| LapceLanguage::from_file_association(path));
+        let association_icon = try_all_equal_value(languages).and_then(|language| {
+            language
+                .extensions()
+                .iter()
+                .find_map(|extension| self.extension.get(*extension))
+        });
+
         let file_names = paths
             .iter()
             .map(|path| path.file_name().and_then(OsStr::to_str));
         let file_name_icon = try_all_equal_value(file_names)
             .and_then(|file_name| self.filename.get(file_name));
 
-        file_name_icon
+        association_icon
+            .or(file_name_icon)
             .or_else(|| {
                 let extensions = paths
                     .iter()
@@ -1,7 +1,7 @@
 use std::{collections::HashMap, sync::Arc};
 
 use floem::peniko::Color;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use structdesc::FieldNames;
 
 #[derive(FieldNames, Debug, Clone, Deserialize, Serialize, Default)]
@@ -15,14 +15,34 @@ pub struct TerminalConfig {
         desc = "Set the terminal font size, If 0, it uses editor font size."
     )]
     pub font_size: usize,
+    /// The steps Zoom In and Zoom Out have moved the font size away from
+    /// `font-size`, so that Reset Zoom can go back to it.
+    #[field_names(skip)]
+    #[serde(default)]
+    pub font_size_zoom: i64,
     #[field_names(
         desc = "Set the terminal line height, If 0, it uses editor line height"
     )]
     pub line_height: f64,
+    #[field_names(
+        desc = "Prefix every line of terminal output logged to a file with the time it was received"
+    )]
+    pub log_timestamps: bool,
+    #[field_names(
+        desc = "Show the CPU and memory usage of the foreground process of each terminal in its tab (Linux only)"
+    )]
+    pub show_process_usage: bool,
+    #[field_names(
+        desc = "Insert the paths of files dropped onto a terminal relative to the workspace instead of absolute"
+    )]
+    pub drop_relative_paths: bool,
 
     #[field_names(skip)]
     pub profiles: HashMap<String, TerminalProfile>,
+    /// The profile to use per operating system. A plain profile name, e.g. in a
+    /// workspace's `.lapce/settings.toml`, applies to every operating system.
     #[field_names(skip)]
+    #[serde(deserialize_with = "deserialize_default_profile")]
     pub default_profile: HashMap<String, String>,
 
     #[serde(skip)]
@@ -43,6 +63,27 @@ pub struct TerminalProfile {
     pub environment: Option<HashMap<String, String>>,
 }
 
+fn deserialize_default_profile<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DefaultProfile {
+        All(String),
+        PerOs(HashMap<String, String>),
+    }
+
+    Ok(match DefaultProfile::deserialize(deserializer)? {
+        DefaultProfile::All(name) => {
+            HashMap::from([(std::env::consts::OS.to_string(), name)])
+        }
+        DefaultProfile::PerOs(profiles) => profiles,
+    })
+}
+
 impl TerminalConfig {
     pub fn get_indexed_colors(&mut self) {
         let mut indexed_colors = HashMap::new();
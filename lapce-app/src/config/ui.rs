@@ -8,6 +8,11 @@ pub struct UIConfig {
     #[field_names(desc = "Set the UI scale. Defaults to 1.0")]
     scale: f64,
 
+    #[field_names(
+        desc = "Set what Zoom In, Zoom Out and Reset Zoom resize: the whole window, the editor font or the terminal font"
+    )]
+    pub zoom_scope: ZoomScope,
+
     #[field_names(
         desc = "Set the UI font family. If empty, it uses system default."
     )]
@@ -62,6 +67,15 @@ pub struct UIConfig {
 
     #[field_names(desc = "Display the Open Editors section in the explorer")]
     pub open_editors_visible: bool,
+
+    #[field_names(desc = "Maximize the window while in Zen mode")]
+    pub zen_mode_maximize: bool,
+
+    #[field_names(desc = "Center the editors while in Zen mode")]
+    pub zen_mode_centered: bool,
+
+    #[field_names(desc = "Set the width of the centered editors while in Zen mode")]
+    zen_mode_width: usize,
 }
 
 #[derive(
@@ -103,6 +117,26 @@ pub enum TabSeparatorHeight {
     Full,
 }
 
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Deserialize,
+    Serialize,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    strum_macros::VariantNames,
+)]
+pub enum ZoomScope {
+    Editor,
+    Terminal,
+    #[default]
+    Window,
+}
+
 impl UIConfig {
     pub fn scale(&self) -> f64 {
         self.scale.clamp(0.1, 4.0)
@@ -112,6 +146,10 @@ pub fn font_size(&self) -> usize {
         self.font_size.clamp(6, 32)
     }
 
+    pub fn zen_mode_width(&self) -> usize {
+        self.zen_mode_width.max(200)
+    }
+
     pub fn font_family(&self) -> Vec<FamilyOwned> {
         FamilyOwned::parse_list(&self.font_family).collect()
     }
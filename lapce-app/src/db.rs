@@ -15,6 +15,7 @@
     app::{AppData, AppInfo},
     doc::DocInfo,
     panel::{data::PanelOrder, kind::PanelKind},
+    terminal::layout::TerminalLayout,
     window::{WindowData, WindowInfo},
     window_tab::WindowTabData,
     workspace::{LapceWorkspace, WorkspaceInfo},
@@ -27,6 +28,7 @@
 const PANEL_ORDERS: &str = "panel_orders";
 const DISABLED_VOLTS: &str = "disabled_volts";
 const RECENT_WORKSPACES: &str = "recent_workspaces";
+const TERMINAL_LAYOUTS: &str = "terminal_layouts";
 
 pub enum SaveEvent {
     App(AppInfo),
@@ -36,6 +38,7 @@ pub enum SaveEvent {
     DisabledVolts(Vec<VoltID>),
     WorkspaceDisabledVolts(Arc<LapceWorkspace>, Vec<VoltID>),
     PanelOrder(PanelOrder),
+    TerminalLayouts(LapceWorkspace, Vec<TerminalLayout>),
 }
 
 #[derive(Clone)]
@@ -110,6 +113,13 @@ pub fn new() -> Result<Self> {
                                 tracing::error!("{:?}", err);
                             }
                         }
+                        SaveEvent::TerminalLayouts(workspace, layouts) => {
+                            if let Err(err) = local_db
+                                .insert_terminal_layouts(&workspace, &layouts)
+                            {
+                                tracing::error!("{:?}", err);
+                            }
+                        }
                     }
                 }
             })
@@ -393,6 +403,46 @@ fn insert_panel_orders(&self, order: &PanelOrder) -> Result<()> {
         Ok(())
     }
 
+    pub fn get_terminal_layouts(
+        &self,
+        workspace: &LapceWorkspace,
+    ) -> Result<Vec<TerminalLayout>> {
+        let layouts = std::fs::read_to_string(
+            self.workspace_folder
+                .join(workspace_folder_name(workspace))
+                .join(TERMINAL_LAYOUTS),
+        )?;
+        let layouts: Vec<TerminalLayout> = serde_json::from_str(&layouts)?;
+        Ok(layouts)
+    }
+
+    pub fn save_terminal_layouts(
+        &self,
+        workspace: &LapceWorkspace,
+        layouts: Vec<TerminalLayout>,
+    ) {
+        if let Err(err) = self
+            .save_tx
+            .send(SaveEvent::TerminalLayouts(workspace.clone(), layouts))
+        {
+            tracing::error!("{:?}", err);
+        }
+    }
+
+    fn insert_terminal_layouts(
+        &self,
+        workspace: &LapceWorkspace,
+        layouts: &[TerminalLayout],
+    ) -> Result<()> {
+        let folder = self.workspace_folder.join(workspace_folder_name(workspace));
+        if let Err(err) = std::fs::create_dir_all(&folder) {
+            tracing::error!("{:?}", err);
+        }
+        let layouts = serde_json::to_string_pretty(layouts)?;
+        std::fs::write(folder.join(TERMINAL_LAYOUTS), layouts)?;
+        Ok(())
+    }
+
     pub fn save_doc_position(
         &self,
         workspace: &LapceWorkspace,
@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
     path::PathBuf,
     rc::Rc,
@@ -12,12 +12,14 @@
     views::VirtualVector,
 };
 use lapce_rpc::{
+    counter::Counter,
     dap_types::{
-        self, DapId, RunDebugConfig, SourceBreakpoint, StackFrame, Stopped,
-        ThreadId, Variable,
+        self, DapId, RunDebugConfig, SetVariableResponse, SourceBreakpoint,
+        StackFrame, Stopped, ThreadId, Variable,
     },
     proxy::ProxyResponse,
     terminal::TermId,
+    RpcError,
 };
 use serde::{Deserialize, Serialize};
 
@@ -52,6 +54,13 @@ pub struct RunDebugProcess {
     pub is_prelaunch: bool,
 }
 
+/// The file in the `.lapce` folder of the workspace listing the configs to
+/// run, and to debug.
+pub const RUN_TOML: &str = "run.toml";
+/// The file in the `.lapce` folder of the workspace listing more configs, to
+/// launch a program in a debugger or attach one to it.
+pub const LAUNCH_TOML: &str = "launch.toml";
+
 #[derive(Deserialize, Serialize)]
 pub struct RunDebugConfigs {
     pub configs: Vec<RunDebugConfig>,
@@ -62,6 +71,9 @@ pub struct RunDebugData {
     pub active_term: RwSignal<Option<TermId>>,
     pub daps: RwSignal<im::HashMap<DapId, DapData>>,
     pub breakpoints: RwSignal<BTreeMap<PathBuf, BTreeMap<usize, LapceBreakpoint>>>,
+    /// The expressions of the watch view, evaluated again each time a debug
+    /// session stops.
+    pub watches: RwSignal<im::Vector<String>>,
 }
 
 impl RunDebugData {
@@ -77,6 +89,7 @@ pub fn new(
             active_term,
             daps,
             breakpoints,
+            watches: cx.create_rw_signal(im::Vector::new()),
         }
     }
 
@@ -170,6 +183,15 @@ pub fn reference(&self) -> usize {
     }
 }
 
+/// The trees of variables of the debug panel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VariableTree {
+    /// The variables of the scopes of the active frame.
+    Variables,
+    /// The values of the watched expressions.
+    Watch,
+}
+
 #[derive(Clone, Default)]
 pub struct DapVariable {
     pub item: ScopeOrVar,
@@ -180,6 +202,83 @@ pub struct DapVariable {
     pub children_expanded_count: usize,
 }
 
+/// The most entries kept in the debug console, dropping the oldest ones.
+const CONSOLE_LIMIT: usize = 2000;
+
+/// A line of the debug console.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ConsoleEntry {
+    /// An expression evaluated in the console.
+    Input(String),
+    /// What the program printed, or the debugger, with the category of the
+    /// output as `stdout`, `stderr` or `console`.
+    Output {
+        text: String,
+        category: Option<String>,
+    },
+    /// The value of an evaluated expression.
+    Result(String),
+    Error(String),
+}
+
+impl ConsoleEntry {
+    /// The text of the entry as shown in the console.
+    pub fn text(&self) -> String {
+        match self {
+            ConsoleEntry::Input(expression) => format!("> {expression}"),
+            ConsoleEntry::Output { text, .. } => {
+                text.trim_end_matches(['\r', '\n']).to_string()
+            }
+            ConsoleEntry::Result(text) | ConsoleEntry::Error(text) => text.clone(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        match self {
+            ConsoleEntry::Error(_) => true,
+            ConsoleEntry::Output { category, .. } => {
+                category.as_deref() == Some("stderr")
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Add `entry` to the console, joining the output printed without a newline
+/// with the output of the same category after it.
+///
+/// Each entry has an id of its own among the entries of all the consoles,
+/// which an entry gets anew when output is joined to it.
+fn push_console_entry(
+    console: &mut im::Vector<(u64, ConsoleEntry)>,
+    mut entry: ConsoleEntry,
+) {
+    static CONSOLE_ENTRY_ID_COUNTER: Counter = Counter::new();
+
+    if let ConsoleEntry::Output { text, category } = &entry {
+        if let Some((
+            _,
+            ConsoleEntry::Output {
+                text: last_text,
+                category: last_category,
+            },
+        )) = console.back()
+        {
+            if last_category == category && !last_text.ends_with('\n') {
+                entry = ConsoleEntry::Output {
+                    text: format!("{last_text}{text}"),
+                    category: category.clone(),
+                };
+                console.pop_back();
+            }
+        }
+    }
+    console.push_back((CONSOLE_ENTRY_ID_COUNTER.next(), entry));
+    while console.len() > CONSOLE_LIMIT {
+        console.pop_front();
+    }
+}
+
 #[derive(Clone)]
 pub struct DapData {
     pub term_id: TermId,
@@ -190,6 +289,13 @@ pub struct DapData {
     pub variables_id: RwSignal<usize>,
     pub variables: RwSignal<DapVariable>,
     pub breakline: Memo<Option<(usize, PathBuf)>>,
+    /// The frame the expressions of the debug console are evaluated in, the
+    /// top one of the stopped thread unless another one was picked.
+    pub active_frame: RwSignal<Option<usize>>,
+    pub console: RwSignal<im::Vector<(u64, ConsoleEntry)>>,
+    /// The watched expressions, shared by all the debug sessions.
+    pub watch_expressions: RwSignal<im::Vector<String>>,
+    pub watches: RwSignal<DapVariable>,
     pub common: Rc<CommonData>,
 }
 
@@ -198,6 +304,7 @@ pub fn new(
         cx: Scope,
         dap_id: DapId,
         term_id: TermId,
+        watch_expressions: RwSignal<im::Vector<String>>,
         common: Rc<CommonData>,
     ) -> Self {
         let stopped = cx.create_rw_signal(false);
@@ -243,10 +350,29 @@ pub fn new(
                 children_expanded_count: 0,
             }),
             breakline,
+            active_frame: cx.create_rw_signal(None),
+            console: cx.create_rw_signal(im::Vector::new()),
+            watch_expressions,
+            watches: cx.create_rw_signal(DapVariable::watch_root(
+                &watch_expressions.get_untracked(),
+            )),
             common,
         }
     }
 
+    pub fn tree(&self, tree: VariableTree) -> RwSignal<DapVariable> {
+        match tree {
+            VariableTree::Variables => self.variables,
+            VariableTree::Watch => self.watches,
+        }
+    }
+
+    pub fn push_console(&self, entry: ConsoleEntry) {
+        self.console.update(|console| {
+            push_console_entry(console, entry);
+        });
+    }
+
     pub fn stopped(
         &self,
         cx: Scope,
@@ -265,6 +391,7 @@ pub fn stopped(
         for (thread_id, frames) in stack_traces {
             let is_main_thread = main_thread_id.as_ref() == Some(thread_id);
             if is_main_thread {
+                self.active_frame.set(frames.first().map(|frame| frame.id));
                 if let Some(frame) = frames.first() {
                     if let Some(path) =
                         frame.source.as_ref().and_then(|source| source.path.clone())
@@ -331,13 +458,134 @@ pub fn stopped(
                 .map(|v| v.children_expanded_count + 1)
                 .sum::<usize>();
         });
+        self.evaluate_watches();
+    }
+
+    /// Evaluate the watched expressions in the active frame.
+    pub fn evaluate_watches(&self) {
+        let expressions = self.watch_expressions.get_untracked();
+        // The expressions expanded stay expanded, with the children of their
+        // new values.
+        let expanded: HashSet<String> = self.watches.with_untracked(|watches| {
+            watches
+                .children
+                .iter()
+                .filter(|watch| watch.expanded)
+                .map(|watch| watch.item.name().to_string())
+                .collect()
+        });
+        self.watches.set(DapVariable::watch_root(&expressions));
+
+        let frame_id = self.active_frame.get_untracked();
+        for (i, expression) in expressions.into_iter().enumerate() {
+            let dap = self.clone();
+            let watches = self.watches;
+            let is_expanded = expanded.contains(&expression);
+            let name = expression.clone();
+            let send = create_ext_action(
+                self.common.scope,
+                move |result: Result<ProxyResponse, RpcError>| {
+                    let (value, ty, reference) = match result {
+                        Ok(ProxyResponse::DapEvaluateResponse { response }) => (
+                            response.result,
+                            response.ty,
+                            response.variables_reference,
+                        ),
+                        Ok(_) => return,
+                        Err(err) => (err.message, None, 0),
+                    };
+                    let mut is_evaluated = false;
+                    watches.update(|watches| {
+                        // The expressions changed while it was evaluated
+                        let Some(watch) = watches
+                            .children
+                            .get_mut(i)
+                            .filter(|watch| watch.item.name() == name)
+                        else {
+                            return;
+                        };
+                        if let ScopeOrVar::Var(var) = &mut watch.item {
+                            var.value = value;
+                            var.ty = ty;
+                            var.variables_reference = reference;
+                        }
+                        is_evaluated = true;
+                    });
+                    if is_evaluated && is_expanded && reference > 0 {
+                        dap.toggle_expand(
+                            VariableTree::Watch,
+                            Vec::new(),
+                            reference,
+                        );
+                    }
+                },
+            );
+            self.common.proxy.dap_evaluate(
+                self.dap_id,
+                expression,
+                frame_id,
+                "watch",
+                move |result| {
+                    send(result);
+                },
+            );
+        }
     }
 
-    pub fn toggle_expand(&self, parent: Vec<usize>, reference: usize) {
+    /// Set the variable `name`, a child of the last of `parent`, to the value
+    /// of the expression `value`, and update it in `tree` once the debugger
+    /// did.
+    pub fn set_variable(
+        &self,
+        tree: VariableTree,
+        parent: Vec<usize>,
+        name: String,
+        value: String,
+    ) {
+        let Some(reference) = parent.last().copied() else {
+            return;
+        };
+        let root = self.tree(tree);
+        let dap = self.clone();
+        let variable = name.clone();
+        let send = create_ext_action(
+            self.common.scope,
+            move |result: Result<ProxyResponse, RpcError>| match result {
+                Ok(ProxyResponse::DapSetVariableResponse { response }) => {
+                    root.update(|root| {
+                        root.set_var_value(&parent, &variable, response);
+                    });
+                    // The watched expressions may depend on the variable
+                    dap.evaluate_watches();
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    dap.push_console(ConsoleEntry::Error(err.message));
+                }
+            },
+        );
+        self.common.proxy.dap_set_variable(
+            self.dap_id,
+            reference,
+            name,
+            value,
+            move |result| {
+                send(result);
+            },
+        );
+    }
+
+    pub fn toggle_expand(
+        &self,
+        tree: VariableTree,
+        parent: Vec<usize>,
+        reference: usize,
+    ) {
         self.variables_id.update(|id| {
             *id += 1;
         });
-        self.variables.update(|variables| {
+        let root = self.tree(tree);
+        root.update(|variables| {
             if let Some(var) = variables.get_var_mut(&parent, reference) {
                 if var.expanded {
                     var.expanded = false;
@@ -346,7 +594,7 @@ pub fn toggle_expand(&self, parent: Vec<usize>, reference: usize) {
                     var.expanded = true;
                     if !var.read {
                         var.read = true;
-                        self.read_var_children(&parent, reference);
+                        self.read_var_children(root, &parent, reference);
                     } else {
                         variables.update_count_recursive(&parent, reference);
                     }
@@ -355,8 +603,12 @@ pub fn toggle_expand(&self, parent: Vec<usize>, reference: usize) {
         });
     }
 
-    fn read_var_children(&self, parent: &[usize], reference: usize) {
-        let root = self.variables;
+    fn read_var_children(
+        &self,
+        root: RwSignal<DapVariable>,
+        parent: &[usize],
+        reference: usize,
+    ) {
         let parent = parent.to_vec();
         let variables_id = self.variables_id;
 
@@ -426,6 +678,73 @@ fn slice(
 }
 
 impl DapVariable {
+    /// The root of the watch view, with the expressions not evaluated yet.
+    pub fn watch_root(expressions: &im::Vector<String>) -> DapVariable {
+        let children = expressions
+            .iter()
+            .map(|expression| DapVariable {
+                item: ScopeOrVar::Var(Variable {
+                    name: expression.clone(),
+                    evaluate_name: Some(expression.clone()),
+                    ..Default::default()
+                }),
+                parent: Vec::new(),
+                expanded: false,
+                read: false,
+                children: Vec::new(),
+                children_expanded_count: 0,
+            })
+            .collect::<Vec<_>>();
+        DapVariable {
+            item: ScopeOrVar::Scope(dap_types::Scope::default()),
+            parent: Vec::new(),
+            expanded: true,
+            read: true,
+            children_expanded_count: children.len(),
+            children,
+        }
+    }
+
+    /// Update the variable `name`, a child of the last of `parent`, with the
+    /// value the debugger set it to, collapsing it when its children changed.
+    pub fn set_var_value(
+        &mut self,
+        parent: &[usize],
+        name: &str,
+        response: SetVariableResponse,
+    ) {
+        let Some((reference, ancestors)) = parent.split_last() else {
+            return;
+        };
+        let Some(var) =
+            self.get_var_mut(ancestors, *reference)
+                .and_then(|container| {
+                    container
+                        .children
+                        .iter_mut()
+                        .find(|c| c.item.name() == name)
+                })
+        else {
+            return;
+        };
+        if let ScopeOrVar::Var(variable) = &mut var.item {
+            variable.value = response.value;
+            if response.ty.is_some() {
+                variable.ty = response.ty;
+            }
+            if let Some(children) = response.variables_reference {
+                if children != variable.variables_reference {
+                    variable.variables_reference = children;
+                    var.expanded = false;
+                    var.read = false;
+                    var.children.clear();
+                    var.children_expanded_count = 0;
+                }
+            }
+        }
+        self.update_count_recursive(ancestors, *reference);
+    }
+
     pub fn append_view_slice(
         &self,
         view_items: &mut Vec<DapVariableViewdata>,
@@ -515,9 +834,11 @@ pub fn update_count(
 
 #[cfg(test)]
 mod tests {
-    use lapce_rpc::dap_types::{Scope, Variable};
+    use lapce_rpc::dap_types::{DebugRequest, Scope, SetVariableResponse, Variable};
 
-    use super::{DapVariable, ScopeOrVar};
+    use super::{
+        push_console_entry, ConsoleEntry, DapVariable, RunDebugConfigs, ScopeOrVar,
+    };
 
     #[test]
     fn test_update_count() {
@@ -637,4 +958,132 @@ fn test_update_count() {
         assert_eq!(var.children_expanded_count, 4);
         assert_eq!(root.children_expanded_count, 11);
     }
+
+    #[test]
+    fn test_attach_config() {
+        let configs: RunDebugConfigs = toml::from_str(
+            r#"
+[[configs]]
+name = "attach"
+type = "lldb"
+request = "attach"
+
+[configs.adapter]
+program = "lldb-dap"
+
+[configs.arguments]
+pid = 1234
+"#,
+        )
+        .unwrap();
+        let config = &configs.configs[0];
+        assert_eq!(config.request, DebugRequest::Attach);
+        assert!(config.program.is_empty());
+        assert_eq!(config.adapter.as_ref().unwrap().program, "lldb-dap");
+        assert_eq!(config.arguments["pid"], serde_json::json!(1234));
+        assert!(config.is_debuggable());
+    }
+
+    #[test]
+    fn test_push_console_entry() {
+        let output = |text: &str, category: &str| ConsoleEntry::Output {
+            text: text.to_string(),
+            category: Some(category.to_string()),
+        };
+        let mut console = im::Vector::new();
+        push_console_entry(&mut console, output("hello ", "stdout"));
+        let first_id = console[0].0;
+        push_console_entry(&mut console, output("world\n", "stdout"));
+        // The joined entry is a new one
+        assert!(console[0].0 > first_id);
+        push_console_entry(&mut console, output("next\n", "stdout"));
+        push_console_entry(&mut console, output("partial", "stdout"));
+        push_console_entry(&mut console, output("error\n", "stderr"));
+        push_console_entry(&mut console, ConsoleEntry::Input("a".to_string()));
+        assert!(console
+            .iter()
+            .zip(console.iter().skip(1))
+            .all(|((id, _), (next_id, _))| id < next_id));
+        assert_eq!(
+            console
+                .into_iter()
+                .map(|(_, entry)| entry)
+                .collect::<Vec<_>>(),
+            vec![
+                output("hello world\n", "stdout"),
+                output("next\n", "stdout"),
+                output("partial", "stdout"),
+                output("error\n", "stderr"),
+                ConsoleEntry::Input("a".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_var_value() {
+        let var = |name: &str, reference: usize| DapVariable {
+            item: ScopeOrVar::Var(Variable {
+                name: name.to_string(),
+                value: "1".to_string(),
+                variables_reference: reference,
+                ..Default::default()
+            }),
+            parent: vec![1],
+            expanded: false,
+            read: false,
+            children: Vec::new(),
+            children_expanded_count: 0,
+        };
+        let mut root = DapVariable {
+            item: ScopeOrVar::Scope(Scope::default()),
+            parent: Vec::new(),
+            expanded: true,
+            read: true,
+            children: vec![DapVariable {
+                item: ScopeOrVar::Scope(Scope {
+                    variables_reference: 1,
+                    ..Default::default()
+                }),
+                parent: Vec::new(),
+                expanded: true,
+                read: true,
+                children: vec![var("a", 0), var("b", 2)],
+                children_expanded_count: 2,
+            }],
+            children_expanded_count: 3,
+        };
+
+        root.set_var_value(
+            &[1],
+            "a",
+            SetVariableResponse {
+                value: "42".to_string(),
+                ty: Some("i32".to_string()),
+                variables_reference: None,
+                named_variables: None,
+                indexed_variables: None,
+            },
+        );
+        let a = &root.children[0].children[0].item;
+        assert_eq!(a.value(), Some("42"));
+        assert_eq!(a.ty(), Some("i32"));
+
+        root.set_var_value(
+            &[1],
+            "b",
+            SetVariableResponse {
+                value: "[]".to_string(),
+                ty: None,
+                variables_reference: Some(3),
+                named_variables: None,
+                indexed_variables: None,
+            },
+        );
+        assert_eq!(root.children[0].children[1].item.reference(), 3);
+        assert_eq!(root.children_expanded_count, 3);
+
+        let watches = DapVariable::watch_root(&im::vector!["x + 1".to_string()]);
+        assert_eq!(watches.children_expanded_count, 1);
+        assert_eq!(watches.children[0].item.name(), "x + 1");
+    }
 }
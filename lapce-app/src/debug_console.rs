@@ -0,0 +1,528 @@
+use std::{ops::Range, rc::Rc};
+
+use floem::{
+    ext_event::create_ext_action,
+    keyboard::Modifiers,
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+};
+use lapce_core::{
+    command::{EditCommand, FocusCommand, MoveCommand},
+    encoding::{offset_utf16_to_utf8_str, offset_utf8_to_utf16_str},
+    mode::Mode,
+    selection::Selection,
+};
+use lapce_rpc::{dap_types::CompletionItem, proxy::ProxyResponse, RpcError};
+use lapce_xi_rope::Rope;
+
+use crate::{
+    command::{CommandExecuted, CommandKind, LapceCommand},
+    debug::{ConsoleEntry, DapData, DapVariable, VariableTree},
+    editor::EditorData,
+    keypress::{condition::Condition, KeyPressFocus},
+    main_split::Editors,
+    panel::kind::PanelKind,
+    terminal::panel::TerminalPanelData,
+    window_tab::{CommonData, Focus},
+};
+
+/// The inputs of the debug panel, which take the keys while it's focused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugInput {
+    Console,
+    /// The expression to add to the watch view.
+    Watch,
+    /// The new value of the variable being edited.
+    Variable,
+}
+
+/// The variable whose value is being edited in the debug panel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EditingVariable {
+    pub tree: VariableTree,
+    pub parent: Vec<usize>,
+    pub name: String,
+}
+
+/// The inputs of the debug panel: the console evaluating expressions in the
+/// paused frame of the active debug session, the expression to watch, and the
+/// value of the variable being edited.
+#[derive(Clone)]
+pub struct DebugConsoleData {
+    pub editor: EditorData,
+    pub watch_editor: EditorData,
+    pub variable_editor: EditorData,
+    pub focused_input: RwSignal<DebugInput>,
+    pub editing_variable: RwSignal<Option<EditingVariable>>,
+    /// The expressions evaluated, the most recent last.
+    pub history: RwSignal<Vec<String>>,
+    /// The expression of the history shown in the input while going through
+    /// it with the arrow keys.
+    history_index: RwSignal<Option<usize>>,
+    terminal: TerminalPanelData,
+    pub common: Rc<CommonData>,
+}
+
+impl KeyPressFocus for DebugConsoleData {
+    fn get_mode(&self) -> Mode {
+        Mode::Insert
+    }
+
+    fn check_condition(&self, condition: Condition) -> bool {
+        matches!(condition, Condition::PanelFocus)
+    }
+
+    fn run_command(
+        &self,
+        command: &LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        let input = self.focused_input.get_untracked();
+        if input != DebugInput::Console {
+            return self.run_input_command(input, command, count, mods);
+        }
+        match &command.kind {
+            CommandKind::Edit(EditCommand::InsertNewLine) => {
+                self.evaluate();
+                CommandExecuted::Yes
+            }
+            CommandKind::Edit(EditCommand::InsertTab) => {
+                self.complete();
+                CommandExecuted::Yes
+            }
+            CommandKind::Move(MoveCommand::Up) => {
+                self.previous_history();
+                CommandExecuted::Yes
+            }
+            CommandKind::Move(MoveCommand::Down) => {
+                self.next_history();
+                CommandExecuted::Yes
+            }
+            CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MultiSelection(_) => {
+                self.editor.run_command(command, count, mods)
+            }
+            _ => CommandExecuted::No,
+        }
+    }
+
+    fn receive_char(&self, c: &str) {
+        self.focused_editor().receive_char(c);
+    }
+}
+
+impl DebugConsoleData {
+    pub fn new(
+        cx: Scope,
+        editors: Editors,
+        terminal: TerminalPanelData,
+        common: Rc<CommonData>,
+    ) -> Self {
+        Self {
+            editor: editors.make_local(cx, common.clone()),
+            watch_editor: editors.make_local(cx, common.clone()),
+            variable_editor: editors.make_local(cx, common.clone()),
+            focused_input: cx.create_rw_signal(DebugInput::Console),
+            editing_variable: cx.create_rw_signal(None),
+            history: cx.create_rw_signal(Vec::new()),
+            history_index: cx.create_rw_signal(None),
+            terminal,
+            common,
+        }
+    }
+
+    pub fn focused_editor(&self) -> &EditorData {
+        match self.focused_input.get_untracked() {
+            DebugInput::Console => &self.editor,
+            DebugInput::Watch => &self.watch_editor,
+            DebugInput::Variable => &self.variable_editor,
+        }
+    }
+
+    /// Enter adds the watch or sets the variable, and escape leaves the
+    /// input.
+    fn run_input_command(
+        &self,
+        input: DebugInput,
+        command: &LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        match &command.kind {
+            CommandKind::Edit(EditCommand::InsertNewLine) => {
+                match input {
+                    DebugInput::Watch => self.add_watch(),
+                    _ => self.finish_editing_variable(),
+                }
+                CommandExecuted::Yes
+            }
+            CommandKind::Focus(FocusCommand::ModalClose) => {
+                self.focused_editor().reset();
+                if input == DebugInput::Variable {
+                    self.editing_variable.set(None);
+                }
+                self.focused_input.set(DebugInput::Console);
+                CommandExecuted::Yes
+            }
+            CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MultiSelection(_) => {
+                self.focused_editor().run_command(command, count, mods)
+            }
+            _ => CommandExecuted::No,
+        }
+    }
+
+    /// Watch the expression of the watch input in every debug session.
+    pub fn add_watch(&self) {
+        let expression = editor_text(&self.watch_editor).trim().to_string();
+        if expression.is_empty() {
+            return;
+        }
+        self.watch_editor.reset();
+        self.terminal.debug.watches.update(|watches| {
+            watches.push_back(expression);
+        });
+        self.evaluate_watches();
+    }
+
+    /// Stop watching the expression at `index` of the watch view.
+    pub fn remove_watch(&self, index: usize) {
+        self.terminal.debug.watches.update(|watches| {
+            if index < watches.len() {
+                watches.remove(index);
+            }
+        });
+        self.evaluate_watches();
+    }
+
+    fn evaluate_watches(&self) {
+        let daps = self.terminal.debug.daps.get_untracked();
+        for dap in daps.values() {
+            if dap.stopped.get_untracked() {
+                dap.evaluate_watches();
+            } else {
+                dap.watches.set(DapVariable::watch_root(
+                    &dap.watch_expressions.get_untracked(),
+                ));
+            }
+        }
+    }
+
+    /// Start editing the value of a variable in the input in place of it.
+    pub fn edit_variable(&self, variable: EditingVariable, value: &str) {
+        self.variable_editor
+            .doc()
+            .reload(Rope::from(value.to_string()), true);
+        self.variable_editor
+            .cursor()
+            .update(|cursor| cursor.set_insert(Selection::region(0, value.len())));
+        self.editing_variable.set(Some(variable));
+        self.focused_input.set(DebugInput::Variable);
+        self.common.focus.set(Focus::Panel(PanelKind::Debug));
+    }
+
+    fn finish_editing_variable(&self) {
+        let value = editor_text(&self.variable_editor).trim().to_string();
+        let variable = self.editing_variable.get_untracked();
+        self.editing_variable.set(None);
+        self.focused_input.set(DebugInput::Console);
+        let Some(variable) = variable.filter(|_| !value.is_empty()) else {
+            return;
+        };
+        if let Some(dap) = self.terminal.get_active_dap(false) {
+            dap.set_variable(variable.tree, variable.parent, variable.name, value);
+        }
+    }
+
+    /// The console of the active debug session.
+    pub fn console(&self) -> im::Vector<(u64, ConsoleEntry)> {
+        self.terminal
+            .get_active_dap(true)
+            .map(|dap| dap.console.get())
+            .unwrap_or_default()
+    }
+
+    fn input(&self) -> String {
+        editor_text(&self.editor)
+    }
+
+    fn set_input(&self, text: String, offset: usize) {
+        self.editor.doc().reload(Rope::from(text), true);
+        self.editor
+            .cursor()
+            .update(|cursor| cursor.set_insert(Selection::caret(offset)));
+    }
+
+    /// Evaluate the expression of the input in the active frame, and show it
+    /// with its value in the console.
+    pub fn evaluate(&self) {
+        let expression = self.input().trim().to_string();
+        if expression.is_empty() {
+            return;
+        }
+        let Some(dap) = self.terminal.get_active_dap(false) else {
+            return;
+        };
+
+        self.history.update(|history| {
+            if history.last() != Some(&expression) {
+                history.push(expression.clone());
+            }
+        });
+        self.history_index.set(None);
+        self.editor.reset();
+        dap.push_console(ConsoleEntry::Input(expression.clone()));
+
+        let dap_id = dap.dap_id;
+        let frame_id = dap.active_frame.get_untracked();
+        let send = create_ext_action(
+            self.common.scope,
+            move |result: Result<ProxyResponse, RpcError>| match result {
+                Ok(ProxyResponse::DapEvaluateResponse { response }) => {
+                    dap.push_console(ConsoleEntry::Result(response.result));
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    dap.push_console(ConsoleEntry::Error(err.message));
+                }
+            },
+        );
+        self.common.proxy.dap_evaluate(
+            dap_id,
+            expression,
+            frame_id,
+            "repl",
+            move |result| {
+                send(result);
+            },
+        );
+    }
+
+    fn previous_history(&self) {
+        let len = self.history.with_untracked(|history| history.len());
+        if len == 0 {
+            return;
+        }
+        let index = match self.history_index.get_untracked() {
+            Some(index) => index.saturating_sub(1),
+            None => len - 1,
+        };
+        self.show_history(Some(index));
+    }
+
+    fn next_history(&self) {
+        let Some(index) = self.history_index.get_untracked() else {
+            return;
+        };
+        let len = self.history.with_untracked(|history| history.len());
+        self.show_history((index + 1 < len).then_some(index + 1));
+    }
+
+    /// Show the expression `index` of the history in the input, or an empty
+    /// input past the end of the history.
+    fn show_history(&self, index: Option<usize>) {
+        let text = index
+            .and_then(|index| {
+                self.history
+                    .with_untracked(|history| history.get(index).cloned())
+            })
+            .unwrap_or_default();
+        let offset = text.len();
+        self.history_index.set(index);
+        self.set_input(text, offset);
+    }
+
+    /// Ask the debugger for the completions at the cursor, and complete the
+    /// input with what they have in common, or list them when there's
+    /// nothing to add.
+    fn complete(&self) {
+        let Some(dap) = self.terminal.get_active_dap(false) else {
+            return;
+        };
+        let text = self.input();
+        let offset = self.editor.cursor().with_untracked(|c| c.offset());
+        let column = offset_utf8_to_utf16_str(&text, offset) + 1;
+
+        let console = self.clone();
+        let dap_id = dap.dap_id;
+        let frame_id = dap.active_frame.get_untracked();
+        let input = text.clone();
+        let send = create_ext_action(
+            self.common.scope,
+            move |result: Result<ProxyResponse, RpcError>| {
+                if let Ok(ProxyResponse::DapCompletionsResponse { targets }) = result
+                {
+                    console.apply_completions(&dap, &input, offset, &targets);
+                }
+            },
+        );
+        self.common.proxy.dap_completions(
+            dap_id,
+            text,
+            column,
+            frame_id,
+            move |result| {
+                send(result);
+            },
+        );
+    }
+
+    fn apply_completions(
+        &self,
+        dap: &DapData,
+        input: &str,
+        offset: usize,
+        targets: &[CompletionItem],
+    ) {
+        // The input was edited while waiting for the debugger
+        if self.input() != input {
+            return;
+        }
+        if let Some((text, offset)) = complete_input(input, offset, targets) {
+            self.set_input(text, offset);
+        } else if targets.len() > 1 {
+            let labels = targets
+                .iter()
+                .map(|target| target.label.as_str())
+                .collect::<Vec<_>>();
+            dap.push_console(ConsoleEntry::Output {
+                text: format!("{}\n", labels.join("  ")),
+                category: Some("console".to_string()),
+            });
+        }
+    }
+}
+
+fn editor_text(editor: &EditorData) -> String {
+    editor
+        .doc()
+        .buffer
+        .with_untracked(|buffer| buffer.to_string())
+}
+
+/// The range of `input` replaced by the completion `item` asked for at
+/// `offset`, the word before the cursor when the debugger doesn't say.
+fn completion_range(
+    input: &str,
+    offset: usize,
+    item: &CompletionItem,
+) -> Range<usize> {
+    match item.start {
+        Some(start) => {
+            // Columns count from 1, in UTF-16 code units
+            let start16 = start.saturating_sub(1);
+            let end16 = start16 + item.length.unwrap_or(0);
+            let start = offset_utf16_to_utf8_str(input, start16);
+            let end = offset_utf16_to_utf8_str(input, end16).max(start);
+            start..end
+        }
+        None => {
+            let start = input[..offset]
+                .char_indices()
+                .rev()
+                .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+                .last()
+                .map(|(i, _)| i)
+                .unwrap_or(offset);
+            start..offset
+        }
+    }
+}
+
+/// The input completed with the text the completions `targets` have in
+/// common, and the offset of the cursor after it, if that adds anything to
+/// what's typed.
+fn complete_input(
+    input: &str,
+    offset: usize,
+    targets: &[CompletionItem],
+) -> Option<(String, usize)> {
+    let first = targets.first()?;
+    let range = completion_range(input, offset, first);
+    if targets[1..]
+        .iter()
+        .any(|target| completion_range(input, offset, target) != range)
+    {
+        return None;
+    }
+
+    let texts = targets
+        .iter()
+        .map(|target| target.text.as_deref().unwrap_or(&target.label))
+        .collect::<Vec<_>>();
+    let prefix = texts[1..].iter().fold(texts[0], |prefix, text| {
+        let len = prefix
+            .char_indices()
+            .zip(text.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| prefix.len().min(text.len()));
+        &prefix[..len]
+    });
+
+    let typed = &input[range.clone()];
+    if prefix == typed || (targets.len() > 1 && prefix.len() <= typed.len()) {
+        return None;
+    }
+
+    let mut text = input.to_string();
+    text.replace_range(range.clone(), prefix);
+    Some((text, range.start + prefix.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use lapce_rpc::dap_types::CompletionItem;
+
+    use super::complete_input;
+
+    fn item(
+        label: &str,
+        start: Option<usize>,
+        length: Option<usize>,
+    ) -> CompletionItem {
+        CompletionItem {
+            label: label.to_string(),
+            text: None,
+            sort_text: None,
+            detail: None,
+            ty: None,
+            start,
+            length,
+        }
+    }
+
+    #[test]
+    fn test_complete_input() {
+        // The word before the cursor is replaced without a start
+        assert_eq!(
+            complete_input("foo.ba", 6, &[item("bar", None, None)]),
+            Some(("foo.bar".to_string(), 7))
+        );
+        // The common prefix of several completions, when it adds anything
+        assert_eq!(
+            complete_input(
+                "x + l",
+                5,
+                &[item("length", None, None), item("left", None, None)]
+            ),
+            Some(("x + le".to_string(), 6))
+        );
+        assert_eq!(
+            complete_input(
+                "x + le",
+                6,
+                &[item("length", None, None), item("left", None, None)]
+            ),
+            None
+        );
+        // A start and a length counted from 1
+        assert_eq!(
+            complete_input("self.va", 7, &[item("self.value", Some(1), Some(7))]),
+            Some(("self.value".to_string(), 10))
+        );
+        assert_eq!(complete_input("a", 1, &[]), None);
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     borrow::Cow,
-    cell::RefCell,
+    cell::{Cell, RefCell},
     collections::HashMap,
     ops::Range,
     path::{Path, PathBuf},
@@ -9,9 +9,10 @@
         atomic::{self, AtomicUsize},
         Arc,
     },
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
+use chrono::TimeZone;
 use floem::{
     action::exec_after,
     ext_event::create_ext_action,
@@ -27,7 +28,10 @@
         id::EditorId,
         layout::{LineExtraStyle, TextLayoutLine},
         phantom_text::{PhantomText, PhantomTextKind, PhantomTextLine},
-        text::{Document, DocumentPhantom, PreeditData, Styling, SystemClipboard},
+        text::{
+            Document, DocumentPhantom, PreeditData, Styling, SystemClipboard,
+            WrapMethod,
+        },
         view::{ScreenLines, ScreenLinesBase},
         CursorInfo, Editor, EditorStyle,
     },
@@ -52,33 +56,46 @@
     rope_text_pos::RopeTextPosition,
     selection::{InsertDrift, Selection},
     style::line_styles,
-    syntax::{edit::SyntaxEdit, BracketParser, Syntax},
+    syntax::{
+        edit::SyntaxEdit, textobject::TextObject, BracketParser, DefinitionKind,
+        Syntax,
+    },
     word::{get_char_property, CharClassification, WordCursor},
 };
 use lapce_rpc::{
     buffer::BufferId,
     plugin::PluginId,
-    proxy::ProxyResponse,
+    proxy::{ExternalFormatter, ProxyCallback, ProxyResponse, ProxyRpcHandler},
     style::{LineStyle, LineStyles, Style},
+    RpcError,
 };
 use lapce_xi_rope::{
     spans::{Spans, SpansBuilder},
     Interval, Rope, RopeDelta, Transformer,
 };
 use lsp_types::{
-    CodeActionOrCommand, CodeLens, Diagnostic, DiagnosticSeverity,
-    DocumentSymbolResponse, InlayHint, InlayHintLabel, TextEdit,
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeLens, Command, Diagnostic,
+    DiagnosticSeverity, DocumentLink, DocumentSymbol, DocumentSymbolResponse,
+    InlayHint, InlayHintLabel, TextEdit, Url, WorkspaceEdit,
 };
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
 use crate::{
     command::{CommandKind, InternalCommand, LapceCommand},
-    config::{color::LapceColor, LapceConfig},
+    config::{
+        color::LapceColor,
+        editor::{EditorConfig, WrapIndent},
+        LapceConfig,
+    },
+    document_link::{url_links, DocumentLinks},
     editor::{
         compute_screen_lines,
-        gutter::FoldingRanges,
+        gutter::{
+            region_folding_ranges, FoldingRange, FoldingRangeKind, FoldingRanges,
+        },
         location::{EditorLocation, EditorPosition},
+        view::editor_wrap,
         EditorData,
     },
     find::{Find, FindProgress, FindResult},
@@ -89,15 +106,73 @@
         document_symbol::{SymbolData, SymbolInformationItemData},
         kind::PanelKind,
     },
+    spell::ADD_WORD_COMMAND,
+    undo_tree::UndoTree,
     window_tab::{CommonData, Focus},
     workspace::LapceWorkspace,
 };
 
+/// What the diagnostics of a file are from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DiagnosticSource {
+    /// The language server of a plugin.
+    Plugin(PluginId),
+    /// The problem matcher of a task, by the name of the task.
+    Task(String),
+}
+
 #[derive(Clone, Debug)]
 pub struct DiagnosticData {
     pub expanded: RwSignal<bool>,
+    /// The diagnostics of all the sources, ordered by their ranges.
     pub diagnostics: RwSignal<im::Vector<Diagnostic>>,
     pub diagnostics_span: RwSignal<Spans<Diagnostic>>,
+    /// The diagnostics by the language server or the task they are from.
+    pub source_diagnostics:
+        RwSignal<im::HashMap<DiagnosticSource, im::Vector<Diagnostic>>>,
+}
+
+impl DiagnosticData {
+    /// Replace the diagnostics from `source`, keeping those of the other
+    /// sources.
+    pub fn set_source_diagnostics(
+        &self,
+        source: DiagnosticSource,
+        diagnostics: Vec<Diagnostic>,
+    ) {
+        self.source_diagnostics.update(|sources| {
+            if diagnostics.is_empty() {
+                sources.remove(&source);
+            } else {
+                sources.insert(source, diagnostics.into());
+            }
+        });
+        self.merge_sources();
+    }
+
+    /// Add diagnostics to those from `source`.
+    pub fn extend_source_diagnostics(
+        &self,
+        source: DiagnosticSource,
+        diagnostics: Vec<Diagnostic>,
+    ) {
+        self.source_diagnostics.update(|sources| {
+            sources.entry(source).or_default().extend(diagnostics);
+        });
+        self.merge_sources();
+    }
+
+    fn merge_sources(&self) {
+        let diagnostics = self.source_diagnostics.with_untracked(|sources| {
+            sources
+                .values()
+                .flatten()
+                .cloned()
+                .sorted_by_key(|d| d.range.start)
+                .collect()
+        });
+        self.diagnostics.set(diagnostics);
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -112,6 +187,59 @@ pub struct DocHistory {
     pub version: String,
 }
 
+impl DocHistory {
+    /// The version of the file kept by the local history when it was saved at
+    /// `timestamp`.
+    pub fn local(path: PathBuf, timestamp: u64) -> Self {
+        Self {
+            path,
+            version: format!("local-{timestamp}"),
+        }
+    }
+
+    /// The text of the clipboard when `path` was compared with it.
+    pub fn clipboard(path: PathBuf) -> Self {
+        Self {
+            path,
+            version: "clipboard".to_string(),
+        }
+    }
+
+    /// The text of `path` once the replacements of the global search are
+    /// made.
+    pub fn replaced(path: PathBuf) -> Self {
+        Self {
+            path,
+            version: "replaced".to_string(),
+        }
+    }
+
+    /// Whether the version is a text which isn't kept anywhere, like the
+    /// clipboard compared with, rather than one read back from the file's
+    /// history.
+    pub fn is_snapshot(&self) -> bool {
+        self.version == "clipboard" || self.version == "replaced"
+    }
+
+    /// When the version was saved, if it's one kept by the local history.
+    pub fn local_timestamp(&self) -> Option<u64> {
+        self.version.strip_prefix("local-")?.parse().ok()
+    }
+
+    /// The version as shown to the user.
+    pub fn description(&self) -> String {
+        let time = self.local_timestamp().and_then(|timestamp| {
+            chrono::Local
+                .timestamp_millis_opt(timestamp as i64)
+                .single()
+        });
+        match time {
+            Some(time) => format!("saved {}", time.format("%Y-%m-%d %H:%M:%S")),
+            None => self.version.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum DocContent {
     /// A file at some location. This can be a remote path.
@@ -121,7 +249,13 @@ pub enum DocContent {
     /// A document of an old version in the source control
     History(DocHistory),
     /// A new file which doesn't exist in the file system
-    Scratch { id: BufferId, name: String },
+    Scratch {
+        id: BufferId,
+        name: String,
+        /// Whether it's only for reading, as when it shows generated text.
+        #[serde(default)]
+        read_only: bool,
+    },
 }
 
 impl DocContent {
@@ -138,7 +272,7 @@ pub fn read_only(&self) -> bool {
             DocContent::File { read_only, .. } => *read_only,
             DocContent::Local => false,
             DocContent::History(_) => true,
-            DocContent::Scratch { .. } => false,
+            DocContent::Scratch { read_only, .. } => *read_only,
         }
     }
 
@@ -160,9 +294,9 @@ pub struct DocInfo {
     pub cursor_offset: usize,
 }
 
-/// (Offset -> (Plugin the code actions are from, Code Actions))
+/// (Offset -> Code Actions, with the plugins they are from)
 pub type CodeActions =
-    im::HashMap<usize, (PluginId, im::Vector<CodeActionOrCommand>)>;
+    im::HashMap<usize, im::Vector<(PluginId, CodeActionOrCommand)>>;
 
 pub type AllCodeLens = im::HashMap<usize, (PluginId, usize, im::Vector<CodeLens>)>;
 
@@ -174,6 +308,8 @@ pub struct Doc {
     pub cache_rev: RwSignal<u64>,
     /// Whether the buffer's content has been loaded/initialized into the buffer.
     pub loaded: RwSignal<bool>,
+    /// The file couldn't be read, so the document won't be loaded.
+    pub load_failed: RwSignal<bool>,
     pub buffer: RwSignal<Buffer>,
     pub syntax: RwSignal<Syntax>,
     semantic_styles: RwSignal<Option<Spans<Style>>>,
@@ -197,6 +333,14 @@ pub struct Doc {
     pub code_lens: RwSignal<AllCodeLens>,
 
     pub folding_ranges: RwSignal<FoldingRanges>,
+    /// The folding ranges last given by the language server, preferred over
+    /// the ones of the syntax tree when there are any.
+    lsp_folding_ranges: RwSignal<Vec<FoldingRange>>,
+    /// The revision of the buffer when its content was loaded, to tell whether
+    /// the document is still as it was opened.
+    opened_rev: RwSignal<Option<u64>>,
+
+    pub document_links: RwSignal<DocumentLinks>,
 
     /// Stores information about different versions of the document from source control.
     histories: RwSignal<im::HashMap<String, DocumentHistory>>,
@@ -208,6 +352,10 @@ pub struct Doc {
     /// A cache for the sticky headers which maps a line to the lines it should show in the header.
     pub sticky_headers: Rc<RefCell<HashMap<usize, Option<Vec<usize>>>>>,
 
+    /// The width of a character the wrapped lines are indented with, along
+    /// with the id of the config and the language it was measured for.
+    wrap_char_width: Rc<Cell<Option<(u64, LapceLanguage, f64)>>>,
+
     pub preedit: PreeditData,
 
     pub find_result: FindResult,
@@ -219,6 +367,12 @@ pub struct Doc {
     pub common: Rc<CommonData>,
 
     pub document_symbol_data: RwSignal<Option<SymbolData>>,
+
+    /// The name of the encoding the file is read and saved with.
+    pub encoding: RwSignal<String>,
+
+    /// The states the document went through, including the undone ones.
+    pub undo_tree: RwSignal<UndoTree>,
 }
 impl Doc {
     pub fn new(
@@ -237,7 +391,6 @@ pub fn new(
             syntax: cx.create_rw_signal(syntax),
             line_styles: Rc::new(RefCell::new(HashMap::new())),
             parser: Rc::new(RefCell::new(BracketParser::new(
-                String::new(),
                 config.editor.bracket_pair_colorization,
                 config.editor.bracket_colorization_limit,
             ))),
@@ -254,9 +407,11 @@ pub fn new(
                 read_only: false,
             }),
             loaded: cx.create_rw_signal(false),
+            load_failed: cx.create_rw_signal(false),
             histories: cx.create_rw_signal(im::HashMap::new()),
             head_changes: cx.create_rw_signal(im::Vector::new()),
             sticky_headers: Rc::new(RefCell::new(HashMap::new())),
+            wrap_char_width: Rc::new(Cell::new(None)),
             code_actions: cx.create_rw_signal(im::HashMap::new()),
             find_result: FindResult::new(cx),
             preedit: PreeditData::new(cx),
@@ -265,6 +420,11 @@ pub fn new(
             code_lens: cx.create_rw_signal(im::HashMap::new()),
             document_symbol_data: cx.create_rw_signal(None),
             folding_ranges: cx.create_rw_signal(FoldingRanges::default()),
+            lsp_folding_ranges: cx.create_rw_signal(Vec::new()),
+            opened_rev: cx.create_rw_signal(None),
+            document_links: cx.create_rw_signal(DocumentLinks::default()),
+            encoding: cx.create_rw_signal("UTF-8".to_string()),
+            undo_tree: cx.create_rw_signal(UndoTree::new(Rope::from(""))),
         }
     }
 
@@ -280,14 +440,15 @@ pub fn new_content(
     ) -> Doc {
         let cx = cx.create_child();
         let config = common.config.get_untracked();
+        let mut buffer = Buffer::new("");
+        buffer.set_line_ending(config.editor.default_line_ending.line_ending());
         Self {
             scope: cx,
             buffer_id: BufferId::next(),
-            buffer: cx.create_rw_signal(Buffer::new("")),
+            buffer: cx.create_rw_signal(buffer),
             syntax: cx.create_rw_signal(Syntax::plaintext()),
             line_styles: Rc::new(RefCell::new(HashMap::new())),
             parser: Rc::new(RefCell::new(BracketParser::new(
-                String::new(),
                 config.editor.bracket_pair_colorization,
                 config.editor.bracket_colorization_limit,
             ))),
@@ -297,6 +458,7 @@ pub fn new_content(
                 expanded: cx.create_rw_signal(true),
                 diagnostics: cx.create_rw_signal(im::Vector::new()),
                 diagnostics_span: cx.create_rw_signal(SpansBuilder::new(0).build()),
+                source_diagnostics: cx.create_rw_signal(im::HashMap::new()),
             },
             completion_lens: cx.create_rw_signal(None),
             completion_pos: cx.create_rw_signal((0, 0)),
@@ -307,7 +469,9 @@ pub fn new_content(
             histories: cx.create_rw_signal(im::HashMap::new()),
             head_changes: cx.create_rw_signal(im::Vector::new()),
             sticky_headers: Rc::new(RefCell::new(HashMap::new())),
+            wrap_char_width: Rc::new(Cell::new(None)),
             loaded: cx.create_rw_signal(true),
+            load_failed: cx.create_rw_signal(false),
             find_result: FindResult::new(cx),
             code_actions: cx.create_rw_signal(im::HashMap::new()),
             preedit: PreeditData::new(cx),
@@ -316,6 +480,11 @@ pub fn new_content(
             code_lens: cx.create_rw_signal(im::HashMap::new()),
             document_symbol_data: cx.create_rw_signal(None),
             folding_ranges: cx.create_rw_signal(FoldingRanges::default()),
+            lsp_folding_ranges: cx.create_rw_signal(Vec::new()),
+            opened_rev: cx.create_rw_signal(None),
+            document_links: cx.create_rw_signal(DocumentLinks::default()),
+            encoding: cx.create_rw_signal("UTF-8".to_string()),
+            undo_tree: cx.create_rw_signal(UndoTree::new(Rope::from(""))),
         }
     }
 
@@ -338,7 +507,6 @@ pub fn new_history(
             syntax: cx.create_rw_signal(syntax),
             line_styles: Rc::new(RefCell::new(HashMap::new())),
             parser: Rc::new(RefCell::new(BracketParser::new(
-                String::new(),
                 config.editor.bracket_pair_colorization,
                 config.editor.bracket_colorization_limit,
             ))),
@@ -348,6 +516,7 @@ pub fn new_history(
                 expanded: cx.create_rw_signal(true),
                 diagnostics: cx.create_rw_signal(im::Vector::new()),
                 diagnostics_span: cx.create_rw_signal(SpansBuilder::new(0).build()),
+                source_diagnostics: cx.create_rw_signal(im::HashMap::new()),
             },
             completion_lens: cx.create_rw_signal(None),
             completion_pos: cx.create_rw_signal((0, 0)),
@@ -356,7 +525,9 @@ pub fn new_history(
             cache_rev: cx.create_rw_signal(0),
             content: cx.create_rw_signal(content),
             sticky_headers: Rc::new(RefCell::new(HashMap::new())),
+            wrap_char_width: Rc::new(Cell::new(None)),
             loaded: cx.create_rw_signal(true),
+            load_failed: cx.create_rw_signal(false),
             histories: cx.create_rw_signal(im::HashMap::new()),
             head_changes: cx.create_rw_signal(im::Vector::new()),
             code_actions: cx.create_rw_signal(im::HashMap::new()),
@@ -367,6 +538,11 @@ pub fn new_history(
             code_lens: cx.create_rw_signal(im::HashMap::new()),
             document_symbol_data: cx.create_rw_signal(None),
             folding_ranges: cx.create_rw_signal(FoldingRanges::default()),
+            lsp_folding_ranges: cx.create_rw_signal(Vec::new()),
+            opened_rev: cx.create_rw_signal(None),
+            document_links: cx.create_rw_signal(DocumentLinks::default()),
+            encoding: cx.create_rw_signal("UTF-8".to_string()),
+            undo_tree: cx.create_rw_signal(UndoTree::new(Rope::from(""))),
         }
     }
 
@@ -415,6 +591,83 @@ fn editor_data(&self, id: EditorId) -> Option<EditorData> {
         self.editors.editor_untracked(id)
     }
 
+    /// The editors of the main split the document belongs to.
+    pub fn editors(&self) -> Editors {
+        self.editors
+    }
+
+    /// The phantom text that indents the wrapped parts of the line, as set by
+    /// `wrap-indent`.  
+    /// The wrap points are found by assuming the editor font is monospaced, and
+    /// other phantom text on the line is not accounted for.
+    fn wrap_indent_phantom(
+        &self,
+        editor_id: EditorId,
+        line: usize,
+    ) -> Option<SmallVec<[PhantomText; 6]>> {
+        let language = self.syntax.with_untracked(|s| s.language);
+        let lapce_config = self.common.config.get_untracked();
+        let config = lapce_config.editor_config(language);
+        if config.wrap_indent == WrapIndent::None {
+            return None;
+        }
+
+        let width = match editor_wrap(config) {
+            WrapMethod::EditorWidth => self
+                .editor_data(editor_id)?
+                .viewport()
+                .get_untracked()
+                .width(),
+            WrapMethod::WrapWidth { width } => width as f64,
+            _ => return None,
+        };
+        // This is called for every line that's laid out, so the width is kept
+        // rather than measured each time.
+        let char_width = match self.wrap_char_width.get() {
+            Some((id, cached_language, char_width))
+                if id == lapce_config.id && cached_language == language =>
+            {
+                char_width
+            }
+            _ => {
+                let char_width = config.char_width();
+                self.wrap_char_width.set(Some((
+                    lapce_config.id,
+                    language,
+                    char_width,
+                )));
+                char_width
+            }
+        };
+        let wrap_columns = (width / char_width) as usize;
+
+        let content = self
+            .buffer
+            .with_untracked(|buffer| buffer.line_content(line).to_string());
+        let indent = config.wrap_indent.width(&content, config.tab_width);
+        let text = "\u{a0}".repeat(indent);
+        let indents = WrapIndent::wrap_columns(
+            &content,
+            indent,
+            wrap_columns,
+            config.tab_width,
+        )
+        .into_iter()
+        .map(|col| PhantomText {
+            kind: PhantomTextKind::Placeholder,
+            col,
+            text: text.clone(),
+            affinity: Some(CursorAffinity::Forward),
+            fg: None,
+            font_size: None,
+            bg: None,
+            under_line: None,
+        })
+        .collect::<SmallVec<_>>();
+
+        (!indents.is_empty()).then_some(indents)
+    }
+
     pub fn syntax(&self) -> ReadSignal<Syntax> {
         self.syntax.read_only()
     }
@@ -445,16 +698,27 @@ pub fn loaded(&self) -> bool {
 
     //// Initialize the content with some text, this marks the document as loaded.
     pub fn init_content(&self, content: Rope) {
+        let has_line_break = content.line_of_offset(content.len()) > 0;
+        let default_line_ending = self
+            .common
+            .config
+            .with_untracked(|config| config.editor.default_line_ending);
         batch(|| {
             self.syntax.with_untracked(|syntax| {
                 self.buffer.update(|buffer| {
                     buffer.init_content(content);
+                    // There's nothing to detect the line ending from.
+                    if !has_line_break {
+                        buffer.set_line_ending(default_line_ending.line_ending());
+                    }
                     buffer.detect_indent(|| {
                         IndentStyle::from_str(syntax.language.indent_unit())
                     });
                 });
             });
             self.loaded.set(true);
+            self.opened_rev.set(Some(self.rev()));
+            self.undo_tree.set(UndoTree::new(self.text()));
             self.on_update(None);
             self.init_parser();
             self.init_diagnostics();
@@ -463,21 +727,15 @@ pub fn init_content(&self, content: Rope) {
     }
 
     fn init_parser(&self) {
-        let code = self.buffer.get_untracked().to_string();
+        let palette = self.common.config.get_untracked().bracket_colors();
         self.syntax.with_untracked(|syntax| {
-            if syntax.styles.is_some() {
-                self.parser.borrow_mut().update_code(
-                    code,
-                    &self.buffer.get_untracked(),
-                    Some(syntax),
-                );
-            } else {
+            self.buffer.with_untracked(|buffer| {
                 self.parser.borrow_mut().update_code(
-                    code,
-                    &self.buffer.get_untracked(),
-                    None,
+                    buffer,
+                    syntax.styles.is_some().then_some(syntax),
+                    &palette,
                 );
-            }
+            })
         });
     }
 
@@ -491,6 +749,7 @@ pub fn reload(&self, content: Rope, set_pristine: bool) {
             .try_update(|buffer| buffer.reload(content, set_pristine))
             .unwrap();
         self.apply_deltas(&[delta]);
+        self.record_undo_state(EditType::Other);
     }
 
     pub fn handle_file_changed(&self, content: Rope) {
@@ -510,6 +769,7 @@ pub fn do_insert(
         }
 
         let old_cursor = cursor.mode.clone();
+        let old_text = self.text();
         let deltas = self.syntax.with_untracked(|syntax| {
             self.buffer
                 .try_update(|buffer| {
@@ -532,6 +792,7 @@ pub fn do_insert(
             buffer.set_cursor_after(cursor.mode.clone());
         });
         self.apply_deltas(&deltas);
+        self.record_undo_states(old_text, &deltas, EditType::InsertChars);
         deltas
     }
 
@@ -549,6 +810,7 @@ pub fn do_raw_edit(
             .try_update(|buffer| buffer.edit(edits, edit_type))
             .unwrap();
         self.apply_deltas(&[(text.clone(), delta.clone(), inval_lines.clone())]);
+        self.record_undo_state(edit_type);
         Some((text, delta, inval_lines))
     }
 
@@ -568,6 +830,7 @@ pub fn do_edit(
 
         let mut clipboard = SystemClipboard::new();
         let old_cursor = cursor.mode.clone();
+        let old_text = self.text();
         let deltas = self.syntax.with_untracked(|syntax| {
             self.buffer
                 .try_update(|buffer| {
@@ -595,11 +858,112 @@ pub fn do_edit(
                 buffer.set_cursor_after(cursor.mode.clone());
             });
             self.apply_deltas(&deltas);
+            // Each delta is an undo, a redo or an edit of the buffer
+            match cmd {
+                EditCommand::Undo => self
+                    .undo_tree
+                    .update(|tree| deltas.iter().for_each(|_| tree.undo())),
+                EditCommand::Redo => self
+                    .undo_tree
+                    .update(|tree| deltas.iter().for_each(|_| tree.redo())),
+                EditCommand::DeleteBackward | EditCommand::DeleteForward => {
+                    self.record_undo_states(old_text, &deltas, EditType::Delete)
+                }
+                _ => self.record_undo_states(old_text, &deltas, EditType::Other),
+            }
         }
 
         deltas
     }
 
+    /// Record the text after an edit of `edit_type` in the undo tree, merged
+    /// with the edit before it when the buffer puts both in the same undo
+    /// group.
+    fn record_undo_state(&self, edit_type: EditType) {
+        let text = self.text();
+        self.undo_tree
+            .update(|tree| tree.record(text, edit_type, SystemTime::now()));
+    }
+
+    /// Record the text after each of the `deltas` the buffer went through
+    /// from `text`, as edits of `edit_type`.
+    fn record_undo_states(
+        &self,
+        mut text: Rope,
+        deltas: &[(Rope, RopeDelta, InvalLines)],
+        edit_type: EditType,
+    ) {
+        if deltas.is_empty() {
+            return;
+        }
+        let now = SystemTime::now();
+        self.undo_tree.update(|tree| {
+            for (_, delta, _) in deltas {
+                text = delta.apply(&text);
+                tree.record(text.clone(), edit_type, now);
+            }
+        });
+    }
+
+    /// Go to the state of the undo tree made just before or after the current
+    /// one, whichever branch it's in.
+    pub fn step_undo_tree(&self, older: bool) {
+        let state = self.undo_tree.with_untracked(|tree| {
+            if older {
+                tree.older()
+            } else {
+                tree.newer()
+            }
+        });
+        if let Some(state) = state {
+            self.restore_undo_state(state);
+        }
+    }
+
+    /// Change the text to the one of `state` in the undo tree, as an edit that
+    /// can be undone like any other.
+    pub fn restore_undo_state(&self, state: usize) {
+        if self.content.with_untracked(|c| c.read_only()) {
+            return;
+        }
+        let Some(target) = self.undo_tree.with_untracked(|tree| {
+            tree.state(state).map(|state| state.text.to_string())
+        }) else {
+            return;
+        };
+        let current = self.text().to_string();
+        if current == target {
+            return;
+        }
+        // Only replace what differs, so that the rest keeps its styles.
+        let prefix: usize = current
+            .chars()
+            .zip(target.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        let suffix: usize = current[prefix..]
+            .chars()
+            .rev()
+            .zip(target[prefix..].chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(c, _)| c.len_utf8())
+            .sum();
+        let selection = Selection::region(prefix, current.len() - suffix);
+        let (text, delta, inval_lines) = self
+            .buffer
+            .try_update(|buffer| {
+                buffer.edit(
+                    &[(selection, &target[prefix..target.len() - suffix])],
+                    EditType::Other,
+                )
+            })
+            .unwrap();
+        self.apply_deltas(&[(text, delta, inval_lines)]);
+        self.undo_tree
+            .update(|tree| tree.record_restore(state, SystemTime::now()));
+    }
+
     pub fn apply_deltas(&self, deltas: &[(Rope, RopeDelta, InvalLines)]) {
         let rev = self.rev() - deltas.len() as u64;
         batch(|| {
@@ -611,6 +975,7 @@ pub fn apply_deltas(&self, deltas: &[(Rope, RopeDelta, InvalLines)]) {
                 self.update_find_result(delta);
                 if let DocContent::File { path, .. } = self.content.get_untracked() {
                     self.update_breakpoints(delta, &path, &inval.old_text);
+                    self.update_bookmarks(delta, &path, &inval.old_text);
                     self.common.proxy.update(
                         path,
                         delta.clone(),
@@ -662,25 +1027,21 @@ pub fn line_ending(&self) -> LineEnding {
             self.get_code_lens();
             self.get_document_symbol();
             self.get_folding_range();
+            self.get_document_links();
         });
     }
 
     fn do_bracket_colorization(&self) {
         if self.parser.borrow().active {
+            let palette = self.common.config.get_untracked().bracket_colors();
             self.syntax.with_untracked(|syntax| {
-                if syntax.rev == self.rev() && syntax.styles.is_some() {
-                    self.parser.borrow_mut().update_code(
-                        self.buffer.get_untracked().to_string(),
-                        &self.buffer.get_untracked(),
-                        Some(syntax),
-                    );
-                } else {
-                    self.parser.borrow_mut().update_code(
-                        self.buffer.get_untracked().to_string(),
-                        &self.buffer.get_untracked(),
-                        None,
-                    );
-                }
+                let syntax = (syntax.rev == self.rev() && syntax.styles.is_some())
+                    .then_some(syntax);
+                self.buffer.with_untracked(|buffer| {
+                    self.parser
+                        .borrow_mut()
+                        .update_code(buffer, syntax, &palette);
+                })
             })
         }
     }
@@ -702,6 +1063,60 @@ pub fn do_text_edit(&self, edits: &[TextEdit]) {
         self.do_raw_edit(&edits, EditType::Completion);
     }
 
+    /// Send a `request` through the proxy and pass its response to `f`, or
+    /// `None` once the configured format timeout has elapsed, so that a slow
+    /// language server can't hold up saving.
+    pub fn request_with_timeout(
+        &self,
+        request: impl FnOnce(ProxyRpcHandler, Box<dyn ProxyCallback>) + Send + 'static,
+        f: impl FnOnce(Option<ProxyResponse>) + 'static,
+    ) {
+        let timeout = self.common.config.get_untracked().editor.format_timeout();
+        let send = create_ext_action(self.scope, f);
+        let (tx, rx) = crossbeam_channel::bounded(1);
+        let proxy = self.common.proxy.clone();
+        std::thread::spawn(move || {
+            request(
+                proxy,
+                Box::new(move |result| {
+                    if let Err(err) = tx.send(result) {
+                        tracing::error!("{:?}", err);
+                    }
+                }),
+            );
+            send(rx.recv_timeout(timeout).ok().and_then(|result| result.ok()));
+        });
+    }
+
+    /// Send a formatting `request` with [`Doc::request_with_timeout`] and
+    /// pass its edits to `f`.
+    pub fn request_formatting(
+        &self,
+        request: impl FnOnce(ProxyRpcHandler, Box<dyn ProxyCallback>) + Send + 'static,
+        f: impl FnOnce(Option<Vec<TextEdit>>) + 'static,
+    ) {
+        self.request_with_timeout(request, move |response| {
+            let edits = match response {
+                Some(ProxyResponse::GetDocumentFormatting { edits })
+                | Some(ProxyResponse::GetDocumentRangeFormatting { edits }) => {
+                    Some(edits)
+                }
+                _ => None,
+            };
+            f(edits);
+        });
+    }
+
+    /// The external formatter set for the language of the document.
+    pub fn external_formatter(&self) -> Option<ExternalFormatter> {
+        let language = self.syntax.with_untracked(|s| s.language);
+        self.common
+            .config
+            .get_untracked()
+            .editor_config(language)
+            .formatter(language.as_ref())
+    }
+
     fn check_auto_save(&self) {
         let config = self.common.config.get_untracked();
         if config.editor.autosave_interval > 0 {
@@ -712,9 +1127,9 @@ fn check_auto_save(&self) {
             };
             let rev = self.rev();
             let doc = self.clone();
-            let scope = self.scope;
-            let proxy = self.common.proxy.clone();
-            let format = config.editor.format_on_save;
+            let language = self.syntax.with_untracked(|s| s.language);
+            let format = config.editor_config(language).format_on_save;
+            let formatter = self.external_formatter();
             exec_after(
                 Duration::from_millis(config.editor.autosave_interval),
                 move |_| {
@@ -731,22 +1146,22 @@ fn check_auto_save(&self) {
                     }
 
                     if format {
-                        let send = create_ext_action(scope, move |result| {
-                            let current_rev = doc.rev();
-                            if current_rev != rev {
-                                return;
-                            }
-                            if let Ok(ProxyResponse::GetDocumentFormatting {
-                                edits,
-                            }) = result
-                            {
-                                doc.do_text_edit(&edits);
-                            }
-                            doc.save(|| {});
-                        });
-                        proxy.get_document_formatting(path, move |result| {
-                            send(result);
-                        });
+                        let format_doc = doc.clone();
+                        doc.request_formatting(
+                            move |proxy, f| {
+                                proxy.get_document_formatting(path, formatter, f)
+                            },
+                            move |edits| {
+                                let current_rev = format_doc.rev();
+                                if current_rev != rev {
+                                    return;
+                                }
+                                if let Some(edits) = edits {
+                                    format_doc.do_text_edit(&edits);
+                                }
+                                format_doc.save(|| {});
+                            },
+                        );
                     } else {
                         doc.save(|| {});
                     }
@@ -793,6 +1208,9 @@ fn update_inlay_hints(&self, delta: &RopeDelta) {
                 doc.do_bracket_colorization();
                 doc.clear_style_cache();
                 doc.clear_sticky_headers_cache();
+                if doc.lsp_folding_ranges.with_untracked(|f| f.is_empty()) {
+                    doc.update_folding_ranges();
+                }
             }
         });
 
@@ -864,6 +1282,112 @@ pub fn line_style(&self, line: usize) -> Arc<Vec<LineStyle>> {
         self.line_styles.borrow().get(&line).cloned().unwrap()
     }
 
+    /// The column ranges of the misspelled words of `line`, which are checked
+    /// in the comments and strings, or everywhere in prose files.
+    pub fn misspellings(&self, line: usize) -> Vec<Range<usize>> {
+        let config = self.common.config.get_untracked();
+        if !config.editor.enable_spell_check {
+            return Vec::new();
+        }
+
+        let text = self
+            .buffer
+            .with_untracked(|buffer| buffer.line_content(line).to_string());
+        let language = self.syntax.with_untracked(|syntax| syntax.language);
+        let ranges = if matches!(
+            language,
+            LapceLanguage::Markdown | LapceLanguage::PlainText
+        ) {
+            vec![0..text.len()]
+        } else {
+            self.line_style(line)
+                .iter()
+                .filter(|line_style| {
+                    line_style.style.fg_color.as_deref().is_some_and(|scope| {
+                        scope.starts_with("comment") || scope.starts_with("string")
+                    })
+                })
+                .map(|line_style| line_style.start..line_style.end)
+                .collect()
+        };
+
+        let dictionary = &config.editor.spell_check_dictionary;
+        ranges
+            .into_iter()
+            .filter_map(|range| {
+                let start = range.start;
+                let text = text.get(range)?;
+                Some(
+                    self.common
+                        .spell
+                        .misspellings(dictionary, text)
+                        .into_iter()
+                        .map(move |r| r.start + start..r.end + start),
+                )
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// The quick fixes of the misspelled word at `offset`, which are its
+    /// corrections and adding it to the workspace's dictionary.
+    pub fn spelling_actions(&self, offset: usize) -> Vec<CodeActionOrCommand> {
+        let DocContent::File { path, .. } = self.content.get_untracked() else {
+            return Vec::new();
+        };
+        let Ok(uri) = Url::from_file_path(&path) else {
+            return Vec::new();
+        };
+        let (line, line_start) = self.buffer.with_untracked(|buffer| {
+            let line = buffer.line_of_offset(offset);
+            (line, buffer.offset_of_line(line))
+        });
+        let col = offset - line_start;
+        let Some(range) = self
+            .misspellings(line)
+            .into_iter()
+            .find(|range| range.start <= col && col <= range.end)
+        else {
+            return Vec::new();
+        };
+        let (word, range) = self.buffer.with_untracked(|buffer| {
+            let start = line_start + range.start;
+            let end = line_start + range.end;
+            (
+                buffer.slice_to_cow(start..end).to_string(),
+                lsp_types::Range::new(
+                    buffer.offset_to_position(start),
+                    buffer.offset_to_position(end),
+                ),
+            )
+        });
+
+        let config = self.common.config.get_untracked();
+        let mut actions: Vec<CodeActionOrCommand> = self
+            .common
+            .spell
+            .suggestions(&config.editor.spell_check_dictionary, &word)
+            .into_iter()
+            .map(|suggestion| {
+                CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Change to \"{suggestion}\""),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    edit: Some(WorkspaceEdit::new(HashMap::from([(
+                        uri.clone(),
+                        vec![TextEdit::new(range, suggestion)],
+                    )]))),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        actions.push(CodeActionOrCommand::Command(Command::new(
+            format!("Add \"{word}\" to Workspace Dictionary"),
+            ADD_WORD_COMMAND.to_string(),
+            Some(vec![serde_json::Value::String(word)]),
+        )));
+        actions
+    }
+
     /// Request semantic styles for the buffer from the LSP through the proxy.
     pub fn get_semantic_styles(&self) {
         if !self.loaded() {
@@ -984,9 +1508,28 @@ pub fn get_document_symbol(&self) {
                     if let Ok(ProxyResponse::GetDocumentSymbols { resp }) = result {
                         let items: Vec<RwSignal<SymbolInformationItemData>> =
                             match resp {
-                                DocumentSymbolResponse::Flat(_symbols) => {
-                                    Vec::with_capacity(0)
-                                }
+                                // Listed without nesting in the outline
+                                #[allow(deprecated)]
+                                DocumentSymbolResponse::Flat(symbols) => symbols
+                                    .into_iter()
+                                    .map(|x| {
+                                        let item = DocumentSymbol {
+                                            name: x.name,
+                                            detail: x.container_name,
+                                            kind: x.kind,
+                                            tags: x.tags,
+                                            deprecated: x.deprecated,
+                                            range: x.location.range,
+                                            selection_range: x.location.range,
+                                            children: None,
+                                        };
+                                        cx.create_rw_signal(
+                                            SymbolInformationItemData::from((
+                                                item, cx,
+                                            )),
+                                        )
+                                    })
+                                    .collect(),
                                 DocumentSymbolResponse::Nested(symbols) => symbols
                                     .into_iter()
                                     .map(|x| {
@@ -1105,22 +1648,18 @@ pub fn get_folding_range(&self) {
                     if rev != doc.rev() {
                         return;
                     }
-                    if let Ok(ProxyResponse::LspFoldingRangeResponse {
-                        resp, ..
-                    }) = result
-                    {
-                        let folding = resp
+                    let folding = match result {
+                        Ok(ProxyResponse::LspFoldingRangeResponse {
+                            resp, ..
+                        }) => resp
                             .unwrap_or_default()
                             .into_iter()
-                            .map(|x| {
-                                crate::editor::gutter::FoldingRange::from_lsp(x)
-                            })
-                            .sorted_by(|x, y| x.start.line.cmp(&y.start.line))
-                            .collect();
-                        doc.folding_ranges.update(|symbol| {
-                            symbol.0 = folding;
-                        });
-                    }
+                            .map(FoldingRange::from_lsp)
+                            .collect(),
+                        _ => Vec::new(),
+                    };
+                    doc.lsp_folding_ranges.set(folding);
+                    doc.update_folding_ranges();
                 }
             });
 
@@ -1129,7 +1668,157 @@ pub fn get_folding_range(&self) {
                 .get_lsp_folding_range(path, move |result| {
                     send(result);
                 });
+        } else {
+            self.lsp_folding_ranges.set(Vec::new());
+            self.update_folding_ranges();
+        }
+    }
+
+    /// Get the links of the document from the language server.
+    pub fn get_document_links(&self) {
+        let rev = self.rev();
+        let doc = self.clone();
+        let set_links =
+            move |plugin_id, links: Vec<(usize, usize, DocumentLink)>| {
+                if doc.rev() != rev {
+                    return;
+                }
+                doc.document_links.set(DocumentLinks {
+                    rev,
+                    plugin_id,
+                    links,
+                });
+            };
+
+        let path = match self.content.get_untracked() {
+            DocContent::File { path, .. } => path,
+            _ => {
+                set_links(None, Vec::new());
+                return;
+            }
+        };
+        let doc = self.clone();
+        let send = create_ext_action(self.scope, move |result| {
+            let (plugin_id, links) = match result {
+                Ok(ProxyResponse::GetDocumentLinks { plugin_id, links }) => {
+                    let links = doc.buffer.with_untracked(|buffer| {
+                        links
+                            .into_iter()
+                            .map(|link| {
+                                (
+                                    buffer.offset_of_position(&link.range.start),
+                                    buffer.offset_of_position(&link.range.end),
+                                    link,
+                                )
+                            })
+                            .sorted_by_key(|(start, _, _)| *start)
+                            .collect()
+                    });
+                    (Some(plugin_id), links)
+                }
+                _ => (None, Vec::new()),
+            };
+            set_links(plugin_id, links);
+        });
+        self.common.proxy.get_document_links(path, move |result| {
+            send(result);
+        });
+    }
+
+    /// The document link at `offset` with the plugin it's from, or the URL at
+    /// `offset` in the text of its line when the server gives no links.
+    pub fn document_link_at(
+        &self,
+        offset: usize,
+    ) -> Option<(Option<PluginId>, usize, usize, DocumentLink)> {
+        let rev = self.rev();
+        let link = self.document_links.with_untracked(|links| {
+            (links.rev == rev && !links.links.is_empty()).then(|| {
+                links.link_at(offset).map(|(start, end, link)| {
+                    (links.plugin_id, *start, *end, link.clone())
+                })
+            })
+        });
+        if let Some(link) = link {
+            return link;
+        }
+        self.buffer.with_untracked(|buffer| {
+            let line = buffer.line_of_offset(offset);
+            let line_start = buffer.offset_of_line(line);
+            let line_end = buffer.line_end_offset(line, true);
+            let text = buffer.text().slice_to_cow(line_start..line_end);
+            url_links(&text).into_iter().find_map(|(start, end, link)| {
+                let (start, end) = (line_start + start, line_start + end);
+                (start <= offset && offset < end).then_some((None, start, end, link))
+            })
+        })
+    }
+
+    /// Set the folding ranges from the language server, or from the syntax
+    /// tree when the server gives none, along with the ranges of the region
+    /// markers. While the document is as it was opened, the new ranges of the
+    /// kinds configured to be folded on open are folded.
+    fn update_folding_ranges(&self) {
+        let rev = self.rev();
+        let mut folding = self.lsp_folding_ranges.get_untracked();
+        if folding.is_empty() {
+            let syntax_folding = self.syntax.with_untracked(|syntax| {
+                if syntax.layers.is_none() {
+                    Some(Vec::new())
+                } else if syntax.rev == rev {
+                    Some(
+                        syntax
+                            .folding_ranges()
+                            .into_iter()
+                            .map(FoldingRange::from_syntax)
+                            .collect(),
+                    )
+                } else {
+                    None
+                }
+            });
+            // The ranges are updated again once the syntax tree catches up.
+            let Some(syntax_folding) = syntax_folding else {
+                return;
+            };
+            folding = syntax_folding;
         }
+        let text = self.text();
+        let doc = self.clone();
+        let send = create_ext_action(self.scope, move |regions| {
+            if doc.rev() == rev {
+                doc.set_folding_ranges(folding, regions);
+            }
+        });
+        rayon::spawn(move || {
+            let regions = region_folding_ranges(text.lines(0..text.len()));
+            send(regions);
+        });
+    }
+
+    fn set_folding_ranges(
+        &self,
+        folding: Vec<FoldingRange>,
+        regions: Vec<FoldingRange>,
+    ) {
+        let (fold_imports, fold_regions) =
+            self.common.config.with_untracked(|config| {
+                (
+                    config.editor.auto_fold_imports,
+                    config.editor.auto_fold_regions,
+                )
+            });
+        let opened = self.opened_rev.get_untracked() == Some(self.rev());
+        self.folding_ranges.update(|ranges| {
+            ranges.replace(folding.into_iter().chain(regions), |range| {
+                opened
+                    && match range.kind {
+                        Some(FoldingRangeKind::Imports) => fold_imports,
+                        Some(FoldingRangeKind::Region) => fold_regions,
+                        _ => false,
+                    }
+            });
+        });
     }
 
     /// Get the current completion lens text
@@ -1185,6 +1874,26 @@ fn update_breakpoints(&self, delta: &RopeDelta, path: &Path, old_text: &Rope) {
         }
     }
 
+    fn update_bookmarks(&self, delta: &RopeDelta, path: &Path, old_text: &Rope) {
+        if self
+            .common
+            .bookmarks
+            .with_untracked(|bookmarks| bookmarks.get(path).is_none())
+        {
+            return;
+        }
+        let mut transformer = Transformer::new(delta);
+        self.buffer.with_untracked(|buffer| {
+            self.common.bookmarks.update(|bookmarks| {
+                bookmarks.update_lines(path, |line| {
+                    let offset = old_text.offset_of_line(line);
+                    let offset = transformer.transform(offset, false);
+                    buffer.line_of_offset(offset)
+                });
+            });
+        });
+    }
+
     /// Update the completion lens position after an edit so that it appears in the correct place.
     pub fn update_completion_lens(&self, delta: &RopeDelta) {
         let Some(completion) = self.completion_lens.get_untracked() else {
@@ -1356,7 +2065,9 @@ pub fn head_changes(&self) -> RwSignal<im::Vector<DiffLines>> {
         self.head_changes
     }
 
-    /// Retrieve the `head` version of the buffer
+    /// Retrieve the `head` version of the buffer, which its changes are shown
+    /// against. Like `git diff`, that's the version staged in the index, so
+    /// that the staged hunks stop showing.
     pub fn retrieve_head(&self) {
         if let DocContent::File { path, .. } = self.content.get_untracked() {
             let histories = self.histories;
@@ -1386,20 +2097,25 @@ pub fn retrieve_head(&self) {
             let path = path.clone();
             let proxy = self.common.proxy.clone();
             std::thread::spawn(move || {
-                proxy.get_buffer_head(path, move |result| {
+                proxy.get_buffer_index(path, move |result| {
                     send(result);
                 });
             });
         }
     }
 
+    /// The text of the `head` version of the file, as staged in the index,
+    /// once retrieved.
+    pub fn head_text(&self) -> Option<Rope> {
+        self.histories.with_untracked(|histories| {
+            histories
+                .get("head")
+                .map(|history| history.buffer.text().clone())
+        })
+    }
+
     pub fn trigger_head_change(&self) {
-        let history = if let Some(text) =
-            self.histories.with_untracked(|histories| {
-                histories
-                    .get("head")
-                    .map(|history| history.buffer.text().clone())
-            }) {
+        let history = if let Some(text) = self.head_text() {
             text
         } else {
             return;
@@ -1437,20 +2153,35 @@ pub fn trigger_head_change(&self) {
     }
 
     pub fn save(&self, after_action: impl FnOnce() + 'static) {
+        self.save_with_result(move |result| {
+            if result.is_ok() {
+                after_action();
+            }
+        });
+    }
+
+    /// Save the document, calling `on_result` with the error if the proxy failed
+    /// to write it. Nothing is reported if the buffer changed during the save.
+    pub fn save_with_result(
+        &self,
+        on_result: impl FnOnce(Result<(), RpcError>) + 'static,
+    ) {
         let content = self.content.get_untracked();
         if let DocContent::File { path, .. } = content {
             let rev = self.rev();
             let buffer = self.buffer;
-            let send = create_ext_action(self.scope, move |result| {
-                if let Ok(ProxyResponse::SaveResponse {}) = result {
+            let send = create_ext_action(self.scope, move |result| match result {
+                Ok(ProxyResponse::SaveResponse {}) => {
                     let current_rev = buffer.with_untracked(|buffer| buffer.rev());
                     if current_rev == rev {
                         buffer.update(|buffer| {
                             buffer.set_pristine();
                         });
-                        after_action();
+                        on_result(Ok(()));
                     }
                 }
+                Ok(_) => {}
+                Err(err) => on_result(Err(err)),
             });
 
             self.common.proxy.save(rev, path, true, move |result| {
@@ -1459,6 +2190,62 @@ pub fn save(&self, after_action: impl FnOnce() + 'static) {
         }
     }
 
+    /// Read the file again decoded with `encoding`, replacing the content of
+    /// the document.
+    pub fn reopen_with_encoding(
+        &self,
+        encoding: String,
+        on_error: impl FnOnce(RpcError) + 'static,
+    ) {
+        let DocContent::File { path, .. } = self.content.get_untracked() else {
+            return;
+        };
+        let doc = self.clone();
+        let send = create_ext_action(self.scope, move |result| match result {
+            Ok(ProxyResponse::NewBufferResponse {
+                content, encoding, ..
+            }) => {
+                doc.reload(Rope::from(content), true);
+                doc.encoding.set(encoding);
+            }
+            Ok(_) => {}
+            Err(err) => on_error(err),
+        });
+        self.common
+            .proxy
+            .reopen_buffer(path, encoding, move |result| {
+                send(result);
+            });
+    }
+
+    /// Save the document in `encoding`, which it's saved with from then on.
+    pub fn save_with_encoding(
+        &self,
+        encoding: String,
+        on_result: impl FnOnce(Result<(), RpcError>) + 'static,
+    ) {
+        let DocContent::File { path, .. } = self.content.get_untracked() else {
+            return;
+        };
+        let doc = self.clone();
+        let new_encoding = encoding.clone();
+        let send = create_ext_action(self.scope, move |result| match result {
+            Ok(_) => {
+                doc.encoding.set(new_encoding);
+                doc.save_with_result(on_result);
+            }
+            Err(err) => on_result(Err(err)),
+        });
+        self.common.proxy.set_buffer_encoding(
+            path,
+            self.rev(),
+            encoding,
+            move |result| {
+                send(result);
+            },
+        );
+    }
+
     pub fn set_inline_completion(
         &self,
         inline_completion: String,
@@ -1544,6 +2331,72 @@ pub fn find_enclosing_brackets(&self, offset: usize) -> Option<(usize, usize)> {
                 })
             })
     }
+
+    /// The start of the next definition of `kind` after `offset`, or of the
+    /// previous one when not `forward`, when the syntax tree is up to date.
+    pub fn find_definition(
+        &self,
+        offset: usize,
+        kind: DefinitionKind,
+        forward: bool,
+    ) -> Option<usize> {
+        let rev = self.rev();
+        self.syntax.with_untracked(|syntax| {
+            if syntax.rev == rev {
+                syntax.find_definition(offset, kind, forward)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The range of the text object at `offset`, or of what's inside of it
+    /// when `inside`, when the syntax tree is up to date.
+    pub fn find_text_object(
+        &self,
+        offset: usize,
+        object: TextObject,
+        inside: bool,
+    ) -> Option<Range<usize>> {
+        let rev = self.rev();
+        self.syntax.with_untracked(|syntax| {
+            if syntax.rev == rev {
+                syntax.find_text_object(offset, object, inside)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The ranges of the parameter at `offset` and of the one to swap it
+    /// with, when the syntax tree is up to date.
+    pub fn find_parameter_swap(
+        &self,
+        offset: usize,
+        forward: bool,
+    ) -> Option<(Range<usize>, Range<usize>)> {
+        let rev = self.rev();
+        self.syntax.with_untracked(|syntax| {
+            if syntax.rev == rev {
+                syntax.find_parameter_swap(offset, forward)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The range of the syntax node to expand the selection from `start` to
+    /// `end` to, when the syntax tree is up to date.
+    pub fn expand_range(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        let rev = self.rev();
+        self.syntax.with_untracked(|syntax| {
+            if syntax.rev == rev {
+                syntax.expand_range(start, end)
+            } else {
+                None
+            }
+        })
+    }
 }
 impl Document for Doc {
     fn text(&self) -> Rope {
@@ -1653,13 +2506,14 @@ fn edit(
             .try_update(|buffer| buffer.edit(iter, edit_type))
             .unwrap();
         self.apply_deltas(&[delta]);
+        self.record_undo_state(edit_type);
     }
 }
 
 impl DocumentPhantom for Doc {
     fn phantom_text(
         &self,
-        _: EditorId,
+        editor_id: EditorId,
         _: &EditorStyle,
         line: usize,
     ) -> PhantomTextLine {
@@ -1879,6 +2733,10 @@ fn phantom_text(
             text.push(preedit)
         }
 
+        if let Some(mut indents) = self.wrap_indent_phantom(editor_id, line) {
+            text.append(&mut indents);
+        }
+
         text.sort_by(|a, b| {
             if a.col == b.col {
                 a.kind.cmp(&b.kind)
@@ -1941,6 +2799,13 @@ pub struct DocStyling {
     doc: Rc<Doc>,
 }
 impl DocStyling {
+    /// Run `f` with the editor settings for the language of the document.
+    fn with_editor_config<R>(&self, f: impl FnOnce(&EditorConfig) -> R) -> R {
+        let language = self.doc.syntax.with_untracked(|s| s.language);
+        self.config
+            .with_untracked(|config| f(config.editor_config(language)))
+    }
+
     fn apply_colorization(
         &self,
         edid: EditorId,
@@ -1971,13 +2836,11 @@ fn id(&self) -> u64 {
     }
 
     fn font_size(&self, _: EditorId, _line: usize) -> usize {
-        self.config
-            .with_untracked(|config| config.editor.font_size())
+        self.with_editor_config(|config| config.font_size())
     }
 
     fn line_height(&self, _: EditorId, _line: usize) -> f32 {
-        self.config
-            .with_untracked(|config| config.editor.line_height()) as f32
+        self.with_editor_config(|config| config.line_height()) as f32
     }
 
     fn font_family(
@@ -1986,8 +2849,8 @@ fn font_family(
         _line: usize,
     ) -> std::borrow::Cow<[floem::text::FamilyOwned]> {
         // TODO: cache this
-        Cow::Owned(self.config.with_untracked(|config| {
-            FamilyOwned::parse_list(&config.editor.font_family).collect()
+        Cow::Owned(self.with_editor_config(|config| {
+            FamilyOwned::parse_list(&config.font_family).collect()
         }))
     }
 
@@ -2018,7 +2881,7 @@ fn indent_line(&self, _: EditorId, line: usize, line_content: &str) -> usize {
     }
 
     fn tab_width(&self, _: EditorId, _line: usize) -> usize {
-        self.config.with_untracked(|config| config.editor.tab_width)
+        self.with_editor_config(|config| config.tab_width)
     }
 
     fn atomic_soft_tabs(&self, _: EditorId, _line: usize) -> bool {
@@ -2140,6 +3003,20 @@ fn apply_layout_styles(
                 });
         });
 
+        for range in doc.misspellings(line) {
+            let start = phantom_text.col_after(range.start, true);
+            let end = phantom_text.col_after(range.end, false);
+            let styles = extra_styles_for_range(
+                layout,
+                start,
+                end,
+                None,
+                None,
+                Some(config.color(LapceColor::EDITOR_MISSPELLING)),
+            );
+            layout_line.extra_style.extend(styles);
+        }
+
         // Add the styling for the diagnostic severity, if applicable
         if let Some(max_severity) = max_severity {
             let theme_prop = if max_severity == DiagnosticSeverity::ERROR {
@@ -0,0 +1,76 @@
+use lapce_rpc::plugin::PluginId;
+use lsp_types::DocumentLink;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use url::Url;
+
+static URL_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\bhttps?://[^\s<>"'`]+"#).unwrap());
+
+/// The links of a document from the language server.
+#[derive(Clone, Default)]
+pub struct DocumentLinks {
+    /// The revision of the document the links are in.
+    pub rev: u64,
+    /// The plugin the links are from, to resolve the ones without targets.
+    pub plugin_id: Option<PluginId>,
+    /// The start and end offsets of the links, ordered by offset.
+    pub links: Vec<(usize, usize, DocumentLink)>,
+}
+
+impl DocumentLinks {
+    /// The link at `offset`, if any.
+    pub fn link_at(&self, offset: usize) -> Option<&(usize, usize, DocumentLink)> {
+        self.links
+            .iter()
+            .find(|(start, end, _)| *start <= offset && offset < *end)
+    }
+}
+
+/// The links to the URLs in `text`, without the punctuation ending the
+/// sentences they're in.
+pub fn url_links(text: &str) -> Vec<(usize, usize, DocumentLink)> {
+    URL_REGEX
+        .find_iter(text)
+        .filter_map(|m| {
+            let url = m
+                .as_str()
+                .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '}']);
+            let target = Url::parse(url).ok()?;
+            let link = DocumentLink {
+                range: lsp_types::Range::default(),
+                target: Some(target),
+                tooltip: None,
+                data: None,
+            };
+            Some((m.start(), m.start() + url.len(), link))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::url_links;
+
+    #[test]
+    fn test_url_links() {
+        let text = "See https://lapce.dev/docs. Or (http://a.b/c?d=e), `x`";
+        let links: Vec<_> = url_links(text)
+            .into_iter()
+            .map(|(start, end, link)| {
+                (&text[start..end], link.target.unwrap().to_string())
+            })
+            .collect();
+        assert_eq!(
+            links,
+            vec![
+                (
+                    "https://lapce.dev/docs",
+                    "https://lapce.dev/docs".to_string()
+                ),
+                ("http://a.b/c?d=e", "http://a.b/c?d=e".to_string()),
+            ]
+        );
+        assert!(url_links("no links here").is_empty());
+    }
+}
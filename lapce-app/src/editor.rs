@@ -1,5 +1,7 @@
 use std::{
+    cell::Cell,
     collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
     rc::Rc,
     str::FromStr,
     sync::Arc,
@@ -17,11 +19,13 @@
         batch, use_context, ReadSignal, RwSignal, Scope, SignalGet, SignalUpdate,
         SignalWith,
     },
+    text::{Attrs, FamilyOwned, LineHeightValue},
     views::editor::{
+        actions::CommonAction,
         command::CommandExecuted,
         id::EditorId,
         movement,
-        text::Document,
+        text::{Document, SystemClipboard},
         view::{
             DiffSection, DiffSectionKind, LineInfo, ScreenLines, ScreenLinesBase,
         },
@@ -41,49 +45,77 @@
         EditCommand, FocusCommand, MotionModeCommand, MultiSelectionCommand,
         ScrollCommand,
     },
-    cursor::{Cursor, CursorMode},
+    cursor::{Cursor, CursorAffinity, CursorMode},
     editor::EditType,
-    mode::{Mode, MotionMode},
+    line_ending::LineEnding,
+    mode::{Mode, MotionMode, VisualMode},
+    register::{Clipboard, RegisterData},
     rope_text_pos::RopeTextPosition,
     selection::{InsertDrift, SelRegion, Selection},
+    syntax::{textobject::TextObject, DefinitionKind},
 };
-use lapce_rpc::{buffer::BufferId, plugin::PluginId, proxy::ProxyResponse};
-use lapce_xi_rope::{Rope, RopeDelta, Transformer};
+use lapce_rpc::{
+    buffer::BufferId,
+    plugin::{PluginEvent, PluginId},
+    proxy::ProxyResponse,
+    RpcError,
+};
+use lapce_xi_rope::{find::CaseMatching, Rope, RopeDelta, Transformer};
 use lsp_types::{
-    CodeActionResponse, CompletionItem, CompletionTextEdit, GotoDefinitionResponse,
-    HoverContents, InlayHint, InlayHintLabel, InlineCompletionTriggerKind, Location,
-    MarkedString, MarkupKind, Range, TextEdit,
+    CodeActionKind, CodeActionOrCommand, CompletionItem, CompletionItemKind,
+    CompletionTextEdit, HoverContents, InlayHint, InlayHintLabel,
+    InlineCompletionTriggerKind, Location, MarkedString, MarkupKind, Position,
+    Range, SelectionRange, TextEdit, Url, WorkspaceEdit,
 };
 use nucleo::Utf32Str;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use view::StickyHeaderInfo;
 
 use self::{
-    diff::DiffInfo,
+    diff::{DiffInfo, RemovedLine},
     location::{EditorLocation, EditorPosition},
 };
 use crate::{
+    clipboard::PrimaryClipboard,
     command::{CommandKind, InternalCommand, LapceCommand, LapceWorkbenchCommand},
-    completion::CompletionStatus,
-    config::LapceConfig,
+    completion::{CompletionStatus, ScoredCompletionItem},
+    config::{color::LapceColor, LapceConfig},
     db::LapceDb,
+    debug::{LAUNCH_TOML, RUN_TOML},
     doc::{Doc, DocContent},
     editor_tab::EditorTabChild,
+    emmet::{self, EmmetSyntax},
+    find::{Find, FindSearchString},
     id::{DiffEditorId, EditorTabId},
-    inline_completion::{InlineCompletionItem, InlineCompletionStatus},
+    inline_completion::{
+        next_word_len, InlineCompletionItem, InlineCompletionStatus,
+    },
+    keyboard_macro::MacroRegisterAction,
     keypress::{condition::Condition, KeyPressFocus},
+    listener::Listener,
     lsp::path_from_url,
-    main_split::{Editors, MainSplitData, SplitDirection, SplitMoveDirection},
+    main_split::{
+        workspace_edits, Editors, MainSplitData, SplitDirection, SplitMoveDirection,
+    },
     markdown::{
-        from_marked_string, from_plaintext, parse_markdown, MarkdownContent,
+        self, from_marked_string, from_plaintext, parse_markdown, MarkdownContent,
     },
     panel::{
         call_hierarchy_view::CallHierarchyItemData,
         implementation_view::{init_implementation_root, map_to_location},
         kind::PanelKind,
     },
+    peek::definition_locations,
+    signature::call_closed,
     snippet::Snippet,
+    text_transform::{indent_width, reindent, CaseTransform, LineTransform},
     tracing::*,
+    user_snippet::{expand_variables, SnippetVariables},
+    vim::{
+        compile_pattern, line_edits, parse_ex_command, Address, ExCommand,
+        LineAddress, LineCommand, LineRange, Mark, VimCharAction, VimState,
+    },
     window_tab::{CommonData, Focus, WindowTabData},
 };
 
@@ -92,6 +124,9 @@
 pub mod location;
 pub mod view;
 
+/// How many lines a click has to move the cursor by to be saved as a jump.
+const JUMP_CLICK_LINES: usize = 10;
+
 #[derive(Clone, Debug)]
 pub enum InlineFindDirection {
     Left,
@@ -155,6 +190,7 @@ pub fn to_data(
                         let content = DocContent::Scratch {
                             id: BufferId::next(),
                             name: name.to_string(),
+                            read_only: false,
                         };
                         let doc = Doc::new_content(
                             data.scope,
@@ -205,6 +241,43 @@ pub struct OnScreenFind {
 
 pub type SnippetIndex = Vec<(usize, (usize, usize))>;
 
+/// A rectangular selection, made of one region per line from the line of the
+/// anchor to that of the head, between their horizontal positions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockSelection {
+    anchor_line: usize,
+    anchor_x: f64,
+    head_line: usize,
+    head_x: f64,
+    /// The regions last selected for the block, to tell whether the cursor
+    /// was moved since.
+    regions: Vec<(usize, usize)>,
+}
+
+impl BlockSelection {
+    fn new(line: usize, x: f64) -> Self {
+        Self {
+            anchor_line: line,
+            anchor_x: x,
+            head_line: line,
+            head_x: x,
+            regions: Vec::new(),
+        }
+    }
+}
+
+/// Selected text dragged with the mouse, to be moved or copied to where it's
+/// dropped, in the same or another editor.
+#[derive(Clone, Debug)]
+pub struct TextDrag {
+    source: EditorData,
+    /// The dragged region of the source document.
+    region: (usize, usize),
+    text: String,
+    /// The editor and offset the text would be dropped at.
+    pub target: Option<(EditorId, usize)>,
+}
+
 /// Shares data between cloned instances as long as the signals aren't swapped out.
 #[derive(Clone, Debug)]
 pub struct EditorData {
@@ -216,12 +289,32 @@ pub struct EditorData {
     pub inline_find: RwSignal<Option<InlineFindDirection>>,
     pub on_screen_find: RwSignal<OnScreenFind>,
     pub last_inline_find: RwSignal<Option<(InlineFindDirection, String)>>,
+    /// Set while waiting for the register or the mark of a vim command.
+    pub vim_char_action: RwSignal<Option<VimCharAction>>,
     pub find_focus: RwSignal<bool>,
     pub editor: Rc<Editor>,
     pub kind: RwSignal<EditorViewKind>,
     pub sticky_header_height: RwSignal<f64>,
     pub common: Rc<CommonData>,
     pub sticky_header_info: RwSignal<StickyHeaderInfo>,
+    pub block_selection: RwSignal<Option<BlockSelection>>,
+    /// The selections the selection was expanded from, ending with the one it
+    /// was last expanded to, so that shrinking it goes back through them.
+    pub expanded_selections: RwSignal<Vec<Selection>>,
+    /// The ranges the language server linked to the one the cursor is in,
+    /// ordered by offset, which are edited along with it.
+    pub linked_editing_ranges: RwSignal<Option<Vec<(usize, usize)>>>,
+    /// The range of the document link under the pointer while the modifier
+    /// for following links is held, which is underlined.
+    pub hovered_link: RwSignal<Option<(usize, usize)>>,
+    /// The offset of the text under the pointer, if it's over the text.
+    pointer_offset: RwSignal<Option<usize>>,
+    /// The laid out lines removed from the left side of the inline diff this
+    /// editor shows, kept until the diff or the config changes.
+    pub diff_removed_lines: RwSignal<Rc<Vec<Vec<RemovedLine>>>>,
+    /// The width of a character of the editor font, with the id of the config
+    /// it was measured for.
+    char_width: Rc<Cell<Option<(u64, f64)>>>,
 }
 
 impl PartialEq for EditorData {
@@ -242,7 +335,7 @@ fn new(
         let cx = cx.create_child();
 
         let confirmed = confirmed.unwrap_or_else(|| cx.create_rw_signal(false));
-        EditorData {
+        let editor = EditorData {
             scope: cx,
             editor_tab_id: cx.create_rw_signal(editor_tab_id),
             diff_editor_id: cx.create_rw_signal(diff_editor_id),
@@ -255,13 +348,56 @@ fn new(
                 regions: Vec::new(),
             }),
             last_inline_find: cx.create_rw_signal(None),
+            vim_char_action: cx.create_rw_signal(None),
             find_focus: cx.create_rw_signal(false),
             editor: Rc::new(editor),
             kind: cx.create_rw_signal(EditorViewKind::Normal),
             sticky_header_height: cx.create_rw_signal(0.0),
             common,
             sticky_header_info: cx.create_rw_signal(StickyHeaderInfo::default()),
-        }
+            block_selection: cx.create_rw_signal(None),
+            expanded_selections: cx.create_rw_signal(Vec::new()),
+            linked_editing_ranges: cx.create_rw_signal(None),
+            hovered_link: cx.create_rw_signal(None),
+            pointer_offset: cx.create_rw_signal(None),
+            diff_removed_lines: cx.create_rw_signal(Rc::new(Vec::new())),
+            char_width: Rc::new(Cell::new(None)),
+        };
+        editor.watch_diff_removed_lines();
+        editor
+    }
+
+    /// Lay out the removed lines of an inline diff again whenever the diff
+    /// or the config changes, rather than on every paint.
+    fn watch_diff_removed_lines(&self) {
+        let editor = self.clone();
+        let kind = self.kind;
+        let config = self.common.config;
+        let diff_removed_lines = self.diff_removed_lines;
+        self.scope.create_effect(move |_| {
+            let config = config.get();
+            let layouts = kind.with(|kind| {
+                let EditorViewKind::Diff(diff_info) = kind else {
+                    return Vec::new();
+                };
+                if diff_info.inline.is_none() {
+                    return Vec::new();
+                }
+                let language = editor.doc().syntax.with_untracked(|s| s.language);
+                let editor_config = config.editor_config(language);
+                let family: Vec<FamilyOwned> =
+                    FamilyOwned::parse_list(&editor_config.font_family).collect();
+                let attrs = Attrs::new()
+                    .family(&family)
+                    .font_size(editor_config.font_size() as f32)
+                    .line_height(LineHeightValue::Px(
+                        editor_config.line_height() as f32
+                    ))
+                    .color(config.color(LapceColor::EDITOR_FOREGROUND));
+                diff_info.inline_removed_layouts(attrs)
+            });
+            diff_removed_lines.set(Rc::new(layouts));
+        });
     }
 
     /// Create a new local editor.  
@@ -302,8 +438,11 @@ pub fn new_doc(
 
     /// Swap out the document this editor is for
     pub fn update_doc(&self, doc: Rc<Doc>) {
+        let old = self.doc().content.get_untracked();
         let style = doc.styling();
+        let editors = doc.editors();
         self.editor.update_doc(doc, Some(style));
+        editors.notify_doc_swapped(self, &old);
     }
 
     /// Create a new editor using the same underlying [`Doc`]  
@@ -393,6 +532,15 @@ pub fn screen_lines(&self) -> RwSignal<ScreenLines> {
         self.editor.screen_lines
     }
 
+    /// The height of the lines of the document, which depends on the font size
+    /// set for its language.
+    pub fn line_height(&self) -> usize {
+        let language = self.doc().syntax.with_untracked(|s| s.language);
+        self.common
+            .config
+            .with_untracked(|config| config.editor_config(language).line_height())
+    }
+
     pub fn doc(&self) -> Rc<Doc> {
         let doc = self.editor.doc();
         let Ok(doc) = doc.downcast_rc() else {
@@ -417,7 +565,654 @@ pub fn rope_text(&self) -> RopeTextVal {
         self.editor.rope_text()
     }
 
+    /// Change the line ending of the document, converting its line breaks to it
+    /// with an edit that can be undone.
+    pub fn set_line_ending(&self, line_ending: LineEnding) {
+        self.doc().buffer.update(|buffer| {
+            buffer.set_line_ending(line_ending);
+        });
+        self.run_edit_command(&EditCommand::NormalizeLineEndings);
+    }
+
+    /// Wrap each selection in the block comment tokens of the language, or unwrap it
+    /// when it already is a block comment. Cursors without a selection toggle the
+    /// comment around the text of their line.
+    pub fn toggle_block_comment(&self) {
+        let doc = self.doc();
+        let Some((open, close)) = doc
+            .syntax()
+            .with_untracked(|syntax| syntax.language.block_comment_tokens())
+        else {
+            return;
+        };
+        let (selection, edits) = doc.buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let mut edits = Vec::new();
+            let mut prev_end = None;
+            for region in selection.regions() {
+                let (start, end) = if region.is_caret() {
+                    let line = buffer.line_of_offset(region.start);
+                    (
+                        buffer.first_non_blank_character_on_line(line),
+                        buffer.line_end_offset(line, true),
+                    )
+                } else {
+                    (region.min(), region.max())
+                };
+                // Several cursors on a line toggle its comment once.
+                if prev_end.is_some_and(|prev_end| start <= prev_end) {
+                    continue;
+                }
+                prev_end = Some(end);
+                let text = buffer.slice_to_cow(start..end);
+                let content = text.trim();
+                if content.is_empty() {
+                    continue;
+                }
+
+                let content_start = start + (text.len() - text.trim_start().len());
+                let content_end = content_start + content.len();
+                let inner = content
+                    .strip_prefix(open)
+                    .and_then(|inner| inner.strip_suffix(close));
+                if let Some(inner) = inner {
+                    // Remove a space of padding along with the tokens.
+                    let open_len = open.len() + usize::from(inner.starts_with(' '));
+                    let close_len = close.len()
+                        + usize::from(inner.len() > 1 && inner.ends_with(' '));
+                    edits.push((
+                        Selection::region(content_start, content_start + open_len),
+                        String::new(),
+                    ));
+                    edits.push((
+                        Selection::region(content_end - close_len, content_end),
+                        String::new(),
+                    ));
+                } else {
+                    edits.push((Selection::caret(start), format!("{open} ")));
+                    edits.push((Selection::caret(end), format!(" {close}")));
+                }
+            }
+            (selection, edits)
+        });
+        let edits = edits
+            .iter()
+            .map(|(selection, text)| (selection.clone(), text.as_str()))
+            .collect::<Vec<_>>();
+        if !edits.is_empty() {
+            self.do_edit_with_drift(&selection, &edits, InsertDrift::Inside);
+        }
+    }
+
+    /// The comment prefixes the new lines of the cursors continue with, for the
+    /// cursors that are within a line comment.
+    fn comment_continuations(&self, cursor: &Cursor) -> Vec<Option<String>> {
+        let continue_comments = self
+            .common
+            .config
+            .with_untracked(|config| config.editor.continue_comments);
+        let CursorMode::Insert(selection) = &cursor.mode else {
+            return Vec::new();
+        };
+        let doc = self.doc();
+        let (token, block_tokens) = doc.syntax().with_untracked(|syntax| {
+            (
+                syntax.language.comment_token(),
+                syntax.language.block_comment_tokens(),
+            )
+        });
+        // Languages without line comments use the opening block comment token.
+        if !continue_comments
+            || token.is_empty()
+            || block_tokens.is_some_and(|(open, _)| open == token)
+        {
+            return Vec::new();
+        }
+
+        doc.buffer.with_untracked(|buffer| {
+            selection
+                .regions()
+                .iter()
+                .map(|region| {
+                    let offset = region.min();
+                    let line = buffer.line_of_offset(offset);
+                    let start = buffer.first_non_blank_character_on_line(line);
+                    let end = buffer.line_end_offset(line, true);
+                    let text = buffer.slice_to_cow(start..end);
+                    let prefix = comment_prefix(&text, token)?;
+                    (offset >= start + prefix.len()).then(|| prefix.to_string())
+                })
+                .collect()
+        })
+    }
+
+    /// Start the new lines of the cursors with the comment prefixes from
+    /// [`Self::comment_continuations`].
+    fn continue_comments(&self, prefixes: &[Option<String>]) {
+        let (selection, edits) = self.doc().buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let edits = if selection.regions().len() == prefixes.len() {
+                selection
+                    .regions()
+                    .iter()
+                    .zip(prefixes)
+                    .filter_map(|(region, prefix)| {
+                        Some((Selection::caret(region.max()), prefix.as_deref()?))
+                    })
+                    .collect::<Vec<_>>()
+            } else {
+                Vec::new()
+            };
+            (selection, edits)
+        });
+        if !edits.is_empty() {
+            self.do_edit(&selection, &edits);
+        }
+    }
+
+    /// Wrap each selection in `open` and `close`, keeping the wrapped text selected.
+    /// Cursors without a selection are left alone.
+    pub fn surround_with(&self, open: &str, close: &str) {
+        let (selection, edits) = self.doc().buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let edits = selection
+                .regions()
+                .iter()
+                .filter(|region| !region.is_caret())
+                .flat_map(|region| {
+                    [
+                        (Selection::caret(region.min()), open),
+                        (Selection::caret(region.max()), close),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            (selection, edits)
+        });
+        if !edits.is_empty() {
+            self.do_edit_with_drift(&selection, &edits, InsertDrift::Inside);
+        }
+    }
+
+    /// Delete the innermost brackets or quotes around each cursor.
+    pub fn remove_surrounding_pair(&self) {
+        let doc = self.doc();
+        let (selection, offsets) = doc.buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let mut offsets = Vec::new();
+            for region in selection.regions() {
+                let offset = region.min();
+                let brackets = doc.find_enclosing_brackets(offset);
+                let quotes = enclosing_quotes(buffer, offset);
+                // The pair that starts last is the one nested in the other.
+                let pair = [brackets, quotes]
+                    .into_iter()
+                    .flatten()
+                    .max_by_key(|(start, _)| *start);
+                if let Some((start, end)) = pair {
+                    offsets.extend([start, end]);
+                }
+            }
+            offsets.sort_unstable();
+            offsets.dedup();
+            (selection, offsets)
+        });
+        let edits = offsets
+            .into_iter()
+            .map(|offset| (Selection::region(offset, offset + 1), ""))
+            .collect::<Vec<_>>();
+        if !edits.is_empty() {
+            self.do_edit(&selection, &edits);
+        }
+    }
+
+    /// Expand every selection to the range enclosing it, as from an
+    /// identifier to its expression, then its statement and its block. The
+    /// selection ranges of the language server are used when it gives them,
+    /// and the syntax nodes otherwise.
+    pub fn expand_selection(&self) {
+        let doc = self.doc();
+        let selection = doc.buffer.with_untracked(|buffer| {
+            self.cursor().get_untracked().edit_selection(buffer)
+        });
+        let path = match if doc.loaded() {
+            doc.content.with_untracked(|c| c.path().cloned())
+        } else {
+            None
+        } {
+            Some(path) => path,
+            None => {
+                self.expand_selection_to(selection, Vec::new());
+                return;
+            }
+        };
+        let rev = doc.rev();
+        let positions = doc.buffer.with_untracked(|buffer| {
+            selection
+                .regions()
+                .iter()
+                .map(|region| buffer.offset_to_position(region.min()))
+                .collect()
+        });
+
+        let editor = self.clone();
+        let send =
+            create_ext_action(self.scope, move |ranges: Vec<SelectionRange>| {
+                let doc = editor.doc();
+                let current = doc.buffer.with_untracked(|buffer| {
+                    editor.cursor().get_untracked().edit_selection(buffer)
+                });
+                if doc.rev() != rev || current != selection {
+                    return;
+                }
+                editor.expand_selection_to(selection, ranges);
+            });
+        self.common
+            .proxy
+            .get_selection_range(path, positions, move |result| {
+                let ranges = match result {
+                    Ok(ProxyResponse::GetSelectionRange { ranges }) => ranges,
+                    _ => Vec::new(),
+                };
+                send(ranges);
+            });
+    }
+
+    /// Expand every region of the selection to the smallest of the selection
+    /// ranges at its start which encloses it, or to the syntax node enclosing
+    /// it when there is none.
+    fn expand_selection_to(
+        &self,
+        selection: Selection,
+        ranges: Vec<SelectionRange>,
+    ) {
+        let doc = self.doc();
+        let mut expanded = Selection::new();
+        for (i, region) in selection.regions().iter().enumerate() {
+            let (min, max) = (region.min(), region.max());
+            let mut range = ranges.get(i);
+            let mut enclosing = None;
+            while let Some(r) = range {
+                let (start, end) = doc.buffer.with_untracked(|buffer| {
+                    (
+                        buffer.offset_of_position(&r.range.start),
+                        buffer.offset_of_position(&r.range.end),
+                    )
+                });
+                if start <= min && end >= max && (start, end) != (min, max) {
+                    enclosing = Some((start, end));
+                    break;
+                }
+                range = r.parent.as_deref();
+            }
+            let (start, end) = enclosing
+                .or_else(|| doc.expand_range(min, max))
+                .unwrap_or((min, max));
+            expanded.add_region(SelRegion::new(start, end, None));
+        }
+        if expanded == selection {
+            return;
+        }
+
+        let mut stack = self.expanded_selections.get_untracked();
+        if stack.last() != Some(&selection) {
+            stack = vec![selection];
+        }
+        stack.push(expanded.clone());
+        self.expanded_selections.set(stack);
+        self.set_expanded_selection(expanded);
+    }
+
+    /// Shrink the selection back to what it was before it was last expanded.
+    pub fn shrink_selection(&self) {
+        let selection = self.doc().buffer.with_untracked(|buffer| {
+            self.cursor().get_untracked().edit_selection(buffer)
+        });
+        let mut stack = self.expanded_selections.get_untracked();
+        if stack.len() < 2 || stack.last() != Some(&selection) {
+            self.expanded_selections.set(Vec::new());
+            return;
+        }
+        stack.pop();
+        let Some(previous) = stack.last().cloned() else {
+            return;
+        };
+        self.expanded_selections.set(stack);
+        self.set_expanded_selection(previous);
+    }
+
+    fn set_expanded_selection(&self, selection: Selection) {
+        self.cursor().update(|cursor| match cursor.mode {
+            CursorMode::Insert(_) => cursor.set_insert(selection),
+            _ => {
+                if let Some(region) = selection.first() {
+                    let end = self.doc().buffer.with_untracked(|buffer| {
+                        buffer.prev_grapheme_offset(region.max(), 1, 0)
+                    });
+                    cursor.mode = CursorMode::Visual {
+                        start: region.min(),
+                        end: end.max(region.min()),
+                        mode: VisualMode::Normal,
+                    };
+                }
+            }
+        });
+    }
+
+    /// Move the cursor to the start of the next function or class, as `kind`
+    /// says, or to the previous one when not `forward`.
+    pub fn go_to_definition_of_kind(&self, kind: DefinitionKind, forward: bool) {
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        if let Some(offset) = self.doc().find_definition(offset, kind, forward) {
+            self.cursor()
+                .update(|cursor| cursor.set_offset(offset, false, false));
+        }
+    }
+
+    /// Apply the pending delete or yank to the text object at the cursor, or
+    /// to what's inside of it when `inside`, or select it otherwise. With
+    /// `change`, it's deleted and insert mode entered, as `cif` does.
+    pub fn select_text_object(
+        &self,
+        object: TextObject,
+        inside: bool,
+        change: bool,
+    ) {
+        let doc = self.doc();
+        let mut cursor = self.cursor().get_untracked();
+        let motion_mode = if change {
+            Some(MotionMode::Delete { count: 1 })
+        } else {
+            cursor.motion_mode.take()
+        };
+        let Some(range) = doc.find_text_object(cursor.offset(), object, inside)
+        else {
+            self.cursor().set(cursor);
+            return;
+        };
+
+        let Some(motion_mode) = motion_mode else {
+            self.set_expanded_selection(Selection::region(range.start, range.end));
+            return;
+        };
+        let mut register = self.common.register.get_untracked();
+        doc.exec_motion_mode(
+            &self.editor,
+            &mut cursor,
+            motion_mode,
+            range.clone(),
+            false,
+            &mut register,
+        );
+        if change {
+            cursor.mode = CursorMode::Insert(Selection::caret(range.start));
+        }
+        self.cursor().set(cursor);
+        self.common.register.set(register);
+    }
+
+    /// Swap the parameter or argument at the cursor with the next one, or the
+    /// previous one when not `forward`, keeping the cursor on it.
+    pub fn swap_parameter(&self, forward: bool) {
+        let doc = self.doc();
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let Some((current, other)) = doc.find_parameter_swap(offset, forward) else {
+            return;
+        };
+        let (selection, current_text, other_text) =
+            doc.buffer.with_untracked(|buffer| {
+                (
+                    self.cursor().get_untracked().edit_selection(buffer),
+                    buffer.slice_to_cow(current.clone()).to_string(),
+                    buffer.slice_to_cow(other.clone()).to_string(),
+                )
+            });
+        self.do_edit(
+            &selection,
+            &[
+                (
+                    Selection::region(current.start, current.end),
+                    other_text.as_str(),
+                ),
+                (
+                    Selection::region(other.start, other.end),
+                    current_text.as_str(),
+                ),
+            ],
+        );
+
+        // Where the parameter at the cursor moved to
+        let start = if forward {
+            other.start + other_text.len() - current_text.len()
+        } else {
+            other.start
+        };
+        let offset = start + (offset - current.start);
+        self.cursor()
+            .update(|cursor| cursor.set_offset(offset, false, false));
+    }
+
+    /// Remove the spaces and tabs at the end of every line of the document.
+    pub fn trim_trailing_whitespace(&self) {
+        let (selection, edits) = self.doc().buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let edits = (0..=buffer.last_line())
+                .filter_map(|line| trailing_whitespace(buffer, line))
+                .map(|(start, end)| (Selection::region(start, end), ""))
+                .collect::<Vec<_>>();
+            (selection, edits)
+        });
+        if !edits.is_empty() {
+            self.do_edit(&selection, &edits);
+        }
+    }
+
+    /// Replace the lines the selections are on, or all the lines of the document
+    /// when nothing is selected, with what `transform` gives for them.
+    pub fn transform_lines(&self, transform: LineTransform) {
+        let (selection, edits) = self.doc().buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let line_ending = buffer.line_ending().get_chars();
+            let edits = selected_lines(buffer, &selection)
+                .into_iter()
+                .filter_map(|(start_line, end_line)| {
+                    let start = buffer.offset_of_line(start_line);
+                    let end = buffer.offset_of_line(end_line)
+                        + buffer
+                            .line_content(end_line)
+                            .trim_end_matches(['\r', '\n'])
+                            .len();
+                    let text = buffer.slice_to_cow(start..end);
+                    let lines = text.lines().collect::<Vec<_>>();
+                    let new_text = transform.apply(&lines).join(line_ending);
+                    (new_text != text)
+                        .then(|| (Selection::region(start, end), new_text))
+                })
+                .collect::<Vec<_>>();
+            (selection, edits)
+        });
+        self.do_transform_edit(&selection, edits);
+    }
+
+    /// Change the case of the selected text, or of the whole document when
+    /// nothing is selected.
+    pub fn transform_case(&self, transform: CaseTransform) {
+        let (selection, edits) = self.doc().buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let ranges = if selection.regions().iter().any(|r| !r.is_caret()) {
+                selection
+                    .regions()
+                    .iter()
+                    .filter(|r| !r.is_caret())
+                    .map(|r| (r.min(), r.max()))
+                    .collect()
+            } else {
+                vec![(0, buffer.len())]
+            };
+            let edits = ranges
+                .into_iter()
+                .filter_map(|(start, end)| {
+                    let text = buffer.slice_to_cow(start..end);
+                    let new_text = transform.apply(&text);
+                    (new_text != text)
+                        .then(|| (Selection::region(start, end), new_text))
+                })
+                .collect::<Vec<_>>();
+            (selection, edits)
+        });
+        self.do_transform_edit(&selection, edits);
+    }
+
+    fn do_transform_edit(
+        &self,
+        selection: &Selection,
+        edits: Vec<(Selection, String)>,
+    ) {
+        if edits.is_empty() {
+            return;
+        }
+        let edits = edits
+            .iter()
+            .map(|(selection, text)| (selection, text.as_str()))
+            .collect::<Vec<_>>();
+        self.do_edit(selection, &edits);
+    }
+
+    /// Expand the Emmet abbreviation before the cursor into a snippet, when there's
+    /// a single cursor in a document whose language has abbreviations. Returns
+    /// whether there was an abbreviation to expand.
+    fn expand_emmet_abbreviation(&self) -> bool {
+        let enabled = self
+            .common
+            .config
+            .with_untracked(|config| config.editor.emmet);
+        let doc = self.doc();
+        let language = doc.syntax().with_untracked(|syntax| syntax.language);
+        let Some(syntax) = EmmetSyntax::from_language(language) else {
+            return false;
+        };
+        let offset = match self.cursor().with_untracked(|c| c.mode.clone()) {
+            CursorMode::Insert(selection)
+                if enabled
+                    && selection.regions().len() == 1
+                    && selection.regions()[0].is_caret() =>
+            {
+                selection.regions()[0].start
+            }
+            _ => return false,
+        };
+
+        let expansion = doc.buffer.with_untracked(|buffer| {
+            let line_start = buffer.offset_of_line(buffer.line_of_offset(offset));
+            let text = buffer.slice_to_cow(line_start..offset);
+            let indent = &text[..text.len() - text.trim_start().len()];
+            let (start, snippet) = emmet::expand_at_end(
+                &text,
+                syntax,
+                indent,
+                buffer.indent_style().as_str(),
+            )?;
+            Some((line_start + start, snippet))
+        });
+        // In JavaScript only the markup of the elements is abbreviated.
+        let expansion = expansion.filter(|(start, _)| {
+            syntax != EmmetSyntax::Jsx
+                || doc
+                    .syntax()
+                    .with_untracked(|syntax| syntax.accepts_jsx(*start))
+        });
+        let Some((start, snippet)) = expansion else {
+            return false;
+        };
+        if let Err(err) = self.completion_apply_snippet(
+            &snippet,
+            &Selection::region(start, offset),
+            Vec::new(),
+            start,
+        ) {
+            tracing::error!("{:?}", err);
+            return false;
+        }
+        true
+    }
+
+    /// Paste the lines of the clipboard re-indented to match the indentation
+    /// where they are pasted. Returns whether they were pasted.
+    fn reindented_paste(&self) -> bool {
+        let language = self.doc().syntax.with_untracked(|s| s.language);
+        let (enabled, tab_width) = self.common.config.with_untracked(|config| {
+            (
+                config.editor.reindent_on_paste,
+                config.editor_config(language).tab_width,
+            )
+        });
+        let region = match self.cursor().with_untracked(|c| c.mode.clone()) {
+            CursorMode::Insert(selection)
+                if enabled && selection.regions().len() == 1 =>
+            {
+                selection.regions()[0]
+            }
+            _ => return false,
+        };
+        let Some(text) = SystemClipboard::new().get_string() else {
+            return false;
+        };
+        if !text.contains('\n') {
+            return false;
+        }
+
+        let pasted = self.doc().buffer.with_untracked(|buffer| {
+            let offset = region.min();
+            let line = buffer.line_of_offset(offset);
+            let prefix = buffer.slice_to_cow(buffer.offset_of_line(line)..offset);
+            let content = buffer.line_content(line);
+            let indent_unit = buffer.indent_style().as_str();
+            let indent = if !content.trim().is_empty() {
+                indent_width(&content, tab_width)
+            } else if !prefix.is_empty() {
+                indent_width(&prefix, tab_width)
+            } else {
+                new_line_indent(buffer, line, indent_unit, tab_width)
+            };
+            reindent(&text, &prefix, indent, indent_unit, tab_width)
+        });
+        if pasted == text {
+            return false;
+        }
+        let selection = Selection::region(region.min(), region.max());
+        self.do_edit(&selection, &[(&selection, pasted.as_str())]);
+        true
+    }
+
+    /// Paste the clipboard as it is, without re-indenting it.
+    pub fn paste_without_formatting(&self) {
+        let selection = match self.cursor().with_untracked(|c| c.mode.clone()) {
+            CursorMode::Insert(selection) => selection,
+            _ => {
+                self.run_edit_command(&EditCommand::ClipboardPaste);
+                return;
+            }
+        };
+        let Some(text) = SystemClipboard::new().get_string() else {
+            return;
+        };
+        let edits = selection
+            .regions()
+            .iter()
+            .map(|region| {
+                (Selection::region(region.min(), region.max()), text.as_str())
+            })
+            .collect::<Vec<_>>();
+        self.do_edit(&selection, &edits);
+    }
+
     fn run_edit_command(&self, cmd: &EditCommand) -> CommandExecuted {
+        if *cmd == EditCommand::InsertTab && self.expand_emmet_abbreviation() {
+            return CommandExecuted::Yes;
+        }
+        if *cmd == EditCommand::ClipboardPaste && self.reindented_paste() {
+            return CommandExecuted::Yes;
+        }
+
         let doc = self.doc();
         let text = self.editor.rope_text();
         let is_local = doc.content.with_untracked(|content| content.is_local());
@@ -437,6 +1232,22 @@ fn run_edit_command(&self, cmd: &EditCommand) -> CommandExecuted {
                 None
             };
 
+        let comment_prefixes = if *cmd == EditCommand::InsertNewLine {
+            self.comment_continuations(&cursor)
+        } else {
+            Vec::new()
+        };
+
+        let linked = match cmd {
+            EditCommand::DeleteBackward => {
+                self.add_linked_editing_carets(&mut cursor, true, false)
+            }
+            EditCommand::DeleteForward => {
+                self.add_linked_editing_carets(&mut cursor, false, true)
+            }
+            _ => None,
+        };
+
         let deltas =
             batch(|| doc.do_edit(&mut cursor, cmd, modal, &mut register, smart_tab));
 
@@ -449,6 +1260,21 @@ fn run_edit_command(&self, cmd: &EditCommand) -> CommandExecuted {
         self.editor.cursor.set(cursor);
         self.editor.register.set(register);
 
+        match linked {
+            Some(index) => self.remove_linked_editing_carets(index),
+            // The other edits would make the linked ranges differ.
+            None if !deltas.is_empty()
+                && self.linked_editing_ranges.with_untracked(Option::is_some) =>
+            {
+                self.linked_editing_ranges.set(None)
+            }
+            None => {}
+        }
+
+        if !deltas.is_empty() && comment_prefixes.iter().any(Option::is_some) {
+            self.continue_comments(&comment_prefixes);
+        }
+
         if show_completion(cmd, &doc_before_edit, &deltas) {
             self.update_completion(false);
         } else {
@@ -467,8 +1293,13 @@ fn run_edit_command(&self, cmd: &EditCommand) -> CommandExecuted {
         }
 
         self.apply_deltas(&deltas);
+        if !deltas.is_empty() {
+            self.update_signature(None);
+        }
         if let EditCommand::NormalMode = cmd {
+            self.common.signature.cancel();
             self.snippet.set(None);
+            self.linked_editing_ranges.set(None);
             self.quit_on_screen_find();
         }
 
@@ -527,17 +1358,11 @@ fn run_multi_selection_command(
                             (first.min(), first.max())
                         };
                         let search_str = rope_text.slice_to_cow(start..end);
-                        let case_sensitive = find.case_sensitive(false);
-                        let multicursor_case_sensitive =
-                            config.editor.multicursor_case_sensitive;
-                        let case_sensitive =
-                            multicursor_case_sensitive || case_sensitive;
-                        // let search_whole_word = config.editor.multicursor_whole_words;
-                        find.set_case_sensitive(case_sensitive);
-                        find.set_find(&search_str);
+                        let search =
+                            MulticursorSearch::new(find, &search_str, &config);
                         let mut offset = 0;
                         while let Some((start, end)) =
-                            find.next(rope_text.text(), offset, false, false)
+                            search.next(rope_text.text(), offset, false)
                         {
                             offset = end;
                             selection.add_region(SelRegion::new(start, end, None));
@@ -565,18 +1390,12 @@ fn run_multi_selection_command(
                             let r = selection.last_inserted().unwrap();
                             let search_str =
                                 rope_text.slice_to_cow(r.min()..r.max());
-                            let case_sensitive = find.case_sensitive(false);
-                            let case_sensitive =
-                                config.editor.multicursor_case_sensitive
-                                    || case_sensitive;
-                            // let search_whole_word =
-                            // config.editor.multicursor_whole_words;
-                            find.set_case_sensitive(case_sensitive);
-                            find.set_find(&search_str);
+                            let search =
+                                MulticursorSearch::new(find, &search_str, &config);
                             let mut offset = r.max();
                             let mut seen = HashSet::new();
                             while let Some((start, end)) =
-                                find.next(rope_text.text(), offset, false, true)
+                                search.next(rope_text.text(), offset, true)
                             {
                                 if !selection
                                     .regions()
@@ -613,11 +1432,12 @@ fn run_multi_selection_command(
 
                             let search_str =
                                 rope_text.slice_to_cow(r.min()..r.max());
-                            find.set_find(&search_str);
+                            let search =
+                                MulticursorSearch::new(find, &search_str, &config);
                             let mut offset = r.max();
                             let mut seen = HashSet::new();
                             while let Some((start, end)) =
-                                find.next(rope_text.text(), offset, false, true)
+                                search.next(rope_text.text(), offset, true)
                             {
                                 if !selection
                                     .regions()
@@ -650,6 +1470,24 @@ fn run_multi_selection_command(
         CommandExecuted::Yes
     }
 
+    /// Save `offset` and `scroll_offset` in the jump locations, for going back
+    /// to where the cursor was before a jump.
+    fn save_jump_location(&self, offset: usize, scroll_offset: Vec2) {
+        let path = self
+            .doc()
+            .content
+            .with_untracked(|content| content.path().cloned());
+        if let Some(path) = path {
+            self.common
+                .internal_command
+                .send(InternalCommand::SaveJumpLocation {
+                    path,
+                    offset,
+                    scroll_offset,
+                });
+        }
+    }
+
     fn run_move_command(
         &self,
         movement: &lapce_core::movement::Movement,
@@ -660,22 +1498,9 @@ fn run_move_command(
         if movement.is_jump()
             && movement != &self.editor.last_movement.get_untracked()
         {
-            let path = self
-                .doc()
-                .content
-                .with_untracked(|content| content.path().cloned());
-            if let Some(path) = path {
-                let offset = self.cursor().with_untracked(|c| c.offset());
-                let scroll_offset =
-                    self.viewport().get_untracked().origin().to_vec2();
-                self.common.internal_command.send(
-                    InternalCommand::SaveJumpLocation {
-                        path,
-                        offset,
-                        scroll_offset,
-                    },
-                );
-            }
+            let offset = self.cursor().with_untracked(|c| c.offset());
+            let scroll_offset = self.viewport().get_untracked().origin().to_vec2();
+            self.save_jump_location(offset, scroll_offset);
         }
         self.editor.last_movement.set(movement.clone());
 
@@ -710,6 +1535,7 @@ fn run_move_command(
             })
         }
         self.cancel_completion();
+        self.update_signature(None);
         CommandExecuted::Yes
     }
 
@@ -774,6 +1600,10 @@ pub fn run_focus_command(
         match cmd {
             FocusCommand::ModalClose => {
                 self.cancel_completion();
+                self.common.signature.cancel();
+            }
+            FocusCommand::GetSignature => {
+                self.trigger_signature_help();
             }
             FocusCommand::SplitVertical => {
                 if let Some(editor_tab_id) =
@@ -1029,6 +1859,9 @@ pub fn run_focus_command(
             FocusCommand::GotoDefinition => {
                 self.go_to_definition();
             }
+            FocusCommand::GotoTypeDefinition => {
+                self.go_to_type_definition();
+            }
             FocusCommand::ShowCodeActions => {
                 self.show_code_actions(false);
             }
@@ -1124,6 +1957,275 @@ pub fn run_focus_command(
         CommandExecuted::Yes
     }
 
+    /// Record into or replay the macro of the register `c`, typed after the vim
+    /// `q` or `@` command.
+    fn macro_register(&self, action: MacroRegisterAction, c: &str) {
+        match action {
+            MacroRegisterAction::Record => {
+                let macros = self.common.keypress.with_untracked(|k| k.macros);
+                macros.update(|macros| macros.start(c.to_string()));
+            }
+            MacroRegisterAction::Replay { count } => {
+                // `@@` replays the last macro.
+                let name = (c != "@").then(|| c.to_string());
+                self.common
+                    .internal_command
+                    .send(InternalCommand::ReplayMacro { name, count });
+            }
+        }
+    }
+
+    /// Act on the register or the mark `c`, typed after the vim `"`, `m`, `'`
+    /// or `` ` `` command.
+    fn vim_char(&self, action: VimCharAction, c: &str) {
+        let mut chars = c.chars();
+        let (Some(name), None) = (chars.next(), chars.next()) else {
+            return;
+        };
+        let doc = self.doc();
+        let path = doc
+            .content
+            .with_untracked(|content| content.path().cloned());
+        match action {
+            VimCharAction::SelectRegister => {
+                if VimState::is_register(name) {
+                    self.common
+                        .vim
+                        .update(|vim| vim.selected_register = Some(name));
+                }
+            }
+            VimCharAction::CreateMark => {
+                let Some(path) = path else {
+                    return;
+                };
+                let offset = self.cursor().with_untracked(|c| c.offset());
+                let position = doc
+                    .buffer
+                    .with_untracked(|buffer| buffer.offset_to_position(offset));
+                self.common
+                    .vim
+                    .update(|vim| vim.set_mark(name, Mark { path, position }));
+            }
+            VimCharAction::GoToMark { exact } => {
+                let Some(mark) = self
+                    .common
+                    .vim
+                    .with_untracked(|vim| vim.mark(name, path.as_deref()))
+                else {
+                    return;
+                };
+                let position = if exact {
+                    EditorPosition::Position(mark.position)
+                } else {
+                    EditorPosition::Line(mark.position.line as usize)
+                };
+                self.common
+                    .internal_command
+                    .send(InternalCommand::JumpToLocation {
+                        location: EditorLocation {
+                            path: mark.path,
+                            position: Some(position),
+                            scroll_offset: None,
+                            ignore_unconfirmed: false,
+                            same_editor_tab: false,
+                        },
+                    });
+            }
+        }
+    }
+
+    /// Run `command` with the register `name` selected by the vim `"` command,
+    /// which receives what the command yanks or deletes, or holds what it
+    /// pastes.
+    fn run_with_register(
+        &self,
+        name: char,
+        command: &LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        self.common.vim.update(|vim| vim.selected_register = None);
+        let unnamed = self.common.register.with_untracked(|r| r.unnamed.clone());
+
+        if let CommandKind::Edit(EditCommand::Paste | EditCommand::PasteBefore) =
+            &command.kind
+        {
+            let clipboard = |content| RegisterData {
+                content,
+                mode: VisualMode::Normal,
+            };
+            let data = match name {
+                '"' => Some(unnamed.clone()),
+                '+' => SystemClipboard::new().get_string().map(clipboard),
+                '*' => PrimaryClipboard::new().get_string().map(clipboard),
+                _ => self
+                    .common
+                    .vim
+                    .with_untracked(|vim| vim.register(name).cloned()),
+            };
+            let Some(data) = data else {
+                return CommandExecuted::Yes;
+            };
+            self.common.register.update(|r| r.unnamed = data);
+            let executed = self.run_command(command, count, mods);
+            self.common.register.update(|r| r.unnamed = unnamed);
+            return executed;
+        }
+
+        // Emptied to tell whether the command yanked or deleted anything.
+        self.common.register.update(|r| {
+            r.unnamed = RegisterData {
+                content: String::new(),
+                mode: VisualMode::Normal,
+            }
+        });
+        let executed = self.run_command(command, count, mods);
+        let data = self.common.register.with_untracked(|r| r.unnamed.clone());
+        if data.content.is_empty() {
+            self.common.register.update(|r| r.unnamed = unnamed);
+            // Still waiting for the motion of `"ay` for instance, unless the
+            // command was cancelled.
+            if !matches!(command.kind, CommandKind::Edit(EditCommand::NormalMode)) {
+                self.common
+                    .vim
+                    .update(|vim| vim.selected_register = Some(name));
+            }
+            return executed;
+        }
+        match name {
+            // What's deleted into the black hole register isn't kept anywhere.
+            '_' => self.common.register.update(|r| r.unnamed = unnamed),
+            '+' => SystemClipboard::new().put_string(&data.content),
+            '*' => PrimaryClipboard::new().put_string(&data.content),
+            _ => self.common.vim.update(|vim| vim.store(name, data)),
+        }
+        executed
+    }
+
+    /// Run the vim ex command `input`, typed in the command palette after `:`.
+    pub fn run_ex_command(&self, input: &str) -> Result<(), String> {
+        match parse_ex_command(input)? {
+            ExCommand::GoToLine(address) => {
+                let (current, last) = self.ex_current_line();
+                let line =
+                    address.resolve(current, last, &|name| self.mark_line(name))?;
+                let offset = self.doc().buffer.with_untracked(|buffer| {
+                    buffer.first_non_blank_character_on_line(line)
+                });
+                self.run_move_command(
+                    &lapce_core::movement::Movement::Offset(offset),
+                    None,
+                    Modifiers::empty(),
+                );
+            }
+            ExCommand::Lines { range, command } => {
+                let current = Address {
+                    base: LineAddress::Current,
+                    offset: 0,
+                };
+                let range = range.unwrap_or(LineRange::Lines(current, current));
+                self.run_ex_line_command(range, None, &command)?;
+            }
+            ExCommand::Global {
+                range,
+                pattern,
+                invert,
+                command,
+            } => {
+                let regex = compile_pattern(&pattern, false)?;
+                self.run_ex_line_command(
+                    range.unwrap_or(LineRange::Whole),
+                    Some((&regex, invert)),
+                    &command,
+                )?;
+            }
+            ExCommand::Write => self.save(true, || {}),
+            ExCommand::WriteAll => {
+                self.common
+                    .workbench_command
+                    .send(LapceWorkbenchCommand::SaveAll);
+            }
+            ExCommand::Quit => {
+                self.common.lapce_command.send(LapceCommand {
+                    kind: CommandKind::Focus(FocusCommand::SplitClose),
+                    data: None,
+                });
+            }
+            ExCommand::WriteQuit => {
+                let lapce_command = self.common.lapce_command;
+                self.save(true, move || {
+                    lapce_command.send(LapceCommand {
+                        kind: CommandKind::Focus(FocusCommand::SplitClose),
+                        data: None,
+                    });
+                });
+            }
+            ExCommand::Edit(file) => {
+                let file = PathBuf::from(file);
+                let path = match self.common.workspace.path.as_ref() {
+                    Some(workspace) if file.is_relative() => workspace.join(file),
+                    _ => file,
+                };
+                self.common
+                    .internal_command
+                    .send(InternalCommand::OpenFile { path });
+            }
+        }
+        Ok(())
+    }
+
+    /// The line of the cursor and the last line, for the ranges of ex commands.
+    fn ex_current_line(&self) -> (usize, usize) {
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        self.doc().buffer.with_untracked(|buffer| {
+            (buffer.line_of_offset(offset), buffer.last_line())
+        })
+    }
+
+    /// The line of the mark `name` in this file.
+    fn mark_line(&self, name: char) -> Option<usize> {
+        let path = self
+            .doc()
+            .content
+            .with_untracked(|content| content.path().cloned())?;
+        let mark = self
+            .common
+            .vim
+            .with_untracked(|vim| vim.mark(name, Some(&path)))?;
+        (mark.path == path).then_some(mark.position.line as usize)
+    }
+
+    fn run_ex_line_command(
+        &self,
+        range: LineRange,
+        filter: Option<(&Regex, bool)>,
+        command: &LineCommand,
+    ) -> Result<(), String> {
+        let (current, last) = self.ex_current_line();
+        let (start, end) =
+            range.resolve(current, last, &|name| self.mark_line(name))?;
+        let text = self
+            .doc()
+            .buffer
+            .with_untracked(|buffer| buffer.text().to_string());
+        let edits = line_edits(&text, start, end, filter, command)?;
+        if edits.is_empty() {
+            return Err("Pattern not found".to_string());
+        }
+
+        let edits = edits
+            .iter()
+            .map(|(range, new)| {
+                (Selection::region(range.start, range.end), new.as_str())
+            })
+            .collect::<Vec<_>>();
+        let selection = self.doc().buffer.with_untracked(|buffer| {
+            self.cursor().get_untracked().edit_selection(buffer)
+        });
+        self.do_edit(&selection, &edits);
+        Ok(())
+    }
+
     /// Jump to the next/previous column on the line which matches the given text
     fn inline_find(&self, direction: InlineFindDirection, c: &str) {
         let offset = self.cursor().with_untracked(|c| c.offset());
@@ -1296,23 +2398,10 @@ enum DefinitionOrReferece {
                     definition, ..
                 }) = result
                 {
-                    if let Some(location) = match definition {
-                        GotoDefinitionResponse::Scalar(location) => Some(location),
-                        GotoDefinitionResponse::Array(locations) => {
-                            if !locations.is_empty() {
-                                Some(locations[0].clone())
-                            } else {
-                                None
-                            }
-                        }
-                        GotoDefinitionResponse::Link(location_links) => {
-                            let location_link = location_links[0].clone();
-                            Some(Location {
-                                uri: location_link.target_uri,
-                                range: location_link.target_selection_range,
-                            })
-                        }
-                    } {
+                    let mut locations = definition_locations(definition);
+                    if locations.len() > 1 {
+                        send(DefinitionOrReferece::References(locations));
+                    } else if let Some(location) = locations.pop() {
                         if location.range.start == start_position {
                             proxy.get_references(
                                 path.clone(),
@@ -1370,6 +2459,43 @@ enum DefinitionOrReferece {
         );
     }
 
+    fn go_to_type_definition(&self) {
+        let doc = self.doc();
+        let path = match if doc.loaded() {
+            doc.content.with_untracked(|c| c.path().cloned())
+        } else {
+            None
+        } {
+            Some(path) => path,
+            None => return,
+        };
+
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let position = doc
+            .buffer
+            .with_untracked(|buffer| buffer.offset_to_position(offset));
+
+        let internal_command = self.common.internal_command;
+        let cursor = self.cursor().read_only();
+        let send = create_ext_action(self.scope, move |locations| {
+            if cursor.with_untracked(|c| c.offset()) == offset {
+                jump_to_locations(internal_command, locations);
+            }
+        });
+        self.common.proxy.get_type_definition(
+            offset,
+            path,
+            position,
+            move |result| {
+                if let Ok(ProxyResponse::GetTypeDefinition { definition, .. }) =
+                    result
+                {
+                    send(definition_locations(definition));
+                }
+            },
+        );
+    }
+
     pub fn call_hierarchy(&self, window_tab_data: WindowTabData) {
         let doc = self.doc();
         let path = match if doc.loaded() {
@@ -1527,7 +2653,12 @@ pub fn go_to_implementation(&self, window_tab_data: WindowTabData) {
                     }) = result
                     {
                         let locations = map_to_location(resp);
-                        if !locations.is_empty() {
+                        if locations.len() == 1 {
+                            jump_to_locations(
+                                window_tab_data.common.internal_command,
+                                locations,
+                            );
+                        } else if !locations.is_empty() {
                             proxy.references_resolve(
                                 locations,
                                 update_implementation,
@@ -1574,6 +2705,34 @@ fn select_inline_completion(&self) {
         }
     }
 
+    /// Accept the next word of the inline completion, keeping the rest of it
+    /// shown, or the whole completion when that is all there is left of it.
+    pub fn accept_inline_completion_word(&self) {
+        if !self.has_inline_completions() {
+            return;
+        }
+        let doc = self.doc();
+        let Some(text) = doc.inline_completion.get_untracked() else {
+            return;
+        };
+        let len = next_word_len(&text);
+        if len == 0 || len == text.len() {
+            self.select_inline_completion();
+            return;
+        }
+
+        let (line, col) = doc.inline_completion_pos.get_untracked();
+        let offset = doc
+            .buffer
+            .with_untracked(|buffer| buffer.offset_of_line_col(line, col));
+        if self.cursor().with_untracked(|c| c.offset()) != offset {
+            return;
+        }
+        let selection = Selection::caret(offset);
+        self.do_edit(&selection, &[(selection.clone(), &text[..len])]);
+        self.update_inline_completion(InlineCompletionTriggerKind::Automatic);
+    }
+
     fn next_inline_completion(&self) {
         if self
             .common
@@ -1712,6 +2871,28 @@ fn has_inline_completions(&self) -> bool {
     }
 
     pub fn select_completion(&self) {
+        self.select_completion_with(None);
+    }
+
+    /// Whether typing `c` accepts the selected completion item, as `.` after
+    /// a field, when it is one of the commit characters of the item.
+    fn is_completion_commit_character(&self, c: &str) -> bool {
+        self.has_completions()
+            && self.common.completion.with_untracked(|completion| {
+                completion.current_item().is_some_and(|item| {
+                    item.item
+                        .commit_characters
+                        .as_ref()
+                        .is_some_and(|chars| chars.iter().any(|ch| ch == c))
+                })
+            })
+    }
+
+    /// Accept the selected completion item, then type the commit character
+    /// which accepted it, if any. The commit character is typed before an item
+    /// that has to be resolved first, which is skipped if the document was
+    /// edited meanwhile.
+    fn select_completion_with(&self, commit: Option<String>) {
         let item = self
             .common
             .completion
@@ -1721,13 +2902,20 @@ pub fn select_completion(&self) {
         if let Some(item) = item {
             if item.item.data.is_some() {
                 let editor = self.clone();
-                let rev = doc.buffer.with_untracked(|buffer| buffer.rev());
                 let path = doc.content.with_untracked(|c| c.path().cloned());
                 let offset = self.cursor().with_untracked(|c| c.offset());
+                // The commit character is typed right away, and the item is
+                // applied in front of it once resolved.
+                if let Some(c) = &commit {
+                    self.receive_char(c);
+                }
+                let rev = doc.buffer.with_untracked(|buffer| buffer.rev());
+                let typed_end = self.cursor().with_untracked(|c| c.offset());
                 let buffer = doc.buffer;
                 let content = doc.content;
                 let send = create_ext_action(self.scope, move |item| {
-                    if editor.cursor().with_untracked(|c| c.offset() != offset) {
+                    // The item is stale once anything else was edited.
+                    if editor.cursor().with_untracked(|c| c.offset() != typed_end) {
                         return;
                     }
                     if buffer.with_untracked(|b| b.rev()) != rev
@@ -1737,8 +2925,26 @@ pub fn select_completion(&self) {
                     {
                         return;
                     }
+                    editor.cursor().update(|cursor| {
+                        cursor.set_insert(Selection::caret(offset));
+                    });
                     if let Err(err) = editor.apply_completion_item(&item) {
                         tracing::error!("{:?}", err);
+                        editor.cursor().update(|cursor| {
+                            cursor.set_insert(Selection::caret(typed_end));
+                        });
+                        return;
+                    }
+                    // Back after what was typed, unless a snippet put the
+                    // cursor in one of its placeholders
+                    if typed_end > offset
+                        && item.insert_text_format
+                            != Some(lsp_types::InsertTextFormat::SNIPPET)
+                    {
+                        editor.cursor().update(|cursor| {
+                            let end = cursor.offset() + typed_end - offset;
+                            cursor.set_insert(Selection::caret(end));
+                        });
                     }
                 });
                 self.common.proxy.completion_resolve(
@@ -1757,8 +2963,13 @@ pub fn select_completion(&self) {
                         send(item);
                     },
                 );
-            } else if let Err(err) = self.apply_completion_item(&item.item) {
-                tracing::error!("{:?}", err);
+            } else {
+                if let Err(err) = self.apply_completion_item(&item.item) {
+                    tracing::error!("{:?}", err);
+                }
+                if let Some(c) = &commit {
+                    self.receive_char(c);
+                }
             }
         }
     }
@@ -1776,6 +2987,66 @@ pub fn cancel_completion(&self) {
         self.doc().clear_completion_lens()
     }
 
+    /// Request the signatures of the call being typed when `typed` opens its
+    /// arguments or starts another, and keep them up to date while they're shown
+    /// and the cursor is within the arguments. `typed` is `None` for edits and
+    /// moves other than typing.
+    fn update_signature(&self, typed: Option<&str>) {
+        let show_signature = self
+            .common
+            .config
+            .with_untracked(|config| config.editor.show_signature);
+        if !show_signature {
+            return;
+        }
+        let signature = &self.common.signature;
+        let shown = signature.is_shown_in(self.id());
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        match typed {
+            Some("(") => self.request_signature(offset),
+            Some(",") if !shown => self.trigger_signature_help(),
+            _ if shown => {
+                let start = signature.offset.get_untracked();
+                if offset < start
+                    || call_closed(&self.rope_text().slice_to_cow(start..offset))
+                {
+                    signature.cancel();
+                } else {
+                    self.request_signature(start);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Show the signatures of the call around the cursor.
+    fn trigger_signature_help(&self) {
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let start = self
+            .doc()
+            .find_enclosing_brackets(offset)
+            .map_or(offset, |(open, _)| open + 1);
+        self.request_signature(start);
+    }
+
+    /// Request the signatures at the cursor, for the call with arguments
+    /// starting at `start`.
+    fn request_signature(&self, start: usize) {
+        let doc = self.doc();
+        let Some(path) = doc
+            .content
+            .with_untracked(|content| content.path().cloned())
+        else {
+            return;
+        };
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let position = doc
+            .buffer
+            .with_untracked(|buffer| buffer.offset_to_position(offset));
+        let request_id = self.common.signature.request(self.id(), start);
+        self.common.proxy.signature_help(request_id, path, position);
+    }
+
     /// Update the displayed autocompletion box
     /// Sends a request to the LSP for completion information
     fn update_completion(&self, display_if_empty_input: bool) {
@@ -1858,12 +3129,14 @@ fn update_completion(&self, display_if_empty_input: bool) {
         }
 
         let doc = self.doc();
+        let snippet_items = self.user_snippet_items(&path);
         self.common.completion.update(|completion| {
             completion.path.clone_from(&path);
             completion.offset = start_offset;
             completion.input.clone_from(&input);
             completion.status = CompletionStatus::Started;
             completion.input_items.clear();
+            completion.snippet_items = snippet_items;
             completion.request_id += 1;
             let start_pos = doc
                 .buffer
@@ -1891,6 +3164,70 @@ fn update_completion(&self, display_if_empty_input: bool) {
         });
     }
 
+    /// The completion items for the user snippets of the document's language,
+    /// with their variables expanded for the cursor position.
+    fn user_snippet_items(&self, path: &Path) -> im::Vector<ScoredCompletionItem> {
+        let doc = self.doc();
+        let language = doc.syntax.with_untracked(|s| s.language);
+        let snippets = self.common.user_snippets.get_untracked();
+        let snippets: Vec<_> = snippets.for_language(language).collect();
+        if snippets.is_empty() {
+            return im::Vector::new();
+        }
+
+        let cursor = self.cursor().get_untracked();
+        let offset = cursor.offset();
+        let (line, current_line, selected_text) =
+            doc.buffer.with_untracked(|buffer| {
+                let line = buffer.line_of_offset(offset);
+                let current_line = buffer
+                    .line_content(line)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string();
+                let selected_text = cursor
+                    .get_selection()
+                    .map(|(start, end)| {
+                        buffer
+                            .slice_to_cow(start.min(end)..start.max(end))
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                (line, current_line, selected_text)
+            });
+        let variables = SnippetVariables {
+            path: path.to_path_buf(),
+            line,
+            current_line,
+            selected_text,
+            read_clipboard: Some(|| SystemClipboard::new().get_string()),
+            ..Default::default()
+        };
+        let resolve = |name: &str| variables.resolve(name);
+
+        snippets
+            .into_iter()
+            .map(|snippet| ScoredCompletionItem {
+                item: CompletionItem {
+                    label: snippet.prefix.clone(),
+                    kind: Some(CompletionItemKind::SNIPPET),
+                    detail: Some(if snippet.description.is_empty() {
+                        snippet.name.clone()
+                    } else {
+                        snippet.description.clone()
+                    }),
+                    insert_text: Some(expand_variables(&snippet.body, &resolve)),
+                    insert_text_format: Some(lsp_types::InsertTextFormat::SNIPPET),
+                    ..Default::default()
+                },
+                plugin_id: PluginId(0),
+                priority: 0,
+                score: 0,
+                label_score: 0,
+                indices: Vec::new(),
+            })
+            .collect()
+    }
+
     /// Check if there are completions that are being rendered
     fn has_completions(&self) -> bool {
         self.common.completion.with_untracked(|completion| {
@@ -1922,43 +3259,48 @@ fn apply_completion_item(&self, item: &CompletionItem) -> anyhow::Result<()> {
             .insert_text_format
             .unwrap_or(lsp_types::InsertTextFormat::PLAIN_TEXT);
         if let Some(edit) = &item.text_edit {
-            match edit {
+            let (range, new_text) = match edit {
                 CompletionTextEdit::Edit(edit) => {
-                    let offset = cursor.offset();
-                    let start_offset = buffer.prev_code_boundary(offset);
-                    let end_offset = buffer.next_code_boundary(offset);
-                    let edit_start = buffer.offset_of_position(&edit.range.start);
-                    let edit_end = buffer.offset_of_position(&edit.range.end);
+                    (edit.range, edit.new_text.as_str())
+                }
+                // The whole word at the cursor is replaced, as for the items
+                // without an edit.
+                CompletionTextEdit::InsertAndReplace(edit) => {
+                    (edit.replace, edit.new_text.as_str())
+                }
+            };
+            let offset = cursor.offset();
+            let start_offset = buffer.prev_code_boundary(offset);
+            let end_offset = buffer.next_code_boundary(offset);
+            let edit_start = buffer.offset_of_position(&range.start);
+            let edit_end = buffer.offset_of_position(&range.end);
 
-                    let selection = lapce_core::selection::Selection::region(
-                        start_offset.min(edit_start),
-                        end_offset.max(edit_end),
+            let selection = lapce_core::selection::Selection::region(
+                start_offset.min(edit_start),
+                end_offset.max(edit_end),
+            );
+            match text_format {
+                lsp_types::InsertTextFormat::PLAIN_TEXT => {
+                    self.do_edit(
+                        &selection,
+                        &[
+                            &[(selection.clone(), new_text)][..],
+                            &additional_edit[..],
+                        ]
+                        .concat(),
                     );
-                    match text_format {
-                        lsp_types::InsertTextFormat::PLAIN_TEXT => {
-                            self.do_edit(
-                                &selection,
-                                &[
-                                    &[(selection.clone(), edit.new_text.as_str())][..],
-                                    &additional_edit[..],
-                                ]
-                                .concat(),
-                            );
-                            return Ok(());
-                        }
-                        lsp_types::InsertTextFormat::SNIPPET => {
-                            self.completion_apply_snippet(
-                                &edit.new_text,
-                                &selection,
-                                additional_edit,
-                                start_offset,
-                            )?;
-                            return Ok(());
-                        }
-                        _ => {}
-                    }
+                    return Ok(());
                 }
-                CompletionTextEdit::InsertAndReplace(_) => (),
+                lsp_types::InsertTextFormat::SNIPPET => {
+                    self.completion_apply_snippet(
+                        new_text,
+                        &selection,
+                        additional_edit,
+                        start_offset,
+                    )?;
+                    return Ok(());
+                }
+                _ => {}
             }
         }
 
@@ -1966,14 +3308,21 @@ fn apply_completion_item(&self, item: &CompletionItem) -> anyhow::Result<()> {
         let start_offset = buffer.prev_code_boundary(offset);
         let end_offset = buffer.next_code_boundary(offset);
         let selection = Selection::region(start_offset, end_offset);
+        let insert_text = item.insert_text.as_deref().unwrap_or(item.label.as_str());
+
+        if text_format == lsp_types::InsertTextFormat::SNIPPET {
+            return self.completion_apply_snippet(
+                insert_text,
+                &selection,
+                additional_edit,
+                start_offset,
+            );
+        }
 
         self.do_edit(
             &selection,
             &[
-                &[(
-                    selection.clone(),
-                    item.insert_text.as_deref().unwrap_or(item.label.as_str()),
-                )][..],
+                &[(selection.clone(), insert_text)][..],
                 &additional_edit[..],
             ]
             .concat(),
@@ -2071,6 +3420,17 @@ pub fn do_edit(
         &self,
         selection: &Selection,
         edits: &[(impl AsRef<Selection>, &str)],
+    ) {
+        self.do_edit_with_drift(selection, edits, InsertDrift::Default);
+    }
+
+    /// Like [`Self::do_edit`], with `drift` deciding whether the selections grow to
+    /// include text inserted at their edges.
+    fn do_edit_with_drift(
+        &self,
+        selection: &Selection,
+        edits: &[(impl AsRef<Selection>, &str)],
+        drift: InsertDrift,
     ) {
         let mut cursor = self.cursor().get_untracked();
         let doc = self.doc();
@@ -2079,7 +3439,7 @@ pub fn do_edit(
                 Some(e) => e,
                 None => return,
             };
-        let selection = selection.apply_delta(&delta, true, InsertDrift::Default);
+        let selection = selection.apply_delta(&delta, true, drift);
         let old_cursor = cursor.mode.clone();
         doc.buffer.update(|buffer| {
             cursor.update_selection(buffer, selection);
@@ -2117,6 +3477,7 @@ fn apply_deltas(&self, deltas: &[(Rope, RopeDelta, InvalLines)]) {
         for (_, delta, _) in deltas {
             // self.inactive_apply_delta(delta);
             self.update_snippet_offset(delta);
+            self.update_linked_editing_offset(delta);
             // self.update_breakpoints(delta);
         }
         // self.update_signature();
@@ -2146,6 +3507,20 @@ fn update_snippet_offset(&self, delta: &RopeDelta) {
         }
     }
 
+    fn update_linked_editing_offset(&self, delta: &RopeDelta) {
+        if self.linked_editing_ranges.with_untracked(|r| r.is_some()) {
+            self.linked_editing_ranges.update(|ranges| {
+                let mut transformer = Transformer::new(delta);
+                if let Some(ranges) = ranges.as_mut() {
+                    for (start, end) in ranges.iter_mut() {
+                        *start = transformer.transform(*start, false);
+                        *end = transformer.transform(*end, true);
+                    }
+                }
+            });
+        }
+    }
+
     fn do_go_to_location(
         &self,
         location: EditorLocation,
@@ -2217,6 +3592,146 @@ pub fn go_to_position(
         }
     }
 
+    /// Request the ranges linked to the one at the cursor once it left the
+    /// current ones, so that editing one of them edits the others.
+    pub fn update_linked_editing_ranges(&self) {
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let within = self.linked_editing_ranges.with_untracked(|ranges| {
+            ranges.as_ref().is_some_and(|ranges| {
+                ranges
+                    .iter()
+                    .any(|(start, end)| offset >= *start && offset <= *end)
+            })
+        });
+        if within {
+            return;
+        }
+        if self.linked_editing_ranges.with_untracked(Option::is_some) {
+            self.linked_editing_ranges.set(None);
+        }
+        if self.get_mode() != Mode::Insert
+            || !self
+                .common
+                .config
+                .with_untracked(|config| config.editor.linked_editing)
+        {
+            return;
+        }
+
+        let doc = self.doc();
+        let path = match if doc.loaded() {
+            doc.content.with_untracked(|c| c.path().cloned())
+        } else {
+            None
+        } {
+            Some(path) => path,
+            None => return,
+        };
+        let rev = doc.rev();
+        let position = doc
+            .buffer
+            .with_untracked(|buffer| buffer.offset_to_position(offset));
+
+        let editor = self.clone();
+        let send = create_ext_action(self.scope, move |ranges: Vec<Range>| {
+            let doc = editor.doc();
+            if doc.rev() != rev
+                || editor.cursor().with_untracked(|c| c.offset()) != offset
+            {
+                return;
+            }
+            let mut ranges: Vec<(usize, usize)> =
+                doc.buffer.with_untracked(|buffer| {
+                    ranges
+                        .iter()
+                        .map(|range| {
+                            (
+                                buffer.offset_of_position(&range.start),
+                                buffer.offset_of_position(&range.end),
+                            )
+                        })
+                        .collect()
+                });
+            ranges.sort();
+            if ranges.len() > 1
+                && ranges
+                    .iter()
+                    .any(|(start, end)| offset >= *start && offset <= *end)
+            {
+                editor.linked_editing_ranges.set(Some(ranges));
+            }
+        });
+        self.common
+            .proxy
+            .get_linked_editing_ranges(path, position, move |result| {
+                if let Ok(ProxyResponse::GetLinkedEditingRanges { ranges }) = result
+                {
+                    send(ranges.ranges);
+                }
+            });
+    }
+
+    /// Add carets at the same places in the other linked editing ranges as the
+    /// one in the range of the cursor, so that the edit is made in all of them.
+    /// Returns the index of the cursor's caret among them, or `None` when the
+    /// edit isn't made in the other ranges, as when deleting `backward` at the
+    /// start of the range or `forward` at its end.
+    fn add_linked_editing_carets(
+        &self,
+        cursor: &mut Cursor,
+        backward: bool,
+        forward: bool,
+    ) -> Option<usize> {
+        let ranges = self.linked_editing_ranges.get_untracked()?;
+        let CursorMode::Insert(selection) = &cursor.mode else {
+            return None;
+        };
+        let [region] = selection.regions() else {
+            return None;
+        };
+        let (min, max) = (region.min(), region.max());
+        let index = ranges
+            .iter()
+            .position(|(start, end)| min >= *start && max <= *end)?;
+        let (range_start, range_end) = ranges[index];
+        if min == max
+            && (backward && min == range_start || forward && max == range_end)
+        {
+            return None;
+        }
+
+        let mut carets = Selection::new();
+        for (start, end) in &ranges {
+            let offset = |o: usize| (start + o - range_start).min(*end);
+            carets.add_region(SelRegion::new(
+                offset(region.start),
+                offset(region.end),
+                None,
+            ));
+        }
+        if carets.regions().len() != ranges.len() {
+            return None;
+        }
+        cursor.set_insert(carets);
+        Some(index)
+    }
+
+    /// Keep only the caret at `index`, after the edit was made at the carets
+    /// added with [`Self::add_linked_editing_carets`].
+    fn remove_linked_editing_carets(&self, index: usize) {
+        let mut cursor = self.cursor().get_untracked();
+        let CursorMode::Insert(selection) = &cursor.mode else {
+            return;
+        };
+        let Some(region) = selection.regions().get(index).copied() else {
+            return;
+        };
+        let mut selection = Selection::new();
+        selection.add_region(region);
+        cursor.set_insert(selection);
+        self.cursor().set(cursor);
+    }
+
     pub fn get_code_actions(&self) {
         let doc = self.doc();
         let path = match if doc.loaded() {
@@ -2239,7 +3754,7 @@ pub fn get_code_actions(&self) {
 
         // insert some empty data, so that we won't make the request again
         doc.code_actions().update(|c| {
-            c.insert(offset, (PluginId(0), im::Vector::new()));
+            c.insert(offset, im::Vector::new());
         });
 
         let (position, rev, diagnostics) = doc.buffer.with_untracked(|buffer| {
@@ -2263,10 +3778,10 @@ pub fn get_code_actions(&self) {
 
         let send = create_ext_action(
             self.scope,
-            move |resp: (PluginId, CodeActionResponse)| {
+            move |actions: im::Vector<(PluginId, CodeActionOrCommand)>| {
                 if doc.rev() == rev {
                     doc.code_actions().update(|c| {
-                        c.insert(offset, (resp.0, resp.1.into()));
+                        c.insert(offset, actions);
                     });
                 }
             },
@@ -2277,12 +3792,9 @@ pub fn get_code_actions(&self) {
             position,
             diagnostics,
             move |result| {
-                if let Ok(ProxyResponse::GetCodeActionsResponse {
-                    plugin_id,
-                    resp,
-                }) = result
+                if let Ok(ProxyResponse::GetCodeActionsResponse { actions }) = result
                 {
-                    send((plugin_id, resp))
+                    send(actions.into())
                 }
             },
         );
@@ -2291,31 +3803,122 @@ pub fn get_code_actions(&self) {
     pub fn show_code_actions(&self, mouse_click: bool) {
         let offset = self.cursor().with_untracked(|c| c.offset());
         let doc = self.doc();
-        let code_actions = doc
+        let mut code_actions = doc
             .code_actions()
-            .with_untracked(|c| c.get(&offset).cloned());
-        if let Some((plugin_id, code_actions)) = code_actions {
-            if !code_actions.is_empty() {
-                self.common.internal_command.send(
-                    InternalCommand::ShowCodeActions {
-                        offset,
-                        mouse_click,
-                        plugin_id,
-                        code_actions,
-                    },
-                );
-            }
+            .with_untracked(|c| c.get(&offset).cloned())
+            .unwrap_or_default();
+        code_actions.extend(
+            doc.spelling_actions(offset)
+                .into_iter()
+                .map(|action| (PluginId(0), action)),
+        );
+        if !code_actions.is_empty() {
+            self.common
+                .internal_command
+                .send(InternalCommand::ShowCodeActions {
+                    offset,
+                    mouse_click,
+                    code_actions,
+                });
         }
     }
 
-    fn do_save(&self, after_action: impl FnOnce() + 'static) {
-        self.doc().save(after_action);
+    /// Show the code actions of `kind` for the selection, as the refactorings
+    /// with `refactor` or the actions on the whole file with `source`, rather
+    /// than all the actions at the cursor.
+    pub fn show_code_actions_of_kind(&self, kind: CodeActionKind) {
+        let doc = self.doc();
+        let path = match if doc.loaded() {
+            doc.content.with_untracked(|c| c.path().cloned())
+        } else {
+            None
+        } {
+            Some(path) => path,
+            None => return,
+        };
+        let offset = self.cursor().with_untracked(|c| c.offset());
+        let rev = doc.rev();
+        let range = doc.buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let region = selection.first();
+            let (start, end) = region
+                .map(|region| (region.min(), region.max()))
+                .unwrap_or((offset, offset));
+            Range {
+                start: buffer.offset_to_position(start),
+                end: buffer.offset_to_position(end),
+            }
+        });
+
+        let editor = self.clone();
+        let prefix = format!("{}.", kind.as_str());
+        let send = create_ext_action(
+            self.scope,
+            move |actions: im::Vector<(PluginId, CodeActionOrCommand)>| {
+                let doc = editor.doc();
+                if doc.rev() != rev
+                    || editor.cursor().with_untracked(|c| c.offset()) != offset
+                {
+                    return;
+                }
+                // Servers may not filter the actions by the requested kind.
+                let code_actions: im::Vector<_> = actions
+                    .into_iter()
+                    .filter(|(_, action)| match action {
+                        CodeActionOrCommand::CodeAction(action) => {
+                            action.kind.as_ref().is_some_and(|k| {
+                                *k == kind || k.as_str().starts_with(&prefix)
+                            })
+                        }
+                        CodeActionOrCommand::Command(_) => false,
+                    })
+                    .collect();
+                if !code_actions.is_empty() {
+                    editor.common.internal_command.send(
+                        InternalCommand::ShowCodeActions {
+                            offset,
+                            mouse_click: false,
+                            code_actions,
+                        },
+                    );
+                }
+            },
+        );
+        self.common.proxy.get_code_actions_of_kind(
+            path,
+            range,
+            kind.clone(),
+            move |result| {
+                if let Ok(ProxyResponse::GetCodeActionsResponse { actions }) = result
+                {
+                    send(actions.into());
+                }
+            },
+        );
+    }
+
+    fn do_save(&self, on_result: impl FnOnce(Result<(), RpcError>) + 'static) {
+        self.doc().save_with_result(on_result);
     }
 
     pub fn save(
         &self,
         allow_formatting: bool,
         after_action: impl FnOnce() + 'static,
+    ) {
+        self.save_with_result(allow_formatting, move |result| {
+            if result.is_ok() {
+                after_action();
+            }
+        });
+    }
+
+    /// Save the document like [`Self::save`], calling `on_result` with the error
+    /// if the proxy failed to write it.
+    pub fn save_with_result(
+        &self,
+        allow_formatting: bool,
+        on_result: impl FnOnce(Result<(), RpcError>) + 'static,
     ) {
         let doc = self.doc();
         let is_pristine = doc.is_pristine();
@@ -2343,36 +3946,149 @@ pub fn save(
         if allow_formatting && config.editor.normalize_line_endings {
             self.run_edit_command(&EditCommand::NormalizeLineEndings);
         }
+        if allow_formatting && config.editor.trim_trailing_whitespace_on_save {
+            self.trim_trailing_whitespace();
+        }
 
-        let rev = doc.rev();
-        let format_on_save = allow_formatting && config.editor.format_on_save;
+        let language = doc.syntax.with_untracked(|s| s.language);
+        let format_on_save =
+            allow_formatting && config.editor_config(language).format_on_save;
+        let code_actions = if allow_formatting {
+            config.editor.code_actions_on_save()
+        } else {
+            Vec::new()
+        };
+        let editor = self.clone();
+        self.run_code_actions_on_save(path.clone(), code_actions, move || {
+            editor.format_and_save(path, format_on_save, on_result);
+        });
+    }
+
+    fn format_and_save(
+        &self,
+        path: PathBuf,
+        format_on_save: bool,
+        on_result: impl FnOnce(Result<(), RpcError>) + 'static,
+    ) {
         if format_on_save {
+            let rev = self.doc().rev();
+            let formatter = self.doc().external_formatter();
             let editor = self.clone();
-            let send = create_ext_action(self.scope, move |result| {
-                if let Ok(Ok(ProxyResponse::GetDocumentFormatting { edits })) =
-                    result
-                {
-                    let current_rev = editor.doc().rev();
-                    if current_rev == rev {
-                        editor.do_text_edit(&edits);
+            self.doc().request_formatting(
+                move |proxy, f| proxy.get_document_formatting(path, formatter, f),
+                move |edits| {
+                    if let Some(edits) = edits {
+                        let current_rev = editor.doc().rev();
+                        if current_rev == rev {
+                            editor.do_text_edit(&edits);
+                        }
                     }
-                }
-                editor.do_save(after_action);
-            });
+                    editor.do_save(on_result);
+                },
+            );
+        } else {
+            self.do_save(on_result);
+        }
+    }
 
-            let (tx, rx) = crossbeam_channel::bounded(1);
-            let proxy = self.common.proxy.clone();
-            std::thread::spawn(move || {
-                proxy.get_document_formatting(path, move |result| {
-                    if let Err(err) = tx.send(result) {
-                        tracing::error!("{:?}", err);
+    /// Apply the first code action of each of `kinds` to the whole document,
+    /// one kind after the other so that each sees the edits of the previous
+    /// ones, and then call `after`.
+    fn run_code_actions_on_save(
+        &self,
+        path: PathBuf,
+        mut kinds: Vec<CodeActionKind>,
+        after: impl FnOnce() + 'static,
+    ) {
+        if kinds.is_empty() {
+            after();
+            return;
+        }
+        let kind = kinds.remove(0);
+        let doc = self.doc();
+        let rev = doc.rev();
+        let range = doc.buffer.with_untracked(|buffer| Range {
+            start: Position::default(),
+            end: buffer.offset_to_position(buffer.len()),
+        });
+
+        let editor = self.clone();
+        let request_path = path.clone();
+        doc.request_with_timeout(
+            move |proxy, f| {
+                proxy.get_code_actions_of_kind(request_path, range, kind, f)
+            },
+            move |response| {
+                let action = match response {
+                    Some(ProxyResponse::GetCodeActionsResponse { actions }) => {
+                        actions.into_iter().find_map(|(plugin_id, action)| {
+                            match action {
+                                CodeActionOrCommand::CodeAction(action) => {
+                                    Some((plugin_id, action))
+                                }
+                                CodeActionOrCommand::Command(_) => None,
+                            }
+                        })
                     }
-                });
-                let result = rx.recv_timeout(std::time::Duration::from_secs(1));
-                send(result);
-            });
-        } else {
-            self.do_save(after_action);
+                    _ => None,
+                };
+                match action {
+                    Some((plugin_id, action)) if action.edit.is_none() => {
+                        let doc = editor.doc();
+                        doc.request_with_timeout(
+                            move |proxy, f| {
+                                proxy.code_action_resolve(action, plugin_id, f)
+                            },
+                            move |response| {
+                                if let Some(
+                                    ProxyResponse::CodeActionResolveResponse {
+                                        item,
+                                    },
+                                ) = response
+                                {
+                                    editor.apply_code_action_on_save(
+                                        &path,
+                                        rev,
+                                        item.edit.as_ref(),
+                                    );
+                                }
+                                editor.run_code_actions_on_save(path, kinds, after);
+                            },
+                        );
+                    }
+                    action => {
+                        if let Some((_, action)) = action {
+                            editor.apply_code_action_on_save(
+                                &path,
+                                rev,
+                                action.edit.as_ref(),
+                            );
+                        }
+                        editor.run_code_actions_on_save(path, kinds, after);
+                    }
+                }
+            },
+        );
+    }
+
+    /// Apply the edits a code action on save makes to the document, unless
+    /// the document was changed in the meantime.
+    fn apply_code_action_on_save(
+        &self,
+        path: &Path,
+        rev: u64,
+        edit: Option<&WorkspaceEdit>,
+    ) {
+        if self.doc().rev() != rev {
+            return;
+        }
+        let Some(edits) = edit.and_then(workspace_edits) else {
+            return;
+        };
+        for (url, edits) in edits {
+            if url.to_file_path().ok().as_deref() == Some(path) {
+                self.do_text_edit(&edits);
+            }
         }
     }
 
@@ -2382,30 +4098,56 @@ pub fn format(&self) {
         let content = doc.content.get_untracked();
 
         if let DocContent::File { path, .. } = content {
+            let formatter = doc.external_formatter();
             let editor = self.clone();
-            let send = create_ext_action(self.scope, move |result| {
-                if let Ok(Ok(ProxyResponse::GetDocumentFormatting { edits })) =
-                    result
-                {
-                    let current_rev = editor.doc().rev();
-                    if current_rev == rev {
-                        editor.do_text_edit(&edits);
+            doc.request_formatting(
+                move |proxy, f| proxy.get_document_formatting(path, formatter, f),
+                move |edits| {
+                    if let Some(edits) = edits {
+                        if editor.doc().rev() == rev {
+                            editor.do_text_edit(&edits);
+                        }
                     }
-                }
-            });
+                },
+            );
+        }
+    }
 
-            let (tx, rx) = crossbeam_channel::bounded(1);
-            let proxy = self.common.proxy.clone();
-            std::thread::spawn(move || {
-                proxy.get_document_formatting(path, move |result| {
-                    if let Err(err) = tx.send(result) {
-                        tracing::error!("{:?}", err);
+    /// Format the selection with the LSP, or the line of the cursor when
+    /// nothing is selected.
+    pub fn format_selection(&self) {
+        let doc = self.doc();
+        let rev = doc.rev();
+        let DocContent::File { path, .. } = doc.content.get_untracked() else {
+            return;
+        };
+
+        let range = doc.buffer.with_untracked(|buffer| {
+            let selection = self.cursor().get_untracked().edit_selection(buffer);
+            let (mut start, mut end) =
+                (selection.min_offset(), selection.max_offset());
+            if start == end {
+                let line = buffer.line_of_offset(start);
+                start = buffer.offset_of_line(line);
+                end = buffer.line_end_offset(line, true);
+            }
+            Range {
+                start: buffer.offset_to_position(start),
+                end: buffer.offset_to_position(end),
+            }
+        });
+
+        let editor = self.clone();
+        doc.request_formatting(
+            move |proxy, f| proxy.get_document_range_formatting(path, range, f),
+            move |edits| {
+                if let Some(edits) = edits {
+                    if editor.doc().rev() == rev {
+                        editor.do_text_edit(&edits);
                     }
-                });
-                let result = rx.recv_timeout(std::time::Duration::from_secs(1));
-                send(result);
-            });
-        }
+                }
+            },
+        );
     }
 
     fn search_whole_word_forward(&self, mods: Modifiers) {
@@ -2469,29 +4211,90 @@ fn replace_next(&self, text: &str) {
 
         if let Some((start, end)) = next {
             let selection = Selection::region(start, end);
-            self.do_edit(&selection, &[(selection.clone(), text)]);
+            let text = self
+                .common
+                .find
+                .replacement(&buffer.slice_to_cow(start..end), text);
+            self.do_edit(&selection, &[(selection.clone(), text.as_str())]);
         }
     }
 
     fn replace_all(&self, text: &str) {
         let offset = self.cursor().with_untracked(|c| c.offset());
+        let doc = self.doc();
 
-        self.doc().update_find();
+        doc.update_find();
 
-        let edits: Vec<(Selection, &str)> = self
-            .doc()
-            .find_result
-            .occurrences
-            .get_untracked()
-            .regions()
-            .iter()
-            .map(|region| (Selection::region(region.start, region.end), text))
-            .collect();
+        let find = &self.common.find;
+        // Only the occurrences in the selection are replaced when there is one.
+        let selection = if find.in_selection.get_untracked() {
+            doc.buffer.with_untracked(|buffer| {
+                self.cursor()
+                    .get_untracked()
+                    .edit_selection(buffer)
+                    .regions()
+                    .iter()
+                    .filter(|region| !region.is_caret())
+                    .map(|region| (region.min(), region.max()))
+                    .collect::<Vec<_>>()
+            })
+        } else {
+            Vec::new()
+        };
+        let edits: Vec<(Selection, String)> = doc.buffer.with_untracked(|buffer| {
+            doc.find_result.occurrences.with_untracked(|occurrences| {
+                occurrences
+                    .regions()
+                    .iter()
+                    .filter(|region| {
+                        selection.is_empty()
+                            || selection.iter().any(|(start, end)| {
+                                *start <= region.min() && region.max() <= *end
+                            })
+                    })
+                    .map(|region| {
+                        let matched =
+                            buffer.slice_to_cow(region.min()..region.max());
+                        (
+                            Selection::region(region.start, region.end),
+                            find.replacement(&matched, text),
+                        )
+                    })
+                    .collect()
+            })
+        });
         if !edits.is_empty() {
+            let edits: Vec<(Selection, &str)> = edits
+                .iter()
+                .map(|(selection, text)| (selection.clone(), text.as_str()))
+                .collect();
             self.do_edit(&Selection::caret(offset), &edits);
         }
     }
 
+    /// Tell the plugins subscribed to selection changes where the selection is.
+    pub fn selection_changed(&self) {
+        let doc = self.doc();
+        let Some(path) = doc.content.with_untracked(|c| c.path().cloned()) else {
+            return;
+        };
+        let selections = doc.buffer.with_untracked(|buffer| {
+            self.cursor()
+                .get_untracked()
+                .edit_selection(buffer)
+                .regions()
+                .iter()
+                .map(|region| Range {
+                    start: buffer.offset_to_position(region.min()),
+                    end: buffer.offset_to_position(region.max()),
+                })
+                .collect()
+        });
+        self.common
+            .proxy
+            .plugin_event(PluginEvent::SelectionChanged { path, selections });
+    }
+
     pub fn save_doc_position(&self) {
         let doc = self.doc();
         let path = match if doc.loaded() {
@@ -2657,6 +4460,7 @@ fn search(&self) {
 
     pub fn pointer_down(&self, pointer_event: &PointerInputEvent) {
         self.cancel_completion();
+        self.common.signature.cancel();
         self.cancel_inline_completion();
         if let Some(editor_tab_id) = self.editor_tab_id.get_untracked() {
             self.common
@@ -2671,11 +4475,30 @@ pub fn pointer_down(&self, pointer_event: &PointerInputEvent) {
             self.common.focus.set(Focus::Workbench);
             self.find_focus.set(false);
         }
+        self.common.text_drag.set(None);
         match pointer_event.button {
             PointerButton::Primary => {
+                if self.start_text_drag(pointer_event) {
+                    return;
+                }
                 self.active().set(true);
+                let offset = self.cursor().with_untracked(|c| c.offset());
+                let scroll_offset =
+                    self.viewport().get_untracked().origin().to_vec2();
                 self.left_click(pointer_event);
 
+                // Clicking far away from the cursor is a jump that can be
+                // gone back from.
+                let new_offset = self.cursor().with_untracked(|c| c.offset());
+                let lines = self.doc().buffer.with_untracked(|buffer| {
+                    buffer
+                        .line_of_offset(offset)
+                        .abs_diff(buffer.line_of_offset(new_offset))
+                });
+                if lines > JUMP_CLICK_LINES {
+                    self.save_jump_location(offset, scroll_offset);
+                }
+
                 let y =
                     pointer_event.pos.y - self.editor.viewport.get_untracked().y0;
                 if self.sticky_header_height.get_untracked() > y {
@@ -2703,10 +4526,13 @@ pub fn pointer_down(&self, pointer_event: &PointerInputEvent) {
                     }
                 }
 
-                if (cfg!(target_os = "macos") && pointer_event.modifiers.meta())
-                    || (cfg!(not(target_os = "macos"))
-                        && pointer_event.modifiers.control())
-                {
+                if link_modifier(pointer_event.modifiers) {
+                    let mode = self.cursor().with_untracked(|c| c.get_mode());
+                    let (offset, _) =
+                        self.editor.offset_of_point(mode, pointer_event.pos);
+                    if self.open_document_link(offset) {
+                        return;
+                    }
                     let rs = self.find_hint(pointer_event.pos);
                     match rs {
                         FindHintRs::NoMatchBreak
@@ -2800,6 +4626,17 @@ fn left_click(&self, pointer_event: &PointerInputEvent) {
     #[instrument]
     fn single_click(&self, pointer_event: &PointerInputEvent) {
         self.editor.single_click(pointer_event);
+        self.update_linked_editing_ranges();
+        // Dragging from an alt click selects a block.
+        let block = pointer_event.modifiers.alt().then(|| {
+            let mode = self.cursor().with_untracked(|c| c.get_mode());
+            let (offset, _) = self.editor.offset_of_point(mode, pointer_event.pos);
+            BlockSelection::new(
+                self.rope_text().line_of_offset(offset),
+                pointer_event.pos.x,
+            )
+        });
+        self.block_selection.set(block);
     }
 
     #[instrument]
@@ -2812,18 +4649,169 @@ fn triple_click(&self, pointer_event: &PointerInputEvent) {
         self.editor.triple_click(pointer_event);
     }
 
+    /// Start dragging the selection if it's pressed on. Returns whether it was.
+    fn start_text_drag(&self, pointer_event: &PointerInputEvent) -> bool {
+        let drag_and_drop = self
+            .common
+            .config
+            .with_untracked(|config| config.editor.drag_and_drop);
+        if !drag_and_drop
+            || pointer_event.count != 1
+            || pointer_event.modifiers.shift()
+            || pointer_event.modifiers.alt()
+        {
+            return false;
+        }
+        let cursor = self.cursor().get_untracked();
+        let CursorMode::Insert(selection) = &cursor.mode else {
+            return false;
+        };
+        let [region] = selection.regions() else {
+            return false;
+        };
+        let (start, end) = (region.min(), region.max());
+        let (offset, _) = self
+            .editor
+            .offset_of_point(cursor.get_mode(), pointer_event.pos);
+        if region.is_caret() || offset < start || offset >= end {
+            return false;
+        }
+
+        let text = self.rope_text().slice_to_cow(start..end).to_string();
+        self.common.text_drag.set(Some(TextDrag {
+            source: self.clone(),
+            region: (start, end),
+            text,
+            target: None,
+        }));
+        true
+    }
+
+    /// Drop the dragged text at the pointer, moving it or, with Ctrl held,
+    /// copying it. Releasing the pointer without moving it or within the dragged
+    /// text only places the cursor.
+    fn drop_text(&self, drag: TextDrag, pointer_event: &PointerInputEvent) {
+        let (start, end) = drag.region;
+        let same_doc = Rc::ptr_eq(&self.doc(), &drag.source.doc());
+        let target_read_only = self
+            .doc()
+            .content
+            .with_untracked(|content| content.read_only());
+        let offset = match drag.target {
+            Some((id, offset))
+                if id == self.id()
+                    && !target_read_only
+                    && !(same_doc && offset >= start && offset <= end) =>
+            {
+                offset
+            }
+            _ => {
+                self.single_click(pointer_event);
+                return;
+            }
+        };
+        if let Some(editor_tab_id) = self.editor_tab_id.get_untracked() {
+            self.common
+                .internal_command
+                .send(InternalCommand::FocusEditorTab { editor_tab_id });
+        }
+
+        // Text can't be moved out of a read only document, so it's copied instead
+        let copy = pointer_event.modifiers.control()
+            || drag
+                .source
+                .doc()
+                .content
+                .with_untracked(|content| content.read_only());
+        let text = drag.text.as_str();
+        let selection = self.doc().buffer.with_untracked(|buffer| {
+            self.cursor().get_untracked().edit_selection(buffer)
+        });
+        let insert = (Selection::caret(offset), text);
+        let mut dropped_at = offset;
+        if same_doc && !copy {
+            let delete = (Selection::region(start, end), "");
+            if offset < start {
+                self.do_edit(&selection, &[insert, delete]);
+            } else {
+                self.do_edit(&selection, &[delete, insert]);
+                dropped_at -= text.len();
+            }
+        } else {
+            let rev = self.doc().rev();
+            self.do_edit(&selection, &[insert]);
+            if self.doc().rev() == rev {
+                // The text didn't make it into the target, so the source is left alone
+                return;
+            }
+            if !copy {
+                let source_selection =
+                    drag.source.doc().buffer.with_untracked(|buffer| {
+                        drag.source.cursor().get_untracked().edit_selection(buffer)
+                    });
+                drag.source.do_edit(
+                    &source_selection,
+                    &[(Selection::region(start, end), "")],
+                );
+            }
+        }
+        self.cursor().update(|cursor| {
+            cursor.set_insert(Selection::region(dropped_at, dropped_at + text.len()))
+        });
+    }
+
     #[instrument]
     pub fn pointer_move(&self, pointer_event: &PointerMoveEvent) {
         let mode = self.cursor().with_untracked(|c| c.get_mode());
         let (offset, is_inside) =
             self.editor.offset_of_point(mode, pointer_event.pos);
+        if self.common.text_drag.with_untracked(Option::is_some) {
+            let target = Some((self.id(), offset));
+            if self.common.text_drag.with_untracked(|drag| {
+                drag.as_ref().map(|d| d.target) != Some(target)
+            }) {
+                self.common.text_drag.update(|drag| {
+                    if let Some(drag) = drag {
+                        drag.target = target;
+                    }
+                });
+            }
+            return;
+        }
         if self.active().get_untracked()
             && self.cursor().with_untracked(|c| c.offset()) != offset
         {
-            self.cursor().update(|cursor| {
-                cursor.set_offset(offset, true, pointer_event.modifiers.alt())
-            });
+            match self.block_selection.get_untracked() {
+                Some(block)
+                    if pointer_event.modifiers.alt() && mode == Mode::Insert =>
+                {
+                    self.set_block_selection(BlockSelection {
+                        head_line: self.rope_text().line_of_offset(offset),
+                        head_x: pointer_event.pos.x,
+                        ..block
+                    });
+                }
+                _ => {
+                    self.cursor().update(|cursor| {
+                        cursor.set_offset(
+                            offset,
+                            true,
+                            pointer_event.modifiers.alt(),
+                        )
+                    });
+                }
+            }
+        }
+        let link_modifier_held = self.common.window_common.link_modifier_held;
+        let held = link_modifier(pointer_event.modifiers);
+        if link_modifier_held.get_untracked() != held {
+            link_modifier_held.set(held);
         }
+        let pointer_offset = is_inside.then_some(offset);
+        if self.pointer_offset.get_untracked() != pointer_offset {
+            self.pointer_offset.set(pointer_offset);
+        }
+        self.update_hovered_link();
         if self.common.hover.active.get_untracked() {
             let hover_editor_id = self.common.hover.editor_id.get_untracked();
             if hover_editor_id != self.id() {
@@ -2864,14 +4852,223 @@ pub fn pointer_move(&self, pointer_event: &PointerMoveEvent) {
         }
     }
 
+    /// Grow or shrink the block selection by moving its head by `lines` and
+    /// `columns`, starting a block at the cursor if the cursor was moved since
+    /// the last block selection.
+    pub fn grow_block_selection(&self, lines: isize, columns: isize) {
+        let Some(regions) = self.cursor().with_untracked(|c| match &c.mode {
+            CursorMode::Insert(selection) => Some(
+                selection
+                    .regions()
+                    .iter()
+                    .map(|r| (r.start, r.end))
+                    .collect::<Vec<_>>(),
+            ),
+            _ => None,
+        }) else {
+            return;
+        };
+        let block = self
+            .block_selection
+            .get_untracked()
+            .filter(|block| block.regions == regions)
+            .unwrap_or_else(|| {
+                let offset = self.cursor().with_untracked(|c| c.offset());
+                let (line, x) = self.line_x_of_offset(offset);
+                BlockSelection::new(line, x)
+            });
+
+        let rope_text = self.rope_text();
+        let head_line = block
+            .head_line
+            .saturating_add_signed(lines)
+            .min(rope_text.last_line());
+        let (start_line, end_line) = if head_line < block.anchor_line {
+            (head_line, block.anchor_line)
+        } else {
+            (block.anchor_line, head_line)
+        };
+        // Keep the head within the longest line, so it doesn't take as many
+        // steps back to shrink the block as it was moved past the line ends.
+        let max_x = (start_line..=end_line)
+            .map(|line| {
+                self.line_x_of_offset(rope_text.line_end_offset(line, true))
+                    .1
+            })
+            .fold(0.0, f64::max);
+        let head_x = (block.head_x + columns as f64 * self.char_width())
+            .clamp(0.0, max_x.max(block.anchor_x));
+
+        self.set_block_selection(BlockSelection {
+            head_line,
+            head_x,
+            ..block
+        });
+    }
+
+    /// Select one region per line of `block`.
+    fn set_block_selection(&self, mut block: BlockSelection) {
+        let (start_line, end_line) = if block.head_line < block.anchor_line {
+            (block.head_line, block.anchor_line)
+        } else {
+            (block.anchor_line, block.head_line)
+        };
+        let mut selection = Selection::new();
+        for line in start_line..=end_line {
+            let start = self.offset_of_line_x(line, block.anchor_x);
+            let end = self.offset_of_line_x(line, block.head_x);
+            // Lines ending before a block that is wider than a column are left
+            // out, as they'd only get a caret at their end.
+            if start == end
+                && block.anchor_x != block.head_x
+                && line != block.head_line
+            {
+                continue;
+            }
+            selection.add_region(SelRegion::new(start, end, None));
+        }
+        block.regions = selection
+            .regions()
+            .iter()
+            .map(|r| (r.start, r.end))
+            .collect();
+        self.cursor().update(|cursor| cursor.set_insert(selection));
+        self.block_selection.set(Some(block));
+    }
+
+    /// The line of `offset` and its horizontal position.
+    fn line_x_of_offset(&self, offset: usize) -> (usize, f64) {
+        let line = self.rope_text().line_of_offset(offset);
+        let (point, _) = self
+            .editor
+            .points_of_offset(offset, CursorAffinity::Forward);
+        (line, point.x)
+    }
+
+    /// The offset in `line` closest to the horizontal position `x`.
+    fn offset_of_line_x(&self, line: usize, x: f64) -> usize {
+        let line_start = self.rope_text().offset_of_line(line);
+        let (top, bottom) = self
+            .editor
+            .points_of_offset(line_start, CursorAffinity::Forward);
+        let point = Point::new(x, (top.y + bottom.y) / 2.0);
+        self.editor.offset_of_point(Mode::Insert, point).0
+    }
+
+    /// The width of a character, measured again only once the config changes
+    /// as it's needed on every step of a column selection.
+    fn char_width(&self) -> f64 {
+        let config = self.common.config.get_untracked();
+        match self.char_width.get() {
+            Some((id, char_width)) if id == config.id => char_width,
+            _ => {
+                let char_width = config.editor.char_width();
+                self.char_width.set(Some((config.id, char_width)));
+                char_width
+            }
+        }
+    }
+
     #[instrument]
     pub fn pointer_up(&self, pointer_event: &PointerInputEvent) {
+        if let Some(drag) = self.common.text_drag.get_untracked() {
+            self.common.text_drag.set(None);
+            self.drop_text(drag, pointer_event);
+        }
         self.editor.pointer_up(pointer_event);
     }
 
     #[instrument]
     pub fn pointer_leave(&self) {
         self.common.mouse_hover_timer.set(TimerToken::INVALID);
+        self.pointer_offset.set(None);
+        self.update_hovered_link();
+    }
+
+    /// Underline the document link under the pointer while the modifier for
+    /// following links is held.
+    pub fn update_hovered_link(&self) {
+        let held = self.common.window_common.link_modifier_held.get_untracked();
+        let link = self
+            .pointer_offset
+            .get_untracked()
+            .filter(|_| held)
+            .and_then(|offset| self.doc().document_link_at(offset))
+            .map(|(_, start, end, _)| (start, end));
+        if self.hovered_link.get_untracked() != link {
+            self.hovered_link.set(link);
+        }
+    }
+
+    /// Open the target of the document link at `offset`, resolving the link
+    /// first if the server left its target out. Returns whether there is a
+    /// link there.
+    fn open_document_link(&self, offset: usize) -> bool {
+        let Some((plugin_id, _, _, link)) = self.doc().document_link_at(offset)
+        else {
+            return false;
+        };
+
+        if let Some(target) = link.target {
+            self.open_link_target(target);
+            return true;
+        }
+        let Some(plugin_id) = plugin_id else {
+            return true;
+        };
+        let editor = self.clone();
+        let send = create_ext_action(self.scope, move |target| {
+            editor.open_link_target(target);
+        });
+        self.common
+            .proxy
+            .document_link_resolve(plugin_id, link, move |result| {
+                if let Ok(ProxyResponse::DocumentLinkResolveResponse { link }) =
+                    result
+                {
+                    if let Some(target) = link.target {
+                        send(target);
+                    }
+                }
+            });
+        true
+    }
+
+    /// Open a file target of a document link in an editor, at the line of its
+    /// `#L<line>` fragment if it has one, and a web target in the browser.
+    /// Targets with any other scheme are ignored.
+    fn open_link_target(&self, target: Url) {
+        let Ok(path) = target.to_file_path() else {
+            if markdown::is_external_link(
+                target.as_str(),
+                markdown::WEB_LINK_SCHEMES,
+            ) {
+                self.common
+                    .internal_command
+                    .send(InternalCommand::OpenWebUri {
+                        uri: target.to_string(),
+                    });
+            } else {
+                tracing::warn!("Ignored the document link to {target}");
+            }
+            return;
+        };
+        let line = target
+            .fragment()
+            .and_then(|fragment| fragment.strip_prefix('L'))
+            .and_then(|line| line.split(',').next()?.parse::<usize>().ok());
+        self.common
+            .internal_command
+            .send(InternalCommand::JumpToLocation {
+                location: EditorLocation {
+                    path,
+                    position: line
+                        .map(|line| EditorPosition::Line(line.saturating_sub(1))),
+                    scroll_offset: None,
+                    ignore_unconfirmed: true,
+                    same_editor_tab: false,
+                },
+            });
     }
 
     #[instrument]
@@ -2901,7 +5098,7 @@ fn right_click(&self, pointer_event: &PointerInputEvent) {
             if path
                 .as_ref()
                 .and_then(|x| x.file_name().and_then(|x| x.to_str()))
-                .map(|x| x == "run.toml")
+                .map(|x| x == RUN_TOML || x == LAUNCH_TOML)
                 .unwrap_or_default()
             {
                 vec![
@@ -2939,6 +5136,12 @@ fn right_click(&self, pointer_event: &PointerInputEvent) {
                     Some(CommandKind::Workbench(
                         LapceWorkbenchCommand::GoToImplementation,
                     )),
+                    Some(CommandKind::Workbench(
+                        LapceWorkbenchCommand::PeekDefinition,
+                    )),
+                    Some(CommandKind::Workbench(
+                        LapceWorkbenchCommand::PeekReferences,
+                    )),
                     Some(CommandKind::Focus(FocusCommand::Rename)),
                     Some(CommandKind::Workbench(
                         LapceWorkbenchCommand::RunInTerminal,
@@ -3042,13 +5245,16 @@ pub fn visual_line(&self, line: usize) -> usize {
             EditorViewKind::Normal => line,
             EditorViewKind::Diff(diff) => {
                 let is_right = diff.is_right;
+                let inline = diff.inline.is_some();
                 let mut last_change: Option<&DiffLines> = None;
                 let mut visual_line = 0;
                 let mut changes = diff.changes.iter().peekable();
                 while let Some(change) = changes.next() {
                     match (is_right, change) {
                         (true, DiffLines::Left(range)) => {
-                            if let Some(DiffLines::Right(_)) = changes.peek() {
+                            if let Some(DiffLines::Right(_)) =
+                                changes.peek().filter(|_| !inline)
+                            {
                             } else {
                                 visual_line += range.len();
                             }
@@ -3069,7 +5275,7 @@ pub fn visual_line(&self, line: usize) -> usize {
                                 return visual_line + line - range.start;
                             }
                             visual_line += range.len();
-                            if is_right {
+                            if is_right && !inline {
                                 if let Some(DiffLines::Left(r)) = last_change {
                                     let len = r.len() - r.len().min(range.len());
                                     if len > 0 {
@@ -3126,6 +5332,7 @@ pub fn actual_line(&self, visual_line: usize, bottom_affinity: bool) -> usize {
             EditorViewKind::Normal => visual_line,
             EditorViewKind::Diff(diff) => {
                 let is_right = diff.is_right;
+                let inline = diff.inline.is_some();
                 let mut actual_line: usize = 0;
                 let mut current_visual_line = 0;
                 let mut last_change: Option<&DiffLines> = None;
@@ -3133,7 +5340,9 @@ pub fn actual_line(&self, visual_line: usize, bottom_affinity: bool) -> usize {
                 while let Some(change) = changes.next() {
                     match (is_right, change) {
                         (true, DiffLines::Left(range)) => {
-                            if let Some(DiffLines::Right(_)) = changes.peek() {
+                            if let Some(DiffLines::Right(_)) =
+                                changes.peek().filter(|_| !inline)
+                            {
                             } else {
                                 current_visual_line += range.len();
                                 if current_visual_line >= visual_line {
@@ -3167,7 +5376,7 @@ pub fn actual_line(&self, visual_line: usize, bottom_affinity: bool) -> usize {
                             }
                             current_visual_line += len;
                             actual_line += len;
-                            if is_right {
+                            if is_right && !inline {
                                 if let Some(DiffLines::Left(r)) = last_change {
                                     let len = r.len() - r.len().min(range.len());
                                     if len > 0 {
@@ -3257,6 +5466,12 @@ fn check_condition(&self, condition: Condition) -> bool {
                 self.on_screen_find.with_untracked(|f| f.active)
             }
             Condition::InSnippet => self.snippet.with_untracked(|s| s.is_some()),
+            Condition::MotionModePending => {
+                self.cursor().with_untracked(|c| c.motion_mode.is_some())
+            }
+            Condition::SignatureHelpFocus => {
+                self.common.signature.is_shown_in(self.id())
+            }
             Condition::EditorFocus => self
                 .doc()
                 .content
@@ -3320,7 +5535,67 @@ fn run_command(
             }
         }
 
+        if let Some(name) =
+            self.common.vim.with_untracked(|vim| vim.selected_register)
+        {
+            if let CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MotionMode(_) = &command.kind
+            {
+                return self.run_with_register(name, command, count, mods);
+            }
+        }
+
         match &command.kind {
+            crate::command::CommandKind::Workbench(
+                LapceWorkbenchCommand::SelectRegister,
+            ) => {
+                self.vim_char_action
+                    .set(Some(VimCharAction::SelectRegister));
+                CommandExecuted::Yes
+            }
+            crate::command::CommandKind::Workbench(
+                LapceWorkbenchCommand::CreateMark,
+            ) => {
+                self.vim_char_action.set(Some(VimCharAction::CreateMark));
+                CommandExecuted::Yes
+            }
+            crate::command::CommandKind::Workbench(
+                LapceWorkbenchCommand::GoToMark,
+            ) => {
+                self.vim_char_action
+                    .set(Some(VimCharAction::GoToMark { exact: false }));
+                CommandExecuted::Yes
+            }
+            crate::command::CommandKind::Workbench(
+                LapceWorkbenchCommand::GoToMarkPosition,
+            ) => {
+                self.vim_char_action
+                    .set(Some(VimCharAction::GoToMark { exact: true }));
+                CommandExecuted::Yes
+            }
+            // Run here rather than by the workbench to keep their count.
+            crate::command::CommandKind::Workbench(
+                LapceWorkbenchCommand::ReplayMacro,
+            ) => {
+                self.common
+                    .internal_command
+                    .send(InternalCommand::ReplayMacro {
+                        name: None,
+                        count: count.unwrap_or(1),
+                    });
+                CommandExecuted::Yes
+            }
+            crate::command::CommandKind::Workbench(
+                LapceWorkbenchCommand::ReplayMacroRegister,
+            ) => {
+                let macro_register =
+                    self.common.keypress.with_untracked(|k| k.macro_register);
+                macro_register.set(Some(MacroRegisterAction::Replay {
+                    count: count.unwrap_or(1),
+                }));
+                CommandExecuted::Yes
+            }
             crate::command::CommandKind::Workbench(_) => CommandExecuted::No,
             crate::command::CommandKind::Edit(cmd) => self.run_edit_command(cmd),
             crate::command::CommandKind::Move(cmd) => {
@@ -3363,6 +5638,10 @@ fn expect_char(&self) -> bool {
         } else {
             self.inline_find.with_untracked(|f| f.is_some())
                 || self.on_screen_find.with_untracked(|f| f.active)
+                || self.common.keypress.with_untracked(|k| {
+                    k.macro_register.with_untracked(Option::is_some)
+                })
+                || self.vim_char_action.with_untracked(Option::is_some)
         }
     }
 
@@ -3380,15 +5659,50 @@ fn receive_char(&self, c: &str) {
                 );
             }
         } else {
+            let macro_register =
+                self.common.keypress.with_untracked(|k| k.macro_register);
+            if let Some(action) = macro_register.try_update(Option::take).flatten() {
+                self.macro_register(action, c);
+                return;
+            }
+            if let Some(action) =
+                self.vim_char_action.try_update(Option::take).flatten()
+            {
+                self.vim_char(action, c);
+                return;
+            }
+
             // normal editor receive char
             if self.get_mode() == Mode::Insert {
+                if self.is_completion_commit_character(c) {
+                    self.select_completion_with(Some(c.to_string()));
+                    return;
+                }
                 let mut cursor = self.cursor().get_untracked();
+                let is_word = c.chars().all(|c| {
+                    c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')
+                });
+                let linked = if is_word {
+                    self.add_linked_editing_carets(&mut cursor, false, false)
+                } else {
+                    None
+                };
                 let deltas = self.doc().do_insert(
                     &mut cursor,
                     c,
                     &self.common.config.get_untracked(),
                 );
                 self.cursor().set(cursor);
+                match linked {
+                    Some(index) => self.remove_linked_editing_carets(index),
+                    None if self
+                        .linked_editing_ranges
+                        .with_untracked(Option::is_some) =>
+                    {
+                        self.linked_editing_ranges.set(None)
+                    }
+                    None => {}
+                }
 
                 if !c
                     .chars()
@@ -3404,6 +5718,7 @@ fn receive_char(&self, c: &str) {
                 );
 
                 self.apply_deltas(&deltas);
+                self.update_signature(Some(c));
             } else if let Some(direction) = self.inline_find.get_untracked() {
                 self.inline_find(direction.clone(), c);
                 self.last_inline_find.set(Some((direction, c.to_string())));
@@ -3460,6 +5775,161 @@ pub fn track(&self) {
     }
 }
 
+/// Whether the modifier for following links is held, Cmd on macOS and Ctrl
+/// elsewhere.
+pub fn link_modifier(modifiers: Modifiers) -> bool {
+    if cfg!(target_os = "macos") {
+        modifiers.meta()
+    } else {
+        modifiers.control()
+    }
+}
+
+/// The offsets of the spaces and tabs at the end of `line`, if it has any.
+pub fn trailing_whitespace(
+    text: &impl RopeText,
+    line: usize,
+) -> Option<(usize, usize)> {
+    let start = text.offset_of_line(line);
+    let end = text.line_end_offset(line, true);
+    let content = text.slice_to_cow(start..end);
+    let trimmed = start + content.trim_end_matches([' ', '\t']).len();
+    (trimmed < end).then_some((trimmed, end))
+}
+
+/// The indentation of a blank `line`, following the last line above it that
+/// isn't blank, one level deeper after an opening bracket or a colon.
+fn new_line_indent(
+    text: &impl RopeText,
+    line: usize,
+    indent_unit: &str,
+    tab_width: usize,
+) -> usize {
+    let Some(content) = (0..line)
+        .rev()
+        .map(|line| text.line_content(line))
+        .find(|content| !content.trim().is_empty())
+    else {
+        return 0;
+    };
+    let indent = indent_width(&content, tab_width);
+    if content.trim_end().ends_with(['{', '(', '[', ':']) {
+        indent + indent_width(indent_unit, tab_width)
+    } else {
+        indent
+    }
+}
+
+/// The first and last lines of each selected region, merged where they overlap,
+/// or all the lines but the empty one after a final line ending when nothing is
+/// selected.
+fn selected_lines(
+    text: &impl RopeText,
+    selection: &Selection,
+) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = selection
+        .regions()
+        .iter()
+        .filter(|region| !region.is_caret())
+        .map(|region| {
+            let start_line = text.line_of_offset(region.min());
+            let mut end_line = text.line_of_offset(region.max());
+            // A selection ending at the start of a line doesn't include it.
+            if end_line > start_line && text.offset_of_line(end_line) == region.max()
+            {
+                end_line -= 1;
+            }
+            (start_line, end_line)
+        })
+        .collect();
+    if ranges.is_empty() {
+        let mut last_line = text.last_line();
+        if last_line > 0 && text.offset_of_line(last_line) == text.len() {
+            last_line -= 1;
+        }
+        return vec![(0, last_line)];
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => {
+                *last_end = (*last_end).max(end);
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// The start of the line comment `text` begins with, to continue it on the next
+/// line: the comment `token`, repetitions of its last character and a `!` marking
+/// doc comments like `///` and `//!`, and a space after them.
+fn comment_prefix<'a>(text: &'a str, token: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(token)?;
+    let last = token.chars().last()?;
+    let mut marker = rest.len() - rest.trim_start_matches(last).len();
+    // A single character token followed by `!` is more likely a shebang.
+    if token.len() > 1 && rest[marker..].starts_with('!') {
+        marker += 1;
+    }
+    let space = usize::from(rest[marker..].starts_with(' '));
+    Some(&text[..token.len() + marker + space])
+}
+
+/// The offsets of the quotes around `offset`, pairing the quotes of its line from
+/// the start of the line.
+fn enclosing_quotes(text: &impl RopeText, offset: usize) -> Option<(usize, usize)> {
+    let line = text.line_of_offset(offset);
+    let line_start = text.offset_of_line(line);
+    let mut open: Option<(usize, char)> = None;
+    let mut escaped = false;
+    for (i, c) in text.line_content(line).char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (open, c) {
+            (Some(_), '\\') => escaped = true,
+            (Some((start, quote)), c) if c == quote => {
+                let end = line_start + i;
+                if (start..=end).contains(&offset) {
+                    return Some((start, end));
+                }
+                open = None;
+            }
+            (None, '"' | '\'' | '`') => open = Some((line_start + i, c)),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Jump to the only one of `locations`, or pick one of them in the palette.
+fn jump_to_locations(
+    internal_command: Listener<InternalCommand>,
+    locations: Vec<Location>,
+) {
+    let mut locations: Vec<EditorLocation> = locations
+        .into_iter()
+        .map(|l| EditorLocation {
+            path: path_from_url(&l.uri),
+            position: Some(EditorPosition::Position(l.range.start)),
+            scroll_offset: None,
+            ignore_unconfirmed: false,
+            same_editor_tab: false,
+        })
+        .collect();
+    if locations.len() > 1 {
+        internal_command.send(InternalCommand::PaletteReferences {
+            references: locations,
+        });
+    } else if let Some(location) = locations.pop() {
+        internal_command.send(InternalCommand::JumpToLocation { location });
+    }
+}
+
 /// Checks if completion should be triggered if the received command
 /// is one that inserts whitespace or deletes whitespace
 fn show_completion(
@@ -3497,6 +5967,50 @@ fn show_completion(
     show_completion
 }
 
+/// The search for the occurrences the multiple cursor commands select, with
+/// the case sensitivity and whole word matching configured for them rather
+/// than the settings of the find.
+struct MulticursorSearch {
+    search_string: FindSearchString,
+    case_matching: CaseMatching,
+    whole_words: bool,
+}
+
+impl MulticursorSearch {
+    /// Search for `search_str`, which the find highlights too.
+    fn new(find: &Find, search_str: &str, config: &LapceConfig) -> Self {
+        find.set_find(search_str);
+        let case_sensitive =
+            config.editor.multicursor_case_sensitive || find.case_sensitive(false);
+        Self {
+            search_string: FindSearchString::new(search_str, false, case_sensitive),
+            case_matching: if case_sensitive {
+                CaseMatching::Exact
+            } else {
+                CaseMatching::CaseInsensitive
+            },
+            whole_words: config.editor.multicursor_whole_words,
+        }
+    }
+
+    fn next(
+        &self,
+        text: &Rope,
+        offset: usize,
+        wrap: bool,
+    ) -> Option<(usize, usize)> {
+        Find::next_match(
+            text,
+            &self.search_string,
+            self.case_matching,
+            self.whole_words,
+            offset,
+            false,
+            wrap,
+        )
+    }
+}
+
 fn show_inline_completion(cmd: &EditCommand) -> bool {
     matches!(
         cmd,
@@ -3618,6 +6132,7 @@ pub(crate) fn compute_screen_lines(
             let mut last_change: Option<&DiffLines> = None;
             let mut changes = diff_info.changes.iter().peekable();
             let is_right = diff_info.is_right;
+            let inline = diff_info.inline.is_some();
 
             let line_y = |info: VLineInfo<()>, vline_y: usize| -> usize {
                 vline_y.saturating_sub(info.rvline.line_index * line_height)
@@ -3626,13 +6141,20 @@ pub(crate) fn compute_screen_lines(
             while let Some(change) = changes.next() {
                 match (is_right, change) {
                     (true, DiffLines::Left(range)) => {
-                        if let Some(DiffLines::Right(_)) = changes.peek() {
+                        if let Some(DiffLines::Right(_)) =
+                            changes.peek().filter(|_| !inline)
+                        {
                         } else {
                             let len = range.len();
                             diff_sections.push(DiffSection {
                                 y_idx,
                                 height: len,
-                                kind: DiffSectionKind::NoCode,
+                                // The removed lines are painted in their room.
+                                kind: if inline {
+                                    DiffSectionKind::Removed
+                                } else {
+                                    DiffSectionKind::NoCode
+                                },
                             });
                             y_idx += len;
                         }
@@ -3672,7 +6194,7 @@ pub(crate) fn compute_screen_lines(
                         y_idx += height;
 
                         if y_idx < min_vline.get() {
-                            if is_right {
+                            if is_right && !inline {
                                 if let Some(DiffLines::Left(r)) = last_change {
                                     // TODO: count vline count in the other editor since this is skipping an amount dependent on those vlines
                                     let len = r.len() - r.len().min(range.len());
@@ -3729,7 +6251,7 @@ pub(crate) fn compute_screen_lines(
                             }
                         }
 
-                        if is_right {
+                        if is_right && !inline {
                             if let Some(DiffLines::Left(r)) = last_change {
                                 // TODO: count vline count in the other editor since this is skipping an amount dependent on those vlines
                                 let len = r.len() - r.len().min(range.len());
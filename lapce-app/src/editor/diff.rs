@@ -1,10 +1,11 @@
-use std::{rc::Rc, sync::atomic};
+use std::{collections::HashMap, ops::Range, rc::Rc, sync::atomic};
 
 use floem::{
     event::EventListener,
     ext_event::create_ext_action,
     reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
     style::CursorStyle,
+    text::{Attrs, AttrsList, TextLayout},
     views::{
         clip, dyn_stack, editor::id::EditorId, empty, label, stack, svg, Decorators,
     },
@@ -12,7 +13,7 @@
 };
 use lapce_core::buffer::{
     diff::{expand_diff_lines, rope_diff, DiffExpand, DiffLines},
-    rope_text::RopeText,
+    rope_text::{RopeText, RopeTextRef},
 };
 use lapce_rpc::{buffer::BufferId, proxy::ProxyResponse};
 use lapce_xi_rope::Rope;
@@ -22,6 +23,8 @@
 use crate::{
     config::{color::LapceColor, icon::LapceIcons},
     doc::{Doc, DocContent},
+    editor::location::EditorPosition,
+    hunk_peek::hunks,
     id::{DiffEditorId, EditorTabId},
     main_split::{Editors, MainSplitData},
     wave::wave_box,
@@ -32,12 +35,95 @@
 pub struct DiffInfo {
     pub is_right: bool,
     pub changes: Vec<DiffLines>,
+    /// The columns that changed within the lines of this side that replace
+    /// lines of the other side, by line.
+    pub line_changes: HashMap<usize, Range<usize>>,
+    /// The lines removed from the left side, when the right side shows them
+    /// above the lines that replace them rather than next to them.
+    pub inline: Option<InlineDiff>,
+}
+
+impl DiffInfo {
+    /// The blocks of lines removed from the left that the right side of an
+    /// inline diff shows, in the order of the room left for them.
+    pub fn inline_removed_lines(&self) -> Vec<Range<usize>> {
+        if !self.is_right || self.inline.is_none() {
+            return Vec::new();
+        }
+        self.changes
+            .iter()
+            .filter_map(|change| match change {
+                DiffLines::Left(range) => Some(range.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Lay out the lines removed from the left that the right side of an
+    /// inline diff shows, by block.
+    pub fn inline_removed_layouts(&self, attrs: Attrs) -> Vec<Vec<RemovedLine>> {
+        let Some(inline) = self.inline.as_ref() else {
+            return Vec::new();
+        };
+        let text = RopeTextRef::new(&inline.text);
+        self.inline_removed_lines()
+            .into_iter()
+            .map(|lines| {
+                lines
+                    .map(|line| {
+                        let content = text.line_content(line);
+                        let mut text_layout = TextLayout::new();
+                        text_layout.set_text(
+                            content.trim_end_matches(['\r', '\n']),
+                            AttrsList::new(attrs),
+                        );
+                        let change = inline.line_changes.get(&line).map(|range| {
+                            (
+                                text_layout.hit_position(range.start).point.x,
+                                text_layout.hit_position(range.end).point.x,
+                            )
+                        });
+                        RemovedLine {
+                            text_layout,
+                            change,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// A line removed from the left side of an inline diff, laid out to be painted
+/// within the right side.
+pub struct RemovedLine {
+    pub text_layout: TextLayout,
+    /// The horizontal extent of the columns that changed, if any did.
+    pub change: Option<(f64, f64)>,
+}
+
+/// The left side of an inline diff, whose removed lines are shown within the
+/// right side.
+#[derive(Clone)]
+pub struct InlineDiff {
+    pub text: Rope,
+    /// The columns that changed within the removed lines, by line.
+    pub line_changes: HashMap<usize, Range<usize>>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DiffEditorInfo {
     pub left_content: DocContent,
     pub right_content: DocContent,
+    /// Whether the diff is shown inline rather than side by side.
+    #[serde(default)]
+    pub inline: bool,
+    /// The texts of the sides which can't be read back, like the clipboard
+    /// compared with, as they were when the diff was saved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub left_text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub right_text: Option<String>,
 }
 
 impl DiffEditorInfo {
@@ -53,7 +139,7 @@ pub fn to_data(
         let new_doc = {
             let data = data.clone();
             let common = data.common.clone();
-            move |content: &DocContent| match content {
+            move |content: &DocContent, text: Option<&String>| match content {
                 DocContent::File { path, .. } => {
                     let (doc, _) = data.get_doc(path.clone(), None);
                     doc
@@ -70,31 +156,48 @@ pub fn to_data(
                     );
                     let doc = Rc::new(doc);
 
-                    {
+                    if history.is_snapshot() {
+                        doc.init_content(Rope::from(
+                            text.map(String::as_str).unwrap_or_default(),
+                        ));
+                    } else {
                         let doc = doc.clone();
                         let send = create_ext_action(cx, move |result| {
-                            if let Ok(ProxyResponse::BufferHeadResponse {
-                                content,
-                                ..
-                            }) = result
+                            if let Ok(
+                                ProxyResponse::BufferHeadResponse {
+                                    content, ..
+                                }
+                                | ProxyResponse::ReadFileResponse { content },
+                            ) = result
                             {
                                 doc.init_content(Rope::from(content));
                             }
                         });
-                        common.proxy.get_buffer_head(
-                            history.path.clone(),
-                            move |result| {
+                        let path = history.path.clone();
+                        if let Some(timestamp) = history.local_timestamp() {
+                            common.proxy.local_history_content(
+                                path,
+                                timestamp,
+                                move |result| {
+                                    send(result);
+                                },
+                            );
+                        } else {
+                            common.proxy.get_buffer_head(path, move |result| {
                                 send(result);
-                            },
-                        );
+                            });
+                        }
                     }
 
                     doc
                 }
-                DocContent::Scratch { name, .. } => {
+                DocContent::Scratch {
+                    name, read_only, ..
+                } => {
                     let doc_content = DocContent::Scratch {
                         id: BufferId::next(),
                         name: name.to_string(),
+                        read_only: *read_only,
                     };
                     let doc = Doc::new_content(
                         cx,
@@ -111,8 +214,8 @@ pub fn to_data(
             }
         };
 
-        let left_doc = new_doc(&self.left_content);
-        let right_doc = new_doc(&self.right_content);
+        let left_doc = new_doc(&self.left_content, self.left_text.as_ref());
+        let right_doc = new_doc(&self.right_content, self.right_text.as_ref());
 
         let diff_editor_data = DiffEditorData::new(
             cx,
@@ -123,6 +226,7 @@ pub fn to_data(
             data.editors,
             data.common.clone(),
         );
+        diff_editor_data.inline.set(self.inline);
 
         data.diff_editors.update(|diff_editors| {
             diff_editors.insert(diff_editor_id, diff_editor_data.clone());
@@ -141,6 +245,9 @@ pub struct DiffEditorData {
     pub right: EditorData,
     pub confirmed: RwSignal<bool>,
     pub focus_right: RwSignal<bool>,
+    /// Whether the right side is shown alone, with the lines removed from the
+    /// left above the lines that replace them.
+    pub inline: RwSignal<bool>,
 }
 
 impl DiffEditorData {
@@ -176,6 +283,7 @@ pub fn new(
             right,
             confirmed,
             focus_right: cx.create_rw_signal(true),
+            inline: cx.create_rw_signal(false),
         };
 
         data.listen_diff_changes();
@@ -184,9 +292,23 @@ pub fn new(
     }
 
     pub fn diff_editor_info(&self) -> DiffEditorInfo {
+        let [(left_content, left_text), (right_content, right_text)] =
+            [&self.left, &self.right].map(|editor| {
+                let doc = editor.doc();
+                let content = doc.content.get_untracked();
+                let text = matches!(
+                    &content,
+                    DocContent::History(history) if history.is_snapshot()
+                )
+                .then(|| doc.buffer.with_untracked(|buffer| buffer.to_string()));
+                (content, text)
+            });
         DiffEditorInfo {
-            left_content: self.left.doc().content.get_untracked(),
-            right_content: self.right.doc().content.get_untracked(),
+            left_content,
+            right_content,
+            inline: self.inline.get_untracked(),
+            left_text,
+            right_text,
         }
     }
 
@@ -217,6 +339,7 @@ pub fn copy(
             id: diff_editor_id,
             editor_tab_id: cx.create_rw_signal(editor_tab_id),
             focus_right: cx.create_rw_signal(true),
+            inline: cx.create_rw_signal(self.inline.get_untracked()),
             left,
             right,
             confirmed,
@@ -247,7 +370,9 @@ fn listen_diff_changes(&self) {
             })
         };
 
+        let inline = self.inline;
         cx.create_effect(move |_| {
+            let inline = inline.get();
             let (_, left_rev) = left_doc_rev.get();
             let (left_editor_view, left_doc) = (left.kind, left.doc());
             let (left_atomic_rev, left_rope) =
@@ -262,12 +387,14 @@ fn listen_diff_changes(&self) {
                     (buffer.atomic_rev(), buffer.text().clone())
                 });
 
+            type LineChanges = HashMap<usize, Range<usize>>;
             let send = {
                 let right_atomic_rev = right_atomic_rev.clone();
-                create_ext_action(cx, move |changes: Option<Vec<DiffLines>>| {
-                    let changes = if let Some(changes) = changes {
-                        changes
-                    } else {
+                let left_text = left_rope.clone();
+                create_ext_action(cx, move |result: Option<(Vec<DiffLines>, [LineChanges; 2])>| {
+                    let Some((changes, [left_line_changes, right_line_changes])) =
+                        result
+                    else {
                         return;
                     };
 
@@ -283,26 +410,147 @@ fn listen_diff_changes(&self) {
                     left_editor_view.set(EditorViewKind::Diff(DiffInfo {
                         is_right: false,
                         changes: changes.clone(),
+                        line_changes: left_line_changes.clone(),
+                        inline: None,
                     }));
                     right_editor_view.set(EditorViewKind::Diff(DiffInfo {
                         is_right: true,
                         changes,
+                        line_changes: right_line_changes,
+                        inline: inline.then(|| InlineDiff {
+                            text: left_text,
+                            line_changes: left_line_changes,
+                        }),
                     }));
                 })
             };
 
             rayon::spawn(move || {
                 let changes = rope_diff(
-                    left_rope,
-                    right_rope,
+                    left_rope.clone(),
+                    right_rope.clone(),
                     right_rev,
                     right_atomic_rev.clone(),
                     Some(3),
                 );
-                send(changes);
+                send(changes.map(|changes| {
+                    let line_changes =
+                        line_changes(&changes, &left_rope, &right_rope);
+                    (changes, line_changes)
+                }));
             });
         });
     }
+
+    /// Switch between showing the sides next to each other and showing the
+    /// right one alone, with the removed lines within it.
+    pub fn toggle_inline(&self) {
+        let inline = !self.inline.get_untracked();
+        if inline {
+            // The left side is hidden.
+            self.focus_right.set(true);
+        }
+        self.inline.set(inline);
+    }
+
+    /// Move the cursor of the focused side to the next change after it, or the
+    /// previous one before it, wrapping around at the ends.
+    pub fn go_to_change(&self, forward: bool) {
+        let editor = if self.focus_right.get_untracked() {
+            &self.right
+        } else {
+            &self.left
+        };
+        let EditorViewKind::Diff(diff_info) = editor.kind.get_untracked() else {
+            return;
+        };
+        let changes = diff_info.changes.iter().cloned().collect();
+        let lines: Vec<usize> = hunks(&changes)
+            .into_iter()
+            .map(|hunk| {
+                if diff_info.is_right {
+                    hunk.buffer.start
+                } else {
+                    hunk.head.start
+                }
+            })
+            .collect();
+
+        let offset = editor.cursor().with_untracked(|c| c.offset());
+        let line = editor
+            .doc()
+            .buffer
+            .with_untracked(|buffer| buffer.line_of_offset(offset));
+        let target = if forward {
+            lines.iter().find(|l| **l > line).or_else(|| lines.first())
+        } else {
+            lines
+                .iter()
+                .rev()
+                .find(|l| **l < line)
+                .or_else(|| lines.last())
+        };
+        if let Some(target) = target {
+            editor.go_to_position(EditorPosition::Line(*target), None, None);
+        }
+    }
+}
+
+/// The columns that changed within each pair of lines where a line of the
+/// left replaced a line of the right, for the left and the right lines.
+fn line_changes(
+    changes: &[DiffLines],
+    left: &Rope,
+    right: &Rope,
+) -> [HashMap<usize, Range<usize>>; 2] {
+    let (left, right) = (RopeTextRef::new(left), RopeTextRef::new(right));
+    let changes = changes.iter().cloned().collect();
+    let mut left_changes = HashMap::new();
+    let mut right_changes = HashMap::new();
+    for hunk in hunks(&changes) {
+        for (left_line, right_line) in hunk.head.zip(hunk.buffer) {
+            let left_content = left.line_content(left_line);
+            let right_content = right.line_content(right_line);
+            if let Some((left_range, right_range)) = changed_range(
+                left_content.trim_end_matches(['\r', '\n']),
+                right_content.trim_end_matches(['\r', '\n']),
+            ) {
+                left_changes.insert(left_line, left_range);
+                right_changes.insert(right_line, right_range);
+            }
+        }
+    }
+    [left_changes, right_changes]
+}
+
+/// The ranges of `left` and `right` between their common prefix and suffix,
+/// unless the lines have nothing in common.
+pub fn changed_range(
+    left: &str,
+    right: &str,
+) -> Option<(Range<usize>, Range<usize>)> {
+    let prefix: usize = left
+        .chars()
+        .zip(right.chars())
+        .take_while(|(l, r)| l == r)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    let suffix: usize = left[prefix..]
+        .chars()
+        .rev()
+        .zip(right[prefix..].chars().rev())
+        .take_while(|(l, r)| l == r)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    if prefix == 0 && suffix == 0 {
+        return None;
+    }
+    let left_range = prefix..left.len() - suffix;
+    let right_range = prefix..right.len() - suffix;
+    if left_range.is_empty() && right_range.is_empty() {
+        return None;
+    }
+    Some((left_range, right_range))
 }
 
 struct DiffShowMoreSection {
@@ -331,6 +579,7 @@ pub fn diff_show_more_section_view(
             let min_line = (viewport.y0 / line_height).floor() as usize;
             let max_line = (viewport.y1 / line_height).ceil() as usize;
 
+            let inline = diff_info.inline.is_some();
             let mut visual_line = 0;
             let mut last_change: Option<&DiffLines> = None;
             let mut changes = diff_info.changes.iter().peekable();
@@ -338,7 +587,9 @@ pub fn diff_show_more_section_view(
             while let Some(change) = changes.next() {
                 match change {
                     DiffLines::Left(range) => {
-                        if let Some(DiffLines::Right(_)) = changes.peek() {
+                        if let Some(DiffLines::Right(_)) =
+                            changes.peek().filter(|_| !inline)
+                        {
                         } else {
                             let len = range.len();
                             visual_line += len;
@@ -348,7 +599,9 @@ pub fn diff_show_more_section_view(
                         let len = range.len();
                         visual_line += len;
 
-                        if let Some(DiffLines::Left(r)) = last_change {
+                        if let Some(DiffLines::Left(r)) =
+                            last_change.filter(|_| !inline)
+                        {
                             let len = r.len() - r.len().min(range.len());
                             if len > 0 {
                                 visual_line += len;
@@ -545,3 +798,46 @@ pub fn diff_show_more_section_view(
     .style(|s| s.absolute().flex_col().size_pct(100.0, 100.0))
     .debug_name("Diff Show More Section")
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lapce_core::buffer::diff::{DiffBothInfo, DiffLines};
+    use lapce_xi_rope::Rope;
+
+    use super::{DiffInfo, InlineDiff};
+
+    #[test]
+    fn test_inline_removed_lines() {
+        let changes = vec![
+            DiffLines::Both(DiffBothInfo {
+                left: 0..10,
+                right: 0..10,
+                skip: Some(0..7),
+            }),
+            // Replaced by the next lines
+            DiffLines::Left(10..12),
+            DiffLines::Right(10..11),
+            DiffLines::Both(DiffBothInfo {
+                left: 12..14,
+                right: 11..13,
+                skip: None,
+            }),
+            DiffLines::Left(14..15),
+        ];
+        let mut diff_info = DiffInfo {
+            is_right: true,
+            changes,
+            line_changes: HashMap::new(),
+            inline: Some(InlineDiff {
+                text: Rope::from(""),
+                line_changes: HashMap::new(),
+            }),
+        };
+        assert_eq!(diff_info.inline_removed_lines(), vec![10..12, 14..15]);
+
+        diff_info.inline = None;
+        assert!(diff_info.inline_removed_lines().is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use floem::{
     context::PaintCx,
     peniko::kurbo::{Point, Rect, Size},
@@ -6,11 +8,16 @@
     Renderer, View, ViewId,
 };
 use im::HashMap;
-use lapce_core::{buffer::rope_text::RopeText, mode::Mode};
+use lapce_core::{
+    buffer::rope_text::RopeText, mode::Mode, syntax::SyntaxFoldingRange,
+};
 use serde::{Deserialize, Serialize};
 
 use super::{view::changes_colors_screen, EditorData};
-use crate::config::{color::LapceColor, LapceConfig};
+use crate::{
+    config::{color::LapceColor, LapceConfig},
+    perf::{self, PerfSubtree},
+};
 
 pub struct EditorGutterView {
     id: ViewId,
@@ -47,7 +54,7 @@ fn paint_head_changes(
         }
 
         let changes = e_data.doc().head_changes().get_untracked();
-        let line_height = config.editor.line_height() as f64;
+        let line_height = e_data.line_height() as f64;
         let gutter_padding_right = self.gutter_padding_right.get_untracked() as f64;
 
         let changes = changes_colors_screen(config, &e_data.editor, changes);
@@ -117,6 +124,7 @@ fn compute_layout(
         &mut self,
         _cx: &mut floem::context::ComputeLayoutCx,
     ) -> Option<floem::peniko::kurbo::Rect> {
+        perf::record_layout(PerfSubtree::Gutter);
         if let Some(width) = self.id.get_layout().map(|l| l.size.width as f64) {
             self.width = width;
         }
@@ -124,6 +132,7 @@ fn compute_layout(
     }
 
     fn paint(&mut self, cx: &mut floem::context::PaintCx) {
+        let _timer = perf::time_paint(PerfSubtree::Gutter);
         let viewport = self.editor.viewport().get_untracked();
         let cursor = self.editor.cursor();
         let screen_lines = self.editor.screen_lines();
@@ -133,7 +142,7 @@ fn paint(&mut self, cx: &mut floem::context::PaintCx) {
             self.editor.kind.with_untracked(|kind| kind.is_normal());
         let (offset, mode) = cursor.with_untracked(|c| (c.offset(), c.get_mode()));
         let config = config.get_untracked();
-        let line_height = config.editor.line_height() as f64;
+        let line_height = self.editor.line_height() as f64;
         let last_line = self.editor.editor.last_line();
         let current_line = self
             .editor
@@ -211,6 +220,37 @@ fn debug_name(&self) -> std::borrow::Cow<'static, str> {
 pub struct FoldedRanges(pub Vec<FoldedRange>);
 
 impl FoldingRanges {
+    /// Replace the ranges with `ranges`, keeping only the first of them
+    /// starting on a line. The ranges starting on the lines of the old ones
+    /// stay folded or unfolded as those were, and the others are folded when
+    /// `fold` says so.
+    pub fn replace(
+        &mut self,
+        ranges: impl IntoIterator<Item = FoldingRange>,
+        fold: impl Fn(&FoldingRange) -> bool,
+    ) {
+        let statuses: HashMap<u32, FoldingRangeStatus> = self
+            .0
+            .iter()
+            .map(|range| (range.start.line, range.status.clone()))
+            .collect();
+        let mut lines = HashSet::new();
+        let mut ranges: Vec<FoldingRange> = ranges
+            .into_iter()
+            .filter(|range| lines.insert(range.start.line))
+            .map(|mut range| {
+                range.status = match statuses.get(&range.start.line) {
+                    Some(status) => status.clone(),
+                    None if fold(&range) => FoldingRangeStatus::Fold,
+                    None => FoldingRangeStatus::Unfold,
+                };
+                range
+            })
+            .collect();
+        ranges.sort_by_key(|range| range.start.line);
+        self.0 = ranges;
+    }
+
     pub fn get_folded_range(&self) -> FoldedRanges {
         let mut range = Vec::new();
         let mut limit_line = 0;
@@ -302,6 +342,7 @@ pub struct FoldingRange {
     pub end: FoldingPosition,
     pub status: FoldingRangeStatus,
     pub collapsed_text: Option<String>,
+    pub kind: Option<FoldingRangeKind>,
 }
 
 impl FoldingRange {
@@ -314,18 +355,6 @@ pub fn from_lsp(value: lsp_types::FoldingRange) -> Self {
             kind,
             collapsed_text,
         } = value;
-        let status = if kind
-            .as_ref()
-            .map(|x| {
-                x == &lsp_types::FoldingRangeKind::Imports
-                // || x == &lsp_types::FoldingRangeKind::Comment
-            })
-            .unwrap_or_default()
-        {
-            FoldingRangeStatus::Fold
-        } else {
-            FoldingRangeStatus::Unfold
-        };
         Self {
             start: FoldingPosition {
                 line: start_line,
@@ -337,12 +366,87 @@ pub fn from_lsp(value: lsp_types::FoldingRange) -> Self {
                 character: end_character,
                 // kind: kind.map(|x| FoldingRangeKind::from(x)),
             },
-            status,
+            status: FoldingRangeStatus::Unfold,
             collapsed_text,
+            kind: kind.map(FoldingRangeKind::from),
+        }
+    }
+
+    pub fn from_syntax(value: SyntaxFoldingRange) -> Self {
+        Self {
+            start: FoldingPosition {
+                line: value.start_line as u32,
+                character: None,
+            },
+            end: FoldingPosition {
+                line: value.end_line as u32,
+                character: None,
+            },
+            status: FoldingRangeStatus::Unfold,
+            collapsed_text: None,
+            kind: value.is_comment.then_some(FoldingRangeKind::Comment),
         }
     }
 }
 
+/// The ranges between the marker comments starting and ending regions, as in
+/// `// region: Name`, `#region Name` and `<!-- #region -->`, up to the line of
+/// the end marker.
+pub fn region_folding_ranges<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+) -> Vec<FoldingRange> {
+    let mut starts = Vec::new();
+    let mut ranges = Vec::new();
+    for (line, text) in lines.into_iter().enumerate() {
+        let line = line as u32;
+        match region_marker(text.as_ref()) {
+            Some(true) => starts.push(line),
+            Some(false) => {
+                if let Some(start) = starts.pop() {
+                    ranges.push(FoldingRange {
+                        start: FoldingPosition {
+                            line: start,
+                            character: None,
+                        },
+                        end: FoldingPosition {
+                            line,
+                            character: None,
+                        },
+                        status: FoldingRangeStatus::Unfold,
+                        collapsed_text: None,
+                        kind: Some(FoldingRangeKind::Region),
+                    });
+                }
+            }
+            None => {}
+        }
+    }
+    ranges.sort_by_key(|range| range.start.line);
+    ranges
+}
+
+/// Whether the line is a marker starting a region, or one ending it.
+fn region_marker(line: &str) -> Option<bool> {
+    let line = line.trim_start();
+    let rest = ["//", "<!--", "/*", "--", "#"]
+        .iter()
+        .find_map(|prefix| line.strip_prefix(prefix))?
+        .trim_start();
+    let rest = rest.strip_prefix('#').unwrap_or(rest);
+    let rest = rest
+        .strip_prefix("pragma ")
+        .map(str::trim_start)
+        .unwrap_or(rest);
+    let (start, rest) = match rest.strip_prefix("endregion") {
+        Some(rest) => (false, rest),
+        None => (true, rest.strip_prefix("region")?),
+    };
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(start)
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Copy)]
 pub struct FoldingPosition {
     pub line: u32,
@@ -405,3 +509,45 @@ fn from(value: lsp_types::FoldingRangeKind) -> Self {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        region_folding_ranges, FoldingRangeKind, FoldingRangeStatus, FoldingRanges,
+    };
+
+    #[test]
+    fn test_region_folding_ranges() {
+        let lines = [
+            "// region: Outer",
+            "#region Inner",
+            "let region_count = 1;",
+            "# regions are not markers",
+            "#endregion",
+            "  <!-- #endregion -->",
+            "// endregion",
+            "#pragma region",
+            "#pragma endregion",
+        ];
+        let ranges: Vec<_> = region_folding_ranges(lines)
+            .into_iter()
+            .map(|range| (range.start.line, range.end.line))
+            .collect();
+        assert_eq!(ranges, vec![(0, 5), (1, 4), (7, 8)]);
+    }
+
+    #[test]
+    fn test_replace_keeps_status() {
+        let mut ranges = FoldingRanges::default();
+        let regions = region_folding_ranges(["// region", "", "// endregion"]);
+        ranges.replace(regions.clone(), |range| {
+            range.kind == Some(FoldingRangeKind::Region)
+        });
+        assert_eq!(ranges.0[0].status, FoldingRangeStatus::Fold);
+
+        ranges.0[0].status.click();
+        ranges.replace(regions.clone().into_iter().chain(regions), |_| true);
+        assert_eq!(ranges.0.len(), 1);
+        assert_eq!(ranges.0[0].status, FoldingRangeStatus::Unfold);
+    }
+}
@@ -1,12 +1,17 @@
 use std::{
     cmp, collections::BTreeMap, ops::DerefMut, path::PathBuf, rc::Rc, sync::Arc,
+    time::Duration,
 };
 
 use floem::{
-    action::{set_ime_allowed, set_ime_cursor_area},
+    action::{
+        exec_after, set_ime_allowed, set_ime_cursor_area, show_context_menu,
+        TimerToken,
+    },
     context::{PaintCx, StyleCx},
     event::{Event, EventListener, EventPropagation},
     keyboard::Modifiers,
+    menu::{Menu, MenuItem},
     peniko::{
         kurbo::{Line, Point, Rect, Size},
         Color,
@@ -17,6 +22,8 @@
     },
     style::{CursorColor, CursorStyle, Style, TextColor},
     taffy::prelude::NodeId,
+    text::{Attrs, AttrsList, FamilyOwned, LineHeightValue, TextLayout},
+    unit::PxPctAuto,
     views::{
         clip, container, dyn_stack,
         editor::{
@@ -42,6 +49,8 @@
 use lapce_core::{
     buffer::{diff::DiffLines, rope_text::RopeText, Buffer},
     cursor::{CursorAffinity, CursorMode},
+    language::LapceLanguage,
+    mode::Mode,
     selection::SelRegion,
 };
 use lapce_rpc::{
@@ -51,14 +60,27 @@
 use lapce_xi_rope::find::CaseMatching;
 use lsp_types::CodeLens;
 
-use super::{gutter::editor_gutter_view, DocSignal, EditorData};
+use super::{
+    gutter::editor_gutter_view, trailing_whitespace, DocSignal, EditorData,
+    EditorViewKind,
+};
 use crate::{
     app::clickable_icon,
     command::InternalCommand,
-    config::{color::LapceColor, editor::WrapStyle, icon::LapceIcons, LapceConfig},
+    config::{
+        color::LapceColor,
+        editor::{EditorConfig, RenderWhitespace, WrapStyle},
+        icon::LapceIcons,
+        LapceConfig,
+    },
     debug::{DapData, LapceBreakpoint},
     doc::DocContent,
-    editor::gutter::FoldingDisplayItem,
+    editor::{
+        gutter::FoldingDisplayItem,
+        location::{EditorLocation, EditorPosition},
+    },
+    panel::document_symbol::BreadcrumbSymbol,
+    perf::{self, PerfSubtree},
     text_input::TextInputBuilder,
     window_tab::{CommonData, Focus, WindowTabData},
     workspace::LapceWorkspace,
@@ -71,15 +93,22 @@ pub struct StickyHeaderInfo {
     pub y_diff: f64,
 }
 
-fn editor_wrap(config: &LapceConfig) -> WrapMethod {
+pub fn editor_wrap(config: &EditorConfig) -> WrapMethod {
     /// Minimum width that we'll allow the view to be wrapped at.
     const MIN_WRAPPED_WIDTH: f32 = 100.0;
 
-    match config.editor.wrap_style {
+    match config.wrap_style {
         WrapStyle::None => WrapMethod::None,
         WrapStyle::EditorWidth => WrapMethod::EditorWidth,
+        // Half a character is added so rounding can't wrap a line that is just
+        // `wrap_column` long.
+        WrapStyle::WrapColumn => WrapMethod::WrapWidth {
+            width: (((config.wrap_column as f64 + 0.5) * config.char_width())
+                as f32)
+                .max(MIN_WRAPPED_WIDTH),
+        },
         WrapStyle::WrapWidth => WrapMethod::WrapWidth {
-            width: (config.editor.wrap_width as f32).max(MIN_WRAPPED_WIDTH),
+            width: (config.wrap_width as f32).max(MIN_WRAPPED_WIDTH),
         },
     }
 }
@@ -91,6 +120,7 @@ pub fn editor_style(
 ) -> Style {
     let config = config.get();
     let doc = doc.get();
+    let language = doc.syntax.with_untracked(|s| s.language);
 
     s.set(
         IndentStyleProp,
@@ -126,12 +156,15 @@ pub fn editor_style(
         config.editor.modal_mode_relative_line_numbers,
     )
     .set(SmartTab, config.editor.smart_tab)
-    .set(WrapProp, editor_wrap(&config))
+    .set(WrapProp, editor_wrap(config.editor_config(language)))
     .set(
         CursorSurroundingLines,
         config.editor.cursor_surrounding_lines,
     )
-    .set(RenderWhitespaceProp, config.editor.render_whitespace)
+    .set(
+        RenderWhitespaceProp,
+        config.editor.render_whitespace.floem_mode(),
+    )
 }
 
 pub struct EditorView {
@@ -141,6 +174,9 @@ pub struct EditorView {
     inner_node: Option<NodeId>,
     viewport: RwSignal<Rect>,
     debug_breakline: Memo<Option<(usize, PathBuf)>>,
+    /// The width of a character the rulers are placed with, along with the id
+    /// of the config and the language it was measured for.
+    ruler_char_width: Option<(u64, LapceLanguage, f64)>,
 }
 
 pub fn editor_view(
@@ -170,6 +206,43 @@ pub fn editor_view(
         id.request_paint();
     });
 
+    let text_drag = e_data.common.text_drag;
+    let editor_id = e_data.id();
+    let drop_offset = create_memo(move |_| {
+        text_drag.with(|drag| {
+            drag.as_ref()?
+                .target
+                .filter(|(id, _)| *id == editor_id)
+                .map(|(_, offset)| offset)
+        })
+    });
+    create_effect(move |_| {
+        drop_offset.track();
+        id.request_paint();
+    });
+
+    let linked_editing_ranges = e_data.linked_editing_ranges;
+    create_effect(move |_| {
+        linked_editing_ranges.track();
+        id.request_paint();
+    });
+
+    let hovered_link = e_data.hovered_link;
+    create_effect(move |_| {
+        hovered_link.track();
+        id.request_paint();
+    });
+
+    // The link under the pointer changes with the modifier and the links of
+    // the document, not only as the pointer moves.
+    let link_modifier_held = e_data.common.window_common.link_modifier_held;
+    let link_editor = e_data.clone();
+    create_effect(move |_| {
+        link_modifier_held.track();
+        doc.with(|doc| doc.document_links.track());
+        link_editor.update_hovered_link();
+    });
+
     create_effect(move |last_rev| {
         let buffer = doc.with(|doc| doc.buffer);
         let rev = buffer.with(|buffer| buffer.rev());
@@ -251,6 +324,23 @@ pub fn editor_view(
         }
     });
 
+    // Let plugins know about the selection once it stops moving.
+    let selection_timer = create_rw_signal(TimerToken::INVALID);
+    let editor = e_data.clone();
+    create_effect(move |_| {
+        cursor.track();
+        if !is_active.get_untracked() {
+            return;
+        }
+        let editor = editor.clone();
+        let token = exec_after(Duration::from_millis(300), move |token| {
+            if selection_timer.try_get_untracked() == Some(token) {
+                editor.selection_changed();
+            }
+        });
+        selection_timer.set(token);
+    });
+
     let doc = e_data.doc_signal();
     EditorView {
         id,
@@ -259,6 +349,7 @@ pub fn editor_view(
         inner_node: None,
         viewport,
         debug_breakline,
+        ruler_char_width: None,
     }
     .on_event(EventListener::ImePreedit, move |event| {
         if !is_active.get_untracked() {
@@ -301,6 +392,7 @@ fn paint_diff_sections(
         let Some(diff_sections) = &screen_lines.diff_sections else {
             return;
         };
+        let line_height = self.editor.line_height();
         for section in diff_sections.iter() {
             match section.kind {
                 DiffSectionKind::NoCode => self.paint_diff_no_code(
@@ -315,12 +407,11 @@ fn paint_diff_sections(
                         &Rect::ZERO
                             .with_size(Size::new(
                                 viewport.width(),
-                                (config.editor.line_height() * section.height)
-                                    as f64,
+                                (line_height * section.height) as f64,
                             ))
                             .with_origin(Point::new(
                                 viewport.x0,
-                                (section.y_idx * config.editor.line_height()) as f64,
+                                (section.y_idx * line_height) as f64,
                             )),
                         config
                             .color(LapceColor::SOURCE_CONTROL_ADDED)
@@ -333,12 +424,11 @@ fn paint_diff_sections(
                         &Rect::ZERO
                             .with_size(Size::new(
                                 viewport.width(),
-                                (config.editor.line_height() * section.height)
-                                    as f64,
+                                (line_height * section.height) as f64,
                             ))
                             .with_origin(Point::new(
                                 viewport.x0,
-                                (section.y_idx * config.editor.line_height()) as f64,
+                                (section.y_idx * line_height) as f64,
                             )),
                         config
                             .color(LapceColor::SOURCE_CONTROL_REMOVED)
@@ -350,6 +440,113 @@ fn paint_diff_sections(
         }
     }
 
+    /// Highlight the parts of the changed lines of a diff that differ from the
+    /// lines they replace.
+    fn paint_diff_line_changes(
+        &self,
+        cx: &mut PaintCx,
+        screen_lines: &ScreenLines,
+        config: &LapceConfig,
+    ) {
+        let EditorViewKind::Diff(diff_info) = self.editor.kind.get_untracked()
+        else {
+            return;
+        };
+        if diff_info.line_changes.is_empty() {
+            return;
+        }
+
+        let ed = &self.editor.editor;
+        let line_height = self.editor.line_height() as f64;
+        let color = if diff_info.is_right {
+            config.color(LapceColor::SOURCE_CONTROL_ADDED)
+        } else {
+            config.color(LapceColor::SOURCE_CONTROL_REMOVED)
+        }
+        .with_alpha_factor(0.35);
+
+        for line_info in screen_lines.iter_line_info() {
+            let rvline_info = line_info.vline_info;
+            let line = rvline_info.rvline.line;
+            let Some(range) = diff_info.line_changes.get(&line) else {
+                continue;
+            };
+
+            // Clip the change to the part of the line shown on this visual line.
+            let first_col = ed.first_col(rvline_info);
+            let last_col = ed.last_col(rvline_info, true);
+            let start_col = range.start.max(first_col);
+            let end_col = range.end.min(last_col);
+            if start_col >= end_col {
+                continue;
+            }
+
+            let x0 = ed
+                .line_point_of_line_col(
+                    line,
+                    start_col,
+                    CursorAffinity::Forward,
+                    true,
+                )
+                .x;
+            let x1 = ed
+                .line_point_of_line_col(
+                    line,
+                    end_col,
+                    CursorAffinity::Backward,
+                    true,
+                )
+                .x;
+            let rect = Size::new(x1 - x0, line_height)
+                .to_rect()
+                .with_origin(Point::new(x0, line_info.vline_y));
+            cx.fill(&rect, color, 0.0);
+        }
+    }
+
+    /// Paint the lines removed from the left side of an inline diff in the
+    /// room the right side leaves for them.
+    fn paint_diff_removed_lines(
+        &self,
+        cx: &mut PaintCx,
+        viewport: Rect,
+        screen_lines: &ScreenLines,
+        config: &LapceConfig,
+    ) {
+        let Some(diff_sections) = &screen_lines.diff_sections else {
+            return;
+        };
+        let removed_lines = self.editor.diff_removed_lines.get_untracked();
+        if removed_lines.is_empty() {
+            return;
+        }
+
+        let line_height = self.editor.line_height() as f64;
+        let change_color = config
+            .color(LapceColor::SOURCE_CONTROL_REMOVED)
+            .with_alpha_factor(0.35);
+
+        // The right side of an inline diff leaves a removed section for each
+        // block of removed lines, in order.
+        let sections = diff_sections
+            .iter()
+            .filter(|section| matches!(section.kind, DiffSectionKind::Removed));
+        for (section, lines) in sections.zip(removed_lines.iter()) {
+            for (i, line) in lines.iter().enumerate().take(section.height) {
+                let y = (section.y_idx + i) as f64 * line_height;
+                if y + line_height < viewport.y0 || y > viewport.y1 {
+                    continue;
+                }
+
+                if let Some((x0, x1)) = line.change {
+                    let rect = Rect::new(x0, y, x1, y + line_height);
+                    cx.fill(&rect, change_color, 0.0);
+                }
+                cx.draw_text(&line.text_layout, Point::new(0.0, y));
+            }
+        }
+    }
+
     fn paint_diff_no_code(
         &self,
         cx: &mut PaintCx,
@@ -358,7 +555,7 @@ fn paint_diff_no_code(
         height: usize,
         config: &LapceConfig,
     ) {
-        let line_height = config.editor.line_height();
+        let line_height = self.editor.line_height();
         let height = (height * line_height) as f64;
         let y = (start_line * line_height) as f64;
         let y_end = y + height;
@@ -411,7 +608,7 @@ fn paint_current_line(
         let config = self.editor.common.config;
 
         let config = config.get_untracked();
-        let line_height = config.editor.line_height() as f64;
+        let line_height = self.editor.line_height() as f64;
         let viewport = self.viewport.get_untracked();
 
         let current_line_color = ed.es.with_untracked(EditorStyle::current_line);
@@ -498,7 +695,7 @@ fn paint_find(&self, cx: &mut PaintCx, screen_lines: &ScreenLines) {
         let occurrences = doc.find_result.occurrences;
 
         let config = config.get_untracked();
-        let line_height = config.editor.line_height() as f64;
+        let line_height = self.editor.line_height() as f64;
         let color = config.color(LapceColor::EDITOR_FOREGROUND);
 
         let start = ed.offset_of_line(min_line);
@@ -604,6 +801,168 @@ fn paint_find_region(
         }
     }
 
+    /// Highlight the spaces and tabs at the end of the lines, except where a cursor
+    /// is within them, as that is most likely text that is still being typed.
+    fn paint_trailing_whitespace(
+        &self,
+        cx: &mut PaintCx,
+        screen_lines: &ScreenLines,
+        config: &LapceConfig,
+    ) {
+        if !config.editor.highlight_trailing_whitespace {
+            return;
+        }
+
+        let ed = &self.editor.editor;
+        let rope_text = ed.rope_text();
+        let line_height = self.editor.line_height() as f64;
+        let color = config.color(LapceColor::EDITOR_TRAILING_WHITESPACE);
+        let cursor_offsets: Vec<usize> =
+            self.editor.cursor().with_untracked(|cursor| {
+                cursor.regions_iter().map(|(_, end)| end).collect()
+            });
+
+        // The trailing whitespace of the last line seen, which may span several
+        // wrapped visual lines.
+        let mut trailing: Option<(usize, Option<_>)> = None;
+        for line_info in screen_lines.iter_line_info() {
+            let rvline_info = line_info.vline_info;
+            let rvline = rvline_info.rvline;
+            let line = rvline.line;
+
+            if trailing.map(|(l, _)| l) != Some(line) {
+                let range = trailing_whitespace(&rope_text, line)
+                    .filter(|(start, end)| {
+                        !cursor_offsets
+                            .iter()
+                            .any(|offset| (*start..=*end).contains(offset))
+                    })
+                    .map(|(start, end)| {
+                        (
+                            ed.rvline_col_of_offset(start, CursorAffinity::Forward),
+                            ed.rvline_col_of_offset(end, CursorAffinity::Backward),
+                        )
+                    });
+                trailing = Some((line, range));
+            }
+            let Some((_, Some(((start_rvline, start_col), (end_rvline, end_col))))) =
+                trailing
+            else {
+                continue;
+            };
+            if rvline < start_rvline || rvline > end_rvline {
+                continue;
+            }
+
+            let left_col = if rvline == start_rvline { start_col } else { 0 };
+            let right_col = if rvline == end_rvline {
+                end_col
+            } else {
+                ed.last_col(rvline_info, true)
+            };
+            let x0 = ed
+                .line_point_of_line_col(
+                    line,
+                    left_col,
+                    CursorAffinity::Forward,
+                    true,
+                )
+                .x;
+            let x1 = ed
+                .line_point_of_line_col(
+                    line,
+                    right_col,
+                    CursorAffinity::Backward,
+                    true,
+                )
+                .x;
+            let rect = Size::new(x1 - x0, line_height)
+                .to_rect()
+                .with_origin(Point::new(x0, line_info.vline_y));
+            cx.fill(&rect, color, 0.0);
+        }
+    }
+
+    /// Paint the spaces and tabs within the selections, for the `selection` mode of
+    /// `render-whitespace` which floem doesn't handle itself.
+    fn paint_selection_whitespace(
+        &self,
+        cx: &mut PaintCx,
+        screen_lines: &ScreenLines,
+        config: &LapceConfig,
+    ) {
+        if config.editor.render_whitespace != RenderWhitespace::Selection {
+            return;
+        }
+        let line_of = |rvline: Option<&RVLine>| {
+            rvline
+                .and_then(|rvline| screen_lines.info(*rvline))
+                .map(|info| info.vline_info.rvline.line)
+        };
+        let (Some(min_line), Some(max_line)) = (
+            line_of(screen_lines.lines.first()),
+            line_of(screen_lines.lines.last()),
+        ) else {
+            return;
+        };
+
+        let ed = &self.editor.editor;
+        let rope_text = ed.rope_text();
+        let start = ed.offset_of_line(min_line);
+        let end = ed.offset_of_line(max_line + 1);
+        let regions: Vec<(usize, usize)> =
+            self.editor.cursor().with_untracked(|cursor| {
+                cursor
+                    .regions_iter()
+                    .map(|(start, end)| (start.min(end), start.max(end)))
+                    .collect()
+            });
+
+        let family: Vec<FamilyOwned> =
+            FamilyOwned::parse_list(&config.editor.font_family).collect();
+        let attrs = Attrs::new()
+            .family(&family)
+            .font_size(config.editor.font_size() as f32)
+            .line_height(LineHeightValue::Px(self.editor.line_height() as f32))
+            .color(config.color(LapceColor::EDITOR_VISIBLE_WHITESPACE));
+        let mut space = TextLayout::new();
+        space.set_text("\u{b7}", AttrsList::new(attrs));
+        let mut tab = TextLayout::new();
+        tab.set_text("\u{2192}", AttrsList::new(attrs));
+
+        for (region_start, region_end) in regions {
+            let region_start = region_start.max(start);
+            let region_end = region_end.min(end);
+            if region_start >= region_end {
+                continue;
+            }
+
+            let text = rope_text.slice_to_cow(region_start..region_end);
+            for (i, c) in text.char_indices() {
+                let text_layout = match c {
+                    ' ' => &space,
+                    '\t' => &tab,
+                    _ => continue,
+                };
+                let offset = region_start + i;
+                let (rvline, col) =
+                    ed.rvline_col_of_offset(offset, CursorAffinity::Forward);
+                let Some(info) = screen_lines.info(rvline) else {
+                    continue;
+                };
+                let x = ed
+                    .line_point_of_line_col(
+                        rvline.line,
+                        col,
+                        CursorAffinity::Forward,
+                        true,
+                    )
+                    .x;
+                cx.draw_text(text_layout, Point::new(x, info.vline_y));
+            }
+        }
+    }
+
     fn paint_sticky_headers(
         &self,
         cx: &mut PaintCx,
@@ -618,7 +977,7 @@ fn paint_sticky_headers(
             return;
         }
 
-        let line_height = config.editor.line_height();
+        let line_height = self.editor.line_height();
         let Some(start_vline) = screen_lines.lines.first() else {
             return;
         };
@@ -711,6 +1070,118 @@ fn paint_sticky_headers(
         }
     }
 
+    /// Paint a caret where the text being dragged would be dropped.
+    fn paint_drop_caret(&self, cx: &mut PaintCx, config: &LapceConfig) {
+        let editor_id = self.editor.id();
+        let Some(offset) = self.editor.common.text_drag.with_untracked(|drag| {
+            drag.as_ref()?
+                .target
+                .filter(|(id, _)| *id == editor_id)
+                .map(|(_, offset)| offset)
+        }) else {
+            return;
+        };
+        let (top, bottom) = self
+            .editor
+            .editor
+            .points_of_offset(offset, CursorAffinity::Forward);
+        let rect = Rect::new(top.x - 1.0, top.y, top.x + 1.0, bottom.y);
+        cx.fill(&rect, config.color(LapceColor::EDITOR_CARET), 0.0);
+    }
+
+    /// Paint the carets the edits at the cursor are mirrored to, in the other
+    /// linked editing ranges.
+    fn paint_linked_editing_carets(&self, cx: &mut PaintCx, config: &LapceConfig) {
+        let Some(ranges) = self.editor.linked_editing_ranges.get_untracked() else {
+            return;
+        };
+        let offset = self.editor.cursor().with_untracked(|c| c.offset());
+        let Some((range_start, _)) = ranges
+            .iter()
+            .find(|(start, end)| offset >= *start && offset <= *end)
+            .copied()
+        else {
+            return;
+        };
+        for (start, end) in ranges {
+            if start == range_start {
+                continue;
+            }
+            let offset = (start + offset - range_start).min(end);
+            let (top, bottom) = self
+                .editor
+                .editor
+                .points_of_offset(offset, CursorAffinity::Forward);
+            let rect = Rect::new(top.x - 1.0, top.y, top.x + 1.0, bottom.y);
+            cx.fill(
+                &rect,
+                config
+                    .color(LapceColor::EDITOR_CARET)
+                    .with_alpha_factor(0.5),
+                0.0,
+            );
+        }
+    }
+
+    /// Draw the vertical rulers at the columns set for the language of the
+    /// document.
+    fn paint_rulers(
+        &mut self,
+        cx: &mut PaintCx,
+        viewport: Rect,
+        config: &LapceConfig,
+    ) {
+        let language = self.editor.doc().syntax.with_untracked(|s| s.language);
+        let editor_config = config.editor_config(language);
+        let rulers = editor_config.rulers();
+        if rulers.is_empty() {
+            return;
+        }
+        // Measuring the width lays out text, so it's only done again when the
+        // config or the language changes.
+        let char_width = match self.ruler_char_width {
+            Some((id, cached_language, char_width))
+                if id == config.id && cached_language == language =>
+            {
+                char_width
+            }
+            _ => {
+                let char_width = editor_config.char_width();
+                self.ruler_char_width = Some((config.id, language, char_width));
+                char_width
+            }
+        };
+        let color = config.color(LapceColor::EDITOR_RULER);
+        for column in rulers {
+            let x = (column as f64 * char_width).round();
+            if x < viewport.x0 || x > viewport.x1 {
+                continue;
+            }
+            let rect = Rect::new(x, viewport.y0, x + 1.0, viewport.y1);
+            cx.fill(&rect, color, 0.0);
+        }
+    }
+
+    /// Underline the document link under the pointer.
+    fn paint_hovered_link(&self, cx: &mut PaintCx, config: &LapceConfig) {
+        let Some((start, end)) = self.editor.hovered_link.get_untracked() else {
+            return;
+        };
+        let (start, _) = self
+            .editor
+            .editor
+            .points_of_offset(start, CursorAffinity::Forward);
+        let (end, bottom) = self
+            .editor
+            .editor
+            .points_of_offset(end, CursorAffinity::Backward);
+        if start.y != end.y {
+            return;
+        }
+        let rect = Rect::new(start.x, bottom.y - 2.0, end.x, bottom.y - 1.0);
+        cx.fill(&rect, config.color(LapceColor::EDITOR_LINK), 0.0);
+    }
+
     fn paint_scroll_bar(
         &self,
         cx: &mut PaintCx,
@@ -745,7 +1216,7 @@ fn paint_scroll_bar(
         let changes = doc.head_changes().get_untracked();
         let total_height = viewport.height();
         let total_width = viewport.width();
-        let line_height = config.editor.line_height();
+        let line_height = self.editor.line_height();
         let content_height = if config.editor.scroll_beyond_last_line {
             (total_len * line_height) as f64 + total_height - line_height as f64
         } else {
@@ -777,7 +1248,7 @@ fn paint_char_highlights(
     ) {
         let editor = &self.editor.editor;
         let config = self.editor.common.config.get_untracked();
-        let line_height = config.editor.line_height() as f64;
+        let line_height = self.editor.line_height() as f64;
 
         for (rvline, col) in highlight_line_cols {
             // Is the given line on screen?
@@ -818,12 +1289,11 @@ fn paint_scope_lines(
         screen_lines: &ScreenLines,
         (start, start_col): (RVLine, usize),
         (end, end_col): (RVLine, usize),
+        brush: Color,
     ) {
         let editor = &self.editor.editor;
         let doc = self.editor.doc();
-        let config = self.editor.common.config.get_untracked();
-        let line_height = config.editor.line_height() as f64;
-        let brush = config.color(LapceColor::EDITOR_FOREGROUND);
+        let line_height = self.editor.line_height() as f64;
 
         if start == end {
             if let Some(line_info) = screen_lines.info(start) {
@@ -995,13 +1465,35 @@ fn paint_bracket_highlights_scope_lines(
             }
 
             if config.editor.highlight_scope_lines {
-                if let Some([start_line_col, end_line_col]) = bracket_line_cols {
+                if let (Some([start_line_col, end_line_col]), Some([start, _])) =
+                    (bracket_line_cols, bracket_offsets)
+                {
+                    // The guide takes the color of the pair's brackets when they are
+                    // colorized.
+                    let doc = e_data.doc();
+                    let (line, col) = doc
+                        .buffer
+                        .with_untracked(|buffer| buffer.offset_to_line_col(start));
+                    let brush = doc
+                        .parser
+                        .borrow()
+                        .bracket_pos
+                        .get(&line)
+                        .and_then(|styles| {
+                            styles.iter().find(|style| style.start == col)
+                        })
+                        .and_then(|style| style.style.fg_color.as_ref())
+                        .and_then(|color| config.style_color(color))
+                        .unwrap_or_else(|| {
+                            config.color(LapceColor::EDITOR_FOREGROUND)
+                        });
                     self.paint_scope_lines(
                         cx,
                         viewport,
                         screen_lines,
                         start_line_col,
                         end_line_col,
+                        brush,
                     );
                 }
             }
@@ -1105,6 +1597,7 @@ fn compute_layout(
         &mut self,
         cx: &mut floem::context::ComputeLayoutCx,
     ) -> Option<Rect> {
+        perf::record_layout(PerfSubtree::Editor);
         let viewport = cx.current_viewport();
         if self.viewport.with_untracked(|v| v != &viewport) {
             self.viewport.set(viewport);
@@ -1113,6 +1606,7 @@ fn compute_layout(
     }
 
     fn paint(&mut self, cx: &mut PaintCx) {
+        let _timer = perf::time_paint(PerfSubtree::Editor);
         let viewport = self.viewport.get_untracked();
         let e_data = &self.editor;
         let ed = &e_data.editor;
@@ -1133,15 +1627,23 @@ fn paint(&mut self, cx: &mut PaintCx) {
         // within the active screen lines without issue.
         let screen_lines = ed.screen_lines.get_untracked();
         self.paint_current_line(cx, is_local, &screen_lines);
+        self.paint_trailing_whitespace(cx, &screen_lines, &config);
         FloemEditorView::paint_selection(cx, ed, &screen_lines);
         let screen_lines = ed.screen_lines.get_untracked();
         self.paint_diff_sections(cx, viewport, &screen_lines, &config);
+        self.paint_diff_line_changes(cx, &screen_lines, &config);
+        self.paint_diff_removed_lines(cx, viewport, &screen_lines, &config);
         let screen_lines = ed.screen_lines.get_untracked();
         self.paint_find(cx, &screen_lines);
         let screen_lines = ed.screen_lines.get_untracked();
         self.paint_bracket_highlights_scope_lines(cx, viewport, &screen_lines);
+        self.paint_rulers(cx, viewport, &config);
         let screen_lines = ed.screen_lines.get_untracked();
         FloemEditorView::paint_text(cx, ed, viewport, is_active, &screen_lines);
+        self.paint_selection_whitespace(cx, &screen_lines, &config);
+        self.paint_drop_caret(cx, &config);
+        self.paint_linked_editing_carets(cx, &config);
+        self.paint_hovered_link(cx, &config);
         let screen_lines = ed.screen_lines.get_untracked();
         self.paint_sticky_headers(cx, viewport, &screen_lines);
         self.paint_scroll_bar(cx, viewport, is_local, config);
@@ -1331,7 +1833,18 @@ pub fn editor_container_view(
             )
             .debug_name("find view"),
         ))
-        .style(|s| s.width_full().flex_basis(0).flex_grow(1.0)),
+        .style(move |s| {
+            let config = config.get();
+            s.width_full().flex_basis(0).flex_grow(1.0).apply_if(
+                config.editor.centered_layout,
+                |s| {
+                    s.max_width(
+                        config.editor.centered_layout_max_width.max(200) as f32
+                    )
+                    .margin_horiz(PxPctAuto::Auto)
+                },
+            )
+        }),
     ))
     .on_cleanup(move || {
         let editor = editor.get_untracked();
@@ -1473,6 +1986,7 @@ fn editor_gutter_breakpoints(
     icon_padding: f32,
 ) -> impl View {
     let breakpoints = window_tab_data.terminal.debug.breakpoints;
+    let bookmarks = window_tab_data.common.bookmarks;
     let daps = window_tab_data.terminal.debug.daps;
     let common = window_tab_data.common.clone();
 
@@ -1516,6 +2030,52 @@ fn editor_gutter_breakpoints(
                 )
             })
             .debug_name("Breakpoint Stack"),
+            // The bookmarks are drawn under the breakpoints on the same line.
+            dyn_stack(
+                move || {
+                    let e_data = e_data.get();
+                    let doc = e_data.doc_signal().get();
+                    let content = doc.content.get();
+                    let lines: Vec<usize> = content
+                        .path()
+                        .and_then(|path| {
+                            bookmarks.with(|b| {
+                                b.get(path).map(|b| b.keys().copied().collect())
+                            })
+                        })
+                        .unwrap_or_default();
+                    lines
+                },
+                move |line| *line,
+                move |line| {
+                    container(
+                        svg(move || config.get().ui_svg(LapceIcons::BOOKMARK))
+                            .style(move |s| {
+                                let config = config.get();
+                                let size = config.ui.icon_size() as f32;
+                                s.size(size, size)
+                                    .color(config.color(LapceColor::EDITOR_FOCUS))
+                            }),
+                    )
+                    .style(move |s| {
+                        let config = config.get();
+                        let line_y = screen_lines
+                            .with(|s| s.info_for_line(line))
+                            .map(|l| l.y);
+                        s.absolute()
+                            .width(config.ui.icon_size() as f32 + icon_padding * 2.0)
+                            .height(e_data.get().line_height() as f32)
+                            .justify_center()
+                            .items_center()
+                            .margin_top(
+                                line_y.unwrap_or_default() as f32
+                                    - viewport.get().y0 as f32,
+                            )
+                            .apply_if(line_y.is_none(), |s| s.hide())
+                    })
+                },
+            )
+            .style(|s| s.absolute().size_pct(100.0, 100.0)),
             dyn_stack(
                 move || {
                     let e_data = e_data.get();
@@ -1793,7 +2353,7 @@ fn editor_gutter_code_actions(
             cursor.with(|cursor| (cursor.offset(), cursor.affinity));
         let has_code_actions = doc
             .code_actions()
-            .with(|c| c.get(&offset).map(|c| !c.1.is_empty()).unwrap_or(false));
+            .with(|c| c.get(&offset).map(|c| !c.is_empty()).unwrap_or(false));
         if has_code_actions {
             let vline = ed.vline_of_offset(offset, affinity);
             Some(vline)
@@ -1904,6 +2464,37 @@ fn editor_gutter(
                     .on_resize(move |rect| {
                         gutter_rect.set(rect);
                     })
+                    .on_event(EventListener::PointerUp, {
+                        let ed = ed.clone();
+                        move |event| {
+                            let Event::PointerUp(pointer_event) = event else {
+                                return EventPropagation::Continue;
+                            };
+                            // Only clicks on the diff markers peek at the hunk.
+                            let marker_x = gutter_width.get_untracked()
+                                - gutter_padding_right.get_untracked() as f64
+                                + 5.0;
+                            let x = pointer_event.pos.x - marker_x;
+                            if !(-3.0..=6.0).contains(&x) {
+                                return EventPropagation::Continue;
+                            }
+                            let point = Point::new(
+                                0.0,
+                                pointer_event.pos.y + viewport.get_untracked().y0,
+                            );
+                            let (offset, _) =
+                                ed.offset_of_point(Mode::Normal, point);
+                            let line = ed.rope_text().line_of_offset(offset);
+                            if window_tab_data
+                                .hunk_peek
+                                .open(e_data.get_untracked(), line)
+                            {
+                                EventPropagation::Stop
+                            } else {
+                                EventPropagation::Continue
+                            }
+                        }
+                    })
                     .on_event_stop(EventListener::PointerWheel, move |event| {
                         if let Event::PointerWheel(pointer_event) = event {
                             scroll_delta.set(pointer_event.delta);
@@ -1990,12 +2581,14 @@ fn editor_breadcrumbs(
                             .style(|s| s.items_center())
                         },
                     )
-                    .style(|s| s.padding_horiz(10.0))
+                    .style(|s| s.padding_left(10.0))
                 },
+                breadcrumb_symbols(e_data.clone(), doc_path, config)
+                    .style(|s| s.padding_right(10.0)),
                 label(move || {
                     let doc = doc.get();
                     if let DocContent::History(history) = doc.content.get() {
-                        format!("({})", history.version)
+                        format!("({})", history.description())
                     } else {
                         "".to_string()
                     }
@@ -2036,6 +2629,91 @@ fn editor_breadcrumbs(
     .debug_name("Editor BreadCrumbs")
 }
 
+/// The chain of symbols containing the cursor, each of which opens a menu of
+/// the symbols beside it to go to when clicked.
+fn breadcrumb_symbols(
+    e_data: EditorData,
+    doc_path: Memo<Option<PathBuf>>,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let doc = e_data.doc_signal();
+    let cursor = e_data.cursor();
+    let internal_command = e_data.common.internal_command;
+    let symbols = create_memo(move |_| {
+        let doc = doc.get();
+        let offset = cursor.with(|c| c.offset());
+        let position = doc
+            .buffer
+            .with_untracked(|buffer| buffer.offset_to_position(offset));
+        doc.document_symbol_data.with(|symbols| {
+            symbols
+                .as_ref()
+                .map(|symbols| symbols.breadcrumbs(position))
+                .unwrap_or_default()
+        })
+    });
+
+    dyn_stack(
+        move || symbols.get().into_iter().enumerate(),
+        |(i, symbol)| (*i, symbol.clone()),
+        move |(_, symbol)| {
+            let BreadcrumbSymbol {
+                name,
+                kind,
+                siblings,
+                ..
+            } = symbol;
+            stack((
+                svg(move || config.get().ui_svg(LapceIcons::BREADCRUMB_SEPARATOR))
+                    .style(move |s| {
+                        let config = config.get();
+                        let size = config.ui.icon_size() as f32;
+                        s.size(size, size)
+                            .color(config.color(LapceColor::LAPCE_ICON_ACTIVE))
+                    }),
+                svg(move || {
+                    let config = config.get();
+                    config
+                        .symbol_svg(&kind)
+                        .unwrap_or_else(|| config.ui_svg(LapceIcons::FILE))
+                })
+                .style(move |s| {
+                    let config = config.get();
+                    let size = config.ui.icon_size() as f32;
+                    s.size(size, size).margin_right(4.0).color(
+                        config.symbol_color(&kind).unwrap_or_else(|| {
+                            config.color(LapceColor::LAPCE_ICON_ACTIVE)
+                        }),
+                    )
+                }),
+                label(move || name.clone()).style(|s| s.selectable(false)),
+            ))
+            .on_click_stop(move |_| {
+                let Some(path) = doc_path.get_untracked() else {
+                    return;
+                };
+                let mut menu = Menu::new("");
+                for (name, _, position) in siblings.iter().cloned() {
+                    let path = path.clone();
+                    menu = menu.entry(MenuItem::new(name).action(move || {
+                        internal_command.send(InternalCommand::JumpToLocation {
+                            location: EditorLocation {
+                                path: path.clone(),
+                                position: Some(EditorPosition::Position(position)),
+                                scroll_offset: None,
+                                ignore_unconfirmed: false,
+                                same_editor_tab: false,
+                            },
+                        });
+                    }));
+                }
+                show_context_menu(menu, None);
+            })
+            .style(|s| s.items_center().hover(|s| s.cursor(CursorStyle::Pointer)))
+        },
+    )
+}
+
 fn editor_content(
     e_data: RwSignal<EditorData>,
     debug_breakline: Memo<Option<(usize, PathBuf)>>,
@@ -2073,6 +2751,9 @@ fn editor_content(
     }
 
     let current_scroll = create_rw_signal(Rect::ZERO);
+    // Measured when the config changes rather than whenever the cursor moves.
+    let char_width =
+        create_memo(move |_| config.with(|config| config.editor.char_width()));
 
     scroll({
         let editor_content_view =
@@ -2098,8 +2779,13 @@ fn editor_content(
             })
             .on_event_cont(EventListener::PointerDown, move |event| {
                 if let Event::PointerDown(pointer_event) = event {
-                    id.request_active();
-                    e_data.get_untracked().pointer_down(pointer_event);
+                    let editor = e_data.get_untracked();
+                    editor.pointer_down(pointer_event);
+                    // Dragged text goes to the editor under the pointer, rather
+                    // than the one it was pressed in.
+                    if editor.common.text_drag.with_untracked(Option::is_none) {
+                        id.request_active();
+                    }
                 }
             })
             .on_event_stop(EventListener::PointerMove, move |event| {
@@ -2147,6 +2833,10 @@ fn editor_content(
         );
         let config = config.get_untracked();
         let line_height = config.editor.line_height();
+        let surrounding_width = match config.editor.cursor_surrounding_columns {
+            0 => 10.0,
+            columns => columns as f64 * char_width.get_untracked(),
+        };
         // TODO: is there a good way to avoid the calculation of the vline here?
         let vline = e_data.editor.vline_of_rvline(rvline);
         let vline = e_data.visual_line(vline.get());
@@ -2154,7 +2844,7 @@ fn editor_content(
             (x, (vline * line_height) as f64),
             (width, line_height as f64),
         )
-        .inflate(10.0, 0.0);
+        .inflate(surrounding_width, 0.0);
 
         let viewport = viewport.get_untracked();
         let smallest_distance = (viewport.y0 - rect.y0)
@@ -2275,6 +2965,7 @@ fn replace_editor_view(
 ) -> impl View {
     let config = replace_editor.common.config;
     let visual = replace_editor.common.find.visual;
+    let preserve_case = replace_editor.common.find.preserve_case;
 
     stack((
         TextInputBuilder::new()
@@ -2291,11 +2982,19 @@ fn replace_editor_view(
                 replace_focus.set(true);
             })
             .style(|s| s.width_pct(100.0)),
-        empty().style(move |s| {
-            let config = config.get();
-            let size = config.ui.icon_size() as f32 + 10.0;
-            s.size(0.0, size).padding_vert(4.0)
-        }),
+        clickable_icon(
+            || LapceIcons::SEARCH_PRESERVE_CASE,
+            move || {
+                preserve_case.update(|preserve_case| {
+                    *preserve_case = !*preserve_case;
+                });
+            },
+            move || preserve_case.get(),
+            || false,
+            || "Preserve Case",
+            config,
+        )
+        .style(|s| s.padding_vert(4.0).padding_horiz(6.0)),
     ))
     .style(move |s| {
         let config = config.get();
@@ -2321,6 +3020,7 @@ fn find_view(
     let config = common.config;
     let find_visual = common.find.visual;
     let replace_doc = replace_editor.doc_signal();
+    let in_selection = common.find.in_selection;
     let focus = common.focus;
 
     let find_pos = create_memo(move |_| {
@@ -2455,6 +3155,19 @@ fn find_view(
                     config,
                 )
                 .style(|s| s.padding_left(6.0)),
+                clickable_icon(
+                    || LapceIcons::SEARCH_IN_SELECTION,
+                    move || {
+                        in_selection.update(|in_selection| {
+                            *in_selection = !*in_selection;
+                        });
+                    },
+                    move || in_selection.get(),
+                    || false,
+                    || "Replace All in Selection",
+                    config,
+                )
+                .style(|s| s.padding_left(6.0)),
             ))
             .style(move |s| {
                 s.items_center()
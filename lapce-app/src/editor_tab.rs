@@ -27,8 +27,8 @@
         EditorData, EditorInfo,
     },
     id::{
-        DiffEditorId, EditorTabId, KeymapId, SettingsId, SplitId,
-        ThemeColorSettingsId, VoltViewId,
+        DiffEditorId, EditorTabId, ImageViewId, KeymapId, MarkdownPreviewId,
+        SettingsId, SplitId, ThemeColorSettingsId, VoltViewId,
     },
     main_split::{Editors, MainSplitData},
     plugin::PluginData,
@@ -43,6 +43,8 @@ pub enum EditorTabChildInfo {
     ThemeColorSettings,
     Keymap,
     Volt(VoltID),
+    Image(PathBuf),
+    MarkdownPreview(PathBuf),
 }
 
 impl EditorTabChildInfo {
@@ -70,6 +72,16 @@ pub fn to_data(
             EditorTabChildInfo::Volt(id) => {
                 EditorTabChild::Volt(VoltViewId::next(), id.to_owned())
             }
+            EditorTabChildInfo::Image(path) => EditorTabChild::Image(
+                data.new_image_viewer(path.to_owned()),
+                path.to_owned(),
+            ),
+            EditorTabChildInfo::MarkdownPreview(path) => {
+                EditorTabChild::MarkdownPreview(
+                    MarkdownPreviewId::next(),
+                    path.to_owned(),
+                )
+            }
         }
     }
 }
@@ -79,6 +91,9 @@ pub struct EditorTabInfo {
     pub active: usize,
     pub is_focus: bool,
     pub children: Vec<EditorTabChildInfo>,
+    /// The indices of the pinned children.
+    #[serde(default)]
+    pub pinned: Vec<usize>,
 }
 
 impl EditorTabInfo {
@@ -90,22 +105,29 @@ pub fn to_data(
         let editor_tab_id = EditorTabId::next();
         let editor_tab_data = {
             let cx = data.scope.create_child();
+            let children: Vec<_> = self
+                .children
+                .iter()
+                .map(|child| {
+                    (
+                        cx.create_rw_signal(0),
+                        cx.create_rw_signal(Rect::ZERO),
+                        child.to_data(data.clone(), editor_tab_id),
+                    )
+                })
+                .collect();
+            let pinned = self
+                .pinned
+                .iter()
+                .filter_map(|i| children.get(*i).map(|(_, _, child)| child.clone()))
+                .collect();
             let editor_tab_data = EditorTabData {
                 scope: cx,
                 editor_tab_id,
                 split,
                 active: self.active,
-                children: self
-                    .children
-                    .iter()
-                    .map(|child| {
-                        (
-                            cx.create_rw_signal(0),
-                            cx.create_rw_signal(Rect::ZERO),
-                            child.to_data(data.clone(), editor_tab_id),
-                        )
-                    })
-                    .collect(),
+                children,
+                pinned,
                 layout_rect: Rect::ZERO,
                 window_origin: Point::ZERO,
                 locations: cx.create_rw_signal(im::Vector::new()),
@@ -131,9 +153,10 @@ pub enum EditorTabChildSource {
     ThemeColorSettings,
     Keymap,
     Volt(VoltID),
+    Image(PathBuf),
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum EditorTabChild {
     Editor(EditorId),
     DiffEditor(DiffEditorId),
@@ -141,6 +164,8 @@ pub enum EditorTabChild {
     ThemeColorSettings(ThemeColorSettingsId),
     Keymap(KeymapId),
     Volt(VoltViewId, VoltID),
+    Image(ImageViewId, PathBuf),
+    MarkdownPreview(MarkdownPreviewId, PathBuf),
 }
 
 #[derive(PartialEq)]
@@ -162,6 +187,8 @@ pub fn id(&self) -> u64 {
             EditorTabChild::ThemeColorSettings(id) => id.to_raw(),
             EditorTabChild::Keymap(id) => id.to_raw(),
             EditorTabChild::Volt(id, _) => id.to_raw(),
+            EditorTabChild::Image(id, _) => id.to_raw(),
+            EditorTabChild::MarkdownPreview(id, _) => id.to_raw(),
         }
     }
 
@@ -195,6 +222,12 @@ pub fn child_info(&self, data: &WindowTabData) -> EditorTabChildInfo {
             }
             EditorTabChild::Keymap(_) => EditorTabChildInfo::Keymap,
             EditorTabChild::Volt(_, id) => EditorTabChildInfo::Volt(id.to_owned()),
+            EditorTabChild::Image(_, path) => {
+                EditorTabChildInfo::Image(path.to_owned())
+            }
+            EditorTabChild::MarkdownPreview(_, path) => {
+                EditorTabChildInfo::MarkdownPreview(path.to_owned())
+            }
         }
     }
 
@@ -221,7 +254,9 @@ pub fn view_info(
                             Some((path, confirmed, is_pristine))
                         }
                         DocContent::Local => None,
-                        DocContent::History(_) => None,
+                        DocContent::History(history) => {
+                            Some((history.path, confirmed, is_pristine))
+                        }
                         DocContent::Scratch { name, .. } => {
                             Some((PathBuf::from(name), confirmed, is_pristine))
                         }
@@ -396,6 +431,36 @@ pub fn view_info(
                     is_pristine: true,
                 }
             }),
+            EditorTabChild::Image(_, path) => create_memo(move |_| {
+                let config = config.get();
+                let (icon, color) = config.file_svg(&path);
+                EditorTabChildViewInfo {
+                    icon,
+                    color,
+                    name: path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    path: Some(path.clone()),
+                    confirmed: None,
+                    is_pristine: true,
+                }
+            }),
+            EditorTabChild::MarkdownPreview(_, path) => create_memo(move |_| {
+                let config = config.get();
+                EditorTabChildViewInfo {
+                    icon: config.ui_svg(LapceIcons::MARKDOWN_PREVIEW),
+                    color: Some(config.color(LapceColor::LAPCE_ICON_ACTIVE)),
+                    name: format!(
+                        "Preview {}",
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    ),
+                    path: None,
+                    confirmed: None,
+                    is_pristine: true,
+                }
+            }),
         }
     }
 }
@@ -407,6 +472,8 @@ pub struct EditorTabData {
     pub editor_tab_id: EditorTabId,
     pub active: usize,
     pub children: Vec<(RwSignal<usize>, RwSignal<Rect>, EditorTabChild)>,
+    /// The children which aren't closed along with the other tabs.
+    pub pinned: im::HashSet<EditorTabChild>,
     pub window_origin: Point,
     pub layout_rect: Rect,
     pub locations: RwSignal<im::Vector<EditorLocation>>,
@@ -438,6 +505,10 @@ pub fn get_editor(
         None
     }
 
+    pub fn is_pinned(&self, child: &EditorTabChild) -> bool {
+        self.pinned.contains(child)
+    }
+
     pub fn get_unconfirmed_editor_tab_child(
         &self,
         editors: Editors,
@@ -477,6 +548,13 @@ pub fn tab_info(&self, data: &WindowTabData) -> EditorTabInfo {
                 .iter()
                 .map(|(_, _, child)| child.child_info(data))
                 .collect(),
+            pinned: self
+                .children
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, _, child))| self.pinned.contains(child))
+                .map(|(i, _)| i)
+                .collect(),
         };
         info
     }
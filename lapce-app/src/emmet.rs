@@ -0,0 +1,858 @@
+//! Expansion of [Emmet](https://emmet.io) abbreviations, like `ul>li*3>a` into
+//! nested elements or `m10` into a CSS declaration. The expansions are snippets,
+//! with tab stops at the empty attribute values and element contents.
+
+use lapce_core::language::LapceLanguage;
+
+use crate::user_snippet::escape;
+
+/// The elements that may be written in an abbreviation without any operator, so
+/// that ordinary words aren't expanded.
+const KNOWN_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "address",
+    "area",
+    "article",
+    "aside",
+    "audio",
+    "b",
+    "base",
+    "blockquote",
+    "body",
+    "br",
+    "button",
+    "canvas",
+    "caption",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "embed",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "head",
+    "header",
+    "hr",
+    "html",
+    "i",
+    "iframe",
+    "img",
+    "input",
+    "label",
+    "legend",
+    "li",
+    "link",
+    "main",
+    "meta",
+    "nav",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "pre",
+    "script",
+    "section",
+    "select",
+    "small",
+    "source",
+    "span",
+    "strong",
+    "style",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "template",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "title",
+    "tr",
+    "track",
+    "u",
+    "ul",
+    "video",
+];
+
+/// The elements that have no content or closing tag.
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "source", "track", "wbr",
+];
+
+/// The elements that stay on the line of their parent.
+const INLINE_TAGS: &[&str] = &[
+    "a", "abbr", "b", "br", "button", "code", "em", "i", "img", "input", "label",
+    "small", "span", "strong", "sub", "sup", "u",
+];
+
+/// The attributes an element is given unless the abbreviation sets them.
+const DEFAULT_ATTRS: &[(&str, &[(&str, &str)])] = &[
+    ("a", &[("href", "")]),
+    ("form", &[("action", "")]),
+    ("iframe", &[("src", "")]),
+    ("img", &[("src", ""), ("alt", "")]),
+    ("input", &[("type", "text")]),
+    ("label", &[("for", "")]),
+    ("link", &[("rel", "stylesheet"), ("href", "")]),
+];
+
+const CSS_PROPERTIES: &[(&str, &str)] = &[
+    ("ai", "align-items"),
+    ("b", "bottom"),
+    ("bd", "border"),
+    ("bg", "background"),
+    ("bgc", "background-color"),
+    ("br", "border-radius"),
+    ("bxsh", "box-shadow"),
+    ("bxz", "box-sizing"),
+    ("c", "color"),
+    ("cur", "cursor"),
+    ("d", "display"),
+    ("ff", "font-family"),
+    ("fs", "font-style"),
+    ("fw", "font-weight"),
+    ("fx", "flex"),
+    ("fxd", "flex-direction"),
+    ("fz", "font-size"),
+    ("g", "gap"),
+    ("h", "height"),
+    ("jc", "justify-content"),
+    ("l", "left"),
+    ("lh", "line-height"),
+    ("lts", "letter-spacing"),
+    ("m", "margin"),
+    ("mah", "max-height"),
+    ("maw", "max-width"),
+    ("mb", "margin-bottom"),
+    ("mih", "min-height"),
+    ("miw", "min-width"),
+    ("ml", "margin-left"),
+    ("mr", "margin-right"),
+    ("mt", "margin-top"),
+    ("op", "opacity"),
+    ("ov", "overflow"),
+    ("p", "padding"),
+    ("pb", "padding-bottom"),
+    ("pl", "padding-left"),
+    ("pos", "position"),
+    ("pr", "padding-right"),
+    ("pt", "padding-top"),
+    ("r", "right"),
+    ("t", "top"),
+    ("ta", "text-align"),
+    ("td", "text-decoration"),
+    ("trs", "transition"),
+    ("tt", "text-transform"),
+    ("w", "width"),
+    ("z", "z-index"),
+];
+
+/// The properties whose numbers have no unit.
+const CSS_UNITLESS: &[&str] = &["flex", "font-weight", "opacity", "z-index"];
+
+const CSS_KEYWORDS: &[(&str, &str)] = &[
+    ("aic", "align-items: center"),
+    ("bxzbb", "box-sizing: border-box"),
+    ("curp", "cursor: pointer"),
+    ("db", "display: block"),
+    ("df", "display: flex"),
+    ("dg", "display: grid"),
+    ("di", "display: inline"),
+    ("dib", "display: inline-block"),
+    ("dif", "display: inline-flex"),
+    ("dn", "display: none"),
+    ("fsi", "font-style: italic"),
+    ("fwb", "font-weight: bold"),
+    ("fwn", "font-weight: normal"),
+    ("fxdc", "flex-direction: column"),
+    ("fxdr", "flex-direction: row"),
+    ("jcc", "justify-content: center"),
+    ("jcsb", "justify-content: space-between"),
+    ("ova", "overflow: auto"),
+    ("ovh", "overflow: hidden"),
+    ("posa", "position: absolute"),
+    ("posf", "position: fixed"),
+    ("posr", "position: relative"),
+    ("poss", "position: sticky"),
+    ("tac", "text-align: center"),
+    ("taj", "text-align: justify"),
+    ("tal", "text-align: left"),
+    ("tar", "text-align: right"),
+    ("tdn", "text-decoration: none"),
+    ("tdu", "text-decoration: underline"),
+    ("ttl", "text-transform: lowercase"),
+    ("ttu", "text-transform: uppercase"),
+];
+
+/// What abbreviations expand into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmmetSyntax {
+    Html,
+    /// Elements within JavaScript, with `className` and `htmlFor` attributes and
+    /// closed void elements.
+    Jsx,
+    Css,
+}
+
+impl EmmetSyntax {
+    pub fn from_language(language: LapceLanguage) -> Option<Self> {
+        match language {
+            LapceLanguage::Html | LapceLanguage::Vue | LapceLanguage::Svelte => {
+                Some(EmmetSyntax::Html)
+            }
+            LapceLanguage::Jsx | LapceLanguage::Tsx => Some(EmmetSyntax::Jsx),
+            LapceLanguage::Css | LapceLanguage::Scss => Some(EmmetSyntax::Css),
+            _ => None,
+        }
+    }
+}
+
+/// Find the abbreviation at the end of `line`, the text of a line before the
+/// cursor, and expand it. Returns the offset of the abbreviation in `line` and
+/// the snippet it expands into.
+///
+/// `indent` is the indentation of the line and `indent_unit` that of each level of
+/// nested elements. Whether JSX abbreviations are where markup can be written,
+/// rather than JavaScript, is left to the syntax tree of the caller.
+pub fn expand_at_end(
+    line: &str,
+    syntax: EmmetSyntax,
+    indent: &str,
+    indent_unit: &str,
+) -> Option<(usize, String)> {
+    let start = abbreviation_start(line)?;
+    let before = &line[..start];
+    let abbreviation = &line[start..];
+    let snippet = match syntax {
+        EmmetSyntax::Css => {
+            // Only expand where a declaration starts, rather than in selectors or
+            // values.
+            let before = before.trim_end();
+            if !(before.is_empty() || before.ends_with([';', '{'])) {
+                return None;
+            }
+            expand_css(abbreviation)?
+        }
+        EmmetSyntax::Html | EmmetSyntax::Jsx => {
+            // Within a tag the text is an attribute rather than an abbreviation.
+            if before.rfind('<') > before.rfind('>') {
+                return None;
+            }
+            expand_markup(abbreviation, syntax, indent, indent_unit)?
+        }
+    };
+    Some((start, snippet))
+}
+
+/// The offset of the abbreviation at the end of `line`, which takes any character
+/// within attribute brackets and text braces.
+fn abbreviation_start(line: &str) -> Option<usize> {
+    let mut start = line.len();
+    let mut depth = 0usize;
+    for (i, c) in line.char_indices().rev() {
+        if depth > 0 {
+            match c {
+                ']' | '}' => depth += 1,
+                '[' | '{' => depth -= 1,
+                _ => {}
+            }
+        } else {
+            match c {
+                ']' | '}' => depth += 1,
+                c if c.is_alphanumeric() || "-_.#*>+^$()%".contains(c) => {}
+                _ => break,
+            }
+        }
+        start = i;
+    }
+    let first = line[start..].chars().next()?;
+    (depth == 0 && (first.is_alphabetic() || ".#([".contains(first)))
+        .then_some(start)
+}
+
+/// An element of an abbreviation, or a group of them in parentheses.
+#[derive(Debug, Default)]
+struct Node {
+    group: bool,
+    name: String,
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, String)>,
+    text: Option<String>,
+    repeat: usize,
+    children: Vec<Node>,
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.pos += c.len_utf8();
+        }
+        eaten
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        let len = self.text[start..]
+            .find(|c| !f(c))
+            .unwrap_or(self.text.len() - start);
+        self.pos += len;
+        &self.text[start..start + len]
+    }
+
+    /// Parse the elements up to the end of the abbreviation or of a group.
+    fn parse_list(&mut self) -> Option<Vec<Node>> {
+        let mut nodes = Vec::new();
+        loop {
+            let (mut siblings, climb) = self.parse_siblings()?;
+            nodes.append(&mut siblings);
+            // Climbing above the top level stays at the top level.
+            if climb == 0 {
+                return Some(nodes);
+            }
+        }
+    }
+
+    /// Parse siblings, and the children of each, until the end of the list or a
+    /// climb with `^` above their level. Returns the number of levels to climb
+    /// still.
+    fn parse_siblings(&mut self) -> Option<(Vec<Node>, usize)> {
+        let mut nodes = Vec::new();
+        loop {
+            let mut node = self.parse_term()?;
+            if self.eat('>') {
+                let (children, climb) = self.parse_siblings()?;
+                node.children = children;
+                nodes.push(node);
+                match climb {
+                    0 => return Some((nodes, 0)),
+                    1 => continue,
+                    climb => return Some((nodes, climb - 1)),
+                }
+            }
+            nodes.push(node);
+            if self.eat('+') {
+                continue;
+            }
+            let mut climb = 0;
+            while self.eat('^') {
+                climb += 1;
+            }
+            return Some((nodes, climb));
+        }
+    }
+
+    fn parse_term(&mut self) -> Option<Node> {
+        let mut node = if self.eat('(') {
+            let children = self.parse_list()?;
+            if !self.eat(')') {
+                return None;
+            }
+            Node {
+                group: true,
+                children,
+                ..Default::default()
+            }
+        } else {
+            self.parse_element()?
+        };
+        node.repeat = if self.eat('*') {
+            self.take_while(|c| c.is_ascii_digit()).parse().ok()?
+        } else {
+            1
+        };
+        Some(node)
+    }
+
+    fn parse_element(&mut self) -> Option<Node> {
+        let is_name = |c: char| c.is_alphanumeric() || c == '-' || c == '$';
+        let mut node = Node {
+            name: self.take_while(is_name).to_string(),
+            ..Default::default()
+        };
+        loop {
+            if self.eat('.') {
+                let class = self.take_while(|c| is_name(c) || c == '_');
+                if class.is_empty() {
+                    return None;
+                }
+                node.classes.push(class.to_string());
+            } else if self.eat('#') {
+                let id = self.take_while(|c| is_name(c) || c == '_');
+                if id.is_empty() {
+                    return None;
+                }
+                node.id = Some(id.to_string());
+            } else if self.eat('[') {
+                self.parse_attrs(&mut node)?;
+            } else if self.eat('{') {
+                node.text = Some(self.take_while(|c| c != '}').to_string());
+                if !self.eat('}') {
+                    return None;
+                }
+            } else {
+                break;
+            }
+        }
+        let empty = node.name.is_empty()
+            && node.id.is_none()
+            && node.classes.is_empty()
+            && node.attrs.is_empty()
+            && node.text.is_none();
+        (!empty).then_some(node)
+    }
+
+    /// Parse the attributes of `[name=value other="quoted value"]`, after the
+    /// opening bracket.
+    fn parse_attrs(&mut self, node: &mut Node) -> Option<()> {
+        loop {
+            self.take_while(char::is_whitespace);
+            if self.eat(']') {
+                return Some(());
+            }
+            let name = self
+                .take_while(|c| !c.is_whitespace() && c != '=' && c != ']')
+                .to_string();
+            if name.is_empty() {
+                return None;
+            }
+            let value = if self.eat('=') {
+                match self.peek()? {
+                    quote @ ('"' | '\'') => {
+                        self.eat(quote);
+                        let value = self.take_while(|c| c != quote).to_string();
+                        if !self.eat(quote) {
+                            return None;
+                        }
+                        value
+                    }
+                    _ => self
+                        .take_while(|c| !c.is_whitespace() && c != ']')
+                        .to_string(),
+                }
+            } else {
+                String::new()
+            };
+            node.attrs.push((name, value));
+        }
+    }
+}
+
+/// An element with its repetitions, groups and numbering resolved.
+struct Element {
+    tag: String,
+    attrs: Vec<(String, String)>,
+    text: Option<String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn is_void(&self) -> bool {
+        VOID_TAGS.contains(&self.tag.as_str())
+    }
+
+    fn is_inline(&self) -> bool {
+        INLINE_TAGS.contains(&self.tag.as_str())
+            && self.children.iter().all(Element::is_inline)
+    }
+}
+
+fn expand_markup(
+    abbreviation: &str,
+    syntax: EmmetSyntax,
+    indent: &str,
+    indent_unit: &str,
+) -> Option<String> {
+    let mut parser = Parser {
+        text: abbreviation,
+        pos: 0,
+    };
+    let nodes = parser.parse_list()?;
+    if parser.pos != abbreviation.len() {
+        return None;
+    }
+    // A lone word is only an abbreviation when it names an element, so that
+    // Tab after ordinary text still indents.
+    let has_operator = abbreviation.contains(|c| ">+^*.#[{(".contains(c));
+    if !nodes_have_valid_names(&nodes, syntax, has_operator) {
+        return None;
+    }
+
+    let elements = resolve(&nodes, "", 1);
+    let mut renderer = Renderer {
+        syntax,
+        indent,
+        indent_unit,
+        tabstop: 0,
+        out: String::new(),
+    };
+    for (i, element) in elements.iter().enumerate() {
+        if i > 0 {
+            renderer.newline(0);
+        }
+        renderer.element(element, 0);
+    }
+    Some(renderer.out)
+}
+
+fn nodes_have_valid_names(
+    nodes: &[Node],
+    syntax: EmmetSyntax,
+    has_operator: bool,
+) -> bool {
+    nodes.iter().all(|node| {
+        let name = node.name.as_str();
+        let valid = if name.is_empty() {
+            node.group || has_operator
+        } else {
+            KNOWN_TAGS.contains(&name)
+                // Custom elements, and components in JSX.
+                || name.contains('-')
+                || (syntax == EmmetSyntax::Jsx
+                    && name.starts_with(|c: char| c.is_uppercase()))
+        };
+        valid && nodes_have_valid_names(&node.children, syntax, has_operator)
+    })
+}
+
+/// Repeat the nodes, flatten their groups and number them, for an `index` of
+/// the closest repeated ancestor.
+fn resolve(nodes: &[Node], parent: &str, index: usize) -> Vec<Element> {
+    let mut elements = Vec::new();
+    for node in nodes {
+        for i in 1..=node.repeat {
+            let index = if node.repeat > 1 { i } else { index };
+            if node.group {
+                elements.extend(resolve(&node.children, parent, index));
+                continue;
+            }
+
+            let tag = if node.name.is_empty() {
+                implicit_tag(parent).to_string()
+            } else {
+                number(&node.name, index)
+            };
+            let mut attrs: Vec<(String, String)> = DEFAULT_ATTRS
+                .iter()
+                .find(|(t, _)| *t == tag)
+                .map(|(_, attrs)| {
+                    attrs
+                        .iter()
+                        .filter(|(name, _)| {
+                            !node.attrs.iter().any(|(n, _)| n == name)
+                        })
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let Some(id) = &node.id {
+                attrs.push(("id".to_string(), number(id, index)));
+            }
+            if !node.classes.is_empty() {
+                let classes = node
+                    .classes
+                    .iter()
+                    .map(|class| number(class, index))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                attrs.push(("class".to_string(), classes));
+            }
+            attrs.extend(
+                node.attrs
+                    .iter()
+                    .map(|(name, value)| (name.clone(), number(value, index))),
+            );
+
+            elements.push(Element {
+                children: resolve(&node.children, &tag, index),
+                tag,
+                attrs,
+                text: node.text.as_ref().map(|text| number(text, index)),
+            });
+        }
+    }
+    elements
+}
+
+/// The element of an abbreviation without a name, like `.item`, within `parent`.
+fn implicit_tag(parent: &str) -> &'static str {
+    match parent {
+        "ul" | "ol" => "li",
+        "table" | "tbody" | "thead" | "tfoot" => "tr",
+        "tr" => "td",
+        "select" | "optgroup" => "option",
+        parent if INLINE_TAGS.contains(&parent) => "span",
+        _ => "div",
+    }
+}
+
+/// Replace each run of `$` with `index`, padded with zeros to the length of the
+/// run.
+fn number(text: &str, index: usize) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let len = rest[start..]
+            .find(|c| c != '$')
+            .unwrap_or(rest.len() - start);
+        result.push_str(&format!("{index:0len$}"));
+        rest = &rest[start + len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+struct Renderer<'a> {
+    syntax: EmmetSyntax,
+    indent: &'a str,
+    indent_unit: &'a str,
+    tabstop: usize,
+    out: String,
+}
+
+impl Renderer<'_> {
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        self.out.push_str(self.indent);
+        for _ in 0..depth {
+            self.out.push_str(self.indent_unit);
+        }
+    }
+
+    fn tabstop(&mut self) {
+        self.tabstop += 1;
+        self.out.push_str(&format!("${{{}}}", self.tabstop));
+    }
+
+    fn element(&mut self, element: &Element, depth: usize) {
+        self.out.push('<');
+        self.out.push_str(&escape(&element.tag));
+        for (name, value) in &element.attrs {
+            let name = match (self.syntax, name.as_str()) {
+                (EmmetSyntax::Jsx, "class") => "className",
+                (EmmetSyntax::Jsx, "for") => "htmlFor",
+                (_, name) => name,
+            };
+            self.out.push_str(&format!(" {}=\"", escape(name)));
+            if value.is_empty() {
+                self.tabstop();
+            } else {
+                self.out.push_str(&escape(value));
+            }
+            self.out.push('"');
+        }
+        if element.is_void() {
+            self.out.push_str(match self.syntax {
+                EmmetSyntax::Jsx => " />",
+                _ => ">",
+            });
+            return;
+        }
+        self.out.push('>');
+
+        if element.children.is_empty() {
+            match &element.text {
+                Some(text) => self.out.push_str(&escape(text)),
+                None => self.tabstop(),
+            }
+        } else if element.children.iter().all(Element::is_inline) {
+            if let Some(text) = &element.text {
+                self.out.push_str(&escape(text));
+            }
+            for child in &element.children {
+                self.element(child, depth);
+            }
+        } else {
+            if let Some(text) = &element.text {
+                self.newline(depth + 1);
+                self.out.push_str(&escape(text));
+            }
+            for child in &element.children {
+                self.newline(depth + 1);
+                self.element(child, depth + 1);
+            }
+            self.newline(depth);
+        }
+
+        self.out.push_str(&format!("</{}>", escape(&element.tag)));
+    }
+}
+
+fn expand_css(abbreviation: &str) -> Option<String> {
+    if let Some((_, declaration)) =
+        CSS_KEYWORDS.iter().find(|(a, _)| *a == abbreviation)
+    {
+        return Some(format!("{declaration};"));
+    }
+
+    let property_len = abbreviation
+        .find(|c: char| !c.is_ascii_lowercase())
+        .unwrap_or(abbreviation.len());
+    let (property, value) = abbreviation.split_at(property_len);
+    let (_, property) = CSS_PROPERTIES.iter().find(|(a, _)| *a == property)?;
+    let value = if value.is_empty() {
+        "${1}".to_string()
+    } else if let Some(hex) = value.strip_prefix('#') {
+        css_color(hex)?
+    } else {
+        css_numbers(value, CSS_UNITLESS.contains(property))?
+    };
+    Some(format!("{property}: {value};"))
+}
+
+/// Expand a short hex color like `f` or `fc` to `#fff` or `#fcfcfc`.
+fn css_color(hex: &str) -> Option<String> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    match hex.len() {
+        1 | 2 => Some(format!("#{}", hex.repeat(3))),
+        3 | 6 => Some(format!("#{hex}")),
+        _ => None,
+    }
+}
+
+/// Expand numbers separated by `-`, like `10-20`, with their units. A number
+/// without a unit is in `px`, or `em` when it has a fraction, and the units `p`,
+/// `e`, `r` and `x` are short for `%`, `em`, `rem` and `ex`.
+fn css_numbers(value: &str, unitless: bool) -> Option<String> {
+    let mut numbers = Vec::new();
+    let mut rest = value;
+    while !rest.is_empty() {
+        if !numbers.is_empty() {
+            rest = rest.strip_prefix('-')?;
+        }
+        let (sign, body) = match rest.strip_prefix('-') {
+            Some(body) => ("-", body),
+            None => ("", rest),
+        };
+        let number_len = body
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(body.len());
+        let number = &body[..number_len];
+        let value: f64 = number.parse().ok()?;
+        let unit_len = body[number_len..]
+            .find(|c: char| !c.is_ascii_alphabetic() && c != '%')
+            .unwrap_or(body.len() - number_len);
+        let unit = match &body[number_len..number_len + unit_len] {
+            _ if unitless => "",
+            "" if value == 0.0 => "",
+            "" if number.contains('.') => "em",
+            "" => "px",
+            "p" => "%",
+            "e" => "em",
+            "r" => "rem",
+            "x" => "ex",
+            unit => unit,
+        };
+        numbers.push(format!("{sign}{number}{unit}"));
+        rest = &body[number_len + unit_len..];
+    }
+    Some(numbers.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(line: &str, syntax: EmmetSyntax) -> Option<String> {
+        expand_at_end(line, syntax, "", "  ").map(|(_, snippet)| snippet)
+    }
+
+    #[test]
+    fn test_expand_markup() {
+        assert_eq!(
+            expand("ul>li*3>a", EmmetSyntax::Html).unwrap(),
+            "<ul>\n  <li><a href=\"${1}\">${2}</a></li>\n  \
+             <li><a href=\"${3}\">${4}</a></li>\n  \
+             <li><a href=\"${5}\">${6}</a></li>\n</ul>"
+        );
+        assert_eq!(
+            expand("div.a.b#c{hi}", EmmetSyntax::Html).unwrap(),
+            "<div id=\"c\" class=\"a b\">hi</div>"
+        );
+        assert_eq!(
+            expand("(h1+p)+.x>span^hr", EmmetSyntax::Html).unwrap(),
+            "<h1>${1}</h1>\n<p>${2}</p>\n<div class=\"x\"><span>${3}</span></div>\n<hr>"
+        );
+        assert_eq!(
+            expand("li.item$$*2", EmmetSyntax::Html).unwrap(),
+            "<li class=\"item01\">${1}</li>\n<li class=\"item02\">${2}</li>"
+        );
+        assert_eq!(
+            expand("input[name=q disabled]", EmmetSyntax::Html).unwrap(),
+            "<input type=\"text\" name=\"q\" disabled=\"${1}\">"
+        );
+        assert_eq!(
+            expand("label.l+img", EmmetSyntax::Jsx).unwrap(),
+            "<label htmlFor=\"${1}\" className=\"l\">${2}</label>\n\
+             <img src=\"${3}\" alt=\"${4}\" />"
+        );
+    }
+
+    #[test]
+    fn test_expand_markup_position() {
+        assert_eq!(
+            expand_at_end("  text p>b", EmmetSyntax::Html, "  ", "  "),
+            Some((7, "<p><b>${1}</b></p>".to_string()))
+        );
+        // Ordinary words, attributes and JavaScript aren't expanded.
+        assert_eq!(expand("hello", EmmetSyntax::Html), None);
+        assert_eq!(expand("<a href", EmmetSyntax::Html), None);
+        assert_eq!(expand("this.props", EmmetSyntax::Jsx), None);
+        assert_eq!(expand("Item.", EmmetSyntax::Html), None);
+        assert_eq!(expand("", EmmetSyntax::Html), None);
+        assert!(expand("MyComponent", EmmetSyntax::Jsx).is_some());
+    }
+
+    #[test]
+    fn test_expand_css() {
+        assert_eq!(expand("  m10", EmmetSyntax::Css).unwrap(), "margin: 10px;");
+        assert_eq!(
+            expand("p10-2e--5p", EmmetSyntax::Css).unwrap(),
+            "padding: 10px 2em -5%;"
+        );
+        assert_eq!(expand("w1.5", EmmetSyntax::Css).unwrap(), "width: 1.5em;");
+        assert_eq!(expand("m0", EmmetSyntax::Css).unwrap(), "margin: 0;");
+        assert_eq!(expand("z10", EmmetSyntax::Css).unwrap(), "z-index: 10;");
+        assert_eq!(expand("c#f", EmmetSyntax::Css).unwrap(), "color: #fff;");
+        assert_eq!(expand("dn", EmmetSyntax::Css).unwrap(), "display: none;");
+        assert_eq!(
+            expand("{ pos", EmmetSyntax::Css).unwrap(),
+            "position: ${1};"
+        );
+        assert_eq!(expand("color: red", EmmetSyntax::Css), None);
+        assert_eq!(expand("xyz", EmmetSyntax::Css), None);
+    }
+}
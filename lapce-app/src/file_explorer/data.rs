@@ -14,24 +14,28 @@
     keyboard::Modifiers,
     menu::{Menu, MenuItem},
     reactive::{ReadSignal, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
-    views::editor::text::SystemClipboard,
+    views::{editor::text::SystemClipboard, VirtualVector},
 };
 use globset::Glob;
 use lapce_core::{
-    command::{EditCommand, FocusCommand},
+    command::{EditCommand, FocusCommand, MoveCommand},
     mode::Mode,
     register::Clipboard,
 };
 use lapce_rpc::{
     file::{
-        Duplicating, FileNodeItem, FileNodeViewKind, Naming, NamingState, NewNode,
-        Renaming,
+        Duplicating, FileNodeItem, FileNodeViewData, FileNodeViewKind, Naming,
+        NamingState, NewNode, Renaming,
     },
     proxy::ProxyResponse,
 };
 
+use super::node::FileNodeVirtualList;
 use crate::{
-    command::{CommandExecuted, CommandKind, InternalCommand, LapceCommand},
+    command::{
+        CommandExecuted, CommandKind, InternalCommand, LapceCommand,
+        LapceWorkbenchCommand,
+    },
     config::LapceConfig,
     editor::EditorData,
     keypress::{condition::Condition, KeyPressFocus},
@@ -48,9 +52,23 @@ enum RenamedPath {
     },
 }
 
+/// The unread, closed node of the directory at the root of a tree.
+fn root_node(path: PathBuf) -> FileNodeItem {
+    FileNodeItem {
+        path,
+        is_dir: true,
+        read: false,
+        open: false,
+        children: HashMap::new(),
+        children_open_count: 0,
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct FileExplorerData {
     pub root: RwSignal<FileNodeItem>,
+    /// The trees of the folders added to the workspace besides its root.
+    pub folders: RwSignal<im::Vector<RwSignal<FileNodeItem>>>,
     pub naming: RwSignal<Naming>,
     pub naming_editor_data: EditorData,
     pub common: Rc<CommonData>,
@@ -65,8 +83,11 @@ fn get_mode(&self) -> Mode {
     }
 
     fn check_condition(&self, condition: Condition) -> bool {
-        self.naming.with_untracked(Naming::is_accepting_input)
-            && condition == Condition::ModalFocus
+        if self.naming.with_untracked(Naming::is_accepting_input) {
+            condition == Condition::ModalFocus
+        } else {
+            matches!(condition, Condition::PanelFocus | Condition::ListFocus)
+        }
     }
 
     fn run_command(
@@ -100,7 +121,7 @@ fn run_command(
                 _ => self.naming_editor_data.run_command(command, count, mods),
             }
         } else {
-            CommandExecuted::No
+            self.run_list_command(command)
         }
     }
 
@@ -120,18 +141,12 @@ fn receive_char(&self, c: &str) {
 impl FileExplorerData {
     pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
         let path = common.workspace.path.clone().unwrap_or_default();
-        let root = cx.create_rw_signal(FileNodeItem {
-            path: path.clone(),
-            is_dir: true,
-            read: false,
-            open: false,
-            children: HashMap::new(),
-            children_open_count: 0,
-        });
+        let root = cx.create_rw_signal(root_node(path.clone()));
         let naming = cx.create_rw_signal(Naming::None);
         let naming_editor_data = editors.make_local(cx, common.clone());
         let data = Self {
             root,
+            folders: cx.create_rw_signal(im::Vector::new()),
             naming,
             naming_editor_data,
             common,
@@ -146,18 +161,95 @@ pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
         data
     }
 
-    /// Reload the file explorer data via reading the root directory.  
+    /// Reload the file explorer data via reading the root directory and those
+    /// of the folders read.
     /// Note that this will not update immediately.
     pub fn reload(&self) {
         let path = self.root.with_untracked(|root| root.path.clone());
         self.read_dir(&path);
+        for folder in self.folders.get_untracked() {
+            let (path, read) =
+                folder.with_untracked(|folder| (folder.path.clone(), folder.read));
+            if read {
+                self.read_dir(&path);
+            }
+        }
+    }
+
+    /// Show the trees of `folders` after the one of the root, keeping the
+    /// state of those already shown.
+    pub fn set_folders(&self, folders: &[PathBuf]) {
+        let current = self.folders.get_untracked();
+        let mut added = Vec::new();
+        let folders = folders
+            .iter()
+            .map(|path| {
+                current
+                    .iter()
+                    .find(|folder| folder.with_untracked(|f| &f.path == path))
+                    .copied()
+                    .unwrap_or_else(|| {
+                        added.push(path.clone());
+                        self.common.scope.create_rw_signal(root_node(path.clone()))
+                    })
+            })
+            .collect();
+        self.folders.set(folders);
+        for path in added {
+            self.toggle_expand(&path);
+        }
+    }
+
+    /// The tree holding `path`, the innermost one of a folder inside another.
+    fn root_of(&self, path: &Path) -> Option<RwSignal<FileNodeItem>> {
+        std::iter::once(self.root)
+            .chain(self.folders.get_untracked())
+            .filter(|root| root.with_untracked(|root| path.starts_with(&root.path)))
+            .max_by_key(|root| {
+                root.with_untracked(|root| root.path.as_os_str().len())
+            })
+    }
+
+    /// The line of `path` in the explorer, if its tree is open down to it.
+    fn find_file_at_line(&self, path: &Path) -> (bool, f64) {
+        let Some(root_path) = self
+            .root_of(path)
+            .map(|root| root.with_untracked(|root| root.path.clone()))
+        else {
+            return (false, 0.0);
+        };
+        let (in_root, mut line) = self.root.with_untracked(|root| {
+            (root.path == root_path, root.children_open_count)
+        });
+        if in_root {
+            return self
+                .root
+                .with_untracked(|root| root.find_file_at_line(path));
+        }
+        for folder in self.folders.get_untracked() {
+            let (in_folder, count) = folder.with_untracked(|folder| {
+                (folder.path == root_path, folder.children_open_count)
+            });
+            if in_folder {
+                let (found, inner) = if root_path == path {
+                    (true, 0.0)
+                } else {
+                    folder.with_untracked(|folder| folder.find_file_at_line(path))
+                };
+                return (found, (line + 1) as f64 + inner);
+            }
+            line += count + 1;
+        }
+        (false, 0.0)
     }
 
     /// Toggle whether the directory is expanded or not.  
     /// Does nothing if the path does not exist or is not a directory.
     pub fn toggle_expand(&self, path: &Path) {
-        let Some(read) = self
-            .root
+        let Some(root) = self.root_of(path) else {
+            return;
+        };
+        let Some(read) = root
             .try_update(|root| {
                 let read = if let Some(node) = root.get_file_node_mut(path) {
                     if !node.is_dir {
@@ -193,7 +285,10 @@ pub fn read_dir(&self, path: &Path) {
     /// `done : FnOnce(was_read: bool)` is called when the operation is completed, whether success,
     /// failure, or ignored.
     pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
-        let root = self.root;
+        let Some(root) = self.root_of(path) else {
+            done(false);
+            return;
+        };
         let data = self.clone();
         let config = self.common.config;
         let send = {
@@ -204,6 +299,7 @@ pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
                     return;
                 };
 
+                let mut reread = Vec::new();
                 root.update(|root| {
                     // Get the node for this path, which should already exist if we're calling
                     // read_dir on it.
@@ -239,7 +335,7 @@ pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
                         for item in items {
                             if let Some(existing) = node.children.get(&item.path) {
                                 if existing.read {
-                                    data.read_dir(&existing.path);
+                                    reread.push(existing.path.clone());
                                 }
                             } else {
                                 node.children.insert(item.path.clone(), item);
@@ -248,6 +344,9 @@ pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
                     }
                     root.update_node_count_recursive(&path);
                 });
+                for path in reread {
+                    data.read_dir(&path);
+                }
 
                 done(true);
             })
@@ -260,8 +359,10 @@ pub fn read_dir_cb(&self, path: &Path, done: impl FnOnce(bool) + 'static) {
     /// Returns `true` if `path` exists in the file explorer tree and is a directory, `false`
     /// otherwise.
     fn is_dir(&self, path: &Path) -> bool {
-        self.root.with_untracked(|root| {
-            root.get_file_node(path).is_some_and(|node| node.is_dir)
+        self.root_of(path).is_some_and(|root| {
+            root.with_untracked(|root| {
+                root.get_file_node(path).is_some_and(|node| node.is_dir)
+            })
         })
     }
 
@@ -396,6 +497,141 @@ pub fn cancel_naming(&self) {
         self.naming.set(Naming::None);
     }
 
+    fn run_list_command(&self, command: &LapceCommand) -> CommandExecuted {
+        match &command.kind {
+            CommandKind::Focus(FocusCommand::ListNext) => {
+                self.select_next(1);
+            }
+            CommandKind::Focus(FocusCommand::ListPrevious) => {
+                self.select_next(-1);
+            }
+            CommandKind::Focus(FocusCommand::ListNextPage) => {
+                self.select_next(10);
+            }
+            CommandKind::Focus(FocusCommand::ListPreviousPage) => {
+                self.select_next(-10);
+            }
+            CommandKind::Focus(FocusCommand::ListSelect) => {
+                let Some(path) = self.selected_path() else {
+                    return CommandExecuted::Yes;
+                };
+                if self.is_dir(&path) {
+                    self.toggle_expand(&path);
+                } else {
+                    self.common
+                        .internal_command
+                        .send(InternalCommand::OpenAndConfirmedFile { path });
+                }
+            }
+            CommandKind::Move(MoveCommand::Right) => {
+                let Some(path) = self.selected_path() else {
+                    return CommandExecuted::Yes;
+                };
+                let closed = self.root_of(&path).is_some_and(|root| {
+                    root.with_untracked(|root| {
+                        root.get_file_node(&path)
+                            .map(|node| node.is_dir && !node.open)
+                            .unwrap_or(false)
+                    })
+                });
+                if closed {
+                    self.toggle_expand(&path);
+                } else {
+                    self.select_next(1);
+                }
+            }
+            CommandKind::Move(MoveCommand::Left) => {
+                let Some(path) = self.selected_path() else {
+                    return CommandExecuted::Yes;
+                };
+                let open = self.root_of(&path).is_some_and(|root| {
+                    root.with_untracked(|root| {
+                        root.get_file_node(&path)
+                            .map(|node| node.is_dir && node.open)
+                            .unwrap_or(false)
+                    })
+                });
+                if open {
+                    self.toggle_expand(&path);
+                } else if let Some(parent) = path.parent() {
+                    // Go up to the parent directory, unless it's the root or
+                    // out of the tree of a folder
+                    let is_folder = self.root_of(&path).is_some_and(|root| {
+                        root.with_untracked(|root| root.path == path)
+                    });
+                    if self.common.workspace.path.as_deref() != Some(parent)
+                        && !is_folder
+                    {
+                        self.select_path(parent.to_path_buf());
+                    }
+                }
+            }
+            CommandKind::Workbench(LapceWorkbenchCommand::ShowContextMenu) => {
+                let path = self
+                    .selected_path()
+                    .or_else(|| self.common.workspace.path.clone());
+                if let Some(path) = path {
+                    self.secondary_click(&path);
+                }
+            }
+            _ => return CommandExecuted::No,
+        }
+        CommandExecuted::Yes
+    }
+
+    /// The nodes as they are listed in the explorer.
+    fn visible_nodes(&self) -> Vec<FileNodeViewData> {
+        let mut list = FileNodeVirtualList::new(
+            self.root.get_untracked(),
+            self.folders
+                .get_untracked()
+                .iter()
+                .map(|folder| folder.get_untracked())
+                .collect(),
+            Naming::None,
+        );
+        let len = list.total_len();
+        list.slice(0..len).collect()
+    }
+
+    fn selected_path(&self) -> Option<PathBuf> {
+        self.select.with_untracked(|select| match select {
+            Some(FileNodeViewKind::Path(path)) => Some(path.clone()),
+            _ => None,
+        })
+    }
+
+    fn select_path(&self, path: PathBuf) {
+        let index = self
+            .visible_nodes()
+            .iter()
+            .position(|node| node.kind.path() == Some(path.as_path()));
+        if let Some(index) = index {
+            self.scroll_to_line.set(Some(index as f64));
+        }
+        self.select.set(Some(FileNodeViewKind::Path(path)));
+    }
+
+    /// Move the selection by `delta` nodes, stopping at either end.
+    fn select_next(&self, delta: isize) {
+        let nodes = self.visible_nodes();
+        if nodes.is_empty() {
+            return;
+        }
+        let current = self.select.with_untracked(|select| {
+            select
+                .as_ref()
+                .and_then(|kind| nodes.iter().position(|node| &node.kind == kind))
+        });
+        let index = match current {
+            Some(i) => (i as isize + delta).clamp(0, nodes.len() as isize - 1),
+            None if delta < 0 => nodes.len() as isize - 1,
+            None => 0,
+        } as usize;
+        self.scroll_to_line.set(Some(index as f64));
+        self.select.set(Some(nodes[index].kind.clone()));
+    }
+
     pub fn click(&self, path: &Path, config: ReadSignal<Arc<LapceConfig>>) {
         if self.is_dir(path) {
             self.toggle_expand(path);
@@ -409,8 +645,10 @@ pub fn click(&self, path: &Path, config: ReadSignal<Arc<LapceConfig>>) {
     }
 
     pub fn reveal_in_file_tree(&self, path: PathBuf) {
-        let done = self
-            .root
+        let Some(root) = self.root_of(&path) else {
+            return;
+        };
+        let (done, read_dir) = root
             .try_update(|root| {
                 // the directories in which the file are located are all readed and opened
                 if root.get_file_node(&path).is_some() {
@@ -422,7 +660,7 @@ pub fn reveal_in_file_tree(&self, path: PathBuf) {
                         }
                     }
                     root.update_node_count_recursive(&path);
-                    true
+                    (true, None)
                 } else {
                     // read and open the directories in which the file are located
                     let mut read_dir = None;
@@ -440,20 +678,19 @@ pub fn reveal_in_file_tree(&self, path: PathBuf) {
                             break;
                         }
                     }
-                    if let (true, Some(dir)) = (exist, read_dir) {
-                        let explorer = self.clone();
-                        let select_path = path.clone();
-                        self.read_dir_cb(&dir, move |_| {
-                            explorer.reveal_in_file_tree(select_path);
-                        })
-                    }
-                    false
+                    (false, read_dir.filter(|_| exist))
                 }
             })
-            .unwrap_or(false);
+            .unwrap_or((false, None));
+        if let Some(dir) = read_dir {
+            let explorer = self.clone();
+            let select_path = path.clone();
+            self.read_dir_cb(&dir, move |_| {
+                explorer.reveal_in_file_tree(select_path);
+            })
+        }
         if done {
-            let (found, line) =
-                self.root.with_untracked(|x| x.find_file_at_line(&path));
+            let (found, line) = self.find_file_at_line(&path);
             if found {
                 self.scroll_to_line.set(Some(line));
                 self.select.set(Some(FileNodeViewKind::Path(path)));
@@ -496,6 +733,9 @@ pub fn secondary_click(&self, path: &Path) {
         };
 
         let is_workspace = path == workspace_path;
+        let is_folder = self.folders.with_untracked(|f| {
+            f.iter().any(|f| f.with_untracked(|f| f.path == path))
+        });
 
         let base_path_a = if is_dir {
             Some(path_a.clone())
@@ -578,7 +818,17 @@ pub fn secondary_click(&self, path: &Path) {
             }));
         }
 
-        if !is_workspace {
+        if is_folder {
+            let path = path_a.clone();
+            let internal_command = common.internal_command;
+            menu = menu.entry(MenuItem::new("Remove Folder from Workspace").action(
+                move || {
+                    internal_command.send(InternalCommand::RemoveWorkspaceFolder {
+                        path: path.clone(),
+                    });
+                },
+            ));
+        } else if !is_workspace {
             let path = path_a.clone();
             menu = menu.entry(MenuItem::new("Rename").action(move || {
                 naming.set(Naming::Renaming(Renaming {
@@ -615,6 +865,18 @@ pub fn secondary_click(&self, path: &Path) {
             }));
         }
 
+        if is_dir {
+            menu = menu.separator();
+
+            let path = path_a.clone();
+            let internal_command = common.internal_command;
+            menu =
+                menu.entry(MenuItem::new("Find in Folder...").action(move || {
+                    internal_command
+                        .send(InternalCommand::FindInFolder { path: path.clone() });
+                }));
+        }
+
         menu = menu.separator();
 
         let path = path_a.clone();
@@ -624,10 +886,12 @@ pub fn secondary_click(&self, path: &Path) {
         }));
 
         let path = path_a.clone();
-        let workspace = common.workspace.clone();
+        let root = self
+            .root_of(&path)
+            .map(|root| root.with_untracked(|root| root.path.clone()));
         menu = menu.entry(MenuItem::new("Copy Relative Path").action(move || {
-            let relative_path = if let Some(workspace_path) = &workspace.path {
-                path.strip_prefix(workspace_path).unwrap_or(&path)
+            let relative_path = if let Some(root) = &root {
+                path.strip_prefix(root).unwrap_or(&path)
             } else {
                 path.as_ref()
             };
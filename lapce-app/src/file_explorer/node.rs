@@ -3,13 +3,21 @@
 
 pub struct FileNodeVirtualList {
     file_node_item: FileNodeItem,
+    /// The folders added to the workspace, listed after the children of the
+    /// root with a node of their own.
+    folders: Vec<FileNodeItem>,
     naming: Naming,
 }
 
 impl FileNodeVirtualList {
-    pub fn new(file_node_item: FileNodeItem, naming: Naming) -> Self {
+    pub fn new(
+        file_node_item: FileNodeItem,
+        folders: Vec<FileNodeItem>,
+        naming: Naming,
+    ) -> Self {
         Self {
             file_node_item,
+            folders,
             naming,
         }
     }
@@ -18,6 +26,11 @@ pub fn new(file_node_item: FileNodeItem, naming: Naming) -> Self {
 impl VirtualVector<FileNodeViewData> for FileNodeVirtualList {
     fn total_len(&self) -> usize {
         self.file_node_item.children_open_count
+            + self
+                .folders
+                .iter()
+                .map(|folder| folder.children_open_count + 1)
+                .sum::<usize>()
     }
 
     fn slice(
@@ -31,7 +44,21 @@ fn slice(
         let max = range.end;
         let mut view_items = Vec::new();
 
-        root.append_children_view_slice(&mut view_items, naming, min, max, 0, 0);
+        let mut i =
+            root.append_children_view_slice(&mut view_items, naming, min, max, 0, 0);
+        for folder in &self.folders {
+            if i > max {
+                break;
+            }
+            i = folder.append_view_slice(
+                &mut view_items,
+                naming,
+                min,
+                max,
+                i + 1,
+                0,
+            );
+        }
 
         view_items.into_iter()
     }
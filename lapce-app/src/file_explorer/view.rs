@@ -35,7 +35,7 @@
     plugin::PluginData,
     source_control::SourceControlData,
     text_input::TextInputBuilder,
-    window_tab::{Focus, WindowTabData},
+    window_tab::{DragContent, Focus, WindowTabData},
 };
 
 /// Blends `foreground` with `background`.
@@ -286,11 +286,14 @@ fn file_explorer_view(
     source_control: SourceControlData,
 ) -> impl View {
     let root = data.root;
+    let folders = data.folders;
     let ui_line_height = data.common.ui_line_height;
     let config = data.common.config;
     let naming = data.naming;
     let scroll_to_line = data.scroll_to_line;
     let select = data.select;
+    let focus = data.common.focus;
+    let dragging = data.common.dragging;
     let secondary_click_data = data.clone();
     let scroll_rect = create_rw_signal(Rect::ZERO);
 
@@ -298,7 +301,13 @@ fn file_explorer_view(
         virtual_stack(
             VirtualDirection::Vertical,
             VirtualItemSize::Fixed(Box::new(move || ui_line_height.get())),
-            move || FileNodeVirtualList::new(root.get(), data.naming.get()),
+            move || {
+                FileNodeVirtualList::new(
+                    root.get(),
+                    folders.get().iter().map(|folder| folder.get()).collect(),
+                    data.naming.get(),
+                )
+            },
             move |node| (node.kind.clone(), node.is_dir, node.open, node.level),
             move |node| {
                 let level = node.level;
@@ -411,6 +420,7 @@ fn file_explorer_view(
                     let double_click_path = path.clone();
                     let secondary_click_path = path.clone();
                     let aux_click_path = path.clone();
+                    let drag_path = path.clone();
                     view.on_click_stop({
                         let kind = kind.clone();
                         move |_| {
@@ -427,16 +437,31 @@ fn file_explorer_view(
                     .on_secondary_click_stop(move |_| {
                         secondary_click_data.secondary_click(&secondary_click_path);
                     })
-                    .on_event_stop(
-                        EventListener::PointerDown,
-                        move |event| {
-                            if let Event::PointerDown(pointer_event) = event {
-                                if pointer_event.button.is_auxiliary() {
-                                    aux_click_data.middle_click(&aux_click_path);
-                                }
+                    .on_event_stop(EventListener::PointerDown, move |event| {
+                        if let Event::PointerDown(pointer_event) = event {
+                            if pointer_event.button.is_auxiliary() {
+                                aux_click_data.middle_click(&aux_click_path);
                             }
-                        },
-                    )
+                        }
+                    })
+                    .draggable()
+                    .on_event_stop(EventListener::DragStart, move |_| {
+                        dragging.set(Some(DragContent::File(drag_path.clone())));
+                    })
+                    .on_event_stop(EventListener::DragEnd, move |_| {
+                        dragging.set(None);
+                    })
+                    .dragging_style(move |s| {
+                        let config = config.get();
+                        s.border(1.0)
+                            .border_radius(6.0)
+                            .border_color(config.color(LapceColor::LAPCE_BORDER))
+                            .background(
+                                config
+                                    .color(LapceColor::PANEL_BACKGROUND)
+                                    .with_alpha_factor(0.7),
+                            )
+                    })
                 } else {
                     view
                 }
@@ -445,6 +470,9 @@ fn file_explorer_view(
         .style(|s| s.absolute().flex_col().min_width_full()),
     )
     .style(|s| s.absolute().size_full().line_height(1.8))
+    .on_event_cont(EventListener::PointerDown, move |_| {
+        focus.set(Focus::Panel(PanelKind::FileExplorer));
+    })
     .on_secondary_click_stop(move |_| {
         if let Naming::None = naming.get_untracked() {
             if let Some(path) = &secondary_click_data.common.workspace.path {
@@ -0,0 +1,210 @@
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobMatcher};
+use lapce_core::{directory::Directory, language::LapceLanguage};
+use lapce_proxy::plugin::wasi::find_all_volts;
+use lapce_rpc::plugin::VoltID;
+use serde::{Deserialize, Deserializer};
+
+use crate::user_snippet::escape;
+
+/// A template to create a new file from, defined by the user in the templates
+/// directory or provided by a plugin through `file-templates` in its `volt.toml`.
+///
+/// The body is a snippet, so it can contain tabstops and placeholders, and
+/// before it's inserted `$FILENAME`, `$FILENAME_BASE`, `$DIRECTORY`, `$DATE`
+/// and `$YEAR` are replaced with the values for the new file.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FileTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// The languages the template is offered for.
+    #[serde(default)]
+    pub languages: Vec<String>,
+    /// A glob the file name has to match for the template to be offered.
+    pub glob: Option<TemplateGlob>,
+    pub body: String,
+}
+
+impl FileTemplate {
+    /// Load the user's templates and the ones of all enabled plugins.
+    pub fn load_all(
+        extra_plugin_paths: &[PathBuf],
+        disabled: impl Fn(&VoltID) -> bool,
+    ) -> Vec<FileTemplate> {
+        let mut templates = Vec::new();
+        if let Some(dir) = Directory::templates_directory() {
+            if let Ok(entries) = std::fs::read_dir(dir) {
+                let mut paths: Vec<PathBuf> = entries
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .collect();
+                paths.sort();
+                templates.extend(paths.iter().filter_map(|path| Self::load(path)));
+            }
+        }
+        for meta in find_all_volts(extra_plugin_paths) {
+            if disabled(&meta.id()) {
+                continue;
+            }
+            if let Some(plugin_templates) = meta.file_templates.as_ref() {
+                templates.extend(
+                    plugin_templates
+                        .iter()
+                        .filter_map(|path| Self::load(Path::new(path))),
+                );
+            }
+        }
+        templates
+    }
+
+    fn load(path: &Path) -> Option<FileTemplate> {
+        if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+            return None;
+        }
+        let content = std::fs::read_to_string(path).ok()?;
+        match toml::from_str(&content) {
+            Ok(template) => Some(template),
+            Err(err) => {
+                tracing::error!("invalid file template {}: {err}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Whether the template should be offered for a new file at `path`.
+    /// A template that restricts neither the languages nor the file name is
+    /// offered for every file.
+    pub fn matches(&self, path: &Path) -> bool {
+        if let Some(glob) = self.glob.as_ref() {
+            let Some(file_name) = path.file_name() else {
+                return false;
+            };
+            if !glob.0.is_match(file_name) {
+                return false;
+            }
+        }
+
+        if self.languages.is_empty() {
+            return true;
+        }
+        let language = LapceLanguage::from_path(path);
+        self.languages
+            .iter()
+            .any(|name| LapceLanguage::from_name(name) == Some(language))
+    }
+
+    /// The snippet to insert in the new file at `path`, with the variables
+    /// replaced by their values, escaped so they are inserted as they are.
+    pub fn expand(&self, path: &Path) -> String {
+        let file_name = path
+            .file_name()
+            .map(|name| escape(&name.to_string_lossy()))
+            .unwrap_or_default();
+        let file_name_base = path
+            .file_stem()
+            .map(|name| escape(&name.to_string_lossy()))
+            .unwrap_or_default();
+        let directory = path
+            .parent()
+            .map(|dir| escape(&dir.to_string_lossy()))
+            .unwrap_or_default();
+        let now = chrono::Local::now();
+        // Longer names go first, so `$FILENAME` doesn't eat `$FILENAME_BASE`.
+        let variables = [
+            ("FILENAME_BASE", file_name_base),
+            ("FILENAME", file_name),
+            ("DIRECTORY", directory),
+            ("DATE", now.format("%Y-%m-%d").to_string()),
+            ("YEAR", now.format("%Y").to_string()),
+        ];
+
+        // Replace in a single pass, so the values aren't searched for variables.
+        let mut result = String::with_capacity(self.body.len());
+        let mut rest = self.body.as_str();
+        while let Some(i) = rest.find('$') {
+            result.push_str(&rest[..i]);
+            rest = &rest[i + 1..];
+            match variables.iter().find(|(name, _)| rest.starts_with(name)) {
+                Some((name, value)) => {
+                    result.push_str(value);
+                    rest = &rest[name.len()..];
+                }
+                None => result.push('$'),
+            }
+        }
+        result.push_str(rest);
+        result
+    }
+}
+
+/// The glob of a [`FileTemplate`], compiled once when the template is loaded.
+#[derive(Clone, Debug)]
+pub struct TemplateGlob(GlobMatcher);
+
+impl PartialEq for TemplateGlob {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.glob() == other.0.glob()
+    }
+}
+
+impl Eq for TemplateGlob {}
+
+impl<'de> Deserialize<'de> for TemplateGlob {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let glob = String::deserialize(deserializer)?;
+        Glob::new(&glob)
+            .map(|glob| TemplateGlob(glob.compile_matcher()))
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use globset::Glob;
+
+    use super::{FileTemplate, TemplateGlob};
+
+    fn template(languages: &[&str], glob: Option<&str>, body: &str) -> FileTemplate {
+        FileTemplate {
+            name: "test".to_string(),
+            description: String::new(),
+            languages: languages.iter().map(|l| l.to_string()).collect(),
+            glob: glob
+                .map(|g| TemplateGlob(Glob::new(g).unwrap().compile_matcher())),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let path = Path::new("/project/src/main.rs");
+        assert!(template(&[], None, "").matches(path));
+        assert!(template(&["rust"], None, "").matches(path));
+        assert!(!template(&["python"], None, "").matches(path));
+        assert!(template(&[], Some("main.rs"), "").matches(path));
+        assert!(template(&["rust"], Some("*.rs"), "").matches(path));
+        assert!(!template(&["rust"], Some("lib.rs"), "").matches(path));
+    }
+
+    #[test]
+    fn test_expand() {
+        let path = Path::new("/project/src/button.tsx");
+        let expanded = template(
+            &[],
+            None,
+            "// $FILENAME\nexport function ${1:$FILENAME_BASE}() {\n\t$0\n}",
+        );
+        assert_eq!(
+            expanded.expand(path),
+            "// button.tsx\nexport function ${1:button}() {\n\t$0\n}"
+        );
+
+        let path = Path::new("/project/$YEAR/a}b.rs");
+        let escaped = template(&[], None, "$DIRECTORY ${1:$FILENAME}");
+        assert_eq!(escaped.expand(path), "/project/\\$YEAR ${1:a\\}b.rs}");
+    }
+}
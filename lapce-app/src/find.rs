@@ -57,6 +57,49 @@ pub struct FindSearchString {
     pub regex: Option<Regex>,
 }
 
+impl FindSearchString {
+    pub fn new(content: &str, is_regex: bool, case_sensitive: bool) -> Self {
+        // create regex from untrusted input
+        let regex = match is_regex {
+            false => None,
+            true => RegexBuilder::new(content)
+                .size_limit(REGEX_SIZE_LIMIT)
+                .case_insensitive(!case_sensitive)
+                .build()
+                .ok(),
+        };
+        Self {
+            content: content.to_string(),
+            regex,
+        }
+    }
+
+    /// Like [`Find::replacement`], for this search, with the case of `matched`
+    /// kept if `keep_case` is set.
+    pub fn replacement(
+        &self,
+        matched: &str,
+        replace: &str,
+        keep_case: bool,
+    ) -> String {
+        let text = self
+            .regex
+            .as_ref()
+            .and_then(|regex| {
+                let captures = regex.captures(matched)?;
+                let mut text = String::new();
+                captures.expand(replace, &mut text);
+                Some(text)
+            })
+            .unwrap_or_else(|| replace.to_string());
+        if keep_case {
+            preserve_case(matched, &text)
+        } else {
+            text
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Find {
     pub rev: RwSignal<u64>,
@@ -74,6 +117,10 @@ pub struct Find {
     pub replace_active: RwSignal<bool>,
     /// replace editor is focused
     pub replace_focus: RwSignal<bool>,
+    /// Replacements keep the case of the text they replace.
+    pub preserve_case: RwSignal<bool>,
+    /// Replace All only replaces the occurrences in the selection.
+    pub in_selection: RwSignal<bool>,
     /// Triggered by changes in the search string
     pub triggered_by_changes: RwSignal<bool>,
 }
@@ -89,6 +136,8 @@ pub fn new(cx: Scope) -> Self {
             is_regex: cx.create_rw_signal(false),
             replace_active: cx.create_rw_signal(false),
             replace_focus: cx.create_rw_signal(false),
+            preserve_case: cx.create_rw_signal(false),
+            in_selection: cx.create_rw_signal(false),
             triggered_by_changes: cx.create_rw_signal(false),
         };
 
@@ -174,20 +223,12 @@ pub fn set_find(&self, search_string: &str) {
             return;
         }
 
-        // create regex from untrusted input
-        let regex = match is_regex {
-            false => None,
-            true => RegexBuilder::new(search_string)
-                .size_limit(REGEX_SIZE_LIMIT)
-                .case_insensitive(!self.case_sensitive(false))
-                .build()
-                .ok(),
-        };
         self.triggered_by_changes.set(true);
-        self.search_string.set(Some(FindSearchString {
-            content: search_string.to_string(),
-            regex,
-        }));
+        self.search_string.set(Some(FindSearchString::new(
+            search_string,
+            is_regex,
+            self.case_sensitive(false),
+        )));
     }
 
     pub fn next(
@@ -202,116 +243,128 @@ pub fn next(
         }
         let case_matching = self.case_matching.get_untracked();
         let whole_words = self.whole_words.get_untracked();
-        self.search_string.with_untracked(
-            |search_string| -> Option<(usize, usize)> {
-                let search_string = search_string.as_ref()?;
-                if !reverse {
-                    let mut raw_lines = text.lines_raw(offset..text.len());
-                    let mut find_cursor = Cursor::new(text, offset);
-                    while let Some(start) = find(
-                        &mut find_cursor,
-                        &mut raw_lines,
-                        case_matching,
-                        &search_string.content,
-                        search_string.regex.as_ref(),
-                    ) {
-                        let end = find_cursor.pos();
-
-                        if whole_words
-                            && !Self::is_matching_whole_words(text, start, end)
-                        {
-                            raw_lines =
-                                text.lines_raw(find_cursor.pos()..text.len());
-                            continue;
-                        }
-                        raw_lines = text.lines_raw(find_cursor.pos()..text.len());
+        self.search_string.with_untracked(|search_string| {
+            Self::next_match(
+                text,
+                search_string.as_ref()?,
+                case_matching,
+                whole_words,
+                offset,
+                reverse,
+                wrap,
+            )
+        })
+    }
 
-                        if start > offset {
-                            return Some((start, end));
-                        }
-                    }
-                    if wrap {
-                        let mut raw_lines = text.lines_raw(0..offset);
-                        let mut find_cursor = Cursor::new(text, 0);
-                        while let Some(start) = find(
-                            &mut find_cursor,
-                            &mut raw_lines,
-                            case_matching,
-                            &search_string.content,
-                            search_string.regex.as_ref(),
-                        ) {
-                            let end = find_cursor.pos();
-
-                            if whole_words
-                                && !Self::is_matching_whole_words(text, start, end)
-                            {
-                                raw_lines =
-                                    text.lines_raw(find_cursor.pos()..offset);
-                                continue;
-                            }
-                            return Some((start, end));
-                        }
-                    }
-                } else {
-                    let mut raw_lines = text.lines_raw(0..offset);
-                    let mut find_cursor = Cursor::new(text, 0);
-                    let mut regions = Vec::new();
-                    while let Some(start) = find(
-                        &mut find_cursor,
-                        &mut raw_lines,
-                        case_matching,
-                        &search_string.content,
-                        search_string.regex.as_ref(),
-                    ) {
-                        let end = find_cursor.pos();
+    /// The occurrence of `search_string` after `offset`, or before it when
+    /// `reverse`, searched with the given settings rather than those of the
+    /// find.
+    pub fn next_match(
+        text: &Rope,
+        search_string: &FindSearchString,
+        case_matching: CaseMatching,
+        whole_words: bool,
+        offset: usize,
+        reverse: bool,
+        wrap: bool,
+    ) -> Option<(usize, usize)> {
+        if !reverse {
+            let mut raw_lines = text.lines_raw(offset..text.len());
+            let mut find_cursor = Cursor::new(text, offset);
+            while let Some(start) = find(
+                &mut find_cursor,
+                &mut raw_lines,
+                case_matching,
+                &search_string.content,
+                search_string.regex.as_ref(),
+            ) {
+                let end = find_cursor.pos();
+
+                if whole_words && !Self::is_matching_whole_words(text, start, end) {
+                    raw_lines = text.lines_raw(find_cursor.pos()..text.len());
+                    continue;
+                }
+                raw_lines = text.lines_raw(find_cursor.pos()..text.len());
+
+                if start > offset {
+                    return Some((start, end));
+                }
+            }
+            if wrap {
+                let mut raw_lines = text.lines_raw(0..offset);
+                let mut find_cursor = Cursor::new(text, 0);
+                while let Some(start) = find(
+                    &mut find_cursor,
+                    &mut raw_lines,
+                    case_matching,
+                    &search_string.content,
+                    search_string.regex.as_ref(),
+                ) {
+                    let end = find_cursor.pos();
+
+                    if whole_words
+                        && !Self::is_matching_whole_words(text, start, end)
+                    {
                         raw_lines = text.lines_raw(find_cursor.pos()..offset);
-                        if whole_words
-                            && !Self::is_matching_whole_words(text, start, end)
-                        {
-                            continue;
-                        }
-                        if start < offset {
-                            regions.push((start, end));
-                        }
+                        continue;
                     }
-                    if !regions.is_empty() {
-                        return Some(regions[regions.len() - 1]);
+                    return Some((start, end));
+                }
+            }
+        } else {
+            let mut raw_lines = text.lines_raw(0..offset);
+            let mut find_cursor = Cursor::new(text, 0);
+            let mut regions = Vec::new();
+            while let Some(start) = find(
+                &mut find_cursor,
+                &mut raw_lines,
+                case_matching,
+                &search_string.content,
+                search_string.regex.as_ref(),
+            ) {
+                let end = find_cursor.pos();
+                raw_lines = text.lines_raw(find_cursor.pos()..offset);
+                if whole_words && !Self::is_matching_whole_words(text, start, end) {
+                    continue;
+                }
+                if start < offset {
+                    regions.push((start, end));
+                }
+            }
+            if !regions.is_empty() {
+                return Some(regions[regions.len() - 1]);
+            }
+            if wrap {
+                let mut raw_lines = text.lines_raw(offset..text.len());
+                let mut find_cursor = Cursor::new(text, offset);
+                let mut regions = Vec::new();
+                while let Some(start) = find(
+                    &mut find_cursor,
+                    &mut raw_lines,
+                    case_matching,
+                    &search_string.content,
+                    search_string.regex.as_ref(),
+                ) {
+                    let end = find_cursor.pos();
+
+                    if whole_words
+                        && !Self::is_matching_whole_words(text, start, end)
+                    {
+                        raw_lines = text.lines_raw(find_cursor.pos()..text.len());
+                        continue;
                     }
-                    if wrap {
-                        let mut raw_lines = text.lines_raw(offset..text.len());
-                        let mut find_cursor = Cursor::new(text, offset);
-                        let mut regions = Vec::new();
-                        while let Some(start) = find(
-                            &mut find_cursor,
-                            &mut raw_lines,
-                            case_matching,
-                            &search_string.content,
-                            search_string.regex.as_ref(),
-                        ) {
-                            let end = find_cursor.pos();
-
-                            if whole_words
-                                && !Self::is_matching_whole_words(text, start, end)
-                            {
-                                raw_lines =
-                                    text.lines_raw(find_cursor.pos()..text.len());
-                                continue;
-                            }
-                            raw_lines =
-                                text.lines_raw(find_cursor.pos()..text.len());
-
-                            if start > offset {
-                                regions.push((start, end));
-                            }
-                        }
-                        if !regions.is_empty() {
-                            return Some(regions[regions.len() - 1]);
-                        }
+                    raw_lines = text.lines_raw(find_cursor.pos()..text.len());
+
+                    if start > offset {
+                        regions.push((start, end));
                     }
                 }
-                None
-            },
-        )
+                if !regions.is_empty() {
+                    return Some(regions[regions.len() - 1]);
+                }
+            }
+        }
+        None
     }
 
     /// Checks if the start and end of a match is matching whole words.
@@ -330,6 +383,18 @@ fn is_matching_whole_words(text: &Rope, start: usize, end: usize) -> bool {
         true
     }
 
+    /// The text replacing the occurrence `matched` with `replace`. The
+    /// references to capture groups in `replace`, such as `$1` or `${name}`,
+    /// are expanded for a regex search.
+    pub fn replacement(&self, matched: &str, replace: &str) -> String {
+        let keep_case = self.preserve_case.get_untracked();
+        self.search_string.with_untracked(|search| match search {
+            Some(search) => search.replacement(matched, replace, keep_case),
+            None if keep_case => preserve_case(matched, replace),
+            None => replace.to_string(),
+        })
+    }
+
     /// Returns `true` if the search query is a multi-line regex.
     pub fn is_multiline_regex(&self) -> bool {
         self.search_string.with_untracked(|search| {
@@ -512,6 +577,31 @@ pub fn update_find(
     }
 }
 
+/// Change the case of `replacement` to that of `matched` when it is all upper
+/// case, all lower case or capitalized.
+pub fn preserve_case(matched: &str, replacement: &str) -> String {
+    let mut letters = matched.chars().filter(|c| c.is_alphabetic());
+    let Some(first) = letters.next() else {
+        return replacement.to_string();
+    };
+    let rest: Vec<char> = letters.collect();
+    if first.is_lowercase() && rest.iter().all(|c| c.is_lowercase()) {
+        replacement.to_lowercase()
+    } else if !first.is_uppercase() {
+        replacement.to_string()
+    } else if !rest.is_empty() && rest.iter().all(|c| c.is_uppercase()) {
+        replacement.to_uppercase()
+    } else if rest.iter().all(|c| c.is_lowercase()) {
+        let mut chars = replacement.chars();
+        match chars.next() {
+            Some(c) => c.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    } else {
+        replacement.to_string()
+    }
+}
+
 #[derive(Clone)]
 pub struct FindResult {
     pub find_rev: RwSignal<u64>,
@@ -540,3 +630,19 @@ pub fn reset(&self) {
         self.progress.set(FindProgress::Started);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preserve_case() {
+        assert_eq!(preserve_case("foo", "Bar"), "bar");
+        assert_eq!(preserve_case("FOO", "bar"), "BAR");
+        assert_eq!(preserve_case("Foo", "bar"), "Bar");
+        assert_eq!(preserve_case("F", "bar"), "Bar");
+        assert_eq!(preserve_case("fooBar", "bazQux"), "bazQux");
+        assert_eq!(preserve_case("Foo", "barBaz"), "BarBaz");
+        assert_eq!(preserve_case("123", "Bar"), "Bar");
+    }
+}
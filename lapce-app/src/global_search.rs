@@ -1,19 +1,37 @@
-use std::{ops::Range, path::PathBuf, rc::Rc};
+use std::{
+    collections::HashSet,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
 
 use floem::{
+    action::exec_after,
     ext_event::create_ext_action,
     keyboard::Modifiers,
     reactive::{Memo, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
     views::VirtualVector,
 };
 use indexmap::IndexMap;
-use lapce_core::{mode::Mode, selection::Selection};
+use lapce_core::{
+    buffer::{
+        rope_text::{RopeText, RopeTextRef},
+        Buffer,
+    },
+    editor::EditType,
+    mode::Mode,
+    selection::Selection,
+};
 use lapce_rpc::proxy::{ProxyResponse, SearchMatch};
-use lapce_xi_rope::Rope;
+use lapce_xi_rope::{find::CaseMatching, Rope};
+use lsp_types::{MessageType, ShowMessageParams};
 
 use crate::{
     command::{CommandExecuted, CommandKind},
+    doc::Doc,
     editor::EditorData,
+    find::{Find, FindSearchString},
     keypress::{condition::Condition, KeyPressFocus},
     main_split::MainSplitData,
     window_tab::CommonData,
@@ -38,9 +56,29 @@ pub fn height(&self) -> f64 {
     }
 }
 
+/// The inputs of the search panel, which take the keys while it's focused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchInput {
+    Search,
+    Replace,
+    Include,
+    Exclude,
+}
+
 #[derive(Clone, Debug)]
 pub struct GlobalSearchData {
     pub editor: EditorData,
+    pub replace_editor: EditorData,
+    /// The globs, separated by commas and relative to the workspace, of the
+    /// files searched.
+    pub include_editor: EditorData,
+    /// The globs of the files left out of the search.
+    pub exclude_editor: EditorData,
+    /// The replace input is shown, with a preview of the replacements.
+    pub replace_active: RwSignal<bool>,
+    pub focused_input: RwSignal<SearchInput>,
+    /// The search of the results, from which the replacements are made.
+    pub search_string: RwSignal<Option<FindSearchString>>,
     pub search_result: RwSignal<IndexMap<PathBuf, SearchMatchData>>,
     pub main_split: MainSplitData,
     pub common: Rc<CommonData>,
@@ -68,7 +106,7 @@ fn run_command(
             CommandKind::Edit(_)
             | CommandKind::Move(_)
             | CommandKind::MultiSelection(_) => {
-                return self.editor.run_command(command, count, mods);
+                return self.focused_editor().run_command(command, count, mods);
             }
             CommandKind::MotionMode(_) => {}
         }
@@ -76,7 +114,7 @@ fn run_command(
     }
 
     fn receive_char(&self, c: &str) {
-        self.editor.receive_char(c);
+        self.focused_editor().receive_char(c);
     }
 }
 
@@ -108,10 +146,19 @@ impl GlobalSearchData {
     pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
         let common = main_split.common.clone();
         let editor = main_split.editors.make_local(cx, common.clone());
+        let replace_editor = main_split.editors.make_local(cx, common.clone());
+        let include_editor = main_split.editors.make_local(cx, common.clone());
+        let exclude_editor = main_split.editors.make_local(cx, common.clone());
         let search_result = cx.create_rw_signal(IndexMap::new());
 
         let global_search = Self {
             editor,
+            replace_editor,
+            include_editor,
+            exclude_editor,
+            replace_active: cx.create_rw_signal(false),
+            focused_input: cx.create_rw_signal(SearchInput::Search),
+            search_string: cx.create_rw_signal(None),
             search_result,
             main_split,
             common,
@@ -120,15 +167,29 @@ pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
         {
             let global_search = global_search.clone();
             let buffer = global_search.editor.doc().buffer;
+            let include_buffer = global_search.include_editor.doc().buffer;
+            let exclude_buffer = global_search.exclude_editor.doc().buffer;
             cx.create_effect(move |_| {
                 let pattern = buffer.with(|buffer| buffer.to_string());
+                let globs = |buffer: RwSignal<Buffer>| {
+                    let globs = buffer.with(|buffer| buffer.to_string());
+                    (!globs.trim().is_empty()).then_some(globs)
+                };
+                let include = globs(include_buffer);
+                let exclude = globs(exclude_buffer);
                 if pattern.is_empty() {
+                    global_search.search_string.set(None);
                     global_search.search_result.update(|r| r.clear());
                     return;
                 }
                 let case_sensitive = global_search.common.find.case_sensitive(true);
                 let whole_word = global_search.common.find.whole_words.get();
                 let is_regex = global_search.common.find.is_regex.get();
+                global_search.search_string.set(Some(FindSearchString::new(
+                    &pattern,
+                    is_regex,
+                    case_sensitive,
+                )));
                 let send = {
                     let global_search = global_search.clone();
                     create_ext_action(cx, move |result| {
@@ -144,6 +205,8 @@ pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
                     case_sensitive,
                     whole_word,
                     is_regex,
+                    include,
+                    exclude,
                     move |result| {
                         send(result);
                     },
@@ -169,6 +232,10 @@ fn update_matches(&self, matches: IndexMap<PathBuf, Vec<SearchMatch>>) {
         self.search_result.set(
             matches
                 .into_iter()
+                .filter_map(|(path, matches)| {
+                    let matches = self.unsaved_matches(&path).unwrap_or(matches);
+                    (!matches.is_empty()).then_some((path, matches))
+                })
                 .map(|(path, matches)| {
                     let match_data =
                         current.get(&path).cloned().unwrap_or_else(|| {
@@ -190,6 +257,315 @@ fn update_matches(&self, matches: IndexMap<PathBuf, Vec<SearchMatch>>) {
         );
     }
 
+    /// The matches in the document of `path` if it has unsaved changes, which
+    /// the matches the search found in the file on disk may not line up with.
+    fn unsaved_matches(&self, path: &Path) -> Option<Vec<SearchMatch>> {
+        let doc = self
+            .main_split
+            .docs
+            .with_untracked(|docs| docs.get(path).cloned())?;
+        if !doc.loaded() || doc.is_pristine() {
+            return None;
+        }
+        let search = self.search_string.get_untracked()?;
+        let find = &self.common.find;
+        Some(doc.buffer.with_untracked(|buffer| {
+            buffer_matches(
+                buffer.text(),
+                &search,
+                find.case_matching.get_untracked(),
+                find.whole_words.get_untracked(),
+            )
+        }))
+    }
+
+    fn focused_editor(&self) -> &EditorData {
+        match self.focused_input.get_untracked() {
+            SearchInput::Replace if self.replace_active.get_untracked() => {
+                &self.replace_editor
+            }
+            SearchInput::Include => &self.include_editor,
+            SearchInput::Exclude => &self.exclude_editor,
+            _ => &self.editor,
+        }
+    }
+
+    /// Leave the matches in `path` out of the results and the replacements.
+    pub fn dismiss_file(&self, path: &Path) {
+        self.search_result.update(|result| {
+            result.shift_remove(path);
+        });
+    }
+
+    /// Leave the match `m` of `path` out of the results and the replacements.
+    pub fn dismiss_match(&self, path: &Path, m: &SearchMatch) {
+        let Some(data) = self
+            .search_result
+            .with_untracked(|result| result.get(path).cloned())
+        else {
+            return;
+        };
+        data.matches.update(|matches| {
+            matches.retain(|other| {
+                (other.line, other.start, other.end) != (m.line, m.start, m.end)
+            })
+        });
+        if data.matches.with_untracked(|matches| matches.is_empty()) {
+            self.dismiss_file(path);
+        }
+    }
+
+    /// Replace the matches in all the files of the results.
+    pub fn replace_all(&self) {
+        let paths: Vec<PathBuf> = self
+            .search_result
+            .with_untracked(|result| result.keys().cloned().collect());
+        self.replace_files(paths);
+    }
+
+    /// Replace the matches of the results in `path`.
+    pub fn replace_file(&self, path: &Path) {
+        self.replace_files(vec![path.to_path_buf()]);
+    }
+
+    /// Show the replacements of the matches of the results in `path` as a
+    /// diff of the file with its text once they're made.
+    pub fn preview_file(&self, path: &Path) {
+        let Some(replacements) = self.replacements(vec![path.to_path_buf()]) else {
+            return;
+        };
+        let main_split = self.main_split.clone();
+        self.when_loaded(&replacements, move |replacements| {
+            for replacement in replacements {
+                let edits = replacement.edits();
+                let text = replacement
+                    .doc
+                    .buffer
+                    .with_untracked(|buffer| replaced_text(buffer.text(), &edits));
+                main_split.open_replace_preview(replacement.path.clone(), text);
+            }
+        });
+    }
+
+    /// Replace the matches of the results in `paths` as one batch. The edits
+    /// of every file are worked out once they're all loaded, and only made if
+    /// all of them can be, with one undo step per file. The files which were
+    /// not open are saved and closed afterwards.
+    fn replace_files(&self, paths: Vec<PathBuf>) {
+        let Some(replacements) = self.replacements(paths) else {
+            return;
+        };
+        let global_search = self.clone();
+        self.when_loaded(&replacements, move |replacements| {
+            global_search.apply_replacements(replacements);
+        });
+    }
+
+    /// The replacements to make in the files of the results in `paths`,
+    /// opening their documents.
+    fn replacements(&self, paths: Vec<PathBuf>) -> Option<Vec<FileReplacement>> {
+        let search = self.search_string.get_untracked()?;
+        let replace = self
+            .replace_editor
+            .doc()
+            .buffer
+            .with_untracked(|b| b.to_string());
+        let find = &self.common.find;
+        let case_matching = find.case_matching.get_untracked();
+        let whole_words = find.whole_words.get_untracked();
+        let keep_case = find.preserve_case.get_untracked();
+
+        let replacements = paths
+            .into_iter()
+            .filter_map(|path| {
+                let data = self
+                    .search_result
+                    .with_untracked(|result| result.get(&path).cloned())?;
+                let matches = data.matches.with_untracked(|matches| {
+                    matches.iter().map(|m| (m.line, m.start)).collect()
+                });
+                let (doc, new_doc) = self.main_split.get_doc(path.clone(), None);
+                Some(FileReplacement {
+                    path,
+                    doc,
+                    new_doc,
+                    matches,
+                    search: search.clone(),
+                    replace: replace.clone(),
+                    case_matching,
+                    whole_words,
+                    keep_case,
+                })
+            })
+            .collect::<Vec<_>>();
+        (!replacements.is_empty()).then_some(replacements)
+    }
+
+    /// Run `f` once the documents of all the `replacements` are loaded, or
+    /// tell the user which files couldn't be read if one of them fails to.
+    fn when_loaded(
+        &self,
+        replacements: &[FileReplacement],
+        f: impl Fn(&[FileReplacement]) + 'static,
+    ) {
+        let replacements = replacements.to_vec();
+        if replacements
+            .iter()
+            .all(|replacement| replacement.doc.loaded())
+        {
+            f(&replacements);
+            return;
+        }
+        let scope = self.common.scope.create_child();
+        let global_search = self.clone();
+        scope.create_effect(move |done| {
+            if done == Some(true) {
+                return true;
+            }
+            let failed: Vec<String> = replacements
+                .iter()
+                .filter(|replacement| replacement.doc.load_failed.get())
+                .map(|replacement| replacement.path.display().to_string())
+                .collect();
+            if !failed.is_empty() {
+                global_search.common.notifications.notify(
+                    "Replace Failed",
+                    &ShowMessageParams {
+                        typ: MessageType::ERROR,
+                        message: format!(
+                            "Nothing was replaced, as these files couldn't be read: {}",
+                            failed.join(", ")
+                        ),
+                    },
+                );
+                global_search.release_docs(&replacements);
+            } else if replacements
+                .iter()
+                .all(|replacement| replacement.doc.loaded.get())
+            {
+                f(&replacements);
+            } else {
+                return false;
+            }
+            // The effect can't be disposed of while it's running.
+            exec_after(Duration::ZERO, move |_| scope.dispose());
+            true
+        });
+    }
+
+    /// Close the documents opened for the `replacements`.
+    fn release_docs(&self, replacements: &[FileReplacement]) {
+        for replacement in replacements {
+            if replacement.new_doc {
+                self.main_split.release_doc(&replacement.path);
+            }
+        }
+    }
+
+    /// Make the `replacements`, or none of them if one of the documents is
+    /// read only.
+    fn apply_replacements(&self, replacements: &[FileReplacement]) {
+        let staged: Vec<(&FileReplacement, Vec<(Range<usize>, String)>)> =
+            replacements
+                .iter()
+                .map(|replacement| (replacement, replacement.edits()))
+                .collect();
+        let read_only: Vec<String> = staged
+            .iter()
+            .filter(|(replacement, edits)| {
+                !edits.is_empty()
+                    && replacement
+                        .doc
+                        .content
+                        .with_untracked(|content| content.read_only())
+            })
+            .map(|(replacement, _)| replacement.path.display().to_string())
+            .collect();
+        if !read_only.is_empty() {
+            self.common.notifications.notify(
+                "Replace Failed",
+                &ShowMessageParams {
+                    typ: MessageType::ERROR,
+                    message: format!(
+                        "Nothing was replaced, as these files are read only: {}",
+                        read_only.join(", ")
+                    ),
+                },
+            );
+            self.release_docs(replacements);
+            return;
+        }
+
+        let mut skipped = Vec::new();
+        for (replacement, edits) in staged {
+            // The text at a match may have changed since the search
+            let missed = replacement.matches.len().saturating_sub(edits.len());
+            if missed > 0 {
+                skipped.push(format!("{} ({missed})", replacement.path.display()));
+            }
+            let edited = !edits.is_empty();
+            if edited {
+                let edits: Vec<(Selection, &str)> = edits
+                    .iter()
+                    .map(|(range, text)| {
+                        (Selection::region(range.start, range.end), text.as_str())
+                    })
+                    .collect();
+                replacement.doc.do_raw_edit(&edits, EditType::Other);
+            }
+            self.dismiss_file(&replacement.path);
+            if replacement.new_doc {
+                let main_split = self.main_split.clone();
+                let path = replacement.path.clone();
+                if edited {
+                    replacement.doc.save(move || main_split.release_doc(&path));
+                } else {
+                    main_split.release_doc(&path);
+                }
+            }
+        }
+        if !skipped.is_empty() {
+            self.common.notifications.notify(
+                "Replace",
+                &ShowMessageParams {
+                    typ: MessageType::WARNING,
+                    message: format!(
+                        "Some matches no longer match the search and were left as they were: {}",
+                        skipped.join(", ")
+                    ),
+                },
+            );
+        }
+    }
+
+    /// Limit the search to the files under `path`, in the innermost of the
+    /// workspace `roots` holding it.
+    pub fn set_scope_to_folder(&self, path: &Path, roots: &[PathBuf]) {
+        let root = roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.as_os_str().len());
+        let relative = root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        let relative = escape_glob(&relative.to_string_lossy().replace('\\', "/"));
+        let relative = relative.trim_end_matches('/');
+        // With several roots the glob is made relative to the one holding the
+        // folder, so that it doesn't apply to the others.
+        let root_name = root
+            .and_then(|root| root.file_name())
+            .filter(|_| roots.len() > 1);
+        let include = match (root_name, relative.is_empty()) {
+            (Some(name), true) => format!("./{}/**", name.to_string_lossy()),
+            (Some(name), false) => {
+                format!("./{}/{relative}/**", name.to_string_lossy())
+            }
+            (None, true) => String::new(),
+            (None, false) => format!("{relative}/**"),
+        };
+        self.include_editor.doc().reload(Rope::from(include), true);
+    }
+
     pub fn set_pattern(&self, pattern: String) {
         let pattern_len = pattern.len();
         self.editor.doc().reload(Rope::from(pattern), true);
@@ -198,3 +574,203 @@ pub fn set_pattern(&self, pattern: String) {
             .update(|cursor| cursor.set_insert(Selection::region(0, pattern_len)));
     }
 }
+
+/// Escape the glob metacharacters of `path`. Commas separate the globs and
+/// can't be escaped, so they're matched by any character instead.
+fn escape_glob(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '*' | '?' | '[' | ']' | '{' | '}' => {
+                escaped.push('[');
+                escaped.push(c);
+                escaped.push(']');
+            }
+            ',' => escaped.push('?'),
+            _ => escaped.push(c),
+        }
+    }
+    // A leading `!` negates a glob, and a leading `#` makes it a comment.
+    if escaped.starts_with(['!', '#']) {
+        escaped.insert(0, '\\');
+    }
+    escaped
+}
+
+/// The replacements of the matches of the results in a file.
+#[derive(Clone)]
+struct FileReplacement {
+    path: PathBuf,
+    doc: Rc<Doc>,
+    /// Whether the document was opened for the replacements.
+    new_doc: bool,
+    /// The 1-based lines and the columns the matches start at.
+    matches: HashSet<(usize, usize)>,
+    search: FindSearchString,
+    replace: String,
+    case_matching: CaseMatching,
+    whole_words: bool,
+    keep_case: bool,
+}
+
+impl FileReplacement {
+    fn edits(&self) -> Vec<(Range<usize>, String)> {
+        self.doc.buffer.with_untracked(|buffer| {
+            replacement_edits(
+                buffer.text(),
+                &self.search,
+                self.case_matching,
+                self.whole_words,
+                self.keep_case,
+                &self.matches,
+                &self.replace,
+            )
+        })
+    }
+}
+
+/// The replacements of the occurrences of `search` in `text` which start at
+/// one of the 1-based lines and columns of `matches`, with `replace`. An
+/// occurrence may span the lines after its own for a multi-line regex.
+fn replacement_edits(
+    text: &Rope,
+    search: &FindSearchString,
+    case_matching: CaseMatching,
+    whole_words: bool,
+    keep_case: bool,
+    matches: &HashSet<(usize, usize)>,
+    replace: &str,
+) -> Vec<(Range<usize>, String)> {
+    let mut occurrences = Selection::new();
+    Find::find(
+        text,
+        search,
+        0,
+        text.len(),
+        case_matching,
+        whole_words,
+        false,
+        &mut occurrences,
+    );
+    occurrences
+        .regions()
+        .iter()
+        .filter(|region| {
+            let line = text.line_of_offset(region.min());
+            let column = region.min() - text.offset_of_line(line);
+            matches.contains(&(line + 1, column))
+        })
+        .map(|region| {
+            let matched = text.slice_to_cow(region.min()..region.max());
+            (
+                region.min()..region.max(),
+                search.replacement(&matched, replace, keep_case),
+            )
+        })
+        .collect()
+}
+
+/// The matches of `search` in `text`, as the global search finds them in a
+/// file.
+fn buffer_matches(
+    text: &Rope,
+    search: &FindSearchString,
+    case_matching: CaseMatching,
+    whole_words: bool,
+) -> Vec<SearchMatch> {
+    let mut occurrences = Selection::new();
+    Find::find(
+        text,
+        search,
+        0,
+        text.len(),
+        case_matching,
+        whole_words,
+        false,
+        &mut occurrences,
+    );
+    let rope_text = RopeTextRef::new(text);
+    occurrences
+        .regions()
+        .iter()
+        .map(|region| {
+            let line = rope_text.line_of_offset(region.min());
+            let line_start = rope_text.offset_of_line(line);
+            let line_content = rope_text.line_content(line);
+            // A match across lines ends with the line it starts on.
+            let end = (region.max() - line_start).min(line_content.len());
+            SearchMatch {
+                line: line + 1,
+                start: region.min() - line_start,
+                end,
+                line_content: line_content.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// `text` with the ranges of `edits`, in order, replaced.
+fn replaced_text(text: &Rope, edits: &[(Range<usize>, String)]) -> String {
+    let mut replaced = String::with_capacity(text.len());
+    let mut offset = 0;
+    for (range, replacement) in edits {
+        replaced.push_str(&text.slice_to_cow(offset..range.start));
+        replaced.push_str(replacement);
+        offset = range.end;
+    }
+    replaced.push_str(&text.slice_to_cow(offset..text.len()));
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use lapce_xi_rope::{find::CaseMatching, Rope};
+
+    use super::{buffer_matches, escape_glob, replaced_text, replacement_edits};
+    use crate::find::FindSearchString;
+
+    #[test]
+    fn test_escape_glob() {
+        assert_eq!(escape_glob("src/editor"), "src/editor");
+        assert_eq!(escape_glob("a*b/c?d"), "a[*]b/c[?]d");
+        assert_eq!(escape_glob("[id]/{x}"), "[[]id[]]/[{]x[}]");
+        assert_eq!(escape_glob("a,b"), "a?b");
+        assert_eq!(escape_glob("!draft"), "\\!draft");
+        assert_eq!(escape_glob("#tag"), "\\#tag");
+    }
+
+    #[test]
+    fn test_replacement_edits() {
+        let text = Rope::from("foo bar foo\nfoo");
+        let search = FindSearchString::new("foo", false, true);
+        // The first match of the first line is left out.
+        let matches = HashSet::from([(1, 8), (2, 0)]);
+        let edits = replacement_edits(
+            &text,
+            &search,
+            CaseMatching::Exact,
+            false,
+            false,
+            &matches,
+            "baz",
+        );
+        assert_eq!(
+            edits,
+            vec![(8..11, "baz".to_string()), (12..15, "baz".to_string())]
+        );
+        assert_eq!(replaced_text(&text, &edits), "foo bar baz\nbaz");
+        assert_eq!(replaced_text(&text, &[]), "foo bar foo\nfoo");
+    }
+
+    #[test]
+    fn test_buffer_matches() {
+        let text = Rope::from("foo bar\nbar foo\n");
+        let search = FindSearchString::new("foo", false, true);
+        let matches = buffer_matches(&text, &search, CaseMatching::Exact, false);
+        let matches: Vec<(usize, usize, usize)> =
+            matches.iter().map(|m| (m.line, m.start, m.end)).collect();
+        assert_eq!(matches, vec![(1, 0, 3), (2, 4, 7)]);
+    }
+}
@@ -0,0 +1,450 @@
+//! Peeking at a hunk of the changes to a file since it was staged, opened from
+//! its marker in the editor gutter, with actions to stage or revert it.
+
+use std::{ops::Range, rc::Rc};
+
+use floem::{
+    event::EventListener,
+    ext_event::create_ext_action,
+    kurbo::{Point, Vec2},
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+    style::{CursorStyle, Position},
+    views::{
+        container, editor::text::SystemClipboard, label, scroll, stack, Decorators,
+    },
+    View,
+};
+use lapce_core::{
+    buffer::{
+        diff::DiffLines,
+        rope_text::{RopeText, RopeTextRef},
+    },
+    cursor::CursorAffinity,
+    editor::EditType,
+    register::Clipboard,
+    selection::Selection,
+};
+use lapce_xi_rope::Rope;
+use lsp_types::{MessageType, ShowMessageParams};
+
+use crate::{
+    config::color::LapceColor, doc::DocContent, editor::EditorData,
+    window_tab::CommonData,
+};
+
+/// The number of unchanged lines around a hunk in the patch staging it.
+const CONTEXT_LINES: usize = 3;
+
+/// The lines of the `head` version, as staged in the index, replaced by lines
+/// of the buffer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub head: Range<usize>,
+    pub buffer: Range<usize>,
+}
+
+/// Group the changes to the buffer into hunks of consecutive removed and
+/// added lines.
+pub fn hunks(changes: &im::Vector<DiffLines>) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    let (mut head_line, mut buffer_line) = (0, 0);
+    for change in changes {
+        match change {
+            DiffLines::Both(info) => {
+                hunks.extend(current.take());
+                head_line = info.left.end;
+                buffer_line = info.right.end;
+            }
+            DiffLines::Left(range) => {
+                let hunk = current.get_or_insert(Hunk {
+                    head: range.start..range.start,
+                    buffer: buffer_line..buffer_line,
+                });
+                hunk.head.end = range.end;
+                head_line = range.end;
+            }
+            DiffLines::Right(range) => {
+                let hunk = current.get_or_insert(Hunk {
+                    head: head_line..head_line,
+                    buffer: range.start..range.start,
+                });
+                hunk.buffer.end = range.end;
+                buffer_line = range.end;
+            }
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// The hunk marked in the gutter at `line` of the buffer. The marker of
+/// removed lines sits between the lines around them.
+pub fn hunk_at_line(changes: &im::Vector<DiffLines>, line: usize) -> Option<Hunk> {
+    hunks(changes).into_iter().find(|hunk| {
+        if hunk.buffer.is_empty() {
+            line == hunk.buffer.start || line + 1 == hunk.buffer.start
+        } else {
+            hunk.buffer.contains(&line)
+        }
+    })
+}
+
+fn line_range_offsets(text: &Rope, lines: &Range<usize>) -> Range<usize> {
+    let text = RopeTextRef::new(text);
+    text.offset_of_line(lines.start)..text.offset_of_line(lines.end)
+}
+
+/// Append the lines of `text` in the `lines` range to `patch`, each prefixed
+/// with `prefix`.
+fn push_patch_lines(
+    patch: &mut String,
+    prefix: char,
+    text: &Rope,
+    lines: Range<usize>,
+) {
+    let content = text.slice_to_cow(line_range_offsets(text, &lines));
+    for line in content.split_inclusive('\n') {
+        patch.push(prefix);
+        patch.push_str(line);
+        if !line.ends_with('\n') {
+            patch.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// The hunk as a unified diff against the index, starting at its `@@` header.
+fn hunk_patch(head: &Rope, buffer: &Rope, hunk: &Hunk) -> String {
+    let before = hunk.head.start.min(CONTEXT_LINES);
+    // There may be fewer lines left after the hunk.
+    let after = head
+        .slice_to_cow(line_range_offsets(
+            head,
+            &(hunk.head.end..hunk.head.end + CONTEXT_LINES),
+        ))
+        .split_inclusive('\n')
+        .count();
+    let start = hunk.head.start - before;
+    let old_len = before + hunk.head.len() + after;
+    let new_len = before + hunk.buffer.len() + after;
+    // An empty range is given by the line before it.
+    let line_number = |len: usize| if len == 0 { start } else { start + 1 };
+
+    let mut patch = format!(
+        "@@ -{},{old_len} +{},{new_len} @@\n",
+        line_number(old_len),
+        line_number(new_len),
+    );
+    push_patch_lines(&mut patch, ' ', head, start..hunk.head.start);
+    push_patch_lines(&mut patch, '-', head, hunk.head.clone());
+    push_patch_lines(&mut patch, '+', buffer, hunk.buffer.clone());
+    push_patch_lines(&mut patch, ' ', head, hunk.head.end..hunk.head.end + after);
+    patch
+}
+
+#[derive(Clone)]
+pub struct HunkPeekData {
+    pub active: RwSignal<bool>,
+    pub editor: RwSignal<Option<EditorData>>,
+    pub hunk: RwSignal<Option<Hunk>>,
+    /// The lines of the index replaced in the hunk.
+    pub deleted: RwSignal<String>,
+    pub common: Rc<CommonData>,
+}
+
+impl HunkPeekData {
+    pub fn new(cx: Scope, common: Rc<CommonData>) -> Self {
+        Self {
+            active: cx.create_rw_signal(false),
+            editor: cx.create_rw_signal(None),
+            hunk: cx.create_rw_signal(None),
+            deleted: cx.create_rw_signal(String::new()),
+            common,
+        }
+    }
+
+    /// Peek at the hunk marked at `line` in the gutter of `editor`. Returns
+    /// whether there was a hunk there.
+    pub fn open(&self, editor: EditorData, line: usize) -> bool {
+        let doc = editor.doc();
+        let Some(head) = doc.head_text() else {
+            return false;
+        };
+        let Some(hunk) = doc
+            .head_changes()
+            .with_untracked(|changes| hunk_at_line(changes, line))
+        else {
+            return false;
+        };
+        if self.active.get_untracked()
+            && self.hunk.get_untracked().as_ref() == Some(&hunk)
+        {
+            self.close();
+            return true;
+        }
+
+        self.deleted.set(
+            head.slice_to_cow(line_range_offsets(&head, &hunk.head))
+                .into_owned(),
+        );
+        self.hunk.set(Some(hunk));
+        self.editor.set(Some(editor));
+        self.active.set(true);
+        true
+    }
+
+    pub fn close(&self) {
+        self.active.set(false);
+        self.editor.set(None);
+        self.hunk.set(None);
+    }
+
+    /// The editor and hunk peeked at, if the hunk is still one of the
+    /// changes of its document.
+    fn current(&self) -> Option<(EditorData, Hunk)> {
+        let editor = self.editor.get_untracked()?;
+        let hunk = self.hunk.get_untracked()?;
+        let exists = editor
+            .doc()
+            .head_changes()
+            .with_untracked(|changes| hunks(changes).contains(&hunk));
+        exists.then_some((editor, hunk))
+    }
+
+    pub fn stage(&self) {
+        if let Some((editor, hunk)) = self.current() {
+            let doc = editor.doc();
+            let path = doc.content.with_untracked(|content| match content {
+                DocContent::File { path, .. } => Some(path.clone()),
+                _ => None,
+            });
+            if let (Some(path), Some(head)) = (path, doc.head_text()) {
+                // The patch is applied to the index, which only has the saved
+                // content in common with the buffer.
+                if !doc.is_pristine() {
+                    self.common.notifications.notify(
+                        "Stage Hunk",
+                        &ShowMessageParams {
+                            typ: MessageType::WARNING,
+                            message: format!(
+                                "Save {} before staging its hunks",
+                                path.display()
+                            ),
+                        },
+                    );
+                    return;
+                }
+
+                let buffer = doc.buffer.with_untracked(|b| b.text().clone());
+                let notifications = self.common.notifications.clone();
+                let send = {
+                    let path = path.clone();
+                    create_ext_action(self.common.scope, move |result| {
+                        match result {
+                            // Stop showing the staged hunk.
+                            Ok(_) => doc.retrieve_head(),
+                            Err(err) => {
+                                notifications.notify(
+                                    "Stage Hunk Failed",
+                                    &ShowMessageParams {
+                                        typ: MessageType::ERROR,
+                                        message: format!(
+                                            "{}: {}",
+                                            path.display(),
+                                            err.message
+                                        ),
+                                    },
+                                );
+                            }
+                        }
+                    })
+                };
+                self.common.proxy.git_stage_hunk(
+                    path,
+                    hunk_patch(&head, &buffer, &hunk),
+                    move |result| {
+                        send(result);
+                    },
+                );
+            }
+        }
+        self.close();
+    }
+
+    /// Replace the lines of the hunk with those staged in the index.
+    pub fn revert(&self) {
+        if let Some((editor, hunk)) = self.current() {
+            let doc = editor.doc();
+            if let Some(head) = doc.head_text() {
+                let buffer = doc.buffer.with_untracked(|b| b.text().clone());
+                let range = line_range_offsets(&buffer, &hunk.buffer);
+                let content =
+                    head.slice_to_cow(line_range_offsets(&head, &hunk.head));
+                doc.do_raw_edit(
+                    &[
+                        (
+                            Selection::region(range.start, range.end),
+                            content.as_ref(),
+                        ),
+                    ],
+                    EditType::Other,
+                );
+            }
+        }
+        self.close();
+    }
+
+    pub fn copy_deleted(&self) {
+        let mut clipboard = SystemClipboard::new();
+        clipboard.put_string(self.deleted.get_untracked());
+        self.close();
+    }
+
+    /// Where to show the peek, below the first line of the hunk.
+    fn origin(&self, window_origin: Point) -> Point {
+        let (Some(editor), Some(hunk)) = (self.editor.get(), self.hunk.get()) else {
+            return Point::ZERO;
+        };
+        let offset = editor
+            .doc()
+            .buffer
+            .with_untracked(|b| b.offset_of_line(hunk.buffer.start));
+        let (point_above, point_below) = editor
+            .editor
+            .points_of_offset(offset, CursorAffinity::Forward);
+        let point = if hunk.buffer.is_empty() {
+            point_above
+        } else {
+            point_below
+        };
+        let viewport = editor.viewport().get();
+        let editor_origin = editor.window_origin().get() - window_origin.to_vec2();
+        editor_origin + Vec2::new(0.0, point.y - viewport.y0)
+    }
+}
+
+fn peek_button(
+    text: &'static str,
+    common: Rc<CommonData>,
+    action: impl Fn() + 'static,
+) -> impl View {
+    let config = common.config;
+    label(move || text.to_string())
+        .on_click_stop(move |_| action())
+        .style(move |s| {
+            let config = config.get();
+            s.padding_horiz(10.0)
+                .margin_right(6.0)
+                .line_height(1.6)
+                .border(1.0)
+                .border_radius(6.0)
+                .border_color(config.color(LapceColor::LAPCE_BORDER))
+                .hover(|s| {
+                    s.cursor(CursorStyle::Pointer).background(
+                        config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                    )
+                })
+                .active(|s| {
+                    s.background(
+                        config.color(LapceColor::PANEL_HOVERED_ACTIVE_BACKGROUND),
+                    )
+                })
+        })
+}
+
+pub fn hunk_peek(data: HunkPeekData) -> impl View {
+    let common = data.common.clone();
+    let config = common.config;
+    let active = data.active;
+    let deleted = data.deleted;
+
+    stack((
+        scroll(label(move || {
+            deleted.get().trim_end_matches('\n').to_string()
+        }))
+        .style(move |s| {
+            let config = config.get();
+            s.width_full()
+                .max_height(300.0)
+                .padding(6.0)
+                .font_family(config.editor.font_family.clone())
+                .font_size(config.editor.font_size() as f32)
+                .background(
+                    config
+                        .color(LapceColor::SOURCE_CONTROL_REMOVED)
+                        .with_alpha_factor(0.15),
+                )
+                .apply_if(deleted.with(|d| d.is_empty()), |s| s.hide())
+        }),
+        container(stack((
+            peek_button("Stage Hunk", common.clone(), {
+                let data = data.clone();
+                move || data.stage()
+            }),
+            peek_button("Revert Hunk", common.clone(), {
+                let data = data.clone();
+                move || data.revert()
+            }),
+            peek_button("Copy Deleted Text", common.clone(), {
+                let data = data.clone();
+                move || data.copy_deleted()
+            }),
+            peek_button("Close", common.clone(), {
+                let data = data.clone();
+                move || data.close()
+            }),
+        )))
+        .style(|s| s.padding(6.0)),
+    ))
+    .on_event_stop(EventListener::PointerMove, |_| {})
+    .on_event_stop(EventListener::PointerDown, |_| {})
+    .style(move |s| {
+        let config = config.get();
+        let origin = data.origin(common.window_origin.get());
+        s.position(Position::Absolute)
+            .flex_col()
+            .min_width(400.0)
+            .margin_left(origin.x as f32)
+            .margin_top(origin.y as f32)
+            .border(1.0)
+            .border_radius(6.0)
+            .border_color(config.color(LapceColor::LAPCE_BORDER))
+            .color(config.color(LapceColor::EDITOR_FOREGROUND))
+            .background(config.color(LapceColor::EDITOR_BACKGROUND))
+            .apply_if(!active.get(), |s| s.hide())
+    })
+    .debug_name("Hunk Peek")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hunk_patch() {
+        let head = Rope::from("a\nb\nc\nd\ne\nf\ng\nh\n");
+        let buffer = Rope::from("a\nb\nc\nd\nE\nF\nG\ng\nh\n");
+        let hunk = Hunk {
+            head: 4..6,
+            buffer: 4..7,
+        };
+        assert_eq!(
+            hunk_patch(&head, &buffer, &hunk),
+            "@@ -2,7 +2,8 @@\n b\n c\n d\n-e\n-f\n+E\n+F\n+G\n g\n h\n"
+        );
+    }
+
+    #[test]
+    fn test_hunk_patch_without_newline() {
+        let head = Rope::from("a\nb");
+        let buffer = Rope::from("a\nb\nc\n");
+        let hunk = Hunk {
+            head: 1..2,
+            buffer: 1..3,
+        };
+        assert_eq!(
+            hunk_patch(&head, &buffer, &hunk),
+            "@@ -1,2 +1,3 @@\n a\n-b\n\\ No newline at end of file\n+b\n+c\n"
+        );
+    }
+}
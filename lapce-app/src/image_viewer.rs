@@ -0,0 +1,536 @@
+//! The viewer opened for image files in place of an editor, with zoom, fitting
+//! the image to the view and a pixel grid at high zoom.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use floem::{
+    event::{Event, EventListener, EventPropagation},
+    ext_event::create_ext_action,
+    peniko::kurbo::{Line, Point, Size},
+    reactive::{ReadSignal, RwSignal, Scope, SignalGet, SignalUpdate},
+    style::TextColor,
+    views::{
+        container, dyn_container, empty, img, label, scroll, stack, svg, Decorators,
+    },
+    IntoView, Renderer, View, ViewId,
+};
+
+use crate::{
+    app::clickable_icon,
+    config::{color::LapceColor, icon::LapceIcons, LapceConfig},
+    id::ImageViewId,
+};
+
+/// The extensions of the files opened in the image viewer.
+const IMAGE_EXTENSIONS: &[&str] =
+    &["png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "svg"];
+
+/// The zoom levels stepped through when zooming in and out.
+const ZOOM_STEPS: &[f64] = &[
+    0.1, 0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 3.0, 4.0, 6.0, 8.0, 12.0, 16.0, 24.0, 32.0,
+];
+
+/// The scale from which the pixels are outlined.
+const PIXEL_GRID_SCALE: f64 = 8.0;
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+#[derive(Clone)]
+pub enum ImageContent {
+    Loading,
+    Raster(Arc<Vec<u8>>),
+    Svg(Arc<String>),
+    Error(String),
+}
+
+#[derive(Clone)]
+pub struct ImageViewerData {
+    pub id: ImageViewId,
+    pub path: PathBuf,
+    pub content: RwSignal<ImageContent>,
+    /// The size of the image in pixels, if it could be read.
+    pub dimensions: RwSignal<Option<(u32, u32)>>,
+    /// The zoom chosen by the user, or `None` to fit the image to the view.
+    pub zoom: RwSignal<Option<f64>>,
+    pub view_size: RwSignal<Size>,
+}
+
+impl ImageViewerData {
+    pub fn new(cx: Scope, id: ImageViewId, path: PathBuf) -> Self {
+        let data = Self {
+            id,
+            path,
+            content: cx.create_rw_signal(ImageContent::Loading),
+            dimensions: cx.create_rw_signal(None),
+            zoom: cx.create_rw_signal(None),
+            view_size: cx.create_rw_signal(Size::ZERO),
+        };
+        data.load(cx);
+        data
+    }
+
+    fn load(&self, cx: Scope) {
+        let content = self.content;
+        let dimensions = self.dimensions;
+        let send =
+            create_ext_action(
+                cx,
+                move |result: Result<Vec<u8>, String>| match result {
+                    Ok(bytes) => {
+                        if let Some(svg) = std::str::from_utf8(&bytes)
+                            .ok()
+                            .filter(|s| s.contains("<svg"))
+                        {
+                            dimensions.set(svg_dimensions(svg));
+                            content
+                                .set(ImageContent::Svg(Arc::new(svg.to_string())));
+                        } else {
+                            dimensions.set(image_dimensions(&bytes));
+                            content.set(ImageContent::Raster(Arc::new(bytes)));
+                        }
+                    }
+                    Err(err) => content.set(ImageContent::Error(err)),
+                },
+            );
+        let path = self.path.clone();
+        std::thread::Builder::new()
+            .name("ReadImage".to_owned())
+            .spawn(move || {
+                send(std::fs::read(&path).map_err(|err| err.to_string()));
+            })
+            .unwrap();
+    }
+
+    /// The scale the image is shown at, which is the zoom or the one fitting
+    /// it in the view.
+    pub fn scale(&self) -> f64 {
+        self.zoom
+            .get()
+            .unwrap_or_else(|| match self.dimensions.get() {
+                Some(dimensions) => fit_scale(dimensions, self.view_size.get()),
+                None => 1.0,
+            })
+    }
+
+    pub fn zoom_in(&self) {
+        let scale = self.scale();
+        self.zoom.set(Some(next_zoom(scale, true)));
+    }
+
+    pub fn zoom_out(&self) {
+        let scale = self.scale();
+        self.zoom.set(Some(next_zoom(scale, false)));
+    }
+
+    pub fn fit(&self) {
+        self.zoom.set(None);
+    }
+
+    pub fn actual_size(&self) {
+        self.zoom.set(Some(1.0));
+    }
+}
+
+/// The scale fitting an image of `dimensions` in `view`, which only shrinks
+/// images larger than the view.
+pub fn fit_scale(dimensions: (u32, u32), view: Size) -> f64 {
+    let (width, height) = dimensions;
+    if width == 0 || height == 0 || view.width <= 0.0 || view.height <= 0.0 {
+        return 1.0;
+    }
+    (view.width / width as f64)
+        .min(view.height / height as f64)
+        .min(1.0)
+}
+
+/// The zoom step after `scale` when zooming in, or before it when zooming out.
+pub fn next_zoom(scale: f64, zoom_in: bool) -> f64 {
+    if zoom_in {
+        ZOOM_STEPS
+            .iter()
+            .copied()
+            .find(|step| *step > scale + f64::EPSILON)
+            .unwrap_or(ZOOM_STEPS[ZOOM_STEPS.len() - 1])
+    } else {
+        ZOOM_STEPS
+            .iter()
+            .rev()
+            .copied()
+            .find(|step| *step < scale - f64::EPSILON)
+            .unwrap_or(ZOOM_STEPS[0])
+    }
+}
+
+/// The size in pixels of a png, gif, bmp, ico, jpeg or webp image, read from
+/// its header.
+pub fn image_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    let u16_be =
+        |i: usize| Some(u16::from_be_bytes([*bytes.get(i)?, *bytes.get(i + 1)?]));
+    let u16_le =
+        |i: usize| Some(u16::from_le_bytes([*bytes.get(i)?, *bytes.get(i + 1)?]));
+    let u32_be =
+        |i: usize| Some(u32::from_be_bytes(bytes.get(i..i + 4)?.try_into().ok()?));
+    let i32_le =
+        |i: usize| Some(i32::from_le_bytes(bytes.get(i..i + 4)?.try_into().ok()?));
+    let u24_le = |i: usize| {
+        let b = bytes.get(i..i + 3)?;
+        Some(u32::from_le_bytes([b[0], b[1], b[2], 0]))
+    };
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some((u32_be(16)?, u32_be(20)?))
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some((u16_le(6)? as u32, u16_le(8)? as u32))
+    } else if bytes.starts_with(b"BM") {
+        Some((i32_le(18)?.unsigned_abs(), i32_le(22)?.unsigned_abs()))
+    } else if bytes.starts_with(&[0, 0, 1, 0]) {
+        // The size of the first icon, where 0 stands for 256.
+        let size = |b: u8| if b == 0 { 256 } else { b as u32 };
+        Some((size(*bytes.get(6)?), size(*bytes.get(7)?)))
+    } else if bytes.starts_with(&[0xFF, 0xD8]) {
+        let mut i = 2;
+        loop {
+            if *bytes.get(i)? != 0xFF {
+                return None;
+            }
+            let marker = *bytes.get(i + 1)?;
+            match marker {
+                0xFF => i += 1,
+                0xD0..=0xD9 | 0x01 => i += 2,
+                // The start of frame markers, except those for huffman and
+                // arithmetic coding tables.
+                0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                    return Some((u16_be(i + 7)? as u32, u16_be(i + 5)? as u32));
+                }
+                _ => i += 2 + u16_be(i + 2)? as usize,
+            }
+        }
+    } else if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WEBP") {
+        match bytes.get(12..16)? {
+            b"VP8 " => {
+                Some(((u16_le(26)? & 0x3FFF) as u32, (u16_le(28)? & 0x3FFF) as u32))
+            }
+            b"VP8L" => {
+                let b = bytes.get(21..25)?;
+                let (b0, b1, b2, b3) =
+                    (b[0] as u32, b[1] as u32, b[2] as u32, b[3] as u32);
+                Some((
+                    1 + (((b1 & 0x3F) << 8) | b0),
+                    1 + (((b3 & 0xF) << 10) | (b2 << 2) | ((b1 & 0xC0) >> 6)),
+                ))
+            }
+            b"VP8X" => Some((1 + u24_le(24)?, 1 + u24_le(27)?)),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// The size of an svg image, from the width and height of its root element or
+/// else its view box.
+pub fn svg_dimensions(svg: &str) -> Option<(u32, u32)> {
+    let start = svg.find("<svg")?;
+    let tag = &svg[start..start + svg[start..].find('>')?];
+
+    let attribute = |name: &str| {
+        let mut rest = tag;
+        while let Some(i) = rest.find(name) {
+            let preceded = rest[..i].ends_with(char::is_whitespace);
+            let after = rest[i + name.len()..].trim_start();
+            rest = &rest[i + name.len()..];
+            if let (true, Some(value)) = (preceded, after.strip_prefix('=')) {
+                let value = value.trim_start();
+                let quote = value.chars().next()?;
+                let value = &value[1..];
+                return value.find(quote).map(|end| &value[..end]);
+            }
+        }
+        None
+    };
+    let length = |name: &str| {
+        attribute(name)?
+            .trim()
+            .trim_end_matches("px")
+            .parse::<f64>()
+            .ok()
+            .map(|l| l.round() as u32)
+    };
+
+    if let (Some(width), Some(height)) = (length("width"), length("height")) {
+        return Some((width, height));
+    }
+    let view_box: Vec<f64> = attribute("viewBox")?
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| s.parse().ok())
+        .collect();
+    match view_box.as_slice() {
+        [_, _, width, height] => Some((width.round() as u32, height.round() as u32)),
+        _ => None,
+    }
+}
+
+/// The lines between the pixels of an image scaled by `scale`, shown over it
+/// when zoomed in far enough to make them out.
+struct PixelGrid<F: Fn() -> f64> {
+    id: ViewId,
+    scale: F,
+}
+
+impl<F: Fn() -> f64 + 'static> View for PixelGrid<F> {
+    fn id(&self) -> ViewId {
+        self.id
+    }
+
+    fn paint(&mut self, cx: &mut floem::context::PaintCx) {
+        let scale = (self.scale)();
+        if scale < PIXEL_GRID_SCALE {
+            return;
+        }
+        let Some(color) = self.id.get_combined_style().get(TextColor) else {
+            return;
+        };
+        let layout = self.id.get_layout().unwrap_or_default();
+        let (width, height) = (layout.size.width as f64, layout.size.height as f64);
+
+        let mut x = scale;
+        while x < width {
+            let line = Line::new(Point::new(x, 0.0), Point::new(x, height));
+            cx.stroke(&line, color, 1.0);
+            x += scale;
+        }
+        let mut y = scale;
+        while y < height {
+            let line = Line::new(Point::new(0.0, y), Point::new(width, y));
+            cx.stroke(&line, color, 1.0);
+            y += scale;
+        }
+    }
+}
+
+pub fn image_viewer_view(
+    data: ImageViewerData,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let toolbar = {
+        let zoom_out = data.clone();
+        let zoom_in = data.clone();
+        let fit = data.clone();
+        let actual_size = data.clone();
+        let scale = data.clone();
+        let zoom = data.zoom;
+        stack((
+            clickable_icon(
+                || LapceIcons::IMAGE_ZOOM_OUT,
+                move || zoom_out.zoom_out(),
+                || false,
+                || false,
+                || "Zoom Out",
+                config,
+            ),
+            label(move || format!("{:.0}%", scale.scale() * 100.0))
+                .style(|s| s.min_width(50.0).justify_center().selectable(false)),
+            clickable_icon(
+                || LapceIcons::IMAGE_ZOOM_IN,
+                move || zoom_in.zoom_in(),
+                || false,
+                || false,
+                || "Zoom In",
+                config,
+            ),
+            clickable_icon(
+                || LapceIcons::IMAGE_FIT,
+                move || fit.fit(),
+                move || zoom.get().is_none(),
+                || false,
+                || "Fit to Window",
+                config,
+            )
+            .style(|s| s.margin_left(6.0)),
+            label(|| "1:1".to_string())
+                .on_click_stop(move |_| actual_size.actual_size())
+                .style(move |s| {
+                    let config = config.get();
+                    s.padding_horiz(6.0)
+                        .border_radius(6.0)
+                        .selectable(false)
+                        .apply_if(zoom.get() == Some(1.0), |s| {
+                            s.color(config.color(LapceColor::EDITOR_CARET))
+                        })
+                        .hover(|s| {
+                            s.cursor(floem::style::CursorStyle::Pointer).background(
+                                config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                            )
+                        })
+                }),
+        ))
+        .style(move |s| {
+            let config = config.get();
+            s.width_full()
+                .items_center()
+                .justify_center()
+                .padding(4.0)
+                .border_bottom(1.0)
+                .border_color(config.color(LapceColor::LAPCE_BORDER))
+        })
+    };
+
+    let image = {
+        let content = data.content;
+        dyn_container(
+            move || content.get(),
+            move |content| match content {
+                ImageContent::Loading => empty().into_any(),
+                ImageContent::Raster(bytes) => img(move || bytes.to_vec())
+                    .style(|s| s.size_full())
+                    .into_any(),
+                ImageContent::Svg(svg_str) => svg(move || svg_str.to_string())
+                    .style(|s| s.size_full())
+                    .into_any(),
+                ImageContent::Error(err) => {
+                    label(move || format!("The image couldn't be read: {err}"))
+                        .into_any()
+                }
+            },
+        )
+        .style(|s| s.size_full())
+    };
+
+    let grid = {
+        let data = data.clone();
+        PixelGrid {
+            id: ViewId::new(),
+            scale: move || data.scale(),
+        }
+        .style(move |s| {
+            s.absolute()
+                .size_full()
+                .color(config.get().color(LapceColor::EDITOR_DIM))
+        })
+    };
+
+    let sized = {
+        let data = data.clone();
+        container(stack((image, grid)).style(|s| s.size_full())).style(move |s| {
+            let scale = data.scale();
+            match data.dimensions.get() {
+                Some((width, height)) => s
+                    .width(width as f64 * scale)
+                    .height(height as f64 * scale)
+                    .flex_shrink(0.0),
+                None => s.size_full(),
+            }
+        })
+    };
+
+    let wheel_data = data.clone();
+    let view_size = data.view_size;
+    stack((
+        toolbar,
+        scroll(
+            container(sized)
+                .style(|s| s.min_size_full().items_center().justify_center()),
+        )
+        .on_resize(move |rect| {
+            view_size.set(rect.size());
+        })
+        .on_event(EventListener::PointerWheel, move |event| {
+            if let Event::PointerWheel(pointer_event) = event {
+                let modifiers = pointer_event.modifiers;
+                let zoom = if cfg!(target_os = "macos") {
+                    modifiers.meta()
+                } else {
+                    modifiers.control()
+                };
+                if zoom && pointer_event.delta.y != 0.0 {
+                    if pointer_event.delta.y < 0.0 {
+                        wheel_data.zoom_in();
+                    } else {
+                        wheel_data.zoom_out();
+                    }
+                    return EventPropagation::Stop;
+                }
+            }
+            EventPropagation::Continue
+        })
+        .style(|s| s.size_full().min_height(0.0)),
+    ))
+    .style(move |s| {
+        s.size_full()
+            .flex_col()
+            .background(config.get().color(LapceColor::EDITOR_BACKGROUND))
+    })
+    .debug_name("Image Viewer")
+}
+
+/// The size and scale of the image shown in the status bar.
+pub fn image_status(data: &ImageViewerData) -> String {
+    let scale = format!("{:.0}%", data.scale() * 100.0);
+    match data.dimensions.get() {
+        Some((width, height)) => format!("{width} × {height}  {scale}"),
+        None => scale,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.extend(640u32.to_be_bytes());
+        png.extend(480u32.to_be_bytes());
+        assert_eq!(image_dimensions(&png), Some((640, 480)));
+
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend(32u16.to_le_bytes());
+        gif.extend(16u16.to_le_bytes());
+        assert_eq!(image_dimensions(&gif), Some((32, 16)));
+
+        let jpeg = [
+            0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xC0, 0x00, 0x11,
+            0x08, 0x00, 0x78, 0x00, 0xA0,
+        ];
+        assert_eq!(image_dimensions(&jpeg), Some((160, 120)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X\0\0\0\0\0\0\0\0".to_vec();
+        webp.extend([99, 0, 0, 49, 0, 0]);
+        assert_eq!(image_dimensions(&webp), Some((100, 50)));
+
+        assert_eq!(image_dimensions(b"not an image"), None);
+    }
+
+    #[test]
+    fn test_svg_dimensions() {
+        assert_eq!(
+            svg_dimensions(r#"<svg stroke-width="2" width="24px" height='16'>"#),
+            Some((24, 16))
+        );
+        assert_eq!(
+            svg_dimensions(r#"<?xml?><svg width="100%" viewBox="0 0 48 32">"#),
+            Some((48, 32))
+        );
+        assert_eq!(svg_dimensions("<svg>"), None);
+    }
+
+    #[test]
+    fn test_zoom() {
+        assert_eq!(fit_scale((200, 100), Size::new(100.0, 100.0)), 0.5);
+        assert_eq!(fit_scale((20, 10), Size::new(100.0, 100.0)), 1.0);
+        assert_eq!(next_zoom(1.0, true), 1.5);
+        assert_eq!(next_zoom(0.6, true), 0.75);
+        assert_eq!(next_zoom(0.6, false), 0.5);
+        assert_eq!(next_zoom(32.0, true), 32.0);
+        assert_eq!(next_zoom(0.1, false), 0.1);
+    }
+}
@@ -226,6 +226,20 @@ pub fn update_inline_completion(
     }
 }
 
+/// The length of the next word of the shown text of an inline completion,
+/// along with the whitespace before it, for accepting the completion a word
+/// at a time.
+pub fn next_word_len(text: &str) -> usize {
+    let start = text.len() - text.trim_start().len();
+    let rest = &text[start..];
+    let word = match rest.find(|c: char| !(c.is_alphanumeric() || c == '_')) {
+        Some(0) => rest.chars().next().map_or(0, char::len_utf8),
+        Some(word) => word,
+        None => rest.len(),
+    };
+    start + word
+}
+
 enum ICompletionRes {
     Hide,
     Unchanged,
@@ -0,0 +1,189 @@
+//! Keyboard macros, which record the commands run and the characters typed by
+//! the keyboard so that they can be replayed.
+
+use std::collections::HashMap;
+
+use floem::keyboard::Modifiers;
+
+use crate::command::LapceWorkbenchCommand;
+
+/// The slot recorded into by the commands which don't name one.
+pub const DEFAULT_MACRO: &str = "default";
+
+/// How deep macros replaying other macros can go.
+pub const MAX_REPLAY_DEPTH: usize = 16;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MacroStep {
+    Command {
+        command: String,
+        count: Option<usize>,
+        mods: Modifiers,
+    },
+    Char(String),
+}
+
+/// What to do with the register typed after the vim `q` and `@` commands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MacroRegisterAction {
+    Record,
+    Replay { count: usize },
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MacroRecorder {
+    /// The slot being recorded into and the steps recorded so far.
+    recording: Option<(String, Vec<MacroStep>)>,
+    macros: HashMap<String, Vec<MacroStep>>,
+    /// The last macro recorded or replayed.
+    last: Option<String>,
+    /// How many macros are being replayed, within each other.
+    replaying: usize,
+}
+
+impl MacroRecorder {
+    pub fn recording(&self) -> Option<&str> {
+        self.recording.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    /// Start recording into the slot `name`, replacing what it had once
+    /// stopped.
+    pub fn start(&mut self, name: String) {
+        self.recording = Some((name, Vec::new()));
+    }
+
+    /// Stop recording, keeping the macro if anything was recorded.
+    pub fn stop(&mut self) -> Option<String> {
+        let (name, steps) = self.recording.take()?;
+        if steps.is_empty() {
+            return None;
+        }
+        self.macros.insert(name.clone(), steps);
+        self.last = Some(name.clone());
+        Some(name)
+    }
+
+    /// Record a step of the macro being recorded. The steps run by a replayed
+    /// macro aren't recorded, as they'd be replayed with it.
+    pub fn record(&mut self, step: MacroStep) {
+        if self.replaying > 0 {
+            return;
+        }
+        if let MacroStep::Command { command, .. } = &step {
+            if is_macro_command(command) {
+                return;
+            }
+        }
+        if let Some((_, steps)) = self.recording.as_mut() {
+            steps.push(step);
+        }
+    }
+
+    /// The steps of the macro `name`, or of the last one when it's not given,
+    /// which becomes the last one.
+    pub fn take_for_replay(
+        &mut self,
+        name: Option<&str>,
+    ) -> Option<(String, Vec<MacroStep>)> {
+        let name = name.map(str::to_string).or_else(|| self.last.clone())?;
+        let steps = self.macros.get(&name)?.clone();
+        self.last = Some(name.clone());
+        Some((name, steps))
+    }
+
+    /// Mark the start of a replay, which is refused when macros replay each
+    /// other too deeply.
+    pub fn begin_replay(&mut self) -> bool {
+        if self.replaying >= MAX_REPLAY_DEPTH {
+            return false;
+        }
+        self.replaying += 1;
+        true
+    }
+
+    pub fn end_replay(&mut self) {
+        self.replaying = self.replaying.saturating_sub(1);
+    }
+
+    /// The names of the recorded macros, sorted.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.macros.keys().cloned().collect();
+        names.sort();
+        names
+    }
+}
+
+/// Whether `command` records or replays macros, which isn't recorded itself.
+/// Replaying a register is, along with the register typed after it, so that a
+/// macro can replay another one.
+fn is_macro_command(command: &str) -> bool {
+    [
+        LapceWorkbenchCommand::ToggleMacroRecording,
+        LapceWorkbenchCommand::ReplayMacro,
+        LapceWorkbenchCommand::PaletteMacros,
+        LapceWorkbenchCommand::RecordMacroRegister,
+    ]
+    .iter()
+    .any(|cmd| <&'static str>::from(cmd) == command)
+}
+
+#[cfg(test)]
+mod tests {
+    use floem::keyboard::Modifiers;
+
+    use super::{MacroRecorder, MacroStep, MAX_REPLAY_DEPTH};
+
+    fn command(command: &str) -> MacroStep {
+        MacroStep::Command {
+            command: command.to_string(),
+            count: None,
+            mods: Modifiers::empty(),
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let mut recorder = MacroRecorder::default();
+        recorder.record(command("move_down"));
+        recorder.start("a".to_string());
+        assert_eq!(recorder.recording(), Some("a"));
+        recorder.record(command("move_down"));
+        recorder.record(command("toggle_macro_recording"));
+        recorder.record(MacroStep::Char("x".to_string()));
+        assert_eq!(recorder.stop(), Some("a".to_string()));
+        assert_eq!(recorder.recording(), None);
+
+        let (name, steps) = recorder.take_for_replay(None).unwrap();
+        assert_eq!(name, "a");
+        assert_eq!(
+            steps,
+            vec![command("move_down"), MacroStep::Char("x".to_string())]
+        );
+
+        // Nothing recorded keeps the macro that was there.
+        recorder.start("a".to_string());
+        assert_eq!(recorder.stop(), None);
+        assert_eq!(recorder.take_for_replay(Some("a")).unwrap().1.len(), 2);
+        assert!(recorder.take_for_replay(Some("b")).is_none());
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut recorder = MacroRecorder::default();
+        recorder.start("a".to_string());
+        assert!(recorder.begin_replay());
+        recorder.record(command("move_down"));
+        recorder.end_replay();
+        recorder.record(command("move_up"));
+        recorder.stop();
+        assert_eq!(
+            recorder.take_for_replay(Some("a")).unwrap().1,
+            vec![command("move_up")]
+        );
+
+        for _ in 0..MAX_REPLAY_DEPTH {
+            assert!(recorder.begin_replay());
+        }
+        assert!(!recorder.begin_replay());
+    }
+}
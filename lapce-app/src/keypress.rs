@@ -25,6 +25,7 @@
 use crate::{
     command::{lapce_internal_commands, CommandExecuted, CommandKind, LapceCommand},
     config::LapceConfig,
+    keyboard_macro::{MacroRecorder, MacroRegisterAction, MacroStep},
     keypress::{
         condition::{CheckCondition, Condition},
         keymap::KeymapMatch,
@@ -153,6 +154,10 @@ pub struct KeyPressData {
     pub command_keymaps: Rc<IndexMap<String, Vec<KeyMap>>>,
     pub commands_with_keymap: Rc<Vec<KeyMap>>,
     pub commands_without_keymap: Rc<Vec<LapceCommand>>,
+    /// The keyboard macros recorded and the one being recorded.
+    pub macros: RwSignal<MacroRecorder>,
+    /// Set while waiting for the register of the vim `q` and `@` commands.
+    pub macro_register: RwSignal<Option<MacroRegisterAction>>,
 }
 
 impl KeyPressData {
@@ -167,6 +172,8 @@ pub fn new(cx: Scope, config: &LapceConfig) -> Self {
             commands: Rc::new(lapce_internal_commands()),
             commands_with_keymap: Rc::new(Vec::new()),
             commands_without_keymap: Rc::new(Vec::new()),
+            macros: cx.create_rw_signal(MacroRecorder::default()),
+            macro_register: cx.create_rw_signal(None),
         };
         keypress.load_commands();
         keypress
@@ -248,12 +255,29 @@ fn run_command<T: KeyPressFocus + ?Sized>(
         focus: &T,
     ) -> CommandExecuted {
         if let Some(cmd) = self.commands.get(command) {
-            focus.run_command(cmd, count, mods)
+            let executed = focus.run_command(cmd, count, mods);
+            if executed == CommandExecuted::Yes {
+                self.record_macro_step(MacroStep::Command {
+                    command: command.to_string(),
+                    count,
+                    mods,
+                });
+            }
+            executed
         } else {
             CommandExecuted::No
         }
     }
 
+    fn record_macro_step(&self, step: MacroStep) {
+        if self
+            .macros
+            .with_untracked(|macros| macros.recording().is_some())
+        {
+            self.macros.update(|macros| macros.record(step));
+        }
+    }
+
     pub fn keypress<'a>(event: impl Into<EventRef<'a>>) -> Option<KeyPress> {
         let event = event.into();
 
@@ -400,6 +424,13 @@ pub fn handle_keymatch<T: KeyPressFocus + ?Sized>(
                             if let CommandKind::Move(_) = cmd.kind {
                                 let handled = focus.run_command(cmd, None, mods)
                                     == CommandExecuted::Yes;
+                                if handled {
+                                    self.record_macro_step(MacroStep::Command {
+                                        command,
+                                        count: None,
+                                        mods,
+                                    });
+                                }
                                 return KeyPressHandle {
                                     handled,
                                     keymatch,
@@ -427,6 +458,9 @@ pub fn handle_keymatch<T: KeyPressFocus + ?Sized>(
         if mods.is_empty() {
             if let KeyInput::Keyboard { logical, .. } = &keypress.key {
                 if let Key::Character(c) = logical {
+                    // Recorded first, so that the register a macro is
+                    // recorded into isn't part of it.
+                    self.record_macro_step(MacroStep::Char(c.to_string()));
                     focus.receive_char(c);
                     self.count.set(None);
                     return KeyPressHandle {
@@ -435,6 +469,7 @@ pub fn handle_keymatch<T: KeyPressFocus + ?Sized>(
                         keypress,
                     };
                 } else if let Key::Named(NamedKey::Space) = logical {
+                    self.record_macro_step(MacroStep::Char(" ".to_string()));
                     focus.receive_char(" ");
                     self.count.set(None);
                     return KeyPressHandle {
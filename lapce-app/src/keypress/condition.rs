@@ -55,6 +55,8 @@ pub enum Condition {
     ModalFocus,
     #[strum(serialize = "in_snippet")]
     InSnippet,
+    #[strum(serialize = "motion_mode_pending")]
+    MotionModePending,
     #[strum(serialize = "terminal_focus")]
     TerminalFocus,
     #[strum(serialize = "source_control_focus")]
@@ -71,6 +73,8 @@ pub enum Condition {
     SearchFocus,
     #[strum(serialize = "replace_focus")]
     ReplaceFocus,
+    #[strum(serialize = "signature_help_focus")]
+    SignatureHelpFocus,
 }
 
 #[cfg(test)]
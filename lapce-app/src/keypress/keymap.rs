@@ -306,6 +306,11 @@ fn from_str(s: &str) -> Result<Self, Self::Err> {
                 "f12" => Key::Named(NamedKey::F12),
                 "mediastop" => Key::Named(NamedKey::MediaStop),
                 "open" => Key::Named(NamedKey::Open),
+                "mousemiddle" => {
+                    return Ok(KeyMapKey::Pointer(PointerButton::Auxiliary))
+                }
+                "mouseforward" => return Ok(KeyMapKey::Pointer(PointerButton::X2)),
+                "mousebackward" => return Ok(KeyMapKey::Pointer(PointerButton::X1)),
                 _ => Key::Character(s.to_lowercase().into()),
             };
             KeyMapKey::Logical(key)
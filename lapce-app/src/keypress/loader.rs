@@ -137,7 +137,7 @@ fn get_modes(toml_keymap: &toml_edit::Table) -> Modes {
 
 #[cfg(test)]
 mod tests {
-    use floem::keyboard::Key;
+    use floem::{keyboard::Key, pointer::PointerButton};
 
     use super::*;
     use crate::keypress::keymap::KeyMapKey;
@@ -231,6 +231,7 @@ fn test_keymap() {
         // Mouse keys
         let keypress = KeyMapPress::parse("MouseForward");
         assert_eq!(keymaps.get(&keypress).unwrap().len(), 1);
+        assert_eq!(keypress[0].key, KeyMapKey::Pointer(PointerButton::X2));
 
         let keypress = KeyMapPress::parse("mousebackward");
         assert_eq!(keymaps.get(&keypress).unwrap().len(), 1);
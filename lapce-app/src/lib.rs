@@ -1,6 +1,8 @@
 pub mod about;
 pub mod alert;
 pub mod app;
+pub mod bookmark;
+pub mod clipboard;
 pub mod code_action;
 pub mod code_lens;
 pub mod command;
@@ -8,38 +10,56 @@
 pub mod config;
 pub mod db;
 pub mod debug;
+pub mod debug_console;
 pub mod doc;
+pub mod document_link;
 pub mod editor;
 pub mod editor_tab;
+pub mod emmet;
 pub mod file_explorer;
+pub mod file_template;
 pub mod find;
 pub mod focus_text;
 pub mod global_search;
 pub mod history;
 pub mod hover;
+pub mod hunk_peek;
 pub mod id;
+pub mod image_viewer;
 pub mod inline_completion;
+pub mod keyboard_macro;
 pub mod keymap;
 pub mod keypress;
 pub mod listener;
 pub mod lsp;
 pub mod main_split;
 pub mod markdown;
+pub mod markdown_preview;
+pub mod notification;
 pub mod palette;
 pub mod panel;
+pub mod peek;
+pub mod perf;
 pub mod plugin;
 pub mod proxy;
 pub mod rename;
 pub mod settings;
+pub mod signature;
 pub mod snippet;
 pub mod source_control;
+pub mod spell;
 pub mod status;
+pub mod task;
 pub mod terminal;
 pub mod text_area;
 pub mod text_input;
+pub mod text_transform;
 pub mod title;
 pub mod tracing;
+pub mod undo_tree;
 pub mod update;
+pub mod user_snippet;
+pub mod vim;
 pub mod wave;
 pub mod web_link;
 pub mod window;
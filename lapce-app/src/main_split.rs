@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     path::{Path, PathBuf},
     rc::Rc,
 };
@@ -11,23 +11,28 @@
     keyboard::Modifiers,
     peniko::kurbo::{Point, Rect, Vec2},
     reactive::{Memo, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
-    views::editor::id::EditorId,
+    views::editor::{
+        core::register::Clipboard, id::EditorId, text::SystemClipboard,
+    },
 };
 use itertools::Itertools;
 use lapce_core::{
     buffer::rope_text::RopeText, command::FocusCommand, cursor::Cursor,
-    rope_text_pos::RopeTextPosition, selection::Selection, syntax::Syntax,
+    editor::EditType, language::LapceLanguage, rope_text_pos::RopeTextPosition,
+    selection::Selection, syntax::Syntax,
 };
 use lapce_rpc::{
     buffer::BufferId,
     core::FileChanged,
-    plugin::{PluginId, VoltID},
+    plugin::{PluginEvent, PluginId, VoltID},
     proxy::ProxyResponse,
+    RpcError,
 };
 use lapce_xi_rope::{spans::SpansBuilder, Rope};
 use lsp_types::{
-    CodeAction, CodeActionOrCommand, DiagnosticSeverity, DocumentChangeOperation,
-    DocumentChanges, OneOf, Position, TextEdit, Url, WorkspaceEdit,
+    CodeAction, CodeActionOrCommand, DeleteFile, DiagnosticSeverity,
+    DocumentChangeOperation, DocumentChanges, MessageType, OneOf, Position,
+    RenameFile, ResourceOp, ShowMessageParams, TextEdit, Url, WorkspaceEdit,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -46,12 +51,15 @@
     editor_tab::{
         EditorTabChild, EditorTabChildSource, EditorTabData, EditorTabInfo,
     },
+    file_template::FileTemplate,
     id::{
-        DiffEditorId, EditorTabId, KeymapId, SettingsId, SplitId,
-        ThemeColorSettingsId, VoltViewId,
+        DiffEditorId, EditorTabId, ImageViewId, KeymapId, MarkdownPreviewId,
+        SettingsId, SplitId, ThemeColorSettingsId, VoltViewId,
     },
+    image_viewer::{is_image, ImageViewerData},
     keypress::{EventRef, KeyPressData, KeyPressHandle},
-    panel::implementation_view::ReferencesRoot,
+    panel::{implementation_view::ReferencesRoot, problem_view::problem_severity},
+    spell::ADD_WORD_COMMAND,
     window_tab::{CommonData, Focus, WindowTabData},
 };
 
@@ -241,6 +249,7 @@ fn new(cx: Scope) -> Self {
     /// Returns the id of the editor.
     pub fn insert(&self, editor: EditorData) -> EditorId {
         let id = editor.id();
+        self.notify_doc_opened(&editor);
         self.0.update(|editors| {
             if editors.insert(id, editor).is_some() {
                 event!(Level::WARN, "Inserted EditorId that already exists");
@@ -251,11 +260,74 @@ pub fn insert(&self, editor: EditorData) -> EditorId {
     }
 
     pub fn insert_with_id(&self, id: EditorId, editor: EditorData) {
+        self.notify_doc_opened(&editor);
         self.0.update(|editors| {
             editors.insert(id, editor);
         });
     }
 
+    /// Tell the plugins about the file of `editor` being opened if no editor
+    /// shows it yet, as they're told about it being closed once the last
+    /// editor showing it is removed.
+    fn notify_doc_opened(&self, editor: &EditorData) {
+        if let DocContent::File { path, .. } = editor.doc().content.get_untracked() {
+            self.notify_path_opened(editor, path);
+        }
+    }
+
+    fn notify_path_opened(&self, editor: &EditorData, path: PathBuf) {
+        if !self.path_shown(&path, editor.id()) {
+            editor
+                .common
+                .proxy
+                .plugin_event(PluginEvent::DocumentOpened { path });
+        }
+    }
+
+    /// Tell the plugins about the file at `path` being closed if no editor but
+    /// `editor`, which stopped showing it, shows it.
+    pub fn notify_path_closed(&self, editor: &EditorData, path: PathBuf) {
+        if !self.path_shown(&path, editor.id()) {
+            editor
+                .common
+                .proxy
+                .plugin_event(PluginEvent::DocumentClosed { path });
+        }
+    }
+
+    /// Tell the plugins about the files `editor` stopped and started showing
+    /// when its document was swapped for another one. Editors that aren't
+    /// registered, like the previews, aren't counted as showing files.
+    pub fn notify_doc_swapped(&self, editor: &EditorData, old: &DocContent) {
+        if self.editor_untracked(editor.id()).is_none() {
+            return;
+        }
+        let old = old.path().cloned();
+        let new = editor.doc().content.with_untracked(|c| c.path().cloned());
+        if old == new {
+            return;
+        }
+        if let Some(old) = old {
+            self.notify_path_closed(editor, old);
+        }
+        if let Some(new) = new {
+            self.notify_path_opened(editor, new);
+        }
+    }
+
+    /// Whether an editor other than the one with the id `except` shows the
+    /// file at `path`.
+    fn path_shown(&self, path: &Path, except: EditorId) -> bool {
+        self.0.with_untracked(|editors| {
+            editors.iter().any(|(id, editor_data)| {
+                *id != except
+                    && editor_data.doc().content.with_untracked(|content| {
+                        content.path().map(PathBuf::as_path) == Some(path)
+                    })
+            })
+        })
+    }
+
     pub fn new_local(&self, cx: Scope, common: Rc<CommonData>) -> EditorId {
         let editor = EditorData::new_local(cx, *self, common);
 
@@ -397,6 +469,7 @@ pub struct MainSplitData {
     pub editor_tabs: RwSignal<im::HashMap<EditorTabId, RwSignal<EditorTabData>>>,
     pub editors: Editors,
     pub diff_editors: RwSignal<im::HashMap<DiffEditorId, DiffEditorData>>,
+    pub image_viewers: RwSignal<im::HashMap<ImageViewId, ImageViewerData>>,
     pub docs: RwSignal<im::HashMap<PathBuf, Rc<Doc>>>,
     pub scratch_docs: RwSignal<im::HashMap<String, Rc<Doc>>>,
     pub diagnostics: RwSignal<im::HashMap<PathBuf, DiagnosticData>>,
@@ -409,6 +482,9 @@ pub struct MainSplitData {
     pub current_location: RwSignal<usize>,
     pub width: RwSignal<f64>,
     pub code_lens: RwSignal<CodeLensData>,
+    /// The inverses of the file operations of the workspace edits, to undo
+    /// them with.
+    pub file_operation_undo: RwSignal<Vec<Vec<ResourceOp>>>,
     pub common: Rc<CommonData>,
 }
 
@@ -430,6 +506,7 @@ pub fn new(cx: Scope, common: Rc<CommonData>) -> Self {
         let editors = Editors::new(cx);
         let diff_editors: RwSignal<im::HashMap<DiffEditorId, DiffEditorData>> =
             cx.create_rw_signal(im::HashMap::new());
+        let image_viewers = cx.create_rw_signal(im::HashMap::new());
         let docs: RwSignal<im::HashMap<PathBuf, Rc<Doc>>> =
             cx.create_rw_signal(im::HashMap::new());
         let scratch_docs = cx.create_rw_signal(im::HashMap::new());
@@ -486,6 +563,7 @@ pub fn new(cx: Scope, common: Rc<CommonData>) -> Self {
             editor_tabs,
             editors,
             diff_editors,
+            image_viewers,
             docs,
             scratch_docs,
             active_editor,
@@ -496,6 +574,7 @@ pub fn new(cx: Scope, common: Rc<CommonData>) -> Self {
             current_location,
             width: cx.create_rw_signal(0.0),
             code_lens: cx.create_rw_signal(CodeLensData::new(common.clone())),
+            file_operation_undo: cx.create_rw_signal(Vec::new()),
             common,
             references,
             implementations,
@@ -519,6 +598,7 @@ pub fn key_down<'a>(
                 let editor = self.editors.editor_untracked(editor_id)?;
                 let handle = keypress.key_down(event, &editor);
                 editor.get_code_actions();
+                editor.update_linked_editing_ranges();
                 Some(handle)
             }
             EditorTabChild::DiffEditor(diff_editor_id) => {
@@ -533,12 +613,15 @@ pub fn key_down<'a>(
                 };
                 let handle = keypress.key_down(event, editor);
                 editor.get_code_actions();
+                editor.update_linked_editing_ranges();
                 Some(handle)
             }
             EditorTabChild::Settings(_) => None,
             EditorTabChild::ThemeColorSettings(_) => None,
             EditorTabChild::Keymap(_) => None,
             EditorTabChild::Volt(_, _) => None,
+            EditorTabChild::Image(_, _) => None,
+            EditorTabChild::MarkdownPreview(_, _) => None,
         }
     }
 
@@ -645,9 +728,11 @@ pub fn get_doc(
                     if let Ok(ProxyResponse::NewBufferResponse {
                         content,
                         read_only,
+                        encoding,
                     }) = result
                     {
                         local_doc.init_content(Rope::from(content));
+                        local_doc.encoding.set(encoding);
                         if read_only {
                             local_doc.content.update(|content| {
                                 if let DocContent::File { read_only, .. } = content {
@@ -657,6 +742,8 @@ pub fn get_doc(
                         } else if let Some(unsaved) = unsaved {
                             local_doc.reload(Rope::from(unsaved), false);
                         }
+                    } else {
+                        local_doc.load_failed.set(true);
                     }
                 });
 
@@ -673,6 +760,26 @@ pub fn get_doc(
         }
     }
 
+    /// Close the document of `path`, opened without an editor, if no editor
+    /// has shown it since.
+    pub fn release_doc(&self, path: &Path) {
+        let doc_shown = self.editors.with_editors_untracked(|editors| {
+            editors.iter().any(|(_, editor_data)| {
+                editor_data.doc().content.with_untracked(|content| {
+                    content.path().map(|p| p.as_path()) == Some(path)
+                })
+            })
+        });
+        if doc_shown {
+            return;
+        }
+        // The plugins were told about the document being closed along with
+        // the last editor showing it, if there was one.
+        self.docs.update(|docs| {
+            docs.remove(path);
+        });
+    }
+
     pub fn go_to_location(
         &self,
         location: EditorLocation,
@@ -682,6 +789,12 @@ pub fn go_to_location(
             self.common.focus.set(Focus::Workbench);
         }
         let path = location.path.clone();
+        // The viewer reads the image from the local file system, so images of
+        // remote workspaces are opened as documents through the proxy.
+        if is_image(&path) && self.common.workspace.kind.is_local() {
+            self.open_image(path);
+            return;
+        }
         let (doc, new_doc) = self.get_doc(path.clone(), None);
 
         let child = self.get_editor_tab_child(
@@ -696,6 +809,129 @@ pub fn go_to_location(
         }
     }
 
+    /// Open a new read-only scratch document holding `content`, which isn't
+    /// a file until it's saved as one.
+    pub fn open_scratch_doc(&self, content: String) {
+        if self.common.focus.get_untracked() != Focus::Workbench {
+            self.common.focus.set(Focus::Workbench);
+        }
+        let EditorTabChild::Editor(editor_id) = self.new_file() else {
+            return;
+        };
+        if let Some(editor) = self.editors.editor_untracked(editor_id) {
+            let doc = editor.doc();
+            doc.init_content(Rope::from(content));
+            doc.content.update(|content| {
+                if let DocContent::Scratch { read_only, .. } = content {
+                    *read_only = true;
+                }
+            });
+        }
+    }
+
+    pub fn open_image(&self, path: PathBuf) {
+        self.get_editor_tab_child(EditorTabChildSource::Image(path), false, false);
+    }
+
+    pub fn new_image_viewer(&self, path: PathBuf) -> ImageViewId {
+        let id = ImageViewId::next();
+        let image_viewer = ImageViewerData::new(self.scope, id, path);
+        self.image_viewers.update(|image_viewers| {
+            image_viewers.insert(id, image_viewer);
+        });
+        id
+    }
+
+    /// The diff editor shown in the active editor tab, if any.
+    pub fn active_diff_editor(&self) -> Option<DiffEditorData> {
+        let editor_tab = self.active_editor_tab.get_untracked()?;
+        let editor_tab = self
+            .editor_tabs
+            .with_untracked(|editor_tabs| editor_tabs.get(&editor_tab).copied())?;
+        let (_, _, child) = editor_tab.with_untracked(|editor_tab| {
+            editor_tab.children.get(editor_tab.active).cloned()
+        })?;
+        if let EditorTabChild::DiffEditor(diff_editor_id) = child {
+            self.diff_editors.with_untracked(|diff_editors| {
+                diff_editors.get(&diff_editor_id).cloned()
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The image viewer shown in the active editor tab, if any.
+    pub fn active_image_viewer(&self) -> Option<ImageViewerData> {
+        let editor_tab = self.active_editor_tab.get()?;
+        let editor_tab = self
+            .editor_tabs
+            .with(|editor_tabs| editor_tabs.get(&editor_tab).copied())?;
+        let (_, _, child) = editor_tab.with(|editor_tab| {
+            editor_tab.children.get(editor_tab.active).cloned()
+        })?;
+        if let EditorTabChild::Image(image_view_id, _) = child {
+            self.image_viewers
+                .with(|image_viewers| image_viewers.get(&image_view_id).cloned())
+        } else {
+            None
+        }
+    }
+
+    /// Show a preview of the markdown file of the active editor in a new split
+    /// to its right, or focus the one already showing it.
+    pub fn open_markdown_preview(&self) -> Option<()> {
+        let editor = self.active_editor.get_untracked()?;
+        let doc = editor.doc();
+        if doc.syntax().with_untracked(|s| s.language) != LapceLanguage::Markdown {
+            return None;
+        }
+        let path = doc
+            .content
+            .with_untracked(|content| content.path().cloned())?;
+        let editor_tab_id = self.active_editor_tab.get_untracked()?;
+
+        let editor_tabs = self.editor_tabs.get_untracked();
+        for (id, editor_tab) in &editor_tabs {
+            let index = editor_tab.with_untracked(|editor_tab| {
+                editor_tab.children.iter().position(|(_, _, child)| {
+                    matches!(child, EditorTabChild::MarkdownPreview(_, p) if p == &path)
+                })
+            });
+            if let Some(index) = index {
+                editor_tab.update(|editor_tab| {
+                    editor_tab.active = index;
+                });
+                if *id != editor_tab_id {
+                    self.active_editor_tab.set(Some(*id));
+                }
+                return Some(());
+            }
+        }
+
+        let editor_tab = editor_tabs.get(&editor_tab_id).copied()?;
+        let (active, index) = editor_tab.try_update(|editor_tab| {
+            editor_tab.children.push((
+                editor_tab.scope.create_rw_signal(0),
+                editor_tab.scope.create_rw_signal(Rect::ZERO),
+                EditorTabChild::MarkdownPreview(MarkdownPreviewId::next(), path),
+            ));
+            (editor_tab.active, editor_tab.children.len() - 1)
+        })?;
+        self.move_editor_tab_child_to_new_split(
+            editor_tab_id,
+            index,
+            editor_tab_id,
+            SplitMoveDirection::Right,
+        )?;
+
+        // Keep editing the file with the preview beside it.
+        editor_tab.update(|editor_tab| {
+            editor_tab.active = active;
+        });
+        self.active_editor_tab.set(Some(editor_tab_id));
+        Some(())
+    }
+
     pub fn open_file_changes(&self, path: PathBuf) {
         let (right, _) = self.get_doc(path.clone(), None);
         let left = Doc::new_history(
@@ -729,6 +965,122 @@ pub fn open_file_changes(&self, path: PathBuf) {
         );
     }
 
+    /// Compare the file with its version saved at `timestamp`, which is shown
+    /// on the left.
+    pub fn open_local_version(&self, path: PathBuf, timestamp: u64) {
+        let (right, _) = self.get_doc(path.clone(), None);
+        let left = Doc::new_history(
+            self.scope,
+            DocContent::History(DocHistory::local(path.clone(), timestamp)),
+            self.editors,
+            self.common.clone(),
+        );
+        let left = Rc::new(left);
+
+        let send = {
+            let left = left.clone();
+            create_ext_action(self.scope, move |result| match result {
+                Ok(ProxyResponse::ReadFileResponse { content }) => {
+                    left.init_content(Rope::from(content));
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::error!("{:?}", err);
+                }
+            })
+        };
+        self.common
+            .proxy
+            .local_history_content(path, timestamp, move |result| {
+                send(result);
+            });
+
+        self.get_editor_tab_child(
+            EditorTabChildSource::DiffEditor { left, right },
+            false,
+            false,
+        );
+    }
+
+    /// Replace the content of the open file with its version saved at
+    /// `timestamp`, which can be undone and is only written once saved.
+    pub fn restore_local_version(&self, path: PathBuf, timestamp: u64) {
+        let Some(doc) = self.docs.with_untracked(|docs| docs.get(&path).cloned())
+        else {
+            return;
+        };
+        let send = create_ext_action(self.scope, move |result| match result {
+            Ok(ProxyResponse::ReadFileResponse { content }) => {
+                let len = doc.buffer.with_untracked(|buffer| buffer.len());
+                doc.do_raw_edit(
+                    &[(Selection::region(0, len), content.as_str())],
+                    EditType::Other,
+                );
+            }
+            Ok(_) => {}
+            Err(err) => {
+                tracing::error!("{:?}", err);
+            }
+        });
+        self.common
+            .proxy
+            .local_history_content(path, timestamp, move |result| {
+                send(result);
+            });
+    }
+
+    /// Compare the active editor's buffer, on the right, with the text of the
+    /// clipboard.
+    pub fn open_clipboard_diff(&self) -> Option<()> {
+        let right = self.active_editor.get_untracked()?.doc();
+        let path = right.content.with_untracked(|content| match content {
+            DocContent::File { path, .. } => Some(path.clone()),
+            DocContent::History(history) => Some(history.path.clone()),
+            DocContent::Scratch { name, .. } => Some(PathBuf::from(name)),
+            DocContent::Local => None,
+        })?;
+        let text = SystemClipboard::new().get_string().unwrap_or_default();
+        let left = Doc::new_history(
+            self.scope,
+            DocContent::History(DocHistory::clipboard(path)),
+            self.editors,
+            self.common.clone(),
+        );
+        left.init_content(Rope::from(text));
+
+        self.get_editor_tab_child(
+            EditorTabChildSource::DiffEditor {
+                left: Rc::new(left),
+                right,
+            },
+            false,
+            false,
+        );
+        Some(())
+    }
+
+    /// Compare the file at `path`, on the left, with `text`, its content once
+    /// the replacements of the global search are made.
+    pub fn open_replace_preview(&self, path: PathBuf, text: String) {
+        let (left, _) = self.get_doc(path.clone(), None);
+        let right = Doc::new_history(
+            self.scope,
+            DocContent::History(DocHistory::replaced(path)),
+            self.editors,
+            self.common.clone(),
+        );
+        right.init_content(Rope::from(text));
+
+        self.get_editor_tab_child(
+            EditorTabChildSource::DiffEditor {
+                left,
+                right: Rc::new(right),
+            },
+            false,
+            false,
+        );
+    }
+
     pub fn open_diff_files(&self, left_path: PathBuf, right_path: PathBuf) {
         let [left, right] =
             [left_path, right_path].map(|path| self.get_doc(path, None).0);
@@ -753,6 +1105,7 @@ fn new_editor_tab(
                 active: 0,
                 editor_tab_id,
                 children: vec![],
+                pinned: im::HashSet::new(),
                 window_origin: Point::ZERO,
                 layout_rect: Rect::ZERO,
                 locations: cx.create_rw_signal(im::Vector::new()),
@@ -872,6 +1225,8 @@ fn get_editor_tab_child(
                         EditorTabChild::ThemeColorSettings(_) => true,
                         EditorTabChild::Keymap(_) => true,
                         EditorTabChild::Volt(_, _) => true,
+                        EditorTabChild::Image(_, _) => true,
+                        EditorTabChild::MarkdownPreview(_, _) => true,
                     };
 
                     if can_be_selected {
@@ -1037,6 +1392,33 @@ fn get_editor_tab_child(
                         })
                     }
                 }
+                EditorTabChildSource::Image(path) => {
+                    if let Some(index) =
+                        active_editor_tab.with_untracked(|editor_tab| {
+                            editor_tab.children.iter().position(|(_, _, child)| {
+                                if let EditorTabChild::Image(_, current_path) = child
+                                {
+                                    current_path == path
+                                } else {
+                                    false
+                                }
+                            })
+                        })
+                    {
+                        Some(index)
+                    } else if ignore_unconfirmed {
+                        None
+                    } else {
+                        active_editor_tab.with_untracked(|editor_tab| {
+                            editor_tab
+                                .get_unconfirmed_editor_tab_child(
+                                    editors,
+                                    &diff_editors,
+                                )
+                                .map(|(i, _)| i)
+                        })
+                    }
+                }
             }
         };
 
@@ -1060,6 +1442,7 @@ fn get_editor_tab_child(
                     let doc_content = DocContent::Scratch {
                         id: BufferId::next(),
                         name: name.clone(),
+                        read_only: false,
                     };
                     let doc = Doc::new_content(
                         self.scope,
@@ -1094,6 +1477,10 @@ fn get_editor_tab_child(
                 EditorTabChildSource::Volt(id) => {
                     EditorTabChild::Volt(VoltViewId::next(), id.to_owned())
                 }
+                EditorTabChildSource::Image(path) => EditorTabChild::Image(
+                    self.new_image_viewer(path.to_owned()),
+                    path.to_owned(),
+                ),
                 EditorTabChildSource::DiffEditor { left, right } => {
                     let diff_editor_id = DiffEditorId::next();
                     let diff_editor = DiffEditorData::new(
@@ -1130,6 +1517,8 @@ fn get_editor_tab_child(
                         EditorTabChild::ThemeColorSettings(_) => {}
                         EditorTabChild::Keymap(_) => {}
                         EditorTabChild::Volt(_, _) => {}
+                        EditorTabChild::Image(_, _) => {}
+                        EditorTabChild::MarkdownPreview(_, _) => {}
                     }
                     (editor_tab_id, current_child.clone())
                 });
@@ -1196,6 +1585,12 @@ fn get_editor_tab_child(
                 EditorTabChild::ThemeColorSettings(_) => {}
                 EditorTabChild::Keymap(_) => {}
                 EditorTabChild::Volt(_, _) => {}
+                EditorTabChild::Image(image_view_id, _) => {
+                    self.image_viewers.update(|image_viewers| {
+                        image_viewers.remove(image_view_id);
+                    });
+                }
+                EditorTabChild::MarkdownPreview(_, _) => {}
             }
 
             // Now loading the new child
@@ -1271,6 +1666,18 @@ fn get_editor_tab_child(
                                         false
                                     }
                                 }),
+                            EditorTabChildSource::Image(path) => editor_tab
+                                .children
+                                .iter()
+                                .position(|(_, _, child)| {
+                                    if let EditorTabChild::Image(_, current_path) =
+                                        child
+                                    {
+                                        current_path == path
+                                    } else {
+                                        false
+                                    }
+                                }),
                             EditorTabChildSource::NewFileEditor => None,
                         })
                     {
@@ -1321,6 +1728,9 @@ pub fn remove_editor(&self, editor_id: EditorId) {
 
             let doc = editor.doc();
             let (content, _) = (doc.content.get_untracked(), doc.is_pristine());
+            if let DocContent::File { path, .. } = &content {
+                self.editors.notify_path_closed(&editor, path.clone());
+            }
             if let DocContent::Scratch { name, .. } = content {
                 let doc_exists = self.editors.with_editors_untracked(|editors| {
                     editors.iter().any(|(_, editor_data)| {
@@ -1541,6 +1951,7 @@ fn split_editor_tab(
         editor_tab: &EditorTabData,
     ) -> Option<RwSignal<EditorTabData>> {
         let (_, _, child) = editor_tab.children.get(editor_tab.active)?;
+        let is_pinned = editor_tab.is_pinned(child);
 
         let editor_tab_id = EditorTabId::next();
 
@@ -1575,8 +1986,22 @@ fn split_editor_tab(
             EditorTabChild::Volt(_, id) => {
                 EditorTabChild::Volt(VoltViewId::next(), id.to_owned())
             }
+            EditorTabChild::Image(_, path) => EditorTabChild::Image(
+                self.new_image_viewer(path.to_owned()),
+                path.to_owned(),
+            ),
+            EditorTabChild::MarkdownPreview(_, path) => {
+                EditorTabChild::MarkdownPreview(
+                    MarkdownPreviewId::next(),
+                    path.to_owned(),
+                )
+            }
         };
 
+        let mut pinned = im::HashSet::new();
+        if is_pinned {
+            pinned.insert(new_child.clone());
+        }
         let editor_tab = {
             let cx = self.scope.create_child();
             let editor_tab = EditorTabData {
@@ -1589,6 +2014,7 @@ fn split_editor_tab(
                     cx.create_rw_signal(Rect::ZERO),
                     new_child,
                 )],
+                pinned,
                 window_origin: Point::ZERO,
                 layout_rect: Rect::ZERO,
                 locations: cx.create_rw_signal(editor_tab.locations.get_untracked()),
@@ -1917,6 +2343,8 @@ fn editor_tab_child_close_warning(
             EditorTabChild::ThemeColorSettings(_) => None,
             EditorTabChild::Keymap(_) => None,
             EditorTabChild::Volt(_, _) => None,
+            EditorTabChild::Image(_, _) => None,
+            EditorTabChild::MarkdownPreview(_, _) => None,
         }
     }
 
@@ -1938,7 +2366,8 @@ pub fn split_active(&self, direction: SplitDirection) -> Option<()> {
         Some(())
     }
 
-    pub fn editor_tab_child_close_active(&self) -> Option<()> {
+    /// The active editor tab and the child active in it.
+    pub fn active_editor_tab_child(&self) -> Option<(EditorTabId, EditorTabChild)> {
         let active_editor_tab = self.active_editor_tab.get_untracked()?;
         let editor_tab = self.editor_tabs.with_untracked(|editor_tabs| {
             editor_tabs.get(&active_editor_tab).copied()
@@ -1946,6 +2375,11 @@ pub fn editor_tab_child_close_active(&self) -> Option<()> {
         let (_, _, child) = editor_tab.with_untracked(|editor_tab| {
             editor_tab.children.get(editor_tab.active).cloned()
         })?;
+        Some((active_editor_tab, child))
+    }
+
+    pub fn editor_tab_child_close_active(&self) -> Option<()> {
+        let (active_editor_tab, child) = self.active_editor_tab_child()?;
         self.editor_tab_child_close(active_editor_tab, child, false);
         Some(())
     }
@@ -1961,7 +2395,7 @@ pub fn editor_tab_child_close_by_kind(
 
             let editor_tab = editor_tabs.get(&editor_tab_id).copied()?;
             let editor_tab = editor_tab.get_untracked();
-            match kind {
+            let tabs_to_close: Vec<EditorTabChild> = match kind {
                 TabCloseKind::CloseOther => editor_tab
                     .children
                     .iter()
@@ -1996,7 +2430,12 @@ pub fn editor_tab_child_close_by_kind(
                     }
                     tabs_to_close
                 }
-            }
+            };
+            // Pinned tabs are only closed one by one.
+            tabs_to_close
+                .into_iter()
+                .filter(|child| !editor_tab.is_pinned(child))
+                .collect()
         };
         for child_tab in tabs_to_close {
             self.editor_tab_child_close(editor_tab_id, child_tab, false);
@@ -2004,6 +2443,50 @@ pub fn editor_tab_child_close_by_kind(
         Some(())
     }
 
+    /// Pin or unpin `child`, so that it's kept when closing the other tabs.
+    /// A pinned tab is no longer a preview one replaced by the next file.
+    pub fn editor_tab_child_pin(
+        &self,
+        editor_tab_id: EditorTabId,
+        child: EditorTabChild,
+        pinned: bool,
+    ) -> Option<()> {
+        let editor_tab = self.editor_tabs.with_untracked(|editor_tabs| {
+            editor_tabs.get(&editor_tab_id).copied()
+        })?;
+        if pinned {
+            self.editor_tab_child_confirm(&child);
+        }
+        editor_tab.update(|editor_tab| {
+            if pinned {
+                editor_tab.pinned.insert(child);
+            } else {
+                editor_tab.pinned.remove(&child);
+            };
+        });
+        Some(())
+    }
+
+    /// Keep `child` open instead of replacing it with the next file opened
+    /// as a preview.
+    pub fn editor_tab_child_confirm(&self, child: &EditorTabChild) {
+        let confirmed = match child {
+            EditorTabChild::Editor(editor_id) => self
+                .editors
+                .editor_untracked(*editor_id)
+                .map(|editor| editor.confirmed),
+            EditorTabChild::DiffEditor(diff_editor_id) => {
+                self.diff_editors.with_untracked(|diff_editors| {
+                    diff_editors.get(diff_editor_id).map(|d| d.confirmed)
+                })
+            }
+            _ => None,
+        };
+        if let Some(confirmed) = confirmed {
+            confirmed.set(true);
+        }
+    }
+
     pub fn editor_tab_child_close(
         &self,
         editor_tab_id: EditorTabId,
@@ -2130,6 +2613,7 @@ pub fn editor_tab_child_close(
         let editor_tab_children_len = editor_tab
             .try_update(|editor_tab| {
                 editor_tab.children.remove(index);
+                editor_tab.pinned.remove(&child);
                 editor_tab.active =
                     index.min(editor_tab.children.len().saturating_sub(1));
                 editor_tab.children.len()
@@ -2153,6 +2637,12 @@ pub fn editor_tab_child_close(
             EditorTabChild::ThemeColorSettings(_) => {}
             EditorTabChild::Keymap(_) => {}
             EditorTabChild::Volt(_, _) => {}
+            EditorTabChild::Image(image_view_id, _) => {
+                self.image_viewers.update(|image_viewers| {
+                    image_viewers.remove(&image_view_id);
+                });
+            }
+            EditorTabChild::MarkdownPreview(_, _) => {}
         }
 
         if editor_tab_children_len == 0 {
@@ -2183,6 +2673,21 @@ pub fn editor_tab_update_layout(
 
     pub fn run_code_action(&self, plugin_id: PluginId, action: CodeActionOrCommand) {
         match action {
+            CodeActionOrCommand::Command(command)
+                if command.command == ADD_WORD_COMMAND =>
+            {
+                let word = command
+                    .arguments
+                    .and_then(|args| args.first()?.as_str().map(String::from));
+                if let Some(word) = word {
+                    self.common.spell.add_word(&word);
+                    // Relayout the open documents, so the word is no longer
+                    // underlined.
+                    for doc in self.docs.get_untracked().values() {
+                        doc.clear_text_cache();
+                    }
+                }
+            }
             CodeActionOrCommand::Command(command) => {
                 self.run_code_lens(
                     &command.command,
@@ -2190,8 +2695,8 @@ pub fn run_code_action(&self, plugin_id: PluginId, action: CodeActionOrCommand)
                 );
             }
             CodeActionOrCommand::CodeAction(action) => {
-                if let Some(edit) = action.edit.as_ref() {
-                    self.apply_workspace_edit(edit);
+                if action.edit.is_some() {
+                    self.apply_code_action(plugin_id, action);
                 } else {
                     self.resolve_code_action(plugin_id, action);
                 }
@@ -2199,65 +2704,304 @@ pub fn run_code_action(&self, plugin_id: PluginId, action: CodeActionOrCommand)
         }
     }
 
+    /// Apply the workspace edit of a code action, then run its command, as
+    /// the LSP has it.
+    fn apply_code_action(&self, plugin_id: PluginId, action: CodeAction) {
+        if let Some(edit) = action.edit.as_ref() {
+            self.apply_workspace_edit(edit);
+        }
+        if let Some(command) = action.command {
+            self.run_code_action(plugin_id, CodeActionOrCommand::Command(command));
+        }
+    }
+
     pub fn run_code_lens(&self, command: &str, args: Vec<Value>) {
         self.code_lens.get_untracked().run(command, args);
     }
 
-    /// Resolve a code action and apply its held workspace edit
+    /// Resolve a code action, apply its held workspace edit and run its
+    /// command
     fn resolve_code_action(&self, plugin_id: PluginId, action: CodeAction) {
         let main_split = self.clone();
-        let send = create_ext_action(self.scope, move |edit| {
-            main_split.apply_workspace_edit(&edit);
+        let send = create_ext_action(self.scope, move |item| {
+            main_split.apply_code_action(plugin_id, item);
         });
         self.common
             .proxy
             .code_action_resolve(action, plugin_id, move |result| {
                 if let Ok(ProxyResponse::CodeActionResolveResponse { item }) = result
                 {
-                    if let Some(edit) = item.edit {
-                        send(edit);
-                    }
+                    send(*item);
                 }
             });
     }
 
     /// Perform a workspace edit, which are from the LSP (such as code actions, or symbol renaming)
     pub fn apply_workspace_edit(&self, edit: &WorkspaceEdit) {
-        if let Some(DocumentChanges::Operations(_op)) =
+        if let Some(DocumentChanges::Operations(ops)) =
             edit.document_changes.as_ref()
         {
-            // TODO
+            // The resource operations have to be applied in order with the
+            // text edits, as the edits can be to files they create or rename.
+            if ops
+                .iter()
+                .any(|op| matches!(op, DocumentChangeOperation::Op(_)))
+            {
+                self.apply_document_change_operations(
+                    ops.iter().cloned().collect(),
+                    Some(Vec::new()),
+                );
+                return;
+            }
         }
 
         if let Some(edits) = workspace_edits(edit) {
             for (url, edits) in edits {
-                if let Ok(path) = url.to_file_path() {
-                    let active_path = self
-                        .active_editor
-                        .get_untracked()
-                        .map(|editor| editor.doc())
-                        .map(|doc| doc.content.get_untracked())
-                        .and_then(|content| content.path().cloned());
-                    let position = if active_path.as_ref() == Some(&path) {
-                        None
-                    } else {
-                        edits
-                            .first()
-                            .map(|edit| EditorPosition::Position(edit.range.start))
-                    };
-                    let location = EditorLocation {
-                        path,
-                        position,
-                        scroll_offset: None,
-                        ignore_unconfirmed: true,
-                        same_editor_tab: false,
-                    };
-                    self.jump_to_location(location, Some(edits));
+                self.apply_text_edits(&url, edits);
+            }
+        }
+    }
+
+    fn apply_text_edits(&self, url: &Url, edits: Vec<TextEdit>) {
+        if let Ok(path) = url.to_file_path() {
+            let active_path = self
+                .active_editor
+                .get_untracked()
+                .map(|editor| editor.doc())
+                .map(|doc| doc.content.get_untracked())
+                .and_then(|content| content.path().cloned());
+            let position = if active_path.as_ref() == Some(&path) {
+                None
+            } else {
+                edits
+                    .first()
+                    .map(|edit| EditorPosition::Position(edit.range.start))
+            };
+            let location = EditorLocation {
+                path,
+                position,
+                scroll_offset: None,
+                ignore_unconfirmed: true,
+                same_editor_tab: false,
+            };
+            self.jump_to_location(location, Some(edits));
+        }
+    }
+
+    /// Apply the operations in order, waiting for the proxy to finish each
+    /// resource operation before going on with the rest. The inverses of the
+    /// applied resource operations are added to `undo`, which is pushed to
+    /// the file operation undo stack at the end, if given.
+    fn apply_document_change_operations(
+        &self,
+        mut ops: VecDeque<DocumentChangeOperation>,
+        mut undo: Option<Vec<ResourceOp>>,
+    ) {
+        while let Some(op) = ops.pop_front() {
+            match op {
+                DocumentChangeOperation::Edit(edit) => {
+                    let edits = edit
+                        .edits
+                        .into_iter()
+                        .map(|e| match e {
+                            OneOf::Left(e) => e,
+                            OneOf::Right(e) => e.text_edit,
+                        })
+                        .collect();
+                    self.apply_text_edits(&edit.text_document.uri, edits);
+                }
+                DocumentChangeOperation::Op(op) => {
+                    let main_split = self.clone();
+                    self.apply_resource_op(op, move |result| match result {
+                        Ok(inverse) => {
+                            if let (Some(undo), Some(inverse)) =
+                                (undo.as_mut(), inverse)
+                            {
+                                undo.push(inverse);
+                            }
+                            main_split.apply_document_change_operations(ops, undo);
+                        }
+                        Err(err) => {
+                            event!(
+                                Level::ERROR,
+                                "Failed to apply workspace edit: {}",
+                                err
+                            );
+                            main_split.push_file_operation_undo(undo);
+                        }
+                    });
+                    return;
+                }
+            }
+        }
+        self.push_file_operation_undo(undo);
+    }
+
+    fn push_file_operation_undo(&self, undo: Option<Vec<ResourceOp>>) {
+        if let Some(undo) = undo.filter(|undo| !undo.is_empty()) {
+            self.file_operation_undo.update(|stack| stack.push(undo));
+        }
+    }
+
+    /// Revert the file creations, renames and deletions of the last workspace
+    /// edit that had any, the deleted files being restored from the backups
+    /// the proxy kept of them.
+    pub fn undo_workspace_file_operations(&self) {
+        let Some(undo) = self
+            .file_operation_undo
+            .try_update(|stack| stack.pop())
+            .flatten()
+        else {
+            return;
+        };
+        let ops = undo
+            .into_iter()
+            .rev()
+            .map(DocumentChangeOperation::Op)
+            .collect();
+        self.apply_document_change_operations(ops, None);
+    }
+
+    /// Apply a resource operation through the proxy, and call `f` with its
+    /// inverse, or `None` when it was skipped or can't be inverted.
+    fn apply_resource_op(
+        &self,
+        op: ResourceOp,
+        f: impl FnOnce(Result<Option<ResourceOp>, String>) + 'static,
+    ) {
+        let proxy = &self.common.proxy;
+        match op {
+            ResourceOp::Create(create) => {
+                let Ok(path) = create.uri.to_file_path() else {
+                    f(Err(format!("{} is not a file", create.uri)));
+                    return;
+                };
+                let ignore_if_exists = create
+                    .options
+                    .as_ref()
+                    .and_then(|options| options.ignore_if_exists)
+                    .unwrap_or(false);
+                let send = create_ext_action(
+                    self.scope,
+                    move |result: Result<ProxyResponse, RpcError>| match result {
+                        Ok(_) => f(Ok(Some(ResourceOp::Delete(DeleteFile {
+                            uri: create.uri,
+                            options: None,
+                        })))),
+                        Err(_) if ignore_if_exists => f(Ok(None)),
+                        Err(err) => f(Err(err.message)),
+                    },
+                );
+                proxy.create_file(path, send);
+            }
+            ResourceOp::Rename(rename) => {
+                let (Ok(from), Ok(to)) =
+                    (rename.old_uri.to_file_path(), rename.new_uri.to_file_path())
+                else {
+                    f(Err(format!(
+                        "{} or {} is not a file",
+                        rename.old_uri, rename.new_uri
+                    )));
+                    return;
+                };
+                let ignore_if_exists = rename
+                    .options
+                    .as_ref()
+                    .and_then(|options| options.ignore_if_exists)
+                    .unwrap_or(false);
+                let main_split = self.clone();
+                let send = create_ext_action(self.scope, {
+                    let (from, to) = (from.clone(), to.clone());
+                    move |result: Result<ProxyResponse, RpcError>| match result {
+                        Ok(response) => {
+                            let to =
+                                if let ProxyResponse::CreatePathResponse { path } =
+                                    response
+                                {
+                                    path
+                                } else {
+                                    to
+                                };
+                            main_split.update_renamed_path(&from, &to);
+                            f(Ok(Some(ResourceOp::Rename(RenameFile {
+                                old_uri: rename.new_uri,
+                                new_uri: rename.old_uri,
+                                options: None,
+                                annotation_id: None,
+                            }))))
+                        }
+                        Err(_) if ignore_if_exists => f(Ok(None)),
+                        Err(err) => f(Err(err.message)),
+                    }
+                });
+                proxy.rename_path(from, to, send);
+            }
+            ResourceOp::Delete(delete) => {
+                let Ok(path) = delete.uri.to_file_path() else {
+                    f(Err(format!("{} is not a file", delete.uri)));
+                    return;
+                };
+                let ignore_if_not_exists = delete
+                    .options
+                    .as_ref()
+                    .and_then(|options| options.ignore_if_not_exists)
+                    .unwrap_or(false);
+                if ignore_if_not_exists && !path.exists() {
+                    f(Ok(None));
+                    return;
                 }
+                // The inverse restores the backup the proxy keeps of it.
+                let send = create_ext_action(
+                    self.scope,
+                    move |result: Result<ProxyResponse, RpcError>| match result {
+                        Ok(ProxyResponse::CreatePathResponse { path }) => {
+                            match Url::from_file_path(&path) {
+                                Ok(backup) => {
+                                    f(Ok(Some(ResourceOp::Rename(RenameFile {
+                                        old_uri: backup,
+                                        new_uri: delete.uri,
+                                        options: None,
+                                        annotation_id: None,
+                                    }))))
+                                }
+                                Err(_) => f(Ok(None)),
+                            }
+                        }
+                        Ok(_) => f(Ok(None)),
+                        Err(err) => f(Err(err.message)),
+                    },
+                );
+                proxy.trash_path_with_backup(path, send);
             }
         }
     }
 
+    /// Point the docs of the files at or under `from` to their paths under
+    /// `to`, after it was renamed.
+    pub fn update_renamed_path(&self, from: &Path, to: &Path) {
+        let renamed_docs: Vec<_> = self.docs.with_untracked(|docs| {
+            docs.iter()
+                .filter(|(path, _)| path.starts_with(from))
+                .map(|(path, doc)| (path.clone(), doc.clone()))
+                .collect()
+        });
+        for (path, doc) in renamed_docs {
+            let Ok(suffix) = path.strip_prefix(from) else {
+                continue;
+            };
+            let new_path = to.join(suffix);
+            doc.content.update(|content| {
+                if let DocContent::File { path, .. } = content {
+                    *path = new_path.clone();
+                }
+            });
+            self.docs.update(|docs| {
+                docs.remove(&path);
+                docs.insert(new_path, doc);
+            });
+        }
+    }
+
     pub fn next_error(&self) {
         let file_diagnostics =
             self.file_diagnostics_items(DiagnosticSeverity::ERROR);
@@ -2287,7 +3031,8 @@ pub fn next_error(&self) {
         self.jump_to_location(location, None);
     }
 
-    fn file_diagnostics_items(
+    /// The diagnostics of the given severity, grouped by file and sorted by path.
+    pub fn file_diagnostics_items(
         &self,
         severity: DiagnosticSeverity,
     ) -> Vec<(PathBuf, Vec<EditorDiagnostic>)> {
@@ -2300,7 +3045,7 @@ fn file_diagnostics_items(
                     let diags = span
                         .iter()
                         .filter_map(|(iv, diag)| {
-                            if diag.severity == Some(severity) {
+                            if problem_severity(diag.severity) == Some(severity) {
                                 Some(EditorDiagnostic {
                                     range: Some((iv.start, iv.end)),
                                     diagnostic: diag.to_owned(),
@@ -2319,7 +3064,7 @@ fn file_diagnostics_items(
                     let diagnostics = diagnostic.diagnostics.get_untracked();
                     let diagnostics: Vec<EditorDiagnostic> = diagnostics
                         .into_iter()
-                        .filter(|d| d.severity == Some(severity))
+                        .filter(|d| problem_severity(d.severity) == Some(severity))
                         .map(|d| EditorDiagnostic {
                             range: None,
                             diagnostic: d,
@@ -2346,6 +3091,7 @@ pub fn get_diagnostic_data(&self, path: &Path) -> DiagnosticData {
                 diagnostics_span: self
                     .scope
                     .create_rw_signal(SpansBuilder::new(0).build()),
+                source_diagnostics: self.scope.create_rw_signal(im::HashMap::new()),
             };
             self.diagnostics.update(|d| {
                 d.insert(path.to_path_buf(), diagnostic_data.clone());
@@ -2397,6 +3143,70 @@ pub fn open_file_changed(&self, path: &Path, content: &FileChanged) {
         }
     }
 
+    /// Discard the unsaved changes of the document at `path`, reloading it from
+    /// disk.
+    pub fn revert_file(&self, path: PathBuf) {
+        let Some(doc) = self.docs.with_untracked(|docs| docs.get(&path).cloned())
+        else {
+            return;
+        };
+        let notifications = self.common.notifications.clone();
+        doc.reopen_with_encoding(doc.encoding.get_untracked(), move |err| {
+            notifications.notify(
+                "Revert Failed",
+                &ShowMessageParams {
+                    typ: MessageType::ERROR,
+                    message: format!("{}: {}", path.display(), err.message),
+                },
+            );
+        });
+    }
+
+    /// Show the message and the patch of the commit `rev` resolves to in a
+    /// read only editor.
+    pub fn open_commit(&self, rev: String) {
+        let main_split = self.clone();
+        let shown_rev = rev.clone();
+        let send = create_ext_action(self.scope, move |result| match result {
+            Ok(ProxyResponse::GitShowCommitResponse { id, content }) => {
+                // The extension gives the content diff highlighting.
+                let path =
+                    PathBuf::from(format!("{}.diff", id.get(..7).unwrap_or(&id)));
+                let doc = Doc::new_history(
+                    main_split.scope,
+                    DocContent::History(DocHistory {
+                        path: path.clone(),
+                        version: id,
+                    }),
+                    main_split.editors,
+                    main_split.common.clone(),
+                );
+                doc.init_content(Rope::from(content));
+                main_split.get_editor_tab_child(
+                    EditorTabChildSource::Editor {
+                        path,
+                        doc: Rc::new(doc),
+                    },
+                    false,
+                    false,
+                );
+            }
+            Ok(_) => {}
+            Err(err) => {
+                main_split.common.notifications.notify(
+                    "Show Commit Failed",
+                    &ShowMessageParams {
+                        typ: MessageType::ERROR,
+                        message: format!("{shown_rev}: {}", err.message),
+                    },
+                );
+            }
+        });
+        self.common.proxy.git_show_commit(rev, move |result| {
+            send(result);
+        });
+    }
+
     pub fn set_find_pattern(&self, pattern: Option<String>) {
         if let Some(pattern) = pattern {
             self.find_editor.doc().reload(Rope::from(pattern), true);
@@ -2435,6 +3245,26 @@ pub fn new_file(&self) -> EditorTabChild {
         self.get_editor_tab_child(EditorTabChildSource::NewFileEditor, false, false)
     }
 
+    /// Create the file at `path` with the expanded template, leaving the cursor
+    /// at the first tabstop of the template.
+    pub fn new_file_from_template(&self, path: PathBuf, template: &FileTemplate) {
+        let EditorTabChild::Editor(editor_id) = self.new_file() else {
+            return;
+        };
+        let Some(editor) = self.editors.editor_untracked(editor_id) else {
+            return;
+        };
+        if let Err(err) = editor.completion_apply_snippet(
+            &template.expand(&path),
+            &Selection::caret(0),
+            Vec::new(),
+            0,
+        ) {
+            event!(Level::ERROR, "Failed to apply file template: {:?}", err);
+        }
+        self.save_as(editor.doc(), path, || {});
+    }
+
     pub fn save_as(&self, doc: Rc<Doc>, path: PathBuf, action: impl Fn() + 'static) {
         let (buffer_id, doc_content, rev, content) = (
             doc.buffer_id,
@@ -2657,17 +3487,21 @@ pub fn move_editor_tab_child(
                 .editor_tabs
                 .with_untracked(|editor_tabs| editor_tabs.get(&to_tab).cloned())?;
 
-            let (_, _, child) = from_editor_tab
+            let ((_, _, child), is_pinned) = from_editor_tab
                 .try_update(|tab| {
                     let child = tab.children.remove(from_index);
+                    let is_pinned = tab.pinned.remove(&child.2).is_some();
                     tab.active =
                         tab.active.min(tab.children.len().saturating_sub(1));
-                    child
+                    (child, is_pinned)
                 })
                 .unwrap();
 
             self.editor_tab_child_set_parent(&child, to_tab);
             to_editor_tab.update(|tab| {
+                if is_pinned {
+                    tab.pinned.insert(child.clone());
+                }
                 tab.children.insert(
                     to_index,
                     (
@@ -2743,6 +3577,8 @@ fn editor_tab_child_set_parent(
             EditorTabChild::ThemeColorSettings(_) => {}
             EditorTabChild::Keymap(_) => {}
             EditorTabChild::Volt(_, _) => {}
+            EditorTabChild::Image(_, _) => {}
+            EditorTabChild::MarkdownPreview(_, _) => {}
         }
         Some(())
     }
@@ -2760,6 +3596,13 @@ pub fn move_editor_tab_child_to_new_split(
         let to_editor_tab = self
             .editor_tabs
             .with_untracked(|editor_tabs| editor_tabs.get(&to_tab).cloned())?;
+        if from_tab == to_tab
+            && from_editor_tab.with_untracked(|tab| tab.children.len() <= 1)
+        {
+            // Splitting the only child off its own editor tab would leave
+            // the layout as it was.
+            return Some(());
+        }
         let to_split_id =
             to_editor_tab.with_untracked(|editor_tab| editor_tab.split);
         let to_split = self
@@ -2791,17 +3634,22 @@ pub fn move_editor_tab_child_to_new_split(
             };
             let new_editor_tab_id = EditorTabId::next();
 
-            let (_, _, child) = from_editor_tab
+            let ((_, _, child), is_pinned) = from_editor_tab
                 .try_update(|tab| {
                     let child = tab.children.remove(from_index);
+                    let is_pinned = tab.pinned.remove(&child.2).is_some();
                     tab.active =
                         tab.active.min(tab.children.len().saturating_sub(1));
-                    child
+                    (child, is_pinned)
                 })
                 .unwrap();
 
             self.editor_tab_child_set_parent(&child, new_editor_tab_id);
 
+            let mut pinned = im::HashSet::new();
+            if is_pinned {
+                pinned.insert(child.clone());
+            }
             let cx = self.scope.create_child();
             let new_editor_tab = EditorTabData {
                 scope: cx,
@@ -2813,6 +3661,7 @@ pub fn move_editor_tab_child_to_new_split(
                     cx.create_rw_signal(Rect::ZERO),
                     child,
                 )],
+                pinned,
                 window_origin: Point::ZERO,
                 layout_rect: Rect::ZERO,
                 locations: cx.create_rw_signal(im::Vector::new()),
@@ -2859,16 +3708,21 @@ pub fn move_editor_tab_child_to_new_split(
 
             let new_editor_tab_id = EditorTabId::next();
 
-            let (_, _, child) = from_editor_tab
+            let ((_, _, child), is_pinned) = from_editor_tab
                 .try_update(|tab| {
                     let child = tab.children.remove(from_index);
+                    let is_pinned = tab.pinned.remove(&child.2).is_some();
                     tab.active =
                         tab.active.min(tab.children.len().saturating_sub(1));
-                    child
+                    (child, is_pinned)
                 })
                 .unwrap();
             self.editor_tab_child_set_parent(&child, new_editor_tab_id);
 
+            let mut pinned = im::HashSet::new();
+            if is_pinned {
+                pinned.insert(child.clone());
+            }
             let new_editor_tab = {
                 let cx = self.scope.create_child();
                 EditorTabData {
@@ -2881,6 +3735,7 @@ pub fn move_editor_tab_child_to_new_split(
                         cx.create_rw_signal(Rect::ZERO),
                         child,
                     )],
+                    pinned,
                     window_origin: Point::ZERO,
                     layout_rect: Rect::ZERO,
                     locations: cx.create_rw_signal(im::Vector::new()),
@@ -2992,7 +3847,7 @@ pub fn get_active_editor(&self) -> Option<EditorData> {
     }
 }
 
-fn workspace_edits(edit: &WorkspaceEdit) -> Option<HashMap<Url, Vec<TextEdit>>> {
+pub fn workspace_edits(edit: &WorkspaceEdit) -> Option<HashMap<Url, Vec<TextEdit>>> {
     if let Some(changes) = edit.changes.as_ref() {
         return Some(changes.clone());
     }
@@ -1,25 +1,100 @@
+use std::ops::Range;
+
 use floem::text::{
     Attrs, AttrsList, FamilyOwned, LineHeightValue, Style, TextLayout, Weight,
 };
 use lapce_core::{language::LapceLanguage, syntax::Syntax};
 use lapce_xi_rope::Rope;
 use lsp_types::MarkedString;
-use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag};
+use pulldown_cmark::{
+    BrokenLink, CodeBlockKind, CowStr, Event, Options, Parser, Tag,
+};
 use smallvec::SmallVec;
 
 use crate::config::{color::LapceColor, LapceConfig};
 
+/// A link within the text of a [`MarkdownContent::Text`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkdownLink {
+    pub range: Range<usize>,
+    pub url: String,
+}
+
 #[derive(Clone)]
 pub enum MarkdownContent {
-    Text(TextLayout),
+    Text(TextLayout, Vec<MarkdownLink>),
     Image { url: String, title: String },
     Separator,
 }
 
+/// What a part of a markdown document refers to in the rest of it, for the
+/// part to be rendered on its own.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MarkdownReferences {
+    /// The label, the destination and the title of the link reference
+    /// definitions used.
+    pub links: Vec<(String, String, String)>,
+    /// The label and the number of the footnotes referred to or defined.
+    pub footnotes: Vec<(String, usize)>,
+}
+
+impl MarkdownReferences {
+    fn link(&self, label: &str) -> Option<(&str, &str)> {
+        self.links
+            .iter()
+            .find(|(link, _, _)| same_label(link, label))
+            .map(|(_, url, title)| (url.as_str(), title.as_str()))
+    }
+
+    fn footnote_marker(&self, label: &str) -> String {
+        match self
+            .footnotes
+            .iter()
+            .find(|(note, _)| same_label(note, label))
+        {
+            Some((_, number)) => format!("[{number}]"),
+            None => format!("[^{label}]"),
+        }
+    }
+}
+
+/// Whether two labels of link references match, which ignores the case and
+/// how they're spaced.
+fn same_label(a: &str, b: &str) -> bool {
+    a.split_whitespace()
+        .map(str::to_lowercase)
+        .eq(b.split_whitespace().map(str::to_lowercase))
+}
+
+/// The extensions of CommonMark that are rendered.
+pub fn markdown_options() -> Options {
+    Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+}
+
 pub fn parse_markdown(
     text: &str,
     line_height: f64,
     config: &LapceConfig,
+) -> Vec<MarkdownContent> {
+    parse_markdown_with_references(
+        text,
+        &MarkdownReferences::default(),
+        line_height,
+        config,
+    )
+}
+
+/// Parse a part of a document, with the link reference definitions and the
+/// footnotes of the rest of it in `references`.
+pub fn parse_markdown_with_references(
+    text: &str,
+    references: &MarkdownReferences,
+    line_height: f64,
+    config: &LapceConfig,
 ) -> Vec<MarkdownContent> {
     let mut res = Vec::new();
 
@@ -38,22 +113,38 @@ pub fn parse_markdown(
     let mut pos = 0;
 
     let mut tag_stack: SmallVec<[(usize, Tag); 4]> = SmallVec::new();
+    let mut links = Vec::new();
+    // The number of the next item of each list being parsed, or `None` for
+    // bulleted lists.
+    let mut lists: SmallVec<[Option<u64>; 4]> = SmallVec::new();
+    // The cells of the table being parsed, by row, which are laid out once the
+    // widths of the columns are known.
+    let mut table: Option<Vec<Vec<String>>> = None;
 
-    let parser = Parser::new_ext(
+    let mut options = markdown_options();
+    if !references.footnotes.is_empty() {
+        // The footnotes referred to are defined in other parts, which only the
+        // older syntax allows.
+        options |= Options::ENABLE_OLD_FOOTNOTES;
+    }
+    let mut resolve_link = |link: BrokenLink| {
+        references
+            .link(&link.reference)
+            .map(|(url, title)| (url.to_string().into(), title.to_string().into()))
+    };
+    let parser = Parser::new_with_broken_link_callback(
         text,
-        Options::ENABLE_TABLES
-            | Options::ENABLE_FOOTNOTES
-            | Options::ENABLE_STRIKETHROUGH
-            | Options::ENABLE_TASKLISTS
-            | Options::ENABLE_HEADING_ATTRIBUTES,
+        options,
+        Some(&mut resolve_link),
     );
     let mut last_text = CowStr::from("");
     // Whether we should add a newline on the next entry
     // This is used so that we don't emit newlines at the very end of the generation
     let mut add_newline = false;
     for event in parser {
-        // Add the newline since we're going to be outputting more
-        if add_newline {
+        // Add the newline since we're going to be outputting more, unless the
+        // text ends here for a separator
+        if add_newline && !matches!(event, Event::Rule) {
             current_text.push('\n');
             builder_dirty = true;
             pos += 1;
@@ -62,6 +153,54 @@ pub fn parse_markdown(
 
         match event {
             Event::Start(tag) => {
+                match &tag {
+                    Tag::Table(_) => table = Some(Vec::new()),
+                    Tag::TableHead | Tag::TableRow => {
+                        if let Some(rows) = table.as_mut() {
+                            rows.push(Vec::new());
+                        }
+                    }
+                    Tag::TableCell => {
+                        if let Some(row) = table.as_mut().and_then(|t| t.last_mut())
+                        {
+                            row.push(String::new());
+                        }
+                    }
+                    Tag::List(start) => lists.push(*start),
+                    Tag::FootnoteDefinition(label) => {
+                        let marker =
+                            format!("{} ", references.footnote_marker(label));
+                        attr_list.add_span(
+                            pos..pos + marker.len(),
+                            default_attrs
+                                .color(config.color(LapceColor::EDITOR_LINK)),
+                        );
+                        current_text.push_str(&marker);
+                        pos += marker.len();
+                        builder_dirty = true;
+                    }
+                    Tag::Item => {
+                        if !current_text.is_empty() && !current_text.ends_with('\n')
+                        {
+                            current_text.push('\n');
+                            pos += 1;
+                        }
+                        let depth = lists.len().saturating_sub(1);
+                        let marker = match lists.last_mut() {
+                            Some(Some(number)) => {
+                                let marker =
+                                    format!("{}{number}. ", "    ".repeat(depth));
+                                *number += 1;
+                                marker
+                            }
+                            _ => format!("{}• ", "    ".repeat(depth)),
+                        };
+                        current_text.push_str(&marker);
+                        pos += marker.len();
+                        builder_dirty = true;
+                    }
+                    _ => {}
+                }
                 tag_stack.push((pos, tag));
             }
             Event::End(end_tag) => {
@@ -86,6 +225,32 @@ pub fn parse_markdown(
                     }
 
                     match &tag {
+                        Tag::Table(_) => {
+                            let rows = table.take().unwrap_or_default();
+                            let (text, header) = format_table(&rows);
+                            attr_list.add_span(
+                                pos..pos + text.len(),
+                                default_attrs.family(&code_font_family),
+                            );
+                            attr_list.add_span(
+                                pos..pos + header,
+                                default_attrs
+                                    .family(&code_font_family)
+                                    .weight(Weight::BOLD),
+                            );
+                            current_text.push_str(&text);
+                            pos += text.len();
+                            builder_dirty = true;
+                        }
+                        Tag::List(_) => {
+                            lists.pop();
+                        }
+                        Tag::Link { dest_url, .. } => {
+                            links.push(MarkdownLink {
+                                range: start_offset..pos,
+                                url: dest_url.to_string(),
+                            });
+                        }
                         Tag::CodeBlock(kind) => {
                             let language =
                                 if let CodeBlockKind::Fenced(language) = kind {
@@ -116,7 +281,10 @@ pub fn parse_markdown(
                             if builder_dirty {
                                 let mut text_layout = TextLayout::new();
                                 text_layout.set_text(&current_text, attr_list);
-                                res.push(MarkdownContent::Text(text_layout));
+                                res.push(MarkdownContent::Text(
+                                    text_layout,
+                                    std::mem::take(&mut links),
+                                ));
                                 attr_list = AttrsList::new(default_attrs);
                                 current_text.clear();
                                 pos = 0;
@@ -137,6 +305,15 @@ pub fn parse_markdown(
                     tracing::warn!("Unbalanced markdown tag")
                 }
             }
+            Event::Text(text) | Event::Code(text) if table.is_some() => {
+                if let Some(cell) = table
+                    .as_mut()
+                    .and_then(|t| t.last_mut())
+                    .and_then(|row| row.last_mut())
+                {
+                    cell.push_str(&text);
+                }
+            }
             Event::Text(text) => {
                 if let Some((_, tag)) = tag_stack.last() {
                     if should_skip_text_in_tag(tag) {
@@ -180,9 +357,38 @@ pub fn parse_markdown(
                 pos += 1;
                 builder_dirty = true;
             }
-            Event::Rule => {}
-            Event::FootnoteReference(_text) => {}
-            Event::TaskListMarker(_text) => {}
+            Event::Rule => {
+                if builder_dirty {
+                    let mut text_layout = TextLayout::new();
+                    text_layout.set_text(&current_text, attr_list);
+                    res.push(MarkdownContent::Text(
+                        text_layout,
+                        std::mem::take(&mut links),
+                    ));
+                    attr_list = AttrsList::new(default_attrs);
+                    current_text.clear();
+                    pos = 0;
+                    builder_dirty = false;
+                }
+                res.push(MarkdownContent::Separator);
+                add_newline = false;
+            }
+            Event::FootnoteReference(label) => {
+                let marker = references.footnote_marker(&label);
+                attr_list.add_span(
+                    pos..pos + marker.len(),
+                    default_attrs.color(config.color(LapceColor::EDITOR_LINK)),
+                );
+                current_text.push_str(&marker);
+                pos += marker.len();
+                builder_dirty = true;
+            }
+            Event::TaskListMarker(checked) => {
+                let marker = if checked { "[x] " } else { "[ ] " };
+                current_text.push_str(marker);
+                pos += marker.len();
+                builder_dirty = true;
+            }
             Event::InlineHtml(_) => {} // TODO(panekj): Implement
             Event::InlineMath(_) => {} // TODO(panekj): Implement
             Event::DisplayMath(_) => {} // TODO(panekj): Implement
@@ -192,7 +398,7 @@ pub fn parse_markdown(
     if builder_dirty {
         let mut text_layout = TextLayout::new();
         text_layout.set_text(&current_text, attr_list);
-        res.push(MarkdownContent::Text(text_layout));
+        res.push(MarkdownContent::Text(text_layout, links));
     }
 
     res
@@ -256,10 +462,75 @@ fn attribute_for_tag<'a>(
 fn should_add_newline_after_tag(tag: &Tag) -> bool {
     !matches!(
         tag,
-        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. }
+        Tag::Emphasis
+            | Tag::Strong
+            | Tag::Strikethrough
+            | Tag::Link { .. }
+            | Tag::TableHead
+            | Tag::TableRow
+            | Tag::TableCell
     )
 }
 
+/// Lay out the cells of a table in aligned columns, with a rule under the
+/// header row, returning the text and the length of the header row.
+fn format_table(rows: &[Vec<String>]) -> (String, usize) {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.trim().chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut text = String::new();
+    let mut header = 0;
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            text.push('\n');
+        }
+        let line: Vec<String> = widths
+            .iter()
+            .enumerate()
+            .map(|(column, width)| {
+                let cell = row.get(column).map(|c| c.trim()).unwrap_or("");
+                format!("{cell:<width$}")
+            })
+            .collect();
+        text.push_str(line.join(" │ ").trim_end());
+        if i == 0 {
+            header = text.len();
+            let rule: Vec<String> =
+                widths.iter().map(|width| "─".repeat(*width)).collect();
+            text.push('\n');
+            text.push_str(&rule.join("─┼─"));
+        }
+    }
+    (text, header)
+}
+
+/// The url of the link at `index` in the text of a [`MarkdownContent::Text`].
+pub fn link_at(links: &[MarkdownLink], index: usize) -> Option<&str> {
+    links
+        .iter()
+        .find(|link| link.range.contains(&index))
+        .map(|link| link.url.as_str())
+}
+
+/// The schemes of the link targets that are opened in the browser.
+pub const WEB_LINK_SCHEMES: &[&str] = &["http", "https"];
+
+/// Whether `target` parses as a url with one of `schemes`. Link targets come
+/// from documents and language servers, and other schemes can open local
+/// files or launch the handlers of other applications, so only these are
+/// handed to the OS to open.
+pub fn is_external_link(target: &str, schemes: &[&str]) -> bool {
+    url::Url::parse(target).is_ok_and(|url| schemes.contains(&url.scheme()))
+}
+
 /// Whether it should skip the text node after a specific tag  
 /// For example, images are skipped because it emits their title as a separate text node.  
 fn should_skip_text_in_tag(tag: &Tag) -> bool {
@@ -338,5 +609,22 @@ pub fn from_plaintext(
                 .line_height(LineHeightValue::Normal(line_height as f32)),
         ),
     );
-    vec![MarkdownContent::Text(text_layout)]
+    vec![MarkdownContent::Text(text_layout, Vec::new())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_external_link, WEB_LINK_SCHEMES};
+
+    #[test]
+    fn test_is_external_link() {
+        assert!(is_external_link("https://lapce.dev", WEB_LINK_SCHEMES));
+        assert!(is_external_link("HTTP://lapce.dev/docs", WEB_LINK_SCHEMES));
+        assert!(!is_external_link("file:///etc/passwd", WEB_LINK_SCHEMES));
+        assert!(!is_external_link("javascript:alert(1)", WEB_LINK_SCHEMES));
+        assert!(!is_external_link("vscode://open", WEB_LINK_SCHEMES));
+        assert!(!is_external_link("mailto:a@b.c", WEB_LINK_SCHEMES));
+        assert!(!is_external_link("docs/README.md", WEB_LINK_SCHEMES));
+        assert!(is_external_link("mailto:a@b.c", &["mailto"]));
+    }
 }
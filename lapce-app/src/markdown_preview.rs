@@ -0,0 +1,485 @@
+//! The rendered preview of a markdown file shown beside its editor, which
+//! follows the edits and the scrolling of the editor.
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicU64, Arc},
+};
+
+use floem::{
+    ext_event::create_ext_action,
+    kurbo::{Point, Rect},
+    reactive::{
+        create_effect, create_memo, create_rw_signal, Scope, SignalGet,
+        SignalUpdate, SignalWith,
+    },
+    views::{
+        container, dyn_container, dyn_stack, empty, img, label, rich_text, scroll,
+        svg, Decorators,
+    },
+    IntoView, View,
+};
+use pulldown_cmark::{Event, LinkType, Parser, Tag};
+
+use crate::{
+    command::InternalCommand,
+    config::color::LapceColor,
+    doc::DocContent,
+    image_viewer::is_image,
+    listener::Listener,
+    main_split::MainSplitData,
+    markdown::{
+        self, markdown_options, parse_markdown_with_references, MarkdownContent,
+        MarkdownReferences,
+    },
+};
+
+/// A part of a markdown document that is rendered on its own, so that an edit
+/// only re-renders the block it's in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkdownBlock {
+    pub lines: Range<usize>,
+    pub text: String,
+    /// The link reference definitions and the footnotes of the rest of the
+    /// document the block uses.
+    pub references: MarkdownReferences,
+}
+
+/// The key of a block, which is its text and references with the number of
+/// identical blocks before it, and stays the same when other blocks are
+/// edited.
+type BlockKey = (String, MarkdownReferences, usize);
+
+/// Split `text` into its top-level blocks, parsing the whole document once so
+/// that the links to reference definitions and the footnotes resolve when a
+/// block is rendered on its own.
+pub fn markdown_blocks(text: &str) -> Vec<MarkdownBlock> {
+    let mut blocks = Vec::new();
+    // The footnotes in the order of their first reference.
+    let mut footnotes: Vec<String> = Vec::new();
+    let mut defined_footnotes: Vec<String> = Vec::new();
+    // The start of the block being read with the references found in it.
+    let mut current: Option<(usize, MarkdownReferences)> = None;
+    let mut depth = 0;
+    let mut line = 0;
+    let mut line_offset = 0;
+
+    for (event, range) in
+        Parser::new_ext(text, markdown_options()).into_offset_iter()
+    {
+        let (_, references) =
+            current.get_or_insert_with(|| (range.start, Default::default()));
+        match &event {
+            Event::Start(tag) => {
+                depth += 1;
+                match tag {
+                    Tag::Link {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    }
+                    | Tag::Image {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    } if matches!(
+                        link_type,
+                        LinkType::Reference
+                            | LinkType::Collapsed
+                            | LinkType::Shortcut
+                    ) =>
+                    {
+                        let link = (
+                            id.to_string(),
+                            dest_url.to_string(),
+                            title.to_string(),
+                        );
+                        if !references.links.contains(&link) {
+                            references.links.push(link);
+                        }
+                    }
+                    Tag::FootnoteDefinition(label) => {
+                        add_footnote(&mut references.footnotes, label);
+                        defined_footnotes.push(label.to_string());
+                    }
+                    _ => {}
+                }
+            }
+            Event::End(_) => depth -= 1,
+            Event::FootnoteReference(label) => {
+                add_footnote(&mut references.footnotes, label);
+                if !footnotes.contains(&label.to_string()) {
+                    footnotes.push(label.to_string());
+                }
+            }
+            _ => {}
+        }
+
+        if depth == 0 {
+            let Some((start, references)) = current.take() else {
+                continue;
+            };
+            let block = text[start..range.end].trim_end();
+            let mut line_of = |offset: usize| {
+                line += text[line_offset..offset].matches('\n').count();
+                line_offset = offset;
+                line
+            };
+            let lines = line_of(start)..line_of(start + block.len()) + 1;
+            blocks.push(MarkdownBlock {
+                lines,
+                text: block.to_string(),
+                references,
+            });
+        }
+    }
+
+    // The footnotes are numbered in the order they're referred to, followed
+    // by the ones which are only defined.
+    for label in defined_footnotes {
+        if !footnotes.contains(&label) {
+            footnotes.push(label);
+        }
+    }
+    for block in &mut blocks {
+        for (label, number) in &mut block.references.footnotes {
+            *number = footnotes.iter().position(|l| l == label).unwrap_or(0) + 1;
+        }
+    }
+    blocks
+}
+
+/// Add the footnote with `label` to the ones of a block, to be numbered once
+/// the whole document is read.
+fn add_footnote(footnotes: &mut Vec<(String, usize)>, label: &str) {
+    if !footnotes.iter().any(|(l, _)| l == label) {
+        footnotes.push((label.to_string(), 0));
+    }
+}
+
+/// The blocks of `text` by their key.
+fn keyed_blocks(text: &str) -> Vec<(BlockKey, MarkdownBlock)> {
+    let mut seen: HashMap<(String, MarkdownReferences), usize> = HashMap::new();
+    markdown_blocks(text)
+        .into_iter()
+        .map(|block| {
+            let count = seen
+                .entry((block.text.clone(), block.references.clone()))
+                .or_default();
+            let key = (block.text.clone(), block.references.clone(), *count);
+            *count += 1;
+            (key, block)
+        })
+        .collect()
+}
+
+pub fn markdown_preview_view(main_split: MainSplitData, path: PathBuf) -> impl View {
+    let config = main_split.common.config;
+    let internal_command = main_split.common.internal_command;
+    let (doc, _) = main_split.get_doc(path.clone(), None);
+    let buffer = doc.buffer;
+    let blocks = create_memo(move |_| {
+        buffer.with(|buffer| keyed_blocks(&buffer.text().to_string()))
+    });
+    // Where each block is laid out in the preview.
+    let block_rects = create_rw_signal(HashMap::<BlockKey, Rect>::new());
+    // Forget the blocks that were edited or removed.
+    create_effect(move |_| {
+        let keys: HashSet<BlockKey> = blocks
+            .with(|blocks| blocks.iter().map(|(key, _)| key.clone()).collect());
+        block_rects.update(|rects| rects.retain(|key, _| keys.contains(key)));
+    });
+    let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    // Follow the scrolling of the active editor when it shows this file.
+    let active_editor = main_split.active_editor;
+    let scroll_path = path.clone();
+    let scroll_to = move || {
+        let editor = active_editor.get()?;
+        let is_path = editor.doc_signal().get().content.with(|content| {
+            matches!(content, DocContent::File { path, .. } if path == &scroll_path)
+        });
+        if !is_path {
+            return None;
+        }
+        let line_height = config.get_untracked().editor.line_height() as f64;
+        let line = editor.viewport().get().y0 / line_height;
+        if line < 1.0 {
+            return Some(Point::ZERO);
+        }
+        blocks.with_untracked(|blocks| {
+            let (key, block) = blocks
+                .iter()
+                .take_while(|(_, block)| block.lines.start as f64 <= line)
+                .last()?;
+            let rect =
+                block_rects.with_untracked(|rects| rects.get(key).copied())?;
+            let progress = ((line - block.lines.start as f64)
+                / block.lines.len().max(1) as f64)
+                .min(1.0);
+            Some(Point::new(0.0, rect.y0 + rect.height() * progress))
+        })
+    };
+
+    let block_view = move |(key, block): (BlockKey, MarkdownBlock)| {
+        let directory = directory.clone();
+        let id = AtomicU64::new(0);
+        dyn_stack(
+            move || {
+                parse_markdown_with_references(
+                    &block.text,
+                    &block.references,
+                    1.8,
+                    &config.get(),
+                )
+            },
+            move |_| id.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            move |content| match content {
+                MarkdownContent::Text(text_layout, links) => {
+                    let width = create_rw_signal(0.0);
+                    let layout = text_layout.clone();
+                    let directory = directory.clone();
+                    container(
+                        rich_text(move || text_layout.clone())
+                            .on_resize(move |rect| width.set(rect.width()))
+                            .on_click_stop(move |event| {
+                                let Some(point) = event.point() else {
+                                    return;
+                                };
+                                let mut layout = layout.clone();
+                                layout.set_size(
+                                    width.get_untracked() as f32,
+                                    f32::MAX,
+                                );
+                                let hit = layout.hit_point(point);
+                                if let Some(url) = hit
+                                    .is_inside
+                                    .then(|| markdown::link_at(&links, hit.index))
+                                    .flatten()
+                                {
+                                    open_link(&directory, url, internal_command);
+                                }
+                            })
+                            .style(|s| s.width_full()),
+                    )
+                    .style(|s| s.width_full())
+                    .into_any()
+                }
+                MarkdownContent::Image { url, title } => {
+                    image_view(&directory, url, title).into_any()
+                }
+                MarkdownContent::Separator => empty()
+                    .style(move |s| {
+                        s.width_full()
+                            .margin_vert(5.0)
+                            .height(1.0)
+                            .background(config.get().color(LapceColor::LAPCE_BORDER))
+                    })
+                    .into_any(),
+            },
+        )
+        .on_resize(move |rect| {
+            block_rects.update(|rects| {
+                rects.insert(key.clone(), rect);
+            });
+        })
+        .style(|s| s.flex_col().width_full().padding_bottom(10.0))
+    };
+
+    scroll(
+        dyn_stack(move || blocks.get(), |(key, _)| key.clone(), block_view)
+            .style(|s| s.flex_col().width_full().max_width(900.0).padding(20.0)),
+    )
+    .scroll_to(scroll_to)
+    .style(move |s| {
+        s.size_full()
+            .background(config.get().color(LapceColor::EDITOR_BACKGROUND))
+    })
+    .debug_name("Markdown Preview")
+}
+
+/// The schemes of the links of the preview that are handed to the OS to open.
+const PREVIEW_LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Open a relative link of the document in `directory` in an editor, and a
+/// web or mail link with the OS. Links with any other scheme are ignored.
+fn open_link(
+    directory: &Path,
+    url: &str,
+    internal_command: Listener<InternalCommand>,
+) {
+    if let Some(path) = link_path(directory, url) {
+        internal_command.send(InternalCommand::OpenFile { path });
+    } else if markdown::is_external_link(url, PREVIEW_LINK_SCHEMES) {
+        internal_command.send(InternalCommand::OpenWebUri {
+            uri: url.to_string(),
+        });
+    } else {
+        tracing::warn!("Ignored the markdown preview link to {url}");
+    }
+}
+
+/// The file a relative link of the document in `directory` points to,
+/// without the fragment or the query of the link.
+fn link_path(directory: &Path, url: &str) -> Option<PathBuf> {
+    if url::Url::parse(url).is_ok() {
+        return None;
+    }
+    let path = url.split(['#', '?']).next().unwrap_or_default();
+    if path.is_empty() {
+        return None;
+    }
+    Some(directory.join(path))
+}
+
+/// An image of the document, read from the file system relative to the
+/// document on another thread, or its title for the images that aren't local
+/// files and until it's read.
+fn image_view(directory: &Path, url: String, title: String) -> impl View {
+    let path = directory.join(&url);
+    let bytes = create_rw_signal(None);
+    if !url.contains("://") && is_image(&path) {
+        let send =
+            create_ext_action(Scope::current(), move |read: Option<Vec<u8>>| {
+                bytes.set(read.map(Arc::new));
+            });
+        let path = path.clone();
+        std::thread::Builder::new()
+            .name("ReadMarkdownImage".to_owned())
+            .spawn(move || {
+                send(std::fs::read(&path).ok());
+            })
+            .unwrap();
+    }
+    let is_svg = path.extension().is_some_and(|ext| ext == "svg");
+    let text = if title.is_empty() { url } else { title };
+
+    dyn_container(
+        move || bytes.get(),
+        move |bytes: Option<Arc<Vec<u8>>>| match bytes {
+            Some(bytes) if is_svg => {
+                let svg_str = String::from_utf8_lossy(&bytes).into_owned();
+                svg(move || svg_str.clone())
+                    .style(|s| s.max_width_full().height(200.0))
+                    .into_any()
+            }
+            Some(bytes) => img(move || bytes.to_vec())
+                .style(|s| s.max_width_full())
+                .into_any(),
+            None => {
+                let text = text.clone();
+                label(move || format!("[{text}]")).into_any()
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_blocks() {
+        let text =
+            "# Title\n\nSome text\nover two lines\n\n\n- item\n\n  continued\n";
+        let blocks = markdown_blocks(text);
+        assert_eq!(
+            blocks,
+            vec![
+                MarkdownBlock {
+                    lines: 0..1,
+                    text: "# Title".to_string(),
+                    references: MarkdownReferences::default(),
+                },
+                MarkdownBlock {
+                    lines: 2..4,
+                    text: "Some text\nover two lines".to_string(),
+                    references: MarkdownReferences::default(),
+                },
+                MarkdownBlock {
+                    lines: 6..9,
+                    text: "- item\n\n  continued".to_string(),
+                    references: MarkdownReferences::default(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_blocks_code() {
+        let text = "```rust\nfn a() {}\n\nfn b() {}\n```\n\nafter";
+        let blocks = markdown_blocks(text);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].lines, 0..5);
+        assert_eq!(blocks[0].text, "```rust\nfn a() {}\n\nfn b() {}\n```");
+        assert_eq!(blocks[1].lines, 6..7);
+    }
+
+    #[test]
+    fn test_markdown_blocks_references() {
+        let text = "See [the docs] and [this][a].\n\nA note[^b] and[^a].\n\n\
+                    [the docs]: https://lapce.dev \"Docs\"\n\
+                    [a]: https://a\n\n\
+                    [^a]: First.\n\n\
+                    [^b]: Second.\n";
+        let blocks = markdown_blocks(text);
+        // The definitions of the links aren't rendered.
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(
+            blocks[0].references.links,
+            vec![
+                (
+                    "the docs".to_string(),
+                    "https://lapce.dev".to_string(),
+                    "Docs".to_string()
+                ),
+                ("a".to_string(), "https://a".to_string(), String::new()),
+            ]
+        );
+        // The footnotes are numbered in the order they're referred to.
+        assert_eq!(
+            blocks[1].references.footnotes,
+            vec![("b".to_string(), 1), ("a".to_string(), 2)]
+        );
+        assert_eq!(blocks[2].lines, 7..8);
+        assert_eq!(blocks[2].references.footnotes, vec![("a".to_string(), 2)]);
+        assert_eq!(blocks[3].references.footnotes, vec![("b".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_keyed_blocks() {
+        let keys: Vec<_> = keyed_blocks("a\n\nb\n\na\n")
+            .into_iter()
+            .map(|((text, _, count), _)| (text, count))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                ("a".to_string(), 0),
+                ("b".to_string(), 0),
+                ("a".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_path() {
+        let directory = Path::new("/docs");
+        assert_eq!(
+            link_path(directory, "guide/setup.md#install"),
+            Some(PathBuf::from("/docs/guide/setup.md"))
+        );
+        assert_eq!(
+            link_path(directory, "../README.md"),
+            Some(PathBuf::from("/docs/../README.md"))
+        );
+        assert_eq!(link_path(directory, "#heading"), None);
+        assert_eq!(link_path(directory, "https://lapce.dev"), None);
+        assert_eq!(link_path(directory, "file:///etc/passwd"), None);
+        assert_eq!(link_path(directory, "javascript:alert(1)"), None);
+    }
+}
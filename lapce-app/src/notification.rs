@@ -0,0 +1,647 @@
+//! The notification center. The messages of Lapce, of the plugins and of the
+//! language servers are shown as toasts in the corner of the window, and kept
+//! in a history opened from the bell of the status bar. The work reported by
+//! the language servers is listed there too while it's in progress, with a
+//! button cancelling it when the server allows it.
+
+use std::{rc::Rc, sync::Arc, time::Duration};
+
+use floem::{
+    action::exec_after,
+    event::EventListener,
+    reactive::{
+        create_memo, ReadSignal, RwSignal, Scope, SignalGet, SignalUpdate,
+        SignalWith,
+    },
+    style::{CursorStyle, Style},
+    text::Weight,
+    views::{
+        container, dyn_stack,
+        editor::{core::register::Clipboard, text::SystemClipboard},
+        empty, label,
+        scroll::{scroll, PropagatePointerWheel},
+        stack, svg, text, Decorators,
+    },
+    View,
+};
+use lapce_rpc::counter::Counter;
+use lsp_types::{MessageType, ShowMessageParams};
+
+use crate::{
+    app::clickable_icon,
+    config::{color::LapceColor, icon::LapceIcons, LapceConfig},
+};
+
+/// How long the toasts of information and warnings stay shown, the toasts of
+/// errors staying until they're closed.
+const TOAST_TIMEOUT: Duration = Duration::from_secs(8);
+/// How long the toast of a work stays shown once it's finished.
+const PROGRESS_DONE_TIMEOUT: Duration = Duration::from_secs(2);
+/// The number of notifications kept in the history, dropping the oldest ones.
+const HISTORY_LIMIT: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NotificationId(pub u64);
+
+impl NotificationId {
+    pub fn next() -> Self {
+        static NOTIFICATION_ID_COUNTER: Counter = Counter::new();
+        Self(NOTIFICATION_ID_COUNTER.next())
+    }
+}
+
+#[derive(Clone)]
+pub struct NotificationProgress {
+    pub percentage: Option<u32>,
+    pub done: bool,
+    /// Cancels the work, for the work which can be cancelled.
+    pub cancel: Option<Rc<dyn Fn()>>,
+}
+
+#[derive(Clone)]
+pub struct Notification {
+    pub id: NotificationId,
+    pub typ: MessageType,
+    pub title: String,
+    pub message: String,
+    /// The state of the work, for the notifications of work in progress.
+    pub progress: Option<NotificationProgress>,
+}
+
+impl Notification {
+    /// The message of the notification, with how far along its work is.
+    pub fn text(&self) -> String {
+        match self.progress.as_ref() {
+            Some(NotificationProgress {
+                percentage: Some(percentage),
+                done: false,
+                ..
+            }) => {
+                if self.message.is_empty() {
+                    format!("{percentage}%")
+                } else {
+                    format!("{} ({percentage}%)", self.message)
+                }
+            }
+            _ => self.message.clone(),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.progress
+            .as_ref()
+            .is_some_and(|progress| !progress.done)
+    }
+}
+
+#[derive(Clone)]
+pub struct NotificationData {
+    /// Every notification, the most recent last.
+    pub history: RwSignal<im::Vector<Notification>>,
+    /// The notifications shown as toasts.
+    pub toasts: RwSignal<im::HashSet<NotificationId>>,
+    /// The number of messages received since the history was last opened.
+    pub unread: RwSignal<usize>,
+    pub show_history: RwSignal<bool>,
+}
+
+impl NotificationData {
+    pub fn new(cx: Scope) -> Self {
+        Self {
+            history: cx.create_rw_signal(im::Vector::new()),
+            toasts: cx.create_rw_signal(im::HashSet::new()),
+            unread: cx.create_rw_signal(0),
+            show_history: cx.create_rw_signal(false),
+        }
+    }
+
+    /// Show a message, e.g. one sent by a plugin with `window/showMessage`.
+    pub fn notify(
+        &self,
+        title: &str,
+        message: &ShowMessageParams,
+    ) -> NotificationId {
+        let id = NotificationId::next();
+        self.push(
+            Notification {
+                id,
+                typ: message.typ,
+                title: title.to_string(),
+                message: message.message.clone(),
+                progress: None,
+            },
+            true,
+        );
+        if !self.show_history.get_untracked() {
+            self.unread.update(|unread| *unread += 1);
+        }
+        if message.typ != MessageType::ERROR {
+            let data = self.clone();
+            exec_after(TOAST_TIMEOUT, move |_| data.dismiss(id));
+        }
+        id
+    }
+
+    /// Show a work in progress until [`NotificationData::end_progress`]. The
+    /// work which can be cancelled gets a toast with its cancel button, the
+    /// rest being shown in the status bar.
+    pub fn start_progress(
+        &self,
+        title: String,
+        message: Option<String>,
+        percentage: Option<u32>,
+        cancel: Option<Rc<dyn Fn()>>,
+    ) -> NotificationId {
+        let id = NotificationId::next();
+        let toast = cancel.is_some();
+        self.push(
+            Notification {
+                id,
+                typ: MessageType::INFO,
+                title,
+                message: message.unwrap_or_default(),
+                progress: Some(NotificationProgress {
+                    percentage,
+                    done: false,
+                    cancel,
+                }),
+            },
+            toast,
+        );
+        id
+    }
+
+    /// Update a work in progress, keeping its previous message or percentage
+    /// when not given new ones.
+    pub fn report_progress(
+        &self,
+        id: NotificationId,
+        message: Option<String>,
+        percentage: Option<u32>,
+    ) {
+        self.update(id, |notification| {
+            if let Some(message) = message {
+                notification.message = message;
+            }
+            if let Some(progress) = notification.progress.as_mut() {
+                if percentage.is_some() {
+                    progress.percentage = percentage;
+                }
+            }
+        });
+    }
+
+    /// Finish a work in progress, which is removed once its toast is hidden.
+    pub fn end_progress(&self, id: NotificationId, message: Option<String>) {
+        self.update(id, |notification| {
+            if let Some(message) = message {
+                notification.message = message;
+            }
+            if let Some(progress) = notification.progress.as_mut() {
+                progress.done = true;
+            }
+        });
+        if self.toasts.with_untracked(|toasts| toasts.contains(&id)) {
+            let data = self.clone();
+            exec_after(PROGRESS_DONE_TIMEOUT, move |_| data.remove(id));
+        } else {
+            self.remove(id);
+        }
+    }
+
+    /// Hide the toast of the notification, keeping it in the history.
+    pub fn dismiss(&self, id: NotificationId) {
+        if self.toasts.with_untracked(|toasts| toasts.contains(&id)) {
+            self.toasts.update(|toasts| {
+                toasts.remove(&id);
+            });
+        }
+    }
+
+    pub fn remove(&self, id: NotificationId) {
+        self.dismiss(id);
+        self.history.update(|history| {
+            history.retain(|notification| notification.id != id);
+        });
+    }
+
+    /// Remove every notification but the work still in progress.
+    pub fn clear(&self) {
+        self.history.update(|history| {
+            history.retain(Notification::is_running);
+        });
+        let history = self.history;
+        self.toasts.update(|toasts| {
+            history.with_untracked(|history| {
+                toasts.retain(|id| history.iter().any(|n| n.id == *id));
+            });
+        });
+        self.unread.set(0);
+    }
+
+    pub fn toggle_history(&self) {
+        let show = !self.show_history.get_untracked();
+        self.show_history.set(show);
+        if show {
+            self.unread.set(0);
+        }
+    }
+
+    fn push(&self, notification: Notification, toast: bool) {
+        let id = notification.id;
+        let mut dropped = Vec::new();
+        self.history.update(|history| {
+            history.push_back(notification);
+            while history.len() > HISTORY_LIMIT {
+                if let Some(notification) = history.pop_front() {
+                    dropped.push(notification.id);
+                }
+            }
+        });
+        if toast || !dropped.is_empty() {
+            self.toasts.update(|toasts| {
+                for id in dropped {
+                    toasts.remove(&id);
+                }
+                if toast {
+                    toasts.insert(id);
+                }
+            });
+        }
+    }
+
+    fn update(&self, id: NotificationId, f: impl FnOnce(&mut Notification)) {
+        self.history.update(|history| {
+            if let Some(notification) = history.iter_mut().find(|n| n.id == id) {
+                f(notification);
+            }
+        });
+    }
+
+    /// Get a value of the notification `id`, tracking its updates.
+    fn with_notification<T>(
+        &self,
+        id: NotificationId,
+        f: impl FnOnce(&Notification) -> T,
+    ) -> Option<T> {
+        self.history
+            .with(|history| history.iter().find(|n| n.id == id).map(f))
+    }
+}
+
+/// The toasts shown in the corner of the window, hidden while the history is
+/// open as it lists them too.
+pub fn notification_toasts(
+    data: NotificationData,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let history = data.history;
+    let toasts = data.toasts;
+    let show_history = data.show_history;
+    container(
+        container(
+            container(
+                scroll(
+                    dyn_stack(
+                        move || {
+                            let toasts = toasts.get();
+                            history
+                                .get()
+                                .into_iter()
+                                .filter(move |n| toasts.contains(&n.id))
+                        },
+                        |n| n.id,
+                        move |notification| {
+                            notification_view(
+                                data.clone(),
+                                notification,
+                                false,
+                                config,
+                            )
+                        },
+                    )
+                    .style(|s| s.flex_col().width_full()),
+                )
+                .style(|s| {
+                    s.absolute()
+                        .width_full()
+                        .min_height(0.0)
+                        .max_height_full()
+                        .set(PropagatePointerWheel, false)
+                }),
+            )
+            .style(|s| s.size_full()),
+        )
+        .style(|s| {
+            s.width(360.0)
+                .max_width_pct(80.0)
+                .padding(10.0)
+                .height_full()
+        }),
+    )
+    .style(move |s| {
+        s.absolute()
+            .size_full()
+            .justify_end()
+            .apply_if(show_history.get(), |s| s.hide())
+    })
+    .debug_name("Notification Toasts")
+}
+
+/// The history of the notifications, opened from the bell of the status bar.
+pub fn notification_history(
+    data: NotificationData,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let history = data.history;
+    let show_history = data.show_history;
+    let header = stack((
+        text("Notifications")
+            .style(|s| s.flex_grow(1.0).min_width(0.0).font_weight(Weight::BOLD)),
+        text("Clear All")
+            .on_click_stop({
+                let data = data.clone();
+                move |_| data.clear()
+            })
+            .style(move |s| button_style(s, config)),
+        clickable_icon(
+            || LapceIcons::CLOSE,
+            move || show_history.set(false),
+            || false,
+            || false,
+            || "Close",
+            config,
+        )
+        .style(|s| s.margin_left(6.0)),
+    ))
+    .style(move |s| {
+        s.width_full()
+            .items_center()
+            .padding_horiz(10.0)
+            .padding_vert(6.0)
+            .border_bottom(1.0)
+            .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+    });
+
+    let list = scroll(
+        stack((
+            dyn_stack(
+                move || history.get().into_iter().rev(),
+                |n| n.id,
+                move |notification| {
+                    notification_view(data.clone(), notification, true, config)
+                },
+            )
+            .style(|s| s.flex_col().width_full()),
+            text("No new notifications").style(move |s| {
+                s.padding(10.0)
+                    .color(config.get().color(LapceColor::EDITOR_DIM))
+                    .apply_if(!history.with(|history| history.is_empty()), |s| {
+                        s.hide()
+                    })
+            }),
+        ))
+        .style(|s| s.flex_col().width_full().padding(10.0)),
+    )
+    .style(|s| {
+        s.width_full()
+            .min_height(0.0)
+            .flex_shrink(1.0)
+            .set(PropagatePointerWheel, false)
+    });
+
+    container(
+        stack((header, list))
+            .on_event_stop(EventListener::PointerDown, |_| {})
+            .style(move |s| {
+                let config = config.get();
+                s.flex_col()
+                    .width(400.0)
+                    .max_width_pct(80.0)
+                    .max_height_pct(70.0)
+                    .border(1.0)
+                    .border_radius(6.0)
+                    .border_color(config.color(LapceColor::LAPCE_BORDER))
+                    .background(config.color(LapceColor::PANEL_BACKGROUND))
+            }),
+    )
+    .style(move |s| {
+        s.absolute()
+            .size_full()
+            .padding(10.0)
+            .justify_end()
+            .items_end()
+            .apply_if(!show_history.get(), |s| s.hide())
+    })
+    .debug_name("Notification History")
+}
+
+fn notification_view(
+    data: NotificationData,
+    notification: Notification,
+    in_history: bool,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let id = notification.id;
+    let typ = notification.typ;
+    // The message and the progress change as the work is reported, without
+    // building the view again.
+    let message = {
+        let data = data.clone();
+        create_memo(move |_| {
+            data.with_notification(id, Notification::text)
+                .unwrap_or_default()
+        })
+    };
+    let is_running = {
+        let data = data.clone();
+        create_memo(move |_| {
+            data.with_notification(id, Notification::is_running)
+                .unwrap_or(false)
+        })
+    };
+    let percentage = {
+        let data = data.clone();
+        create_memo(move |_| {
+            data.with_notification(id, |notification| {
+                notification
+                    .progress
+                    .as_ref()
+                    .and_then(|progress| progress.percentage)
+            })
+            .flatten()
+        })
+    };
+    let cancel = {
+        let data = data.clone();
+        move || {
+            data.with_notification(id, |notification| {
+                notification
+                    .progress
+                    .as_ref()
+                    .filter(|progress| !progress.done)
+                    .and_then(|progress| progress.cancel.clone())
+            })
+            .flatten()
+        }
+    };
+    let has_cancel = {
+        let cancel = cancel.clone();
+        create_memo(move |_| cancel().is_some())
+    };
+    let close = {
+        let data = data.clone();
+        move || {
+            if in_history {
+                data.remove(id);
+            } else {
+                data.dismiss(id);
+            }
+        }
+    };
+
+    stack((
+        svg(move || {
+            let icon = match typ {
+                MessageType::ERROR => LapceIcons::ERROR,
+                MessageType::WARNING => LapceIcons::WARNING,
+                _ => LapceIcons::INFO,
+            };
+            config.get().ui_svg(icon)
+        })
+        .style(move |s| {
+            let config = config.get();
+            let size = config.ui.icon_size() as f32;
+            let color = match typ {
+                MessageType::ERROR => config.color(LapceColor::LAPCE_ERROR),
+                MessageType::WARNING => config.color(LapceColor::LAPCE_WARN),
+                _ => config.color(LapceColor::LAPCE_ICON_ACTIVE),
+            };
+            s.min_width(size)
+                .size(size, size)
+                .margin_right(10.0)
+                .margin_top(4.0)
+                .color(color)
+        }),
+        stack((
+            text(notification.title.clone()).style(|s| {
+                s.min_width(0.0).line_height(1.8).font_weight(Weight::BOLD)
+            }),
+            label(move || message.get()).style(move |s| {
+                s.min_width(0.0)
+                    .line_height(1.8)
+                    .margin_top(5.0)
+                    .apply_if(message.with(|message| message.is_empty()), |s| {
+                        s.hide()
+                    })
+            }),
+            container(empty().style(move |s| {
+                let config = config.get();
+                s.height(2.0)
+                    .width_pct(percentage.get().unwrap_or(100).min(100) as f64)
+                    .background(config.color(LapceColor::LAPCE_ICON_ACTIVE))
+            }))
+            .style(move |s| {
+                s.width_full()
+                    .margin_top(8.0)
+                    .background(config.get().color(LapceColor::LAPCE_BORDER))
+                    .apply_if(!is_running.get(), |s| s.hide())
+            }),
+            text("Cancel")
+                .on_click_stop(move |_| {
+                    if let Some(cancel) = cancel() {
+                        cancel();
+                    }
+                })
+                .style(move |s| {
+                    button_style(s, config)
+                        .margin_top(8.0)
+                        .apply_if(!has_cancel.get(), |s| s.hide())
+                }),
+        ))
+        .style(move |s| {
+            s.flex_col()
+                .items_start()
+                .min_width(0.0)
+                .flex_basis(0.0)
+                .flex_grow(1.0)
+        }),
+        clickable_icon(
+            || LapceIcons::CLOSE,
+            close.clone(),
+            || false,
+            || false,
+            || "Close",
+            config,
+        )
+        .style(|s| s.margin_left(6.0)),
+    ))
+    .on_double_click_stop(move |_| close())
+    .on_secondary_click_stop({
+        let data = data.clone();
+        move |_| {
+            let message = data
+                .with_notification(id, |notification| notification.message.clone())
+                .unwrap_or_default();
+            let mut clipboard = SystemClipboard::new();
+            if !message.is_empty() {
+                clipboard.put_string(&message);
+            }
+        }
+    })
+    .on_event_stop(EventListener::PointerDown, |_| {})
+    .style(move |s| {
+        let config = config.get();
+        s.width_full()
+            .items_start()
+            .padding(10.0)
+            .margin_bottom(10.0)
+            .border(1.0)
+            .border_radius(6.0)
+            .border_color(config.color(LapceColor::LAPCE_BORDER))
+            .background(config.color(LapceColor::PANEL_BACKGROUND))
+    })
+}
+
+fn button_style(s: Style, config: ReadSignal<Arc<LapceConfig>>) -> Style {
+    let config = config.get();
+    s.padding_horiz(10.0)
+        .line_height(1.6)
+        .border(1.0)
+        .border_radius(6.0)
+        .border_color(config.color(LapceColor::LAPCE_BORDER))
+        .hover(|s| {
+            s.cursor(CursorStyle::Pointer)
+                .background(config.color(LapceColor::PANEL_HOVERED_BACKGROUND))
+        })
+        .active(|s| {
+            s.background(config.color(LapceColor::PANEL_HOVERED_ACTIVE_BACKGROUND))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(message: &str, percentage: Option<u32>, done: bool) -> Notification {
+        Notification {
+            id: NotificationId(1),
+            typ: MessageType::INFO,
+            title: "rust-analyzer: Indexing".to_string(),
+            message: message.to_string(),
+            progress: Some(NotificationProgress {
+                percentage,
+                done,
+                cancel: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_text_of_progress() {
+        assert_eq!(progress("3/10", Some(30), false).text(), "3/10 (30%)");
+        assert_eq!(progress("", Some(30), false).text(), "30%");
+        assert_eq!(progress("3/10", None, false).text(), "3/10");
+        assert_eq!(progress("done", Some(100), true).text(), "done");
+    }
+}
@@ -1,7 +1,8 @@
 use std::{
+    borrow::Cow,
     cell::RefCell,
     collections::{HashMap, HashSet},
-    path::PathBuf,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{
         atomic::{AtomicU64, Ordering},
@@ -11,6 +12,7 @@
 };
 
 use anyhow::Result;
+use chrono::TimeZone;
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use floem::{
     ext_event::{create_ext_action, create_signal_from_channel},
@@ -23,35 +25,50 @@
 use im::Vector;
 use itertools::Itertools;
 use lapce_core::{
-    buffer::rope_text::RopeText, command::FocusCommand, language::LapceLanguage,
-    line_ending::LineEnding, mode::Mode, movement::Movement, selection::Selection,
+    buffer::{
+        diff::{rope_diff, DiffLines},
+        rope_text::RopeText,
+    },
+    command::FocusCommand,
+    language::LapceLanguage,
+    line_ending::LineEnding,
+    mode::Mode,
+    movement::Movement,
+    selection::Selection,
     syntax::Syntax,
 };
-use lapce_rpc::proxy::ProxyResponse;
+use lapce_rpc::{
+    buffer::FILE_ENCODINGS, dap_types::DebugRequest, proxy::ProxyResponse,
+};
 use lapce_xi_rope::Rope;
-use lsp_types::{DocumentSymbol, DocumentSymbolResponse};
+use lsp_types::{DocumentSymbol, DocumentSymbolResponse, SymbolInformation};
 use nucleo::Utf32Str;
 use strum::{EnumMessage, IntoEnumIterator};
 use tracing::error;
 
 use self::{
-    item::{PaletteItem, PaletteItemContent},
+    item::{DirtyFileAction, LocalVersionAction, PaletteItem, PaletteItemContent},
     kind::PaletteKind,
 };
 use crate::{
+    app::AppData,
     command::{
         CommandExecuted, CommandKind, InternalCommand, LapceCommand, WindowCommand,
     },
     db::LapceDb,
-    debug::{RunDebugConfigs, RunDebugMode},
+    debug::{RunDebugConfigs, RunDebugMode, LAUNCH_TOML, RUN_TOML},
+    doc::{Doc, DocContent},
     editor::{
         location::{EditorLocation, EditorPosition},
         EditorData,
     },
+    file_template::FileTemplate,
     keypress::{condition::Condition, KeyPressData, KeyPressFocus},
     lsp::path_from_url,
     main_split::MainSplitData,
     source_control::SourceControlData,
+    terminal::panel::TerminalPanelData,
+    vim::parse_ex_command,
     window_tab::{CommonData, Focus},
     workspace::{LapceWorkspace, LapceWorkspaceType, SshHost},
 };
@@ -94,6 +111,9 @@ pub struct PaletteData {
     pub filtered_items: ReadSignal<im::Vector<PaletteItem>>,
     pub input: RwSignal<PaletteInput>,
     kind: RwSignal<PaletteKind>,
+    /// Whether the palette was opened with `:` in modal mode, to run its input
+    /// as a vim ex command.
+    ex_command: RwSignal<bool>,
     pub input_editor: EditorData,
     pub preview_editor: EditorData,
     pub has_preview: RwSignal<bool>,
@@ -105,8 +125,17 @@ pub struct PaletteData {
     pub main_split: MainSplitData,
     pub references: RwSignal<Vec<EditorLocation>>,
     pub source_control: SourceControlData,
+    pub terminal: TerminalPanelData,
     pub common: Rc<CommonData>,
+    /// Bumped for every workspace symbol query of the "Go to Anything"
+    /// palette, so the responses to outdated queries can be dropped.
+    symbol_query_id: RwSignal<u64>,
     left_diff_path: RwSignal<Option<PathBuf>>,
+    /// The path of the file to create from one of the templates.
+    new_file_path: RwSignal<Option<PathBuf>>,
+    file_templates: RwSignal<Vec<FileTemplate>>,
+    /// The document showing the changes of a dirty file in the preview.
+    dirty_diff_doc: Rc<Doc>,
 }
 
 impl std::fmt::Debug for PaletteData {
@@ -122,6 +151,7 @@ pub fn new(
         main_split: MainSplitData,
         keypress: ReadSignal<KeyPressData>,
         source_control: SourceControlData,
+        terminal: TerminalPanelData,
         common: Rc<CommonData>,
     ) -> Self {
         let status = cx.create_rw_signal(PaletteStatus::Inactive);
@@ -214,6 +244,11 @@ pub fn new(
 
         let clicked_index = cx.create_rw_signal(Option::<usize>::None);
         let left_diff_path = cx.create_rw_signal(None);
+        let ex_command = cx.create_rw_signal(false);
+        let new_file_path = cx.create_rw_signal(None);
+        let file_templates = cx.create_rw_signal(Vec::new());
+        let dirty_diff_doc =
+            Rc::new(Doc::new_local(cx, main_split.editors, common.clone()));
 
         let palette = Self {
             run_id_counter,
@@ -236,8 +271,14 @@ pub fn new(
             executed_run_configs: Rc::new(RefCell::new(HashMap::new())),
             references,
             source_control,
+            terminal,
             common,
+            symbol_query_id: cx.create_rw_signal(0),
             left_diff_path,
+            ex_command,
+            new_file_path,
+            file_templates,
+            dirty_diff_doc,
         };
 
         {
@@ -293,12 +334,15 @@ pub fn new(
                             }
                         })
                         .unwrap();
+                    let kind = input.with_untracked(|i| i.kind);
                     if let Some(new_kind) = new_kind {
                         palette.run_inner(new_kind);
-                    } else if input
-                        .with_untracked(|i| i.kind == PaletteKind::WorkspaceSymbol)
-                    {
+                    } else if kind == PaletteKind::WorkspaceSymbol {
                         palette.run_inner(PaletteKind::WorkspaceSymbol);
+                    } else if kind == PaletteKind::Anything {
+                        // Only the symbols depend on the query, the other
+                        // sources are filtered like any other palette.
+                        palette.get_anything_symbols();
                     }
                 }
                 Some(new_input)
@@ -334,6 +378,7 @@ pub fn run(&self, kind: PaletteKind) {
         self.status.set(PaletteStatus::Started);
         let symbol = kind.symbol();
         self.kind.set(kind);
+        self.ex_command.set(false);
         // Refresh the palette input with only the symbol prefix, losing old content.
         self.input_editor.doc().reload(Rope::from(symbol), true);
         self.input_editor
@@ -341,12 +386,36 @@ pub fn run(&self, kind: PaletteKind) {
             .update(|cursor| cursor.set_insert(Selection::caret(symbol.len())));
     }
 
+    /// Open the command palette for running its input as a vim ex command,
+    /// when it is one, rather than the command it matches.
+    pub fn run_ex_command(&self) {
+        self.run(PaletteKind::Command);
+        self.ex_command.set(true);
+    }
+
+    /// Pick the file to compare `path` with, which is shown on the left.
+    pub fn compare_with(&self, path: PathBuf) {
+        self.left_diff_path.set(Some(path));
+        self.run(PaletteKind::DiffFiles);
+    }
+
+    /// Offer the given templates to create the file at `path` from.
+    pub fn run_file_templates(&self, path: PathBuf, templates: Vec<FileTemplate>) {
+        self.new_file_path.set(Some(path));
+        self.file_templates.set(templates);
+        self.run(PaletteKind::FileTemplate);
+    }
+
     /// Get the placeholder text to use in the palette input field.
     pub fn placeholder_text(&self) -> &'static str {
         match self.kind.get() {
             PaletteKind::SshHost => {
                 "Type [user@]host or select a previously connected workspace below"
             }
+            PaletteKind::SaveTerminalLayout => {
+                "Type a name for the layout or select one below to replace it"
+            }
+            PaletteKind::BookmarkLabel => "Type a label for the bookmark",
             PaletteKind::DiffFiles => {
                 if self.left_diff_path.with(Option::is_some) {
                     "Select right file"
@@ -412,10 +481,31 @@ fn run_inner(&self, kind: PaletteKind) {
             PaletteKind::LineEnding => {
                 self.get_line_endings();
             }
+            PaletteKind::ReopenWithEncoding => {
+                self.get_encodings(false);
+            }
+            PaletteKind::SaveWithEncoding => {
+                self.get_encodings(true);
+            }
+            PaletteKind::SurroundWith => {
+                self.get_surround_pairs();
+            }
             PaletteKind::SCMReferences => {
                 self.get_scm_references();
             }
             PaletteKind::TerminalProfile => self.get_terminal_profiles(),
+            PaletteKind::DirtyFiles => self.get_dirty_files(),
+            PaletteKind::LocalHistory => self.get_local_history(),
+            PaletteKind::Macro => self.get_macros(),
+            PaletteKind::FileTemplate => self.get_file_templates(),
+            PaletteKind::Window => self.get_windows(),
+            PaletteKind::Anything => self.get_anything(),
+            PaletteKind::TerminalLayout | PaletteKind::SaveTerminalLayout => {
+                self.get_terminal_layouts()
+            }
+            PaletteKind::Bookmark => self.get_bookmarks(),
+            PaletteKind::BookmarkLabel => self.items.set(Vector::new()),
+            PaletteKind::Task => self.get_tasks(),
         }
     }
 
@@ -471,25 +561,7 @@ fn get_files_and_prepend(&self, prepend: Option<im::Vector<PaletteItem>>) {
             create_ext_action(self.common.scope, move |items: Vec<PathBuf>| {
                 let items = items
                     .into_iter()
-                    .map(|full_path| {
-                        // Strip the workspace prefix off the path, to avoid clutter
-                        let path =
-                            if let Some(workspace_path) = workspace.path.as_ref() {
-                                full_path
-                                    .strip_prefix(workspace_path)
-                                    .unwrap_or(&full_path)
-                                    .to_path_buf()
-                            } else {
-                                full_path.clone()
-                            };
-                        let filter_text = path.to_string_lossy().into_owned();
-                        PaletteItem {
-                            content: PaletteItemContent::File { path, full_path },
-                            filter_text,
-                            score: 0,
-                            indices: Vec::new(),
-                        }
-                    })
+                    .map(|full_path| Self::file_item(&workspace, full_path))
                     .collect::<im::Vector<_>>();
                 let mut new_items = im::Vector::new();
                 if let Some(prepend) = prepend {
@@ -505,6 +577,25 @@ fn get_files_and_prepend(&self, prepend: Option<im::Vector<PaletteItem>>) {
         });
     }
 
+    fn file_item(workspace: &LapceWorkspace, full_path: PathBuf) -> PaletteItem {
+        // Strip the workspace prefix off the path, to avoid clutter
+        let path = if let Some(workspace_path) = workspace.path.as_ref() {
+            full_path
+                .strip_prefix(workspace_path)
+                .unwrap_or(&full_path)
+                .to_path_buf()
+        } else {
+            full_path.clone()
+        };
+        let filter_text = path.to_string_lossy().into_owned();
+        PaletteItem {
+            content: PaletteItemContent::File { path, full_path },
+            filter_text,
+            score: 0,
+            indices: Vec::new(),
+        }
+    }
+
     /// Initialize the palette with the files in the current workspace.
     fn get_files(&self) {
         self.get_files_and_prepend(None);
@@ -629,6 +720,44 @@ fn get_workspaces(&self) {
         self.items.set(items);
     }
 
+    /// Initialize the palette with the workspaces open in all the windows.
+    fn get_windows(&self) {
+        let Some(app) = use_context::<AppData>() else {
+            return;
+        };
+        let windows = app.windows.with_untracked(|windows| {
+            windows
+                .iter()
+                .map(|(window_id, window)| {
+                    (*window_id, window.window_tabs.get_untracked())
+                })
+                .sorted_by_key(|(window_id, _)| u64::from(*window_id))
+                .collect::<Vec<_>>()
+        });
+
+        let mut items = im::Vector::new();
+        for (i, (window_id, window_tabs)) in windows.into_iter().enumerate() {
+            for (index, (_, window_tab)) in window_tabs.iter().enumerate() {
+                let workspace = window_tab.workspace.as_ref().clone();
+                let name = workspace
+                    .display()
+                    .unwrap_or_else(|| "Empty Workspace".to_string());
+                items.push_back(PaletteItem {
+                    content: PaletteItemContent::Window {
+                        window_id,
+                        index,
+                        workspace,
+                    },
+                    filter_text: format!("{name} (Window {})", i + 1),
+                    score: 0,
+                    indices: vec![],
+                });
+            }
+        }
+
+        self.items.set(items);
+    }
+
     /// Initialize the list of references in the file, from the current editor location.
     fn get_references(&self) {
         let items = self
@@ -756,45 +885,173 @@ fn get_workspace_symbols(&self) {
         let set_items = self.items.write_only();
         let send = create_ext_action(self.common.scope, move |result| {
             if let Ok(ProxyResponse::GetWorkspaceSymbols { symbols }) = result {
-                let items: im::Vector<PaletteItem> = symbols
+                let items: im::Vector<PaletteItem> =
+                    symbols.iter().map(Self::workspace_symbol_item).collect();
+                set_items.set(items);
+            } else {
+                set_items.update(|items| items.clear());
+            }
+        });
+
+        self.common
+            .proxy
+            .get_workspace_symbols(input, move |result| {
+                send(result);
+            });
+    }
+
+    fn workspace_symbol_item(s: &SymbolInformation) -> PaletteItem {
+        // TODO: Should we be using filter text?
+        let mut filter_text = s.name.clone();
+        if let Some(container_name) = s.container_name.as_ref() {
+            filter_text += container_name;
+        }
+        PaletteItem {
+            content: PaletteItemContent::WorkspaceSymbol {
+                kind: s.kind,
+                name: s.name.clone(),
+                location: EditorLocation {
+                    path: path_from_url(&s.location.uri),
+                    position: Some(EditorPosition::Position(s.location.range.start)),
+                    scroll_offset: None,
+                    ignore_unconfirmed: false,
+                    same_editor_tab: false,
+                },
+                container_name: s.container_name.clone(),
+            },
+            filter_text,
+            score: 0,
+            indices: Vec::new(),
+        }
+    }
+
+    /// Initialize the palette with everything there is to navigate to. The
+    /// recent locations and the terminals are listed right away, while the
+    /// files and the workspace symbols are added as the proxy responds.
+    fn get_anything(&self) {
+        let run_id = self.run_id.get_untracked();
+
+        let mut recent_paths = HashSet::new();
+        let mut items: im::Vector<PaletteItem> =
+            self.main_split.locations.with_untracked(|locations| {
+                locations
                     .iter()
-                    .map(|s| {
-                        // TODO: Should we be using filter text?
-                        let mut filter_text = s.name.clone();
-                        if let Some(container_name) = s.container_name.as_ref() {
-                            filter_text += container_name;
-                        }
+                    .rev()
+                    .filter(|l| recent_paths.insert(l.path.clone()))
+                    .map(|l| {
+                        let path = self
+                            .workspace
+                            .path
+                            .as_ref()
+                            .and_then(|workspace_path| {
+                                l.path.strip_prefix(workspace_path).ok()
+                            })
+                            .unwrap_or(&l.path)
+                            .to_path_buf();
+                        let filter_text = path.to_string_lossy().into_owned();
                         PaletteItem {
-                            content: PaletteItemContent::WorkspaceSymbol {
-                                kind: s.kind,
-                                name: s.name.clone(),
-                                location: EditorLocation {
-                                    path: path_from_url(&s.location.uri),
-                                    position: Some(EditorPosition::Position(
-                                        s.location.range.start,
-                                    )),
-                                    scroll_offset: None,
-                                    ignore_unconfirmed: false,
-                                    same_editor_tab: false,
-                                },
-                                container_name: s.container_name.clone(),
+                            content: PaletteItemContent::Reference {
+                                path,
+                                location: l.clone(),
                             },
                             filter_text,
                             score: 0,
                             indices: Vec::new(),
                         }
                     })
-                    .collect();
-                set_items.set(items);
-            } else {
-                set_items.update(|items| items.clear());
+                    .collect()
+            });
+        self.terminal.tab_info.with_untracked(|info| {
+            for (_, tab) in &info.tabs {
+                let Some(terminal) = tab.active_terminal(false) else {
+                    continue;
+                };
+                let title = terminal.title.get_untracked();
+                items.push_back(PaletteItem {
+                    content: PaletteItemContent::Terminal {
+                        term_id: terminal.term_id,
+                        title: title.clone(),
+                    },
+                    filter_text: format!("Terminal: {title}"),
+                    score: 0,
+                    indices: Vec::new(),
+                });
+            }
+        });
+        self.items.set(items);
+
+        let workspace = self.workspace.clone();
+        let current_run_id = self.run_id.read_only();
+        let set_items = self.items.write_only();
+        let send =
+            create_ext_action(self.common.scope, move |paths: Vec<PathBuf>| {
+                // The palette has been closed or rerun in the meantime.
+                if current_run_id.get_untracked() != run_id {
+                    return;
+                }
+                let files = paths
+                    .into_iter()
+                    .filter(|path| !recent_paths.contains(path))
+                    .map(|full_path| Self::file_item(&workspace, full_path))
+                    .collect::<im::Vector<_>>();
+                set_items.update(|items| items.append(files));
+            });
+        self.common.proxy.get_files(move |result| {
+            if let Ok(ProxyResponse::GetFilesResponse { items }) = result {
+                send(items);
             }
         });
 
+        self.get_anything_symbols();
+    }
+
+    /// Replace the workspace symbols in the "Go to Anything" palette with the
+    /// ones matching the current input. A response that arrives after the
+    /// input changed again is dropped, so a slow language server can't
+    /// overwrite the results of a newer query.
+    fn get_anything_symbols(&self) {
+        let run_id = self.run_id.get_untracked();
+        let query_id = self.symbol_query_id.get_untracked() + 1;
+        self.symbol_query_id.set(query_id);
+
+        let current_run_id = self.run_id.read_only();
+        let current_query_id = self.symbol_query_id.read_only();
+        let set_items = self.items.write_only();
+        let send = create_ext_action(
+            self.common.scope,
+            move |symbols: Vec<SymbolInformation>| {
+                if current_run_id.get_untracked() != run_id
+                    || current_query_id.get_untracked() != query_id
+                {
+                    return;
+                }
+                let symbols = symbols
+                    .iter()
+                    .map(Self::workspace_symbol_item)
+                    .collect::<im::Vector<_>>();
+                set_items.update(|items| {
+                    items.retain(|item| {
+                        !matches!(
+                            item.content,
+                            PaletteItemContent::WorkspaceSymbol { .. }
+                        )
+                    });
+                    items.append(symbols);
+                });
+            },
+        );
+
+        let input = self.input.with_untracked(|input| input.input.clone());
+        if input.is_empty() {
+            send(Vec::new());
+            return;
+        }
         self.common
             .proxy
             .get_workspace_symbols(input, move |result| {
-                send(result);
+                if let Ok(ProxyResponse::GetWorkspaceSymbols { symbols }) = result {
+                    send(symbols);
+                }
             });
     }
 
@@ -879,21 +1136,43 @@ fn get_wsl_hosts(&self) {
         self.items.set(items);
     }
 
-    fn set_run_configs(&self, content: String) {
-        let configs: Option<RunDebugConfigs> = toml::from_str(&content).ok();
+    fn set_run_configs(&self, run_toml: String, launch_toml: String) {
+        let configs: Option<RunDebugConfigs> = toml::from_str(&run_toml).ok();
         if configs.is_none() {
             if let Some(path) = self.workspace.path.as_ref() {
-                let path = path.join(".lapce").join("run.toml");
+                let path = path.join(".lapce").join(RUN_TOML);
                 self.common
                     .internal_command
                     .send(InternalCommand::OpenFile { path });
             }
         }
+        let launch_configs = if launch_toml.trim().is_empty() {
+            None
+        } else {
+            match toml::from_str::<RunDebugConfigs>(&launch_toml) {
+                Ok(configs) => Some(configs),
+                Err(err) => {
+                    tracing::error!("invalid {LAUNCH_TOML}: {err}");
+                    if let Some(path) = self.workspace.path.as_ref() {
+                        let path = path.join(".lapce").join(LAUNCH_TOML);
+                        self.common
+                            .internal_command
+                            .send(InternalCommand::OpenFile { path });
+                    }
+                    None
+                }
+            }
+        };
 
         let executed_run_configs = self.executed_run_configs.borrow();
         let mut items = Vec::new();
-        if let Some(configs) = configs.as_ref() {
-            for config in &configs.configs {
+        for config in configs
+            .iter()
+            .chain(launch_configs.iter())
+            .flat_map(|configs| configs.configs.iter())
+        {
+            // There's no program to run when attaching to one
+            if config.request == DebugRequest::Launch {
                 items.push((
                     executed_run_configs
                         .get(&(RunDebugMode::Run, config.name.clone())),
@@ -912,26 +1191,26 @@ fn set_run_configs(&self, content: String) {
                         indices: vec![],
                     },
                 ));
-                if config.ty.is_some() {
-                    items.push((
-                        executed_run_configs
-                            .get(&(RunDebugMode::Debug, config.name.clone())),
-                        PaletteItem {
-                            content: PaletteItemContent::RunAndDebug {
-                                mode: RunDebugMode::Debug,
-                                config: config.clone(),
-                            },
-                            filter_text: format!(
-                                "Debug {} {} {}",
-                                config.name,
-                                config.program,
-                                config.args.clone().unwrap_or_default().join(" ")
-                            ),
-                            score: 0,
-                            indices: vec![],
+            }
+            if config.is_debuggable() {
+                items.push((
+                    executed_run_configs
+                        .get(&(RunDebugMode::Debug, config.name.clone())),
+                    PaletteItem {
+                        content: PaletteItemContent::RunAndDebug {
+                            mode: RunDebugMode::Debug,
+                            config: config.clone(),
                         },
-                    ));
-                }
+                        filter_text: format!(
+                            "Debug {} {} {}",
+                            config.name,
+                            config.program,
+                            config.args.clone().unwrap_or_default().join(" ")
+                        ),
+                        score: 0,
+                        indices: vec![],
+                    },
+                ));
             }
         }
 
@@ -941,31 +1220,47 @@ fn set_run_configs(&self, content: String) {
     }
 
     fn get_run_configs(&self) {
-        if let Some(workspace) = self.common.workspace.path.as_deref() {
-            let run_toml = workspace.join(".lapce").join("run.toml");
-            let (doc, new_doc) = self.main_split.get_doc(run_toml.clone(), None);
-            if !new_doc {
-                let content = doc.buffer.with_untracked(|b| b.to_string());
-                self.set_run_configs(content);
-            } else {
-                let loaded = doc.loaded;
-                let palette = self.clone();
-                self.common.scope.create_effect(move |prev_loaded| {
-                    if prev_loaded == Some(true) {
-                        return true;
-                    }
-
-                    let loaded = loaded.get();
-                    if loaded {
-                        let content = doc.buffer.with_untracked(|b| b.to_string());
-                        if content.is_empty() {
-                            doc.reload(Rope::from(DEFAULT_RUN_TOML), false);
-                        }
-                        palette.set_run_configs(content);
-                    }
-                    loaded
-                });
+        let palette = self.clone();
+        self.with_workspace_toml(RUN_TOML, move |doc, new_doc, run_toml| {
+            if new_doc && run_toml.is_empty() {
+                doc.reload(Rope::from(DEFAULT_RUN_TOML), false);
             }
+            let local_palette = palette.clone();
+            palette.with_workspace_toml(LAUNCH_TOML, move |_, _, launch_toml| {
+                local_palette.set_run_configs(run_toml.clone(), launch_toml);
+            });
+        });
+    }
+
+    /// Call `f` with the document of the `.lapce/<file_name>` file of the
+    /// workspace, whether it was just opened, and its text once it's loaded.
+    fn with_workspace_toml(
+        &self,
+        file_name: &str,
+        f: impl Fn(Rc<Doc>, bool, String) + 'static,
+    ) {
+        let Some(workspace) = self.common.workspace.path.as_deref() else {
+            return;
+        };
+        let path = workspace.join(".lapce").join(file_name);
+        let (doc, new_doc) = self.main_split.get_doc(path, None);
+        if !new_doc {
+            let content = doc.buffer.with_untracked(|b| b.to_string());
+            f(doc, false, content);
+        } else {
+            let loaded = doc.loaded;
+            self.common.scope.create_effect(move |prev_loaded| {
+                if prev_loaded == Some(true) {
+                    return true;
+                }
+
+                let loaded = loaded.get();
+                if loaded {
+                    let content = doc.buffer.with_untracked(|b| b.to_string());
+                    f(doc.clone(), true, content);
+                }
+                loaded
+            });
         }
     }
 
@@ -1047,6 +1342,48 @@ fn get_line_endings(&self) {
         self.items.set(items);
     }
 
+    fn get_encodings(&self, save: bool) {
+        let items = FILE_ENCODINGS
+            .iter()
+            .map(|name| PaletteItem {
+                content: PaletteItemContent::Encoding {
+                    name: name.to_string(),
+                    save,
+                },
+                filter_text: name.to_string(),
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+        if let Some(editor) = self.main_split.active_editor.get_untracked() {
+            let encoding = editor.doc().encoding.get_untracked();
+            self.preselect_matching(&items, &encoding);
+        }
+        self.items.set(items);
+    }
+
+    fn get_surround_pairs(&self) {
+        const PAIRS: &[(&str, &str)] = &[
+            ("(", ")"),
+            ("[", "]"),
+            ("{", "}"),
+            ("<", ">"),
+            ("\"", "\""),
+            ("'", "'"),
+            ("`", "`"),
+        ];
+        let items = PAIRS
+            .iter()
+            .map(|&(open, close)| PaletteItem {
+                content: PaletteItemContent::SurroundPair { open, close },
+                filter_text: format!("{open}{close}"),
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+        self.items.set(items);
+    }
+
     fn get_scm_references(&self) {
         let branches = self.source_control.branches.get_untracked();
         let tags = self.source_control.tags.get_untracked();
@@ -1110,6 +1447,282 @@ fn get_terminal_profiles(&self) {
         self.items.set(items);
     }
 
+    fn get_terminal_layouts(&self) {
+        let db: Arc<LapceDb> = use_context().unwrap();
+        let layouts = db.get_terminal_layouts(&self.workspace).unwrap_or_default();
+        let items = layouts
+            .into_iter()
+            .map(|layout| PaletteItem {
+                filter_text: layout.name.clone(),
+                content: PaletteItemContent::TerminalLayout { name: layout.name },
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+        self.items.set(items);
+    }
+
+    fn get_bookmarks(&self) {
+        let bookmarks = self.common.bookmarks.with_untracked(|b| b.all());
+        let items = bookmarks
+            .into_iter()
+            .map(|(path, bookmark)| {
+                let relative = self
+                    .workspace
+                    .path
+                    .as_ref()
+                    .and_then(|root| path.strip_prefix(root).ok())
+                    .unwrap_or(&path);
+                let location =
+                    format!("{}:{}", relative.display(), bookmark.line + 1);
+                let filter_text = match bookmark.label {
+                    Some(label) => format!("{label}  {location}"),
+                    None => location,
+                };
+                PaletteItem {
+                    filter_text,
+                    content: PaletteItemContent::Bookmark {
+                        location: EditorLocation {
+                            path,
+                            position: Some(EditorPosition::Line(bookmark.line)),
+                            scroll_offset: None,
+                            ignore_unconfirmed: false,
+                            same_editor_tab: false,
+                        },
+                    },
+                    score: 0,
+                    indices: Vec::new(),
+                }
+            })
+            .collect();
+        self.items.set(items);
+    }
+
+    /// Initialize the palette with the tasks of the workspace.
+    fn get_tasks(&self) {
+        let set_items = self.items.write_only();
+        let send = create_ext_action(self.common.scope, move |result| {
+            let Ok(ProxyResponse::GetTasksResponse { tasks }) = result else {
+                set_items.update(|items| items.clear());
+                return;
+            };
+            let items = tasks
+                .into_iter()
+                .map(|task| {
+                    let command = task.command_line();
+                    let filter_text = if command == task.name {
+                        command
+                    } else {
+                        format!("{}  {command}", task.name)
+                    };
+                    PaletteItem {
+                        content: PaletteItemContent::Task { task },
+                        filter_text,
+                        score: 0,
+                        indices: Vec::new(),
+                    }
+                })
+                .collect();
+            set_items.set(items);
+        });
+        self.common.proxy.get_tasks(move |result| send(result));
+    }
+
+    fn get_file_templates(&self) {
+        let items = self.file_templates.with_untracked(|templates| {
+            templates
+                .iter()
+                .map(|template| PaletteItem {
+                    content: PaletteItemContent::FileTemplate {
+                        template: template.clone(),
+                    },
+                    filter_text: template.name.clone(),
+                    score: 0,
+                    indices: Vec::new(),
+                })
+                .collect()
+        });
+        self.items.set(items);
+    }
+
+    /// Initialize the palette with the files that have unsaved changes, offering
+    /// to save or revert each of them, or to save them all.
+    fn get_dirty_files(&self) {
+        let paths: Vec<PathBuf> = self.main_split.docs.with_untracked(|docs| {
+            docs.iter()
+                .filter(|(_, doc)| {
+                    doc.content.with_untracked(|c| c.is_file()) && !doc.is_pristine()
+                })
+                .map(|(path, _)| path.clone())
+                .sorted()
+                .collect()
+        });
+
+        let mut items = im::Vector::new();
+        if !paths.is_empty() {
+            items.push_back(PaletteItem {
+                content: PaletteItemContent::SaveAllDirtyFiles {
+                    paths: paths.clone(),
+                },
+                filter_text: format!("Save All ({} files)", paths.len()),
+                score: 0,
+                indices: Vec::new(),
+            });
+        }
+        for full_path in paths {
+            let path = if let Some(workspace_path) = self.workspace.path.as_ref() {
+                full_path
+                    .strip_prefix(workspace_path)
+                    .unwrap_or(&full_path)
+                    .to_path_buf()
+            } else {
+                full_path.clone()
+            };
+            for (action, verb) in [
+                (DirtyFileAction::Save, "Save"),
+                (DirtyFileAction::Revert, "Revert"),
+            ] {
+                items.push_back(PaletteItem {
+                    content: PaletteItemContent::DirtyFile {
+                        path: path.clone(),
+                        full_path: full_path.clone(),
+                        action,
+                    },
+                    filter_text: format!("{verb} {}", path.to_string_lossy()),
+                    score: 0,
+                    indices: Vec::new(),
+                });
+            }
+        }
+
+        self.items.set(items);
+    }
+
+    /// The versions of the active file kept by the local history, newest first.
+    fn get_local_history(&self) {
+        let content = self
+            .main_split
+            .active_editor
+            .get_untracked()
+            .map(|editor| editor.doc().content.get_untracked());
+        let Some(DocContent::File { path, .. }) = content else {
+            self.items.update(|items| items.clear());
+            return;
+        };
+
+        let set_items = self.items.write_only();
+        let send = create_ext_action(self.common.scope, move |result| {
+            let Ok(ProxyResponse::LocalHistoryResponse { timestamps }) = result
+            else {
+                set_items.update(|items| items.clear());
+                return;
+            };
+            let mut items = im::Vector::new();
+            for timestamp in timestamps {
+                let Some(time) = chrono::Local
+                    .timestamp_millis_opt(timestamp as i64)
+                    .single()
+                else {
+                    continue;
+                };
+                let time = time.format("%Y-%m-%d %H:%M:%S");
+                for (action, verb) in [
+                    (LocalVersionAction::Compare, "Compare with"),
+                    (LocalVersionAction::Restore, "Restore"),
+                ] {
+                    items.push_back(PaletteItem {
+                        content: PaletteItemContent::LocalVersion {
+                            path: path.clone(),
+                            timestamp,
+                            action,
+                        },
+                        filter_text: format!("{verb} version saved {time}"),
+                        score: 0,
+                        indices: Vec::new(),
+                    });
+                }
+            }
+            set_items.set(items);
+        });
+        self.common
+            .proxy
+            .local_history(path.clone(), move |result| send(result));
+    }
+
+    /// Initialize the palette with the recorded keyboard macros.
+    fn get_macros(&self) {
+        let names = self
+            .common
+            .keypress
+            .with_untracked(|k| k.macros.with_untracked(|m| m.names()));
+        let items = names
+            .into_iter()
+            .map(|name| PaletteItem {
+                filter_text: name.clone(),
+                content: PaletteItemContent::Macro { name },
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+        self.items.set(items);
+    }
+
+    /// Show the unsaved changes of `path` against its content on disk in the
+    /// preview editor.
+    fn preview_dirty_file(&self, path: &Path) {
+        let Some(doc) = self
+            .main_split
+            .docs
+            .with_untracked(|docs| docs.get(path).cloned())
+        else {
+            return;
+        };
+
+        self.has_preview.set(true);
+        self.dirty_diff_doc.reload(Rope::from(""), true);
+        self.preview_editor.update_doc(self.dirty_diff_doc.clone());
+
+        let index = self.index.get_untracked();
+        let palette_index = self.index;
+        let diff_doc = self.dirty_diff_doc.clone();
+        let show_diff = create_ext_action(self.common.scope, move |text: String| {
+            if palette_index.get_untracked() == index {
+                diff_doc.reload(Rope::from(text), true);
+            }
+        });
+        let buffer = doc.buffer.with_untracked(|buffer| buffer.text().clone());
+        // Reading the file as it's reopened, with the encoding the document
+        // already has, leaves its buffer unchanged.
+        self.common.proxy.reopen_buffer(
+            path.to_path_buf(),
+            doc.encoding.get_untracked(),
+            move |result| {
+                let disk = match result {
+                    Ok(ProxyResponse::NewBufferResponse { content, .. }) => {
+                        Rope::from(content)
+                    }
+                    Ok(_) => return,
+                    Err(err) => {
+                        show_diff(err.message);
+                        return;
+                    }
+                };
+                rayon::spawn(move || {
+                    let changes = rope_diff(
+                        disk.clone(),
+                        buffer.clone(),
+                        0,
+                        Arc::new(AtomicU64::new(0)),
+                        Some(3),
+                    );
+                    if let Some(changes) = changes {
+                        show_diff(dirty_diff_text(&disk, &buffer, &changes));
+                    }
+                });
+            },
+        );
+    }
+
     fn preselect_matching(&self, items: &im::Vector<PaletteItem>, matching: &str) {
         let Some((idx, _)) = items
             .iter()
@@ -1125,6 +1738,47 @@ fn select(&self) {
         let index = self.index.get_untracked();
         let items = self.filtered_items.get_untracked();
         self.close();
+        let ex_command = self.input.with_untracked(|input| {
+            ex_command_input(
+                self.kind.get_untracked(),
+                self.ex_command.get_untracked(),
+                &input.input,
+            )
+        });
+        if let Some(command) = ex_command {
+            self.common
+                .internal_command
+                .send(InternalCommand::RunExCommand { command });
+            return;
+        }
+        if self.kind.get_untracked() == PaletteKind::SaveTerminalLayout {
+            // A typed name takes precedence over the layouts it matches.
+            let input = self.input.with_untracked(|input| input.input.clone());
+            let name = if !input.trim().is_empty() {
+                Some(input.trim().to_string())
+            } else if let Some(PaletteItemContent::TerminalLayout { name }) =
+                items.get(index).map(|item| &item.content)
+            {
+                Some(name.clone())
+            } else {
+                None
+            };
+            if let Some(name) = name {
+                self.common
+                    .internal_command
+                    .send(InternalCommand::SaveTerminalLayout { name });
+            }
+            return;
+        }
+        if self.kind.get_untracked() == PaletteKind::BookmarkLabel {
+            let label = self.input.with_untracked(|input| input.input.clone());
+            self.common
+                .internal_command
+                .send(InternalCommand::AddBookmark {
+                    label: label.trim().to_string(),
+                });
+            return;
+        }
         if let Some(item) = items.get(index) {
             match &item.content {
                 PaletteItemContent::PaletteHelp { cmd } => {
@@ -1195,7 +1849,8 @@ fn select(&self) {
                         },
                     );
                 }
-                PaletteItemContent::Reference { location, .. } => {
+                PaletteItemContent::Reference { location, .. }
+                | PaletteItemContent::Bookmark { location } => {
                     self.common.internal_command.send(
                         InternalCommand::JumpToLocation {
                             location: location.clone(),
@@ -1307,12 +1962,34 @@ fn select(&self) {
                     else {
                         return;
                     };
-                    let doc = editor.doc();
-
-                    doc.buffer.update(|buffer| {
-                        buffer.set_line_ending(*kind);
+                    editor.set_line_ending(*kind);
+                }
+                PaletteItemContent::Encoding { name, save } => {
+                    let Some(editor) = self.main_split.active_editor.get_untracked()
+                    else {
+                        return;
+                    };
+                    let Some(path) = editor
+                        .doc()
+                        .content
+                        .with_untracked(|content| content.path().cloned())
+                    else {
+                        return;
+                    };
+                    let encoding = name.clone();
+                    self.common.internal_command.send(if *save {
+                        InternalCommand::SaveWithEncoding { path, encoding }
+                    } else {
+                        InternalCommand::ReopenWithEncoding { path, encoding }
                     });
                 }
+                PaletteItemContent::SurroundPair { open, close } => {
+                    let Some(editor) = self.main_split.active_editor.get_untracked()
+                    else {
+                        return;
+                    };
+                    editor.surround_with(open, close);
+                }
                 PaletteItemContent::SCMReference { name } => {
                     self.common
                         .lapce_command
@@ -1329,6 +2006,90 @@ fn select(&self) {
                     .send(InternalCommand::NewTerminal {
                         profile: Some(profile.to_owned()),
                     }),
+                PaletteItemContent::DirtyFile {
+                    full_path, action, ..
+                } => {
+                    let path = full_path.clone();
+                    self.common.internal_command.send(match action {
+                        DirtyFileAction::Save => {
+                            InternalCommand::SaveFiles { paths: vec![path] }
+                        }
+                        DirtyFileAction::Revert => {
+                            InternalCommand::RevertFile { path }
+                        }
+                    });
+                }
+                PaletteItemContent::FileTemplate { template } => {
+                    if let Some(path) = self.new_file_path.get_untracked() {
+                        self.common.internal_command.send(
+                            InternalCommand::NewFileFromTemplate {
+                                path,
+                                template: template.clone(),
+                            },
+                        );
+                    }
+                }
+                PaletteItemContent::Window {
+                    window_id, index, ..
+                } => {
+                    let window = use_context::<AppData>().and_then(|app| {
+                        app.windows.with_untracked(|windows| {
+                            windows.get(window_id).cloned()
+                        })
+                    });
+                    if let Some(window) = window {
+                        window.active.set(*index);
+                        window.raise();
+                    }
+                }
+                PaletteItemContent::LocalVersion {
+                    path,
+                    timestamp,
+                    action,
+                } => {
+                    let path = path.clone();
+                    let timestamp = *timestamp;
+                    self.common.internal_command.send(match action {
+                        LocalVersionAction::Compare => {
+                            InternalCommand::OpenLocalVersion { path, timestamp }
+                        }
+                        LocalVersionAction::Restore => {
+                            InternalCommand::RestoreLocalVersion { path, timestamp }
+                        }
+                    });
+                }
+                PaletteItemContent::Macro { name } => {
+                    self.common.internal_command.send(
+                        InternalCommand::ReplayMacro {
+                            name: Some(name.clone()),
+                            count: 1,
+                        },
+                    );
+                }
+                PaletteItemContent::SaveAllDirtyFiles { paths } => {
+                    self.common
+                        .internal_command
+                        .send(InternalCommand::SaveFiles {
+                            paths: paths.clone(),
+                        });
+                }
+                PaletteItemContent::Terminal { term_id, .. } => {
+                    self.common
+                        .internal_command
+                        .send(InternalCommand::FocusTerminal { term_id: *term_id });
+                }
+                PaletteItemContent::TerminalLayout { name } => {
+                    self.common.internal_command.send(
+                        InternalCommand::RestoreTerminalLayout {
+                            name: name.clone(),
+                        },
+                    );
+                }
+                PaletteItemContent::Task { task } => {
+                    self.common
+                        .internal_command
+                        .send(InternalCommand::RunTask { task: task.clone() });
+                }
             }
         } else if self.kind.get_untracked() == PaletteKind::SshHost {
             let input = self.input.with_untracked(|input| input.input.clone());
@@ -1394,7 +2155,10 @@ fn preview(&self) {
                 PaletteItemContent::WslHost { .. } => {}
                 PaletteItemContent::Language { .. } => {}
                 PaletteItemContent::LineEnding { .. } => {}
-                PaletteItemContent::Reference { location, .. } => {
+                PaletteItemContent::Encoding { .. } => {}
+                PaletteItemContent::SurroundPair { .. } => {}
+                PaletteItemContent::Reference { location, .. }
+                | PaletteItemContent::Bookmark { location } => {
                     self.has_preview.set(true);
                     let (doc, new_doc) =
                         self.main_split.get_doc(location.path.clone(), None);
@@ -1461,6 +2225,17 @@ fn preview(&self) {
                     }),
                 PaletteItemContent::SCMReference { .. } => {}
                 PaletteItemContent::TerminalProfile { .. } => {}
+                PaletteItemContent::DirtyFile { full_path, .. } => {
+                    self.preview_dirty_file(full_path);
+                }
+                PaletteItemContent::SaveAllDirtyFiles { .. } => {}
+                PaletteItemContent::LocalVersion { .. } => {}
+                PaletteItemContent::Macro { .. } => {}
+                PaletteItemContent::FileTemplate { .. } => {}
+                PaletteItemContent::Window { .. } => {}
+                PaletteItemContent::Terminal { .. } => {}
+                PaletteItemContent::TerminalLayout { .. } => {}
+                PaletteItemContent::Task { .. } => {}
             }
         }
     }
@@ -1477,6 +2252,7 @@ fn cancel(&self) {
         }
 
         self.left_diff_path.set(None);
+        self.new_file_path.set(None);
         self.close();
     }
 
@@ -1698,3 +2474,80 @@ fn receive_char(&self, c: &str) {
         self.input_editor.receive_char(c);
     }
 }
+
+/// Render the changes between the content of a file on disk and its buffer as
+/// text, prefixing removed lines with `-`, added lines with `+` and eliding
+/// unchanged lines away from the changes.
+fn dirty_diff_text(disk: &Rope, buffer: &Rope, changes: &[DiffLines]) -> String {
+    let left = disk.lines(0..disk.len()).collect::<Vec<_>>();
+    let right = buffer.lines(0..buffer.len()).collect::<Vec<_>>();
+    let mut text = String::new();
+    let mut push_line = |prefix: &str, line: Option<&Cow<str>>| {
+        text.push_str(prefix);
+        text.push_str(line.map(|l| l.as_ref()).unwrap_or(""));
+        text.push('\n');
+    };
+    for change in changes {
+        match change {
+            DiffLines::Left(range) => {
+                for line in range.clone() {
+                    push_line("- ", left.get(line));
+                }
+            }
+            DiffLines::Right(range) => {
+                for line in range.clone() {
+                    push_line("+ ", right.get(line));
+                }
+            }
+            DiffLines::Both(info) => {
+                for i in 0..info.left.len() {
+                    if let Some(skip) = info.skip.as_ref() {
+                        if skip.contains(&i) {
+                            if i == skip.start {
+                                push_line("  ", Some(&Cow::Borrowed("...")));
+                            }
+                            continue;
+                        }
+                    }
+                    push_line("  ", left.get(info.left.start + i));
+                }
+            }
+        }
+    }
+    text
+}
+
+/// The input of the command palette opened with `:` to run as a vim ex
+/// command, taking precedence over the commands it matches, when it's a
+/// command or a range vim knows.
+fn ex_command_input(
+    kind: PaletteKind,
+    ex_command: bool,
+    input: &str,
+) -> Option<String> {
+    (kind == PaletteKind::Command && ex_command && parse_ex_command(input).is_ok())
+        .then(|| input.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ex_command_input, PaletteKind};
+
+    #[test]
+    fn test_ex_command_input() {
+        for input in ["w", "q", "wq", "x", "d", "10", "%s/a/b/g"] {
+            assert_eq!(
+                ex_command_input(PaletteKind::Command, true, input).as_deref(),
+                Some(input)
+            );
+            // The command palette opened otherwise runs the matched command.
+            assert_eq!(ex_command_input(PaletteKind::Command, false, input), None);
+        }
+        assert_eq!(ex_command_input(PaletteKind::File, true, "w"), None);
+        assert_eq!(ex_command_input(PaletteKind::Command, true, "save"), None);
+        assert_eq!(
+            ex_command_input(PaletteKind::Command, true, "delete line"),
+            None
+        );
+    }
+}
@@ -1,13 +1,15 @@
 use std::path::PathBuf;
 
+use floem::window::WindowId;
 use lapce_core::line_ending::LineEnding;
-use lapce_rpc::dap_types::RunDebugConfig;
+use lapce_rpc::{dap_types::RunDebugConfig, task::TaskConfig, terminal::TermId};
 use lsp_types::{Range, SymbolKind};
 
 use crate::{
     command::{LapceCommand, LapceWorkbenchCommand},
     debug::RunDebugMode,
     editor::location::EditorLocation,
+    file_template::FileTemplate,
     workspace::{LapceWorkspace, SshHost},
 };
 
@@ -77,6 +79,16 @@ pub enum PaletteItemContent {
     LineEnding {
         kind: LineEnding,
     },
+    Encoding {
+        name: String,
+        /// Whether to save the file with the encoding, rather than reopen it.
+        save: bool,
+    },
+    /// Brackets or quotes to put around the selections.
+    SurroundPair {
+        open: &'static str,
+        close: &'static str,
+    },
     SCMReference {
         name: String,
     },
@@ -84,4 +96,60 @@ pub enum PaletteItemContent {
         name: String,
         profile: lapce_rpc::terminal::TerminalProfile,
     },
+    DirtyFile {
+        path: PathBuf,
+        full_path: PathBuf,
+        action: DirtyFileAction,
+    },
+    SaveAllDirtyFiles {
+        paths: Vec<PathBuf>,
+    },
+    /// A version of the file kept by the local history.
+    LocalVersion {
+        path: PathBuf,
+        timestamp: u64,
+        action: LocalVersionAction,
+    },
+    /// A recorded keyboard macro.
+    Macro {
+        name: String,
+    },
+    FileTemplate {
+        template: FileTemplate,
+    },
+    Window {
+        window_id: WindowId,
+        /// The index of the workspace tab in the window.
+        index: usize,
+        workspace: LapceWorkspace,
+    },
+    /// The active terminal of a terminal tab.
+    Terminal {
+        term_id: TermId,
+        title: String,
+    },
+    /// A terminal layout saved for the workspace.
+    TerminalLayout {
+        name: String,
+    },
+    Bookmark {
+        location: EditorLocation,
+    },
+    Task {
+        task: TaskConfig,
+    },
+}
+
+/// What to do with a modified buffer picked from the dirty files palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DirtyFileAction {
+    Save,
+    Revert,
+}
+
+/// What to do with a version picked from the local history palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocalVersionAction {
+    Compare,
+    Restore,
 }
@@ -20,10 +20,24 @@ pub enum PaletteKind {
     IconTheme,
     Language,
     LineEnding,
+    ReopenWithEncoding,
+    SaveWithEncoding,
+    SurroundWith,
     SCMReferences,
     TerminalProfile,
     DiffFiles,
+    DirtyFiles,
+    LocalHistory,
+    Macro,
+    FileTemplate,
+    Window,
     HelpAndFile,
+    Anything,
+    TerminalLayout,
+    SaveTerminalLayout,
+    Bookmark,
+    BookmarkLabel,
+    Task,
 }
 
 impl PaletteKind {
@@ -46,9 +60,23 @@ pub fn symbol(&self) -> &'static str {
             | PaletteKind::IconTheme
             | PaletteKind::Language
             | PaletteKind::LineEnding
+            | PaletteKind::ReopenWithEncoding
+            | PaletteKind::SaveWithEncoding
+            | PaletteKind::SurroundWith
             | PaletteKind::SCMReferences
             | PaletteKind::HelpAndFile
-            | PaletteKind::DiffFiles => "",
+            | PaletteKind::DiffFiles
+            | PaletteKind::DirtyFiles
+            | PaletteKind::LocalHistory
+            | PaletteKind::Macro
+            | PaletteKind::FileTemplate
+            | PaletteKind::Window
+            | PaletteKind::Anything
+            | PaletteKind::TerminalLayout
+            | PaletteKind::SaveTerminalLayout
+            | PaletteKind::Bookmark
+            | PaletteKind::BookmarkLabel
+            | PaletteKind::Task => "",
             #[cfg(windows)]
             PaletteKind::WslHost => "",
         }
@@ -98,11 +126,39 @@ pub fn command(self) -> Option<LapceWorkbenchCommand> {
             PaletteKind::LineEnding => {
                 Some(LapceWorkbenchCommand::ChangeFileLineEnding)
             }
+            PaletteKind::ReopenWithEncoding => {
+                Some(LapceWorkbenchCommand::ReopenWithEncoding)
+            }
+            PaletteKind::SaveWithEncoding => {
+                Some(LapceWorkbenchCommand::SaveWithEncoding)
+            }
+            PaletteKind::SurroundWith => Some(LapceWorkbenchCommand::SurroundWith),
             PaletteKind::SCMReferences => {
                 Some(LapceWorkbenchCommand::PaletteSCMReferences)
             }
             PaletteKind::TerminalProfile => None, // InternalCommand::NewTerminal
             PaletteKind::DiffFiles => Some(LapceWorkbenchCommand::DiffFiles),
+            PaletteKind::DirtyFiles => {
+                Some(LapceWorkbenchCommand::PaletteDirtyFiles)
+            }
+            PaletteKind::LocalHistory => {
+                Some(LapceWorkbenchCommand::PaletteLocalHistory)
+            }
+            PaletteKind::Macro => Some(LapceWorkbenchCommand::PaletteMacros),
+            PaletteKind::FileTemplate => None, // LapceWorkbenchCommand::NewFileFromTemplate
+            PaletteKind::Window => Some(LapceWorkbenchCommand::SwitchWindow),
+            PaletteKind::Anything => Some(LapceWorkbenchCommand::PaletteAnything),
+            PaletteKind::TerminalLayout => {
+                Some(LapceWorkbenchCommand::RestoreTerminalLayout)
+            }
+            PaletteKind::SaveTerminalLayout => {
+                Some(LapceWorkbenchCommand::SaveTerminalLayout)
+            }
+            PaletteKind::Bookmark => Some(LapceWorkbenchCommand::PaletteBookmarks),
+            PaletteKind::BookmarkLabel => {
+                Some(LapceWorkbenchCommand::AddLabeledBookmark)
+            }
+            PaletteKind::Task => Some(LapceWorkbenchCommand::PaletteTasks),
         }
     }
 
@@ -129,8 +185,22 @@ pub fn get_input<'a>(&self, input: &'a str) -> &'a str {
             | PaletteKind::IconTheme
             | PaletteKind::Language
             | PaletteKind::LineEnding
+            | PaletteKind::ReopenWithEncoding
+            | PaletteKind::SaveWithEncoding
+            | PaletteKind::SurroundWith
             | PaletteKind::SCMReferences | PaletteKind::HelpAndFile
-            | PaletteKind::DiffFiles => input,
+            | PaletteKind::DiffFiles
+            | PaletteKind::DirtyFiles
+            | PaletteKind::LocalHistory
+            | PaletteKind::Macro
+            | PaletteKind::FileTemplate
+            | PaletteKind::Window
+            | PaletteKind::Anything
+            | PaletteKind::TerminalLayout
+            | PaletteKind::SaveTerminalLayout
+            | PaletteKind::Bookmark
+            | PaletteKind::BookmarkLabel
+            | PaletteKind::Task => input,
             PaletteKind::PaletteHelp
             | PaletteKind::Command
             | PaletteKind::Workspace
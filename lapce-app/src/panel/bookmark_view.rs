@@ -0,0 +1,105 @@
+use std::rc::Rc;
+
+use floem::{
+    reactive::{SignalGet, SignalUpdate},
+    style::CursorStyle,
+    views::{container, dyn_stack, label, scroll, stack, Decorators},
+    View,
+};
+
+use super::position::PanelPosition;
+use crate::{
+    app::clickable_icon,
+    command::InternalCommand,
+    config::{color::LapceColor, icon::LapceIcons},
+    editor::location::{EditorLocation, EditorPosition},
+    window_tab::WindowTabData,
+};
+
+pub fn bookmark_panel(
+    window_tab_data: Rc<WindowTabData>,
+    _position: PanelPosition,
+) -> impl View {
+    let config = window_tab_data.common.config;
+    let ui_line_height = window_tab_data.common.ui_line_height;
+    let bookmarks = window_tab_data.common.bookmarks;
+    let internal_command = window_tab_data.common.internal_command;
+    let workspace = window_tab_data.workspace.clone();
+
+    container(
+        scroll(
+            dyn_stack(
+                move || bookmarks.get().all(),
+                |(path, bookmark)| (path.clone(), bookmark.clone()),
+                move |(path, bookmark)| {
+                    let line = bookmark.line;
+                    let location = format!(
+                        "{}:{}",
+                        workspace
+                            .path
+                            .as_ref()
+                            .and_then(|root| path.strip_prefix(root).ok())
+                            .unwrap_or(&path)
+                            .display(),
+                        line + 1
+                    );
+                    let text = bookmark
+                        .label
+                        .unwrap_or_else(|| format!("Line {}", line + 1));
+                    let remove_path = path.clone();
+                    stack((
+                        label(move || text.clone())
+                            .style(|s| s.margin_right(10.0).selectable(false)),
+                        label(move || location.clone()).style(move |s| {
+                            s.color(config.get().color(LapceColor::EDITOR_DIM))
+                                .min_width(0.0)
+                                .flex_grow(1.0)
+                                .flex_basis(0.0)
+                                .selectable(false)
+                        }),
+                        clickable_icon(
+                            || LapceIcons::CLOSE,
+                            move || {
+                                bookmarks.update(|bookmarks| {
+                                    bookmarks.remove(&remove_path, line)
+                                });
+                            },
+                            || false,
+                            || false,
+                            || "Remove Bookmark",
+                            config,
+                        ),
+                    ))
+                    .on_click_stop(move |_| {
+                        internal_command.send(InternalCommand::JumpToLocation {
+                            location: EditorLocation {
+                                path: path.clone(),
+                                position: Some(EditorPosition::Line(line)),
+                                scroll_offset: None,
+                                ignore_unconfirmed: false,
+                                same_editor_tab: false,
+                            },
+                        });
+                    })
+                    .style(move |s| {
+                        s.padding_horiz(10.0)
+                            .min_width_full()
+                            .items_center()
+                            .height(ui_line_height.get())
+                            .hover(|s| {
+                                s.cursor(CursorStyle::Pointer).background(
+                                    config
+                                        .get()
+                                        .color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                                )
+                            })
+                    })
+                },
+            )
+            .style(|s| s.flex_col().min_width_full()),
+        )
+        .style(|s| s.absolute().size_full()),
+    )
+    .style(|s| s.size_full())
+    .debug_name("Bookmarks Panel")
+}
@@ -39,12 +39,18 @@ pub fn default_panel_order() -> PanelOrder {
             PanelKind::Problem,
             PanelKind::CallHierarchy,
             PanelKind::References,
-            PanelKind::Implementation
+            PanelKind::Implementation,
+            PanelKind::Output,
+            PanelKind::Task
         ],
     );
     order.insert(
         PanelPosition::RightTop,
-        im::vector![PanelKind::DocumentSymbol,],
+        im::vector![
+            PanelKind::DocumentSymbol,
+            PanelKind::UndoTree,
+            PanelKind::Bookmarks
+        ],
     );
 
     order
@@ -56,13 +62,16 @@ pub enum PanelSection {
     FileExplorer,
     Error,
     Warn,
+    Info,
     Changes,
     Installed,
     Available,
     Process,
     Variable,
+    Watch,
     StackFrame,
     Breakpoint,
+    DebugConsole,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -335,6 +344,18 @@ pub fn toggle_maximize(&self, kind: &PanelKind) {
         }
     }
 
+    /// Grow or shrink the bottom panel container by `delta`, if the panel of
+    /// `kind` lives there.
+    pub fn resize_bottom(&self, kind: &PanelKind, delta: f64) {
+        if let Some((_, p)) = self.panel_position(kind) {
+            if p.is_bottom() && !self.panel_bottom_maximized(false) {
+                self.size.update(|size| {
+                    size.bottom = (size.bottom + delta).max(100.0);
+                });
+            }
+        }
+    }
+
     pub fn toggle_bottom_maximize(&self) {
         let maximized = !self.panel_bottom_maximized(false);
         self.styles.update(|styles| {
@@ -4,13 +4,14 @@
     event::EventListener,
     peniko::Color,
     reactive::{
-        create_rw_signal, ReadSignal, RwSignal, SignalGet, SignalUpdate, SignalWith,
+        create_memo, create_rw_signal, ReadSignal, RwSignal, SignalGet,
+        SignalUpdate, SignalWith,
     },
     style::CursorStyle,
     text::Style as FontStyle,
     views::{
-        container, dyn_stack, label, scroll, stack, svg, text, virtual_stack,
-        Decorators, VirtualDirection, VirtualItemSize,
+        container, dyn_container, dyn_stack, label, scroll, stack, svg, text,
+        virtual_stack, Decorators, VirtualDirection, VirtualItemSize,
     },
     View,
 };
@@ -19,17 +20,24 @@
     terminal::TermId,
 };
 
-use super::{data::PanelSection, position::PanelPosition, view::PanelBuilder};
+use super::{
+    data::PanelSection, kind::PanelKind, position::PanelPosition, view::PanelBuilder,
+};
 use crate::{
     app::clickable_icon,
     command::InternalCommand,
     config::{color::LapceColor, icon::LapceIcons, LapceConfig},
-    debug::{DapVariable, RunDebugMode, StackTraceData},
+    debug::{
+        ConsoleEntry, DapData, DapVariable, RunDebugMode, ScopeOrVar,
+        StackTraceData, VariableTree,
+    },
+    debug_console::{DebugInput, EditingVariable},
     editor::location::{EditorLocation, EditorPosition},
     listener::Listener,
     settings::checkbox,
     terminal::panel::TerminalPanelData,
-    window_tab::WindowTabData,
+    text_input::TextInputBuilder,
+    window_tab::{Focus, WindowTabData},
 };
 
 pub fn debug_panel(
@@ -49,9 +57,14 @@ pub fn debug_panel(
         )
         .add(
             "Variables",
-            variables_view(window_tab_data.clone()),
+            variables_view(window_tab_data.clone(), VariableTree::Variables),
             window_tab_data.panel.section_open(PanelSection::Variable),
         )
+        .add(
+            "Watch",
+            watch_view(window_tab_data.clone()),
+            window_tab_data.panel.section_open(PanelSection::Watch),
+        )
         .add(
             "Stack Frames",
             debug_stack_traces(terminal.clone(), internal_command, config),
@@ -63,6 +76,13 @@ pub fn debug_panel(
             breakpoints_view(window_tab_data.clone()),
             window_tab_data.panel.section_open(PanelSection::Breakpoint),
         )
+        .add(
+            "Console",
+            debug_console_view(window_tab_data.clone()),
+            window_tab_data
+                .panel
+                .section_open(PanelSection::DebugConsole),
+        )
         .build()
         .debug_name("Debug Panel")
 }
@@ -330,34 +350,70 @@ fn debug_processes(
     })
 }
 
-fn variables_view(window_tab_data: Rc<WindowTabData>) -> impl View {
+/// The debug session of the active process while it's paused.
+fn paused_dap(terminal: &TerminalPanelData) -> Option<DapData> {
+    let dap = terminal.get_active_dap(true)?;
+    if !dap.stopped.get() {
+        return None;
+    }
+    let process_stopped = terminal
+        .get_terminal(&dap.term_id)
+        .and_then(|t| t.run_debug.with(|r| r.as_ref().map(|r| r.stopped)))
+        .unwrap_or(true);
+    (!process_stopped).then_some(dap)
+}
+
+fn watch_view(window_tab_data: Rc<WindowTabData>) -> impl View {
+    let debug_console = window_tab_data.debug_console.clone();
+    let focused_input = debug_console.focused_input;
+    let focus = window_tab_data.common.focus;
+    let config = window_tab_data.common.config;
+    stack((
+        TextInputBuilder::new()
+            .is_focused(move || {
+                focus.get() == Focus::Panel(PanelKind::Debug)
+                    && focused_input.get() == DebugInput::Watch
+            })
+            .build_editor(debug_console.watch_editor.clone())
+            .placeholder(|| "Add an expression to watch".to_string())
+            .on_event_cont(EventListener::PointerDown, move |_| {
+                focus.set(Focus::Panel(PanelKind::Debug));
+                focused_input.set(DebugInput::Watch);
+            })
+            .style(move |s| {
+                s.width_full()
+                    .border_bottom(1.0)
+                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+            }),
+        variables_view(window_tab_data.clone(), VariableTree::Watch),
+    ))
+    .style(|s| s.width_full().flex_col().flex_grow(1.0).flex_basis(0.0))
+}
+
+fn variables_view(
+    window_tab_data: Rc<WindowTabData>,
+    tree: VariableTree,
+) -> impl View {
     let terminal = window_tab_data.terminal.clone();
     let local_terminal = window_tab_data.terminal.clone();
+    let debug_console = window_tab_data.debug_console.clone();
+    let watches = window_tab_data.terminal.debug.watches;
     let ui_line_height = window_tab_data.common.ui_line_height;
     let config = window_tab_data.common.config;
+    let focus = window_tab_data.common.focus;
     container(
         scroll(
             virtual_stack(
                 VirtualDirection::Vertical,
                 VirtualItemSize::Fixed(Box::new(move || ui_line_height.get())),
-                move || {
-                    let dap = terminal.get_active_dap(true);
-                    dap.map(|dap| {
-                        if !dap.stopped.get() {
-                            return DapVariable::default();
-                        }
-                        let process_stopped = terminal
-                            .get_terminal(&dap.term_id)
-                            .and_then(|t| {
-                                t.run_debug.with(|r| r.as_ref().map(|r| r.stopped))
-                            })
-                            .unwrap_or(true);
-                        if process_stopped {
-                            return DapVariable::default();
-                        }
-                        dap.variables.get()
-                    })
-                    .unwrap_or_default()
+                move || match (paused_dap(&terminal), tree) {
+                    (Some(dap), tree) => dap.tree(tree).get(),
+                    // Listing the expressions while there's nothing to
+                    // evaluate them in
+                    (None, VariableTree::Watch) => {
+                        DapVariable::watch_root(&watches.get())
+                    }
+                    (None, VariableTree::Variables) => DapVariable::default(),
                 },
                 |node| {
                     (
@@ -370,11 +426,35 @@ fn variables_view(window_tab_data: Rc<WindowTabData>) -> impl View {
                 },
                 move |node| {
                     let local_terminal = local_terminal.clone();
+                    let debug_console = debug_console.clone();
                     let level = node.level;
                     let reference = node.item.reference();
-                    let name = node.item.name();
+                    let name = node.item.name().to_string();
                     let ty = node.item.ty();
                     let type_exists = ty.map(|ty| !ty.is_empty()).unwrap_or(false);
+                    let value = node.item.value().unwrap_or("").to_string();
+                    let hide_value = reference > 0 || value.is_empty();
+                    let parent = node.parent.clone();
+                    // The watched expressions themselves can't be set, only
+                    // the variables of a scope or the children of a value
+                    let editable = matches!(node.item, ScopeOrVar::Var(_))
+                        && !parent.is_empty();
+                    let is_watch = tree == VariableTree::Watch && parent.is_empty();
+                    let editing = EditingVariable {
+                        tree,
+                        parent: parent.clone(),
+                        name: name.clone(),
+                    };
+                    let is_editing = {
+                        let editing = editing.clone();
+                        let editing_variable = debug_console.editing_variable;
+                        move || {
+                            editing_variable
+                                .with(|variable| variable.as_ref() == Some(&editing))
+                        }
+                    };
+                    let focused_input = debug_console.focused_input;
+                    let variable_editor = debug_console.variable_editor.clone();
                     stack((
                         svg(move || {
                             let config = config.get();
@@ -395,7 +475,7 @@ fn variables_view(window_tab_data: Rc<WindowTabData>) -> impl View {
                             };
                             s.size(size, size).margin_left(10.0).color(color)
                         }),
-                        text(name),
+                        text(name.clone()),
                         text(": ").style(move |s| {
                             s.apply_if(!type_exists || reference == 0, |s| s.hide())
                         }),
@@ -405,29 +485,76 @@ fn variables_view(window_tab_data: Rc<WindowTabData>) -> impl View {
                                     s.hide()
                                 })
                         }),
-                        text(format!(" = {}", node.item.value().unwrap_or("")))
-                            .style(move |s| s.apply_if(reference > 0, |s| s.hide())),
+                        dyn_container(is_editing, {
+                            let value = value.clone();
+                            move |is_editing| {
+                                if is_editing {
+                                    TextInputBuilder::new()
+                                        .is_focused(move || {
+                                            focus.get()
+                                                == Focus::Panel(PanelKind::Debug)
+                                                && focused_input.get()
+                                                    == DebugInput::Variable
+                                        })
+                                        .build_editor(variable_editor.clone())
+                                        .style(|s| {
+                                            s.margin_left(6.0)
+                                                .min_width(100.0)
+                                                .flex_grow(1.0)
+                                        })
+                                        .into_any()
+                                } else {
+                                    text(format!(" = {value}"))
+                                        .style(move |s| {
+                                            s.apply_if(hide_value, |s| s.hide())
+                                        })
+                                        .into_any()
+                                }
+                            }
+                        }),
+                        {
+                            let debug_console = debug_console.clone();
+                            let name = name.clone();
+                            clickable_icon(
+                                || LapceIcons::CLOSE,
+                                move || {
+                                    let index = watches.with_untracked(|watches| {
+                                        watches.index_of(&name)
+                                    });
+                                    if let Some(index) = index {
+                                        debug_console.remove_watch(index);
+                                    }
+                                },
+                                || false,
+                                || false,
+                                || "Remove",
+                                config,
+                            )
+                            .style(move |s| {
+                                s.margin_left(6.0).apply_if(!is_watch, |s| s.hide())
+                            })
+                        },
                     ))
-                    .on_click_stop(move |_| {
-                        if reference > 0 {
-                            let dap = local_terminal.get_active_dap(false);
-                            if let Some(dap) = dap {
-                                let process_stopped = local_terminal
-                                    .get_terminal(&dap.term_id)
-                                    .and_then(|t| {
-                                        t.run_debug
-                                            .with(|r| r.as_ref().map(|r| r.stopped))
-                                    })
-                                    .unwrap_or(true);
-                                if !process_stopped {
+                    .on_click_stop({
+                        let local_terminal = local_terminal.clone();
+                        let parent = parent.clone();
+                        move |_| {
+                            if reference > 0 {
+                                if let Some(dap) = paused_dap(&local_terminal) {
                                     dap.toggle_expand(
-                                        node.parent.clone(),
+                                        tree,
+                                        parent.clone(),
                                         reference,
                                     );
                                 }
                             }
                         }
                     })
+                    .on_double_click_stop(move |_| {
+                        if editable && paused_dap(&local_terminal).is_some() {
+                            debug_console.edit_variable(editing.clone(), &value);
+                        }
+                    })
                     .style(move |s| {
                         s.items_center()
                             .padding_right(10.0)
@@ -757,3 +884,73 @@ fn breakpoints_view(window_tab_data: Rc<WindowTabData>) -> impl View {
     )
     .style(|s| s.size_pct(100.0, 100.0))
 }
+
+fn debug_console_view(window_tab_data: Rc<WindowTabData>) -> impl View {
+    let debug_console = window_tab_data.debug_console.clone();
+    let editor = debug_console.editor.clone();
+    let config = window_tab_data.common.config;
+    let focus = window_tab_data.common.focus;
+    let focused_input = debug_console.focused_input;
+    let is_focused = move || {
+        focus.get() == Focus::Panel(PanelKind::Debug)
+            && focused_input.get() == DebugInput::Console
+    };
+    let console = create_memo(move |_| debug_console.console());
+    let content_height = create_rw_signal(0.0);
+
+    stack((
+        container(
+            scroll(
+                dyn_stack(
+                    move || console.get(),
+                    |(id, _)| *id,
+                    move |(_, entry)| console_entry_view(entry, config),
+                )
+                .on_resize(move |rect| {
+                    content_height.set(rect.height());
+                })
+                .style(|s| s.flex_col().min_width_full()),
+            )
+            .style(|s| s.absolute().size_full())
+            // Following the output as it's printed
+            .scroll_to(move || Some((0.0, content_height.get()).into())),
+        )
+        .style(|s| s.width_full().flex_grow(1.0).flex_basis(0.0)),
+        TextInputBuilder::new()
+            .is_focused(is_focused)
+            .build_editor(editor)
+            .placeholder(|| "Evaluate an expression".to_string())
+            .on_event_cont(EventListener::PointerDown, move |_| {
+                focus.set(Focus::Panel(PanelKind::Debug));
+                focused_input.set(DebugInput::Console);
+            })
+            .style(move |s| {
+                s.width_full()
+                    .border_top(1.0)
+                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+            }),
+    ))
+    .style(|s| {
+        s.width_full()
+            .flex_col()
+            .line_height(1.6)
+            .flex_grow(1.0)
+            .flex_basis(0.0)
+    })
+}
+
+fn console_entry_view(
+    entry: ConsoleEntry,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let is_input = matches!(entry, ConsoleEntry::Input(_));
+    let is_error = entry.is_error();
+    let text = entry.text();
+    label(move || text.clone()).style(move |s| {
+        let config = config.get();
+        s.padding_horiz(10.0)
+            .font_family(config.editor.font_family.clone())
+            .apply_if(is_input, |s| s.color(config.color(LapceColor::EDITOR_DIM)))
+            .apply_if(is_error, |s| s.color(config.color(LapceColor::LAPCE_ERROR)))
+    })
+}
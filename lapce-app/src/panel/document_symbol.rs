@@ -1,23 +1,43 @@
-use std::{ops::AddAssign, path::PathBuf, rc::Rc};
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    rc::Rc,
+};
 
 use floem::{
+    event::EventListener,
+    keyboard::Modifiers,
+    kurbo::{Point, Size},
     peniko::Color,
-    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+    reactive::{
+        create_memo, Memo, RwSignal, Scope, SignalGet, SignalUpdate, SignalWith,
+    },
     style::CursorStyle,
     views::{
         container, editor::id::Id, label, scroll, stack, svg, virtual_stack,
-        Decorators, VirtualDirection, VirtualItemSize, VirtualVector,
+        Decorators, VirtualDirection, VirtualItemSize,
     },
     View,
 };
-use lsp_types::{DocumentSymbol, SymbolKind};
+use lapce_core::{
+    command::{EditCommand, FocusCommand},
+    mode::Mode,
+};
+use lsp_types::{DocumentSymbol, Position, Range, SymbolKind};
 
-use super::position::PanelPosition;
+use super::{kind::PanelKind, position::PanelPosition};
 use crate::{
-    command::InternalCommand,
+    command::{CommandExecuted, CommandKind, InternalCommand, LapceCommand},
     config::{color::LapceColor, icon::LapceIcons},
-    editor::location::EditorLocation,
-    window_tab::WindowTabData,
+    editor::{
+        location::{EditorLocation, EditorPosition},
+        EditorData,
+    },
+    keypress::{condition::Condition, KeyPressFocus},
+    main_split::MainSplitData,
+    text_input::TextInputBuilder,
+    window_tab::{CommonData, Focus, WindowTabData},
 };
 
 #[derive(Clone, Debug)]
@@ -58,20 +78,66 @@ pub fn new(
         });
         Self { path, file }
     }
-    fn get_children(
-        &self,
-        min: usize,
-        max: usize,
-    ) -> Vec<(
-        usize,
-        usize,
-        Rc<PathBuf>,
-        RwSignal<SymbolInformationItemData>,
-    )> {
-        let path = Rc::new(self.path.clone());
-        let level: usize = 0;
-        let mut next = 0;
-        get_children(self.file, &mut next, min, max, level, path.clone())
+
+    /// The chain of symbols containing `position`, from the outermost, as
+    /// shown in the breadcrumbs of the editor.
+    pub fn breadcrumbs(&self, position: Position) -> Vec<BreadcrumbSymbol> {
+        let mut crumbs = Vec::new();
+        let mut children = self.file.with_untracked(|f| f.children.clone());
+        while let Some(child) = children.iter().find(|c| {
+            c.with_untracked(|c| {
+                c.item.range.start <= position && position < c.item.range.end
+            })
+        }) {
+            let siblings = children
+                .iter()
+                .map(|c| {
+                    c.with_untracked(|c| {
+                        (c.name.clone(), c.item.kind, c.item.selection_range.start)
+                    })
+                })
+                .collect();
+            let (name, kind, range, grandchildren) = child.with_untracked(|c| {
+                (
+                    c.name.clone(),
+                    c.item.kind,
+                    c.item.range,
+                    c.children.clone(),
+                )
+            });
+            crumbs.push(BreadcrumbSymbol {
+                name,
+                kind,
+                range,
+                siblings,
+            });
+            children = grandchildren;
+        }
+        crumbs
+    }
+}
+
+/// A symbol of the breadcrumbs of the editor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreadcrumbSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    pub range: Range,
+    /// The names, kinds and positions of the symbols with the same parent,
+    /// this one included, to navigate between.
+    pub siblings: Vec<(String, SymbolKind, Position)>,
+}
+
+/// `SymbolKind` isn't `Hash`, so the kinds are left out, which only makes
+/// symbols differing by their kinds alone share a hash.
+impl Hash for BreadcrumbSymbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.range.hash(state);
+        for (name, _, position) in &self.siblings {
+            name.hash(state);
+            position.hash(state);
+        }
     }
 }
 
@@ -106,218 +172,475 @@ fn from((mut item, cx): (DocumentSymbol, Scope)) -> Self {
     }
 }
 
-impl SymbolInformationItemData {
-    pub fn child_count(&self) -> usize {
-        let mut count = 1;
-        if self.open.get() {
-            for child in &self.children {
-                count += child.with(|x| x.child_count())
-            }
+/// How the symbols are ordered among their siblings in the outline.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutlineSort {
+    #[default]
+    Position,
+    Name,
+    Kind,
+}
+
+impl OutlineSort {
+    fn next(self) -> Self {
+        match self {
+            OutlineSort::Position => OutlineSort::Name,
+            OutlineSort::Name => OutlineSort::Kind,
+            OutlineSort::Kind => OutlineSort::Position,
         }
-        count
     }
-}
 
-fn get_children(
-    data: RwSignal<SymbolInformationItemData>,
-    next: &mut usize,
-    min: usize,
-    max: usize,
-    level: usize,
-    path: Rc<PathBuf>,
-) -> Vec<(
-    usize,
-    usize,
-    Rc<PathBuf>,
-    RwSignal<SymbolInformationItemData>,
-)> {
-    let mut children = Vec::new();
-    if *next >= min && *next < max {
-        children.push((*next, level, path.clone(), data));
-    } else if *next >= max {
-        return children;
+    fn label(self) -> &'static str {
+        match self {
+            OutlineSort::Position => "Position",
+            OutlineSort::Name => "Name",
+            OutlineSort::Kind => "Kind",
+        }
     }
-    next.add_assign(1);
-    if data.get_untracked().open.get() {
-        for child in data.get().children {
-            let child_children =
-                get_children(child, next, min, max, level + 1, path.clone());
-            children.extend(child_children);
-            if *next > max {
-                break;
-            }
+
+    fn compare(
+        self,
+        a: &SymbolInformationItemData,
+        b: &SymbolInformationItemData,
+    ) -> Ordering {
+        let position = a.item.range.start.cmp(&b.item.range.start);
+        match self {
+            OutlineSort::Position => position,
+            OutlineSort::Name => a
+                .name
+                .to_lowercase()
+                .cmp(&b.name.to_lowercase())
+                .then(position),
+            OutlineSort::Kind => kind_rank(a.item.kind)
+                .cmp(&kind_rank(b.item.kind))
+                .then_with(|| OutlineSort::Name.compare(a, b)),
         }
     }
-    children
+
+    fn sorted(
+        self,
+        children: &[RwSignal<SymbolInformationItemData>],
+    ) -> Vec<RwSignal<SymbolInformationItemData>> {
+        let mut children = children.to_vec();
+        children.sort_by(|a, b| {
+            a.with_untracked(|a| b.with_untracked(|b| self.compare(a, b)))
+        });
+        children
+    }
 }
 
-pub struct VirtualList {
-    root: Option<RwSignal<Option<SymbolData>>>,
+/// The order of the kinds when sorting by kind, with the types first.
+const KIND_ORDER: &[SymbolKind] = &[
+    SymbolKind::MODULE,
+    SymbolKind::NAMESPACE,
+    SymbolKind::PACKAGE,
+    SymbolKind::CLASS,
+    SymbolKind::STRUCT,
+    SymbolKind::INTERFACE,
+    SymbolKind::ENUM,
+    SymbolKind::TYPE_PARAMETER,
+    SymbolKind::CONSTRUCTOR,
+    SymbolKind::METHOD,
+    SymbolKind::FUNCTION,
+    SymbolKind::OPERATOR,
+    SymbolKind::CONSTANT,
+    SymbolKind::ENUM_MEMBER,
+    SymbolKind::FIELD,
+    SymbolKind::PROPERTY,
+    SymbolKind::EVENT,
+    SymbolKind::VARIABLE,
+];
+
+fn kind_rank(kind: SymbolKind) -> usize {
+    KIND_ORDER
+        .iter()
+        .position(|k| *k == kind)
+        .unwrap_or(KIND_ORDER.len())
 }
 
-impl VirtualList {
-    pub fn new(root: Option<RwSignal<Option<SymbolData>>>) -> Self {
-        Self { root }
+/// A symbol as it is listed in the outline.
+#[derive(Clone)]
+pub struct OutlineRow {
+    pub level: usize,
+    pub path: Rc<PathBuf>,
+    pub item: RwSignal<SymbolInformationItemData>,
+}
+
+/// The state of the outline panel, which lists the symbols of the active
+/// editor.
+#[derive(Clone)]
+pub struct OutlineData {
+    pub filter_editor: EditorData,
+    pub sort: RwSignal<OutlineSort>,
+    /// The symbol selected with the keyboard.
+    pub selected: RwSignal<Option<Id>>,
+    pub main_split: MainSplitData,
+    pub common: Rc<CommonData>,
+}
+
+impl KeyPressFocus for OutlineData {
+    fn get_mode(&self) -> Mode {
+        Mode::Insert
+    }
+
+    fn check_condition(&self, condition: Condition) -> bool {
+        matches!(condition, Condition::PanelFocus | Condition::ListFocus)
+    }
+
+    fn run_command(
+        &self,
+        command: &LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        match &command.kind {
+            CommandKind::Focus(FocusCommand::ListNext) => self.select_next(1),
+            CommandKind::Focus(FocusCommand::ListPrevious) => self.select_next(-1),
+            CommandKind::Focus(FocusCommand::ListSelect) => {
+                let selected = self.selected.get_untracked();
+                if let Some(row) = self
+                    .rows()
+                    .into_iter()
+                    .find(|row| Some(row.item.with_untracked(|i| i.id)) == selected)
+                {
+                    self.jump_to(&row);
+                }
+            }
+            CommandKind::Edit(EditCommand::InsertNewLine) => {}
+            CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MultiSelection(_) => {
+                return self.filter_editor.run_command(command, count, mods);
+            }
+            _ => return CommandExecuted::No,
+        }
+        CommandExecuted::Yes
+    }
+
+    fn receive_char(&self, c: &str) {
+        self.filter_editor.receive_char(c);
     }
 }
 
-impl
-    VirtualVector<(
-        usize,
-        usize,
-        Rc<PathBuf>,
-        RwSignal<SymbolInformationItemData>,
-    )> for VirtualList
-{
-    fn total_len(&self) -> usize {
-        if let Some(root) = self.root.as_ref().and_then(|x| x.get()) {
-            root.file.get_untracked().child_count()
-        } else {
-            0
+impl OutlineData {
+    pub fn new(
+        cx: Scope,
+        main_split: MainSplitData,
+        common: Rc<CommonData>,
+    ) -> Self {
+        Self {
+            filter_editor: main_split.editors.make_local(cx, common.clone()),
+            sort: cx.create_rw_signal(OutlineSort::default()),
+            selected: cx.create_rw_signal(None),
+            main_split,
+            common,
         }
     }
 
-    fn slice(
-        &mut self,
-        range: std::ops::Range<usize>,
-    ) -> impl Iterator<
-        Item = (
-            usize,
-            usize,
-            Rc<PathBuf>,
-            RwSignal<SymbolInformationItemData>,
-        ),
-    > {
-        if let Some(root) = self.root.as_ref().and_then(|x| x.get()) {
-            let min = range.start;
-            let max = range.end;
-            let children = root.get_children(min, max);
-            children.into_iter()
-        } else {
-            Vec::new().into_iter()
+    fn filter(&self) -> String {
+        self.filter_editor
+            .doc_signal()
+            .get()
+            .buffer
+            .with(|buffer| buffer.to_string().to_lowercase())
+    }
+
+    /// The symbols of the active editor, as they are listed in the panel.
+    ///
+    /// While filtering, the symbols matching the filter are listed with all
+    /// their parents, whether those were collapsed or not.
+    pub fn rows(&self) -> im::Vector<OutlineRow> {
+        let Some(editor) = self.main_split.get_active_editor() else {
+            return im::Vector::new();
+        };
+        let Some(symbols) = editor.doc().document_symbol_data.get() else {
+            return im::Vector::new();
+        };
+        let filter = self.filter();
+        let sort = self.sort.get();
+        let path = Rc::new(symbols.path.clone());
+        let mut rows = im::Vector::new();
+        push_rows(&mut rows, &path, symbols.file, 0, &filter, sort);
+        rows
+    }
+
+    /// The innermost symbol listed in the panel that contains the cursor of
+    /// the active editor.
+    pub fn current(&self) -> Option<Id> {
+        let editor = self.main_split.get_active_editor()?;
+        let doc = editor.doc();
+        let symbols = doc.document_symbol_data.get()?;
+        let offset = editor.cursor().with(|c| c.offset());
+        let position = doc.buffer.with(|b| b.offset_to_position(offset));
+        let mut current = None;
+        let mut children = symbols.file.with(|f| f.children.clone());
+        while let Some(child) = children.iter().find(|c| {
+            c.with(|c| c.item.range.start <= position && position < c.item.range.end)
+        }) {
+            let (id, open, grandchildren) =
+                child.with(|c| (c.id, c.open.get(), c.children.clone()));
+            current = Some(id);
+            if !open {
+                break;
+            }
+            children = grandchildren;
+        }
+        current
+    }
+
+    fn select_next(&self, delta: isize) {
+        let rows = self.rows();
+        if rows.is_empty() {
+            return;
         }
+        let selected = self.selected.get_untracked();
+        let index = rows
+            .iter()
+            .position(|row| Some(row.item.with_untracked(|i| i.id)) == selected)
+            .map(|i| (i as isize + delta).rem_euclid(rows.len() as isize) as usize)
+            .unwrap_or(0);
+        self.selected
+            .set(Some(rows[index].item.with_untracked(|i| i.id)));
+    }
+
+    fn jump_to(&self, row: &OutlineRow) {
+        let start = row.item.with_untracked(|i| i.item.selection_range.start);
+        self.common
+            .internal_command
+            .send(InternalCommand::JumpToLocation {
+                location: EditorLocation {
+                    path: row.path.to_path_buf(),
+                    position: Some(EditorPosition::Position(start)),
+                    scroll_offset: None,
+                    ignore_unconfirmed: false,
+                    same_editor_tab: false,
+                },
+            });
     }
 }
 
+fn push_rows(
+    rows: &mut im::Vector<OutlineRow>,
+    path: &Rc<PathBuf>,
+    item: RwSignal<SymbolInformationItemData>,
+    level: usize,
+    filter: &str,
+    sort: OutlineSort,
+) {
+    if !filter.is_empty() && !subtree_matches(item, filter) {
+        return;
+    }
+    rows.push_back(OutlineRow {
+        level,
+        path: path.clone(),
+        item,
+    });
+    let (open, children) = item.with(|i| (i.open.get(), sort.sorted(&i.children)));
+    if open || !filter.is_empty() {
+        for child in children {
+            push_rows(rows, path, child, level + 1, filter, sort);
+        }
+    }
+}
+
+fn subtree_matches(item: RwSignal<SymbolInformationItemData>, filter: &str) -> bool {
+    item.with_untracked(|i| {
+        i.name.to_lowercase().contains(filter)
+            || i.children.iter().any(|c| subtree_matches(*c, filter))
+    })
+}
+
 pub fn symbol_panel(
     window_tab_data: Rc<WindowTabData>,
     _position: PanelPosition,
 ) -> impl View {
-    let config = window_tab_data.common.config;
-    let ui_line_height = window_tab_data.common.ui_line_height;
-    scroll(
-        virtual_stack(
-            VirtualDirection::Vertical,
-            VirtualItemSize::Fixed(Box::new(move || ui_line_height.get())),
-            {
-                let window_tab_data = window_tab_data.clone();
-                move || {
-                    let editor = window_tab_data.main_split.get_active_editor();
-                    VirtualList::new(editor.map(|x| x.doc().document_symbol_data))
-                }
-            },
-            move |(_, _, _, item)| item.get_untracked().id,
-            move |(_, level, path,  rw_data)| {
-                let data = rw_data.get_untracked();
-                let open = data.open;
-                let has_child = !data.children.is_empty();
-                let kind = data.item.kind;
-                stack((
-                    container(
-                        svg(move || {
-                            let config = config.get();
-                            let svg_str = match open.get() {
-                                true => LapceIcons::ITEM_OPENED,
-                                false => LapceIcons::ITEM_CLOSED,
-                            };
-                            config.ui_svg(svg_str)
-                        })
-                        .style(move |s| {
-                            let config = config.get();
-                            let color = if has_child {
-                                config.color(LapceColor::LAPCE_ICON_ACTIVE)
-                            } else {
-                                Color::TRANSPARENT
-                            };
-                            let size = config.ui.icon_size() as f32;
-                            s.size(size, size)
-                                 .color(color)
-                        })
-                    ).style(|s| s.padding(4.0).margin_left(6.0).margin_right(2.0))
-                    .on_click_stop({
-                        move |_x| {
-                            if has_child {
-                                open.update(|x| {
-                                    *x = !*x;
-                                });
-                            }
-                        }
-                    }),
-                    svg(move || {
-                        let config = config.get();
-                        config
-                            .symbol_svg(&kind)
-                            .unwrap_or_else(|| config.ui_svg(LapceIcons::FILE))
-                    }).style(move |s| {
-                            let config = config.get();
-                            let size = config.ui.icon_size() as f32;
-                            s.min_width(size)
-                                .size(size, size)
-                                .margin_right(5.0)
-                                .color(config.symbol_color(&kind).unwrap_or_else(|| {
-                                    config.color(LapceColor::LAPCE_ICON_ACTIVE)
-                                }))
-                        }),
-                    label(move || {
-                            data.name.replace('\n', "↵")
-                    })
-                    .style(move |s| {
-                        s.selectable(false)
+    let outline = window_tab_data.outline.clone();
+    let config = outline.common.config;
+    let ui_line_height = outline.common.ui_line_height;
+    let focus = outline.common.focus;
+    let sort = outline.sort;
+    let selected = outline.selected;
+    let is_focused = move || focus.get() == Focus::Panel(PanelKind::DocumentSymbol);
+
+    // Follow the cursor of the active editor
+    let current = create_memo({
+        let outline = outline.clone();
+        move |_| outline.current()
+    });
+
+    stack((
+        stack((
+            container(
+                TextInputBuilder::new()
+                    .is_focused(is_focused)
+                    .build_editor(outline.filter_editor.clone())
+                    .placeholder(|| "Filter symbols".to_string())
+                    .style(|s| {
+                        s.width_pct(100.0).padding_vert(4.0).padding_horiz(6.0)
                     }),
-                    label(move || {
-                        data.detail.clone().unwrap_or_default()
-                    }).style(move |s| s.margin_left(6.0)
-                                              .color(config.get().color(LapceColor::EDITOR_DIM))
-                                              .selectable(false)
-                                              .apply_if(
-                                                data.item.detail.clone().is_none(),
-                                                 |s| s.hide())
-                    ),
-                ))
+            )
+            .on_event_cont(EventListener::PointerDown, move |_| {
+                focus.set(Focus::Panel(PanelKind::DocumentSymbol));
+            })
+            .style(move |s| {
+                s.flex_grow(1.0)
+                    .min_width(0.0)
+                    .cursor(CursorStyle::Text)
+                    .border(1.0)
+                    .border_radius(6.0)
+                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+            }),
+            label(move || format!("Sort: {}", sort.get().label()))
+                .on_click_stop(move |_| {
+                    sort.update(|sort| *sort = sort.next());
+                })
                 .style(move |s| {
-                    s.padding_right(5.0)
-                        .padding_left((level * 10) as f32)
-                        .items_center()
-                        .height(ui_line_height.get())
+                    let config = config.get();
+                    s.margin_left(6.0)
+                        .padding_vert(4.0)
+                        .padding_horiz(6.0)
+                        .border_radius(6.0)
+                        .selectable(false)
+                        .cursor(CursorStyle::Pointer)
                         .hover(|s| {
                             s.background(
-                                config
-                                    .get()
-                                    .color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                                config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
                             )
-                            .cursor(CursorStyle::Pointer)
                         })
-                })
-                .on_click_stop({
-                    let window_tab_data = window_tab_data.clone();
-                    let data = rw_data;
-                    move |_| {
-                        let data = data.get_untracked();
-                            window_tab_data
-                                .common
-                                .internal_command
-                                .send(InternalCommand::JumpToLocation { location: EditorLocation {
-                                    path: path.to_path_buf(),
-                                    position: Some(crate::editor::location::EditorPosition::Position(data.item.selection_range.start)),
-                                    scroll_offset: None,
-                                    ignore_unconfirmed: false,
-                                    same_editor_tab: false,
-                                } });
-                    }
-                })
-            },
+                }),
+        ))
+        .style(|s| s.width_pct(100.0).padding(10.0).items_center()),
+        container(
+            scroll(
+                virtual_stack(
+                    VirtualDirection::Vertical,
+                    VirtualItemSize::Fixed(Box::new(move || ui_line_height.get())),
+                    {
+                        let outline = outline.clone();
+                        move || outline.rows()
+                    },
+                    move |row| row.item.get_untracked().id,
+                    move |row| outline_row(outline.clone(), row, current),
+                )
+                .style(|s| s.flex_col().absolute().min_width_full()),
+            )
+            .ensure_visible({
+                let outline = outline.clone();
+                move || {
+                    let target = if is_focused() {
+                        selected.get().or_else(|| current.get())
+                    } else {
+                        current.get()
+                    };
+                    let index = outline
+                        .rows()
+                        .iter()
+                        .position(|row| {
+                            Some(row.item.with_untracked(|i| i.id)) == target
+                        })
+                        .unwrap_or(0);
+                    let line_height = ui_line_height.get();
+                    Size::new(1.0, line_height)
+                        .to_rect()
+                        .with_origin(Point::new(0.0, index as f64 * line_height))
+                }
+            })
+            .style(|s| s.absolute().size_full()),
         )
-        .style(|s| s.flex_col().absolute().min_width_full()),
-    )
-    .style(|s| s.absolute().size_full())
+        .style(|s| s.width_pct(100.0).flex_grow(1.0).flex_basis(0.0)),
+    ))
+    .style(|s| s.absolute().size_full().flex_col())
+    .debug_name("Outline Panel")
+}
+
+fn outline_row(
+    outline: OutlineData,
+    row: OutlineRow,
+    current: Memo<Option<Id>>,
+) -> impl View {
+    let config = outline.common.config;
+    let ui_line_height = outline.common.ui_line_height;
+    let selected = outline.selected;
+    let data = row.item.get_untracked();
+    let id = data.id;
+    let open = data.open;
+    let has_child = !data.children.is_empty();
+    let kind = data.item.kind;
+    let has_detail = data.detail.is_some();
+    let level = row.level;
+    stack((
+        container(
+            svg(move || {
+                let config = config.get();
+                let svg_str = match open.get() {
+                    true => LapceIcons::ITEM_OPENED,
+                    false => LapceIcons::ITEM_CLOSED,
+                };
+                config.ui_svg(svg_str)
+            })
+            .style(move |s| {
+                let config = config.get();
+                let color = if has_child {
+                    config.color(LapceColor::LAPCE_ICON_ACTIVE)
+                } else {
+                    Color::TRANSPARENT
+                };
+                let size = config.ui.icon_size() as f32;
+                s.size(size, size).color(color)
+            }),
+        )
+        .style(|s| s.padding(4.0).margin_left(6.0).margin_right(2.0))
+        .on_click_stop(move |_| {
+            if has_child {
+                open.update(|open| *open = !*open);
+            }
+        }),
+        svg(move || {
+            let config = config.get();
+            config
+                .symbol_svg(&kind)
+                .unwrap_or_else(|| config.ui_svg(LapceIcons::FILE))
+        })
+        .style(move |s| {
+            let config = config.get();
+            let size = config.ui.icon_size() as f32;
+            s.min_width(size).size(size, size).margin_right(5.0).color(
+                config
+                    .symbol_color(&kind)
+                    .unwrap_or_else(|| config.color(LapceColor::LAPCE_ICON_ACTIVE)),
+            )
+        }),
+        label({
+            let name = data.name.replace('\n', "↵");
+            move || name.clone()
+        })
+        .style(|s| s.selectable(false)),
+        label(move || data.detail.clone().unwrap_or_default()).style(move |s| {
+            s.margin_left(6.0)
+                .color(config.get().color(LapceColor::EDITOR_DIM))
+                .selectable(false)
+                .apply_if(!has_detail, |s| s.hide())
+        }),
+    ))
+    .style(move |s| {
+        let config = config.get();
+        s.padding_right(5.0)
+            .padding_left((level * 10) as f32)
+            .items_center()
+            .height(ui_line_height.get())
+            .apply_if(current.get() == Some(id), |s| {
+                s.background(config.color(LapceColor::PANEL_CURRENT_BACKGROUND))
+            })
+            .apply_if(selected.get() == Some(id), |s| {
+                s.background(config.color(LapceColor::PANEL_HOVERED_BACKGROUND))
+            })
+            .hover(|s| {
+                s.background(config.color(LapceColor::PANEL_HOVERED_BACKGROUND))
+                    .cursor(CursorStyle::Pointer)
+            })
+    })
+    .on_click_stop(move |_| {
+        selected.set(Some(id));
+        outline.jump_to(&row);
+    })
 }
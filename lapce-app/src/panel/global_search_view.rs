@@ -2,14 +2,16 @@
 
 use floem::{
     event::EventListener,
-    reactive::{ReadSignal, SignalGet, SignalUpdate},
+    reactive::{ReadSignal, SignalGet, SignalUpdate, SignalWith},
     style::{CursorStyle, Style},
+    text::{Attrs, AttrsList, TextLayout},
     views::{
-        container, label, scroll, stack, svg, virtual_stack, Decorators,
-        VirtualDirection, VirtualItemSize,
+        container, empty, label, rich_text, scroll, stack, svg, virtual_stack,
+        Decorators, VirtualDirection, VirtualItemSize,
     },
     View,
 };
+use lapce_rpc::proxy::SearchMatch;
 use lapce_xi_rope::find::CaseMatching;
 
 use super::{kind::PanelKind, position::PanelPosition};
@@ -17,9 +19,13 @@
     app::clickable_icon,
     command::InternalCommand,
     config::{color::LapceColor, icon::LapceIcons, LapceConfig},
-    editor::location::{EditorLocation, EditorPosition},
+    editor::{
+        location::{EditorLocation, EditorPosition},
+        EditorData,
+    },
+    find::FindSearchString,
     focus_text::focus_text,
-    global_search::{GlobalSearchData, SearchMatchData},
+    global_search::{GlobalSearchData, SearchInput, SearchMatchData},
     listener::Listener,
     text_input::TextInputBuilder,
     window_tab::{Focus, WindowTabData},
@@ -38,78 +44,262 @@ pub fn global_search_panel(
     let case_matching = global_search.common.find.case_matching;
     let whole_word = global_search.common.find.whole_words;
     let is_regex = global_search.common.find.is_regex;
+    let replace_active = global_search.replace_active;
+    let focused_input = global_search.focused_input;
 
     let focus = global_search.common.focus;
     let is_focused = move || focus.get() == Focus::Panel(PanelKind::Search);
 
+    let search_input = stack((
+        TextInputBuilder::new()
+            .is_focused(move || {
+                is_focused() && focused_input.get() == SearchInput::Search
+            })
+            .build_editor(editor.clone())
+            .style(|s| s.width_pct(100.0)),
+        clickable_icon(
+            || LapceIcons::SEARCH_CASE_SENSITIVE,
+            move || {
+                let new = match case_matching.get_untracked() {
+                    CaseMatching::Exact => CaseMatching::CaseInsensitive,
+                    CaseMatching::CaseInsensitive => CaseMatching::Exact,
+                };
+                case_matching.set(new);
+            },
+            move || case_matching.get() == CaseMatching::Exact,
+            || false,
+            || "Case Sensitive",
+            config,
+        )
+        .style(|s| s.padding_vert(4.0)),
+        clickable_icon(
+            || LapceIcons::SEARCH_WHOLE_WORD,
+            move || {
+                whole_word.update(|whole_word| {
+                    *whole_word = !*whole_word;
+                });
+            },
+            move || whole_word.get(),
+            || false,
+            || "Whole Word",
+            config,
+        )
+        .style(|s| s.padding_left(6.0)),
+        clickable_icon(
+            || LapceIcons::SEARCH_REGEX,
+            move || {
+                is_regex.update(|is_regex| {
+                    *is_regex = !*is_regex;
+                });
+            },
+            move || is_regex.get(),
+            || false,
+            || "Use Regex",
+            config,
+        )
+        .style(|s| s.padding_left(6.0)),
+    ))
+    .on_event_cont(EventListener::PointerDown, move |_| {
+        focus.set(Focus::Panel(PanelKind::Search));
+        focused_input.set(SearchInput::Search);
+    })
+    .style(move |s| {
+        s.width_pct(100.0)
+            .padding_right(6.0)
+            .items_center()
+            .border(1.0)
+            .border_radius(6.0)
+            .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+    });
+
     stack((
-        container(
+        container(stack((
+            clickable_icon(
+                move || {
+                    if replace_active.get() {
+                        LapceIcons::ITEM_OPENED
+                    } else {
+                        LapceIcons::ITEM_CLOSED
+                    }
+                },
+                move || {
+                    replace_active.update(|active| *active = !*active);
+                },
+                || false,
+                || false,
+                || "Toggle Replace",
+                config,
+            )
+            .style(|s| s.margin_right(4.0)),
             stack((
-                TextInputBuilder::new()
-                    .is_focused(is_focused)
-                    .build_editor(editor.clone())
-                    .style(|s| s.width_pct(100.0)),
-                clickable_icon(
-                    || LapceIcons::SEARCH_CASE_SENSITIVE,
-                    move || {
-                        let new = match case_matching.get_untracked() {
-                            CaseMatching::Exact => CaseMatching::CaseInsensitive,
-                            CaseMatching::CaseInsensitive => CaseMatching::Exact,
-                        };
-                        case_matching.set(new);
-                    },
-                    move || case_matching.get() == CaseMatching::Exact,
-                    || false,
-                    || "Case Sensitive",
-                    config,
-                )
-                .style(|s| s.padding_vert(4.0)),
-                clickable_icon(
-                    || LapceIcons::SEARCH_WHOLE_WORD,
-                    move || {
-                        whole_word.update(|whole_word| {
-                            *whole_word = !*whole_word;
-                        });
-                    },
-                    move || whole_word.get(),
-                    || false,
-                    || "Whole Word",
-                    config,
-                )
-                .style(|s| s.padding_left(6.0)),
-                clickable_icon(
-                    || LapceIcons::SEARCH_REGEX,
-                    move || {
-                        is_regex.update(|is_regex| {
-                            *is_regex = !*is_regex;
-                        });
-                    },
-                    move || is_regex.get(),
-                    || false,
-                    || "Use Regex",
-                    config,
-                )
-                .style(|s| s.padding_left(6.0)),
+                search_input,
+                replace_input(global_search.clone(), is_focused),
             ))
-            .on_event_cont(EventListener::PointerDown, move |_| {
-                focus.set(Focus::Panel(PanelKind::Search));
-            })
-            .style(move |s| {
-                s.width_pct(100.0)
-                    .padding_right(6.0)
-                    .items_center()
-                    .border(1.0)
-                    .border_radius(6.0)
-                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
-            }),
-        )
+            .style(|s| s.flex_col().flex_grow(1.0).min_width(0.0)),
+        )))
         .style(|s| s.width_pct(100.0).padding(10.0)),
+        search_filters(global_search.clone(), is_focused),
         search_result(workspace, global_search, internal_command, config),
     ))
     .style(|s| s.absolute().size_pct(100.0, 100.0).flex_col())
     .debug_name("Global Search Panel")
 }
 
+/// The input of the text replacing the matches, with the buttons applying the
+/// replacements.
+fn replace_input(
+    global_search: GlobalSearchData,
+    is_focused: impl Fn() -> bool + 'static + Copy,
+) -> impl View {
+    let config = global_search.common.config;
+    let focus = global_search.common.focus;
+    let preserve_case = global_search.common.find.preserve_case;
+    let replace_active = global_search.replace_active;
+    let focused_input = global_search.focused_input;
+
+    stack((
+        TextInputBuilder::new()
+            .is_focused(move || {
+                is_focused() && focused_input.get() == SearchInput::Replace
+            })
+            .build_editor(global_search.replace_editor.clone())
+            .style(|s| s.width_pct(100.0)),
+        clickable_icon(
+            || LapceIcons::SEARCH_PRESERVE_CASE,
+            move || {
+                preserve_case.update(|preserve_case| {
+                    *preserve_case = !*preserve_case;
+                });
+            },
+            move || preserve_case.get(),
+            || false,
+            || "Preserve Case",
+            config,
+        )
+        .style(|s| s.padding_vert(4.0)),
+        clickable_icon(
+            || LapceIcons::SEARCH_REPLACE_ALL,
+            move || global_search.replace_all(),
+            || false,
+            || false,
+            || "Replace All",
+            config,
+        )
+        .style(|s| s.padding_left(6.0)),
+    ))
+    .on_event_cont(EventListener::PointerDown, move |_| {
+        focus.set(Focus::Panel(PanelKind::Search));
+        focused_input.set(SearchInput::Replace);
+    })
+    .style(move |s| {
+        s.width_pct(100.0)
+            .margin_top(4.0)
+            .padding_right(6.0)
+            .items_center()
+            .border(1.0)
+            .border_radius(6.0)
+            .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+            .apply_if(!replace_active.get(), |s| s.hide())
+    })
+}
+
+/// The line of a match with the matched text replaced, showing the removed
+/// and the added text in the colors of the changes of source control.
+fn replace_preview(
+    m: &SearchMatch,
+    replace: &str,
+    search: Option<&FindSearchString>,
+    keep_case: bool,
+    config: &LapceConfig,
+) -> TextLayout {
+    let content = if config.ui.trim_search_results_whitespace {
+        m.line_content.trim_start()
+    } else {
+        &m.line_content
+    };
+    let trimmed = m.line_content.len() - content.len();
+    let start = m.start.saturating_sub(trimmed).min(content.len());
+    let end = m.end.saturating_sub(trimmed).clamp(start, content.len());
+    let (before, after) = (
+        content.get(..start).unwrap_or(""),
+        content.get(end..).unwrap_or(""),
+    );
+    let matched = content.get(start..end).unwrap_or("");
+    let replacement = match search {
+        Some(search) => search.replacement(matched, replace, keep_case),
+        None => replace.to_string(),
+    };
+
+    let prefix = format!("{}: {before}", m.line);
+    let text = format!("{prefix}{matched}{replacement}{}", after.trim_end());
+    let family = config.ui.font_family();
+    let attrs = Attrs::new()
+        .family(&family)
+        .font_size(config.ui.font_size() as f32)
+        .color(config.color(LapceColor::EDITOR_FOREGROUND));
+    let mut attrs_list = AttrsList::new(attrs);
+    let removed = prefix.len()..prefix.len() + matched.len();
+    let added = removed.end..removed.end + replacement.len();
+    attrs_list.add_span(
+        removed,
+        attrs.color(config.color(LapceColor::SOURCE_CONTROL_REMOVED)),
+    );
+    attrs_list.add_span(
+        added,
+        attrs.color(config.color(LapceColor::SOURCE_CONTROL_ADDED)),
+    );
+    let mut text_layout = TextLayout::new();
+    text_layout.set_text(&text, attrs_list);
+    text_layout
+}
+
+/// The inputs of the globs of the files to search and of those to leave out.
+fn search_filters(
+    global_search: GlobalSearchData,
+    is_focused: impl Fn() -> bool + 'static + Copy,
+) -> impl View {
+    let config = global_search.common.config;
+    let focus = global_search.common.focus;
+    let focused_input = global_search.focused_input;
+    let filter_input =
+        move |editor: EditorData, input: SearchInput, placeholder: &'static str| {
+            TextInputBuilder::new()
+                .is_focused(move || is_focused() && focused_input.get() == input)
+                .build_editor(editor)
+                .placeholder(move || placeholder.to_string())
+                .on_event_cont(EventListener::PointerDown, move |_| {
+                    focus.set(Focus::Panel(PanelKind::Search));
+                    focused_input.set(input);
+                })
+                .style(move |s| {
+                    s.width_pct(100.0)
+                        .border(1.0)
+                        .border_radius(6.0)
+                        .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+                })
+        };
+
+    stack((
+        filter_input(
+            global_search.include_editor.clone(),
+            SearchInput::Include,
+            "Files to include, e.g. src/**, *.rs",
+        ),
+        filter_input(
+            global_search.exclude_editor.clone(),
+            SearchInput::Exclude,
+            "Files to exclude",
+        )
+        .style(|s| s.margin_top(4.0)),
+    ))
+    .style(|s| {
+        s.flex_col()
+            .width_pct(100.0)
+            .padding_horiz(10.0)
+            .padding_bottom(10.0)
+    })
+}
+
 fn search_result(
     workspace: Arc<LapceWorkspace>,
     global_search_data: GlobalSearchData,
@@ -117,6 +307,11 @@ fn search_result(
     config: ReadSignal<Arc<LapceConfig>>,
 ) -> impl View {
     let ui_line_height = global_search_data.common.ui_line_height;
+    let replace_active = global_search_data.replace_active;
+    let replace_doc = global_search_data.replace_editor.doc_signal();
+    let search_string = global_search_data.search_string;
+    let preserve_case = global_search_data.common.find.preserve_case;
+    let actions = global_search_data.clone();
     container({
         scroll({
             virtual_stack(
@@ -153,6 +348,7 @@ fn search_result(
                         .to_string();
 
                     let expanded = match_data.expanded;
+                    let actions = actions.clone();
 
                     stack((
                         stack((
@@ -200,6 +396,56 @@ fn search_result(
                                 }),
                             ))
                             .style(move |s| s.min_width(0.0).items_center()),
+                            empty().style(|s| s.flex_grow(1.0)),
+                            clickable_icon(
+                                || LapceIcons::SCM_DIFF_MODIFIED,
+                                {
+                                    let actions = actions.clone();
+                                    let path = full_path.clone();
+                                    move || actions.preview_file(&path)
+                                },
+                                || false,
+                                || false,
+                                || "Preview Replacements",
+                                config,
+                            )
+                            .style(move |s| {
+                                s.padding_vert(0.0)
+                                    .apply_if(!replace_active.get(), |s| s.hide())
+                            }),
+                            clickable_icon(
+                                || LapceIcons::SEARCH_REPLACE_ALL,
+                                {
+                                    let actions = actions.clone();
+                                    let path = full_path.clone();
+                                    move || actions.replace_file(&path)
+                                },
+                                || false,
+                                || false,
+                                || "Replace All in File",
+                                config,
+                            )
+                            .style(move |s| {
+                                s.padding_vert(0.0)
+                                    .apply_if(!replace_active.get(), |s| s.hide())
+                            }),
+                            clickable_icon(
+                                || LapceIcons::CLOSE,
+                                {
+                                    let actions = actions.clone();
+                                    let path = full_path.clone();
+                                    move || actions.dismiss_file(&path)
+                                },
+                                || false,
+                                || false,
+                                || "Dismiss",
+                                config,
+                            )
+                            .style(move |s| {
+                                s.padding_vert(0.0)
+                                    .margin_right(6.0)
+                                    .apply_if(!replace_active.get(), |s| s.hide())
+                            }),
                         ))
                         .on_click_stop(move |_| {
                             expanded.update(|expanded| *expanded = !*expanded);
@@ -235,53 +481,116 @@ fn search_result(
                                 let start = m.start;
                                 let end = m.end;
                                 let line_content = m.line_content.clone();
+                                let preview_match = m.clone();
+                                let dismiss_match = m.clone();
+                                let actions = actions.clone();
+                                let dismiss_path = full_path.clone();
 
-                                focus_text(
-                                    move || {
-                                        let config = config.get();
-                                        let content = if config
-                                            .ui
-                                            .trim_search_results_whitespace
-                                        {
-                                            m.line_content.trim()
-                                        } else {
-                                            &m.line_content
-                                        };
-                                        format!("{}: {content}", m.line,)
-                                    },
-                                    move || {
-                                        let config = config.get();
-                                        let mut offset = if config
-                                            .ui
-                                            .trim_search_results_whitespace
-                                        {
-                                            line_content.trim_start().len() as i32
-                                                - line_content.len() as i32
-                                        } else {
-                                            0
-                                        };
-                                        offset +=
-                                            line_number.to_string().len() as i32 + 2;
+                                stack((
+                                    focus_text(
+                                        move || {
+                                            let config = config.get();
+                                            let content = if config
+                                                .ui
+                                                .trim_search_results_whitespace
+                                            {
+                                                m.line_content.trim()
+                                            } else {
+                                                &m.line_content
+                                            };
+                                            format!("{}: {content}", m.line,)
+                                        },
+                                        move || {
+                                            let config = config.get();
+                                            let mut offset = if config
+                                                .ui
+                                                .trim_search_results_whitespace
+                                            {
+                                                line_content.trim_start().len()
+                                                    as i32
+                                                    - line_content.len() as i32
+                                            } else {
+                                                0
+                                            };
+                                            offset += line_number.to_string().len()
+                                                as i32
+                                                + 2;
 
-                                        ((start as i32 + offset) as usize
-                                            ..(end as i32 + offset) as usize)
-                                            .collect()
-                                    },
-                                    move || {
-                                        config.get().color(LapceColor::EDITOR_FOCUS)
-                                    },
-                                )
+                                            ((start as i32 + offset) as usize
+                                                ..(end as i32 + offset) as usize)
+                                                .collect()
+                                        },
+                                        move || {
+                                            config
+                                                .get()
+                                                .color(LapceColor::EDITOR_FOCUS)
+                                        },
+                                    )
+                                    .style(
+                                        move |s| {
+                                            s.apply_if(replace_active.get(), |s| {
+                                                s.hide()
+                                            })
+                                        },
+                                    ),
+                                    rich_text(move || {
+                                        let replace = replace_doc
+                                            .get()
+                                            .buffer
+                                            .with(|b| b.to_string());
+                                        search_string.with(|search| {
+                                            replace_preview(
+                                                &preview_match,
+                                                &replace,
+                                                search.as_ref(),
+                                                preserve_case.get(),
+                                                &config.get(),
+                                            )
+                                        })
+                                    })
+                                    .style(
+                                        move |s| {
+                                            s.apply_if(!replace_active.get(), |s| {
+                                                s.hide()
+                                            })
+                                        },
+                                    ),
+                                    empty().style(|s| s.flex_grow(1.0)),
+                                    clickable_icon(
+                                        || LapceIcons::CLOSE,
+                                        move || {
+                                            actions.dismiss_match(
+                                                &dismiss_path,
+                                                &dismiss_match,
+                                            )
+                                        },
+                                        || false,
+                                        || false,
+                                        || "Dismiss",
+                                        config,
+                                    )
+                                    .style(
+                                        move |s| {
+                                            s.padding_vert(0.0)
+                                                .margin_right(6.0)
+                                                .apply_if(
+                                                    !replace_active.get(),
+                                                    |s| s.hide(),
+                                                )
+                                        },
+                                    ),
+                                ))
                                 .style(move |s| {
                                     let config = config.get();
                                     let icon_size = config.ui.icon_size() as f32;
-                                    s.margin_left(10.0 + icon_size + 6.0).hover(
-                                        |s| {
+                                    s.items_center()
+                                        .margin_left(10.0 + icon_size + 6.0)
+                                        .hover(|s| {
                                             s.cursor(CursorStyle::Pointer)
                                                 .background(config.color(
                                                 LapceColor::PANEL_HOVERED_BACKGROUND,
                                             ))
-                                        },
-                                    )
+                                        })
                                 })
                                 .on_click_stop(
                                     move |_| {
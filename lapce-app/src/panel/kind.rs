@@ -19,6 +19,10 @@ pub enum PanelKind {
     DocumentSymbol,
     References,
     Implementation,
+    UndoTree,
+    Bookmarks,
+    Output,
+    Task,
 }
 
 impl PanelKind {
@@ -35,6 +39,10 @@ pub fn svg_name(&self) -> &'static str {
             PanelKind::DocumentSymbol => LapceIcons::DOCUMENT_SYMBOL,
             PanelKind::References => LapceIcons::REFERENCES,
             PanelKind::Implementation => LapceIcons::IMPLEMENTATION,
+            PanelKind::UndoTree => LapceIcons::UNDO_TREE,
+            PanelKind::Bookmarks => LapceIcons::BOOKMARK,
+            PanelKind::Output => LapceIcons::OUTPUT,
+            PanelKind::Task => LapceIcons::TASKS,
         }
     }
 
@@ -61,6 +69,10 @@ pub fn default_position(&self) -> PanelPosition {
             PanelKind::DocumentSymbol => PanelPosition::RightTop,
             PanelKind::References => PanelPosition::BottomLeft,
             PanelKind::Implementation => PanelPosition::BottomLeft,
+            PanelKind::UndoTree => PanelPosition::RightTop,
+            PanelKind::Bookmarks => PanelPosition::RightTop,
+            PanelKind::Output => PanelPosition::BottomLeft,
+            PanelKind::Task => PanelPosition::BottomLeft,
         }
     }
 }
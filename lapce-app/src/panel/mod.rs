@@ -1,3 +1,4 @@
+pub mod bookmark_view;
 pub mod call_hierarchy_view;
 pub mod data;
 pub mod debug_view;
@@ -5,11 +6,14 @@
 pub mod global_search_view;
 pub mod implementation_view;
 pub mod kind;
+pub mod output_view;
 pub mod plugin_view;
 pub mod position;
 pub mod problem_view;
 pub mod references_view;
 pub mod source_control_view;
 pub mod style;
+pub mod task_view;
 pub mod terminal_view;
+pub mod undo_tree_view;
 pub mod view;
@@ -0,0 +1,205 @@
+use std::rc::Rc;
+
+use floem::{
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+    style::CursorStyle,
+    views::{
+        container, dyn_container, dyn_stack, empty, label, scroll, stack,
+        virtual_stack, Decorators, VirtualDirection, VirtualItemSize,
+    },
+    View,
+};
+use lapce_rpc::plugin::VoltID;
+
+use super::position::PanelPosition;
+use crate::{
+    app::clickable_icon,
+    config::{color::LapceColor, icon::LapceIcons},
+    window_tab::WindowTabData,
+};
+
+/// The number of lines kept in an output channel, dropping the oldest ones.
+const MAX_OUTPUT_LINES: usize = 5000;
+
+/// The output of the language servers of a volt.
+#[derive(Clone, Debug)]
+pub struct OutputChannel {
+    pub name: String,
+    /// The lines with their ids, which keep increasing as lines are added.
+    pub lines: RwSignal<im::Vector<(usize, String)>>,
+}
+
+/// The output of the language servers, in a channel for each volt.
+#[derive(Clone)]
+pub struct OutputData {
+    scope: Scope,
+    pub channels: RwSignal<im::HashMap<VoltID, OutputChannel>>,
+    /// The channel shown in the panel.
+    pub active: RwSignal<Option<VoltID>>,
+}
+
+impl OutputData {
+    pub fn new(cx: Scope) -> Self {
+        Self {
+            scope: cx,
+            channels: cx.create_rw_signal(im::HashMap::new()),
+            active: cx.create_rw_signal(None),
+        }
+    }
+
+    /// Add the lines of `message` to the channel of the volt, creating it with
+    /// `name` if it's the first output of the volt.
+    pub fn receive(&self, volt_id: VoltID, name: String, message: &str) {
+        let exists = self.channels.with_untracked(|c| c.contains_key(&volt_id));
+        if !exists {
+            let channel = OutputChannel {
+                name,
+                lines: self.scope.create_rw_signal(im::Vector::new()),
+            };
+            self.channels.update(|channels| {
+                channels.insert(volt_id.clone(), channel);
+            });
+            if self.active.with_untracked(Option::is_none) {
+                self.active.set(Some(volt_id.clone()));
+            }
+        }
+
+        let Some(lines) = self
+            .channels
+            .with_untracked(|c| c.get(&volt_id).map(|channel| channel.lines))
+        else {
+            return;
+        };
+        lines.update(|lines| {
+            let mut next_id = lines.back().map_or(0, |(id, _)| id + 1);
+            for line in message.lines() {
+                lines.push_back((next_id, line.to_string()));
+                next_id += 1;
+            }
+            while lines.len() > MAX_OUTPUT_LINES {
+                lines.pop_front();
+            }
+        });
+    }
+
+    /// Clear the lines of the channel shown in the panel.
+    pub fn clear_active(&self) {
+        let Some(volt_id) = self.active.get_untracked() else {
+            return;
+        };
+        if let Some(lines) = self
+            .channels
+            .with_untracked(|c| c.get(&volt_id).map(|channel| channel.lines))
+        {
+            lines.set(im::Vector::new());
+        }
+    }
+}
+
+pub fn output_panel(
+    window_tab_data: Rc<WindowTabData>,
+    _position: PanelPosition,
+) -> impl View {
+    let config = window_tab_data.common.config;
+    let ui_line_height = window_tab_data.common.ui_line_height;
+    let output = window_tab_data.output.clone();
+    let channels = output.channels;
+    let active = output.active;
+
+    stack((
+        stack((
+            dyn_stack(
+                move || {
+                    let mut channels: Vec<(VoltID, String)> = channels.with(|c| {
+                        c.iter()
+                            .map(|(id, channel)| (id.clone(), channel.name.clone()))
+                            .collect()
+                    });
+                    channels.sort_by(|a, b| a.1.cmp(&b.1));
+                    channels
+                },
+                |(id, _)| id.clone(),
+                move |(id, name)| {
+                    let is_active = {
+                        let id = id.clone();
+                        move || active.with(|active| active.as_ref() == Some(&id))
+                    };
+                    label(move || name.clone())
+                        .on_click_stop(move |_| {
+                            active.set(Some(id.clone()));
+                        })
+                        .style(move |s| {
+                            let config = config.get();
+                            s.padding_horiz(10.0)
+                                .height(ui_line_height.get())
+                                .items_center()
+                                .selectable(false)
+                                .cursor(CursorStyle::Pointer)
+                                .apply_if(!is_active(), |s| {
+                                    s.color(config.color(LapceColor::EDITOR_DIM))
+                                })
+                                .apply_if(is_active(), |s| {
+                                    s.border_bottom(2.0).border_color(config.color(
+                                        LapceColor::LAPCE_TAB_ACTIVE_UNDERLINE,
+                                    ))
+                                })
+                        })
+                },
+            )
+            .style(|s| s.flex_grow(1.0).min_width(0.0)),
+            clickable_icon(
+                || LapceIcons::CLOSE,
+                move || output.clear_active(),
+                || false,
+                || false,
+                || "Clear Output",
+                config,
+            )
+            .style(|s| s.margin_horiz(6.0)),
+        ))
+        .style(move |s| {
+            s.width_pct(100.0)
+                .items_center()
+                .border_bottom(1.0)
+                .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+        }),
+        container(
+            scroll(
+                dyn_container(
+                    move || {
+                        active.get().and_then(|id| {
+                            channels.with_untracked(|channels| {
+                                channels.get(&id).map(|channel| channel.lines)
+                            })
+                        })
+                    },
+                    move |lines| match lines {
+                        Some(lines) => virtual_stack(
+                            VirtualDirection::Vertical,
+                            VirtualItemSize::Fixed(Box::new(move || {
+                                ui_line_height.get()
+                            })),
+                            move || lines.get(),
+                            |(id, _)| *id,
+                            move |(_, line)| {
+                                label(move || line.clone()).style(move |s| {
+                                    s.padding_horiz(10.0)
+                                        .height(ui_line_height.get())
+                                        .items_center()
+                                })
+                            },
+                        )
+                        .style(|s| s.flex_col().min_width_full())
+                        .into_any(),
+                        None => empty().into_any(),
+                    },
+                )
+                .style(|s| s.min_width_full()),
+            )
+            .style(|s| s.absolute().size_full()),
+        )
+        .style(|s| s.size_full()),
+    ))
+    .style(|s| s.flex_col().size_full())
+    .debug_name("Output Panel")
+}
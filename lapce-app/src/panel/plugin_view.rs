@@ -95,11 +95,13 @@ fn installed_view(plugin: PluginData) -> impl View {
     let disabled = plugin.disabled;
     let workspace_disabled = plugin.workspace_disabled;
     let internal_command = plugin.common.internal_command;
+    let selected = plugin.selected;
 
     let view_fn = move |volt: InstalledVoltData, plugin: PluginData| {
         let meta = volt.meta.get_untracked();
         let volt_id = meta.id();
         let local_volt_id = volt_id.clone();
+        let selected_volt_id = volt_id.clone();
         let icon = volt.icon;
         stack((
             dyn_container(
@@ -175,6 +177,7 @@ fn installed_view(plugin: PluginData) -> impl View {
             .style(|s| s.flex_col().flex_grow(1.0).flex_basis(0.0).min_width(0.0)),
         ))
         .on_click_stop(move |_| {
+            selected.set(Some(local_volt_id.clone()));
             internal_command.send(InternalCommand::OpenVoltView {
                 volt_id: local_volt_id.clone(),
             });
@@ -188,6 +191,14 @@ fn installed_view(plugin: PluginData) -> impl View {
                         config.get().color(LapceColor::PANEL_HOVERED_BACKGROUND),
                     )
                 })
+                .apply_if(
+                    selected.with(|s| s.as_ref() == Some(&selected_volt_id)),
+                    |s| {
+                        s.background(
+                            config.get().color(LapceColor::PANEL_CURRENT_BACKGROUND),
+                        )
+                    },
+                )
         })
     };
 
@@ -220,6 +231,7 @@ fn available_view(plugin: PluginData, core_rpc: CoreRpcHandler) -> impl View {
     let installed = plugin.installed;
     let config = plugin.common.config;
     let internal_command = plugin.common.internal_command;
+    let selected = plugin.selected;
 
     let local_plugin = plugin.clone();
     let install_button =
@@ -272,6 +284,7 @@ fn available_view(plugin: PluginData, core_rpc: CoreRpcHandler) -> impl View {
         let info = volt.info.get_untracked();
         let icon = volt.icon;
         let volt_id = info.id();
+        let selected_volt_id = volt_id.clone();
         stack((
             dyn_container(
                 move || icon.get(),
@@ -318,11 +331,16 @@ fn available_view(plugin: PluginData, core_rpc: CoreRpcHandler) -> impl View {
             .style(|s| s.flex_col().flex_grow(1.0).flex_basis(0.0).min_width(0.0)),
         ))
         .on_click_stop(move |_| {
+            selected.set(Some(volt_id.clone()));
             internal_command.send(InternalCommand::OpenVoltView {
                 volt_id: volt_id.clone(),
             });
         })
         .style(move |s| {
+            // Installed plugins are selected in the installed section
+            let is_selected = selected
+                .with(|s| s.as_ref() == Some(&selected_volt_id))
+                && !installed.with(|i| i.contains_key(&selected_volt_id));
             s.width_pct(100.0)
                 .padding_horiz(10.0)
                 .padding_vert(5.0)
@@ -331,6 +349,11 @@ fn available_view(plugin: PluginData, core_rpc: CoreRpcHandler) -> impl View {
                         config.get().color(LapceColor::PANEL_HOVERED_BACKGROUND),
                     )
                 })
+                .apply_if(is_selected, |s| {
+                    s.background(
+                        config.get().color(LapceColor::PANEL_CURRENT_BACKGROUND),
+                    )
+                })
         })
     };
 
@@ -1,36 +1,234 @@
-use std::{path::PathBuf, rc::Rc, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
 
 use floem::{
+    event::EventListener,
+    keyboard::Modifiers,
     peniko::Color,
     reactive::{
-        create_effect, create_rw_signal, ReadSignal, SignalGet, SignalUpdate,
-        SignalWith,
+        create_effect, create_rw_signal, ReadSignal, RwSignal, Scope, SignalGet,
+        SignalUpdate, SignalWith,
     },
     style::{CursorStyle, Style},
     views::{container, dyn_stack, label, scroll, stack, svg, Decorators},
     View,
 };
-use lsp_types::{DiagnosticRelatedInformation, DiagnosticSeverity};
+use itertools::Itertools;
+use lapce_core::{command::FocusCommand, mode::Mode};
+use lapce_rpc::task::TaskOutputLink;
+use lsp_types::{
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, NumberOrString,
+    Range,
+};
 
-use super::{data::PanelSection, position::PanelPosition, view::PanelBuilder};
+use super::{
+    data::PanelSection, kind::PanelKind, position::PanelPosition, view::PanelBuilder,
+};
 use crate::{
-    command::InternalCommand,
+    command::{CommandExecuted, CommandKind, InternalCommand, LapceCommand},
     config::{color::LapceColor, icon::LapceIcons, LapceConfig},
     doc::{DiagnosticData, EditorDiagnostic},
-    editor::location::{EditorLocation, EditorPosition},
+    editor::{
+        location::{EditorLocation, EditorPosition},
+        EditorData,
+    },
+    keypress::{condition::Condition, KeyPressFocus},
     listener::Listener,
     lsp::path_from_url,
-    window_tab::WindowTabData,
+    main_split::MainSplitData,
+    text_input::TextInputBuilder,
+    window_tab::{Focus, WindowTabData},
     workspace::LapceWorkspace,
 };
 
+/// Identifies a diagnostic in the panel by its severity, file and range.
+type ProblemKey = (DiagnosticSeverity, PathBuf, Range);
+
+/// The keyboard selection and the filter of the problem panel.
+#[derive(Clone, Debug)]
+pub struct ProblemData {
+    pub selected: RwSignal<Option<ProblemKey>>,
+    /// The input of the text the listed problems are filtered by.
+    pub filter_editor: EditorData,
+    main_split: MainSplitData,
+}
+
+impl KeyPressFocus for ProblemData {
+    fn get_mode(&self) -> Mode {
+        Mode::Insert
+    }
+
+    fn check_condition(&self, condition: Condition) -> bool {
+        matches!(condition, Condition::PanelFocus | Condition::ListFocus)
+    }
+
+    fn run_command(
+        &self,
+        command: &LapceCommand,
+        count: Option<usize>,
+        mods: Modifiers,
+    ) -> CommandExecuted {
+        match &command.kind {
+            CommandKind::Focus(FocusCommand::ListNext) => self.select_next(1),
+            CommandKind::Focus(FocusCommand::ListPrevious) => self.select_next(-1),
+            CommandKind::Focus(FocusCommand::ListSelect) => {
+                let Some((_, path, diagnostic)) = self.selected_item() else {
+                    return CommandExecuted::Yes;
+                };
+                self.main_split.common.internal_command.send(
+                    InternalCommand::JumpToLocation {
+                        location: diagnostic_location(path, &diagnostic),
+                    },
+                );
+            }
+            CommandKind::Edit(_)
+            | CommandKind::Move(_)
+            | CommandKind::MultiSelection(_) => {
+                return self.filter_editor.run_command(command, count, mods);
+            }
+            _ => return CommandExecuted::No,
+        }
+        CommandExecuted::Yes
+    }
+
+    fn receive_char(&self, c: &str) {
+        self.filter_editor.receive_char(c);
+    }
+}
+
+impl ProblemData {
+    pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
+        let filter_editor =
+            main_split.editors.make_local(cx, main_split.common.clone());
+        Self {
+            selected: cx.create_rw_signal(None),
+            filter_editor,
+            main_split,
+        }
+    }
+
+    pub fn filter(&self) -> String {
+        self.filter_editor.doc().buffer.with(|b| b.to_string())
+    }
+
+    /// The errors, the warnings and then the information, in the order they
+    /// are listed.
+    fn items(&self) -> Vec<(DiagnosticSeverity, PathBuf, EditorDiagnostic)> {
+        let filter = self.filter();
+        [
+            DiagnosticSeverity::ERROR,
+            DiagnosticSeverity::WARNING,
+            DiagnosticSeverity::INFORMATION,
+        ]
+        .into_iter()
+        .flat_map(|severity| {
+            self.main_split
+                .file_diagnostics_items(severity)
+                .into_iter()
+                .flat_map(move |(path, diagnostics)| {
+                    diagnostics
+                        .into_iter()
+                        .map(move |d| (severity, path.clone(), d))
+                })
+        })
+        .filter(|(_, path, d)| problem_matches(&filter, path, &d.diagnostic))
+        .collect()
+    }
+
+    fn selected_item(
+        &self,
+    ) -> Option<(DiagnosticSeverity, PathBuf, EditorDiagnostic)> {
+        let selected = self.selected.get_untracked()?;
+        self.items()
+            .into_iter()
+            .find(|item| problem_key(item) == selected)
+    }
+
+    /// Move the selection by `delta` items, stopping at either end.
+    fn select_next(&self, delta: isize) {
+        let keys = self.items().iter().map(problem_key).collect::<Vec<_>>();
+        if keys.is_empty() {
+            return;
+        }
+        let current = self.selected.with_untracked(|selected| {
+            selected
+                .as_ref()
+                .and_then(|selected| keys.iter().position(|k| k == selected))
+        });
+        let index = match current {
+            Some(i) => (i as isize + delta).clamp(0, keys.len() as isize - 1),
+            None if delta < 0 => keys.len() as isize - 1,
+            None => 0,
+        } as usize;
+        self.selected.set(Some(keys[index].clone()));
+    }
+}
+
+/// The severity of the section a diagnostic is listed in, where hints are
+/// listed with the information.
+pub fn problem_severity(
+    severity: Option<DiagnosticSeverity>,
+) -> Option<DiagnosticSeverity> {
+    match severity {
+        Some(DiagnosticSeverity::HINT) => Some(DiagnosticSeverity::INFORMATION),
+        severity => severity,
+    }
+}
+
+/// Whether a diagnostic of the file at `path` is matched by the text `filter`,
+/// which is looked for in its message, source and code and in the path,
+/// ignoring case.
+pub fn problem_matches(filter: &str, path: &Path, diagnostic: &Diagnostic) -> bool {
+    let filter = filter.trim().to_lowercase();
+    if filter.is_empty() {
+        return true;
+    }
+    let code = diagnostic.code.as_ref().map(|code| match code {
+        NumberOrString::Number(n) => n.to_string(),
+        NumberOrString::String(s) => s.clone(),
+    });
+    [
+        Some(diagnostic.message.clone()),
+        diagnostic.source.clone(),
+        code,
+        Some(path.to_string_lossy().into_owned()),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|text| text.to_lowercase().contains(&filter))
+}
+
+fn problem_key(
+    (severity, path, d): &(DiagnosticSeverity, PathBuf, EditorDiagnostic),
+) -> ProblemKey {
+    (*severity, path.clone(), d.diagnostic.range)
+}
+
+fn diagnostic_location(path: PathBuf, d: &EditorDiagnostic) -> EditorLocation {
+    let position = if let Some((start, _)) = d.range {
+        EditorPosition::Offset(start)
+    } else {
+        EditorPosition::Position(d.diagnostic.range.start)
+    };
+    EditorLocation {
+        path,
+        position: Some(position),
+        scroll_offset: None,
+        ignore_unconfirmed: false,
+        same_editor_tab: false,
+    }
+}
+
 pub fn problem_panel(
     window_tab_data: Rc<WindowTabData>,
     position: PanelPosition,
 ) -> impl View {
     let config = window_tab_data.common.config;
     let is_bottom = position.is_bottom();
-    PanelBuilder::new(config, position)
+    let sections = PanelBuilder::new(config, position)
         .add_style(
             "Errors",
             problem_section(window_tab_data.clone(), DiagnosticSeverity::ERROR),
@@ -41,13 +239,54 @@ pub fn problem_panel(
                     .apply_if(!is_bottom, |s| s.border_bottom(1.0))
             },
         )
-        .add(
+        .add_style(
             "Warnings",
             problem_section(window_tab_data.clone(), DiagnosticSeverity::WARNING),
             window_tab_data.panel.section_open(PanelSection::Warn),
+            move |s| {
+                s.border_color(config.get().color(LapceColor::LAPCE_BORDER))
+                    .apply_if(is_bottom, |s| s.border_right(1.0))
+                    .apply_if(!is_bottom, |s| s.border_bottom(1.0))
+            },
+        )
+        .add(
+            "Information",
+            problem_section(
+                window_tab_data.clone(),
+                DiagnosticSeverity::INFORMATION,
+            ),
+            window_tab_data.panel.section_open(PanelSection::Info),
         )
-        .build()
-        .debug_name("Problem Panel")
+        .build();
+
+    stack((
+        filter_input(window_tab_data.clone()),
+        container(sections).style(|s| s.size_full().min_height(0.0)),
+    ))
+    .style(|s| s.size_full().flex_col())
+    .debug_name("Problem Panel")
+}
+
+fn filter_input(window_tab_data: Rc<WindowTabData>) -> impl View {
+    let config = window_tab_data.common.config;
+    let focus = window_tab_data.common.focus;
+    let editor = window_tab_data.problem.filter_editor.clone();
+    container(
+        TextInputBuilder::new()
+            .is_focused(move || focus.get() == Focus::Panel(PanelKind::Problem))
+            .build_editor(editor)
+            .placeholder(|| "Filter".to_string())
+            .on_event_cont(EventListener::PointerDown, move |_| {
+                focus.set(Focus::Panel(PanelKind::Problem));
+            })
+            .style(move |s| {
+                s.width_pct(100.0)
+                    .border(1.0)
+                    .border_radius(6.0)
+                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+            }),
+    )
+    .style(|s| s.width_pct(100.0).padding(10.0))
 }
 
 fn problem_section(
@@ -57,10 +296,18 @@ fn problem_section(
     let config = window_tab_data.common.config;
     let main_split = window_tab_data.main_split.clone();
     let internal_command = window_tab_data.common.internal_command;
+    let problem = window_tab_data.problem.clone();
+    let focus = window_tab_data.common.focus;
     container({
         scroll(
             dyn_stack(
-                move || main_split.diagnostics.get(),
+                move || {
+                    main_split
+                        .diagnostics
+                        .get()
+                        .into_iter()
+                        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                },
                 |(p, _)| p.clone(),
                 move |(path, diagnostic_data)| {
                     file_view(
@@ -68,6 +315,7 @@ fn problem_section(
                         path,
                         diagnostic_data,
                         severity,
+                        problem.clone(),
                         internal_command,
                         config,
                     )
@@ -77,6 +325,9 @@ fn problem_section(
         )
         .style(|s| s.absolute().size_pct(100.0, 100.0))
     })
+    .on_event_cont(EventListener::PointerDown, move |_| {
+        focus.set(Focus::Panel(PanelKind::Problem));
+    })
     .style(|s| s.size_pct(100.0, 100.0))
 }
 
@@ -85,18 +336,26 @@ fn file_view(
     path: PathBuf,
     diagnostic_data: DiagnosticData,
     severity: DiagnosticSeverity,
+    problem: ProblemData,
     internal_command: Listener<InternalCommand>,
     config: ReadSignal<Arc<LapceConfig>>,
 ) -> impl View {
     let collpased = create_rw_signal(false);
+    let selected = problem.selected;
 
     let diagnostics = create_rw_signal(im::Vector::new());
+    let filter_path = path.clone();
     create_effect(move |_| {
+        let filter = problem.filter();
+        let is_shown = |diag: &Diagnostic| {
+            problem_severity(diag.severity) == Some(severity)
+                && problem_matches(&filter, &filter_path, diag)
+        };
         let span = diagnostic_data.diagnostics_span.get();
         let d = if !span.is_empty() {
             span.iter()
                 .filter_map(|(iv, diag)| {
-                    if diag.severity == Some(severity) {
+                    if is_shown(diag) {
                         Some(EditorDiagnostic {
                             range: Some((iv.start, iv.end)),
                             diagnostic: diag.to_owned(),
@@ -111,7 +370,7 @@ fn file_view(
             let diagnostics: im::Vector<EditorDiagnostic> = diagnostics
                 .into_iter()
                 .filter_map(|d| {
-                    if d.severity == Some(severity) {
+                    if is_shown(&d) {
                         Some(EditorDiagnostic {
                             range: None,
                             diagnostic: d,
@@ -138,13 +397,15 @@ fn file_view(
 
     let icon = match severity {
         DiagnosticSeverity::ERROR => LapceIcons::ERROR,
-        _ => LapceIcons::WARNING,
+        DiagnosticSeverity::WARNING => LapceIcons::WARNING,
+        _ => LapceIcons::INFO,
     };
     let icon_color = move || {
         let config = config.get();
         match severity {
             DiagnosticSeverity::ERROR => config.color(LapceColor::LAPCE_ERROR),
-            _ => config.color(LapceColor::LAPCE_WARN),
+            DiagnosticSeverity::WARNING => config.color(LapceColor::LAPCE_WARN),
+            _ => config.color(LapceColor::LAPCE_ICON_ACTIVE),
         }
     };
 
@@ -235,8 +496,10 @@ fn file_view(
                 item_view(
                     full_path.clone(),
                     d,
+                    severity,
                     icon,
                     icon_color,
+                    selected,
                     internal_command,
                     config,
                 )
@@ -252,27 +515,23 @@ fn file_view(
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 fn item_view(
     path: PathBuf,
     d: EditorDiagnostic,
+    severity: DiagnosticSeverity,
     icon: &'static str,
     icon_color: impl Fn() -> Color + 'static,
+    selected: RwSignal<Option<ProblemKey>>,
     internal_command: Listener<InternalCommand>,
     config: ReadSignal<Arc<LapceConfig>>,
 ) -> impl View {
+    let key = (severity, path.clone(), d.diagnostic.range);
+    let key_for_style = key.clone();
+    let location = diagnostic_location(path, &d);
+    let output_link = TaskOutputLink::of(&d.diagnostic);
+    let source = d.diagnostic.source.clone().unwrap_or_default();
     let related = d.diagnostic.related_information.unwrap_or_default();
-    let position = if let Some((start, _)) = d.range {
-        EditorPosition::Offset(start)
-    } else {
-        EditorPosition::Position(d.diagnostic.range.start)
-    };
-    let location = EditorLocation {
-        path,
-        position: Some(position),
-        scroll_offset: None,
-        ignore_unconfirmed: false,
-        same_editor_tab: false,
-    };
     stack((
         container({
             stack((
@@ -299,24 +558,92 @@ fn item_view(
                 }),
             ))
             .style(move |s| {
-                s.width_pct(100.0).min_width(0.0).hover(|s| {
-                    s.cursor(CursorStyle::Pointer).background(
-                        config.get().color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                let config = config.get();
+                s.width_pct(100.0)
+                    .min_width(0.0)
+                    .hover(|s| {
+                        s.cursor(CursorStyle::Pointer).background(
+                            config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                        )
+                    })
+                    .apply_if(
+                        selected.with(|s| s.as_ref() == Some(&key_for_style)),
+                        |s| {
+                            s.background(
+                                config.color(LapceColor::PANEL_CURRENT_BACKGROUND),
+                            )
+                        },
                     )
-                })
             })
         })
         .on_click_stop(move |_| {
+            selected.set(Some(key.clone()));
             internal_command.send(InternalCommand::JumpToLocation {
                 location: location.clone(),
             });
         })
         .style(|s| s.width_pct(100.0).min_width_pct(0.0)),
         related_view(related, internal_command, config),
+        task_output_view(output_link, source, internal_command, config),
     ))
     .style(|s| s.width_pct(100.0).min_width_pct(0.0).flex_col())
 }
 
+/// The link to the lines of the output of the task a problem was found in.
+fn task_output_view(
+    link: Option<TaskOutputLink>,
+    task_name: String,
+    internal_command: Listener<InternalCommand>,
+    config: ReadSignal<Arc<LapceConfig>>,
+) -> impl View {
+    let is_empty = link.is_none();
+    let message = format!("Show in the output of {task_name}");
+    stack((
+        container(
+            label(move || message.clone())
+                .style(move |s| s.width_pct(100.0).min_width(0.0)),
+        )
+        .on_click_stop(move |_| {
+            if let Some(link) = link.clone() {
+                internal_command.send(InternalCommand::RevealTaskOutput { link });
+            }
+        })
+        .style(move |s| {
+            let config = config.get();
+            s.padding_left(10.0 + (config.ui.icon_size() as f32 + 6.0) * 4.0)
+                .padding_right(10.0)
+                .width_pct(100.0)
+                .min_width(0.0)
+                .hover(|s| {
+                    s.cursor(CursorStyle::Pointer).background(
+                        config.color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                    )
+                })
+        }),
+        stack((
+            svg(move || config.get().ui_svg(LapceIcons::TASKS)).style(move |s| {
+                let config = config.get();
+                let size = config.ui.icon_size() as f32;
+                s.size(size, size)
+                    .color(config.color(LapceColor::EDITOR_DIM))
+            }),
+            label(|| " ".to_string()).style(move |s| s.selectable(false)),
+        ))
+        .style(move |s| {
+            s.absolute()
+                .items_center()
+                .margin_left(10.0 + (config.get().ui.icon_size() as f32 + 6.0) * 3.0)
+        }),
+    ))
+    .style(move |s| {
+        s.width_pct(100.0)
+            .min_width(0.0)
+            .items_start()
+            .color(config.get().color(LapceColor::EDITOR_DIM))
+            .apply_if(is_empty, |s| s.hide())
+    })
+}
+
 fn related_view(
     related: Vec<DiagnosticRelatedInformation>,
     internal_command: Listener<InternalCommand>,
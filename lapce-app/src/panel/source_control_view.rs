@@ -3,7 +3,6 @@
 use floem::{
     action::show_context_menu,
     event::{Event, EventListener},
-    menu::{Menu, MenuItem},
     peniko::kurbo::Rect,
     reactive::{create_memo, create_rw_signal, SignalGet, SignalUpdate, SignalWith},
     style::{CursorStyle, Style},
@@ -22,7 +21,7 @@
     view::foldable_panel_section,
 };
 use crate::{
-    command::{CommandKind, InternalCommand, LapceCommand, LapceWorkbenchCommand},
+    command::InternalCommand,
     config::{color::LapceColor, icon::LapceIcons},
     editor::view::editor_view,
     settings::checkbox,
@@ -43,13 +42,17 @@ pub fn source_control_panel(
     let viewport = editor.viewport();
     let window_origin = editor.window_origin();
     let editor = create_rw_signal(editor);
+    let selected = source_control.selected;
     let is_active = move |tracked| {
-        let focus = if tracked {
-            focus.get()
+        let (focus, has_selection) = if tracked {
+            (focus.get(), selected.with(|s| s.is_some()))
         } else {
-            focus.get_untracked()
+            (
+                focus.get_untracked(),
+                selected.with_untracked(|s| s.is_some()),
+            )
         };
-        focus == Focus::Panel(PanelKind::SourceControl)
+        focus == Focus::Panel(PanelKind::SourceControl) && !has_selection
     };
     let is_empty = create_memo(move |_| {
         let doc = doc.get();
@@ -89,6 +92,7 @@ pub fn source_control_panel(
                         let event = event.clone().offset((10.0, 6.0));
                         if let Event::PointerDown(pointer_event) = event {
                             id.request_active();
+                            selected.set(None);
                             editor.get_untracked().pointer_down(&pointer_event);
                         }
                     })
@@ -202,14 +206,17 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
     let workspace = source_control.common.workspace.clone();
     let panel_rect = create_rw_signal(Rect::ZERO);
     let panel_width = create_memo(move |_| panel_rect.get().width());
-    let lapce_command = source_control.common.lapce_command;
     let internal_command = source_control.common.internal_command;
+    let selected = source_control.selected;
 
     let view_fn = move |(path, (diff, checked)): (PathBuf, (FileDiff, bool))| {
         let diff_for_style = diff.clone();
         let full_path = path.clone();
         let diff_for_menu = diff.clone();
         let path_for_click = full_path.clone();
+        let path_for_selected = full_path.clone();
+        let source_control = source_control.clone();
+        let source_control_for_menu = source_control.clone();
 
         let path = if let Some(workspace_path) = workspace.path.as_ref() {
             path.strip_prefix(workspace_path)
@@ -233,11 +240,7 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
             checkbox(move || checked, config)
                 .style(|s| s.hover(|s| s.cursor(CursorStyle::Pointer)))
                 .on_click_stop(move |_| {
-                    file_diffs.update(|diffs| {
-                        if let Some((_, checked)) = diffs.get_mut(&full_path) {
-                            *checked = !*checked;
-                        }
-                    });
+                    source_control.toggle_checked(&full_path);
                 }),
             svg(move || config.get().file_svg(&path).0).style(move |s| {
                 let config = config.get();
@@ -307,26 +310,16 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
             }),
         ))
         .on_click_stop(move |_| {
+            selected.set(Some(path_for_click.clone()));
             internal_command.send(InternalCommand::OpenFileChanges {
                 path: path_for_click.clone(),
             });
         })
         .on_event_cont(EventListener::PointerDown, move |event| {
-            let diff_for_menu = diff_for_menu.clone();
-
-            let discard = move || {
-                lapce_command.send(LapceCommand {
-                    kind: CommandKind::Workbench(
-                        LapceWorkbenchCommand::SourceControlDiscardTargetFileChanges,
-                    ),
-                    data: Some(serde_json::json!(diff_for_menu.clone())),
-                });
-            };
-
             if let Event::PointerDown(pointer_event) = event {
                 if pointer_event.button.is_secondary() {
-                    let menu = Menu::new("")
-                        .entry(MenuItem::new("Discard Changes").action(discard));
+                    let menu = source_control_for_menu
+                        .file_diff_menu(diff_for_menu.clone());
                     show_context_menu(menu, None);
                 }
             }
@@ -341,6 +334,14 @@ fn file_diffs_view(source_control: SourceControlData) -> impl View {
                 .hover(|s| {
                     s.background(config.color(LapceColor::PANEL_HOVERED_BACKGROUND))
                 })
+                .apply_if(
+                    selected.with(|s| s.as_ref() == Some(&path_for_selected)),
+                    |s| {
+                        s.background(
+                            config.color(LapceColor::PANEL_CURRENT_BACKGROUND),
+                        )
+                    },
+                )
         })
     };
 
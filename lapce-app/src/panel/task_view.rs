@@ -0,0 +1,249 @@
+use std::rc::Rc;
+
+use floem::{
+    reactive::{SignalGet, SignalUpdate, SignalWith},
+    style::CursorStyle,
+    views::{
+        container, dyn_container, dyn_stack, empty, label, scroll, stack,
+        virtual_stack, Decorators, VirtualDirection, VirtualItemSize,
+    },
+    View,
+};
+
+use super::position::PanelPosition;
+use crate::{
+    app::clickable_icon,
+    config::{color::LapceColor, icon::LapceIcons},
+    task::TaskStatus,
+    window_tab::WindowTabData,
+};
+
+pub fn task_panel(
+    window_tab_data: Rc<WindowTabData>,
+    _position: PanelPosition,
+) -> impl View {
+    let config = window_tab_data.common.config;
+    let ui_line_height = window_tab_data.common.ui_line_height;
+    let task_data = window_tab_data.task.clone();
+    let tasks = task_data.tasks;
+    let runs = task_data.runs;
+    let selected = task_data.selected;
+
+    let task_list = {
+        let task_data = task_data.clone();
+        stack((
+            stack((
+                label(|| "Tasks".to_string())
+                    .style(|s| s.flex_grow(1.0).padding_horiz(10.0)),
+                clickable_icon(
+                    || LapceIcons::DEBUG_RESTART,
+                    {
+                        let task_data = task_data.clone();
+                        move || task_data.load()
+                    },
+                    || false,
+                    || false,
+                    || "Reload Tasks",
+                    config,
+                )
+                .style(|s| s.margin_horiz(6.0)),
+            ))
+            .style(move |s| {
+                s.width_pct(100.0)
+                    .height(ui_line_height.get() + 4.0)
+                    .items_center()
+                    .border_bottom(1.0)
+                    .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+            }),
+            scroll(
+                dyn_stack(
+                    move || tasks.get(),
+                    |task| task.name.clone(),
+                    move |task| {
+                        let name = task.name.clone();
+                        let status = {
+                            let name = name.clone();
+                            move || {
+                                runs.with(|runs| {
+                                    runs.get(&name).map(|run| run.status.get())
+                                })
+                            }
+                        };
+                        let is_selected = {
+                            let name = name.clone();
+                            move || selected.with(|s| s.as_ref() == Some(&name))
+                        };
+                        let task_data = task_data.clone();
+                        let status_label = status.clone();
+                        let status_color = status.clone();
+                        let is_running =
+                            move || status().is_some_and(|s| s.is_running());
+                        let icon_running = is_running.clone();
+                        let tooltip_running = is_running.clone();
+                        let command = task.command_line();
+                        let task_name = task.name.clone();
+                        stack((
+                            label(move || task_name.clone()).style(|s| {
+                                s.margin_left(10.0).min_width(0.0).text_ellipsis()
+                            }),
+                            label(move || command.clone()).style(move |s| {
+                                s.margin_left(10.0)
+                                    .flex_grow(1.0)
+                                    .flex_basis(0.0)
+                                    .min_width(0.0)
+                                    .text_ellipsis()
+                                    .color(
+                                        config
+                                            .get()
+                                            .color(LapceColor::PANEL_FOREGROUND_DIM),
+                                    )
+                            }),
+                            label(move || {
+                                status_label()
+                                    .map(|status| status.label())
+                                    .unwrap_or_default()
+                            })
+                            .style(move |s| {
+                                let config = config.get();
+                                let color = match status_color() {
+                                    Some(TaskStatus::Running) => {
+                                        config.color(LapceColor::LAPCE_ICON_ACTIVE)
+                                    }
+                                    Some(status) if !status.is_success() => {
+                                        config.color(LapceColor::LAPCE_ERROR)
+                                    }
+                                    _ => config
+                                        .color(LapceColor::PANEL_FOREGROUND_DIM),
+                                };
+                                s.margin_horiz(6.0).color(color)
+                            }),
+                            clickable_icon(
+                                move || {
+                                    if icon_running() {
+                                        LapceIcons::DEBUG_STOP
+                                    } else {
+                                        LapceIcons::START
+                                    }
+                                },
+                                move || {
+                                    if is_running() {
+                                        task_data.stop(&task.name);
+                                    } else {
+                                        task_data.run(task.clone());
+                                    }
+                                },
+                                || false,
+                                || false,
+                                move || {
+                                    if tooltip_running() {
+                                        "Stop"
+                                    } else {
+                                        "Run"
+                                    }
+                                },
+                                config,
+                            )
+                            .style(|s| s.margin_right(6.0)),
+                        ))
+                        .on_click_stop(move |_| {
+                            selected.set(Some(name.clone()));
+                        })
+                        .style(move |s| {
+                            let config = config.get();
+                            s.width_pct(100.0)
+                                .height(ui_line_height.get() + 4.0)
+                                .items_center()
+                                .apply_if(is_selected(), |s| {
+                                    s.background(
+                                        config.color(
+                                            LapceColor::PANEL_CURRENT_BACKGROUND,
+                                        ),
+                                    )
+                                })
+                                .hover(|s| {
+                                    s.cursor(CursorStyle::Pointer).background(
+                                        config
+                                            .color(
+                                                LapceColor::PANEL_HOVERED_BACKGROUND,
+                                            )
+                                            .with_alpha_factor(0.3),
+                                    )
+                                })
+                        })
+                    },
+                )
+                .style(|s| s.flex_col().width_pct(100.0)),
+            )
+            .style(|s| s.width_pct(100.0).flex_grow(1.0).flex_basis(0.0)),
+        ))
+        .style(move |s| {
+            s.flex_col()
+                .width(300.0)
+                .height_pct(100.0)
+                .border_right(1.0)
+                .border_color(config.get().color(LapceColor::LAPCE_BORDER))
+        })
+    };
+
+    let output = container(
+        dyn_container(
+            move || task_data.selected_run(),
+            move |run| match run {
+                Some(run) => {
+                    let output = run.output;
+                    let highlight = run.highlight;
+                    scroll(
+                        virtual_stack(
+                            VirtualDirection::Vertical,
+                            VirtualItemSize::Fixed(Box::new(move || {
+                                ui_line_height.get()
+                            })),
+                            move || output.get(),
+                            |(id, _)| *id,
+                            move |(id, line)| {
+                                label(move || line.clone()).style(move |s| {
+                                    let is_highlighted = highlight.with(|lines| {
+                                        lines
+                                            .as_ref()
+                                            .is_some_and(|lines| lines.contains(&id))
+                                    });
+                                    s.padding_horiz(10.0)
+                                        .height(ui_line_height.get())
+                                        .items_center()
+                                        .apply_if(is_highlighted, |s| {
+                                            s.background(config.get().color(
+                                                LapceColor::EDITOR_CURRENT_LINE,
+                                            ))
+                                        })
+                                })
+                            },
+                        )
+                        .style(|s| s.flex_col().min_width_full()),
+                    )
+                    .scroll_to(move || {
+                        let line = match highlight.get() {
+                            // The first line of the problem revealed, by its
+                            // index among the lines kept
+                            Some(lines) => output.with_untracked(|output| {
+                                let first = output.front().map_or(0, |(id, _)| *id);
+                                lines.start.saturating_sub(first)
+                            }),
+                            // Following the output as it's printed
+                            None => output.with(|output| output.len()),
+                        };
+                        Some((0.0, line as f64 * ui_line_height.get()).into())
+                    })
+                    .style(|s| s.absolute().size_full())
+                    .into_any()
+                }
+                None => empty().into_any(),
+            },
+        )
+        .style(|s| s.size_full()),
+    )
+    .style(|s| s.flex_grow(1.0).flex_basis(0.0).height_pct(100.0));
+
+    stack((task_list, output))
+        .style(|s| s.size_full())
+        .debug_name("Task Panel")
+}
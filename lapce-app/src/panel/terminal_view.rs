@@ -13,11 +13,11 @@
     },
     View, ViewId,
 };
-use lapce_rpc::terminal::TermId;
+use lapce_rpc::terminal::{TermId, TerminalProcessUsage};
 
-use super::kind::PanelKind;
+use super::{data::panel_position, kind::PanelKind};
 use crate::{
-    app::clickable_icon,
+    app::{clickable_icon, tooltip_label},
     command::{InternalCommand, LapceWorkbenchCommand},
     config::{color::LapceColor, icon::LapceIcons},
     debug::RunDebugMode,
@@ -25,9 +25,13 @@
     terminal::{
         panel::TerminalPanelData, tab::TerminalTabData, view::terminal_view,
     },
-    window_tab::{Focus, WindowTabData},
+    window_tab::{DragContent, Focus, WindowTabData},
 };
 
+/// The maximum number of characters of the working directory shown in a
+/// terminal tab header, which has a fixed width.
+const TERMINAL_CWD_MAX_CHARS: usize = 16;
+
 pub fn terminal_panel(window_tab_data: Rc<WindowTabData>) -> impl View {
     let focus = window_tab_data.common.focus;
     stack((
@@ -54,6 +58,8 @@ fn terminal_tab_header(window_tab_data: Rc<WindowTabData>) -> impl View {
     let icon_width = create_rw_signal(0.0);
     let scroll_size = create_rw_signal(Size::ZERO);
     let workbench_command = window_tab_data.common.workbench_command;
+    let internal_command = window_tab_data.common.internal_command;
+    let panel = window_tab_data.panel.clone();
 
     stack((
         scroll(dyn_stack(
@@ -71,6 +77,7 @@ fn terminal_tab_header(window_tab_data: Rc<WindowTabData>) -> impl View {
                 let terminal = terminal.clone();
                 let local_terminal = terminal.clone();
                 let terminal_tab_id = tab.terminal_tab_id;
+                let menu_tab = tab.clone();
 
                 let title = {
                     let tab = tab.clone();
@@ -91,6 +98,33 @@ fn terminal_tab_header(window_tab_data: Rc<WindowTabData>) -> impl View {
                     }
                 };
 
+                let cwd = {
+                    let tab = tab.clone();
+                    move || {
+                        tab.active_terminal(true)
+                            .and_then(|t| t.cwd.get())
+                            .map(|cwd| {
+                                middle_truncate(
+                                    &cwd.to_string_lossy(),
+                                    TERMINAL_CWD_MAX_CHARS,
+                                )
+                            })
+                            .unwrap_or_default()
+                    }
+                };
+
+                let process_usage = {
+                    let tab = tab.clone();
+                    move || {
+                        if !config.get().terminal.show_process_usage {
+                            return None;
+                        }
+                        tab.active_terminal(true)
+                            .and_then(|t| t.process_usage.get())
+                    }
+                };
+                let process_usage_tooltip = process_usage.clone();
+
                 let svg_string = move || {
                     let terminal = tab.active_terminal(true);
                     let run_debug = terminal.as_ref().map(|t| t.run_debug);
@@ -134,6 +168,39 @@ fn terminal_tab_header(window_tab_data: Rc<WindowTabData>) -> impl View {
                                     .text_ellipsis()
                                     .selectable(false)
                             }),
+                            label(cwd).style(move |s| {
+                                s.margin_left(6.0)
+                                    .flex_shrink(0.0)
+                                    .selectable(false)
+                                    .color(
+                                        config.get().color(LapceColor::EDITOR_DIM),
+                                    )
+                            }),
+                            tooltip_label(
+                                config,
+                                label(move || {
+                                    process_usage()
+                                        .map(|usage| format_process_usage(&usage))
+                                        .unwrap_or_default()
+                                })
+                                .style(move |s| {
+                                    s.margin_left(6.0)
+                                        .flex_shrink(0.0)
+                                        .selectable(false)
+                                        .color(
+                                            config
+                                                .get()
+                                                .color(LapceColor::EDITOR_DIM),
+                                        )
+                                }),
+                                move || {
+                                    process_usage_tooltip()
+                                        .map(|usage| {
+                                            format!("{} ({})", usage.name, usage.pid)
+                                        })
+                                        .unwrap_or_default()
+                                },
+                            ),
                             clickable_icon(
                                 || LapceIcons::CLOSE,
                                 move || {
@@ -185,6 +252,14 @@ fn terminal_tab_header(window_tab_data: Rc<WindowTabData>) -> impl View {
                         s.absolute().padding_horiz(3.0).size_pct(100.0, 100.0)
                     }),
                 ))
+                .on_secondary_click_stop(move |_| {
+                    if let Some(terminal) = menu_tab.active_terminal(false) {
+                        tab_header_secondary_click(
+                            internal_command,
+                            terminal.term_id,
+                        );
+                    }
+                })
                 .on_event_cont(
                     EventListener::PointerDown,
                     move |_| {
@@ -217,15 +292,51 @@ fn terminal_tab_header(window_tab_data: Rc<WindowTabData>) -> impl View {
             let size = scroll_size.get();
             s.size(size.width, size.height)
         }),
-        container(clickable_icon(
-            || LapceIcons::ADD,
-            move || {
-                workbench_command.send(LapceWorkbenchCommand::NewTerminalTab);
+        stack((
+            clickable_icon(
+                || LapceIcons::ADD,
+                move || {
+                    workbench_command.send(LapceWorkbenchCommand::NewTerminalTab);
+                },
+                || false,
+                || false,
+                || "New Terminal",
+                config,
+            ),
+            {
+                let panel = panel.clone();
+                let icon_panel = panel.clone();
+                let click_panel = panel.clone();
+                let tooltip_panel = panel.clone();
+                clickable_icon(
+                    move || {
+                        if icon_panel.panel_bottom_maximized(true) {
+                            LapceIcons::PANEL_RESTORE
+                        } else {
+                            LapceIcons::PANEL_MAXIMISE
+                        }
+                    },
+                    move || click_panel.toggle_maximize(&PanelKind::Terminal),
+                    || false,
+                    || false,
+                    move || {
+                        if tooltip_panel.panel_bottom_maximized(false) {
+                            "Restore Panel"
+                        } else {
+                            "Maximize Panel"
+                        }
+                    },
+                    config,
+                )
+                .style(move |s| {
+                    // Only the bottom panels can be maximized
+                    let is_bottom = panel
+                        .panels
+                        .with(|panels| panel_position(panels, &PanelKind::Terminal))
+                        .is_some_and(|(_, position)| position.is_bottom());
+                    s.margin_left(6.0).apply_if(!is_bottom, |s| s.hide())
+                })
             },
-            || false,
-            || false,
-            || "New Terminal",
-            config,
         ))
         .on_resize(move |rect| {
             let width = rect.size().width;
@@ -283,6 +394,7 @@ fn terminal_tab_split(
         move |(index, terminal)| {
             let terminal_panel_data = terminal_panel_data.clone();
             let terminal_scope = terminal.scope;
+            let split_size = terminal.split_size;
             container({
                 let terminal_view = terminal_view(
                     terminal.term_id,
@@ -311,6 +423,35 @@ fn terminal_tab_split(
                             );
                         }
                     })
+                    .on_event(EventListener::Drop, {
+                        let terminal = terminal.clone();
+                        move |_| {
+                            let dragging = terminal.common.dragging;
+                            if let Some(DragContent::File(path)) =
+                                dragging.get_untracked()
+                            {
+                                dragging.set(None);
+                                terminal.paste_paths(&[path]);
+                                active.set(index.get_untracked());
+                                EventPropagation::Stop
+                            } else {
+                                EventPropagation::Continue
+                            }
+                        }
+                    })
+                    .on_event_stop(EventListener::DroppedFile, {
+                        let terminal = terminal.clone();
+                        move |event| {
+                            if let Event::DroppedFile(file) = event {
+                                terminal.paste_paths(&[file.path.clone()]);
+                                active.set(index.get_untracked());
+                                terminal
+                                    .common
+                                    .focus
+                                    .set(Focus::Panel(PanelKind::Terminal));
+                            }
+                        }
+                    })
                     .on_event(EventListener::PointerWheel, move |event| {
                         if let Event::PointerWheel(pointer_event) = event {
                             terminal.clone().wheel_scroll(pointer_event.delta.y);
@@ -325,14 +466,16 @@ fn terminal_tab_split(
                     .style(|s| s.size_pct(100.0, 100.0))
             })
             .style(move |s| {
-                s.size_pct(100.0, 100.0).padding_horiz(10.0).apply_if(
-                    index.get() > 0,
-                    |s| {
+                s.height_pct(100.0)
+                    .min_width(0.0)
+                    .flex_basis(0.0)
+                    .flex_grow(split_size.get() as f32)
+                    .padding_horiz(10.0)
+                    .apply_if(index.get() > 0, |s| {
                         s.border_left(1.0).border_color(
                             config.get().color(LapceColor::LAPCE_BORDER),
                         )
-                    },
-                )
+                    })
             })
         },
     )
@@ -355,6 +498,17 @@ fn terminal_tab_content(window_tab_data: Rc<WindowTabData>) -> impl View {
     .style(|s| s.size_pct(100.0, 100.0))
 }
 
+fn tab_header_secondary_click(
+    internal_command: Listener<InternalCommand>,
+    term_id: TermId,
+) {
+    let menu =
+        Menu::new("").entry(MenuItem::new("Duplicate Terminal").action(move || {
+            internal_command.send(InternalCommand::DuplicateTerminal { term_id });
+        }));
+    show_context_menu(menu, None);
+}
+
 fn tab_secondary_click(
     internal_command: Listener<InternalCommand>,
     view_id: ViewId,
@@ -379,3 +533,62 @@ fn tab_secondary_click(
         }));
     show_context_menu(menu, None);
 }
+
+/// Format the CPU usage and the resident memory of a process compactly, e.g.
+/// `12% 340M`.
+fn format_process_usage(usage: &TerminalProcessUsage) -> String {
+    const UNITS: [&str; 4] = ["B", "K", "M", "G"];
+    let mut memory = usage.memory as f64;
+    let mut unit = 0;
+    while memory >= 1024.0 && unit < UNITS.len() - 1 {
+        memory /= 1024.0;
+        unit += 1;
+    }
+    let memory = if unit > 0 && memory < 10.0 {
+        format!("{memory:.1}{}", UNITS[unit])
+    } else {
+        format!("{memory:.0}{}", UNITS[unit])
+    };
+    format!("{:.0}% {memory}", usage.cpu)
+}
+
+/// Shorten `s` to at most `max_chars` characters by replacing its middle with
+/// an ellipsis, keeping as many characters of its start as of its end, or one
+/// more of its end. Characters are counted rather than their width measured,
+/// so wide characters take more room than their count suggests. A
+/// `max_chars` of 0 leaves `s` as it is.
+fn middle_truncate(s: &str, max_chars: usize) -> String {
+    let len = s.chars().count();
+    if len <= max_chars || max_chars == 0 {
+        return s.to_string();
+    }
+    let keep = max_chars - 1;
+    let tail = keep / 2 + keep % 2;
+    let head = keep - tail;
+    let mut truncated: String = s.chars().take(head).collect();
+    truncated.push('…');
+    truncated.extend(s.chars().skip(len - tail));
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::middle_truncate;
+
+    #[test]
+    fn test_middle_truncate() {
+        assert_eq!(middle_truncate("/home/user", 20), "/home/user");
+        assert_eq!(middle_truncate("/home/user", 10), "/home/user");
+        assert_eq!(
+            middle_truncate("/home/user/projects/lapce", 11),
+            "/home…lapce"
+        );
+        assert_eq!(
+            middle_truncate("/home/user/projects/lapce", 10),
+            "/hom…lapce"
+        );
+        assert_eq!(middle_truncate("~/πρόγραμμα/src", 7), "~/π…src");
+        assert_eq!(middle_truncate("/home/user", 1), "…");
+        assert_eq!(middle_truncate("/home/user", 0), "/home/user");
+    }
+}
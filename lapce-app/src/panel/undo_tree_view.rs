@@ -0,0 +1,106 @@
+use std::{rc::Rc, time::SystemTime};
+
+use floem::{
+    reactive::{SignalGet, SignalWith},
+    style::CursorStyle,
+    views::{container, dyn_stack, label, scroll, stack, Decorators},
+    View,
+};
+
+use super::position::PanelPosition;
+use crate::{
+    config::color::LapceColor,
+    undo_tree::{time_ago, UndoTreeRow},
+    window_tab::WindowTabData,
+};
+
+pub fn undo_tree_panel(
+    window_tab_data: Rc<WindowTabData>,
+    _position: PanelPosition,
+) -> impl View {
+    let config = window_tab_data.common.config;
+    let ui_line_height = window_tab_data.common.ui_line_height;
+    let active_editor = window_tab_data.main_split.active_editor;
+
+    // The states of the document of the active editor, newest first.
+    let rows = move || {
+        let Some(doc) = active_editor.get().map(|editor| editor.doc_signal().get())
+        else {
+            return Vec::new();
+        };
+        let now = SystemTime::now();
+        doc.undo_tree.with(|tree| {
+            tree.rows()
+                .into_iter()
+                .map(|row| {
+                    let is_current = row.state == tree.current();
+                    let (time, restored_from) = tree
+                        .state(row.state)
+                        .map(|state| {
+                            (time_ago(state.time, now), state.restored_from)
+                        })
+                        .unwrap_or_default();
+                    let name = match restored_from {
+                        Some(from) => format!("#{} (#{from})", row.state),
+                        None => format!("#{}", row.state),
+                    };
+                    (row, is_current, name, time)
+                })
+                .collect::<Vec<_>>()
+        })
+    };
+
+    container(
+        scroll(
+            dyn_stack(
+                rows,
+                |(row, is_current, name, time)| {
+                    (row.clone(), *is_current, name.clone(), time.clone())
+                },
+                move |(UndoTreeRow { state, graph }, is_current, name, time)| {
+                    stack((
+                        label(move || graph.clone()).style(move |s| {
+                            let config = config.get();
+                            s.font_family(config.editor.font_family.clone())
+                                .margin_right(10.0)
+                                .color(config.color(LapceColor::EDITOR_DIM))
+                        }),
+                        label(move || name.clone()).style(|s| s.margin_right(10.0)),
+                        label(move || time.clone()).style(move |s| {
+                            s.color(config.get().color(LapceColor::EDITOR_DIM))
+                        }),
+                    ))
+                    .on_click_stop(move |_| {
+                        if let Some(editor) = active_editor.get_untracked() {
+                            editor.doc().restore_undo_state(state);
+                        }
+                    })
+                    .style(move |s| {
+                        let config = config.get();
+                        s.padding_horiz(10.0)
+                            .min_width_full()
+                            .items_center()
+                            .selectable(false)
+                            .height(ui_line_height.get())
+                            .apply_if(is_current, |s| {
+                                s.background(
+                                    config
+                                        .color(LapceColor::PANEL_CURRENT_BACKGROUND),
+                                )
+                            })
+                            .hover(|s| {
+                                s.cursor(CursorStyle::Pointer).background(
+                                    config
+                                        .color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                                )
+                            })
+                    })
+                },
+            )
+            .style(|s| s.flex_col().min_width_full()),
+        )
+        .style(|s| s.absolute().size_full()),
+    )
+    .style(|s| s.size_full())
+    .debug_name("Undo Tree Panel")
+}
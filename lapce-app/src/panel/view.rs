@@ -17,14 +17,18 @@
 };
 
 use super::{
+    bookmark_view::bookmark_panel,
     debug_view::debug_panel,
     global_search_view::global_search_panel,
     kind::PanelKind,
+    output_view::output_panel,
     plugin_view::plugin_panel,
     position::{PanelContainerPosition, PanelPosition},
     problem_view::problem_panel,
     source_control_view::source_control_panel,
+    task_view::task_panel,
     terminal_view::terminal_panel,
+    undo_tree_view::undo_tree_panel,
 };
 use crate::{
     app::{clickable_icon, clickable_icon_base},
@@ -35,7 +39,7 @@
         implementation_view::implementation_panel,
         references_view::references_panel,
     },
-    window_tab::{DragContent, WindowTabData},
+    window_tab::{DragContent, Focus, WindowTabData},
 };
 
 pub fn foldable_panel_section(
@@ -453,6 +457,8 @@ fn panel_view(
     position: PanelPosition,
 ) -> impl View {
     let panel = window_tab_data.panel.clone();
+    let focus = window_tab_data.common.focus;
+    let config = window_tab_data.common.config;
     let panels = move || {
         panel
             .panels
@@ -505,16 +511,42 @@ fn panel_view(
                     implementation_panel(window_tab_data.clone(), position)
                         .into_any()
                 }
+                PanelKind::UndoTree => {
+                    undo_tree_panel(window_tab_data.clone(), position).into_any()
+                }
+                PanelKind::Bookmarks => {
+                    bookmark_panel(window_tab_data.clone(), position).into_any()
+                }
+                PanelKind::Output => {
+                    output_panel(window_tab_data.clone(), position).into_any()
+                }
+                PanelKind::Task => {
+                    task_panel(window_tab_data.clone(), position).into_any()
+                }
             };
             view.style(|s| s.size_pct(100.0, 100.0))
         },
     )
     .style(move |s| {
-        s.size_pct(100.0, 100.0).apply_if(
-            !panel.is_position_shown(&position, true)
-                || panel.is_position_empty(&position, true),
-            |s| s.hide(),
-        )
+        // Outline the panel that has the keyboard focus, which unlike a
+        // border doesn't take any room from its content
+        let focused = match focus.get() {
+            Focus::Panel(kind) => panel
+                .active_panel_at_position(&position, true)
+                .is_some_and(|(active, _)| active == kind),
+            _ => false,
+        };
+        s.size_pct(100.0, 100.0)
+            .apply_if(focused, |s| {
+                s.outline(1.0).outline_color(
+                    config.get().color(LapceColor::LAPCE_TAB_ACTIVE_UNDERLINE),
+                )
+            })
+            .apply_if(
+                !panel.is_position_shown(&position, true)
+                    || panel.is_position_empty(&position, true),
+                |s| s.hide(),
+            )
     })
 }
 
@@ -558,9 +590,13 @@ fn panel_picker(
                 PanelKind::Problem => "Problems",
                 PanelKind::Debug => "Debug",
                 PanelKind::CallHierarchy => "Call Hierarchy",
-                PanelKind::DocumentSymbol => "Document Symbol",
+                PanelKind::DocumentSymbol => "Outline",
                 PanelKind::References => "References",
                 PanelKind::Implementation => "Implementation",
+                PanelKind::UndoTree => "Undo Tree",
+                PanelKind::Bookmarks => "Bookmarks",
+                PanelKind::Output => "Output",
+                PanelKind::Task => "Tasks",
             };
             let icon = p.svg_name();
             let is_active = {
@@ -0,0 +1,416 @@
+//! Peeking at the definitions or references of the symbol at the cursor in a
+//! widget shown beneath its line, with the locations listed next to an editor
+//! previewing the selected one.
+
+use std::{path::Path, rc::Rc};
+
+use floem::{
+    event::EventListener,
+    ext_event::create_ext_action,
+    keyboard::Modifiers,
+    kurbo::{Point, Vec2},
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+    style::{CursorStyle, Position},
+    views::{container, dyn_stack, label, scroll, stack, Decorators},
+    View,
+};
+use lapce_core::{
+    buffer::rope_text::RopeText, command::FocusCommand, cursor::CursorAffinity,
+    mode::Mode, movement::Movement,
+};
+use lapce_rpc::proxy::ProxyResponse;
+use lsp_types::{GotoDefinitionResponse, Location};
+
+use crate::{
+    command::{CommandExecuted, CommandKind, LapceCommand},
+    config::color::LapceColor,
+    editor::{
+        location::{EditorLocation, EditorPosition},
+        view::editor_container_view,
+        EditorData,
+    },
+    keypress::{condition::Condition, KeyPressFocus},
+    lsp::path_from_url,
+    main_split::MainSplitData,
+    window_tab::{Focus, WindowTabData},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeekKind {
+    Definition,
+    References,
+}
+
+impl PeekKind {
+    fn title(&self) -> &'static str {
+        match self {
+            PeekKind::Definition => "Definitions",
+            PeekKind::References => "References",
+        }
+    }
+}
+
+/// All the locations of a response to a definition request.
+pub fn definition_locations(definition: GotoDefinitionResponse) -> Vec<Location> {
+    match definition {
+        GotoDefinitionResponse::Scalar(location) => vec![location],
+        GotoDefinitionResponse::Array(locations) => locations,
+        GotoDefinitionResponse::Link(links) => links
+            .into_iter()
+            .map(|link| Location {
+                uri: link.target_uri,
+                range: link.target_selection_range,
+            })
+            .collect(),
+    }
+}
+
+#[derive(Clone)]
+pub struct PeekData {
+    pub active: RwSignal<bool>,
+    pub kind: RwSignal<PeekKind>,
+    /// The editor peeked from and the offset of the symbol peeked at.
+    pub editor: RwSignal<Option<(EditorData, usize)>>,
+    pub locations: RwSignal<Vec<EditorLocation>>,
+    pub index: RwSignal<usize>,
+    pub preview_editor: RwSignal<EditorData>,
+    pub main_split: MainSplitData,
+}
+
+impl std::fmt::Debug for PeekData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeekData").finish()
+    }
+}
+
+impl KeyPressFocus for PeekData {
+    fn get_mode(&self) -> Mode {
+        Mode::Insert
+    }
+
+    fn check_condition(&self, condition: Condition) -> bool {
+        matches!(condition, Condition::ListFocus | Condition::ModalFocus)
+    }
+
+    fn run_command(
+        &self,
+        command: &LapceCommand,
+        _count: Option<usize>,
+        _mods: Modifiers,
+    ) -> CommandExecuted {
+        match &command.kind {
+            CommandKind::Focus(cmd) => self.run_focus_command(cmd),
+            _ => CommandExecuted::No,
+        }
+    }
+
+    fn receive_char(&self, _c: &str) {}
+}
+
+impl PeekData {
+    pub fn new(cx: Scope, main_split: MainSplitData) -> Self {
+        let common = main_split.common.clone();
+        let preview_editor = main_split.editors.make_local(cx, common.clone());
+        let peek = Self {
+            active: cx.create_rw_signal(false),
+            kind: cx.create_rw_signal(PeekKind::Definition),
+            editor: cx.create_rw_signal(None),
+            locations: cx.create_rw_signal(Vec::new()),
+            index: cx.create_rw_signal(0),
+            preview_editor: cx.create_rw_signal(preview_editor),
+            main_split,
+        };
+
+        {
+            let peek = peek.clone();
+            cx.create_effect(move |_| {
+                let focus = common.focus.get();
+                if focus != Focus::Peek && peek.active.get_untracked() {
+                    peek.active.set(false);
+                    peek.editor.set(None);
+                }
+            });
+        }
+
+        peek
+    }
+
+    /// Request the definitions or references of the symbol at the cursor of
+    /// `editor`, and peek at them if there are any.
+    pub fn peek(&self, editor: EditorData, kind: PeekKind) {
+        let doc = editor.doc();
+        let Some(path) = doc
+            .loaded()
+            .then(|| doc.content.with_untracked(|c| c.path().cloned()))
+            .flatten()
+        else {
+            return;
+        };
+        let offset = editor.cursor().with_untracked(|c| c.offset());
+        let position = doc
+            .buffer
+            .with_untracked(|buffer| buffer.offset_to_position(offset));
+
+        let peek = self.clone();
+        let cursor = editor.cursor().read_only();
+        let send = create_ext_action(self.main_split.scope, move |locations| {
+            // The cursor moved on while waiting.
+            if cursor.with_untracked(|c| c.offset()) != offset {
+                return;
+            }
+            peek.show(editor, offset, kind, locations);
+        });
+        let proxy = &self.main_split.common.proxy;
+        match kind {
+            PeekKind::Definition => {
+                proxy.get_definition(offset, path, position, move |result| {
+                    if let Ok(ProxyResponse::GetDefinitionResponse {
+                        definition,
+                        ..
+                    }) = result
+                    {
+                        send(definition_locations(definition));
+                    }
+                });
+            }
+            PeekKind::References => {
+                proxy.get_references(path, position, move |result| {
+                    if let Ok(ProxyResponse::GetReferencesResponse { references }) =
+                        result
+                    {
+                        send(references);
+                    }
+                });
+            }
+        }
+    }
+
+    fn show(
+        &self,
+        editor: EditorData,
+        offset: usize,
+        kind: PeekKind,
+        locations: Vec<Location>,
+    ) {
+        if locations.is_empty() {
+            return;
+        }
+        self.locations.set(
+            locations
+                .into_iter()
+                .map(|l| EditorLocation {
+                    path: path_from_url(&l.uri),
+                    position: Some(EditorPosition::Position(l.range.start)),
+                    scroll_offset: None,
+                    ignore_unconfirmed: false,
+                    same_editor_tab: false,
+                })
+                .collect(),
+        );
+        self.kind.set(kind);
+        self.editor.set(Some((editor, offset)));
+        self.active.set(true);
+        self.select(0);
+        self.main_split.common.focus.set(Focus::Peek);
+    }
+
+    pub fn close(&self) {
+        self.active.set(false);
+        self.editor.set(None);
+        self.main_split.common.focus.set(Focus::Workbench);
+    }
+
+    /// Preview the location at `index` of the list.
+    pub fn select(&self, index: usize) {
+        let Some(location) = self
+            .locations
+            .with_untracked(|locations| locations.get(index).cloned())
+        else {
+            return;
+        };
+        self.index.set(index);
+        let (doc, new_doc) = self.main_split.get_doc(location.path.clone(), None);
+        let preview_editor = self.preview_editor.get_untracked();
+        preview_editor.update_doc(doc);
+        preview_editor.go_to_location(location, new_doc, None);
+    }
+
+    fn next(&self) {
+        let len = self.locations.with_untracked(|l| l.len());
+        let index =
+            Movement::Down.update_index(self.index.get_untracked(), len, 1, true);
+        self.select(index);
+    }
+
+    fn previous(&self) {
+        let len = self.locations.with_untracked(|l| l.len());
+        let index =
+            Movement::Up.update_index(self.index.get_untracked(), len, 1, true);
+        self.select(index);
+    }
+
+    /// Leave the peek for the location previewed.
+    pub fn open(&self) {
+        let location = self.locations.with_untracked(|locations| {
+            locations.get(self.index.get_untracked()).cloned()
+        });
+        self.close();
+        if let Some(location) = location {
+            self.main_split.jump_to_location(location, None);
+        }
+    }
+
+    fn run_focus_command(&self, cmd: &FocusCommand) -> CommandExecuted {
+        match cmd {
+            FocusCommand::ModalClose => self.close(),
+            FocusCommand::ListNext => self.next(),
+            FocusCommand::ListPrevious => self.previous(),
+            FocusCommand::ListSelect => self.open(),
+            _ => return CommandExecuted::No,
+        }
+        CommandExecuted::Yes
+    }
+
+    /// Where to show the peek, below the line of the symbol peeked at.
+    fn origin(&self, window_origin: Point) -> Point {
+        let Some((editor, offset)) = self.editor.get() else {
+            return Point::ZERO;
+        };
+        let (_, point_below) = editor
+            .editor
+            .points_of_offset(offset, CursorAffinity::Forward);
+        let viewport = editor.viewport().get();
+        let editor_origin = editor.window_origin().get() - window_origin.to_vec2();
+        editor_origin + Vec2::new(0.0, point_below.y - viewport.y0)
+    }
+}
+
+fn peek_location_name(
+    location: &EditorLocation,
+    workspace: Option<&Path>,
+) -> String {
+    let path = workspace
+        .and_then(|workspace| location.path.strip_prefix(workspace).ok())
+        .unwrap_or(&location.path);
+    let line = match location.position {
+        Some(EditorPosition::Position(position)) => position.line as usize + 1,
+        _ => 1,
+    };
+    format!("{}:{line}", path.display())
+}
+
+pub fn peek(window_tab_data: Rc<WindowTabData>) -> impl View {
+    let data = window_tab_data.peek.clone();
+    let common = window_tab_data.common.clone();
+    let config = common.config;
+    let workspace = window_tab_data.workspace.clone();
+    let active = data.active;
+    let kind = data.kind;
+    let index = data.index;
+    let locations = data.locations;
+
+    let location_list =
+        scroll(
+            dyn_stack(
+                move || {
+                    let workspace = workspace.path.as_deref();
+                    locations.with(|locations| {
+                        locations
+                            .iter()
+                            .map(|l| peek_location_name(l, workspace))
+                            .enumerate()
+                            .collect::<Vec<_>>()
+                    })
+                },
+                |(i, name)| (*i, name.clone()),
+                {
+                    let data = data.clone();
+                    move |(i, name)| {
+                        let data = data.clone();
+                        label(move || name.clone())
+                            .on_click_stop({
+                                let data = data.clone();
+                                move |_| data.select(i)
+                            })
+                            .on_double_click_stop(move |_| data.open())
+                            .style(move |s| {
+                                let config = config.get();
+                                s.width_full()
+                                    .padding_horiz(10.0)
+                                    .line_height(1.6)
+                                    .text_ellipsis()
+                                    .hover(|s| {
+                                        s.cursor(CursorStyle::Pointer).background(
+                                            config.color(
+                                                LapceColor::PANEL_HOVERED_BACKGROUND,
+                                            ),
+                                        )
+                                    })
+                                    .apply_if(index.get() == i, |s| {
+                                        s.background(config.color(
+                                            LapceColor::PANEL_CURRENT_BACKGROUND,
+                                        ))
+                                    })
+                            })
+                    }
+                },
+            )
+            .style(|s| s.flex_col().width_full()),
+        )
+        .style(move |s| {
+            let config = config.get();
+            s.width(250.0)
+                .height_full()
+                .border_left(1.0)
+                .border_color(config.color(LapceColor::LAPCE_BORDER))
+                .background(config.color(LapceColor::PANEL_BACKGROUND))
+        });
+
+    let preview = container(editor_container_view(
+        window_tab_data.clone(),
+        window_tab_data.workspace.clone(),
+        |_tracked: bool| false,
+        data.preview_editor,
+    ))
+    .style(|s| s.flex_grow(1.0).flex_basis(0.0).height_full());
+
+    stack((
+        label(move || {
+            format!("{} ({})", kind.get().title(), locations.with(|l| l.len()))
+        })
+        .style(move |s| {
+            let config = config.get();
+            s.width_full()
+                .padding_horiz(10.0)
+                .line_height(1.6)
+                .border_bottom(1.0)
+                .border_color(config.color(LapceColor::LAPCE_BORDER))
+                .color(config.color(LapceColor::EDITOR_DIM))
+        }),
+        stack((preview, location_list))
+            .style(|s| s.width_full().flex_grow(1.0).min_height(0.0)),
+    ))
+    .on_event_stop(EventListener::PointerMove, |_| {})
+    .on_event_stop(EventListener::PointerDown, |_| {})
+    .style(move |s| {
+        let config = config.get();
+        let origin = data.origin(common.window_origin.get());
+        let width = data
+            .editor
+            .with(|editor| editor.as_ref().map(|(e, _)| e.viewport().get().width()))
+            .unwrap_or(0.0);
+        s.position(Position::Absolute)
+            .flex_col()
+            .width(width.max(400.0) as f32)
+            .height(config.editor.line_height() as f32 * 15.0)
+            .margin_left(origin.x as f32)
+            .margin_top(origin.y as f32)
+            .border_top(1.0)
+            .border_bottom(1.0)
+            .border_color(config.color(LapceColor::EDITOR_FOCUS))
+            .color(config.color(LapceColor::EDITOR_FOREGROUND))
+            .background(config.color(LapceColor::EDITOR_BACKGROUND))
+            .apply_if(!active.get(), |s| s.hide())
+    })
+    .debug_name("Peek")
+}
@@ -0,0 +1,436 @@
+//! Timings of painting, layout, input and proxy requests, charted by the
+//! performance overlay toggled with `Developer: Toggle Performance Overlay`.
+//!
+//! Nothing is recorded unless an overlay is shown.
+
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use floem::{
+    action::exec_after,
+    context::PaintCx,
+    peniko::{
+        kurbo::{Point, Rect, Size},
+        Color,
+    },
+    reactive::{create_effect, ReadSignal, SignalGet},
+    text::{Attrs, AttrsList, TextLayout},
+    views::Decorators,
+    Renderer, View, ViewId,
+};
+use lapce_rpc::proxy::ProxyRpcHandler;
+
+use crate::{
+    config::{color::LapceColor, LapceConfig},
+    window_tab::WindowTabData,
+};
+
+/// The number of frames kept for the charts.
+const HISTORY: usize = 120;
+/// The time a frame can take at 60 frames per second.
+const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+
+const OVERLAY_WIDTH: f64 = 320.0;
+const ROW_TEXT_HEIGHT: f64 = 18.0;
+const CHART_HEIGHT: f64 = 24.0;
+const PADDING: f64 = 8.0;
+/// The frame chart, one chart per [`PerfSubtree`], the input chart and the
+/// proxy chart.
+const ROWS: usize = 6;
+
+/// The number of overlays shown, across all windows.
+static OVERLAYS: AtomicUsize = AtomicUsize::new(0);
+static STATS: Mutex<PerfStats> = Mutex::new(PerfStats::new());
+
+/// The views whose painting and layout are timed for the overlay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PerfSubtree {
+    Editor,
+    Gutter,
+    Terminal,
+}
+
+impl PerfSubtree {
+    fn name(&self) -> &'static str {
+        match self {
+            PerfSubtree::Editor => "Editor",
+            PerfSubtree::Gutter => "Gutter",
+            PerfSubtree::Terminal => "Terminal",
+        }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    OVERLAYS.load(Ordering::Relaxed) > 0
+}
+
+fn stats() -> std::sync::MutexGuard<'static, PerfStats> {
+    STATS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Start timing the painting of `subtree`, which is recorded when the
+/// returned guard is dropped.
+pub fn time_paint(subtree: PerfSubtree) -> Option<PaintTimer> {
+    is_enabled().then(|| PaintTimer {
+        subtree,
+        start: Instant::now(),
+    })
+}
+
+/// Note an input event, whose latency is the time until the next frame is
+/// painted.
+pub fn record_input() {
+    if is_enabled() {
+        stats().pending_input.get_or_insert_with(Instant::now);
+    }
+}
+
+/// Count a layout of `subtree`.
+pub fn record_layout(subtree: PerfSubtree) {
+    if is_enabled() {
+        stats().subtree(subtree).frame_layouts += 1;
+    }
+}
+
+pub struct PaintTimer {
+    subtree: PerfSubtree,
+    start: Instant,
+}
+
+impl Drop for PaintTimer {
+    fn drop(&mut self) {
+        stats().subtree(self.subtree).frame_paint += self.start.elapsed();
+    }
+}
+
+#[derive(Default)]
+struct SubtreeStats {
+    /// The total paint time of all the views of the subtree, per frame.
+    paints: VecDeque<Duration>,
+    /// The number of layouts of the views of the subtree, per frame.
+    layouts: VecDeque<usize>,
+    frame_paint: Duration,
+    frame_layouts: usize,
+}
+
+struct PerfStats {
+    subtrees: BTreeMap<PerfSubtree, SubtreeStats>,
+    /// The time between the starts of consecutive frames.
+    frames: VecDeque<Duration>,
+    last_frame: Option<Instant>,
+    /// The time between input events and the frames painted after them.
+    inputs: VecDeque<Duration>,
+    /// The earliest input event not painted yet.
+    pending_input: Option<Instant>,
+}
+
+impl PerfStats {
+    const fn new() -> Self {
+        Self {
+            subtrees: BTreeMap::new(),
+            frames: VecDeque::new(),
+            last_frame: None,
+            inputs: VecDeque::new(),
+            pending_input: None,
+        }
+    }
+
+    fn subtree(&mut self, subtree: PerfSubtree) -> &mut SubtreeStats {
+        self.subtrees.entry(subtree).or_default()
+    }
+
+    fn end_frame(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            push_bounded(&mut self.frames, now - last_frame);
+        }
+        self.last_frame = Some(now);
+        if let Some(input) = self.pending_input.take() {
+            push_bounded(&mut self.inputs, now - input);
+        }
+        for stats in self.subtrees.values_mut() {
+            push_bounded(&mut stats.paints, std::mem::take(&mut stats.frame_paint));
+            push_bounded(
+                &mut stats.layouts,
+                std::mem::take(&mut stats.frame_layouts),
+            );
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+fn push_bounded<T>(history: &mut VecDeque<T>, value: T) {
+    if history.len() == HISTORY {
+        history.pop_front();
+    }
+    history.push_back(value);
+}
+
+/// A frame is considered dropped when it took longer than two frames.
+fn is_dropped(frame: Duration) -> bool {
+    frame > FRAME_BUDGET * 2
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn summary(durations: &[Duration]) -> (f64, f64) {
+    let max = durations.iter().max().copied().unwrap_or_default();
+    let avg = if durations.is_empty() {
+        Duration::ZERO
+    } else {
+        durations.iter().sum::<Duration>() / durations.len() as u32
+    };
+    (millis(avg), millis(max))
+}
+
+/// One labelled chart of the overlay.
+struct PerfRow {
+    label: String,
+    /// The values of the chart, and whether each is over budget.
+    values: Vec<(f64, bool)>,
+    /// The value charted at the full height.
+    scale: f64,
+}
+
+pub struct PerfOverlayView {
+    id: ViewId,
+    config: ReadSignal<Arc<LapceConfig>>,
+    proxy: ProxyRpcHandler,
+    visible: ReadSignal<bool>,
+    repaint_scheduled: Rc<Cell<bool>>,
+    /// Whether the overlay is counted in [`OVERLAYS`], to stop counting it
+    /// when its window tab is closed.
+    counted: Rc<Cell<bool>>,
+}
+
+/// The performance overlay of a window tab, shown in its top right corner
+/// while `show_perf_overlay` is set.
+pub fn perf_overlay(window_tab_data: Rc<WindowTabData>) -> PerfOverlayView {
+    let visible = window_tab_data.show_perf_overlay;
+    let counted = Rc::new(Cell::new(false));
+    create_effect({
+        let counted = counted.clone();
+        move |_| {
+            let shown = visible.get();
+            if shown != counted.replace(shown) {
+                set_overlay_shown(shown);
+            }
+        }
+    });
+    PerfOverlayView {
+        id: ViewId::new(),
+        config: window_tab_data.common.config,
+        proxy: window_tab_data.common.proxy.clone(),
+        visible: visible.read_only(),
+        repaint_scheduled: Rc::new(Cell::new(false)),
+        counted,
+    }
+    .style(move |s| {
+        s.absolute()
+            .inset_top(PADDING)
+            .inset_right(PADDING)
+            .width(OVERLAY_WIDTH)
+            .height(
+                PADDING + ROWS as f64 * (ROW_TEXT_HEIGHT + CHART_HEIGHT + PADDING),
+            )
+            .apply_if(!visible.get(), |s| s.hide())
+    })
+}
+
+/// Start or stop recording for an overlay being shown or hidden.
+fn set_overlay_shown(shown: bool) {
+    if shown {
+        OVERLAYS.fetch_add(1, Ordering::Relaxed);
+    } else if OVERLAYS.fetch_sub(1, Ordering::Relaxed) == 1 {
+        stats().clear();
+    }
+}
+
+impl PerfOverlayView {
+    fn rows(&self) -> Vec<PerfRow> {
+        let budget = millis(FRAME_BUDGET);
+        let mut rows = Vec::new();
+        let stats = stats();
+
+        let frames: Vec<Duration> = stats.frames.iter().copied().collect();
+        let (avg, max) = summary(&frames);
+        let dropped = frames.iter().filter(|f| is_dropped(**f)).count();
+        rows.push(PerfRow {
+            label: format!(
+                "Frames: avg {avg:.1} ms, max {max:.1} ms, {dropped} dropped"
+            ),
+            values: frames
+                .iter()
+                .map(|f| (millis(*f), is_dropped(*f)))
+                .collect(),
+            scale: budget * 3.0,
+        });
+
+        for subtree in [
+            PerfSubtree::Editor,
+            PerfSubtree::Gutter,
+            PerfSubtree::Terminal,
+        ] {
+            let (paints, layouts) = stats
+                .subtrees
+                .get(&subtree)
+                .map(|s| {
+                    (
+                        s.paints.iter().copied().collect::<Vec<_>>(),
+                        s.layouts.iter().sum::<usize>(),
+                    )
+                })
+                .unwrap_or_default();
+            let (avg, max) = summary(&paints);
+            let layouts = layouts as f64 / paints.len().max(1) as f64;
+            rows.push(PerfRow {
+                label: format!(
+                    "{} paint: avg {avg:.2} ms, max {max:.2} ms, {layouts:.1} layouts",
+                    subtree.name()
+                ),
+                values: paints
+                    .iter()
+                    .map(|p| (millis(*p), *p > FRAME_BUDGET / 2))
+                    .collect(),
+                scale: budget,
+            });
+        }
+
+        let inputs: Vec<Duration> = stats.inputs.iter().copied().collect();
+        let (avg, max) = summary(&inputs);
+        rows.push(PerfRow {
+            label: format!("Input latency: avg {avg:.1} ms, max {max:.1} ms"),
+            values: inputs
+                .iter()
+                .map(|i| (millis(*i), *i > FRAME_BUDGET))
+                .collect(),
+            scale: budget * 3.0,
+        });
+        drop(stats);
+
+        let latencies = self.proxy.recent_latencies();
+        let (avg, max) = summary(&latencies);
+        rows.push(PerfRow {
+            label: format!("Proxy requests: avg {avg:.1} ms, max {max:.1} ms"),
+            values: latencies
+                .iter()
+                .map(|l| (millis(*l), *l > Duration::from_millis(100)))
+                .collect(),
+            scale: 100.0,
+        });
+
+        rows
+    }
+
+    fn paint_chart(
+        &self,
+        cx: &mut PaintCx,
+        rect: Rect,
+        row: &PerfRow,
+        normal: Color,
+        over_budget: Color,
+    ) {
+        let bar_width = rect.width() / HISTORY as f64;
+        // Right align the bars so the most recent value is always at the edge.
+        let x0 = rect.x1 - row.values.len() as f64 * bar_width;
+        for (i, (value, over)) in row.values.iter().enumerate() {
+            let height = (value / row.scale).min(1.0) * rect.height();
+            let x = x0 + i as f64 * bar_width;
+            cx.fill(
+                &Rect::new(x, rect.y1 - height, x + bar_width, rect.y1),
+                if *over { over_budget } else { normal },
+                0.0,
+            );
+        }
+    }
+
+    /// Keep painting every frame while shown, so the frame times are those
+    /// of the UI rather than of whatever last requested a paint.
+    fn schedule_repaint(&self) {
+        if self.repaint_scheduled.get() {
+            return;
+        }
+        self.repaint_scheduled.set(true);
+        let id = self.id;
+        let visible = self.visible;
+        let repaint_scheduled = self.repaint_scheduled.clone();
+        exec_after(FRAME_BUDGET, move |_| {
+            repaint_scheduled.set(false);
+            if visible.get_untracked() {
+                id.request_paint();
+            }
+        });
+    }
+}
+
+impl Drop for PerfOverlayView {
+    fn drop(&mut self) {
+        if self.counted.replace(false) {
+            set_overlay_shown(false);
+        }
+    }
+}
+
+impl View for PerfOverlayView {
+    fn id(&self) -> ViewId {
+        self.id
+    }
+
+    fn debug_name(&self) -> std::borrow::Cow<'static, str> {
+        "Performance Overlay".into()
+    }
+
+    fn paint(&mut self, cx: &mut PaintCx) {
+        if !self.visible.get_untracked() {
+            return;
+        }
+        stats().end_frame();
+        self.schedule_repaint();
+
+        let config = self.config.get_untracked();
+        let layout = self.id.get_layout().unwrap_or_default();
+        let rect =
+            Size::new(layout.size.width as f64, layout.size.height as f64).to_rect();
+        cx.fill(
+            &rect,
+            config
+                .color(LapceColor::PANEL_BACKGROUND)
+                .with_alpha_factor(0.9),
+            0.0,
+        );
+        cx.stroke(&rect, config.color(LapceColor::LAPCE_BORDER), 1.0);
+
+        let family = config.ui.font_family();
+        let attrs = Attrs::new()
+            .family(&family)
+            .font_size(config.ui.font_size() as f32)
+            .color(config.color(LapceColor::EDITOR_FOREGROUND));
+        let normal = config.color(LapceColor::EDITOR_DIM);
+        let over_budget = config.color(LapceColor::LAPCE_ERROR);
+
+        let mut y = PADDING;
+        for row in self.rows() {
+            let mut text_layout = TextLayout::new();
+            text_layout.set_text(&row.label, AttrsList::new(attrs));
+            cx.draw_text(&text_layout, Point::new(PADDING, y));
+            y += ROW_TEXT_HEIGHT;
+            let chart =
+                Rect::new(PADDING, y, rect.width() - PADDING, y + CHART_HEIGHT);
+            self.paint_chart(cx, chart, &row, normal, over_budget);
+            y += CHART_HEIGHT + PADDING;
+        }
+    }
+}
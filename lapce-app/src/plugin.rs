@@ -23,7 +23,11 @@
     IntoView, View,
 };
 use indexmap::IndexMap;
-use lapce_core::{command::EditCommand, directory::Directory, mode::Mode};
+use lapce_core::{
+    command::{EditCommand, FocusCommand},
+    directory::Directory,
+    mode::Mode,
+};
 use lapce_proxy::plugin::{download_volt, volt_icon, wasi::find_all_volts};
 use lapce_rpc::{
     core::{CoreNotification, CoreRpcHandler},
@@ -34,7 +38,9 @@
 use sha2::{Digest, Sha256};
 
 use crate::{
-    command::{CommandExecuted, CommandKind},
+    command::{
+        CommandExecuted, CommandKind, InternalCommand, LapceWorkbenchCommand,
+    },
     config::{color::LapceColor, LapceConfig},
     db::LapceDb,
     editor::EditorData,
@@ -106,6 +112,8 @@ pub struct PluginData {
     pub all: RwSignal<im::HashMap<VoltID, AvailableVoltData>>,
     pub disabled: RwSignal<HashSet<VoltID>>,
     pub workspace_disabled: RwSignal<HashSet<VoltID>>,
+    /// The plugin selected with the keyboard, either installed or available.
+    pub selected: RwSignal<Option<VoltID>>,
     pub common: Rc<CommonData>,
 }
 
@@ -115,7 +123,7 @@ fn get_mode(&self) -> Mode {
     }
 
     fn check_condition(&self, condition: Condition) -> bool {
-        matches!(condition, Condition::PanelFocus)
+        matches!(condition, Condition::PanelFocus | Condition::ListFocus)
     }
 
     fn run_command(
@@ -125,8 +133,28 @@ fn run_command(
         mods: Modifiers,
     ) -> CommandExecuted {
         match &command.kind {
+            CommandKind::Workbench(LapceWorkbenchCommand::ShowContextMenu) => {
+                self.show_selected_menu();
+                return CommandExecuted::Yes;
+            }
             CommandKind::Workbench(_) => {}
             CommandKind::Scroll(_) => {}
+            CommandKind::Focus(FocusCommand::ListNext) => {
+                self.select_next(1);
+                return CommandExecuted::Yes;
+            }
+            CommandKind::Focus(FocusCommand::ListPrevious) => {
+                self.select_next(-1);
+                return CommandExecuted::Yes;
+            }
+            CommandKind::Focus(FocusCommand::ListSelect) => {
+                if let Some(volt_id) = self.selected.get_untracked() {
+                    self.common
+                        .internal_command
+                        .send(InternalCommand::OpenVoltView { volt_id });
+                }
+                return CommandExecuted::Yes;
+            }
             CommandKind::Focus(_) => {}
             CommandKind::Edit(_)
             | CommandKind::Move(_)
@@ -180,6 +208,7 @@ pub fn new(
             all: cx.create_rw_signal(im::HashMap::new()),
             disabled,
             workspace_disabled,
+            selected: cx.create_rw_signal(None),
             common,
         };
 
@@ -607,6 +636,73 @@ pub fn reload_volt(&self, volt: VoltMetadata) {
         self.common.proxy.reload_volt(volt);
     }
 
+    /// The plugins in the order they are listed in the panel: the installed
+    /// ones, followed by the search results that aren't installed.
+    fn listed_volts(&self) -> Vec<VoltID> {
+        self.installed.with_untracked(|installed| {
+            let available = self.available.volts.with_untracked(|volts| {
+                volts
+                    .keys()
+                    .filter(|id| !installed.contains_key(*id))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            });
+            installed.keys().cloned().chain(available).collect()
+        })
+    }
+
+    /// Move the keyboard selection by `delta` items, stopping at either end.
+    fn select_next(&self, delta: isize) {
+        let volts = self.listed_volts();
+        if volts.is_empty() {
+            return;
+        }
+        let current = self
+            .selected
+            .with_untracked(|selected| {
+                selected
+                    .as_ref()
+                    .and_then(|id| volts.iter().position(|v| v == id))
+            })
+            .map(|i| i as isize);
+        let index = match current {
+            Some(i) => (i + delta).clamp(0, volts.len() as isize - 1),
+            None if delta < 0 => volts.len() as isize - 1,
+            None => 0,
+        };
+        self.selected.set(Some(volts[index as usize].clone()));
+    }
+
+    fn show_selected_menu(&self) {
+        let Some(volt_id) = self.selected.get_untracked() else {
+            return;
+        };
+        if let Some(volt) = self
+            .installed
+            .with_untracked(|installed| installed.get(&volt_id).cloned())
+        {
+            let menu = self.plugin_controls(
+                volt.meta.get_untracked(),
+                volt.latest.get_untracked(),
+            );
+            show_context_menu(menu, None);
+        } else if let Some(volt) = self
+            .available
+            .volts
+            .with_untracked(|volts| volts.get(&volt_id).cloned())
+        {
+            let plugin = self.clone();
+            let menu = Menu::new("").entry(
+                MenuItem::new("Install Plugin")
+                    .enabled(!volt.installing.get_untracked())
+                    .action(move || {
+                        plugin.install_volt(volt.info.get_untracked());
+                    }),
+            );
+            show_context_menu(menu, None);
+        }
+    }
+
     pub fn plugin_controls(&self, meta: VoltMetadata, latest: VoltInfo) -> Menu {
         let volt_id = meta.id();
         let mut menu = Menu::new("");
@@ -971,11 +1067,13 @@ pub fn plugin_info_view(plugin: PluginData, volt: VoltID) -> impl View {
                                     )
                                 },
                                 move |content| match content {
-                                    MarkdownContent::Text(text_layout) => container(
-                                        rich_text(move || text_layout.clone())
-                                            .style(|s| s.width_full()),
-                                    )
-                                    .style(|s| s.width_full()),
+                                    MarkdownContent::Text(text_layout, _) => {
+                                        container(
+                                            rich_text(move || text_layout.clone())
+                                                .style(|s| s.width_full()),
+                                        )
+                                        .style(|s| s.width_full())
+                                    }
                                     MarkdownContent::Image { .. } => {
                                         container(empty())
                                     }
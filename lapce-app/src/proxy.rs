@@ -43,9 +43,11 @@ pub fn shutdown(&self) {
 
 pub fn new_proxy(
     workspace: Arc<LapceWorkspace>,
+    workspace_folders: Vec<PathBuf>,
     disabled_volts: Vec<VoltID>,
     extra_plugin_paths: Vec<PathBuf>,
     plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+    file_associations: HashMap<String, String>,
     term_tx: Sender<(TermId, TermEvent)>,
 ) -> ProxyData {
     let proxy_rpc = ProxyRpcHandler::new();
@@ -62,9 +64,11 @@ pub fn new_proxy(
                 });
                 proxy_rpc.initialize(
                     workspace.path.clone(),
+                    workspace_folders,
                     disabled_volts,
                     extra_plugin_paths,
                     plugin_configurations,
+                    file_associations,
                     1,
                     1,
                 );
@@ -1,20 +1,29 @@
 use std::{
-    io::{BufReader, Write},
+    io::{BufRead, BufReader, Write},
     path::Path,
-    process::{Command, Stdio},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{anyhow, Result};
+use crossbeam_channel::Sender;
 use flate2::read::GzDecoder;
 use lapce_core::{
     directory::Directory,
     meta::{self, ReleaseType},
 };
 use lapce_rpc::{
-    core::CoreRpcHandler,
-    proxy::{ProxyRpc, ProxyRpcHandler},
-    stdio_transport, RpcMessage,
+    core::{CoreNotification, CoreRequest, CoreRpcHandler},
+    proxy::{ProxyResponse, ProxyRpc, ProxyRpcHandler, ProxyStatus},
+    stdio::{reader_transport, ReattachableWriter, MAX_BACKLOG_SIZE},
+    RpcMessage,
 };
+use lsp_types::{MessageType, ShowMessageParams};
+use parking_lot::Mutex;
 use thiserror::Error;
 use tracing::{debug, error};
 
@@ -73,7 +82,7 @@ fn home_dir(&self) -> Result<String> {
 }
 
 pub fn start_remote(
-    remote: impl Remote,
+    remote: impl Remote + Send + Sync + 'static,
     core_rpc: CoreRpcHandler,
     proxy_rpc: ProxyRpcHandler,
 ) -> Result<()> {
@@ -141,62 +150,57 @@ pub fn start_remote(
 
     debug!("remote proxy path: {remote_proxy_path}");
 
-    let mut child = match platform {
-        // Force cmd.exe usage to resolve %envvar% variables
-        Windows => remote
-            .command_builder()
-            .args(["cmd", "/c"])
-            .arg(&remote_proxy_file)
-            .arg("--proxy")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()?,
-        _ => remote
-            .command_builder()
-            .arg(&remote_proxy_file)
-            .arg("--proxy")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()?,
-    };
-    let stdin = child
-        .stdin
-        .take()
-        .ok_or_else(|| anyhow!("can't find stdin"))?;
-    let stdout = BufReader::new(
-        child
-            .stdout
-            .take()
-            .ok_or_else(|| anyhow!("can't find stdout"))?,
-    );
+    // A Windows host can't keep the proxy running after the connection is
+    // lost, so there's no session to reattach to.
+    let session = (platform != Windows).then(|| {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        format!("{}-{now}", std::process::id())
+    });
+
+    let mut args = vec!["--proxy"];
+    if let Some(session) = session.as_deref() {
+        args.extend(["--session", session]);
+    }
+    let (child, stdin, stdout) =
+        spawn_proxy(&remote, platform, &remote_proxy_file, &args)?;
     debug!("process id: {}", child.id());
 
-    let (writer_tx, writer_rx) = crossbeam_channel::unbounded();
+    let writer = Arc::new(ReattachableWriter::new());
+    writer.attach(stdin)?;
     let (reader_tx, reader_rx) = crossbeam_channel::unbounded();
-    stdio_transport(stdin, writer_rx, stdout, reader_tx);
+
+    let connection = Arc::new(RemoteConnection {
+        remote,
+        platform,
+        remote_proxy_file,
+        session,
+        writer: writer.clone(),
+        child: Mutex::new(child),
+        reader_tx,
+        core_rpc: core_rpc.clone(),
+        shutting_down: AtomicBool::new(false),
+    });
+    connection.read(stdout);
 
     let local_proxy_rpc = proxy_rpc.clone();
-    let local_writer_tx = writer_tx.clone();
+    let local_writer = writer.clone();
     std::thread::Builder::new()
         .name("ProxyRpcHandler".to_owned())
         .spawn(move || {
             for msg in local_proxy_rpc.rx() {
                 match msg {
                     ProxyRpc::Request(id, rpc) => {
-                        if let Err(err) =
-                            local_writer_tx.send(RpcMessage::Request(id, rpc))
-                        {
-                            tracing::error!("{:?}", err);
-                        }
+                        local_writer.write(RpcMessage::Request(id, rpc));
                     }
                     ProxyRpc::Notification(rpc) => {
-                        if let Err(err) =
-                            local_writer_tx.send(RpcMessage::Notification(rpc))
-                        {
-                            tracing::error!("{:?}", err);
-                        }
+                        local_writer.write(RpcMessage::Notification(rpc));
                     }
                     ProxyRpc::Shutdown => {
+                        connection.shutting_down.store(true, Ordering::SeqCst);
+                        let mut child = connection.child.lock();
                         if let Err(err) = child.kill() {
                             tracing::error!("{:?}", err);
                         }
@@ -216,22 +220,14 @@ pub fn start_remote(
             for msg in reader_rx {
                 match msg {
                     RpcMessage::Request(id, req) => {
-                        let writer_tx = writer_tx.clone();
+                        let writer = writer.clone();
                         let core_rpc = core_rpc.clone();
                         std::thread::spawn(move || match core_rpc.request(req) {
                             Ok(resp) => {
-                                if let Err(err) =
-                                    writer_tx.send(RpcMessage::Response(id, resp))
-                                {
-                                    tracing::error!("{:?}", err);
-                                }
+                                writer.write(RpcMessage::Response(id, resp));
                             }
                             Err(e) => {
-                                if let Err(err) =
-                                    writer_tx.send(RpcMessage::Error(id, e))
-                                {
-                                    tracing::error!("{:?}", err);
-                                }
+                                writer.write(RpcMessage::Error(id, e));
                             }
                         });
                     }
@@ -252,6 +248,175 @@ pub fn start_remote(
     Ok(())
 }
 
+fn spawn_proxy(
+    remote: &impl Remote,
+    platform: HostPlatform,
+    remote_proxy_file: &str,
+    args: &[&str],
+) -> Result<(Child, ChildStdin, BufReader<ChildStdout>)> {
+    let mut child = match platform {
+        // Force cmd.exe usage to resolve %envvar% variables
+        HostPlatform::Windows => remote
+            .command_builder()
+            .args(["cmd", "/c"])
+            .arg(remote_proxy_file)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?,
+        _ => remote
+            .command_builder()
+            .arg(remote_proxy_file)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?,
+    };
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("can't find stdin"))?;
+    let stdout = BufReader::new(
+        child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("can't find stdout"))?,
+    );
+    Ok((child, stdin, stdout))
+}
+
+/// The delays between the attempts to reattach to the proxy after the
+/// connection to it is lost.
+const RECONNECT_DELAYS: [Duration; 5] = [
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(5),
+    Duration::from_secs(10),
+    Duration::from_secs(30),
+];
+
+/// The connection to a remote proxy, which is reattached to when it's lost,
+/// so the proxy and the terminals it runs survive e.g. a dropped SSH
+/// connection.
+struct RemoteConnection<R: Remote> {
+    remote: R,
+    platform: HostPlatform,
+    remote_proxy_file: String,
+    session: Option<String>,
+    writer: Arc<ReattachableWriter>,
+    child: Mutex<Child>,
+    reader_tx: Sender<RpcMessage<CoreRequest, CoreNotification, ProxyResponse>>,
+    core_rpc: CoreRpcHandler,
+    shutting_down: AtomicBool,
+}
+
+impl<R: Remote + Send + Sync + 'static> RemoteConnection<R> {
+    fn read(self: &Arc<Self>, stdout: BufReader<ChildStdout>) {
+        let connection = self.clone();
+        reader_transport(stdout, self.reader_tx.clone(), move || {
+            connection.reconnect();
+        });
+    }
+
+    fn reconnect(self: &Arc<Self>) {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return;
+        }
+        self.writer.detach();
+        self.core_rpc.notification(CoreNotification::ProxyStatus {
+            status: ProxyStatus::Disconnected,
+        });
+        let Some(session) = self.session.as_deref() else {
+            self.show_message(
+                MessageType::ERROR,
+                "The connection to the remote proxy was lost, and the proxy \
+                 can't be kept running on a Windows host to reconnect to. \
+                 Reopen the workspace to connect again."
+                    .to_string(),
+            );
+            return;
+        };
+
+        let mut last_err = None;
+        for delay in RECONNECT_DELAYS {
+            std::thread::sleep(delay);
+            if self.shutting_down.load(Ordering::SeqCst) {
+                return;
+            }
+            match self.reattach(session) {
+                Ok(stdout) => {
+                    self.read(stdout);
+                    self.core_rpc.notification(CoreNotification::ProxyStatus {
+                        status: ProxyStatus::Connected,
+                    });
+                    return;
+                }
+                Err(err) => {
+                    error!("failed to reattach to the remote proxy: {err}");
+                    last_err = Some(err);
+                }
+            }
+        }
+        let reason = last_err.map(|err| format!(": {err}")).unwrap_or_default();
+        self.show_message(
+            MessageType::ERROR,
+            format!(
+                "Couldn't reconnect to the remote proxy after {} attempts{reason}. \
+                 Reopen the workspace to connect again.",
+                RECONNECT_DELAYS.len()
+            ),
+        );
+    }
+
+    fn show_message(&self, typ: MessageType, message: String) {
+        self.core_rpc.show_message(
+            "Remote Proxy".to_string(),
+            ShowMessageParams { typ, message },
+        );
+    }
+
+    fn reattach(&self, session: &str) -> Result<BufReader<ChildStdout>> {
+        let (mut child, stdin, mut stdout) = spawn_proxy(
+            &self.remote,
+            self.platform,
+            &self.remote_proxy_file,
+            &["--proxy", "--session", session, "--reattach"],
+        )?;
+
+        let mut line = String::new();
+        stdout.read_line(&mut line)?;
+        if line.trim() != lapce_proxy::REATTACHED_MESSAGE {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!("the proxy isn't running anymore"));
+        }
+
+        let dropped = match self.writer.attach(stdin) {
+            Ok(dropped) => dropped,
+            Err(err) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err.into());
+            }
+        };
+        if dropped > 0 {
+            self.show_message(
+                MessageType::WARNING,
+                format!(
+                    "{dropped} messages to the remote proxy were lost while \
+                     disconnected, as more than {} MB of them were waiting to \
+                     be sent.",
+                    MAX_BACKLOG_SIZE / 1024 / 1024
+                ),
+            );
+        }
+        let mut old_child = std::mem::replace(&mut *self.child.lock(), child);
+        let _ = old_child.kill();
+        let _ = old_child.wait();
+        Ok(stdout)
+    }
+}
+
 fn download_remote(
     remote: &impl Remote,
     platform: &HostPlatform,
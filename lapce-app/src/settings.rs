@@ -187,6 +187,9 @@ fn into_settings_map(
             });
             kinds.push_back((kind.to_string(), pos));
             for (name, desc) in fields.iter().zip(descs.iter()) {
+                if !is_supported_setting(kind, name) {
+                    continue;
+                }
                 let field = name.replace('_', "-");
 
                 let value = if let Some(dropdown) =
@@ -304,6 +307,16 @@ fn into_settings_map(
     }
 }
 
+/// Whether the setting `name` of the `kind` section does anything on this
+/// platform, as the ones that don't are left out.
+fn is_supported_setting(kind: &str, name: &str) -> bool {
+    match (kind, name) {
+        // The usage of terminal processes is only sampled on Linux.
+        ("Terminal", "show_process_usage") => cfg!(target_os = "linux"),
+        _ => true,
+    }
+}
+
 pub fn settings_view(
     installed_plugins: RwSignal<IndexMap<VoltID, InstalledVoltData>>,
     editors: Editors,
@@ -0,0 +1,224 @@
+//! The signature help shown above a call while its arguments are typed, with
+//! the parameter being typed highlighted.
+
+use std::ops::Range;
+
+use floem::{
+    peniko::kurbo::Rect,
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+    views::editor::id::EditorId,
+};
+use lsp_types::{ParameterLabel, SignatureHelp, SignatureInformation};
+
+#[derive(Clone)]
+pub struct SignatureData {
+    /// The id of the latest request, so that responses to earlier ones are
+    /// ignored.
+    pub request_id: RwSignal<usize>,
+    pub editor_id: RwSignal<EditorId>,
+    /// The offset the arguments of the call start at, which the popup is
+    /// anchored to.
+    pub offset: RwSignal<usize>,
+    pub signatures: RwSignal<Vec<SignatureInformation>>,
+    /// The index of the overload shown.
+    pub active: RwSignal<usize>,
+    /// The active parameter given for all the signatures, used for those
+    /// without their own.
+    pub active_parameter: RwSignal<Option<u32>>,
+    pub layout_rect: RwSignal<Rect>,
+}
+
+impl SignatureData {
+    pub fn new(cx: Scope) -> Self {
+        Self {
+            request_id: cx.create_rw_signal(0),
+            editor_id: cx.create_rw_signal(EditorId::next()),
+            offset: cx.create_rw_signal(0),
+            signatures: cx.create_rw_signal(Vec::new()),
+            active: cx.create_rw_signal(0),
+            active_parameter: cx.create_rw_signal(None),
+            layout_rect: cx.create_rw_signal(Rect::ZERO),
+        }
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.signatures.with(|signatures| !signatures.is_empty())
+    }
+
+    /// Whether the signatures are shown for the call being typed in `editor_id`.
+    pub fn is_shown_in(&self, editor_id: EditorId) -> bool {
+        self.editor_id.get_untracked() == editor_id
+            && self.signatures.with_untracked(|s| !s.is_empty())
+    }
+
+    /// Start a new request for the call with arguments at `offset` in
+    /// `editor_id`, returning its id.
+    pub fn request(&self, editor_id: EditorId, offset: usize) -> usize {
+        self.editor_id.set(editor_id);
+        self.offset.set(offset);
+        self.request_id.update(|id| *id += 1);
+        self.request_id.get_untracked()
+    }
+
+    /// Handle the response to a signature help request.
+    pub fn receive(&self, request_id: usize, resp: &SignatureHelp) {
+        if request_id != self.request_id.get_untracked() {
+            return;
+        }
+        if resp.signatures.is_empty() {
+            self.cancel();
+            return;
+        }
+
+        // Keep the overload picked by the user while the same ones are offered.
+        let same = self.signatures.with_untracked(|signatures| {
+            signatures
+                .iter()
+                .map(|s| &s.label)
+                .eq(resp.signatures.iter().map(|s| &s.label))
+        });
+        if !same {
+            let active = resp.active_signature.unwrap_or(0) as usize;
+            self.active.set(active.min(resp.signatures.len() - 1));
+        }
+        self.active_parameter.set(resp.active_parameter);
+        self.signatures.set(resp.signatures.clone());
+    }
+
+    pub fn cancel(&self) {
+        if self.signatures.with_untracked(|s| s.is_empty()) {
+            return;
+        }
+        // Ignore the responses to the requests still pending.
+        self.request_id.update(|id| *id += 1);
+        self.signatures.set(Vec::new());
+    }
+
+    pub fn next(&self) {
+        let len = self.signatures.with_untracked(|s| s.len());
+        if len > 0 {
+            self.active.update(|active| *active = (*active + 1) % len);
+        }
+    }
+
+    pub fn previous(&self) {
+        let len = self.signatures.with_untracked(|s| s.len());
+        if len > 0 {
+            self.active
+                .update(|active| *active = (*active + len - 1) % len);
+        }
+    }
+}
+
+/// The range of the label of `signature` naming its active parameter, which is
+/// that of the signature or else `active_parameter`.
+pub fn parameter_range(
+    signature: &SignatureInformation,
+    active_parameter: Option<u32>,
+) -> Option<Range<usize>> {
+    let index = signature.active_parameter.or(active_parameter)?;
+    let parameter = signature.parameters.as_ref()?.get(index as usize)?;
+    let label = &signature.label;
+    match &parameter.label {
+        ParameterLabel::Simple(name) => {
+            // Skip the name of the function, which could contain the parameter's.
+            let from = label.find('(').map_or(0, |i| i + 1);
+            let start = from + label[from..].find(name.as_str())?;
+            Some(start..start + name.len())
+        }
+        ParameterLabel::LabelOffsets([start, end]) => {
+            // The offsets are in UTF-16 code units.
+            let offset = |utf16: u32| {
+                let mut units = 0;
+                for (i, c) in label.char_indices() {
+                    if units >= utf16 {
+                        return Some(i);
+                    }
+                    units += c.len_utf16() as u32;
+                }
+                (units >= utf16).then_some(label.len())
+            };
+            Some(offset(*start)?..offset(*end)?)
+        }
+    }
+}
+
+/// Whether the arguments typed since the start of a call close it.
+pub fn call_closed(arguments: &str) -> bool {
+    let mut depth = 0usize;
+    for c in arguments.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return true,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::ParameterInformation;
+
+    use super::*;
+
+    fn signature(
+        label: &str,
+        parameters: Vec<ParameterLabel>,
+    ) -> SignatureInformation {
+        SignatureInformation {
+            label: label.to_string(),
+            documentation: None,
+            parameters: Some(
+                parameters
+                    .into_iter()
+                    .map(|label| ParameterInformation {
+                        label,
+                        documentation: None,
+                    })
+                    .collect(),
+            ),
+            active_parameter: None,
+        }
+    }
+
+    #[test]
+    fn test_parameter_range() {
+        let simple = signature(
+            "fn a(a: u8, b: &str)",
+            vec![
+                ParameterLabel::Simple("a: u8".to_string()),
+                ParameterLabel::Simple("b: &str".to_string()),
+            ],
+        );
+        assert_eq!(parameter_range(&simple, Some(0)), Some(5..10));
+        assert_eq!(parameter_range(&simple, Some(1)), Some(12..19));
+        assert_eq!(parameter_range(&simple, Some(2)), None);
+        assert_eq!(parameter_range(&simple, None), None);
+
+        let offsets = signature(
+            "f(é: int, b: int)",
+            vec![
+                ParameterLabel::LabelOffsets([2, 8]),
+                ParameterLabel::LabelOffsets([10, 16]),
+            ],
+        );
+        assert_eq!(parameter_range(&offsets, Some(0)), Some(2..9));
+        assert_eq!(parameter_range(&offsets, Some(1)), Some(11..17));
+
+        let own = SignatureInformation {
+            active_parameter: Some(1),
+            ..simple
+        };
+        assert_eq!(parameter_range(&own, Some(0)), Some(12..19));
+    }
+
+    #[test]
+    fn test_call_closed() {
+        assert!(!call_closed("a, b"));
+        assert!(!call_closed("a, g(b), "));
+        assert!(call_closed("a)"));
+        assert!(call_closed("g(b))"));
+    }
+}
@@ -1,15 +1,27 @@
-use std::{path::PathBuf, rc::Rc};
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use floem::{
+    action::show_context_menu,
     keyboard::Modifiers,
-    reactive::{RwSignal, Scope, SignalWith},
+    menu::{Menu, MenuItem},
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
 };
 use indexmap::IndexMap;
-use lapce_core::mode::Mode;
+use lapce_core::{
+    buffer::rope_text::RopeText,
+    command::{FocusCommand, MoveCommand},
+    mode::Mode,
+};
 use lapce_rpc::source_control::FileDiff;
 
 use crate::{
-    command::{CommandExecuted, CommandKind},
+    command::{
+        CommandExecuted, CommandKind, InternalCommand, LapceCommand,
+        LapceWorkbenchCommand,
+    },
     editor::EditorData,
     keypress::{condition::Condition, KeyPressFocus},
     main_split::Editors,
@@ -23,6 +35,9 @@ pub struct SourceControlData {
     pub branch: RwSignal<String>,
     pub branches: RwSignal<im::Vector<String>>,
     pub tags: RwSignal<im::Vector<String>>,
+    /// The file selected with the keyboard. While there is one, the keys
+    /// navigate the list of changes instead of editing the commit message.
+    pub selected: RwSignal<Option<PathBuf>>,
     pub editor: EditorData,
     pub common: Rc<CommonData>,
 }
@@ -33,10 +48,13 @@ fn get_mode(&self) -> Mode {
     }
 
     fn check_condition(&self, condition: Condition) -> bool {
-        matches!(
-            condition,
-            Condition::PanelFocus | Condition::SourceControlFocus
-        )
+        match condition {
+            Condition::PanelFocus | Condition::SourceControlFocus => true,
+            Condition::ListFocus | Condition::ModalFocus => {
+                self.selected.with_untracked(|selected| selected.is_some())
+            }
+            _ => false,
+        }
     }
 
     fn run_command(
@@ -45,7 +63,23 @@ fn run_command(
         count: Option<usize>,
         mods: Modifiers,
     ) -> CommandExecuted {
+        if self.selected.with_untracked(|selected| selected.is_some()) {
+            return self.run_list_command(command);
+        }
         match &command.kind {
+            CommandKind::Move(MoveCommand::Down)
+                if self.is_cursor_on_last_line() =>
+            {
+                // Moving down out of the commit message goes to the changes
+                let first = self
+                    .file_diffs
+                    .with_untracked(|diffs| diffs.keys().next().cloned());
+                if first.is_some() {
+                    self.selected.set(first);
+                    return CommandExecuted::Yes;
+                }
+                self.editor.run_command(command, count, mods)
+            }
             CommandKind::Edit(_)
             | CommandKind::Move(_)
             | CommandKind::MultiSelection(_) => {
@@ -56,6 +90,13 @@ fn run_command(
     }
 
     fn receive_char(&self, c: &str) {
+        if let Some(path) = self.selected.get_untracked() {
+            if c == " " {
+                self.toggle_checked(&path);
+                return;
+            }
+            self.selected.set(None);
+        }
         self.editor.receive_char(c);
     }
 }
@@ -67,11 +108,90 @@ pub fn new(cx: Scope, editors: Editors, common: Rc<CommonData>) -> Self {
             branch: cx.create_rw_signal("".to_string()),
             branches: cx.create_rw_signal(im::Vector::new()),
             tags: cx.create_rw_signal(im::Vector::new()),
+            selected: cx.create_rw_signal(None),
             editor: editors.make_local(cx, common.clone()),
             common,
         }
     }
 
+    fn run_list_command(&self, command: &LapceCommand) -> CommandExecuted {
+        let selected = self.selected.get_untracked();
+        match &command.kind {
+            CommandKind::Focus(FocusCommand::ListNext) => {
+                let next = self.file_diffs.with_untracked(|diffs| {
+                    let index = selected
+                        .and_then(|path| diffs.get_index_of(&path))
+                        .map(|i| (i + 1).min(diffs.len().saturating_sub(1)))
+                        .unwrap_or(0);
+                    diffs.get_index(index).map(|(path, _)| path.clone())
+                });
+                self.selected.set(next);
+            }
+            CommandKind::Focus(FocusCommand::ListPrevious) => {
+                // Going up from the first change returns to the commit message
+                let previous = self.file_diffs.with_untracked(|diffs| {
+                    let index =
+                        selected.and_then(|path| diffs.get_index_of(&path))?;
+                    diffs
+                        .get_index(index.checked_sub(1)?)
+                        .map(|(path, _)| path.clone())
+                });
+                self.selected.set(previous);
+            }
+            CommandKind::Focus(FocusCommand::ListSelect) => {
+                if let Some(path) = selected {
+                    self.common
+                        .internal_command
+                        .send(InternalCommand::OpenFileChanges { path });
+                }
+            }
+            CommandKind::Focus(FocusCommand::ModalClose) => {
+                self.selected.set(None);
+            }
+            CommandKind::Workbench(LapceWorkbenchCommand::ShowContextMenu) => {
+                let diff = selected.and_then(|path| {
+                    self.file_diffs
+                        .with_untracked(|diffs| diffs.get(&path).cloned())
+                });
+                if let Some((diff, _)) = diff {
+                    show_context_menu(self.file_diff_menu(diff), None);
+                }
+            }
+            _ => return CommandExecuted::No,
+        }
+        CommandExecuted::Yes
+    }
+
+    fn is_cursor_on_last_line(&self) -> bool {
+        let offset = self.editor.cursor().with_untracked(|c| c.offset());
+        self.editor.doc().buffer.with_untracked(|buffer| {
+            buffer.line_of_offset(offset) == buffer.last_line()
+        })
+    }
+
+    /// Toggle whether the changes of the file are included in the next commit.
+    pub fn toggle_checked(&self, path: &Path) {
+        self.file_diffs.update(|diffs| {
+            if let Some((_, checked)) = diffs.get_mut(path) {
+                *checked = !*checked;
+            }
+        });
+    }
+
+    /// The context menu of a file in the list of changes.
+    pub fn file_diff_menu(&self, diff: FileDiff) -> Menu {
+        let lapce_command = self.common.lapce_command;
+        let discard = move || {
+            lapce_command.send(LapceCommand {
+                kind: CommandKind::Workbench(
+                    LapceWorkbenchCommand::SourceControlDiscardTargetFileChanges,
+                ),
+                data: Some(serde_json::json!(diff.clone())),
+            });
+        };
+        Menu::new("").entry(MenuItem::new("Discard Changes").action(discard))
+    }
+
     pub fn commit(&self) {
         let diffs: Vec<FileDiff> = self.file_diffs.with_untracked(|file_diffs| {
             file_diffs
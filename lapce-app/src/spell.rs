@@ -0,0 +1,649 @@
+//! The spell checker underlining the misspelled words of comments, strings and
+//! prose files, using hunspell dictionaries and the words added to the
+//! workspace's dictionary.
+
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use floem::{
+    ext_event::create_ext_action,
+    reactive::{RwSignal, Scope, SignalUpdate, SignalWith},
+};
+use lapce_core::directory::Directory;
+
+/// The command of the code action adding a word to the workspace dictionary,
+/// which is run by the editor rather than a language server.
+pub const ADD_WORD_COMMAND: &str = "lapce.spell.addWordToDictionary";
+
+/// The file in the workspace's `.lapce` folder holding the words added to its
+/// dictionary, one per line.
+const WORKSPACE_DICTIONARY: &str = "dictionary.txt";
+
+/// The words shorter than this aren't checked, as they're mostly
+/// abbreviations.
+const MIN_WORD_LEN: usize = 3;
+
+const MAX_SUGGESTIONS: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FlagType {
+    /// Each character is a flag.
+    Char,
+    /// Each pair of characters is a flag.
+    Long,
+    /// The flags are comma separated numbers.
+    Num,
+}
+
+impl FlagType {
+    fn parse(self, flags: &str) -> Vec<String> {
+        match self {
+            FlagType::Char => flags.chars().map(String::from).collect(),
+            FlagType::Long => flags
+                .chars()
+                .collect::<Vec<_>>()
+                .chunks(2)
+                .map(|c| c.iter().collect())
+                .collect(),
+            FlagType::Num => flags
+                .split(',')
+                .map(|f| f.trim().to_string())
+                .filter(|f| !f.is_empty())
+                .collect(),
+        }
+    }
+}
+
+/// One rule of a prefix or suffix class of an affix file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct AffixRule {
+    /// The characters removed from the word before adding the affix.
+    strip: String,
+    add: String,
+    /// The characters the word has to start with for a prefix, or end with
+    /// for a suffix, where each is a character or a `[..]` / `[^..]` class.
+    condition: Vec<CharMatch>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CharMatch {
+    Any,
+    Char(char),
+    Class { chars: Vec<char>, negated: bool },
+}
+
+impl CharMatch {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            CharMatch::Any => true,
+            CharMatch::Char(ch) => *ch == c,
+            CharMatch::Class { chars, negated } => chars.contains(&c) != *negated,
+        }
+    }
+}
+
+fn parse_condition(condition: &str) -> Vec<CharMatch> {
+    if condition == "." {
+        return Vec::new();
+    }
+    let mut matches = Vec::new();
+    let mut chars = condition.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => matches.push(CharMatch::Any),
+            '[' => {
+                let mut class = Vec::new();
+                let mut negated = false;
+                for c in chars.by_ref() {
+                    match c {
+                        ']' => break,
+                        '^' if class.is_empty() && !negated => negated = true,
+                        c => class.push(c),
+                    }
+                }
+                matches.push(CharMatch::Class {
+                    chars: class,
+                    negated,
+                });
+            }
+            c => matches.push(CharMatch::Char(c)),
+        }
+    }
+    matches
+}
+
+#[derive(Clone, Debug, Default)]
+struct AffixClass {
+    /// Whether the class combines with the classes of the other kind, so that
+    /// a word can take both a prefix and a suffix.
+    cross_product: bool,
+    rules: Vec<AffixRule>,
+}
+
+/// The prefixes and suffixes of a hunspell `.aff` file, which the words of
+/// the `.dic` file are inflected with according to their flags.
+#[derive(Default)]
+pub struct Affixes {
+    flag_type: Option<FlagType>,
+    prefixes: HashMap<String, AffixClass>,
+    suffixes: HashMap<String, AffixClass>,
+}
+
+impl Affixes {
+    pub fn parse(content: &str) -> Self {
+        let mut affixes = Affixes::default();
+        for line in content.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["FLAG", kind, ..] => {
+                    affixes.flag_type = Some(match *kind {
+                        "long" => FlagType::Long,
+                        "num" => FlagType::Num,
+                        _ => FlagType::Char,
+                    });
+                }
+                [kind @ ("PFX" | "SFX"), flag, cross_product, count]
+                    if count.parse::<usize>().is_ok() =>
+                {
+                    let classes = if *kind == "PFX" {
+                        &mut affixes.prefixes
+                    } else {
+                        &mut affixes.suffixes
+                    };
+                    classes.entry(flag.to_string()).or_default().cross_product =
+                        *cross_product == "Y";
+                }
+                [kind @ ("PFX" | "SFX"), flag, strip, add, rest @ ..] => {
+                    let classes = if *kind == "PFX" {
+                        &mut affixes.prefixes
+                    } else {
+                        &mut affixes.suffixes
+                    };
+                    // The affixes can have flags of their own, which aren't
+                    // followed.
+                    let add = add.split('/').next().unwrap_or("");
+                    let rule = AffixRule {
+                        strip: if *strip == "0" {
+                            String::new()
+                        } else {
+                            strip.to_string()
+                        },
+                        add: if add == "0" {
+                            String::new()
+                        } else {
+                            add.to_string()
+                        },
+                        condition: parse_condition(rest.first().unwrap_or(&".")),
+                    };
+                    classes
+                        .entry(flag.to_string())
+                        .or_default()
+                        .rules
+                        .push(rule);
+                }
+                _ => {}
+            }
+        }
+        affixes
+    }
+
+    fn flag_type(&self) -> FlagType {
+        self.flag_type.unwrap_or(FlagType::Char)
+    }
+
+    /// The word with the prefixes and suffixes of its flags applied.
+    fn expand(&self, word: &str, flags: &[String]) -> Vec<String> {
+        let mut words = vec![word.to_string()];
+        let mut suffixed = Vec::new();
+        for flag in flags {
+            if let Some(class) = self.suffixes.get(flag) {
+                for rule in &class.rules {
+                    if let Some(w) = apply_suffix(word, rule) {
+                        suffixed.push((w, class.cross_product));
+                    }
+                }
+            }
+        }
+        for flag in flags {
+            if let Some(class) = self.prefixes.get(flag) {
+                for rule in &class.rules {
+                    if let Some(w) = apply_prefix(word, rule) {
+                        words.push(w);
+                    }
+                    if !class.cross_product {
+                        continue;
+                    }
+                    for (suffixed, cross_product) in &suffixed {
+                        if *cross_product {
+                            if let Some(w) = apply_prefix(suffixed, rule) {
+                                words.push(w);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        words.extend(suffixed.into_iter().map(|(w, _)| w));
+        words
+    }
+}
+
+fn apply_suffix(word: &str, rule: &AffixRule) -> Option<String> {
+    let chars: Vec<char> = word.chars().collect();
+    if chars.len() < rule.condition.len() {
+        return None;
+    }
+    let end = &chars[chars.len() - rule.condition.len()..];
+    if !rule.condition.iter().zip(end).all(|(m, c)| m.matches(*c)) {
+        return None;
+    }
+    let stem = word.strip_suffix(rule.strip.as_str())?;
+    Some(format!("{stem}{}", rule.add))
+}
+
+fn apply_prefix(word: &str, rule: &AffixRule) -> Option<String> {
+    let chars: Vec<char> = word.chars().collect();
+    if chars.len() < rule.condition.len() {
+        return None;
+    }
+    if !rule
+        .condition
+        .iter()
+        .zip(&chars)
+        .all(|(m, c)| m.matches(*c))
+    {
+        return None;
+    }
+    let stem = word.strip_prefix(rule.strip.as_str())?;
+    Some(format!("{}{stem}", rule.add))
+}
+
+/// All the words of a hunspell `.dic` file inflected with `affixes`,
+/// lowercased.
+pub fn parse_dictionary(content: &str, affixes: &Affixes) -> HashSet<String> {
+    let mut words = HashSet::new();
+    let mut lines = content.lines();
+    // The first line is the number of words.
+    if let Some(first) = lines.clone().next() {
+        if first.trim().parse::<usize>().is_ok() {
+            lines.next();
+        }
+    }
+    for line in lines {
+        // The word can be followed by morphological fields.
+        let Some(entry) = line.split(['\t', ' ']).next() else {
+            continue;
+        };
+        if entry.is_empty() || entry.starts_with('#') {
+            continue;
+        }
+        let (word, flags) = match entry.split_once('/') {
+            Some((word, flags)) => (word, affixes.flag_type().parse(flags)),
+            None => (entry, Vec::new()),
+        };
+        for word in affixes.expand(word, &flags) {
+            words.insert(word.to_lowercase());
+        }
+    }
+    words
+}
+
+/// The words of `text` worth checking, with their byte ranges.
+///
+/// Identifiers, such as words with digits or underscores, or with capitals
+/// after their first letter, and words shorter than three letters are left
+/// out.
+pub fn words(text: &str) -> Vec<(Range<usize>, &str)> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (start, is_word_char(c)) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                start = None;
+                // Words joined by dots or slashes are paths or urls.
+                let joined = |c: Option<char>| matches!(c, Some('.' | '/' | '\\'));
+                if joined(text[..s].chars().next_back())
+                    || (joined(Some(c))
+                        && text[i + c.len_utf8()..]
+                            .chars()
+                            .next()
+                            .is_some_and(char::is_alphanumeric))
+                {
+                    continue;
+                }
+
+                let word = text[s..i].trim_matches('\'');
+                let word = word.strip_suffix("'s").unwrap_or(word);
+                let offset = s + text[s..i].find(word).unwrap_or(0);
+                let checked = word.chars().count() >= MIN_WORD_LEN
+                    && word.chars().all(|c| c.is_alphabetic() || c == '\'')
+                    && !word.chars().skip(1).any(char::is_uppercase);
+                if checked {
+                    words.push((offset..offset + word.len(), word));
+                }
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// The known words within one edit of `word`, the most likely first.
+pub fn suggestions(words: &HashSet<String>, word: &str) -> Vec<String> {
+    let lower = word.to_lowercase();
+    let chars: Vec<char> = lower.chars().collect();
+    let letters: Vec<char> = {
+        let mut letters: Vec<char> = ('a'..='z').collect();
+        letters.extend(chars.iter().filter(|c| !c.is_ascii_lowercase()));
+        letters
+    };
+
+    let mut candidates: Vec<String> = Vec::new();
+    // Swapped letters and wrong letters are the most common typos.
+    for i in 0..chars.len().saturating_sub(1) {
+        let mut c = chars.clone();
+        c.swap(i, i + 1);
+        candidates.push(c.into_iter().collect());
+    }
+    for i in 0..chars.len() {
+        for l in &letters {
+            let mut c = chars.clone();
+            c[i] = *l;
+            candidates.push(c.into_iter().collect());
+        }
+    }
+    for i in 0..chars.len() {
+        let mut c = chars.clone();
+        c.remove(i);
+        candidates.push(c.into_iter().collect());
+    }
+    for i in 0..=chars.len() {
+        for l in &letters {
+            let mut c = chars.clone();
+            c.insert(i, *l);
+            candidates.push(c.into_iter().collect());
+        }
+    }
+
+    let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(|c| *c != lower && words.contains(c) && seen.insert(c.clone()))
+        .take(MAX_SUGGESTIONS)
+        .map(|c| {
+            if capitalized {
+                let mut chars = c.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// The places a dictionary named `name` is looked for, the user's
+/// dictionaries directory first.
+fn dictionary_paths(name: &str) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = Directory::dictionaries_directory() {
+        dirs.push(dir);
+    }
+    for dir in [
+        "/usr/share/hunspell",
+        "/usr/share/myspell",
+        "/usr/share/myspell/dicts",
+        "/usr/local/share/hunspell",
+        "/Library/Spelling",
+    ] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs.into_iter()
+        .map(|dir| dir.join(format!("{name}.dic")))
+        .collect()
+}
+
+fn load_dictionary(name: &str) -> HashSet<String> {
+    let Some(dic) = dictionary_paths(name).into_iter().find(|p| p.exists()) else {
+        tracing::warn!("no spell checking dictionary named {name} found");
+        return HashSet::new();
+    };
+    let aff = std::fs::read(dic.with_extension("aff")).unwrap_or_default();
+    let encoding = dictionary_encoding(&aff);
+    let read = |content: &[u8]| encoding.decode(content).0.into_owned();
+    let affixes = Affixes::parse(&read(&aff));
+    parse_dictionary(&read(&std::fs::read(&dic).unwrap_or_default()), &affixes)
+}
+
+/// The encoding of the `.aff` and `.dic` files of a dictionary, named by the
+/// `SET` line of the `.aff` file, and UTF-8 when it's missing or unknown.
+fn dictionary_encoding(aff: &[u8]) -> &'static encoding_rs::Encoding {
+    // The line is ASCII, whatever the encoding of the rest of the file.
+    let Some(name) = String::from_utf8_lossy(aff).lines().find_map(|line| {
+        let name = line.trim().strip_prefix("SET")?;
+        name.starts_with(char::is_whitespace)
+            .then(|| name.trim().to_string())
+    }) else {
+        return encoding_rs::UTF_8;
+    };
+    // Hunspell names some encodings differently, e.g. `microsoft-cp1251`.
+    let label = name.to_lowercase();
+    let label = label.strip_prefix("microsoft-").unwrap_or(&label);
+    encoding_rs::Encoding::for_label(label.as_bytes()).unwrap_or_else(|| {
+        tracing::warn!("unknown spell checking dictionary encoding {name}");
+        encoding_rs::UTF_8
+    })
+}
+
+pub struct SpellChecker {
+    cx: Scope,
+    /// The name of the dictionary loaded and its words. It's loaded in the
+    /// background when first needed.
+    pub dictionary: RwSignal<Option<(String, Arc<HashSet<String>>)>>,
+    /// The name of the dictionary last started loading.
+    loading: RefCell<Option<String>>,
+    /// The words added to the workspace's dictionary, lowercased.
+    pub workspace_words: RwSignal<HashSet<String>>,
+    /// The file the workspace's dictionary is kept in, which is `None` when
+    /// there's no local workspace folder to keep it in.
+    workspace_file: Option<PathBuf>,
+}
+
+impl SpellChecker {
+    pub fn new(cx: Scope, workspace_path: Option<&Path>) -> Self {
+        let workspace_file =
+            workspace_path.map(|p| p.join(".lapce").join(WORKSPACE_DICTIONARY));
+        let workspace_words = workspace_file
+            .as_ref()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|content| {
+                content
+                    .lines()
+                    .map(|line| line.trim().to_lowercase())
+                    .filter(|line| !line.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            cx,
+            dictionary: cx.create_rw_signal(None),
+            loading: RefCell::new(None),
+            workspace_words: cx.create_rw_signal(workspace_words),
+            workspace_file,
+        }
+    }
+
+    /// The words of the dictionary named `name`, or `None` while it's being
+    /// loaded in the background.
+    fn dictionary(&self, name: &str) -> Option<Arc<HashSet<String>>> {
+        let words = self.dictionary.with_untracked(|dictionary| {
+            dictionary
+                .as_ref()
+                .filter(|(loaded, _)| loaded == name)
+                .map(|(_, words)| words.clone())
+        });
+        if words.is_none() && self.loading.borrow().as_deref() != Some(name) {
+            *self.loading.borrow_mut() = Some(name.to_string());
+
+            let dictionary = self.dictionary;
+            let send = create_ext_action(self.cx, move |loaded| {
+                dictionary.set(Some(loaded));
+            });
+            let name = name.to_string();
+            std::thread::spawn(move || {
+                let words = Arc::new(load_dictionary(&name));
+                send((name, words));
+            });
+        }
+        words
+    }
+
+    /// The byte ranges of the misspelled words of `text`, checked against the
+    /// dictionary named `dictionary`.
+    pub fn misspellings(&self, dictionary: &str, text: &str) -> Vec<Range<usize>> {
+        // Without a dictionary every word would be misspelled.
+        let Some(words) = self.dictionary(dictionary).filter(|w| !w.is_empty())
+        else {
+            return Vec::new();
+        };
+        self.workspace_words.with_untracked(|workspace_words| {
+            self::words(text)
+                .into_iter()
+                .filter(|(_, word)| {
+                    let word = word.to_lowercase();
+                    !words.contains(&word) && !workspace_words.contains(&word)
+                })
+                .map(|(range, _)| range)
+                .collect()
+        })
+    }
+
+    pub fn suggestions(&self, dictionary: &str, word: &str) -> Vec<String> {
+        self.dictionary(dictionary)
+            .map(|words| suggestions(&words, word))
+            .unwrap_or_default()
+    }
+
+    /// Add `word` to the workspace's dictionary, so that it's no longer
+    /// reported as misspelled.
+    pub fn add_word(&self, word: &str) {
+        let word = word.to_lowercase();
+        self.workspace_words.update(|words| {
+            words.insert(word.clone());
+        });
+
+        let Some(file) = self.workspace_file.as_ref() else {
+            return;
+        };
+        let result = file
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| {
+                std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(file)
+            })
+            .and_then(|mut f| writeln!(f, "{word}"));
+        if let Err(err) = result {
+            tracing::error!("{:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwzESIANRTOLCDUGMPHBYFVKWZ'
+
+PFX U Y 1
+PFX U   0     un         .
+
+SFX S Y 4
+SFX S   y     ies        [^aeiou]y
+SFX S   0     s          [aeiou]y
+SFX S   0     es         [sxzh]
+SFX S   0     s          [^sxzhy]
+
+SFX D Y 2
+SFX D   0     d          e
+SFX D   0     ed         [^ey]
+";
+
+    const DIC: &str = "5
+box/S
+city/S
+key/S
+tie/UD
+word/SD
+";
+
+    #[test]
+    fn test_parse_dictionary() {
+        let words = parse_dictionary(DIC, &Affixes::parse(AFF));
+        let mut words: Vec<_> = words.into_iter().collect();
+        words.sort();
+        assert_eq!(
+            words,
+            vec![
+                "box", "boxes", "cities", "city", "key", "keys", "tie", "tied",
+                "untie", "untied", "word", "worded", "words",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dictionary_encoding() {
+        assert_eq!(dictionary_encoding(AFF.as_bytes()), encoding_rs::UTF_8);
+        assert_eq!(dictionary_encoding(b"TRY abc\n"), encoding_rs::UTF_8);
+
+        let aff = b"SET ISO8859-1\nTRY \xe9\n";
+        let encoding = dictionary_encoding(aff);
+        assert_eq!(encoding, encoding_rs::WINDOWS_1252);
+        assert_eq!(encoding.decode(b"caf\xe9").0, "caf\u{e9}");
+        assert_eq!(
+            dictionary_encoding(b"SET microsoft-cp1251\n"),
+            encoding_rs::WINDOWS_1251
+        );
+    }
+
+    #[test]
+    fn test_words() {
+        let text = "// Teh parser's cache, see src/main.rs and \
+                    foo_bar utf8 HashMap TODO ok";
+        let words: Vec<_> = words(text).into_iter().map(|(_, w)| w).collect();
+        assert_eq!(words, vec!["Teh", "parser", "cache", "see", "and"]);
+
+        let (range, word) = words_at("x 'quoted' y");
+        assert_eq!((range, word), (3..9, "quoted"));
+    }
+
+    fn words_at(text: &str) -> (Range<usize>, &str) {
+        words(text).into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let words = parse_dictionary(DIC, &Affixes::parse(AFF));
+        assert_eq!(suggestions(&words, "wrod"), vec!["word"]);
+        assert_eq!(suggestions(&words, "Boxs"), vec!["Box", "Boxes"]);
+        assert_eq!(suggestions(&words, "citties"), vec!["cities"]);
+        assert!(suggestions(&words, "zzzzzz").is_empty());
+    }
+}
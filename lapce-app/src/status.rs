@@ -1,12 +1,16 @@
 use std::{
+    cell::Cell,
     rc::Rc,
     sync::{atomic::AtomicU64, Arc},
+    time::Duration,
 };
 
 use floem::{
+    action::exec_after,
     event::EventPropagation,
     reactive::{
-        create_memo, Memo, ReadSignal, RwSignal, SignalGet, SignalUpdate, SignalWith,
+        create_effect, create_memo, create_rw_signal, Memo, ReadSignal, RwSignal,
+        SignalGet, SignalUpdate, SignalWith,
     },
     style::{AlignItems, CursorStyle, Display, FlexWrap},
     views::{dyn_stack, label, stack, svg, Decorators},
@@ -14,6 +18,7 @@
 };
 use indexmap::IndexMap;
 use lapce_core::mode::{Mode, VisualMode};
+use lapce_rpc::plugin::PluginId;
 use lsp_types::{DiagnosticSeverity, ProgressToken};
 
 use crate::{
@@ -21,6 +26,7 @@
     command::LapceWorkbenchCommand,
     config::{color::LapceColor, icon::LapceIcons, LapceConfig},
     editor::EditorData,
+    image_viewer::image_status,
     listener::Listener,
     palette::kind::PaletteKind,
     panel::{kind::PanelKind, position::PanelContainerPosition},
@@ -40,6 +46,7 @@ pub fn status(
     let editor = window_tab_data.main_split.active_editor;
     let panel = window_tab_data.panel.clone();
     let palette = window_tab_data.palette.clone();
+    let zen_mode = window_tab_data.zen_mode;
     let diagnostic_count = create_memo(move |_| {
         let mut errors = 0;
         let mut warnings = 0;
@@ -71,6 +78,10 @@ pub fn status(
     };
 
     let progresses = window_tab_data.progresses;
+    let macros = window_tab_data.common.keypress.with_untracked(|k| k.macros);
+    let recording = move || {
+        macros.with(|m| m.recording().map(|name| format!("Recording @{name}")))
+    };
     let mode = create_memo(move |_| window_tab_data.mode());
     let pointer_down = floem::reactive::create_rw_signal(false);
 
@@ -124,6 +135,14 @@ pub fn status(
                     .align_items(Some(AlignItems::Center))
                     .selectable(false)
             }),
+            label(move || recording().unwrap_or_default()).style(move |s| {
+                s.apply_if(recording().is_none(), |s| s.hide())
+                    .padding_horiz(10.0)
+                    .height_pct(100.0)
+                    .align_items(Some(AlignItems::Center))
+                    .color(config.get().color(LapceColor::STATUS_FOREGROUND))
+                    .selectable(false)
+            }),
             stack((
                 svg(move || config.get().ui_svg(LapceIcons::SCM)).style(move |s| {
                     let config = config.get();
@@ -369,6 +388,17 @@ pub fn status(
                 palette_clone.run(PaletteKind::LineEnding);
             });
             let palette_clone = palette.clone();
+            let encoding_info = status_text(config, editor, move || {
+                if let Some(editor) = editor.get() {
+                    editor.doc_signal().get().encoding.get()
+                } else {
+                    String::new()
+                }
+            })
+            .on_click_stop(move |_| {
+                palette_clone.run(PaletteKind::ReopenWithEncoding);
+            });
+            let palette_clone = palette.clone();
             let language_info = status_text(config, editor, move || {
                 if let Some(editor) = editor.get() {
                     let doc = editor.doc_signal().get();
@@ -380,7 +410,65 @@ pub fn status(
             .on_click_stop(move |_| {
                 palette_clone.run(PaletteKind::Language);
             });
-            (cursor_info, line_ending_info, language_info)
+            let main_split = window_tab_data.main_split.clone();
+            let image_viewer = move || main_split.active_image_viewer();
+            let is_image = {
+                let image_viewer = image_viewer.clone();
+                move || image_viewer().is_some()
+            };
+            let image_info = label(move || {
+                image_viewer()
+                    .map(|image_viewer| image_status(&image_viewer))
+                    .unwrap_or_default()
+            })
+            .style(move |s| {
+                let config = config.get();
+                s.height_full()
+                    .padding_horiz(10.0)
+                    .items_center()
+                    .selectable(false)
+                    .color(config.color(LapceColor::STATUS_FOREGROUND))
+                    .apply_if(!is_image(), |s| s.hide())
+            });
+            let notifications = window_tab_data.common.notifications.clone();
+            let unread = notifications.unread;
+            let notification_info = stack((
+                svg(move || config.get().ui_svg(LapceIcons::NOTIFICATION)).style(
+                    move |s| {
+                        let config = config.get();
+                        let size = config.ui.icon_size() as f32;
+                        s.size(size, size)
+                            .color(config.color(LapceColor::LAPCE_ICON_ACTIVE))
+                    },
+                ),
+                label(move || unread.get().to_string()).style(move |s| {
+                    s.margin_left(5.0)
+                        .color(config.get().color(LapceColor::STATUS_FOREGROUND))
+                        .selectable(false)
+                        .apply_if(unread.get() == 0, |s| s.hide())
+                }),
+            ))
+            .on_click_stop(move |_| {
+                notifications.toggle_history();
+            })
+            .style(move |s| {
+                s.height_pct(100.0)
+                    .padding_horiz(10.0)
+                    .items_center()
+                    .hover(|s| {
+                        s.cursor(CursorStyle::Pointer).background(
+                            config.get().color(LapceColor::PANEL_HOVERED_BACKGROUND),
+                        )
+                    })
+            });
+            (
+                image_info,
+                cursor_info,
+                line_ending_info,
+                encoding_info,
+                language_info,
+                notification_info,
+            )
         })
         .style(|s| {
             s.height_pct(100.0)
@@ -404,37 +492,82 @@ pub fn status(
             .flex_grow(0.0)
             .flex_shrink(0.0)
             .items_center()
+            .apply_if(zen_mode.with(Option::is_some), |s| s.hide())
     })
     .debug_name("Status/Bottom Bar")
 }
 
+/// The frames of the spinner shown while the language servers are working.
+const SPINNER_FRAMES: [&str; 10] =
+    ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
 fn progress_view(
     config: ReadSignal<Arc<LapceConfig>>,
-    progresses: RwSignal<IndexMap<ProgressToken, WorkProgress>>,
+    progresses: RwSignal<IndexMap<(PluginId, ProgressToken), WorkProgress>>,
 ) -> impl View {
+    let frame = create_rw_signal(0usize);
+    let spinning = Rc::new(Cell::new(false));
+    create_effect(move |_| {
+        if !progresses.with(|p| p.is_empty()) && !spinning.get() {
+            spinning.set(true);
+            spin(frame, progresses, spinning.clone());
+        }
+    });
+
     let id = AtomicU64::new(0);
-    dyn_stack(
-        move || progresses.get(),
-        move |_| id.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
-        move |(_, p)| {
-            let progress = match p.message {
-                Some(message) if !message.is_empty() => {
-                    format!("{}: {}", p.title, message)
-                }
-                _ => p.title,
-            };
-            label(move || progress.clone()).style(move |s| {
-                s.height_pct(100.0)
-                    .min_width(0.0)
-                    .margin_left(10.0)
-                    .text_ellipsis()
+    stack((
+        label(move || SPINNER_FRAMES[frame.get() % SPINNER_FRAMES.len()]).style(
+            move |s| {
+                s.margin_left(10.0)
                     .selectable(false)
-                    .items_center()
                     .color(config.get().color(LapceColor::STATUS_FOREGROUND))
-            })
-        },
-    )
-    .style(move |s| s.flex_wrap(FlexWrap::Wrap).height_pct(100.0).min_width(0.0))
+                    .apply_if(progresses.with(|p| p.is_empty()), |s| s.hide())
+            },
+        ),
+        dyn_stack(
+            move || progresses.get(),
+            move |_| id.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            move |(_, p)| {
+                let mut progress = format!("{}: {}", p.name, p.title);
+                if let Some(message) = p.message.filter(|m| !m.is_empty()) {
+                    progress.push(' ');
+                    progress.push_str(&message);
+                }
+                if let Some(percentage) = p.percentage {
+                    progress.push_str(&format!(" ({percentage}%)"));
+                }
+                label(move || progress.clone()).style(move |s| {
+                    s.height_pct(100.0)
+                        .min_width(0.0)
+                        .margin_left(10.0)
+                        .text_ellipsis()
+                        .selectable(false)
+                        .items_center()
+                        .color(config.get().color(LapceColor::STATUS_FOREGROUND))
+                })
+            },
+        )
+        .style(move |s| {
+            s.flex_wrap(FlexWrap::Wrap).height_pct(100.0).min_width(0.0)
+        }),
+    ))
+    .style(|s| s.height_pct(100.0).min_width(0.0).items_center())
+}
+
+/// Advance the spinner until there is no more work in progress.
+fn spin(
+    frame: RwSignal<usize>,
+    progresses: RwSignal<IndexMap<(PluginId, ProgressToken), WorkProgress>>,
+    spinning: Rc<Cell<bool>>,
+) {
+    exec_after(Duration::from_millis(100), move |_| {
+        if progresses.with_untracked(|p| p.is_empty()) {
+            spinning.set(false);
+            return;
+        }
+        frame.update(|frame| *frame += 1);
+        spin(frame, progresses, spinning);
+    });
 }
 
 fn status_text<S: std::fmt::Display + 'static>(
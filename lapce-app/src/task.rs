@@ -0,0 +1,327 @@
+//! The tasks of the workspace, from `.lapce/tasks.toml` and detected from its
+//! build files. They run in the proxy, with their output shown in the tasks
+//! panel and the problems found in it in the problems panel.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Duration,
+};
+
+use floem::{
+    action::exec_after,
+    ext_event::create_ext_action,
+    reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith},
+};
+use lapce_rpc::{
+    core::CoreNotification,
+    proxy::ProxyResponse,
+    task::{TaskConfig, TaskId, TaskOutputLink},
+};
+use lsp_types::{Diagnostic, MessageType, ShowMessageParams};
+
+use crate::{
+    doc::DiagnosticSource, main_split::MainSplitData, window_tab::CommonData,
+};
+
+/// The number of lines kept of the output of a task, dropping the oldest ones.
+const TASK_OUTPUT_LIMIT: usize = 5000;
+
+/// How long the problems found by the tasks are gathered before they're shown,
+/// rather than updating the diagnostics of a file for each of them.
+const TASK_DIAGNOSTICS_DELAY: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TaskStatus {
+    Running,
+    /// Exited with its exit code, which is none when killed by a signal.
+    Exited(Option<i32>),
+    /// Couldn't be started.
+    Failed(String),
+}
+
+impl TaskStatus {
+    pub fn is_running(&self) -> bool {
+        matches!(self, TaskStatus::Running)
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, TaskStatus::Exited(Some(0)))
+    }
+
+    /// The status as shown next to the task in the panel.
+    pub fn label(&self) -> String {
+        match self {
+            TaskStatus::Running => "running".to_string(),
+            TaskStatus::Exited(Some(code)) => format!("exit {code}"),
+            TaskStatus::Exited(None) => "stopped".to_string(),
+            TaskStatus::Failed(_) => "failed".to_string(),
+        }
+    }
+}
+
+/// The last run of a task.
+#[derive(Clone)]
+pub struct TaskRun {
+    pub task_id: TaskId,
+    pub status: RwSignal<TaskStatus>,
+    /// The lines printed by the task with their ids, which keep increasing as
+    /// lines are added.
+    pub output: RwSignal<im::Vector<(usize, String)>>,
+    /// The ids of the lines of the problem revealed from the problems panel.
+    pub highlight: RwSignal<Option<Range<usize>>>,
+}
+
+#[derive(Clone)]
+pub struct TaskData {
+    scope: Scope,
+    /// The tasks of `.lapce/tasks.toml`, followed by the detected ones.
+    pub tasks: RwSignal<im::Vector<TaskConfig>>,
+    /// The last run of each task, by the name of the task.
+    pub runs: RwSignal<im::HashMap<String, TaskRun>>,
+    /// The task whose output is shown in the panel.
+    pub selected: RwSignal<Option<String>>,
+    /// The task run last, for rerunning it.
+    pub last_task: RwSignal<Option<TaskConfig>>,
+    /// The problems found by the tasks and not shown yet, by the name of the
+    /// task and their file.
+    pending_diagnostics: Rc<RefCell<HashMap<(String, PathBuf), Vec<Diagnostic>>>>,
+    main_split: MainSplitData,
+    common: Rc<CommonData>,
+}
+
+impl TaskData {
+    pub fn new(
+        cx: Scope,
+        main_split: MainSplitData,
+        common: Rc<CommonData>,
+    ) -> Self {
+        Self {
+            scope: cx,
+            tasks: cx.create_rw_signal(im::Vector::new()),
+            runs: cx.create_rw_signal(im::HashMap::new()),
+            selected: cx.create_rw_signal(None),
+            last_task: cx.create_rw_signal(None),
+            pending_diagnostics: Rc::new(RefCell::new(HashMap::new())),
+            main_split,
+            common,
+        }
+    }
+
+    /// Load the tasks of the workspace again, e.g. after `tasks.toml` changed.
+    pub fn load(&self) {
+        let tasks = self.tasks;
+        let core_rpc = self.common.proxy.core_rpc.clone();
+        let send = create_ext_action(self.scope, move |result| match result {
+            Ok(ProxyResponse::GetTasksResponse { tasks: new }) => {
+                tasks.set(new.into());
+            }
+            Ok(_) => {}
+            Err(err) => {
+                core_rpc.notification(CoreNotification::ShowMessage {
+                    title: "Load Tasks".to_string(),
+                    message: ShowMessageParams {
+                        typ: MessageType::ERROR,
+                        message: err.message,
+                    },
+                });
+            }
+        });
+        self.common.proxy.get_tasks(move |result| send(result));
+    }
+
+    /// Run the task, stopping its previous run if it's still running.
+    pub fn run(&self, task: TaskConfig) {
+        let name = task.name.clone();
+        let previous = self.runs.with_untracked(|runs| runs.get(&name).cloned());
+        if let Some(previous) = previous.as_ref() {
+            if previous.status.get_untracked().is_running() {
+                self.common.proxy.stop_task(previous.task_id);
+            }
+        }
+        self.clear_diagnostics(&name);
+
+        let task_id = TaskId::next();
+        let run = match previous {
+            Some(previous) => {
+                previous.status.set(TaskStatus::Running);
+                previous.output.set(im::Vector::new());
+                previous.highlight.set(None);
+                TaskRun {
+                    task_id,
+                    ..previous
+                }
+            }
+            None => TaskRun {
+                task_id,
+                status: self.scope.create_rw_signal(TaskStatus::Running),
+                output: self.scope.create_rw_signal(im::Vector::new()),
+                highlight: self.scope.create_rw_signal(None),
+            },
+        };
+        self.runs.update(|runs| {
+            runs.insert(name.clone(), run);
+        });
+        self.selected.set(Some(name));
+        self.last_task.set(Some(task.clone()));
+        self.common.proxy.run_task(task_id, task);
+    }
+
+    pub fn rerun_last(&self) {
+        if let Some(task) = self.last_task.get_untracked() {
+            self.run(task);
+        }
+    }
+
+    pub fn stop(&self, name: &str) {
+        let run = self.runs.with_untracked(|runs| runs.get(name).cloned());
+        if let Some(run) = run {
+            if run.status.get_untracked().is_running() {
+                self.common.proxy.stop_task(run.task_id);
+            }
+        }
+    }
+
+    pub fn stop_last(&self) {
+        if let Some(task) = self.last_task.get_untracked() {
+            self.stop(&task.name);
+        }
+    }
+
+    /// The name of the task and its run, if `task_id` is its last run.
+    fn run_of(&self, task_id: TaskId) -> Option<(String, TaskRun)> {
+        self.runs.with_untracked(|runs| {
+            runs.iter()
+                .find(|(_, run)| run.task_id == task_id)
+                .map(|(name, run)| (name.clone(), run.clone()))
+        })
+    }
+
+    pub fn output(&self, task_id: TaskId, id: usize, line: String) {
+        let Some((_, run)) = self.run_of(task_id) else {
+            return;
+        };
+        run.output.update(|output| {
+            // The lines of stdout and stderr are sent separately, so they can
+            // arrive out of order
+            let index = output
+                .iter()
+                .rposition(|(other, _)| *other < id)
+                .map_or(0, |index| index + 1);
+            output.insert(index, (id, line));
+            while output.len() > TASK_OUTPUT_LIMIT {
+                output.pop_front();
+            }
+        });
+    }
+
+    pub fn diagnostic(
+        &self,
+        task_id: TaskId,
+        path: PathBuf,
+        diagnostic: Diagnostic,
+    ) {
+        let Some((name, _)) = self.run_of(task_id) else {
+            return;
+        };
+        let first = {
+            let mut pending = self.pending_diagnostics.borrow_mut();
+            let first = pending.is_empty();
+            pending.entry((name, path)).or_default().push(diagnostic);
+            first
+        };
+        if first {
+            let data = self.clone();
+            exec_after(TASK_DIAGNOSTICS_DELAY, move |_| data.flush_diagnostics());
+        }
+    }
+
+    /// Show the problems found by the tasks since the last time, with a
+    /// single update of the diagnostics of each file.
+    fn flush_diagnostics(&self) {
+        let pending = std::mem::take(&mut *self.pending_diagnostics.borrow_mut());
+        for ((name, path), diagnostics) in pending {
+            self.main_split
+                .get_diagnostic_data(&path)
+                .extend_source_diagnostics(
+                    DiagnosticSource::Task(name),
+                    diagnostics,
+                );
+            self.init_doc_diagnostics(&path);
+        }
+    }
+
+    pub fn exited(
+        &self,
+        task_id: TaskId,
+        exit_code: Option<i32>,
+        error: Option<String>,
+    ) {
+        let Some((_, run)) = self.run_of(task_id) else {
+            return;
+        };
+        self.flush_diagnostics();
+        match error {
+            Some(error) => {
+                let id = run.output.with_untracked(|output| {
+                    output.back().map_or(0, |(id, _)| id + 1)
+                });
+                self.output(task_id, id, error.clone());
+                run.status.set(TaskStatus::Failed(error));
+            }
+            None => run.status.set(TaskStatus::Exited(exit_code)),
+        }
+    }
+
+    /// Select the task run the problem of `link` was found in, highlighting
+    /// the lines it was printed on. Returns whether the run is still shown.
+    pub fn reveal_output(&self, link: &TaskOutputLink) -> bool {
+        let Some((name, run)) = self.run_of(link.task_id) else {
+            return false;
+        };
+        self.selected.set(Some(name));
+        run.highlight.set(Some(link.lines.clone()));
+        true
+    }
+
+    /// Remove the problems found by the previous run of the task.
+    fn clear_diagnostics(&self, name: &str) {
+        self.pending_diagnostics
+            .borrow_mut()
+            .retain(|(task, _), _| task != name);
+        let source = DiagnosticSource::Task(name.to_string());
+        let paths = self.main_split.diagnostics.with_untracked(|diagnostics| {
+            diagnostics
+                .iter()
+                .filter(|(_, data)| {
+                    data.source_diagnostics
+                        .with_untracked(|sources| sources.contains_key(&source))
+                })
+                .map(|(path, data)| (path.clone(), data.clone()))
+                .collect::<Vec<_>>()
+        });
+        for (path, data) in paths {
+            data.set_source_diagnostics(source.clone(), Vec::new());
+            self.init_doc_diagnostics(&path);
+        }
+    }
+
+    fn init_doc_diagnostics(&self, path: &Path) {
+        if let Some(doc) = self
+            .main_split
+            .docs
+            .with_untracked(|docs| docs.get(path).cloned())
+        {
+            doc.init_diagnostics();
+        }
+    }
+
+    /// The output of the task shown in the panel.
+    pub fn selected_run(&self) -> Option<TaskRun> {
+        let selected = self.selected.get()?;
+        self.runs.with(|runs| runs.get(&selected).cloned())
+    }
+}
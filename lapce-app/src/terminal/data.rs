@@ -1,7 +1,14 @@
-use std::{collections::HashMap, path::PathBuf, rc::Rc, sync::Arc};
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
 
 use alacritty_terminal::{
     grid::{Dimensions, Scroll},
+    index::Direction,
     selection::{Selection, SelectionType},
     term::{test::TermSize, TermMode},
     vi_mode::ViMotion,
@@ -21,14 +28,14 @@
 };
 use lapce_rpc::{
     dap_types::RunDebugConfig,
-    terminal::{TermId, TerminalProfile},
+    terminal::{TermId, TerminalProcessUsage, TerminalProfile},
 };
 use parking_lot::RwLock;
 use url::Url;
 
 use super::{
     event::TermEvent,
-    raw::{EventProxy, RawTerminal},
+    raw::{EventProxy, RawTerminal, TerminalLog},
 };
 use crate::{
     command::{CommandExecuted, CommandKind, InternalCommand},
@@ -44,6 +51,14 @@ pub struct TerminalData {
     pub term_id: TermId,
     pub workspace: Arc<LapceWorkspace>,
     pub title: RwSignal<String>,
+    /// The profile the terminal was launched with.
+    pub profile: Option<TerminalProfile>,
+    /// The working directory of the shell, as reported by the proxy.
+    pub cwd: RwSignal<Option<PathBuf>>,
+    /// The resource usage of the foreground process, as reported by the proxy.
+    pub process_usage: RwSignal<Option<TerminalProcessUsage>>,
+    /// The flex factor of this terminal within the split of its tab.
+    pub split_size: RwSignal<f64>,
     pub launch_error: RwSignal<Option<String>>,
     pub mode: RwSignal<Mode>,
     pub visual_mode: RwSignal<VisualMode>,
@@ -289,6 +304,48 @@ fn run_command(
         CommandExecuted::Yes
     }
 
+    /// Insert the shell-quoted paths of files dropped onto the terminal at the
+    /// cursor, each followed by a space to separate it from the next.
+    pub fn paste_paths(&self, paths: &[PathBuf]) {
+        if self.mode.get_untracked() != Mode::Terminal || paths.is_empty() {
+            return;
+        }
+        let relative = self
+            .common
+            .config
+            .get_untracked()
+            .terminal
+            .drop_relative_paths;
+        let workspace = self.workspace.path.as_deref();
+        let windows = cfg!(windows) && !self.workspace.kind.is_remote();
+        let content: String = paths
+            .iter()
+            .map(|path| {
+                let path = match workspace {
+                    Some(workspace) if relative => {
+                        path.strip_prefix(workspace).unwrap_or(path)
+                    }
+                    _ => path,
+                };
+                format!("{} ", shell_quote(&path.to_string_lossy(), windows))
+            })
+            .collect();
+
+        let bracketed_paste = {
+            let raw = self.raw.get_untracked();
+            let raw = raw.read();
+            raw.term.mode().contains(TermMode::BRACKETED_PASTE)
+        };
+        if bracketed_paste {
+            self.receive_char(&format!(
+                "\x1b[200~{}\x1b[201~",
+                content.replace('\x1b', "")
+            ));
+        } else {
+            self.receive_char(&content);
+        }
+    }
+
     fn receive_char(&self, c: &str) {
         if self.mode.get_untracked() == Mode::Terminal {
             self.common
@@ -329,13 +386,16 @@ pub fn new_run_debug(
             cx.create_rw_signal(String::from("Default"))
         };
 
+        let cwd = cx.create_rw_signal(None);
+        let process_usage = cx.create_rw_signal(None);
+        let split_size = cx.create_rw_signal(1.0);
         let launch_error = cx.create_rw_signal(None);
 
         let raw = Self::new_raw_terminal(
             &workspace,
             term_id,
             run_debug.as_ref(),
-            profile,
+            profile.clone(),
             common.clone(),
             launch_error,
         );
@@ -351,6 +411,10 @@ pub fn new_run_debug(
             workspace,
             raw,
             title,
+            profile,
+            cwd,
+            process_usage,
+            split_size,
             run_debug,
             mode,
             visual_mode,
@@ -414,8 +478,11 @@ fn new_raw_terminal(
 
             profile.environment = run_debug.env;
 
-            profile.command = Some(run_debug.program);
-            profile.arguments = run_debug.args;
+            // Without a program, as when attaching a debugger, it's a shell
+            if !run_debug.program.is_empty() {
+                profile.command = Some(run_debug.program);
+                profile.arguments = run_debug.args;
+            }
         }
 
         {
@@ -633,6 +700,14 @@ macro_rules! term_sequence {
         }
     }
 
+    /// Scroll to the previous (`Direction::Left`) or next (`Direction::Right`)
+    /// prompt marked by the shell integration.
+    pub fn scroll_to_prompt(&self, direction: Direction) -> bool {
+        let raw = self.raw.get_untracked();
+        let mut raw = raw.write();
+        raw.scroll_to_prompt(direction)
+    }
+
     pub fn wheel_scroll(&self, delta: f64) {
         let config = self.common.config.get_untracked();
         let step = config.terminal_line_height() as f64;
@@ -737,16 +812,52 @@ pub fn new_process(&self, run_debug: Option<RunDebugProcess>) {
             self.launch_error,
         );
 
+        // Keep logging into the same file across restarts.
+        let log = self.raw.get_untracked().write().log.take();
+        raw.write().log = log;
+
         self.raw.set(raw);
         self.run_debug.set(run_debug);
 
         let term_size = TermSize::new(width, height);
-        self.raw.get_untracked().write().term.resize(term_size);
+        self.raw.get_untracked().write().resize(term_size);
         self.common
             .proxy
             .terminal_resize(self.term_id, width, height);
     }
 
+    /// Make the program in the terminal redraw after reattaching to the proxy
+    /// it runs in, in case some of its output was lost with the connection.
+    pub fn resync(&self) {
+        let (width, height) = {
+            let raw = self.raw.get_untracked();
+            let raw = raw.read();
+            (raw.term.columns(), raw.term.screen_lines())
+        };
+        // Setting the same size again doesn't notify the program, so the
+        // size is changed back and forth.
+        let proxy = &self.common.proxy;
+        proxy.terminal_resize(self.term_id, width, height.saturating_sub(1).max(1));
+        proxy.terminal_resize(self.term_id, width, height);
+    }
+
+    /// Start copying everything the terminal receives into the file at `path`,
+    /// replacing its content.
+    pub fn start_logging(
+        &self,
+        path: &Path,
+        timestamps: bool,
+    ) -> std::io::Result<()> {
+        let file = File::create(path)?;
+        self.raw.get_untracked().write().log =
+            Some(TerminalLog::new(file, timestamps));
+        Ok(())
+    }
+
+    pub fn stop_logging(&self) {
+        self.raw.get_untracked().write().log = None;
+    }
+
     pub fn stop(&self) {
         if let Some(dap_id) = self.run_debug.with_untracked(|x| {
             if let Some(process) = x {
@@ -762,6 +873,25 @@ pub fn stop(&self) {
     }
 }
 
+/// Quote `s` for a shell, if it contains anything the shell would interpret.
+/// POSIX shells get single quotes, and Windows shells double quotes, which
+/// can't appear in a path there. A `%` is left as it is, as cmd expands the
+/// variables within double quotes too.
+fn shell_quote(s: &str, windows: bool) -> String {
+    let is_plain = |c: char| {
+        c.is_ascii_alphanumeric()
+            || matches!(c, '/' | '.' | '-' | '_' | '+' | ',' | ':' | '@' | '%' | '=')
+            || (windows && c == '\\')
+    };
+    if !s.is_empty() && s.chars().all(is_plain) {
+        s.to_string()
+    } else if windows {
+        format!("\"{s}\"")
+    } else {
+        format!("'{}'", s.replace('\'', "'\\''"))
+    }
+}
+
 /// [`RunDebugConfig`] with expanded out program/arguments/etc. Used for creating the terminal.
 #[derive(Debug, Clone)]
 pub struct ExpandedRunDebug {
@@ -856,3 +986,39 @@ fn expand_work_dir(
         Url::from_file_path(PathBuf::from(path)).ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::shell_quote;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/home/user/a.txt", false), "/home/user/a.txt");
+        assert_eq!(shell_quote("", false), "''");
+        assert_eq!(
+            shell_quote("/home/user/my file.txt", false),
+            "'/home/user/my file.txt'"
+        );
+        assert_eq!(shell_quote("/tmp/it's", false), "'/tmp/it'\\''s'");
+        assert_eq!(shell_quote("/tmp/\"a\"", false), "'/tmp/\"a\"'");
+        assert_eq!(shell_quote("/tmp/$HOME", false), "'/tmp/$HOME'");
+        assert_eq!(shell_quote("/tmp/100%", false), "/tmp/100%");
+        assert_eq!(shell_quote("/tmp/a^b", false), "'/tmp/a^b'");
+    }
+
+    #[test]
+    fn test_shell_quote_windows() {
+        assert_eq!(shell_quote("C:\\Users\\a.txt", true), "C:\\Users\\a.txt");
+        assert_eq!(
+            shell_quote("C:\\Program Files\\a.txt", true),
+            "\"C:\\Program Files\\a.txt\""
+        );
+        // Single quotes aren't special to cmd, but are to PowerShell.
+        assert_eq!(shell_quote("C:\\it's", true), "\"C:\\it's\"");
+        assert_eq!(shell_quote("C:\\100%", true), "C:\\100%");
+        assert_eq!(shell_quote("C:\\100% done", true), "\"C:\\100% done\"");
+        // The escape character of cmd is taken literally within quotes.
+        assert_eq!(shell_quote("C:\\a^b", true), "\"C:\\a^b\"");
+        assert_eq!(shell_quote("C:\\a&b", true), "\"C:\\a&b\"");
+    }
+}
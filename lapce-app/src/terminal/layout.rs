@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use lapce_rpc::terminal::TerminalProfile;
+use serde::{Deserialize, Serialize};
+
+/// A named arrangement of the terminal panel, saved per workspace so the
+/// same set of terminals can be opened again in one go.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TerminalLayout {
+    pub name: String,
+    pub tabs: Vec<TerminalTabLayout>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TerminalTabLayout {
+    /// The terminals split side by side in the tab.
+    pub terminals: Vec<TerminalPaneLayout>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TerminalPaneLayout {
+    pub title: String,
+    /// The profile the terminal was launched with.
+    pub profile: Option<TerminalProfile>,
+    /// The working directory of the shell when the layout was saved.
+    pub cwd: Option<PathBuf>,
+    /// The flex factor of the terminal within the split of its tab.
+    pub split_size: f64,
+}
+
+impl TerminalPaneLayout {
+    /// The profile to launch the terminal with, in its saved working
+    /// directory and with its saved title.
+    pub fn launch_profile(&self) -> TerminalProfile {
+        let mut profile = self.profile.clone().unwrap_or_default();
+        profile.name.clone_from(&self.title);
+        if let Some(cwd) = self.cwd.as_ref() {
+            profile.workdir = url::Url::from_directory_path(cwd).ok();
+        }
+        profile
+    }
+}
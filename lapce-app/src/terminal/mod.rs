@@ -1,5 +1,6 @@
 pub mod data;
 pub mod event;
+pub mod layout;
 pub mod panel;
 pub mod raw;
 pub mod tab;
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, path::PathBuf, rc::Rc, sync::Arc};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
 
 use floem::{
     ext_event::create_ext_action,
@@ -7,17 +12,22 @@
 use lapce_core::mode::Mode;
 use lapce_rpc::{
     dap_types::{
-        self, DapId, RunDebugConfig, StackFrame, Stopped, ThreadId, Variable,
+        self, DapId, DebugRequest, RunDebugConfig, StackFrame, Stopped, ThreadId,
+        Variable,
     },
     proxy::ProxyResponse,
-    terminal::{TermId, TerminalProfile},
+    terminal::{TermId, TerminalProcessUsage, TerminalProfile},
 };
 
-use super::{data::TerminalData, tab::TerminalTabData};
+use super::{
+    data::TerminalData,
+    layout::{TerminalLayout, TerminalPaneLayout, TerminalTabLayout},
+    tab::TerminalTabData,
+};
 use crate::{
     debug::{
-        DapData, DapVariable, RunDebugConfigs, RunDebugData, RunDebugMode,
-        RunDebugProcess, ScopeOrVar,
+        ConsoleEntry, DapData, DapVariable, RunDebugConfigs, RunDebugData,
+        RunDebugMode, RunDebugProcess, ScopeOrVar, LAUNCH_TOML, RUN_TOML,
     },
     id::TerminalTabId,
     keypress::{EventRef, KeyPressData, KeyPressFocus, KeyPressHandle},
@@ -62,6 +72,19 @@ pub fn new(
 
         let debug = RunDebugData::new(cx, common.breakpoints);
 
+        {
+            let config = common.config;
+            let proxy = common.proxy.clone();
+            cx.create_effect(move |last_enabled| {
+                let enabled =
+                    config.with(|config| config.terminal.show_process_usage);
+                if last_enabled != Some(enabled) {
+                    proxy.terminal_sample_usage(enabled);
+                }
+                enabled
+            });
+        }
+
         let breakline = {
             let active_term = debug.active_term;
             let daps = debug.daps;
@@ -201,6 +224,95 @@ pub fn new_tab_run_debug(
         terminal_tab
     }
 
+    /// Open a new terminal tab next to the one of `term_id`, with the same
+    /// profile and in the same working directory.
+    pub fn duplicate_terminal(&self, term_id: TermId) {
+        let Some(terminal) = self.get_terminal(&term_id) else {
+            return;
+        };
+        let index = self.tab_info.with_untracked(|info| {
+            info.tabs.iter().position(|(_, tab)| {
+                tab.terminals.with_untracked(|terminals| {
+                    terminals.iter().any(|(_, t)| t.term_id == term_id)
+                })
+            })
+        });
+
+        let mut profile = terminal.profile.clone();
+        if let Some(cwd) = terminal.cwd.get_untracked() {
+            let profile = profile.get_or_insert_with(|| TerminalProfile {
+                name: terminal.title.get_untracked(),
+                ..Default::default()
+            });
+            profile.workdir = url::Url::from_directory_path(cwd).ok();
+        }
+
+        if let Some(index) = index {
+            self.tab_info.update(|info| info.active = index);
+        }
+        self.new_tab(profile);
+        self.update_debug_active_term();
+    }
+
+    /// The current arrangement of the terminals, saved as `name`. Run and
+    /// debug terminals are left out, as they belong to their configuration.
+    pub fn layout(&self, name: String) -> TerminalLayout {
+        let tabs = self.tab_info.with_untracked(|info| {
+            info.tabs
+                .iter()
+                .filter_map(|(_, tab)| {
+                    let terminals: Vec<TerminalPaneLayout> =
+                        tab.terminals.with_untracked(|terminals| {
+                            terminals
+                                .iter()
+                                .filter(|(_, t)| {
+                                    t.run_debug.with_untracked(|r| r.is_none())
+                                })
+                                .map(|(_, t)| TerminalPaneLayout {
+                                    title: t.title.get_untracked(),
+                                    profile: t.profile.clone(),
+                                    cwd: t.cwd.get_untracked(),
+                                    split_size: t.split_size.get_untracked(),
+                                })
+                                .collect()
+                        });
+                    (!terminals.is_empty())
+                        .then_some(TerminalTabLayout { terminals })
+                })
+                .collect()
+        });
+        TerminalLayout { name, tabs }
+    }
+
+    /// Open the terminals of `layout` in new tabs after the active one.
+    pub fn restore_layout(&self, layout: &TerminalLayout) {
+        for tab_layout in &layout.tabs {
+            let Some((first, rest)) = tab_layout.terminals.split_first() else {
+                continue;
+            };
+            let tab = self.new_tab_run_debug(None, Some(first.launch_profile()));
+            tab.terminals.with_untracked(|terminals| {
+                if let Some((_, terminal)) = terminals.front() {
+                    terminal.split_size.set(first.split_size);
+                }
+            });
+            for pane in rest {
+                let terminal = TerminalData::new(
+                    tab.scope,
+                    self.workspace.clone(),
+                    Some(pane.launch_profile()),
+                    self.common.clone(),
+                );
+                terminal.split_size.set(pane.split_size);
+                let i = terminal.scope.create_rw_signal(0);
+                tab.terminals.update(|terminals| {
+                    terminals.push_back((i, terminal));
+                });
+            }
+        }
+        self.update_debug_active_term();
+    }
+
     pub fn next_tab(&self) {
         self.tab_info.update(|info| {
             if info.active >= info.tabs.len().saturating_sub(1) {
@@ -267,6 +379,63 @@ pub fn set_title(&self, term_id: &TermId, title: &str) {
         }
     }
 
+    pub fn set_cwd(&self, term_id: &TermId, cwd: &Path) {
+        if let Some(t) = self.get_terminal(term_id) {
+            t.cwd.set(Some(cwd.to_path_buf()));
+        }
+    }
+
+    pub fn set_process_usage(
+        &self,
+        term_id: &TermId,
+        usage: Option<TerminalProcessUsage>,
+    ) {
+        if let Some(t) = self.get_terminal(term_id) {
+            t.process_usage.set(usage);
+        }
+    }
+
+    /// After the connection to the proxy came back, close the terminals whose
+    /// process didn't survive it, and have the others redraw.
+    pub fn reattach(&self) {
+        let term_ids: Vec<TermId> = self.tab_info.with_untracked(|info| {
+            info.tabs
+                .iter()
+                .flat_map(|(_, tab)| {
+                    tab.terminals.with_untracked(|terminals| {
+                        terminals.iter().map(|(_, t)| t.term_id).collect::<Vec<_>>()
+                    })
+                })
+                .collect()
+        });
+        if term_ids.is_empty() {
+            return;
+        }
+
+        let terminal = self.clone();
+        let requested = term_ids.clone();
+        let send = create_ext_action(self.common.scope, move |result| {
+            let alive = match result {
+                Ok(ProxyResponse::ReattachTerminalsResponse { alive }) => alive,
+                _ => Vec::new(),
+            };
+            for term_id in &requested {
+                if alive.contains(term_id) {
+                    if let Some(t) = terminal.get_terminal(term_id) {
+                        t.resync();
+                    }
+                } else {
+                    terminal.terminal_stopped(term_id, None);
+                }
+            }
+        });
+        self.common
+            .proxy
+            .reattach_terminals(term_ids, move |result| {
+                send(result);
+            });
+    }
+
     pub fn get_terminal(&self, term_id: &TermId) -> Option<TerminalData> {
         self.tab_info.with_untracked(|info| {
             for (_, tab) in &info.tabs {
@@ -411,6 +580,14 @@ pub fn terminal_stopped(&self, term_id: &TermId, exit_code: Option<i32>) {
                     let run_debug = terminal.run_debug.get_untracked();
                     if let Some(run_debug) = run_debug {
                         if run_debug.mode == RunDebugMode::Debug {
+                            if run_debug.config.request == DebugRequest::Attach {
+                                // No program will be run in the terminal, which
+                                // becomes the shell of the session
+                                terminal.new_process(Some(RunDebugProcess {
+                                    stopped: false,
+                                    ..run_debug.clone()
+                                }));
+                            }
                             self.common.proxy.dap_start(
                                 run_debug.config,
                                 self.debug.source_breakpoints(),
@@ -517,23 +694,23 @@ pub fn restart_run_debug(&self, term_id: TermId) -> Option<bool> {
     }
 
     fn get_run_config_by_name(&self, name: &str) -> Option<RunDebugConfig> {
-        if let Some(workspace) = self.common.workspace.path.as_deref() {
-            let run_toml = workspace.join(".lapce").join("run.toml");
-            let (doc, new_doc) = self.main_split.get_doc(run_toml.clone(), None);
-            if !new_doc {
-                let content = doc.buffer.with_untracked(|b| b.to_string());
-                match toml::from_str::<RunDebugConfigs>(&content) {
-                    Ok(configs) => {
-                        return configs.configs.into_iter().find(|x| x.name == name);
-                    }
-                    Err(err) => {
-                        // todo show message window
-                        tracing::error!("deser fail {:?}", err);
-                    }
+        let workspace = self.common.workspace.path.as_deref()?;
+        [RUN_TOML, LAUNCH_TOML].into_iter().find_map(|file_name| {
+            let path = workspace.join(".lapce").join(file_name);
+            let (doc, new_doc) = self.main_split.get_doc(path, None);
+            if new_doc {
+                return None;
+            }
+            let content = doc.buffer.with_untracked(|b| b.to_string());
+            match toml::from_str::<RunDebugConfigs>(&content) {
+                Ok(configs) => configs.configs.into_iter().find(|x| x.name == name),
+                Err(err) => {
+                    // todo show message window
+                    tracing::error!("deser fail {:?}", err);
+                    None
                 }
             }
-        }
-        None
+        })
     }
 
     pub fn focus_terminal(&self, term_id: TermId) {
@@ -649,6 +826,20 @@ pub fn dap_continued(&self, dap_id: &DapId) {
         if let Some(dap) = dap {
             dap.thread_id.set(None);
             dap.stopped.set(false);
+            dap.active_frame.set(None);
+        }
+    }
+
+    pub fn dap_output(&self, dap_id: &DapId, output: &dap_types::Output) {
+        let dap = self
+            .debug
+            .daps
+            .with_untracked(|daps| daps.get(dap_id).cloned());
+        if let Some(dap) = dap {
+            dap.push_console(ConsoleEntry::Output {
+                text: output.output.clone(),
+                category: output.category.clone(),
+            });
         }
     }
 
@@ -771,6 +962,8 @@ pub fn get_dap(&self, term_id: TermId, tracked: bool) -> Option<DapData> {
 
     pub fn dap_frame_scopes(&self, dap_id: DapId, frame_id: usize) {
         if let Some(dap) = self.debug.daps.get_untracked().get(&dap_id) {
+            dap.active_frame.set(Some(frame_id));
+            dap.evaluate_watches();
             let variables = dap.variables;
             let send = create_ext_action(self.common.scope, move |result| {
                 if let Ok(ProxyResponse::DapGetScopesResponse { scopes }) = result {
@@ -1,20 +1,31 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    time::{Duration, Instant},
+};
+
 use alacritty_terminal::{
     event::EventListener,
-    grid::Dimensions,
+    grid::{Dimensions, Scroll},
     index::{Column, Direction, Line, Point},
     term::{
         cell::{Flags, LineLength},
         search::{Match, RegexIter, RegexSearch},
         test::TermSize,
+        TermMode,
     },
     vte::ansi,
     Term,
 };
-use crossbeam_channel::Sender;
+use crossbeam_channel::{RecvTimeoutError, Sender};
 use lapce_rpc::{proxy::ProxyRpcHandler, terminal::TermId};
 
 use super::event::TermNotification;
 
+/// The shell integration sequence (OSC 133) a shell prints where its prompt
+/// starts.
+const PROMPT_START: &[u8] = b"\x1b]133;A";
+
 pub struct EventProxy {
     term_id: TermId,
     proxy: ProxyRpcHandler,
@@ -54,6 +65,21 @@ pub struct RawTerminal {
     pub parser: ansi::Processor,
     pub term: Term<EventProxy>,
     pub scroll_delta: f64,
+    /// The file the output is being logged into, if any.
+    pub log: Option<TerminalLog>,
+    /// The lines where the shell marked the start of a prompt, counted from
+    /// the top of the scrollback, and moved up as lines are trimmed off it.
+    prompts: Vec<usize>,
+    /// The size of the scrollback when its top was last followed.
+    history_size: usize,
+    /// The display offset when the scrollback was last followed, which grows
+    /// by the lines scrolled into the scrollback while it isn't zero.
+    display_offset: usize,
+    /// Whether the display was scrolled up a line to count the lines
+    /// scrolled into the scrollback, and has to be scrolled back down.
+    pinned: bool,
+    /// How much of [`PROMPT_START`] the latest output ended with.
+    prompt_matched: usize,
 }
 
 impl RawTerminal {
@@ -80,13 +106,190 @@ pub fn new(
             parser,
             term,
             scroll_delta: 0.0,
+            log: None,
+            prompts: Vec::new(),
+            history_size: 0,
+            display_offset: 0,
+            pinned: false,
+            prompt_matched: 0,
         }
     }
 
     pub fn update_content(&mut self, content: Vec<u8>) {
+        if let Some(log) = self.log.as_ref() {
+            // The log stops writing when it fails to.
+            if log.tx.send(content.clone()).is_err() {
+                self.log = None;
+            }
+        }
+        self.take_display_offset();
         for byte in content {
+            self.pin_display();
             self.parser.advance(&mut self.term, byte);
+            self.follow_scrollback();
+            self.match_prompt_start(byte);
+        }
+        self.unpin_display();
+    }
+
+    /// Resize the terminal, forgetting the prompts when its lines are
+    /// reflowed to the new width, which they aren't followed through.
+    pub fn resize(&mut self, size: TermSize) {
+        if size.columns != self.term.columns() {
+            self.prompts.clear();
+        }
+        let shrinks = size.screen_lines < self.term.screen_lines();
+        if self.term.mode().contains(TermMode::ALT_SCREEN) {
+            self.term.resize(size);
+            // The lines a smaller screen pushes into the scrollback may trim
+            // it, which can't be followed from the alternate screen.
+            if shrinks {
+                self.prompts.clear();
+            }
+            return;
+        }
+        if shrinks {
+            // The lines a smaller screen pushes into the scrollback may trim
+            // it.
+            self.take_display_offset();
+            self.pin_display();
+            self.term.resize(size);
+            self.follow_scrollback();
+            self.unpin_display();
+        } else {
+            // The lines a larger screen pulls out of the scrollback keep
+            // their place counted from its top.
+            self.term.resize(size);
+            self.history_size = self.term.grid().history_size();
+        }
+    }
+
+    /// Take the display offset as it is, since it's also changed by scrolling
+    /// the display.
+    fn take_display_offset(&mut self) {
+        if !self.term.mode().contains(TermMode::ALT_SCREEN) {
+            self.display_offset = self.term.grid().display_offset();
+        }
+    }
+
+    /// Scroll the display up a line when it's at the bottom, for its offset
+    /// to count the lines scrolled into the scrollback.
+    fn pin_display(&mut self) {
+        if self.pinned || self.term.mode().contains(TermMode::ALT_SCREEN) {
+            return;
+        }
+        let grid = self.term.grid_mut();
+        if grid.display_offset() == 0 && grid.history_size() > 0 {
+            grid.scroll_display(Scroll::Delta(1));
+            self.display_offset = grid.display_offset();
+            self.pinned = true;
+        }
+    }
+
+    /// Scroll the display back down after [`Self::pin_display`], which waits
+    /// for the alternate screen to be left.
+    fn unpin_display(&mut self) {
+        if self.pinned && !self.term.mode().contains(TermMode::ALT_SCREEN) {
+            self.term.grid_mut().scroll_display(Scroll::Bottom);
+            self.display_offset = 0;
+            self.pinned = false;
+        }
+    }
+
+    /// Move the prompts up by the lines trimmed off the top of the scrollback
+    /// since it was last followed, or forget them when that can't be told.
+    fn follow_scrollback(&mut self) {
+        // The scrollback stays as it was while on the alternate screen.
+        if self.term.mode().contains(TermMode::ALT_SCREEN) {
+            return;
+        }
+        let grid = self.term.grid();
+        let history_size = grid.history_size();
+        let display_offset = grid.display_offset();
+        if history_size == self.history_size && display_offset == self.display_offset
+        {
+            return;
+        }
+
+        let trimmed = if history_size < self.history_size {
+            // The scrollback was cleared.
+            Some(self.history_size - history_size)
+        } else if display_offset == 0 {
+            // Nothing is trimmed until there's a scrollback to pin to.
+            Some(0)
+        } else if display_offset < self.display_offset
+            || (display_offset == history_size && history_size == self.history_size)
+        {
+            // The display was moved, or its offset stopped at the top of a
+            // full scrollback, so the lines scrolled can't be told.
+            None
+        } else {
+            // The lines scrolled into a full scrollback trim as many off it.
+            let scrolled = display_offset - self.display_offset;
+            Some(scrolled.saturating_sub(history_size - self.history_size))
+        };
+        match trimmed {
+            Some(0) => {}
+            Some(trimmed) => {
+                self.prompts = self
+                    .prompts
+                    .iter()
+                    .filter_map(|prompt| prompt.checked_sub(trimmed))
+                    .collect();
+            }
+            None => self.prompts.clear(),
         }
+
+        self.history_size = history_size;
+        self.display_offset = display_offset;
+    }
+
+    fn match_prompt_start(&mut self, byte: u8) {
+        if byte == PROMPT_START[self.prompt_matched] {
+            self.prompt_matched += 1;
+        } else {
+            self.prompt_matched = usize::from(byte == PROMPT_START[0]);
+        }
+        if self.prompt_matched == PROMPT_START.len() {
+            self.prompt_matched = 0;
+            let grid = self.term.grid();
+            let line = grid.history_size() as i32 + grid.cursor.point.line.0;
+            let line = line.max(0) as usize;
+            // Lines past the cursor are gone after the screen was cleared.
+            self.prompts.retain(|prompt| *prompt < line);
+            self.prompts.push(line);
+        }
+    }
+
+    /// Scroll the display to put the previous or next prompt at the top, so the
+    /// output of the command run from it can be reviewed.
+    ///
+    /// Returns `false` if there's no prompt in that direction.
+    pub fn scroll_to_prompt(&mut self, direction: Direction) -> bool {
+        let grid = self.term.grid();
+        let history_size = grid.history_size();
+        // The line at the top of the display, counted from the top of the
+        // scrollback.
+        let top = history_size - grid.display_offset();
+        let prompt = match direction {
+            Direction::Left => {
+                self.prompts.iter().rev().find(|prompt| **prompt < top)
+            }
+            Direction::Right => self
+                .prompts
+                .iter()
+                .find(|prompt| **prompt > top && **prompt <= history_size),
+        };
+        let Some(prompt) = prompt else {
+            if direction == Direction::Right && grid.display_offset() > 0 {
+                self.term.scroll_display(Scroll::Bottom);
+                return true;
+            }
+            return false;
+        };
+        let delta = top as i32 - *prompt as i32;
+        self.term.scroll_display(Scroll::Delta(delta));
+        true
     }
 
     pub fn output(&self, line_num: usize) -> Vec<String> {
@@ -146,3 +349,78 @@ pub fn visible_regex_match_iter<'a, EventProxy>(
 }
 /// todo:should be improved
 pub const MAX_SEARCH_LINES: usize = 100;
+
+/// How often the output logged is written out, at most.
+const LOG_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Copies the raw output of a terminal into a file, optionally prefixing every
+/// line with the time it was received. The file is written on a thread of its
+/// own, which flushes it every [`LOG_FLUSH_INTERVAL`] and when the log is
+/// dropped.
+pub struct TerminalLog {
+    tx: Sender<Vec<u8>>,
+}
+
+impl TerminalLog {
+    pub fn new(file: File, timestamps: bool) -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded::<Vec<u8>>();
+        std::thread::spawn(move || {
+            let mut writer = LogWriter {
+                file: BufWriter::new(file),
+                timestamps,
+                line_start: true,
+            };
+            let mut flushed = Instant::now();
+            loop {
+                let result = match rx.recv_timeout(LOG_FLUSH_INTERVAL) {
+                    Ok(content) => writer.write(&content).and_then(|_| {
+                        if flushed.elapsed() < LOG_FLUSH_INTERVAL {
+                            return Ok(());
+                        }
+                        flushed = Instant::now();
+                        writer.file.flush()
+                    }),
+                    Err(RecvTimeoutError::Timeout) => {
+                        flushed = Instant::now();
+                        writer.file.flush()
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        if let Err(err) = writer.file.flush() {
+                            tracing::error!("{:?}", err);
+                        }
+                        return;
+                    }
+                };
+                if let Err(err) = result {
+                    tracing::error!("{:?}", err);
+                    return;
+                }
+            }
+        });
+        Self { tx }
+    }
+}
+
+struct LogWriter {
+    file: BufWriter<File>,
+    timestamps: bool,
+    line_start: bool,
+}
+
+impl LogWriter {
+    fn write(&mut self, content: &[u8]) -> std::io::Result<()> {
+        if !self.timestamps {
+            return self.file.write_all(content);
+        }
+
+        for line in content.split_inclusive(|b| *b == b'\n') {
+            if self.line_start {
+                let now = chrono::Local::now().format("[%Y-%m-%d %H:%M:%S] ");
+                write!(self.file, "{now}")?;
+            }
+            self.file.write_all(line)?;
+            self.line_start = line.ends_with(b"\n");
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use std::{rc::Rc, sync::Arc};
 
-use floem::reactive::{RwSignal, Scope, SignalGet, SignalWith};
+use floem::reactive::{RwSignal, Scope, SignalGet, SignalUpdate, SignalWith};
 use lapce_rpc::terminal::TerminalProfile;
 
 use super::data::TerminalData;
@@ -65,4 +65,40 @@ pub fn active_terminal(&self, tracked: bool) -> Option<TerminalData> {
                 .map(|(_, t)| t)
         }
     }
+
+    /// Move the divider on the right of the active terminal, or on its left for
+    /// the last terminal, by `delta` as a fraction of the width of the split.
+    pub fn resize_active_terminal(&self, delta: f64) {
+        let active = self.active.get_untracked();
+        self.terminals.with_untracked(|terminals| {
+            if terminals.len() < 2 {
+                return;
+            }
+            let active = active.min(terminals.len() - 1);
+            let (left, right) = if active + 1 < terminals.len() {
+                (active, active + 1)
+            } else {
+                (active - 1, active)
+            };
+            let left = terminals[left].1.split_size;
+            let right = terminals[right].1.split_size;
+
+            let total: f64 = terminals
+                .iter()
+                .map(|(_, t)| t.split_size.get_untracked())
+                .sum();
+            // Don't let a terminal get narrower than a tenth of the split.
+            let min = total * 0.1;
+            let lower = min - left.get_untracked();
+            let upper = right.get_untracked() - min;
+            if lower > upper {
+                return;
+            }
+            let delta = (delta * total).clamp(lower, upper);
+            if delta != 0.0 {
+                left.update(|size| *size += delta);
+                right.update(|size| *size -= delta);
+            }
+        });
+    }
 }
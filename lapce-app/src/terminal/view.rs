@@ -1,26 +1,33 @@
-use std::{sync::Arc, time::SystemTime};
+use std::{collections::HashMap, sync::Arc, time::SystemTime};
 
 use alacritty_terminal::{
-    grid::Dimensions,
-    index::Side,
+    grid::{Dimensions, Scroll},
+    index::{Point as TermPoint, Side},
     selection::{Selection, SelectionType},
-    term::{cell::Flags, test::TermSize, RenderableContent},
+    term::{cell::Flags, test::TermSize, RenderableContent, TermMode},
 };
 use floem::{
     context::{EventCx, PaintCx},
     event::{Event, EventPropagation},
+    ext_event::create_ext_action,
     peniko::{
-        kurbo::{Point, Rect, Size},
+        kurbo::{BezPath, Point, Rect, Size},
         Color,
     },
     pointer::PointerInputEvent,
-    reactive::{create_effect, ReadSignal, RwSignal, SignalGet, SignalWith},
-    text::{Attrs, AttrsList, FamilyOwned, TextLayout, Weight},
+    reactive::{
+        create_effect, create_rw_signal, ReadSignal, RwSignal, Scope, SignalGet,
+        SignalUpdate, SignalWith,
+    },
+    text::{Attrs, AttrsList, FamilyOwned, Style as FontStyle, TextLayout, Weight},
     views::editor::{core::register::Clipboard, text::SystemClipboard},
     Renderer, View, ViewId,
 };
 use lapce_core::mode::Mode;
-use lapce_rpc::{proxy::ProxyRpcHandler, terminal::TermId};
+use lapce_rpc::{
+    proxy::{ProxyResponse, ProxyRpcHandler},
+    terminal::TermId,
+};
 use lsp_types::Position;
 use parking_lot::RwLock;
 use regex::Regex;
@@ -28,12 +35,17 @@
 
 use super::{panel::TerminalPanelData, raw::RawTerminal};
 use crate::{
+    clipboard::PrimaryClipboard,
     command::InternalCommand,
     config::{color::LapceColor, LapceConfig},
     debug::RunDebugProcess,
-    editor::location::{EditorLocation, EditorPosition},
+    editor::{
+        link_modifier,
+        location::{EditorLocation, EditorPosition},
+    },
     listener::Listener,
     panel::kind::PanelKind,
+    perf::{self, PerfSubtree},
     window_tab::Focus,
     workspace::LapceWorkspace,
 };
@@ -45,16 +57,52 @@ enum TerminalViewState {
     Config,
     Focus(bool),
     Raw(Arc<RwLock<RawTerminal>>),
+    /// The link modifier was pressed or released, or a commit was resolved.
+    HoveredCommit,
+}
+
+/// How an underline or a strikethrough is drawn.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineStyle {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
 }
 
 struct TerminalLineContent<'a> {
     y: f64,
     bg: Vec<(usize, usize, Color)>,
-    underline: Vec<(usize, usize, Color, f64)>,
+    /// The underlines and strikethroughs as start and end column, color,
+    /// style and the y they are drawn at.
+    decorations: Vec<(usize, usize, Color, LineStyle, f64)>,
     chars: Vec<(char, Attrs<'a>, f64, f64)>,
     cursor: Option<(char, f64)>,
 }
 
+impl TerminalLineContent<'_> {
+    /// Add a decoration to the cell at `col`, extending the one of the
+    /// previous cell if it looks the same.
+    fn push_decoration(
+        &mut self,
+        col: usize,
+        color: Color,
+        style: LineStyle,
+        y: f64,
+    ) {
+        let previous = self
+            .decorations
+            .iter_mut()
+            .find(|d| d.1 == col && d.2 == color && d.3 == style && d.4 == y);
+        if let Some(previous) = previous {
+            previous.1 += 1;
+        } else {
+            self.decorations.push((col, col + 1, color, style, y));
+        }
+    }
+}
+
 pub struct TerminalView {
     id: ViewId,
     term_id: TermId,
@@ -69,6 +117,16 @@ pub struct TerminalView {
     internal_command: Listener<InternalCommand>,
     workspace: Arc<LapceWorkspace>,
     hyper_regs: Vec<Regex>,
+    commit_reg: Regex,
+    /// Whether the words that look like commit hashes are commits, or `None`
+    /// while that is being checked.
+    commits: RwSignal<HashMap<String, Option<bool>>>,
+    link_modifier_held: RwSignal<bool>,
+    pointer_pos: Option<Point>,
+    /// The first and last cells of the commit hash under the pointer while
+    /// the modifier for following links is held, which is underlined.
+    hovered_commit: Option<(TermPoint, TermPoint)>,
+    scope: Scope,
     previous_mouse_action: MouseAction,
     current_mouse_action: MouseAction,
 }
@@ -125,6 +183,16 @@ pub fn terminal_view(
 
     // for rust
     let reg = regex::Regex::new("[\\w\\\\/-]+\\.(rs)?(toml)?:\\d+(:\\d+)?").unwrap();
+    // abbreviated or full git commit hashes
+    let commit_reg = regex::Regex::new("^[0-9a-f]{7,40}$").unwrap();
+    let commits = create_rw_signal(HashMap::new());
+    let link_modifier_held =
+        terminal_panel_data.common.window_common.link_modifier_held;
+    create_effect(move |_| {
+        commits.track();
+        link_modifier_held.track();
+        id.update_state(TerminalViewState::HoveredCommit);
+    });
 
     TerminalView {
         id,
@@ -140,6 +208,12 @@ pub fn terminal_view(
         internal_command,
         workspace,
         hyper_regs: vec![reg],
+        commit_reg,
+        commits,
+        link_modifier_held,
+        pointer_pos: None,
+        hovered_commit: None,
+        scope: Scope::current(),
         previous_mouse_action: Default::default(),
         current_mouse_action: Default::default(),
     }
@@ -207,10 +281,169 @@ fn click(&self, pos: Point) -> Option<()> {
                 });
                 return Some(());
             }
+            let hash = content.trim();
+            if self.is_commit_hash(hash) {
+                match self.commits.with_untracked(|c| c.get(hash).copied()) {
+                    Some(Some(true)) => {
+                        self.internal_command.send(InternalCommand::OpenCommit {
+                            rev: hash.to_string(),
+                        });
+                    }
+                    // Not a commit, so it's an ordinary click
+                    Some(Some(false)) => return None,
+                    Some(None) => {}
+                    None => self.resolve_commit(hash.to_string(), true),
+                }
+                return Some(());
+            }
         }
         None
     }
 
+    /// Whether `word` looks like an abbreviated or full commit hash.
+    fn is_commit_hash(&self, word: &str) -> bool {
+        // A hash has a letter almost always, and numbers such as line counts
+        // or timestamps don't.
+        self.commit_reg.is_match(word)
+            && word.contains(|c: char| c.is_ascii_alphabetic())
+    }
+
+    /// The word at `pos` if it looks like a commit hash, with its first and
+    /// last cells.
+    fn commit_hash_at(&self, pos: Point) -> Option<(String, TermPoint, TermPoint)> {
+        let position = self.get_terminal_point(pos);
+        let raw = self.raw.read();
+        let start = raw.term.semantic_search_left(position);
+        let end = raw.term.semantic_search_right(position);
+        let content = raw.term.bounds_to_string(start, end);
+        let hash = content.trim();
+        self.is_commit_hash(hash)
+            .then(|| (hash.to_string(), start, end))
+    }
+
+    /// Ask the proxy whether `rev` is a commit, opening it if it is and
+    /// `open` is set. Nothing is shown for a word that isn't one.
+    fn resolve_commit(&self, rev: String, open: bool) {
+        self.commits.update(|commits| {
+            commits.insert(rev.clone(), None);
+        });
+        let commits = self.commits;
+        let internal_command = self.internal_command;
+        let request_rev = rev.clone();
+        let send = create_ext_action(self.scope, move |result| {
+            let is_commit = matches!(
+                result,
+                Ok(ProxyResponse::GitResolveCommitResponse { id: Some(_) })
+            );
+            if is_commit && open {
+                internal_command
+                    .send(InternalCommand::OpenCommit { rev: rev.clone() });
+            }
+            commits.update(|commits| {
+                commits.insert(rev, Some(is_commit));
+            });
+        });
+        self.proxy.git_resolve_commit(request_rev, move |result| {
+            send(result);
+        });
+    }
+
+    /// Underline the commit hash under the pointer while the modifier for
+    /// following links is held, once it's known to be a commit.
+    fn update_hovered_commit(&mut self) {
+        let held = self.link_modifier_held.get_untracked();
+        let hovered = self
+            .pointer_pos
+            .filter(|_| held)
+            .and_then(|pos| self.commit_hash_at(pos))
+            .and_then(|(rev, start, end)| {
+                match self.commits.with_untracked(|c| c.get(&rev).copied()) {
+                    Some(Some(true)) => Some((start, end)),
+                    Some(_) => None,
+                    None => {
+                        self.resolve_commit(rev, false);
+                        None
+                    }
+                }
+            });
+        if hovered != self.hovered_commit {
+            self.hovered_commit = hovered;
+            self.id.request_paint();
+        }
+    }
+
+    /// Put the selected text in the primary selection, so it can be pasted
+    /// with a middle-click.
+    fn update_primary_selection(&self) {
+        let content = self.raw.read().term.selection_to_string();
+        if let Some(content) = content.filter(|content| !content.is_empty()) {
+            PrimaryClipboard::new().put_string(content);
+        }
+    }
+
+    fn paste_primary_selection(&self) {
+        if self.mode.get_untracked() != Mode::Terminal {
+            return;
+        }
+        let raw = self.raw.clone();
+        let proxy = self.proxy.clone();
+        let term_id = self.term_id;
+        PrimaryClipboard::get_string_async(move |content| {
+            let mut raw = raw.write();
+            let content = if raw.term.mode().contains(TermMode::BRACKETED_PASTE) {
+                format!("\x1b[200~{}\x1b[201~", content.replace('\x1b', ""))
+            } else {
+                content
+            };
+            raw.term.scroll_display(Scroll::Bottom);
+            drop(raw);
+            proxy.terminal_write(term_id, content);
+        });
+    }
+
+    /// Report a middle-click to the program running in the terminal, when
+    /// it asked for mouse clicks, instead of pasting the primary selection.
+    ///
+    /// Returns `false` if the program didn't ask for them.
+    fn report_middle_click(&self, pos: Point, pressed: bool) -> bool {
+        let raw = self.raw.read();
+        let mode = *raw.term.mode();
+        if !mode.intersects(TermMode::MOUSE_MODE) {
+            return false;
+        }
+        let display_offset = raw.term.grid().display_offset() as i32;
+        drop(raw);
+        let point = self.get_terminal_point(pos);
+        let line = (point.line.0 + display_offset).max(0) as usize + 1;
+        let column = point.column.0 + 1;
+        let report = if mode.contains(TermMode::SGR_MOUSE) {
+            let action = if pressed { 'M' } else { 'm' };
+            format!("\x1b[<1;{column};{line}{action}")
+        } else {
+            // The legacy encoding has a character for each coordinate, an
+            // ASCII one unless UTF-8 was asked for, and a release doesn't
+            // tell the button.
+            let limit = if mode.contains(TermMode::UTF8_MOUSE) {
+                2015
+            } else {
+                95
+            };
+            if column > limit || line > limit {
+                return true;
+            }
+            let button = if pressed { 1 } else { 3 };
+            let mut report = String::from("\x1b[M");
+            report.extend(
+                [button, column, line]
+                    .into_iter()
+                    .filter_map(|value| char::from_u32(32 + value as u32)),
+            );
+            report
+        };
+        self.proxy.terminal_write(self.term_id, report);
+        true
+    }
+
     fn update_mouse_action_by_down(&mut self, mouse: &PointerInputEvent) {
         let mut next_action = MouseAction::None;
         match self.current_mouse_action {
@@ -344,7 +577,7 @@ fn paint_content(
         let mut line_content = TerminalLineContent {
             y: 0.0,
             bg: Vec::new(),
-            underline: Vec::new(),
+            decorations: Vec::new(),
             chars: Vec::new(),
             cursor: None,
         };
@@ -367,7 +600,7 @@ fn paint_content(
                 );
                 line_content.y = y;
                 line_content.bg.clear();
-                line_content.underline.clear();
+                line_content.decorations.clear();
                 line_content.chars.clear();
                 line_content.cursor = None;
             }
@@ -403,18 +636,58 @@ fn paint_content(
                 line_content.cursor = Some((cell.c, x));
             }
 
+            let underline = if cell.flags.contains(Flags::DOUBLE_UNDERLINE) {
+                Some(LineStyle::Double)
+            } else if cell.flags.contains(Flags::UNDERCURL) {
+                Some(LineStyle::Curly)
+            } else if cell.flags.contains(Flags::DOTTED_UNDERLINE) {
+                Some(LineStyle::Dotted)
+            } else if cell.flags.contains(Flags::DASHED_UNDERLINE) {
+                Some(LineStyle::Dashed)
+            } else if cell.flags.contains(Flags::UNDERLINE) {
+                Some(LineStyle::Single)
+            } else {
+                None
+            };
+            if let Some(style) = underline {
+                // SGR 58 sets a color for the underline only
+                let color = cell
+                    .underline_color()
+                    .map(|color| config.terminal_get_color(&color, content.colors))
+                    .unwrap_or(fg);
+                line_content.push_decoration(
+                    point.column.0,
+                    color,
+                    style,
+                    char_y + char_size.height,
+                );
+            }
+            if cell.flags.contains(Flags::STRIKEOUT) {
+                line_content.push_decoration(
+                    point.column.0,
+                    fg,
+                    LineStyle::Single,
+                    char_y + char_size.height / 2.0,
+                );
+            }
+
             let bold = cell.flags.contains(Flags::BOLD)
                 || cell.flags.contains(Flags::DIM_BOLD);
+            let italic = cell.flags.contains(Flags::ITALIC);
 
             if &point == cursor_point && self.is_focused {
                 fg = term_bg;
             }
 
-            if cell.c != ' ' && cell.c != '\t' {
+            if cell.c != ' ' && cell.c != '\t' && !cell.flags.contains(Flags::HIDDEN)
+            {
                 let mut attrs = attrs.color(fg);
                 if bold {
                     attrs = attrs.weight(Weight::BOLD);
                 }
+                if italic {
+                    attrs = attrs.style(FontStyle::Italic);
+                }
                 line_content.chars.push((cell.c, attrs, x, char_y));
             }
         }
@@ -439,12 +712,15 @@ fn paint_line_content(
             cx.fill(&rect, bg, 0.0);
         }
 
-        for (start, end, fg, y) in &line_content.underline {
-            let rect =
-                Size::new(char_width * (end.saturating_sub(*start) as f64), 1.0)
-                    .to_rect()
-                    .with_origin(Point::new(*start as f64 * char_width, y - 1.0));
-            cx.fill(&rect, fg, 0.0);
+        for (start, end, color, style, y) in &line_content.decorations {
+            paint_decoration(
+                cx,
+                *start as f64 * char_width,
+                *end as f64 * char_width,
+                *y,
+                *color,
+                *style,
+            );
         }
 
         if let Some((c, x)) = line_content.cursor {
@@ -479,6 +755,55 @@ fn paint_line_content(
     }
 }
 
+/// Draw an underline or strikethrough between `x0` and `x1`, with its bottom
+/// at `y`.
+fn paint_decoration(
+    cx: &mut PaintCx,
+    x0: f64,
+    x1: f64,
+    y: f64,
+    color: Color,
+    style: LineStyle,
+) {
+    match style {
+        LineStyle::Single => {
+            cx.fill(&Rect::new(x0, y - 1.0, x1, y), color, 0.0);
+        }
+        LineStyle::Double => {
+            cx.fill(&Rect::new(x0, y - 3.0, x1, y - 2.0), color, 0.0);
+            cx.fill(&Rect::new(x0, y - 1.0, x1, y), color, 0.0);
+        }
+        LineStyle::Dotted | LineStyle::Dashed => {
+            let (dash, gap) = if style == LineStyle::Dotted {
+                (1.0, 1.0)
+            } else {
+                (3.0, 2.0)
+            };
+            let mut x = x0;
+            while x < x1 {
+                cx.fill(&Rect::new(x, y - 1.0, (x + dash).min(x1), y), color, 0.0);
+                x += dash + gap;
+            }
+        }
+        LineStyle::Curly => {
+            let amplitude = 1.5;
+            let mid = y - amplitude - 0.5;
+            let mut path = BezPath::new();
+            path.move_to((x0, mid));
+            let mut x = x0;
+            let mut up = true;
+            while x < x1 {
+                let next = (x + 2.0).min(x1);
+                let peak = if up { mid - amplitude } else { mid + amplitude };
+                path.quad_to(((x + next) / 2.0, peak), (next, mid));
+                x = next;
+                up = !up;
+            }
+            cx.stroke(&path, color, 1.0);
+        }
+    }
+}
+
 impl Drop for TerminalView {
     fn drop(&mut self) {
         self.proxy.terminal_close(self.term_id);
@@ -497,15 +822,25 @@ fn event_before_children(
     ) -> EventPropagation {
         match event {
             Event::PointerDown(e) => {
+                if e.button.is_auxiliary() {
+                    if !self.report_middle_click(e.pos, true) {
+                        self.paste_primary_selection();
+                    }
+                    return EventPropagation::Stop;
+                }
                 self.update_mouse_action_by_down(e);
             }
             Event::PointerUp(e) => {
+                if e.button.is_auxiliary() {
+                    self.report_middle_click(e.pos, false);
+                    return EventPropagation::Stop;
+                }
                 self.update_mouse_action_by_up(e);
                 let mut clear_selection = false;
                 match self.current_mouse_action {
                     MouseAction::LeftOnce { pos, .. } => {
                         clear_selection = true;
-                        if e.modifiers.control() && self.click(pos).is_some() {
+                        if link_modifier(e.modifiers) && self.click(pos).is_some() {
                             return EventPropagation::Stop;
                         }
                     }
@@ -519,6 +854,7 @@ fn event_before_children(
                             .update(self.get_terminal_point(end_pos), Side::Right);
                         selection.include_all();
                         self.raw.write().term.selection = Some(selection);
+                        self.update_primary_selection();
                         _cx.app_state_mut().request_paint(self.id);
                     }
                     MouseAction::LeftDouble { pos } => {
@@ -535,6 +871,8 @@ fn event_before_children(
                         selection.update(end_point, Side::Right);
                         selection.include_all();
                         raw.term.selection = Some(selection);
+                        drop(raw);
+                        self.update_primary_selection();
                         _cx.app_state_mut().request_paint(self.id);
                     }
                     MouseAction::RightOnce { pos } => {
@@ -568,6 +906,18 @@ fn event_before_children(
                     _cx.app_state_mut().request_paint(self.id);
                 }
             }
+            Event::PointerMove(e) => {
+                self.pointer_pos = Some(e.pos);
+                let held = link_modifier(e.modifiers);
+                if self.link_modifier_held.get_untracked() != held {
+                    self.link_modifier_held.set(held);
+                }
+                self.update_hovered_commit();
+            }
+            Event::PointerLeave => {
+                self.pointer_pos = None;
+                self.update_hovered_commit();
+            }
             _ => {}
         }
         EventPropagation::Continue
@@ -587,6 +937,10 @@ fn update(
                 TerminalViewState::Raw(raw) => {
                     self.raw = raw;
                 }
+                TerminalViewState::HoveredCommit => {
+                    self.update_hovered_commit();
+                    return;
+                }
             }
             cx.app_state_mut().request_paint(self.id);
         }
@@ -603,6 +957,7 @@ fn compute_layout(
         &mut self,
         _cx: &mut floem::context::ComputeLayoutCx,
     ) -> Option<Rect> {
+        perf::record_layout(PerfSubtree::Terminal);
         let layout = self.id.get_layout().unwrap_or_default();
         let size = layout.size;
         let size = Size::new(size.width as f64, size.height as f64);
@@ -613,7 +968,7 @@ fn compute_layout(
             self.size = size;
             let (width, height) = self.terminal_size();
             let term_size = TermSize::new(width, height);
-            self.raw.write().term.resize(term_size);
+            self.raw.write().resize(term_size);
             self.proxy.terminal_resize(self.term_id, width, height);
         }
 
@@ -621,6 +976,7 @@ fn compute_layout(
     }
 
     fn paint(&mut self, cx: &mut floem::context::PaintCx) {
+        let _timer = perf::time_paint(PerfSubtree::Terminal);
         let config = self.config.get_untracked();
         let mode = self.mode.get_untracked();
         let line_height = config.terminal_line_height() as f64;
@@ -700,7 +1056,21 @@ fn paint(&mut self, cx: &mut floem::context::PaintCx) {
             );
         }
 
+        let display_offset = content.display_offset;
         self.paint_content(cx, content, line_height, char_size, &config);
+        if let Some((start, end)) = self.hovered_commit {
+            if start.line == end.line {
+                let y =
+                    (start.line.0 + display_offset as i32 + 1) as f64 * line_height;
+                let rect = Rect::new(
+                    start.column.0 as f64 * char_width,
+                    y - 2.0,
+                    (end.column.0 + 1) as f64 * char_width,
+                    y - 1.0,
+                );
+                cx.fill(&rect, config.color(LapceColor::EDITOR_LINK), 0.0);
+            }
+        }
         // if data.find.visual {
         //     if let Some(search_string) = data.find.search_string.as_ref() {
         //         if let Ok(dfas) = RegexSearch::new(&regex::escape(search_string)) {
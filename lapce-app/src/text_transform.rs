@@ -0,0 +1,375 @@
+//! Transformations of lines and of the case of text, run on the selections of
+//! an editor or on the whole document when nothing is selected, and the
+//! re-indentation of pasted text.
+
+use std::cmp::Ordering;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineTransform {
+    SortAscending,
+    SortDescending,
+    /// Sort with the numbers in the lines compared by their values.
+    SortNatural,
+    SortCaseInsensitive,
+    /// Delete the lines which were already seen, keeping the first of them.
+    Unique,
+    /// Join the lines into one, separated by single spaces.
+    Join,
+}
+
+impl LineTransform {
+    pub fn apply(self, lines: &[&str]) -> Vec<String> {
+        let mut lines = lines.to_vec();
+        match self {
+            LineTransform::SortAscending => lines.sort(),
+            LineTransform::SortDescending => {
+                lines.sort_by(|a, b| b.cmp(a));
+            }
+            LineTransform::SortNatural => {
+                lines.sort_by(|a, b| natural_cmp(a, b));
+            }
+            LineTransform::SortCaseInsensitive => {
+                lines.sort_by(|a, b| {
+                    a.to_lowercase().cmp(&b.to_lowercase()).then(a.cmp(b))
+                });
+            }
+            LineTransform::Unique => {
+                let mut seen = std::collections::HashSet::new();
+                lines.retain(|line| seen.insert(*line));
+            }
+            LineTransform::Join => {
+                let mut joined =
+                    lines.first().map_or("", |line| line.trim_end()).to_string();
+                for line in lines.iter().skip(1) {
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    if !joined.trim_start().is_empty() {
+                        joined.push(' ');
+                    }
+                    joined.push_str(line);
+                }
+                return vec![joined];
+            }
+        }
+        lines.into_iter().map(str::to_string).collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaseTransform {
+    Upper,
+    Lower,
+    Title,
+    /// Turn the identifiers into `snake_case`.
+    Snake,
+    /// Turn the identifiers into `camelCase`.
+    Camel,
+}
+
+impl CaseTransform {
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            CaseTransform::Upper => text.to_uppercase(),
+            CaseTransform::Lower => text.to_lowercase(),
+            CaseTransform::Title => title_case(text),
+            CaseTransform::Snake | CaseTransform::Camel => {
+                map_identifiers(text, |ident| {
+                    let words = ident_words(ident);
+                    if self == CaseTransform::Snake {
+                        words
+                            .iter()
+                            .map(|word| word.to_lowercase())
+                            .collect::<Vec<_>>()
+                            .join("_")
+                    } else {
+                        words
+                            .iter()
+                            .enumerate()
+                            .map(|(i, word)| {
+                                if i == 0 {
+                                    word.to_lowercase()
+                                } else {
+                                    capitalize(word)
+                                }
+                            })
+                            .collect()
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// Re-indent the lines of `text` pasted after `prefix` on a line, so that the
+/// least indented of them start at the column `indent` and the others keep
+/// their indentation relative to those. The first line only counts when it is
+/// pasted at the start of the line, as it may have been copied from the
+/// middle of one.
+pub fn reindent(
+    text: &str,
+    prefix: &str,
+    indent: usize,
+    indent_unit: &str,
+    tab_width: usize,
+) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    if lines.len() < 2 {
+        return text.to_string();
+    }
+    let first_at_start = prefix.trim().is_empty();
+    let is_blank = |line: &str| line.trim().is_empty();
+    let base = lines
+        .iter()
+        .enumerate()
+        .filter(|(i, line)| {
+            !is_blank(line)
+                && (*i > 0 || first_at_start && line.starts_with([' ', '\t']))
+        })
+        .map(|(_, line)| indent_width(line, tab_width))
+        .min();
+    let Some(base) = base else {
+        return text.to_string();
+    };
+
+    let mut result = String::with_capacity(text.len());
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            result.push('\n');
+        }
+        let (line, cr) = line.strip_suffix('\r').map_or((*line, ""), |l| (l, "\r"));
+        let content = line.trim_start_matches([' ', '\t']);
+        if i == 0 && !first_at_start {
+            result.push_str(line);
+        } else if !content.is_empty() {
+            let width = indent + indent_width(line, tab_width).saturating_sub(base);
+            let width = if i == 0 {
+                width.saturating_sub(indent_width(prefix, tab_width))
+            } else {
+                width
+            };
+            result.push_str(&indentation(width, indent_unit, tab_width));
+            result.push_str(content);
+        }
+        result.push_str(cr);
+    }
+    result
+}
+
+/// The width of the indentation of `line`, with tabs going to the next
+/// multiple of `tab_width`.
+pub fn indent_width(line: &str, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => width += 1,
+            '\t' => width += tab_width - width % tab_width,
+            _ => break,
+        }
+    }
+    width
+}
+
+/// Whitespace as wide as `width`, made of tabs when `indent_unit` is a tab.
+fn indentation(width: usize, indent_unit: &str, tab_width: usize) -> String {
+    if indent_unit.starts_with('\t') {
+        let tab_width = tab_width.max(1);
+        "\t".repeat(width / tab_width) + &" ".repeat(width % tab_width)
+    } else {
+        " ".repeat(width)
+    }
+}
+
+/// Compare `a` and `b` with the runs of digits in them compared as numbers, so
+/// that `item2` comes before `item10`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                let x = x.trim_start_matches('0');
+                let y = y.trim_start_matches('0');
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        number.push(c);
+    }
+    number
+}
+
+/// Uppercase the first letter of every word and lowercase the others.
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_word = false;
+    for c in text.chars() {
+        if in_word {
+            result.extend(c.to_lowercase());
+        } else {
+            result.extend(c.to_uppercase());
+        }
+        in_word = c.is_alphanumeric() || c == '\'';
+    }
+    result
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Replace every identifier of `text` with what `f` gives for it, keeping the
+/// underscores it starts and ends with.
+fn map_identifiers(text: &str, f: impl Fn(&str) -> String) -> String {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let end = rest.find(|c| !is_ident(c)).unwrap_or(rest.len());
+        let (ident, after) = rest.split_at(end);
+        let trimmed = ident.trim_matches('_');
+        if trimmed.is_empty() {
+            result.push_str(ident);
+        } else {
+            let leading = ident.len() - ident.trim_start_matches('_').len();
+            result.push_str(&ident[..leading]);
+            result.push_str(&f(trimmed));
+            result.push_str(&ident[leading + trimmed.len()..]);
+        }
+
+        let end = after.find(is_ident).unwrap_or(after.len());
+        result.push_str(&after[..end]);
+        rest = &after[end..];
+    }
+    result
+}
+
+/// The words of an identifier, split at the underscores and where the case
+/// changes, as in `fooBar` and `HTTPServer`.
+fn ident_words(ident: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in ident.split('_').filter(|part| !part.is_empty()) {
+        let chars: Vec<char> = part.chars().collect();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let boundary = i > 0
+                && c.is_uppercase()
+                && (!chars[i - 1].is_uppercase()
+                    || chars.get(i + 1).is_some_and(|next| next.is_lowercase()));
+            if boundary && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.push(c);
+        }
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reindent, CaseTransform, LineTransform};
+
+    #[test]
+    fn test_sort_lines() {
+        let lines = ["b10", "B2", "a", "b2"];
+        assert_eq!(
+            LineTransform::SortAscending.apply(&lines),
+            vec!["B2", "a", "b10", "b2"]
+        );
+        assert_eq!(
+            LineTransform::SortDescending.apply(&lines),
+            vec!["b2", "b10", "a", "B2"]
+        );
+        assert_eq!(
+            LineTransform::SortNatural.apply(&lines),
+            vec!["B2", "a", "b2", "b10"]
+        );
+        assert_eq!(
+            LineTransform::SortCaseInsensitive.apply(&lines),
+            vec!["a", "b10", "B2", "b2"]
+        );
+        assert_eq!(
+            LineTransform::SortNatural.apply(&["x010", "x9", "x10"]),
+            vec!["x9", "x010", "x10"]
+        );
+    }
+
+    #[test]
+    fn test_unique_and_join_lines() {
+        assert_eq!(
+            LineTransform::Unique.apply(&["a", "b", "a", "", "b", ""]),
+            vec!["a", "b", ""]
+        );
+        assert_eq!(
+            LineTransform::Join.apply(&["    let a =  ", "", "      1 +", "2;"]),
+            vec!["    let a = 1 + 2;"]
+        );
+    }
+
+    #[test]
+    fn test_case() {
+        let text = "fooBar HTTPServer _private_name x1 don't";
+        assert_eq!(
+            CaseTransform::Snake.apply(text),
+            "foo_bar http_server _private_name x1 don't"
+        );
+        assert_eq!(
+            CaseTransform::Camel.apply(text),
+            "fooBar httpServer _privateName x1 don't"
+        );
+        assert_eq!(
+            CaseTransform::Title.apply("hello wORLD, it's 2nd"),
+            "Hello World, It's 2nd"
+        );
+        assert_eq!(CaseTransform::Upper.apply("straße"), "STRASSE");
+        assert_eq!(CaseTransform::Lower.apply("ABC"), "abc");
+    }
+
+    #[test]
+    fn test_reindent() {
+        // Copied from the middle of a line nested deeper than the destination.
+        let text = "if a {\n            b();\n\n        }";
+        assert_eq!(
+            reindent(text, "    let x = ", 4, "    ", 4),
+            "if a {\n        b();\n\n    }"
+        );
+        // Whole lines pasted at the start of a blank line, with tabs.
+        let text = "  foo\r\n    bar\r\n";
+        assert_eq!(reindent(text, "", 8, "\t", 4), "\t\tfoo\r\n\t\t  bar\r\n");
+        assert_eq!(
+            reindent(text, "    ", 8, "    ", 4),
+            "    foo\r\n          bar\r\n"
+        );
+        assert_eq!(reindent("one line", "", 4, "    ", 4), "one line");
+    }
+}
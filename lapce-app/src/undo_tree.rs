@@ -0,0 +1,287 @@
+//! The tree of the states a document went through while being edited. Unlike
+//! the undo history of the buffer, editing after undoing starts a new branch
+//! instead of dropping the undone states, so they can still be restored.
+//!
+//! A state is made for each undo group of the buffer, grouping the edits the
+//! same way it does, so undoing and redoing move between the states without
+//! having to find them from the text.
+
+use std::time::SystemTime;
+
+use lapce_core::editor::EditType;
+use lapce_xi_rope::Rope;
+
+#[derive(Clone)]
+pub struct UndoState {
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub text: Rope,
+    /// When the state was last changed.
+    pub time: SystemTime,
+    /// The state whose text this one was restored to.
+    pub restored_from: Option<usize>,
+}
+
+/// A row of the undo tree as it's shown, the newest state first.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UndoTreeRow {
+    pub state: usize,
+    /// The branches drawn in front of the state.
+    pub graph: String,
+}
+
+#[derive(Clone)]
+pub struct UndoTree {
+    /// The states in the order they were made in.
+    states: Vec<UndoState>,
+    current: usize,
+    /// The type of the last edit, undo or redo, which decides like in the
+    /// buffer whether the next edit starts a new undo group.
+    last_edit_type: EditType,
+}
+
+impl UndoTree {
+    pub fn new(text: Rope) -> Self {
+        Self {
+            states: vec![UndoState {
+                parent: None,
+                children: Vec::new(),
+                text,
+                time: SystemTime::now(),
+                restored_from: None,
+            }],
+            current: 0,
+            last_edit_type: EditType::Other,
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn state(&self, state: usize) -> Option<&UndoState> {
+        self.states.get(state)
+    }
+
+    /// Record `text` as the state after an edit of `edit_type`, which is
+    /// merged into the current state when the buffer adds the edit to the
+    /// current undo group.
+    pub fn record(&mut self, text: Rope, edit_type: EditType, now: SystemTime) {
+        let merge = !edit_type.breaks_undo_group(self.last_edit_type)
+            && self.current != 0
+            && self.states[self.current].children.is_empty();
+        self.last_edit_type = edit_type;
+        if merge {
+            let current = &mut self.states[self.current];
+            current.text = text;
+            current.time = now;
+            return;
+        }
+        self.push(text, None, now);
+    }
+
+    /// Record the edit restoring the text of `state` as a new state.
+    pub fn record_restore(&mut self, state: usize, now: SystemTime) {
+        let Some(text) = self.states.get(state).map(|state| state.text.clone())
+        else {
+            return;
+        };
+        self.last_edit_type = EditType::Other;
+        self.push(text, Some(state), now);
+    }
+
+    fn push(&mut self, text: Rope, restored_from: Option<usize>, now: SystemTime) {
+        let state = self.states.len();
+        self.states[self.current].children.push(state);
+        self.states.push(UndoState {
+            parent: Some(self.current),
+            children: Vec::new(),
+            text,
+            time: now,
+            restored_from,
+        });
+        self.current = state;
+    }
+
+    /// Follow an undo of the buffer, which goes back to the undo group the
+    /// current one was made from.
+    pub fn undo(&mut self) {
+        if let Some(parent) = self.states[self.current].parent {
+            self.current = parent;
+        }
+        self.last_edit_type = EditType::Undo;
+    }
+
+    /// Follow a redo of the buffer, which goes to the last undo group made
+    /// from the current one.
+    pub fn redo(&mut self) {
+        if let Some(child) = self.states[self.current].children.last() {
+            self.current = *child;
+        }
+        self.last_edit_type = EditType::Redo;
+    }
+
+    /// The state the current one stands for in the order the states were
+    /// made in, which is the one it was restored from if it was.
+    fn position(&self) -> usize {
+        self.states[self.current]
+            .restored_from
+            .unwrap_or(self.current)
+    }
+
+    /// The state made before the current one, whichever branch it's in.
+    pub fn older(&self) -> Option<usize> {
+        (0..self.position())
+            .rev()
+            .find(|state| self.states[*state].restored_from.is_none())
+    }
+
+    /// The state made after the current one, whichever branch it's in.
+    pub fn newer(&self) -> Option<usize> {
+        (self.position() + 1..self.states.len())
+            .find(|state| self.states[*state].restored_from.is_none())
+    }
+
+    /// The states with the branches leading to them, newest first. The first
+    /// child of a state continues its branch, and the others start new ones.
+    pub fn rows(&self) -> Vec<UndoTreeRow> {
+        let mut columns = vec![0; self.states.len()];
+        // The state each branch starts from and the last state in it.
+        let mut branches: Vec<(usize, usize)> = vec![(0, 0)];
+        for (i, state) in self.states.iter().enumerate().skip(1) {
+            let parent = state.parent.unwrap_or(0);
+            if self.states[parent].children.first() == Some(&i) {
+                columns[i] = columns[parent];
+                branches[columns[i]].1 = i;
+            } else {
+                columns[i] = branches.len();
+                branches.push((parent, i));
+            }
+        }
+
+        (0..self.states.len())
+            .rev()
+            .map(|i| {
+                let graph = branches
+                    .iter()
+                    .enumerate()
+                    .map(|(column, (start, end))| {
+                        if column == columns[i] {
+                            if i == self.current {
+                                '●'
+                            } else {
+                                '○'
+                            }
+                        } else if *start < i && i < *end {
+                            '│'
+                        } else {
+                            ' '
+                        }
+                    })
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string();
+                UndoTreeRow { state: i, graph }
+            })
+            .collect()
+    }
+}
+
+/// How long ago `time` was, for the rows of the undo tree.
+pub fn time_ago(time: SystemTime, now: SystemTime) -> String {
+    let secs = now.duration_since(time).unwrap_or_default().as_secs();
+    match secs {
+        0..=4 => "just now".to_string(),
+        5..=59 => format!("{secs} seconds ago"),
+        60..=3599 => format!("{} minutes ago", secs / 60),
+        _ => format!("{} hours ago", secs / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use lapce_core::editor::EditType;
+    use lapce_xi_rope::Rope;
+
+    use super::UndoTree;
+
+    fn text(tree: &UndoTree) -> String {
+        tree.state(tree.current()).unwrap().text.to_string()
+    }
+
+    #[test]
+    fn test_record_merge() {
+        let now = SystemTime::now();
+        let mut tree = UndoTree::new(Rope::from(""));
+        tree.record(Rope::from("a"), EditType::InsertChars, now);
+        tree.record(Rope::from("ab"), EditType::InsertChars, now);
+        assert_eq!(tree.current(), 1);
+        assert_eq!(text(&tree), "ab");
+
+        // Deleting after typing starts a new undo group.
+        tree.record(Rope::from("a"), EditType::Delete, now);
+        tree.record(Rope::from(""), EditType::Delete, now);
+        assert_eq!(tree.current(), 2);
+        assert_eq!(text(&tree), "");
+
+        tree.record(Rope::from("c"), EditType::Other, now);
+        tree.record(Rope::from("cd"), EditType::Other, now);
+        assert_eq!(tree.current(), 4);
+
+        // Typing after undoing doesn't merge into the state undone to.
+        tree.undo();
+        tree.record(Rope::from("ce"), EditType::InsertChars, now);
+        assert_eq!(tree.current(), 5);
+    }
+
+    #[test]
+    fn test_branches() {
+        let now = SystemTime::now();
+        let mut tree = UndoTree::new(Rope::from(""));
+        tree.record(Rope::from("a"), EditType::Other, now);
+        tree.record(Rope::from("ab"), EditType::Other, now);
+        tree.undo();
+        assert_eq!(tree.current(), 1);
+        tree.redo();
+        assert_eq!(tree.current(), 2);
+        tree.undo();
+
+        // Editing after undoing keeps the undone state in another branch.
+        tree.record(Rope::from("ac"), EditType::Other, now);
+        assert_eq!(tree.current(), 3);
+        assert_eq!(tree.older(), Some(2));
+        assert_eq!(tree.newer(), None);
+        tree.undo();
+        tree.redo();
+        assert_eq!(tree.current(), 3);
+
+        let rows: Vec<_> = tree
+            .rows()
+            .into_iter()
+            .map(|row| (row.state, row.graph))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (3, " ●".to_string()),
+                (2, "○│".to_string()),
+                (1, "○".to_string()),
+                (0, "○".to_string()),
+            ]
+        );
+
+        // Restoring a state is a new edit, from which the states keep being
+        // stepped through in the order they were made in.
+        tree.record_restore(2, now);
+        assert_eq!(tree.current(), 4);
+        assert_eq!(text(&tree), "ab");
+        assert_eq!(tree.older(), Some(1));
+        assert_eq!(tree.newer(), Some(3));
+        let graphs: Vec<_> = tree.rows().into_iter().map(|row| row.graph).collect();
+        assert_eq!(graphs, vec![" ●", " ○", "○│", "○", "○"]);
+        tree.undo();
+        assert_eq!(tree.current(), 3);
+    }
+}
@@ -0,0 +1,486 @@
+use std::{
+    cell::OnceCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use lapce_core::{directory::Directory, language::LapceLanguage};
+use serde::Deserialize;
+
+/// The file in the snippets directory whose snippets are offered for every
+/// language.
+const GLOBAL_SNIPPETS: &str = "global";
+
+/// A snippet defined by the user in the snippets directory, offered in the
+/// completion list when its prefix is typed.
+///
+/// The snippets of a language are stored in a json file named after it, such
+/// as `rust.json`, in the same format as VS Code's snippet files, so they can
+/// be shared between the two.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UserSnippet {
+    pub name: String,
+    pub prefix: String,
+    pub description: String,
+    /// The snippet to insert, which can contain tabstops, placeholders and
+    /// variables.
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::One(s) => vec![s],
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SnippetDefinition {
+    prefix: OneOrMany,
+    body: OneOrMany,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct UserSnippets {
+    global: Vec<UserSnippet>,
+    languages: HashMap<LapceLanguage, Vec<UserSnippet>>,
+}
+
+impl UserSnippets {
+    /// Load all the snippet files in the snippets directory.
+    pub fn load() -> UserSnippets {
+        let mut snippets = UserSnippets::default();
+        let Some(dir) = Directory::snippets_directory() else {
+            return snippets;
+        };
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return snippets;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect();
+        paths.sort();
+        for path in paths {
+            snippets.load_file(&path);
+        }
+        snippets
+    }
+
+    fn load_file(&mut self, path: &Path) {
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            return;
+        }
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            return;
+        };
+        let target = if name == GLOBAL_SNIPPETS {
+            &mut self.global
+        } else if let Some(language) = LapceLanguage::from_name(name) {
+            self.languages.entry(language).or_default()
+        } else {
+            tracing::error!("no language for snippet file {}", path.display());
+            return;
+        };
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return;
+        };
+        match parse_snippets(&content) {
+            Ok(snippets) => target.extend(snippets),
+            Err(err) => {
+                tracing::error!("invalid snippet file {}: {err}", path.display());
+            }
+        }
+    }
+
+    /// The snippets offered for documents of `language`.
+    pub fn for_language(
+        &self,
+        language: LapceLanguage,
+    ) -> impl Iterator<Item = &UserSnippet> {
+        self.languages
+            .get(&language)
+            .into_iter()
+            .flatten()
+            .chain(self.global.iter())
+    }
+}
+
+/// Parse the content of a snippet file, which can have comments and trailing
+/// commas like VS Code's. A snippet with several prefixes is returned once for
+/// each of them, and a body given as a list of lines is joined with newlines.
+pub fn parse_snippets(content: &str) -> anyhow::Result<Vec<UserSnippet>> {
+    let definitions: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&strip_jsonc(content))?;
+    let mut snippets = Vec::new();
+    for (name, definition) in definitions {
+        let definition: SnippetDefinition = match serde_json::from_value(definition)
+        {
+            Ok(definition) => definition,
+            Err(err) => {
+                tracing::error!("invalid snippet {name}: {err}");
+                continue;
+            }
+        };
+        let body = definition.body.into_vec().join("\n");
+        for prefix in definition.prefix.into_vec() {
+            snippets.push(UserSnippet {
+                name: name.clone(),
+                prefix,
+                description: definition.description.clone(),
+                body: body.clone(),
+            });
+        }
+    }
+    Ok(snippets)
+}
+
+/// Turn JSON with comments into plain JSON, removing the `//` and `/* */`
+/// comments and the commas before a closing bracket or brace.
+fn strip_jsonc(content: &str) -> String {
+    let mut without_comments = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            without_comments.push(c);
+            match c {
+                '\\' => without_comments.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('/', Some('/')) => while chars.next_if(|c| *c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = None;
+                for c in chars.by_ref() {
+                    if last == Some('*') && c == '/' {
+                        break;
+                    }
+                    last = Some(c);
+                }
+                // Keep the tokens around the comment apart.
+                without_comments.push(' ');
+            }
+            _ => {
+                in_string = c == '"';
+                without_comments.push(c);
+            }
+        }
+    }
+
+    let mut json = String::with_capacity(without_comments.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in without_comments.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ','
+            && without_comments[i + 1..]
+                .trim_start()
+                .starts_with(['}', ']'])
+        {
+            continue;
+        }
+        json.push(c);
+    }
+    json
+}
+
+/// The values of the variables a snippet can refer to, at the place it's
+/// inserted.
+#[derive(Clone, Debug, Default)]
+pub struct SnippetVariables {
+    pub path: PathBuf,
+    /// The zero based line of the cursor.
+    pub line: usize,
+    pub current_line: String,
+    pub selected_text: String,
+    /// Reads the clipboard, which is only done when a snippet refers to it.
+    pub read_clipboard: Option<fn() -> Option<String>>,
+    clipboard: OnceCell<String>,
+}
+
+impl SnippetVariables {
+    /// The value of the variable `name`, or `None` if there's no such variable.
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        let now = chrono::Local::now();
+        let to_string = |s: Option<&std::ffi::OsStr>| {
+            s.map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+        Some(match name {
+            "TM_FILENAME" => to_string(self.path.file_name()),
+            "TM_FILENAME_BASE" => to_string(self.path.file_stem()),
+            "TM_DIRECTORY" => to_string(self.path.parent().map(|p| p.as_os_str())),
+            "TM_FILEPATH" => self.path.to_string_lossy().to_string(),
+            "TM_LINE_INDEX" => self.line.to_string(),
+            "TM_LINE_NUMBER" => (self.line + 1).to_string(),
+            "TM_CURRENT_LINE" => self.current_line.clone(),
+            "TM_SELECTED_TEXT" => self.selected_text.clone(),
+            "CLIPBOARD" => self
+                .clipboard
+                .get_or_init(|| {
+                    self.read_clipboard
+                        .and_then(|read| read())
+                        .unwrap_or_default()
+                })
+                .clone(),
+            "CURRENT_YEAR" => now.format("%Y").to_string(),
+            "CURRENT_YEAR_SHORT" => now.format("%y").to_string(),
+            "CURRENT_MONTH" => now.format("%m").to_string(),
+            "CURRENT_MONTH_NAME" => now.format("%B").to_string(),
+            "CURRENT_MONTH_NAME_SHORT" => now.format("%b").to_string(),
+            "CURRENT_DATE" => now.format("%d").to_string(),
+            "CURRENT_DAY_NAME" => now.format("%A").to_string(),
+            "CURRENT_DAY_NAME_SHORT" => now.format("%a").to_string(),
+            "CURRENT_HOUR" => now.format("%H").to_string(),
+            "CURRENT_MINUTE" => now.format("%M").to_string(),
+            "CURRENT_SECOND" => now.format("%S").to_string(),
+            "CURRENT_SECONDS_UNIX" => now.timestamp().to_string(),
+            _ => return None,
+        })
+    }
+}
+
+/// Replace the variables in `body`, written as `$NAME`, `${NAME}` or
+/// `${NAME:default}`, with the values given by `resolve`, leaving the tabstops
+/// and placeholders in place.
+///
+/// The default is used when the value is empty, and an unknown variable is
+/// replaced with its default or else its name.
+pub fn expand_variables(
+    body: &str,
+    resolve: &impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut result = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(i) = rest.find(['$', '\\']) {
+        result.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        if rest.starts_with('\\') {
+            // Keep escapes as they are, so an escaped `$` isn't a variable.
+            let len = rest[1..].chars().next().map_or(0, |c| c.len_utf8());
+            result.push_str(&rest[..1 + len]);
+            rest = &rest[1 + len..];
+            continue;
+        }
+
+        let (name, default, len) = if let Some(braced) = rest.strip_prefix("${") {
+            let name_len = variable_name_len(braced);
+            let after = &braced[name_len..];
+            if name_len == 0 {
+                (None, None, 0)
+            } else if after.starts_with('}') {
+                (Some(&braced[..name_len]), None, 2 + name_len + 1)
+            } else if let Some(default) = after.strip_prefix(':') {
+                match closing_brace(default) {
+                    Some(end) => (
+                        Some(&braced[..name_len]),
+                        Some(&default[..end]),
+                        2 + name_len + 1 + end + 1,
+                    ),
+                    None => (None, None, 0),
+                }
+            } else {
+                (None, None, 0)
+            }
+        } else {
+            let name_len = variable_name_len(&rest[1..]);
+            (
+                (name_len > 0).then(|| &rest[1..1 + name_len]),
+                None,
+                1 + name_len,
+            )
+        };
+
+        let Some(name) = name else {
+            result.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        match (resolve(name), default) {
+            (Some(value), _) if !value.is_empty() => {
+                result.push_str(&escape(&value))
+            }
+            (_, Some(default)) => {
+                result.push_str(&expand_variables(default, resolve))
+            }
+            (Some(_), None) => {}
+            (None, None) => result.push_str(name),
+        }
+        rest = &rest[len..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The length of the variable name at the start of `s`. Variable names start
+/// with a letter or an underscore, unlike tabstops.
+fn variable_name_len(s: &str) -> usize {
+    if !s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        return 0;
+    }
+    s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(s.len())
+}
+
+/// The position of the `}` closing a default, skipping nested placeholders
+/// and escapes.
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Escape the characters of a variable's value that have a meaning in a
+/// snippet.
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '$' | '}' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{expand_variables, parse_snippets, SnippetVariables};
+
+    #[test]
+    fn test_parse_snippets() {
+        let content = r##"{
+            "Print": {
+                "prefix": ["pr", "print"],
+                "body": ["println!(\"$1\");", "$0"],
+                "description": "Print a line"
+            },
+            "Test": {
+                "prefix": "test",
+                "body": "#[test]\nfn ${1:name}() {}"
+            },
+            "Broken": {
+                "body": "no prefix"
+            }
+        }"##;
+        let mut snippets = parse_snippets(content).unwrap();
+        snippets.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+        assert_eq!(snippets.len(), 3);
+        assert_eq!(snippets[0].prefix, "pr");
+        assert_eq!(snippets[0].body, "println!(\"$1\");\n$0");
+        assert_eq!(snippets[0].description, "Print a line");
+        assert_eq!(snippets[1].prefix, "print");
+        assert_eq!(snippets[1].name, "Print");
+        assert_eq!(snippets[2].prefix, "test");
+        assert_eq!(snippets[2].description, "");
+    }
+
+    #[test]
+    fn test_parse_snippets_with_comments() {
+        let content = r#"{
+            // Print a line
+            "Print": {
+                "prefix": "pr", /* "prefix": "print", */
+                "body": ["println!(\"// $1 /* */\");",],
+            },
+        }"#;
+        let snippets = parse_snippets(content).unwrap();
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].prefix, "pr");
+        assert_eq!(snippets[0].body, "println!(\"// $1 /* */\");");
+
+        assert!(parse_snippets("[]").is_err());
+    }
+
+    #[test]
+    fn test_expand_variables() {
+        let variables = SnippetVariables {
+            path: PathBuf::from("/project/src/main.rs"),
+            line: 4,
+            current_line: "let a = 1;".to_string(),
+            selected_text: String::new(),
+            read_clipboard: Some(|| Some("a$b}".to_string())),
+            ..Default::default()
+        };
+        let resolve = |name: &str| variables.resolve(name);
+
+        assert_eq!(
+            expand_variables("// $TM_FILENAME:${TM_LINE_NUMBER}", &resolve),
+            "// main.rs:5"
+        );
+        assert_eq!(
+            expand_variables("mod ${TM_FILENAME_BASE};$0", &resolve),
+            "mod main;$0"
+        );
+        assert_eq!(
+            expand_variables("${1:${TM_DIRECTORY}}", &resolve),
+            "${1:/project/src}"
+        );
+        // The value is escaped, so it's inserted as is.
+        assert_eq!(expand_variables("$CLIPBOARD", &resolve), "a\\$b\\}");
+
+        // The clipboard is only read for the snippets referring to it.
+        let variables = SnippetVariables {
+            read_clipboard: Some(|| unreachable!()),
+            ..Default::default()
+        };
+        assert_eq!(
+            expand_variables("${CLIPBOARDS:x}", &|name| variables.resolve(name)),
+            "x"
+        );
+        // The default is used for empty values, and can contain placeholders.
+        assert_eq!(
+            expand_variables("${TM_SELECTED_TEXT:${1:value}}", &resolve),
+            "${1:value}"
+        );
+        assert_eq!(expand_variables("[$TM_SELECTED_TEXT]", &resolve), "[]");
+        // Unknown variables become their names.
+        assert_eq!(
+            expand_variables("$UNKNOWN ${NOPE:x}", &resolve),
+            "UNKNOWN x"
+        );
+        // Tabstops, escapes and stray dollars are left alone.
+        assert_eq!(
+            expand_variables("$1 ${2} \\$TM_FILENAME $ ${", &resolve),
+            "$1 ${2} \\$TM_FILENAME $ ${"
+        );
+        assert_eq!(expand_variables("${CURRENT_YEAR}", &resolve).len(), 4,);
+    }
+}
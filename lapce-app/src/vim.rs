@@ -0,0 +1,736 @@
+//! The parts of the vim emulation on top of the modal editing of the editor:
+//! named registers, marks and the ex commands of the `:` command line.
+
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use lapce_core::{mode::VisualMode, register::RegisterData};
+use lsp_types::Position;
+use regex::{Regex, RegexBuilder};
+
+/// What the character typed after a vim command is taken as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VimCharAction {
+    /// The register of the next yank, delete or paste, typed after `"`.
+    SelectRegister,
+    /// The mark to create, typed after `m`.
+    CreateMark,
+    /// The mark to jump to, typed after `'` or, for its exact position, after
+    /// `` ` ``.
+    GoToMark { exact: bool },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mark {
+    pub path: PathBuf,
+    pub position: Position,
+}
+
+/// The registers and the marks, shared by the editors of a window tab.
+#[derive(Clone, Default)]
+pub struct VimState {
+    /// The register selected with `"` for the next yank, delete or paste.
+    pub selected_register: Option<char>,
+    /// The registers `a` to `z`.
+    registers: HashMap<char, RegisterData>,
+    /// The marks `a` to `z` of each file.
+    local_marks: HashMap<PathBuf, HashMap<char, Position>>,
+    /// The marks `A` to `Z`, which jump between files.
+    global_marks: HashMap<char, Mark>,
+}
+
+impl VimState {
+    /// Whether `c` names a register: a letter, the unnamed register `"`, the
+    /// black hole register `_`, or the clipboards `+` and `*`.
+    pub fn is_register(c: char) -> bool {
+        c.is_ascii_alphabetic() || matches!(c, '"' | '_' | '+' | '*')
+    }
+
+    /// Store what was yanked or deleted into the letter register `name`,
+    /// appending to it when the letter is uppercase.
+    pub fn store(&mut self, name: char, data: RegisterData) {
+        if !name.is_ascii_alphabetic() {
+            return;
+        }
+        let key = name.to_ascii_lowercase();
+        match self.registers.get_mut(&key) {
+            Some(register) if name.is_ascii_uppercase() => {
+                if data.mode == VisualMode::Linewise
+                    && !register.content.is_empty()
+                    && !register.content.ends_with('\n')
+                {
+                    register.content.push('\n');
+                }
+                register.content.push_str(&data.content);
+                if data.mode == VisualMode::Linewise {
+                    register.mode = VisualMode::Linewise;
+                }
+            }
+            _ => {
+                self.registers.insert(key, data);
+            }
+        }
+    }
+
+    /// The content of the letter register `name`.
+    pub fn register(&self, name: char) -> Option<&RegisterData> {
+        self.registers.get(&name.to_ascii_lowercase())
+    }
+
+    /// Set the mark `name`, which is local to its file when it's lowercase.
+    pub fn set_mark(&mut self, name: char, mark: Mark) {
+        if name.is_ascii_lowercase() {
+            self.local_marks
+                .entry(mark.path)
+                .or_default()
+                .insert(name, mark.position);
+        } else if name.is_ascii_uppercase() {
+            self.global_marks.insert(name, mark);
+        }
+    }
+
+    /// The mark `name`, looking for a local one in the file at `path`.
+    pub fn mark(&self, name: char, path: Option<&Path>) -> Option<Mark> {
+        if name.is_ascii_lowercase() {
+            let path = path?;
+            let position = *self.local_marks.get(path)?.get(&name)?;
+            Some(Mark {
+                path: path.to_path_buf(),
+                position,
+            })
+        } else {
+            self.global_marks.get(&name).cloned()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineAddress {
+    /// `.`
+    Current,
+    /// `$`
+    Last,
+    /// A line number, starting from 1.
+    Line(usize),
+    /// `'a`
+    Mark(char),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Address {
+    pub base: LineAddress,
+    /// Added with `+n` or `-n` after the address.
+    pub offset: isize,
+}
+
+impl Address {
+    /// The line, from 0, the address refers to. `mark` gives the line of a
+    /// mark in the file.
+    pub fn resolve(
+        &self,
+        current: usize,
+        last: usize,
+        mark: &impl Fn(char) -> Option<usize>,
+    ) -> Result<usize, String> {
+        let line = match self.base {
+            LineAddress::Current => current,
+            LineAddress::Last => last,
+            LineAddress::Line(line) => line.saturating_sub(1),
+            LineAddress::Mark(name) => {
+                mark(name).ok_or_else(|| format!("Mark not set: {name}"))?
+            }
+        };
+        line.checked_add_signed(self.offset)
+            .filter(|line| *line <= last)
+            .ok_or_else(|| "Invalid range".to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineRange {
+    /// `%`
+    Whole,
+    Lines(Address, Address),
+}
+
+impl LineRange {
+    /// The first and the last line, from 0, of the range.
+    pub fn resolve(
+        &self,
+        current: usize,
+        last: usize,
+        mark: &impl Fn(char) -> Option<usize>,
+    ) -> Result<(usize, usize), String> {
+        match self {
+            LineRange::Whole => Ok((0, last)),
+            LineRange::Lines(start, end) => {
+                let start = start.resolve(current, last, mark)?;
+                let end = end.resolve(current, last, mark)?;
+                Ok((start.min(end), start.max(end)))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Substitution {
+    pub pattern: String,
+    /// The replacement, with `&` and `\1` already turned into the captures
+    /// of the regex.
+    pub replacement: String,
+    /// Replace every match in the line rather than the first one.
+    pub global: bool,
+    pub ignore_case: bool,
+}
+
+/// A command run on each line of a range.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LineCommand {
+    Delete,
+    Substitute(Substitution),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExCommand {
+    GoToLine(Address),
+    /// `:[range]s/pattern/replacement/[flags]` or `:[range]d`, on the current
+    /// line when no range is given.
+    Lines {
+        range: Option<LineRange>,
+        command: LineCommand,
+    },
+    /// `:[range]g/pattern/command`, on the whole file when no range is given,
+    /// and `:g!` or `:v` for the lines which don't match.
+    Global {
+        range: Option<LineRange>,
+        pattern: String,
+        invert: bool,
+        command: LineCommand,
+    },
+    Write,
+    WriteAll,
+    Quit,
+    WriteQuit,
+    Edit(String),
+}
+
+/// Parse the ex command typed after `:`.
+pub fn parse_ex_command(input: &str) -> Result<ExCommand, String> {
+    let input = input.trim();
+    let (range, rest) = parse_range(input)?;
+    let rest = rest.trim_start();
+    if rest.is_empty() {
+        return match range {
+            Some(LineRange::Lines(_, end)) => Ok(ExCommand::GoToLine(end)),
+            Some(LineRange::Whole) => Ok(ExCommand::GoToLine(Address {
+                base: LineAddress::Last,
+                offset: 0,
+            })),
+            None => Err("No command".to_string()),
+        };
+    }
+
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    let (name, args) = rest.split_at(name_len);
+    let no_range = || {
+        if range.is_some() {
+            Err(format!("No range allowed: {input}"))
+        } else {
+            Ok(())
+        }
+    };
+    let no_args = || {
+        if args.trim().is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Trailing characters: {}", args.trim()))
+        }
+    };
+    match name {
+        "s" | "substitute" => Ok(ExCommand::Lines {
+            range,
+            command: LineCommand::Substitute(parse_substitution(args)?),
+        }),
+        "d" | "delete" => {
+            no_args()?;
+            Ok(ExCommand::Lines {
+                range,
+                command: LineCommand::Delete,
+            })
+        }
+        "g" | "global" | "v" | "vglobal" => {
+            let (bang, args) = match args.strip_prefix('!') {
+                Some(args) => (true, args),
+                None => (false, args),
+            };
+            let invert = bang || name.starts_with('v');
+            let (pattern, command) = split_delimited(args, 1)?;
+            let command = match command.trim() {
+                "d" | "delete" => LineCommand::Delete,
+                command => {
+                    let args = command
+                        .strip_prefix("substitute")
+                        .or_else(|| command.strip_prefix('s'))
+                        .ok_or_else(|| {
+                            format!("Unsupported command for :g: {command}")
+                        })?;
+                    LineCommand::Substitute(parse_substitution(args)?)
+                }
+            };
+            Ok(ExCommand::Global {
+                range,
+                pattern: pattern[0].clone(),
+                invert,
+                command,
+            })
+        }
+        "w" | "write" => {
+            no_range()?;
+            no_args()?;
+            Ok(ExCommand::Write)
+        }
+        "wa" | "wall" => {
+            no_range()?;
+            no_args()?;
+            Ok(ExCommand::WriteAll)
+        }
+        "q" | "quit" => {
+            no_range()?;
+            no_args()?;
+            Ok(ExCommand::Quit)
+        }
+        "wq" | "x" | "xit" => {
+            no_range()?;
+            no_args()?;
+            Ok(ExCommand::WriteQuit)
+        }
+        "e" | "edit" => {
+            no_range()?;
+            let path = args.trim();
+            if path.is_empty() {
+                return Err("No file name".to_string());
+            }
+            Ok(ExCommand::Edit(path.to_string()))
+        }
+        _ => Err(format!("Not an editor command: {rest}")),
+    }
+}
+
+/// Parse the range in front of an ex command, returning what follows it.
+fn parse_range(input: &str) -> Result<(Option<LineRange>, &str), String> {
+    if let Some(rest) = input.strip_prefix('%') {
+        return Ok((Some(LineRange::Whole), rest));
+    }
+    let Some((start, rest)) = parse_address(input)? else {
+        return Ok((None, input));
+    };
+    if let Some(rest) = rest.strip_prefix(',') {
+        let (end, rest) = parse_address(rest)?
+            .ok_or_else(|| "Missing the end of the range".to_string())?;
+        Ok((Some(LineRange::Lines(start, end)), rest))
+    } else {
+        Ok((Some(LineRange::Lines(start, start)), rest))
+    }
+}
+
+fn parse_address(input: &str) -> Result<Option<(Address, &str)>, String> {
+    let (base, mut rest) = if let Some(rest) = input.strip_prefix('.') {
+        (LineAddress::Current, rest)
+    } else if let Some(rest) = input.strip_prefix('$') {
+        (LineAddress::Last, rest)
+    } else if let Some(rest) = input.strip_prefix('\'') {
+        let mut chars = rest.chars();
+        let name = chars
+            .next()
+            .filter(char::is_ascii_alphabetic)
+            .ok_or_else(|| "Invalid mark".to_string())?;
+        (LineAddress::Mark(name), chars.as_str())
+    } else if input.starts_with(|c: char| c.is_ascii_digit()) {
+        let (line, rest) = split_number(input);
+        (LineAddress::Line(line), rest)
+    } else if input.starts_with(['+', '-']) {
+        (LineAddress::Current, input)
+    } else {
+        return Ok(None);
+    };
+
+    let mut offset = 0;
+    while let Some(sign) = rest.chars().next().filter(|c| matches!(*c, '+' | '-')) {
+        let (number, after) = split_number(&rest[1..]);
+        // A sign without a number counts as one line.
+        let number = if after.len() == rest.len() - 1 {
+            1
+        } else {
+            number
+        };
+        let number = number as isize;
+        offset += if sign == '+' { number } else { -number };
+        rest = after;
+    }
+    Ok(Some((Address { base, offset }, rest)))
+}
+
+fn split_number(input: &str) -> (usize, &str) {
+    let len = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, rest) = input.split_at(len);
+    (number.parse().unwrap_or(0), rest)
+}
+
+/// Parse `/pattern/replacement/flags`, where any punctuation can take the
+/// place of the `/`.
+fn parse_substitution(input: &str) -> Result<Substitution, String> {
+    let (parts, flags) = split_delimited(input, 2)?;
+    let mut global = false;
+    let mut ignore_case = false;
+    for flag in flags.trim().chars() {
+        match flag {
+            'g' => global = true,
+            'i' => ignore_case = true,
+            'I' => ignore_case = false,
+            _ => return Err(format!("Invalid flag: {flag}")),
+        }
+    }
+    Ok(Substitution {
+        pattern: parts[0].clone(),
+        replacement: regex_replacement(&parts[1]),
+        global,
+        ignore_case,
+    })
+}
+
+/// Split the `count` parts following the delimiter `input` starts with, each
+/// ended by it, returning them with what follows. The last delimiter can be
+/// left out, and a delimiter is escaped with a backslash.
+fn split_delimited(
+    input: &str,
+    count: usize,
+) -> Result<(Vec<String>, &str), String> {
+    let mut chars = input.char_indices();
+    let delimiter = match chars.next() {
+        Some((_, c))
+            if !c.is_alphanumeric()
+                && !c.is_whitespace()
+                && !matches!(c, '\\' | '"' | '|') =>
+        {
+            c
+        }
+        _ => return Err("Missing a delimiter".to_string()),
+    };
+
+    let mut parts = vec![String::new()];
+    let mut rest = "";
+    while let Some((i, c)) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => {
+                    parts.last_mut().unwrap().push(next)
+                }
+                Some((_, next)) => {
+                    let part = parts.last_mut().unwrap();
+                    part.push('\\');
+                    part.push(next);
+                }
+                None => parts.last_mut().unwrap().push('\\'),
+            }
+        } else if c == delimiter {
+            if parts.len() == count {
+                rest = &input[i + c.len_utf8()..];
+                break;
+            }
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    if parts[0].is_empty() {
+        return Err("Empty pattern".to_string());
+    }
+    parts.resize(count, String::new());
+    Ok((parts, rest))
+}
+
+/// Turn the replacement of a vim substitution, where `&` and `\0` are the
+/// whole match and `\1` to `\9` are groups, into the one of a [`Regex`].
+fn regex_replacement(replacement: &str) -> String {
+    let mut result = String::new();
+    let mut chars = replacement.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => result.push_str("${0}"),
+            '$' => result.push_str("$$"),
+            '\\' => match chars.next() {
+                Some(digit @ '0'..='9') => {
+                    result.push_str(&format!("${{{digit}}}"));
+                }
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('$') => result.push_str("$$"),
+                Some(c) => result.push(c),
+                None => result.push('\\'),
+            },
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+pub fn compile_pattern(pattern: &str, ignore_case: bool) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+/// The edits running `command` on the lines `start` to `end` of `text` makes,
+/// in order. With `filter`, only the lines matching the regex, or not
+/// matching it when inverted, are changed.
+pub fn line_edits(
+    text: &str,
+    start: usize,
+    end: usize,
+    filter: Option<(&Regex, bool)>,
+    command: &LineCommand,
+) -> Result<Vec<(Range<usize>, String)>, String> {
+    let substitution = match command {
+        LineCommand::Delete => None,
+        LineCommand::Substitute(substitution) => Some((
+            compile_pattern(&substitution.pattern, substitution.ignore_case)?,
+            substitution,
+        )),
+    };
+
+    // The start of each line, where its content ends, before a `\r\n`, and
+    // where the next line starts.
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let content_end = line_start + line.trim_end_matches('\r').len();
+        let next = (line_start + line.len() + 1).min(text.len());
+        lines.push((line_start, content_end, next));
+        line_start += line.len() + 1;
+    }
+    let last = lines.len() - 1;
+
+    let mut edits = Vec::new();
+    // The first line of the lines being deleted in a row.
+    let mut deleting: Option<usize> = None;
+    let flush = |deleting: &mut Option<usize>,
+                 end: usize,
+                 edits: &mut Vec<(Range<usize>, String)>| {
+        if let Some(first) = deleting.take() {
+            let range = if end == last && first > 0 {
+                // Without a line after, the newline before goes instead.
+                lines[first - 1].1..text.len()
+            } else {
+                lines[first].0..lines[end].2
+            };
+            edits.push((range, String::new()));
+        }
+    };
+
+    for line in start..=end.min(last) {
+        let (line_start, content_end, _) = lines[line];
+        let content = &text[line_start..content_end];
+        let selected = filter
+            .map(|(regex, invert)| regex.is_match(content) != invert)
+            .unwrap_or(true);
+        if !selected {
+            flush(&mut deleting, line.saturating_sub(1), &mut edits);
+            continue;
+        }
+        match &substitution {
+            None => {
+                deleting.get_or_insert(line);
+            }
+            Some((regex, substitution)) => {
+                let replaced = if substitution.global {
+                    regex.replace_all(content, substitution.replacement.as_str())
+                } else {
+                    regex.replace(content, substitution.replacement.as_str())
+                };
+                if replaced != content {
+                    edits.push((line_start..content_end, replaced.into_owned()));
+                }
+            }
+        }
+    }
+    flush(&mut deleting, end.min(last), &mut edits);
+    Ok(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use lapce_core::{mode::VisualMode, register::RegisterData};
+    use lsp_types::Position;
+
+    use super::{
+        compile_pattern, line_edits, parse_ex_command, Address, ExCommand,
+        LineAddress, LineCommand, LineRange, Mark, Substitution, VimState,
+    };
+
+    fn apply(text: &str, edits: Vec<(std::ops::Range<usize>, String)>) -> String {
+        let mut text = text.to_string();
+        for (range, new) in edits.into_iter().rev() {
+            text.replace_range(range, &new);
+        }
+        text
+    }
+
+    fn substitute(input: &str) -> LineCommand {
+        match parse_ex_command(input).unwrap() {
+            ExCommand::Lines { command, .. } => command,
+            command => panic!("{command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_registers() {
+        let data = |content: &str, mode| RegisterData {
+            content: content.to_string(),
+            mode,
+        };
+        let mut vim = VimState::default();
+        vim.store('a', data("one", VisualMode::Normal));
+        vim.store('A', data("two\n", VisualMode::Linewise));
+        let register = vim.register('a').unwrap();
+        assert_eq!(register.content, "one\ntwo\n");
+        assert_eq!(register.mode, VisualMode::Linewise);
+
+        vim.store('B', data("three", VisualMode::Normal));
+        assert_eq!(vim.register('b').unwrap().content, "three");
+        vim.store('_', data("four", VisualMode::Normal));
+        assert!(vim.register('_').is_none());
+    }
+
+    #[test]
+    fn test_marks() {
+        let mark = |path: &str, line| Mark {
+            path: PathBuf::from(path),
+            position: Position::new(line, 2),
+        };
+        let mut vim = VimState::default();
+        vim.set_mark('a', mark("/a.rs", 1));
+        vim.set_mark('a', mark("/b.rs", 2));
+        vim.set_mark('A', mark("/a.rs", 3));
+        assert_eq!(
+            vim.mark('a', Some(Path::new("/a.rs"))),
+            Some(mark("/a.rs", 1))
+        );
+        assert_eq!(
+            vim.mark('a', Some(Path::new("/b.rs"))),
+            Some(mark("/b.rs", 2))
+        );
+        assert_eq!(vim.mark('a', None), None);
+        assert_eq!(
+            vim.mark('A', Some(Path::new("/b.rs"))),
+            Some(mark("/a.rs", 3))
+        );
+        assert_eq!(vim.mark('b', Some(Path::new("/a.rs"))), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let address = |base, offset| Address { base, offset };
+        assert_eq!(
+            parse_ex_command("12").unwrap(),
+            ExCommand::GoToLine(address(LineAddress::Line(12), 0))
+        );
+        assert_eq!(
+            parse_ex_command(".,$-2d").unwrap(),
+            ExCommand::Lines {
+                range: Some(LineRange::Lines(
+                    address(LineAddress::Current, 0),
+                    address(LineAddress::Last, -2)
+                )),
+                command: LineCommand::Delete,
+            }
+        );
+        assert_eq!(
+            parse_ex_command("'a,'b+s#a\\#b#&-\\1$#gi").unwrap(),
+            ExCommand::Lines {
+                range: Some(LineRange::Lines(
+                    address(LineAddress::Mark('a'), 0),
+                    address(LineAddress::Mark('b'), 1)
+                )),
+                command: LineCommand::Substitute(Substitution {
+                    pattern: "a#b".to_string(),
+                    replacement: "${0}-${1}$$".to_string(),
+                    global: true,
+                    ignore_case: true,
+                }),
+            }
+        );
+        assert_eq!(
+            parse_ex_command("g!/^\\s*$/d").unwrap(),
+            ExCommand::Global {
+                range: None,
+                pattern: "^\\s*$".to_string(),
+                invert: true,
+                command: LineCommand::Delete,
+            }
+        );
+        assert_eq!(parse_ex_command(" w ").unwrap(), ExCommand::Write);
+        assert_eq!(parse_ex_command("wq").unwrap(), ExCommand::WriteQuit);
+        assert_eq!(
+            parse_ex_command("e src/main.rs").unwrap(),
+            ExCommand::Edit("src/main.rs".to_string())
+        );
+        assert!(parse_ex_command("toggle").is_err());
+        assert!(parse_ex_command("%w").is_err());
+        assert!(parse_ex_command("s//x/").is_err());
+        assert!(parse_ex_command("s/a/b/z").is_err());
+        assert!(parse_ex_command("").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mark = |name| (name == 'a').then_some(1);
+        let resolve = |input: &str| match parse_ex_command(input).unwrap() {
+            ExCommand::Lines { range, .. } => range.unwrap().resolve(2, 5, &mark),
+            command => panic!("{command:?}"),
+        };
+        assert_eq!(resolve("%d"), Ok((0, 5)));
+        assert_eq!(resolve("4,2d"), Ok((1, 3)));
+        assert_eq!(resolve("'a,.+d"), Ok((1, 3)));
+        assert_eq!(resolve("-,$d"), Ok((1, 5)));
+        assert!(resolve("'b,.d").is_err());
+        assert!(resolve("1,$+1d").is_err());
+    }
+
+    #[test]
+    fn test_line_edits() {
+        let text = "foo bar foo\r\nbaz\r\nfoo\r\n";
+        let edits = line_edits(text, 0, 3, None, &substitute("s/foo/[&]/")).unwrap();
+        assert_eq!(apply(text, edits), "[foo] bar foo\r\nbaz\r\n[foo]\r\n");
+        let edits = line_edits(text, 0, 1, None, &substitute("s/o/0/g")).unwrap();
+        assert_eq!(apply(text, edits), "f00 bar f00\r\nbaz\r\nfoo\r\n");
+        let edits =
+            line_edits(text, 0, 3, None, &substitute("s/(\\w+) (\\w+)/\\2 \\1/"))
+                .unwrap();
+        assert_eq!(apply(text, edits), "bar foo foo\r\nbaz\r\nfoo\r\n");
+
+        let text = "a\nb\nc\nd";
+        let regex = compile_pattern("[bd]", false).unwrap();
+        let edits =
+            line_edits(text, 0, 3, Some((&regex, false)), &LineCommand::Delete)
+                .unwrap();
+        assert_eq!(apply(text, edits), "a\nc");
+        let edits =
+            line_edits(text, 0, 3, Some((&regex, true)), &LineCommand::Delete)
+                .unwrap();
+        assert_eq!(apply(text, edits), "b\nd");
+        let edits = line_edits(text, 1, 2, None, &LineCommand::Delete).unwrap();
+        assert_eq!(apply(text, edits), "a\nd");
+        let edits = line_edits(text, 0, 3, None, &LineCommand::Delete).unwrap();
+        assert_eq!(apply(text, edits), "");
+    }
+}
@@ -2,13 +2,14 @@
 
 use floem::{
     action::TimerToken,
+    keyboard::{Key, KeyEvent, NamedKey},
     peniko::kurbo::{Point, Size},
     reactive::{
         use_context, Memo, ReadSignal, RwSignal, Scope, SignalGet, SignalUpdate,
         SignalWith,
     },
     window::WindowId,
-    ViewId,
+    View, ViewId,
 };
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +18,7 @@
     command::{InternalCommand, WindowCommand},
     config::LapceConfig,
     db::LapceDb,
+    editor::link_modifier,
     keypress::EventRef,
     listener::Listener,
     update::ReleaseInfo,
@@ -51,6 +53,9 @@ pub struct WindowCommonData {
     pub cursor_blink_timer: RwSignal<TimerToken>,
     // the value to be update by curosr blinking
     pub hide_cursor: RwSignal<bool>,
+    /// Whether the modifier for following links is held, with which the link
+    /// under the pointer is underlined.
+    pub link_modifier_held: RwSignal<bool>,
     pub app_view_id: RwSignal<ViewId>,
     pub extra_plugin_paths: Arc<Vec<PathBuf>>,
 }
@@ -77,6 +82,8 @@ pub struct WindowData {
     pub app_command: Listener<AppCommand>,
     pub position: RwSignal<Point>,
     pub root_view_id: RwSignal<ViewId>,
+    /// The view raising the window, see [`WindowRaiser`].
+    pub raiser_id: RwSignal<ViewId>,
     pub window_scale: RwSignal<f64>,
     pub config: RwSignal<Arc<LapceConfig>>,
     pub ime_enabled: RwSignal<bool>,
@@ -110,6 +117,7 @@ pub fn new(
         let window_tab_header_height = cx.create_rw_signal(0.0);
         let cursor_blink_timer = cx.create_rw_signal(TimerToken::INVALID);
         let hide_cursor = cx.create_rw_signal(false);
+        let link_modifier_held = cx.create_rw_signal(false);
 
         let common = Rc::new(WindowCommonData {
             window_command,
@@ -122,6 +130,7 @@ pub fn new(
             ime_allowed,
             cursor_blink_timer,
             hide_cursor,
+            link_modifier_held,
             app_view_id,
             extra_plugin_paths,
         });
@@ -156,6 +165,7 @@ pub fn new(
             active,
             position,
             root_view_id,
+            raiser_id: cx.create_rw_signal(ViewId::new()),
             window_scale,
             app_command,
             config,
@@ -333,6 +343,21 @@ pub fn run_window_command(&self, cmd: WindowCommand) {
         self.app_command.send(AppCommand::SaveApp);
     }
 
+    /// Keep track of whether the modifier for following links is held, as the
+    /// keys are pressed and released.
+    pub fn update_link_modifier(&self, key_event: &KeyEvent, pressed: bool) {
+        let held = match &key_event.key.logical_key {
+            Key::Named(NamedKey::Meta) if cfg!(target_os = "macos") => pressed,
+            Key::Named(NamedKey::Control) if cfg!(not(target_os = "macos")) => {
+                pressed
+            }
+            _ => link_modifier(key_event.modifiers),
+        };
+        if self.common.link_modifier_held.get_untracked() != held {
+            self.common.link_modifier_held.set(held);
+        }
+    }
+
     pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
         let active = self.active.get_untracked();
         let window_tab = self.window_tabs.with_untracked(|window_tabs| {
@@ -366,6 +391,12 @@ pub fn info(&self) -> WindowInfo {
         }
     }
 
+    /// Raise the window and give it the focus of the system, e.g. when
+    /// switching to it from another window.
+    pub fn raise(&self) {
+        self.raiser_id.get_untracked().update_state(());
+    }
+
     pub fn active_window_tab(&self) -> Option<Rc<WindowTabData>> {
         let window_tabs = self.window_tabs.get_untracked();
         let active = self
@@ -392,3 +423,30 @@ pub fn move_tab(&self, from_index: usize, to_index: usize) {
         self.active.set(to_index);
     }
 }
+
+/// An empty view raising the window it's in once its state is updated. The
+/// window is raised while it's the one being updated, which the handlers of
+/// the events of another window aren't.
+pub struct WindowRaiser {
+    id: ViewId,
+}
+
+pub fn window_raiser(raiser_id: RwSignal<ViewId>) -> WindowRaiser {
+    let id = ViewId::new();
+    raiser_id.set(id);
+    WindowRaiser { id }
+}
+
+impl View for WindowRaiser {
+    fn id(&self) -> ViewId {
+        self.id
+    }
+
+    fn update(
+        &mut self,
+        _cx: &mut floem::context::UpdateCx,
+        _state: Box<dyn std::any::Any>,
+    ) {
+        floem::action::focus_window();
+    }
+}
@@ -7,12 +7,12 @@
     time::Instant,
 };
 
-use alacritty_terminal::vte::ansi::Handler;
+use alacritty_terminal::{index::Direction, vte::ansi::Handler};
 use crossbeam_channel::Sender;
 use floem::{
-    action::{open_file, remove_overlay, TimerToken},
+    action::{open_file, remove_overlay, save_as, TimerToken},
     ext_event::{create_ext_action, create_signal_from_channel},
-    file::FileDialogOptions,
+    file::{FileDialogOptions, FileInfo},
     keyboard::Modifiers,
     kurbo::Size,
     peniko::kurbo::{Point, Rect, Vec2},
@@ -28,22 +28,31 @@
 use indexmap::IndexMap;
 use itertools::Itertools;
 use lapce_core::{
-    command::FocusCommand, cursor::CursorAffinity, directory::Directory, meta,
-    mode::Mode, register::Register,
+    command::FocusCommand,
+    cursor::CursorAffinity,
+    directory::Directory,
+    language::LapceLanguage,
+    meta,
+    mode::Mode,
+    register::Register,
+    syntax::{
+        highlight::reset_highlight_configs, textobject::TextObject, DefinitionKind,
+        Syntax,
+    },
 };
 use lapce_rpc::{
     core::CoreNotification,
-    dap_types::{ConfigSource, RunDebugConfig},
+    dap_types::{ConfigSource, DebugRequest, RunDebugConfig},
     file::{Naming, PathObject},
     plugin::PluginId,
     proxy::{ProxyResponse, ProxyRpcHandler, ProxyStatus},
     source_control::FileDiff,
-    terminal::TermId,
+    terminal::{TermId, TerminalEnvironment},
     RpcError,
 };
 use lsp_types::{
-    CodeActionOrCommand, CodeLens, Diagnostic, ProgressParams, ProgressToken,
-    ShowMessageParams,
+    CodeActionKind, CodeActionOrCommand, CodeLens, MessageType, ProgressParams,
+    ProgressToken, ShowMessageParams,
 };
 use serde_json::Value;
 use tracing::{debug, error, event, Level};
@@ -51,44 +60,66 @@
 use crate::{
     about::AboutData,
     alert::{AlertBoxData, AlertButton},
+    app::grammars::install_grammar,
+    bookmark::Bookmarks,
     code_action::{CodeActionData, CodeActionStatus},
     command::{
         CommandExecuted, CommandKind, InternalCommand, LapceCommand,
         LapceWorkbenchCommand, WindowCommand,
     },
     completion::{CompletionData, CompletionStatus},
-    config::LapceConfig,
+    config::{ui::ZoomScope, LapceConfig},
     db::LapceDb,
     debug::{DapData, LapceBreakpoint, RunDebugMode, RunDebugProcess},
-    doc::DocContent,
-    editor::location::{EditorLocation, EditorPosition},
+    debug_console::DebugConsoleData,
+    doc::{DiagnosticSource, DocContent},
+    editor::{
+        location::{EditorLocation, EditorPosition},
+        EditorData, TextDrag,
+    },
     editor_tab::EditorTabChild,
     file_explorer::data::FileExplorerData,
+    file_template::FileTemplate,
     find::Find,
     global_search::GlobalSearchData,
     hover::HoverData,
+    hunk_peek::HunkPeekData,
     id::WindowTabId,
     inline_completion::InlineCompletionData,
+    keyboard_macro::{MacroRegisterAction, MacroStep, DEFAULT_MACRO},
     keypress::{condition::Condition, EventRef, KeyPressData, KeyPressFocus},
     listener::Listener,
     lsp::path_from_url,
     main_split::{MainSplitData, SplitData, SplitDirection, SplitMoveDirection},
+    notification::{NotificationData, NotificationId},
     palette::{kind::PaletteKind, PaletteData, PaletteStatus, DEFAULT_RUN_TOML},
     panel::{
         call_hierarchy_view::{CallHierarchyData, CallHierarchyItemData},
         data::{default_panel_order, PanelData, PanelSection},
+        document_symbol::OutlineData,
         kind::PanelKind,
-        position::PanelContainerPosition,
+        output_view::OutputData,
+        position::{PanelContainerPosition, PanelPosition},
+        problem_view::ProblemData,
+        style::PanelStyle,
     },
+    peek::{PeekData, PeekKind},
+    perf,
     plugin::PluginData,
     proxy::{new_proxy, ProxyData},
     rename::RenameData,
+    signature::SignatureData,
     source_control::SourceControlData,
+    spell::SpellChecker,
+    task::TaskData,
     terminal::{
         event::{terminal_update_process, TermEvent, TermNotification},
         panel::TerminalPanelData,
     },
+    text_transform::{CaseTransform, LineTransform},
     tracing::*,
+    user_snippet::UserSnippets,
+    vim::VimState,
     window::WindowCommonData,
     workspace::{LapceWorkspace, LapceWorkspaceType, WorkspaceInfo},
 };
@@ -100,6 +131,7 @@ pub enum Focus {
     CodeAction,
     Rename,
     AboutPopup,
+    Peek,
     Panel(PanelKind),
 }
 
@@ -107,6 +139,8 @@ pub enum Focus {
 pub enum DragContent {
     Panel(PanelKind),
     EditorTab(EditorTabChild),
+    /// A file or folder dragged from the file explorer.
+    File(PathBuf),
 }
 
 impl DragContent {
@@ -115,12 +149,23 @@ pub fn is_panel(&self) -> bool {
     }
 }
 
+/// The layout hidden by Zen mode, put back when leaving it.
+#[derive(Clone)]
+pub struct ZenMode {
+    pub panel_styles: im::HashMap<PanelPosition, PanelStyle>,
+    pub window_maximized: bool,
+}
+
 #[derive(Clone)]
 pub struct WorkProgress {
     pub token: ProgressToken,
+    /// The name of the language server doing the work.
+    pub name: String,
     pub title: String,
     pub message: Option<String>,
     pub percentage: Option<u32>,
+    /// The notification of the work in the notification center.
+    pub notification: NotificationId,
 }
 
 #[derive(Clone)]
@@ -132,7 +177,10 @@ pub struct CommonData {
     pub completion: RwSignal<CompletionData>,
     pub inline_completion: RwSignal<InlineCompletionData>,
     pub hover: HoverData,
+    pub signature: SignatureData,
     pub register: RwSignal<Register>,
+    /// The named registers and the marks of the vim emulation.
+    pub vim: RwSignal<VimState>,
     pub find: Find,
     pub workbench_size: RwSignal<Size>,
     pub window_origin: RwSignal<Point>,
@@ -145,10 +193,17 @@ pub struct CommonData {
     pub view_id: RwSignal<ViewId>,
     pub ui_line_height: Memo<f64>,
     pub dragging: RwSignal<Option<DragContent>>,
+    /// The selected text being dragged within or between editors.
+    pub text_drag: RwSignal<Option<TextDrag>>,
     pub config: ReadSignal<Arc<LapceConfig>>,
     pub proxy_status: RwSignal<Option<ProxyStatus>>,
     pub mouse_hover_timer: RwSignal<TimerToken>,
     pub breakpoints: RwSignal<BTreeMap<PathBuf, BTreeMap<usize, LapceBreakpoint>>>,
+    pub bookmarks: RwSignal<Bookmarks>,
+    /// The snippets from the user's snippets directory, offered in completion.
+    pub user_snippets: RwSignal<Arc<UserSnippets>>,
+    pub spell: Rc<SpellChecker>,
+    pub notifications: NotificationData,
     // the current focused view which will receive keyboard events
     pub keyboard_focus: RwSignal<Option<ViewId>>,
     pub window_common: Rc<WindowCommonData>,
@@ -167,6 +222,8 @@ pub struct WindowTabData {
     pub scope: Scope,
     pub window_tab_id: WindowTabId,
     pub workspace: Arc<LapceWorkspace>,
+    /// The folders added to the workspace besides its root.
+    pub workspace_folders: RwSignal<im::Vector<PathBuf>>,
     pub palette: PaletteData,
     pub main_split: MainSplitData,
     pub file_explorer: FileExplorerData,
@@ -178,8 +235,18 @@ pub struct WindowTabData {
     pub source_control: SourceControlData,
     pub rename: RenameData,
     pub global_search: GlobalSearchData,
+    pub debug_console: DebugConsoleData,
+    pub problem: ProblemData,
+    pub outline: OutlineData,
     pub call_hierarchy_data: CallHierarchyData,
+    /// The output of the language servers, shown in the output panel.
+    pub output: OutputData,
+    pub task: TaskData,
     pub about_data: AboutData,
+    pub hunk_peek: HunkPeekData,
+    pub peek: PeekData,
+    pub show_perf_overlay: RwSignal<bool>,
+    pub zen_mode: RwSignal<Option<ZenMode>>,
     pub alert_data: AlertBoxData,
     pub layout_rect: RwSignal<Rect>,
     pub title_height: RwSignal<f64>,
@@ -187,8 +254,7 @@ pub struct WindowTabData {
     pub proxy: ProxyData,
     pub set_config: WriteSignal<Arc<LapceConfig>>,
     pub update_in_progress: RwSignal<bool>,
-    pub progresses: RwSignal<IndexMap<ProgressToken, WorkProgress>>,
-    pub messages: RwSignal<Vec<(String, ShowMessageParams)>>,
+    pub progresses: RwSignal<IndexMap<(PluginId, ProgressToken), WorkProgress>>,
     pub common: Rc<CommonData>,
 }
 
@@ -327,11 +393,17 @@ pub fn new(
                 .unwrap();
         }
 
+        let workspace_folders: im::Vector<PathBuf> = workspace_info
+            .as_ref()
+            .map(|info| info.folders.iter().cloned().collect())
+            .unwrap_or_default();
         let proxy = new_proxy(
             workspace.clone(),
+            workspace_folders.iter().cloned().collect(),
             all_disabled_volts,
             window_common.extra_plugin_paths.as_ref().clone(),
             config.plugins.clone(),
+            config.editor.file_associations.clone(),
             term_tx.clone(),
         );
         let (config, set_config) = cx.create_signal(Arc::new(config));
@@ -368,7 +440,9 @@ pub fn new(
             completion,
             inline_completion,
             hover,
+            signature: SignatureData::new(cx),
             register,
+            vim: cx.create_rw_signal(VimState::default()),
             find,
             internal_command,
             lapce_command,
@@ -379,12 +453,30 @@ pub fn new(
             view_id,
             ui_line_height,
             dragging: cx.create_rw_signal(None),
+            text_drag: cx.create_rw_signal(None),
             workbench_size: cx.create_rw_signal(Size::ZERO),
             config,
             proxy_status,
             mouse_hover_timer: cx.create_rw_signal(TimerToken::INVALID),
             window_origin: cx.create_rw_signal(Point::ZERO),
             breakpoints: cx.create_rw_signal(BTreeMap::new()),
+            bookmarks: cx.create_rw_signal(
+                workspace_info
+                    .as_ref()
+                    .map(|info| info.bookmarks.clone())
+                    .unwrap_or_default(),
+            ),
+            user_snippets: cx.create_rw_signal(Arc::new(UserSnippets::load())),
+            // The workspace dictionary is a local file, so the words added in
+            // remote workspaces are only kept for the session.
+            spell: Rc::new(SpellChecker::new(
+                cx,
+                workspace
+                    .path
+                    .as_deref()
+                    .filter(|_| workspace.kind.is_local()),
+            )),
+            notifications: NotificationData::new(cx),
             keyboard_focus: cx.create_rw_signal(None),
             window_common: window_common.clone(),
         });
@@ -420,15 +512,6 @@ pub fn new(
             });
         }
 
-        let palette = PaletteData::new(
-            cx,
-            workspace.clone(),
-            main_split.clone(),
-            keypress.read_only(),
-            source_control.clone(),
-            common.clone(),
-        );
-
         let title_height = cx.create_rw_signal(0.0);
         let status_height = cx.create_rw_signal(0.0);
         let panel_available_size = cx.create_memo(move |_| {
@@ -505,10 +588,33 @@ pub fn new(
                     })
                     .collect(),
             );
+            terminal
+                .debug
+                .watches
+                .set(workspace_info.watches.iter().cloned().collect());
         }
 
+        let palette = PaletteData::new(
+            cx,
+            workspace.clone(),
+            main_split.clone(),
+            keypress.read_only(),
+            source_control.clone(),
+            terminal.clone(),
+            common.clone(),
+        );
+
         let rename = RenameData::new(cx, main_split.editors, common.clone());
         let global_search = GlobalSearchData::new(cx, main_split.clone());
+        let debug_console = DebugConsoleData::new(
+            cx,
+            main_split.editors,
+            terminal.clone(),
+            common.clone(),
+        );
+        let problem = ProblemData::new(cx, main_split.clone());
+        let outline = OutlineData::new(cx, main_split.clone(), common.clone());
+        let task = TaskData::new(cx, main_split.clone(), common.clone());
 
         let plugin = PluginData::new(
             cx,
@@ -540,11 +646,14 @@ pub fn new(
 
         let about_data = AboutData::new(cx, common.focus);
         let alert_data = AlertBoxData::new(cx, common.clone());
+        let hunk_peek = HunkPeekData::new(cx, common.clone());
+        let peek = PeekData::new(cx, main_split.clone());
 
         let window_tab_data = Self {
             scope: cx,
             window_tab_id: WindowTabId::next(),
             workspace,
+            workspace_folders: cx.create_rw_signal(workspace_folders),
             palette,
             main_split,
             terminal,
@@ -556,12 +665,21 @@ pub fn new(
             plugin,
             rename,
             global_search,
+            debug_console,
+            problem,
+            outline,
             call_hierarchy_data: CallHierarchyData {
                 root: cx.create_rw_signal(None),
                 common: common.clone(),
                 scroll_to_line: cx.create_rw_signal(None),
             },
+            output: OutputData::new(cx),
+            task,
             about_data,
+            hunk_peek,
+            peek,
+            show_perf_overlay: cx.create_rw_signal(false),
+            zen_mode: cx.create_rw_signal(None),
             alert_data,
             layout_rect: cx.create_rw_signal(Rect::ZERO),
             title_height,
@@ -570,7 +688,6 @@ pub fn new(
             set_config,
             update_in_progress: cx.create_rw_signal(false),
             progresses: cx.create_rw_signal(IndexMap::new()),
-            messages: cx.create_rw_signal(Vec::new()),
             common,
         };
 
@@ -578,6 +695,7 @@ pub fn new(
             let focus = window_tab_data.common.focus;
             let active_editor = window_tab_data.main_split.active_editor;
             let rename_active = window_tab_data.rename.active;
+            let hunk_peek = window_tab_data.hunk_peek.clone();
             let internal_command = window_tab_data.common.internal_command;
             cx.create_effect(move |_| {
                 let focus = focus.get();
@@ -587,6 +705,32 @@ pub fn new(
                 if focus != Focus::Rename && rename_active.get_untracked() {
                     rename_active.set(false);
                 }
+                if hunk_peek.active.get_untracked() {
+                    hunk_peek.close();
+                }
+            });
+        }
+
+        {
+            // Relayout the open documents once the spell checking dictionary is
+            // loaded, so their misspelled words are underlined.
+            let dictionary = window_tab_data.common.spell.dictionary;
+            let docs = window_tab_data.main_split.docs;
+            cx.create_effect(move |_| {
+                dictionary.track();
+                for doc in docs.get_untracked().values() {
+                    doc.clear_text_cache();
+                }
+            });
+        }
+
+        {
+            let workspace_folders = window_tab_data.workspace_folders;
+            let file_explorer = window_tab_data.file_explorer.clone();
+            cx.create_effect(move |_| {
+                let folders: Vec<PathBuf> =
+                    workspace_folders.get().into_iter().collect();
+                file_explorer.set_folders(&folders);
             });
         }
 
@@ -624,6 +768,8 @@ pub fn new(
             });
         }
 
+        window_tab_data.task.load();
+
         window_tab_data
     }
 
@@ -645,6 +791,9 @@ pub fn reload_config(&self) {
         self.common.keypress.update(|keypress| {
             keypress.update_keymaps(&config);
         });
+        self.common
+            .user_snippets
+            .set(Arc::new(UserSnippets::load()));
 
         let mut change_plugins = Vec::new();
         for (key, configs) in self.common.config.get_untracked().plugins.iter() {
@@ -657,7 +806,17 @@ pub fn reload_config(&self) {
                 change_plugins.push(key.clone());
             }
         }
+        let associations_changed = self.common.config.with_untracked(|old| {
+            old.editor.file_associations != config.editor.file_associations
+                || old.core.grammars != config.core.grammars
+        });
         self.set_config.set(Arc::new(config.clone()));
+        if associations_changed {
+            self.common
+                .proxy
+                .update_file_associations(config.editor.file_associations.clone());
+            self.update_associated_languages();
+        }
         if !change_plugins.is_empty() {
             self.common
                 .proxy
@@ -728,6 +887,8 @@ pub fn run_workbench_command(
                 let internal_command = self.common.internal_command;
                 internal_command.send(InternalCommand::SetModal { modal: false });
             }
+            // Handled by the editor, as they take the character typed next
+            SelectRegister | CreateMark | GoToMark | GoToMarkPosition => {}
 
             // ==== Files / Folders ====
             OpenFolder => {
@@ -771,6 +932,29 @@ pub fn run_workbench_command(
                     window_command.send(WindowCommand::SetWorkspace { workspace });
                 }
             }
+            AddFolderToWorkspace => {
+                if !self.workspace.kind.is_remote() {
+                    let workspace_folders = self.workspace_folders;
+                    let proxy = self.common.proxy.clone();
+                    let mut options = FileDialogOptions::new().select_directories();
+                    if let Some(path) = self.workspace.path.as_ref() {
+                        options = options.force_starting_directory(path);
+                    }
+                    open_file(options, move |file| {
+                        let Some(path) = file.and_then(|mut file| file.path.pop())
+                        else {
+                            return;
+                        };
+                        if workspace_folders.with_untracked(|f| f.contains(&path)) {
+                            return;
+                        }
+                        workspace_folders.update(|f| f.push_back(path));
+                        proxy.update_workspace_folders(
+                            workspace_folders.get_untracked().into_iter().collect(),
+                        );
+                    });
+                }
+            }
             OpenFile => {
                 if !self.workspace.kind.is_remote() {
                     let internal_command = self.common.internal_command;
@@ -792,6 +976,42 @@ pub fn run_workbench_command(
             NewFile => {
                 self.main_split.new_file();
             }
+            NewFileFromTemplate => {
+                let palette = self.palette.clone();
+                let plugin = self.plugin.clone();
+                let extra_plugin_paths =
+                    self.common.window_common.extra_plugin_paths.clone();
+                let scope = self.scope;
+                save_as(FileDialogOptions::new(), move |file: Option<FileInfo>| {
+                    let Some(path) = file.and_then(|mut file| file.path.pop())
+                    else {
+                        return;
+                    };
+                    let mut disabled = plugin.disabled.get_untracked();
+                    disabled.extend(plugin.workspace_disabled.get_untracked());
+                    let palette = palette.clone();
+                    let send = create_ext_action(
+                        scope,
+                        move |(path, templates): (PathBuf, Vec<FileTemplate>)| {
+                            palette.run_file_templates(path, templates);
+                        },
+                    );
+                    let extra_plugin_paths = extra_plugin_paths.clone();
+                    std::thread::Builder::new()
+                        .name("LoadFileTemplates".to_owned())
+                        .spawn(move || {
+                            let templates = FileTemplate::load_all(
+                                &extra_plugin_paths,
+                                |id| disabled.contains(id),
+                            )
+                            .into_iter()
+                            .filter(|template| template.matches(&path))
+                            .collect();
+                            send((path, templates));
+                        })
+                        .unwrap();
+                });
+            }
             RevealActiveFileInFileExplorer => {
                 if let Some(editor_data) = self.main_split.active_editor.get() {
                     let doc = editor_data.doc();
@@ -904,6 +1124,11 @@ pub fn run_workbench_command(
                     open_uri(&dir);
                 }
             }
+            OpenSnippetsDirectory => {
+                if let Some(dir) = Directory::snippets_directory() {
+                    open_uri(&dir);
+                }
+            }
             OpenPluginsDirectory => {
                 if let Some(dir) = Directory::plugins_directory() {
                     open_uri(&dir);
@@ -919,6 +1144,9 @@ pub fn run_workbench_command(
                     open_uri(&dir);
                 }
             }
+            InstallGrammar => {
+                self.install_grammar();
+            }
 
             InstallTheme => {}
             ExportCurrentThemeSettings => {
@@ -1033,6 +1261,27 @@ pub fn run_workbench_command(
                     });
                 }
             }
+            PinEditorTab => {
+                if let Some((editor_tab_id, child)) =
+                    self.main_split.active_editor_tab_child()
+                {
+                    self.main_split
+                        .editor_tab_child_pin(editor_tab_id, child, true);
+                }
+            }
+            UnpinEditorTab => {
+                if let Some((editor_tab_id, child)) =
+                    self.main_split.active_editor_tab_child()
+                {
+                    self.main_split
+                        .editor_tab_child_pin(editor_tab_id, child, false);
+                }
+            }
+            KeepEditorTabOpen => {
+                if let Some((_, child)) = self.main_split.active_editor_tab_child() {
+                    self.main_split.editor_tab_child_confirm(&child);
+                }
+            }
 
             // ==== Terminal ====
             NewTerminalTab => {
@@ -1080,6 +1329,109 @@ pub fn run_workbench_command(
                 }
                 self.common.focus.set(Focus::Panel(PanelKind::Terminal));
             }
+            TerminalSplitResizeLeft => {
+                if let Some(tab) = self.terminal.active_tab(false) {
+                    tab.resize_active_terminal(-0.05);
+                }
+            }
+            TerminalSplitResizeRight => {
+                if let Some(tab) = self.terminal.active_tab(false) {
+                    tab.resize_active_terminal(0.05);
+                }
+            }
+            // Terminal splits are laid out side by side, so resizing vertically
+            // changes the height of the panel when it is at the bottom.
+            TerminalSplitResizeUp => {
+                self.panel.resize_bottom(&PanelKind::Terminal, 20.0);
+            }
+            TerminalSplitResizeDown => {
+                self.panel.resize_bottom(&PanelKind::Terminal, -20.0);
+            }
+            TerminalPreviousPrompt | TerminalNextPrompt => {
+                let direction = if matches!(cmd, TerminalPreviousPrompt) {
+                    Direction::Left
+                } else {
+                    Direction::Right
+                };
+                if let Some(terminal) = self
+                    .terminal
+                    .active_tab(false)
+                    .and_then(|tab| tab.active_terminal(false))
+                {
+                    if terminal.scroll_to_prompt(direction) {
+                        self.common.view_id.get_untracked().request_paint();
+                    }
+                }
+            }
+            StartTerminalLogging => {
+                let Some(terminal) = self
+                    .terminal
+                    .active_tab(false)
+                    .and_then(|tab| tab.active_terminal(false))
+                else {
+                    return;
+                };
+                let timestamps =
+                    self.common.config.get_untracked().terminal.log_timestamps;
+                let notifications = self.common.notifications.clone();
+                save_as(FileDialogOptions::new(), move |file: Option<FileInfo>| {
+                    let Some(path) = file.and_then(|mut file| file.path.pop())
+                    else {
+                        return;
+                    };
+                    if let Err(err) = terminal.start_logging(&path, timestamps) {
+                        notifications.notify(
+                            "Terminal Logging Failed",
+                            &ShowMessageParams {
+                                typ: MessageType::ERROR,
+                                message: format!("{}: {err}", path.display()),
+                            },
+                        );
+                    }
+                });
+            }
+            StopTerminalLogging => {
+                if let Some(terminal) = self
+                    .terminal
+                    .active_tab(false)
+                    .and_then(|tab| tab.active_terminal(false))
+                {
+                    terminal.stop_logging();
+                }
+            }
+            ShowTerminalEnvironment => {
+                let Some(terminal) = self
+                    .terminal
+                    .active_tab(false)
+                    .and_then(|tab| tab.active_terminal(false))
+                else {
+                    return;
+                };
+                let title = terminal.title.get_untracked();
+                let main_split = self.main_split.clone();
+                let send = create_ext_action(self.scope, move |result| {
+                    if let Ok(ProxyResponse::TerminalEnvironmentResponse {
+                        environment,
+                    }) = result
+                    {
+                        main_split.open_scratch_doc(format_terminal_environment(
+                            &title,
+                            &environment,
+                        ));
+                    }
+                });
+                self.common
+                    .proxy
+                    .terminal_environment(terminal.term_id, move |result| {
+                        send(result);
+                    });
+            }
+            SaveTerminalLayout => {
+                self.palette.run(PaletteKind::SaveTerminalLayout);
+            }
+            RestoreTerminalLayout => {
+                self.palette.run(PaletteKind::TerminalLayout);
+            }
 
             // ==== Remote ====
             ConnectSshHost => {
@@ -1114,9 +1466,15 @@ pub fn run_workbench_command(
                 self.palette.run(PaletteKind::DocumentSymbol);
             }
             PaletteWorkspaceSymbol => {}
+            PaletteAnything => {
+                self.palette.run(PaletteKind::Anything);
+            }
             PaletteCommand => {
                 self.palette.run(PaletteKind::Command);
             }
+            PaletteExCommand => {
+                self.palette.run_ex_command();
+            }
             PaletteWorkspace => {
                 self.palette.run(PaletteKind::Workspace);
             }
@@ -1138,164 +1496,542 @@ pub fn run_workbench_command(
             ChangeFileLineEnding => {
                 self.palette.run(PaletteKind::LineEnding);
             }
-            DiffFiles => self.palette.run(PaletteKind::DiffFiles),
-
-            // ==== Running / Debugging ====
-            RunAndDebugRestart => {
-                let active_term = self.terminal.debug.active_term.get_untracked();
-                if let Some(is_debug) = active_term
-                    .and_then(|term_id| self.terminal.restart_run_debug(term_id))
-                {
-                    self.panel.show_panel(&PanelKind::Terminal);
-                    if is_debug {
-                        self.panel.show_panel(&PanelKind::Debug);
-                    }
-                } else {
-                    self.palette.run(PaletteKind::RunAndDebug);
+            ReopenWithEncoding => {
+                self.palette.run(PaletteKind::ReopenWithEncoding);
+            }
+            SaveWithEncoding => {
+                self.palette.run(PaletteKind::SaveWithEncoding);
+            }
+            ToggleBlockComment => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.toggle_block_comment();
                 }
             }
-            RunAndDebugStop => {
-                let active_term = self.terminal.debug.active_term.get_untracked();
-                if let Some(term_id) = active_term {
-                    self.terminal.stop_run_debug(term_id);
+            SurroundWith => {
+                self.palette.run(PaletteKind::SurroundWith);
+            }
+            RemoveSurroundingPair => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.remove_surrounding_pair();
                 }
             }
-
-            // ==== UI ====
-            ZoomIn => {
-                let mut scale =
-                    self.common.window_common.window_scale.get_untracked();
-                scale += 0.1;
-                if scale > 4.0 {
-                    scale = 4.0
+            ExpandSelection => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.expand_selection();
                 }
-                self.common.window_common.window_scale.set(scale);
-
-                LapceConfig::update_file(
-                    "ui",
-                    "scale",
-                    toml_edit::Value::from(scale),
-                );
             }
-            ZoomOut => {
-                let mut scale =
-                    self.common.window_common.window_scale.get_untracked();
-                scale -= 0.1;
-                if scale < 0.1 {
-                    scale = 0.1
+            ShrinkSelection => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.shrink_selection();
                 }
-                self.common.window_common.window_scale.set(scale);
-
-                LapceConfig::update_file(
-                    "ui",
-                    "scale",
-                    toml_edit::Value::from(scale),
-                );
             }
-            ZoomReset => {
-                self.common.window_common.window_scale.set(1.0);
-
-                LapceConfig::update_file(
-                    "ui",
-                    "scale",
-                    toml_edit::Value::from(1.0),
-                );
+            UndoWorkspaceFileOperations => {
+                self.main_split.undo_workspace_file_operations();
             }
-
-            ToggleMaximizedPanel => {
-                if let Some(data) = data {
-                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
-                        self.panel.toggle_maximize(&kind);
-                    }
-                } else {
-                    self.panel.toggle_active_maximize();
+            ShowRefactorActions => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.show_code_actions_of_kind(CodeActionKind::REFACTOR);
                 }
             }
-            HidePanel => {
-                if let Some(data) = data {
-                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
-                        self.hide_panel(kind);
-                    }
+            ShowSourceActions => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.show_code_actions_of_kind(CodeActionKind::SOURCE);
                 }
             }
-            ShowPanel => {
-                if let Some(data) = data {
-                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
-                        self.show_panel(kind);
-                    }
+            AcceptInlineCompletionWord => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.accept_inline_completion_word();
                 }
             }
-            TogglePanelFocus => {
-                if let Some(data) = data {
-                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
-                        self.toggle_panel_focus(kind);
-                    }
+            PasteWithoutFormatting => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.paste_without_formatting();
                 }
             }
-            TogglePanelVisual => {
-                if let Some(data) = data {
-                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
-                        self.toggle_panel_visual(kind);
-                    }
+            GoToNextFunction => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.go_to_definition_of_kind(DefinitionKind::Function, true);
                 }
             }
-            TogglePanelLeftVisual => {
-                self.toggle_container_visual(&PanelContainerPosition::Left);
-            }
-            TogglePanelRightVisual => {
-                self.toggle_container_visual(&PanelContainerPosition::Right);
+            GoToPreviousFunction => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.go_to_definition_of_kind(DefinitionKind::Function, false);
+                }
             }
-            TogglePanelBottomVisual => {
-                self.toggle_container_visual(&PanelContainerPosition::Bottom);
+            GoToNextClass => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.go_to_definition_of_kind(DefinitionKind::Class, true);
+                }
             }
-            ToggleTerminalFocus => {
-                self.toggle_panel_focus(PanelKind::Terminal);
+            GoToPreviousClass => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.go_to_definition_of_kind(DefinitionKind::Class, false);
+                }
             }
-            ToggleSourceControlFocus => {
-                self.toggle_panel_focus(PanelKind::SourceControl);
+            ToggleOutputVisual => {
+                self.toggle_panel_visual(PanelKind::Output);
             }
-            TogglePluginFocus => {
-                self.toggle_panel_focus(PanelKind::Plugin);
+            RestartLanguageServer => {
+                self.stop_language_servers(true);
             }
-            ToggleFileExplorerFocus => {
-                self.toggle_panel_focus(PanelKind::FileExplorer);
+            StopLanguageServer => {
+                self.stop_language_servers(false);
             }
-            ToggleProblemFocus => {
-                self.toggle_panel_focus(PanelKind::Problem);
+            ToggleTasksVisual => {
+                if !self.panel.is_panel_visible(&PanelKind::Task) {
+                    self.task.load();
+                }
+                self.toggle_panel_visual(PanelKind::Task);
             }
-            ToggleSearchFocus => {
-                self.toggle_panel_focus(PanelKind::Search);
+            PaletteTasks => {
+                self.palette.run(PaletteKind::Task);
             }
-            ToggleTerminalVisual => {
-                self.toggle_panel_visual(PanelKind::Terminal);
+            RerunLastTask => {
+                self.task.rerun_last();
             }
-            ToggleSourceControlVisual => {
-                self.toggle_panel_visual(PanelKind::SourceControl);
+            StopLastTask => {
+                self.task.stop_last();
             }
-            TogglePluginVisual => {
-                self.toggle_panel_visual(PanelKind::Plugin);
+            ToggleNotifications => {
+                self.common.notifications.toggle_history();
             }
-            ToggleFileExplorerVisual => {
-                self.toggle_panel_visual(PanelKind::FileExplorer);
+            ClearNotifications => {
+                self.common.notifications.clear();
             }
-            ToggleProblemVisual => {
-                self.toggle_panel_visual(PanelKind::Problem);
+            SelectInsideFunction => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Function, true, false);
+                }
             }
-            ToggleDebugVisual => {
-                self.toggle_panel_visual(PanelKind::Debug);
+            SelectInsideClass => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Class, true, false);
+                }
             }
-            ToggleSearchVisual => {
-                self.toggle_panel_visual(PanelKind::Search);
+            SelectInsideParameter => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Parameter, true, false);
+                }
             }
-            FocusEditor => {
-                self.common.focus.set(Focus::Workbench);
+            SelectInsideComment => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Comment, true, false);
+                }
             }
-            FocusTerminal => {
-                self.common.focus.set(Focus::Panel(PanelKind::Terminal));
+            SelectAroundFunction => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Function, false, false);
+                }
             }
+            SelectAroundClass => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Class, false, false);
+                }
+            }
+            SelectAroundParameter => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Parameter, false, false);
+                }
+            }
+            SelectAroundComment => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Comment, false, false);
+                }
+            }
+            ChangeInsideFunction => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Function, true, true);
+                }
+            }
+            ChangeInsideClass => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Class, true, true);
+                }
+            }
+            ChangeInsideParameter => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Parameter, true, true);
+                }
+            }
+            ChangeInsideComment => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Comment, true, true);
+                }
+            }
+            ChangeAroundFunction => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Function, false, true);
+                }
+            }
+            ChangeAroundClass => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Class, false, true);
+                }
+            }
+            ChangeAroundParameter => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Parameter, false, true);
+                }
+            }
+            ChangeAroundComment => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.select_text_object(TextObject::Comment, false, true);
+                }
+            }
+            SwapParameterNext => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.swap_parameter(true);
+                }
+            }
+            SwapParameterPrevious => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.swap_parameter(false);
+                }
+            }
+            SortLinesAscending => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_lines(LineTransform::SortAscending);
+                }
+            }
+            SortLinesDescending => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_lines(LineTransform::SortDescending);
+                }
+            }
+            SortLinesNatural => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_lines(LineTransform::SortNatural);
+                }
+            }
+            SortLinesCaseInsensitive => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_lines(LineTransform::SortCaseInsensitive);
+                }
+            }
+            DeleteDuplicateLines => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_lines(LineTransform::Unique);
+                }
+            }
+            JoinSelectedLines => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_lines(LineTransform::Join);
+                }
+            }
+            TransformToUppercase => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_case(CaseTransform::Upper);
+                }
+            }
+            TransformToLowercase => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_case(CaseTransform::Lower);
+                }
+            }
+            TransformToTitleCase => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_case(CaseTransform::Title);
+                }
+            }
+            TransformToSnakeCase => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_case(CaseTransform::Snake);
+                }
+            }
+            TransformToCamelCase => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.transform_case(CaseTransform::Camel);
+                }
+            }
+            NextSignature => {
+                self.common.signature.next();
+            }
+            PreviousSignature => {
+                self.common.signature.previous();
+            }
+            DiffFiles => self.palette.run(PaletteKind::DiffFiles),
+            CompareActiveFileWith => {
+                if let Some(path) = self
+                    .main_split
+                    .active_editor
+                    .get_untracked()
+                    .and_then(|editor| {
+                        editor
+                            .doc()
+                            .content
+                            .with_untracked(|content| content.path().cloned())
+                    })
+                {
+                    self.palette.compare_with(path);
+                }
+            }
+            CompareActiveFileWithClipboard => {
+                self.main_split.open_clipboard_diff();
+            }
+            NextDiffChange => {
+                if let Some(diff_editor) = self.main_split.active_diff_editor() {
+                    diff_editor.go_to_change(true);
+                }
+            }
+            PreviousDiffChange => {
+                if let Some(diff_editor) = self.main_split.active_diff_editor() {
+                    diff_editor.go_to_change(false);
+                }
+            }
+            ToggleInlineDiff => {
+                if let Some(diff_editor) = self.main_split.active_diff_editor() {
+                    diff_editor.toggle_inline();
+                }
+            }
+            PaletteDirtyFiles => self.palette.run(PaletteKind::DirtyFiles),
+            PaletteLocalHistory => self.palette.run(PaletteKind::LocalHistory),
+            SwitchWindow => self.palette.run(PaletteKind::Window),
+
+            // ==== Running / Debugging ====
+            RunAndDebugRestart => {
+                let active_term = self.terminal.debug.active_term.get_untracked();
+                if let Some(is_debug) = active_term
+                    .and_then(|term_id| self.terminal.restart_run_debug(term_id))
+                {
+                    self.panel.show_panel(&PanelKind::Terminal);
+                    if is_debug {
+                        self.panel.show_panel(&PanelKind::Debug);
+                    }
+                } else {
+                    self.palette.run(PaletteKind::RunAndDebug);
+                }
+            }
+            RunAndDebugStop => {
+                let active_term = self.terminal.debug.active_term.get_untracked();
+                if let Some(term_id) = active_term {
+                    self.terminal.stop_run_debug(term_id);
+                }
+            }
+
+            // ==== UI ====
+            ZoomIn => {
+                self.zoom(Some(true));
+            }
+            ZoomOut => {
+                self.zoom(Some(false));
+            }
+            ZoomReset => {
+                self.zoom(None);
+            }
+            ToggleErrorLens => {
+                let enabled =
+                    self.common.config.get_untracked().editor.enable_error_lens;
+                // The config file is watched
+                LapceConfig::update_file(
+                    "editor",
+                    "enable-error-lens",
+                    toml_edit::Value::from(!enabled),
+                );
+            }
+            ToggleCenteredLayout => {
+                let enabled =
+                    self.common.config.get_untracked().editor.centered_layout;
+                LapceConfig::update_file(
+                    "editor",
+                    "centered-layout",
+                    toml_edit::Value::from(!enabled),
+                );
+            }
+
+            MarkdownPreviewToSide => {
+                self.main_split.open_markdown_preview();
+            }
+
+            ToggleMaximizedPanel => {
+                if let Some(data) = data {
+                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
+                        self.panel.toggle_maximize(&kind);
+                    }
+                } else {
+                    self.panel.toggle_active_maximize();
+                }
+            }
+            HidePanel => {
+                if let Some(data) = data {
+                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
+                        self.hide_panel(kind);
+                    }
+                }
+            }
+            ShowPanel => {
+                if let Some(data) = data {
+                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
+                        self.show_panel(kind);
+                    }
+                }
+            }
+            TogglePanelFocus => {
+                if let Some(data) = data {
+                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
+                        self.toggle_panel_focus(kind);
+                    }
+                }
+            }
+            TogglePanelVisual => {
+                if let Some(data) = data {
+                    if let Ok(kind) = serde_json::from_value::<PanelKind>(data) {
+                        self.toggle_panel_visual(kind);
+                    }
+                }
+            }
+            TogglePanelLeftVisual => {
+                self.toggle_container_visual(&PanelContainerPosition::Left);
+            }
+            TogglePanelRightVisual => {
+                self.toggle_container_visual(&PanelContainerPosition::Right);
+            }
+            TogglePanelBottomVisual => {
+                self.toggle_container_visual(&PanelContainerPosition::Bottom);
+            }
+            ToggleTerminalFocus => {
+                self.toggle_panel_focus(PanelKind::Terminal);
+            }
+            ToggleSourceControlFocus => {
+                self.toggle_panel_focus(PanelKind::SourceControl);
+            }
+            TogglePluginFocus => {
+                self.toggle_panel_focus(PanelKind::Plugin);
+            }
+            ToggleFileExplorerFocus => {
+                self.toggle_panel_focus(PanelKind::FileExplorer);
+            }
+            ToggleProblemFocus => {
+                self.toggle_panel_focus(PanelKind::Problem);
+            }
+            ToggleSearchFocus => {
+                self.toggle_panel_focus(PanelKind::Search);
+            }
+            ToggleOutlineFocus => {
+                self.toggle_panel_focus(PanelKind::DocumentSymbol);
+            }
+            ToggleTerminalVisual => {
+                self.toggle_panel_visual(PanelKind::Terminal);
+            }
+            ToggleSourceControlVisual => {
+                self.toggle_panel_visual(PanelKind::SourceControl);
+            }
+            TogglePluginVisual => {
+                self.toggle_panel_visual(PanelKind::Plugin);
+            }
+            ToggleFileExplorerVisual => {
+                self.toggle_panel_visual(PanelKind::FileExplorer);
+            }
+            ToggleProblemVisual => {
+                self.toggle_panel_visual(PanelKind::Problem);
+            }
+            ToggleDebugVisual => {
+                self.toggle_panel_visual(PanelKind::Debug);
+            }
+            ToggleSearchVisual => {
+                self.toggle_panel_visual(PanelKind::Search);
+            }
+            ToggleOutlineVisual => {
+                self.toggle_panel_visual(PanelKind::DocumentSymbol);
+            }
+            ToggleUndoTreeVisual => {
+                self.toggle_panel_visual(PanelKind::UndoTree);
+            }
+            UndoTreeOlder => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.doc().step_undo_tree(true);
+                }
+            }
+            UndoTreeNewer => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    editor.doc().step_undo_tree(false);
+                }
+            }
+            ToggleBookmarksVisual => {
+                self.toggle_panel_visual(PanelKind::Bookmarks);
+            }
+            ToggleBookmark => {
+                if let Some((path, line)) = self.active_editor_line() {
+                    self.common
+                        .bookmarks
+                        .update(|bookmarks| {
+                            bookmarks.toggle(&path, line);
+                        });
+                }
+            }
+            AddLabeledBookmark => {
+                if self.active_editor_line().is_some() {
+                    self.palette.run(PaletteKind::BookmarkLabel);
+                }
+            }
+            NextBookmark => {
+                self.go_to_next_bookmark(true);
+            }
+            PreviousBookmark => {
+                self.go_to_next_bookmark(false);
+            }
+            ClearBookmarks => {
+                self.common.bookmarks.update(|bookmarks| bookmarks.clear());
+            }
+            PaletteBookmarks => {
+                self.palette.run(PaletteKind::Bookmark);
+            }
+            ToggleMacroRecording => {
+                let macros = self.common.keypress.with_untracked(|k| k.macros);
+                if macros.with_untracked(|m| m.recording().is_some()) {
+                    macros.update(|m| {
+                        m.stop();
+                    });
+                } else {
+                    macros.update(|m| m.start(DEFAULT_MACRO.to_string()));
+                }
+            }
+            ReplayMacro => {
+                self.replay_macro(None, 1);
+            }
+            PaletteMacros => {
+                self.palette.run(PaletteKind::Macro);
+            }
+            RecordMacroRegister => {
+                let keypress = self.common.keypress.get_untracked();
+                if keypress.macros.with_untracked(|m| m.recording().is_some()) {
+                    keypress.macros.update(|m| {
+                        m.stop();
+                    });
+                } else {
+                    keypress
+                        .macro_register
+                        .set(Some(MacroRegisterAction::Record));
+                }
+            }
+            ReplayMacroRegister => {
+                self.common.keypress.with_untracked(|k| {
+                    k.macro_register
+                        .set(Some(MacroRegisterAction::Replay { count: 1 }))
+                });
+            }
+            FocusEditor => {
+                self.common.focus.set(Focus::Workbench);
+            }
+            FocusTerminal => {
+                self.common.focus.set(Focus::Panel(PanelKind::Terminal));
+            }
+            FocusNextPanel => {
+                self.cycle_panel_focus(true);
+            }
+            FocusPreviousPanel => {
+                self.cycle_panel_focus(false);
+            }
+            // Handled by the focused panel
+            ShowContextMenu => {}
             OpenUIInspector => {
                 self.common.view_id.get_untracked().inspect();
             }
+            ToggleZenMode => {
+                self.toggle_zen_mode();
+            }
+            TogglePerformanceOverlay => {
+                self.show_perf_overlay.update(|shown| *shown = !*shown);
+            }
             ShowEnvironment => {
                 self.main_split.show_env();
             }
@@ -1490,6 +2226,26 @@ pub fn run_workbench_command(
                     }
                 }
             }
+            FormatSelection => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    editor_data.format_selection();
+                }
+            }
+            BlockSelectUp | BlockSelectDown | BlockSelectLeft | BlockSelectRight => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    let (lines, columns) = match cmd {
+                        BlockSelectUp => (-1, 0),
+                        BlockSelectDown => (1, 0),
+                        BlockSelectLeft => (0, -1),
+                        _ => (0, 1),
+                    };
+                    editor_data.grow_block_selection(lines, columns);
+                }
+            }
             ShowCallHierarchy => {
                 if let Some(editor_data) =
                     self.main_split.active_editor.get_untracked()
@@ -1511,6 +2267,20 @@ pub fn run_workbench_command(
                     editor_data.go_to_implementation(self.clone());
                 }
             }
+            PeekDefinition => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    self.peek.peek(editor_data, PeekKind::Definition);
+                }
+            }
+            PeekReferences => {
+                if let Some(editor_data) =
+                    self.main_split.active_editor.get_untracked()
+                {
+                    self.peek.peek(editor_data, PeekKind::References);
+                }
+            }
             RunInTerminal => {
                 if let Some(editor_data) =
                     self.main_split.active_editor.get_untracked()
@@ -1534,6 +2304,9 @@ pub fn run_workbench_command(
                             cwd: None,
                             env: None,
                             prelaunch: None,
+                            request: Default::default(),
+                            adapter: None,
+                            arguments: Default::default(),
                             debug_command: None,
                             dap_id: Default::default(),
                             tracing_output: false,
@@ -1666,7 +2439,7 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                 let send_current_path = current_path.clone();
                 let send_new_path = new_path.clone();
                 let file_explorer = self.file_explorer.clone();
-                let editors = self.main_split.editors;
+                let main_split = self.main_split.clone();
 
                 let send = create_ext_action(
                     self.scope,
@@ -1682,43 +2455,10 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                                     send_new_path
                                 };
 
-                            // If the renamed item is a file, update any editors the file is open
-                            // in to use the new path.
-                            // If the renamed item is a directory, update any editors in which a
-                            // file the renamed directory is an ancestor of is open to use the
-                            // file's new path.
-                            let renamed_editors_content: Vec<_> = editors
-                                .with_editors_untracked(|editors| {
-                                    editors
-                                        .values()
-                                        .map(|editor| editor.doc().content)
-                                        .filter(|content| {
-                                            content.with_untracked(|content| {
-                                                match content {
-                                                    DocContent::File {
-                                                        path,
-                                                        ..
-                                                    } => path.starts_with(
-                                                        &send_current_path,
-                                                    ),
-                                                    _ => false,
-                                                }
-                                            })
-                                        })
-                                        .collect()
-                                });
-
-                            for content in renamed_editors_content {
-                                content.update(|content| {
-                                    if let DocContent::File { path, .. } = content {
-                                        if let Ok(suffix) =
-                                            path.strip_prefix(&send_current_path)
-                                        {
-                                            *path = new_path.join(suffix);
-                                        }
-                                    }
-                                });
-                            }
+                            // Update the docs of the renamed file, or of the files
+                            // under the renamed directory, to use their new paths.
+                            main_split
+                                .update_renamed_path(&send_current_path, &new_path);
 
                             file_explorer.reload();
                             file_explorer.naming.set(Naming::None);
@@ -1841,14 +2581,21 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
                     kind,
                 );
             }
+            InternalCommand::EditorTabChildPin {
+                editor_tab_id,
+                child,
+                pinned,
+            } => {
+                self.main_split
+                    .editor_tab_child_pin(editor_tab_id, child, pinned);
+            }
             InternalCommand::ShowCodeActions {
                 offset,
                 mouse_click,
-                plugin_id,
                 code_actions,
             } => {
                 let mut code_action = self.code_action.get_untracked();
-                code_action.show(plugin_id, code_actions, offset, mouse_click);
+                code_action.show(code_actions, offset, mouse_click);
                 self.code_action.set(code_action);
             }
             InternalCommand::RunCodeAction { plugin_id, action } => {
@@ -1868,6 +2615,30 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
             InternalCommand::NewTerminal { profile } => {
                 self.terminal.new_tab(profile);
             }
+            InternalCommand::FocusTerminal { term_id } => {
+                self.show_panel(PanelKind::Terminal);
+                self.terminal.focus_terminal(term_id);
+            }
+            InternalCommand::SaveTerminalLayout { name } => {
+                let db: Arc<LapceDb> = use_context().unwrap();
+                let mut layouts =
+                    db.get_terminal_layouts(&self.workspace).unwrap_or_default();
+                let layout = self.terminal.layout(name);
+                match layouts.iter_mut().find(|l| l.name == layout.name) {
+                    Some(existing) => *existing = layout,
+                    None => layouts.push(layout),
+                }
+                db.save_terminal_layouts(&self.workspace, layouts);
+            }
+            InternalCommand::RestoreTerminalLayout { name } => {
+                let db: Arc<LapceDb> = use_context().unwrap();
+                let layouts =
+                    db.get_terminal_layouts(&self.workspace).unwrap_or_default();
+                if let Some(layout) = layouts.iter().find(|l| l.name == name) {
+                    self.show_panel(PanelKind::Terminal);
+                    self.terminal.restore_layout(layout);
+                }
+            }
             InternalCommand::SplitTerminal { term_id } => {
                 self.terminal.split(term_id);
             }
@@ -1986,29 +2757,204 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
             InternalCommand::SaveScratchDoc { doc } => {
                 self.main_split.save_scratch_doc(doc);
             }
-            InternalCommand::SaveScratchDoc2 { doc } => {
-                self.main_split.save_scratch_doc2(doc);
+            InternalCommand::SaveScratchDoc2 { doc } => {
+                self.main_split.save_scratch_doc2(doc);
+            }
+            InternalCommand::UpdateProxyStatus { status } => {
+                self.common.proxy_status.set(Some(status));
+            }
+            InternalCommand::DapFrameScopes { dap_id, frame_id } => {
+                self.terminal.dap_frame_scopes(dap_id, frame_id);
+            }
+            InternalCommand::OpenVoltView { volt_id } => {
+                self.main_split.open_volt_view(volt_id);
+            }
+            InternalCommand::ResetBlinkCursor => {
+                // All the editors share the blinking information and logic, so we can just reset
+                // one of them.
+                if let Some(e_data) = self.main_split.active_editor.get_untracked() {
+                    e_data.editor.cursor_info.reset();
+                }
+            }
+            InternalCommand::OpenDiffFiles {
+                left_path,
+                right_path,
+            } => self.main_split.open_diff_files(left_path, right_path),
+            InternalCommand::NewFileFromTemplate { path, template } => {
+                self.main_split.new_file_from_template(path, &template);
+            }
+            InternalCommand::FindInFolder { path } => {
+                let roots: Vec<PathBuf> = self
+                    .workspace
+                    .path
+                    .iter()
+                    .cloned()
+                    .chain(self.workspace_folders.get_untracked())
+                    .collect();
+                self.global_search.set_scope_to_folder(&path, &roots);
+                self.show_panel(PanelKind::Search);
+            }
+            InternalCommand::RemoveWorkspaceFolder { path } => {
+                self.remove_workspace_folder(&path);
+            }
+            InternalCommand::SaveFiles { paths } => {
+                for path in paths {
+                    let doc = self
+                        .main_split
+                        .docs
+                        .with_untracked(|docs| docs.get(&path).cloned());
+                    // Saving a pristine document doesn't report a result.
+                    let Some(doc) = doc.filter(|doc| !doc.is_pristine()) else {
+                        continue;
+                    };
+                    // Save through an editor of the document, so it's formatted
+                    // and cleaned up like when it's saved from the editor.
+                    let editor =
+                        self.main_split.editors.with_editors_untracked(|editors| {
+                            editors
+                                .values()
+                                .find(|editor| Rc::ptr_eq(&editor.doc(), &doc))
+                                .cloned()
+                        });
+                    // Otherwise a temporary one, disposed of once it's saved.
+                    let (editor, scope) = match editor {
+                        Some(editor) => (editor, None),
+                        None => {
+                            let scope = self.scope.create_child();
+                            let editor = EditorData::new_doc(
+                                scope,
+                                doc,
+                                None,
+                                None,
+                                None,
+                                self.common.clone(),
+                            );
+                            (editor, Some(scope))
+                        }
+                    };
+                    let notifications = self.common.notifications.clone();
+                    editor.save_with_result(true, move |result| {
+                        if let Some(scope) = scope {
+                            scope.dispose();
+                        }
+                        if let Err(err) = result {
+                            notifications.notify(
+                                "Save Failed",
+                                &ShowMessageParams {
+                                    typ: MessageType::ERROR,
+                                    message: format!(
+                                        "{}: {}",
+                                        path.display(),
+                                        err.message
+                                    ),
+                                },
+                            );
+                        }
+                    });
+                }
+            }
+            InternalCommand::ReopenWithEncoding { path, encoding } => {
+                let doc = self
+                    .main_split
+                    .docs
+                    .with_untracked(|docs| docs.get(&path).cloned());
+                let Some(doc) = doc else {
+                    return;
+                };
+                if !doc.is_pristine() {
+                    self.show_message(
+                        "Reopen with Encoding",
+                        &ShowMessageParams {
+                            typ: MessageType::WARNING,
+                            message: format!(
+                                "{} has unsaved changes, save or revert them first",
+                                path.display()
+                            ),
+                        },
+                    );
+                    return;
+                }
+                let notifications = self.common.notifications.clone();
+                doc.reopen_with_encoding(encoding, move |err| {
+                    notifications.notify(
+                        "Reopen Failed",
+                        &ShowMessageParams {
+                            typ: MessageType::ERROR,
+                            message: format!("{}: {}", path.display(), err.message),
+                        },
+                    );
+                });
+            }
+            InternalCommand::SaveWithEncoding { path, encoding } => {
+                let doc = self
+                    .main_split
+                    .docs
+                    .with_untracked(|docs| docs.get(&path).cloned());
+                let Some(doc) = doc else {
+                    return;
+                };
+                let notifications = self.common.notifications.clone();
+                doc.save_with_encoding(encoding, move |result| {
+                    if let Err(err) = result {
+                        notifications.notify(
+                            "Save Failed",
+                            &ShowMessageParams {
+                                typ: MessageType::ERROR,
+                                message: format!(
+                                    "{}: {}",
+                                    path.display(),
+                                    err.message
+                                ),
+                            },
+                        );
+                    }
+                });
+            }
+            InternalCommand::RevertFile { path } => {
+                self.main_split.revert_file(path);
+            }
+            InternalCommand::OpenLocalVersion { path, timestamp } => {
+                self.main_split.open_local_version(path, timestamp);
+            }
+            InternalCommand::RestoreLocalVersion { path, timestamp } => {
+                self.main_split.restore_local_version(path, timestamp);
+            }
+            InternalCommand::RunExCommand { command } => {
+                if let Some(editor) = self.main_split.active_editor.get_untracked() {
+                    if let Err(message) = editor.run_ex_command(&command) {
+                        self.show_message(
+                            &format!(":{}", command.trim()),
+                            &ShowMessageParams {
+                                typ: MessageType::ERROR,
+                                message,
+                            },
+                        );
+                    }
+                }
+            }
+            InternalCommand::RunTask { task } => {
+                self.task.run(task);
+                self.panel.show_panel(&PanelKind::Task);
             }
-            InternalCommand::UpdateProxyStatus { status } => {
-                self.common.proxy_status.set(Some(status));
+            InternalCommand::RevealTaskOutput { link } => {
+                if self.task.reveal_output(&link) {
+                    self.show_panel(PanelKind::Task);
+                }
             }
-            InternalCommand::DapFrameScopes { dap_id, frame_id } => {
-                self.terminal.dap_frame_scopes(dap_id, frame_id);
+            InternalCommand::AddBookmark { label } => {
+                if let Some((path, line)) = self.active_editor_line() {
+                    let label = Some(label).filter(|label| !label.is_empty());
+                    self.common
+                        .bookmarks
+                        .update(|bookmarks| bookmarks.set(&path, line, label));
+                }
             }
-            InternalCommand::OpenVoltView { volt_id } => {
-                self.main_split.open_volt_view(volt_id);
+            InternalCommand::ReplayMacro { name, count } => {
+                self.replay_macro(name.as_deref(), count);
             }
-            InternalCommand::ResetBlinkCursor => {
-                // All the editors share the blinking information and logic, so we can just reset
-                // one of them.
-                if let Some(e_data) = self.main_split.active_editor.get_untracked() {
-                    e_data.editor.cursor_info.reset();
-                }
+            InternalCommand::OpenCommit { rev } => {
+                self.main_split.open_commit(rev);
             }
-            InternalCommand::OpenDiffFiles {
-                left_path,
-                right_path,
-            } => self.main_split.open_diff_files(left_path, right_path),
             InternalCommand::ExecuteProcess { program, arguments } => {
                 let mut cmd = match std::process::Command::new(program)
                     .args(arguments)
@@ -2062,6 +3008,10 @@ pub fn run_internal_command(&self, cmd: InternalCommand) {
             InternalCommand::StopTerminal { term_id } => {
                 self.terminal.stop_run_debug(term_id);
             }
+            InternalCommand::DuplicateTerminal { term_id } => {
+                self.terminal.duplicate_terminal(term_id);
+                self.common.focus.set(Focus::Panel(PanelKind::Terminal));
+            }
             InternalCommand::RestartTerminal { term_id } => {
                 if let Some(is_debug) = self.terminal.restart_run_debug(term_id) {
                     self.panel.show_panel(&PanelKind::Terminal);
@@ -2082,7 +3032,15 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
         let cx = self.scope;
         match rpc {
             CoreNotification::ProxyStatus { status } => {
+                let reconnected = matches!(status, ProxyStatus::Connected)
+                    && matches!(
+                        self.common.proxy_status.get_untracked(),
+                        Some(ProxyStatus::Disconnected)
+                    );
                 self.common.proxy_status.set(Some(status.to_owned()));
+                if reconnected {
+                    self.terminal.reattach();
+                }
             }
             CoreNotification::DiffInfo { diff } => {
                 self.source_control.branch.set(diff.head.clone());
@@ -2116,9 +3074,16 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
                 input,
                 resp,
                 plugin_id,
+                priority,
             } => {
                 self.common.completion.update(|completion| {
-                    completion.receive(*request_id, input, resp, *plugin_id);
+                    completion.receive(
+                        *request_id,
+                        input,
+                        resp,
+                        *plugin_id,
+                        *priority,
+                    );
                 });
 
                 let completion = self.common.completion.get_untracked();
@@ -2132,19 +3097,22 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
                         .update_document_completion(&editor_data, cursor_offset);
                 }
             }
-            CoreNotification::PublishDiagnostics { diagnostics } => {
+            CoreNotification::SignatureHelpResponse {
+                request_id, resp, ..
+            } => {
+                self.common.signature.receive(*request_id, resp);
+            }
+            CoreNotification::PublishDiagnostics {
+                diagnostics,
+                plugin_id,
+            } => {
                 let path = path_from_url(&diagnostics.uri);
-                let diagnostics: im::Vector<Diagnostic> = diagnostics
-                    .diagnostics
-                    .clone()
-                    .into_iter()
-                    .sorted_by_key(|d| d.range.start)
-                    .collect();
-
                 self.main_split
                     .get_diagnostic_data(&path)
-                    .diagnostics
-                    .set(diagnostics);
+                    .set_source_diagnostics(
+                        DiagnosticSource::Plugin(*plugin_id),
+                        diagnostics.diagnostics.clone(),
+                    );
 
                 // inform the document about the diagnostics
                 if let Some(doc) = self
@@ -2190,6 +3158,30 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
                     self.common.focus.set(Focus::Workbench);
                 }
             }
+            CoreNotification::TaskOutput { task_id, id, line } => {
+                self.task.output(*task_id, *id, line.clone());
+            }
+            CoreNotification::TaskDiagnostic {
+                task_id,
+                path,
+                diagnostic,
+            } => {
+                self.task
+                    .diagnostic(*task_id, path.clone(), diagnostic.clone());
+            }
+            CoreNotification::TaskExited {
+                task_id,
+                exit_code,
+                error,
+            } => {
+                self.task.exited(*task_id, *exit_code, error.clone());
+            }
+            CoreNotification::TerminalCwd { term_id, cwd } => {
+                self.terminal.set_cwd(term_id, cwd);
+            }
+            CoreNotification::TerminalProcessUsage { term_id, usage } => {
+                self.terminal.set_process_usage(term_id, usage.clone());
+            }
             CoreNotification::TerminalLaunchFailed { term_id, error } => {
                 self.terminal.launch_failed(term_id, error);
             }
@@ -2215,9 +3207,19 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
             CoreNotification::OpenPaths { paths } => {
                 self.open_paths(paths);
             }
+            CoreNotification::OpenDiffFiles {
+                left_path,
+                right_path,
+            } => {
+                self.main_split
+                    .open_diff_files(left_path.clone(), right_path.clone());
+            }
             CoreNotification::DapContinued { dap_id } => {
                 self.terminal.dap_continued(dap_id);
             }
+            CoreNotification::DapOutput { dap_id, output } => {
+                self.terminal.dap_output(dap_id, output);
+            }
             CoreNotification::DapBreakpointsResp {
                 path, breakpoints, ..
             } => {
@@ -2265,8 +3267,26 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
             CoreNotification::VoltRemoved { volt, .. } => {
                 self.plugin.volt_removed(volt);
             }
-            CoreNotification::WorkDoneProgress { progress } => {
-                self.update_progress(progress);
+            CoreNotification::WorkDoneProgress {
+                plugin_id,
+                name,
+                progress,
+            } => {
+                self.update_progress(*plugin_id, name, progress);
+            }
+            CoreNotification::LanguageServerStopped { plugin_id } => {
+                let mut stopped = Vec::new();
+                self.progresses.update(|p| {
+                    p.retain(|(id, _), progress| {
+                        if id == plugin_id {
+                            stopped.push(progress.notification);
+                        }
+                        id != plugin_id
+                    });
+                });
+                for notification in stopped {
+                    self.common.notifications.end_progress(notification, None);
+                }
             }
             CoreNotification::ShowMessage { title, message } => {
                 self.show_message(title, message);
@@ -2300,7 +3320,6 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
                 }
             }
             CoreNotification::LogMessage { message, target } => {
-                use lsp_types::MessageType;
                 use tracing_log::log::{log, Level};
                 match message.typ {
                     MessageType::ERROR => {
@@ -2321,6 +3340,13 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
                     _ => {}
                 }
             }
+            CoreNotification::LanguageServerOutput {
+                volt_id,
+                name,
+                message,
+            } => {
+                self.output.receive(volt_id.clone(), name.clone(), message);
+            }
             CoreNotification::WorkspaceFileChange => {
                 self.file_explorer.reload();
             }
@@ -2328,7 +3354,110 @@ fn handle_core_notification(&self, rpc: &CoreNotification) {
         }
     }
 
+    /// Stop the language servers of the file of the active editor, or all of
+    /// them without one, and start them again when `restart`.
+    fn stop_language_servers(&self, restart: bool) {
+        let path =
+            self.main_split
+                .active_editor
+                .get_untracked()
+                .and_then(|editor| {
+                    editor
+                        .doc()
+                        .content
+                        .with_untracked(|content| content.path().cloned())
+                });
+        self.common.proxy.stop_language_servers(path, restart);
+    }
+
+    /// The path of the file of the active editor and the line of its cursor.
+    fn active_editor_line(&self) -> Option<(PathBuf, usize)> {
+        let editor = self.main_split.active_editor.get_untracked()?;
+        let doc = editor.doc();
+        let path = doc
+            .content
+            .with_untracked(|content| content.path().cloned())?;
+        let offset = editor.cursor().with_untracked(|cursor| cursor.offset());
+        let line = doc
+            .buffer
+            .with_untracked(|buffer| buffer.line_of_offset(offset));
+        Some((path, line))
+    }
+
+    /// Jump to the bookmark after the cursor of the active editor, or before
+    /// it when not `forward`.
+    fn go_to_next_bookmark(&self, forward: bool) {
+        let (path, line) = self.active_editor_line().unwrap_or_default();
+        let next = self
+            .common
+            .bookmarks
+            .with_untracked(|bookmarks| bookmarks.next(&path, line, forward));
+        if let Some((path, line)) = next {
+            self.main_split.jump_to_location(
+                EditorLocation {
+                    path,
+                    position: Some(EditorPosition::Line(line)),
+                    scroll_offset: None,
+                    ignore_unconfirmed: false,
+                    same_editor_tab: false,
+                },
+                None,
+            );
+        }
+    }
+
+    /// Replay the recorded macro `name`, or the last one, `count` times in the
+    /// active editor. The commands it doesn't handle run on the workbench.
+    fn replay_macro(&self, name: Option<&str>, count: usize) {
+        let keypress = self.common.keypress.get_untracked();
+        let Some((_, steps)) = keypress
+            .macros
+            .try_update(|m| m.take_for_replay(name))
+            .flatten()
+        else {
+            return;
+        };
+        if !keypress
+            .macros
+            .try_update(|m| m.begin_replay())
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        for _ in 0..count {
+            for step in &steps {
+                let editor = self.main_split.active_editor.get_untracked();
+                match step {
+                    MacroStep::Command {
+                        command,
+                        count,
+                        mods,
+                    } => {
+                        let Some(command) = keypress.commands.get(command) else {
+                            continue;
+                        };
+                        let executed = editor
+                            .map(|editor| editor.run_command(command, *count, *mods))
+                            .unwrap_or(CommandExecuted::No);
+                        if executed == CommandExecuted::No {
+                            KeyPressFocus::run_command(self, command, *count, *mods);
+                        }
+                    }
+                    MacroStep::Char(c) => {
+                        if let Some(editor) = editor {
+                            editor.receive_char(c);
+                        }
+                    }
+                }
+            }
+        }
+
+        keypress.macros.update(|m| m.end_replay());
+    }
+
     pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
+        perf::record_input();
         if self.alert_data.active.get_untracked() {
             return false;
         }
@@ -2343,6 +3472,7 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
             }
             Focus::Rename => Some(keypress.key_down(event, &self.rename)),
             Focus::AboutPopup => Some(keypress.key_down(event, &self.about_data)),
+            Focus::Peek => Some(keypress.key_down(event, &self.peek)),
             Focus::Panel(PanelKind::Terminal) => {
                 self.terminal.key_down(event, &keypress)
             }
@@ -2355,6 +3485,18 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
             Focus::Panel(PanelKind::SourceControl) => {
                 Some(keypress.key_down(event, &self.source_control))
             }
+            Focus::Panel(PanelKind::FileExplorer) => {
+                Some(keypress.key_down(event, &self.file_explorer))
+            }
+            Focus::Panel(PanelKind::Problem) => {
+                Some(keypress.key_down(event, &self.problem))
+            }
+            Focus::Panel(PanelKind::Debug) => {
+                Some(keypress.key_down(event, &self.debug_console))
+            }
+            Focus::Panel(PanelKind::DocumentSymbol) => {
+                Some(keypress.key_down(event, &self.outline))
+            }
             _ => None,
         };
 
@@ -2375,6 +3517,192 @@ pub fn key_down<'a>(&self, event: impl Into<EventRef<'a>> + Copy) -> bool {
         }
     }
 
+    /// Hide the panels, bars and tab headers around the editors, or bring
+    /// back the layout they had.
+    pub fn toggle_zen_mode(&self) {
+        let window_maximized =
+            self.common.window_common.window_maximized.get_untracked();
+        let maximize = self.common.config.get_untracked().ui.zen_mode_maximize;
+        if let Some(zen_mode) = self.zen_mode.get_untracked() {
+            self.panel.styles.set(zen_mode.panel_styles);
+            if maximize && !zen_mode.window_maximized && window_maximized {
+                floem::action::set_window_maximized(false);
+            }
+            self.zen_mode.set(None);
+        } else {
+            let panel_styles = self.panel.styles.get_untracked();
+            self.panel.styles.update(|styles| {
+                for style in styles.values_mut() {
+                    style.shown = false;
+                }
+            });
+            if maximize && !window_maximized {
+                floem::action::set_window_maximized(true);
+            }
+            self.zen_mode.set(Some(ZenMode {
+                panel_styles,
+                window_maximized,
+            }));
+            if matches!(self.common.focus.get_untracked(), Focus::Panel(_)) {
+                self.common.focus.set(Focus::Workbench);
+            }
+        }
+    }
+
+    /// Install the grammar of the language of the active editor from its
+    /// configured source, and highlight the documents of the language with it.
+    fn install_grammar(&self) {
+        let Some(editor) = self.main_split.active_editor.get_untracked() else {
+            return;
+        };
+        let language = editor.doc().syntax.with_untracked(|s| s.language);
+        if language == LapceLanguage::PlainText {
+            self.show_message(
+                "Install Grammar",
+                &ShowMessageParams {
+                    typ: MessageType::WARNING,
+                    message: "The file has no language, add it to \
+                              [core.grammars] with the extensions of its files"
+                        .to_string(),
+                },
+            );
+            return;
+        }
+        let config = self.common.config.get_untracked();
+        let Some(source) = config
+            .core
+            .grammar_source(language.as_ref(), &language.grammar_name())
+        else {
+            self.show_message(
+                "Install Grammar",
+                &ShowMessageParams {
+                    typ: MessageType::WARNING,
+                    message: format!(
+                        "No grammar source is set for {}",
+                        language.name()
+                    ),
+                },
+            );
+            return;
+        };
+
+        let window_tab_data = self.clone();
+        let send =
+            create_ext_action(self.scope, move |result: anyhow::Result<()>| {
+                if let Err(err) = result {
+                    window_tab_data.show_message(
+                        "Install Grammar",
+                        &ShowMessageParams {
+                            typ: MessageType::ERROR,
+                            message: format!("{err}"),
+                        },
+                    );
+                } else {
+                    window_tab_data.reload_syntax(language);
+                }
+            });
+        std::thread::Builder::new()
+            .name("InstallGrammar".to_owned())
+            .spawn(move || {
+                send(install_grammar(language, &source));
+            })
+            .unwrap();
+    }
+
+    /// Highlight the open documents with the language their path now has,
+    /// after the `file-associations` setting changed.
+    fn update_associated_languages(&self) {
+        for (path, doc) in self.main_split.docs.get_untracked() {
+            let language = LapceLanguage::from_path(&path);
+            if doc.syntax.with_untracked(|s| s.language) != language {
+                doc.set_language(language);
+                doc.trigger_syntax_change(None);
+            }
+        }
+    }
+
+    /// Parse the documents of `language` again with its grammar and queries,
+    /// after they were installed.
+    fn reload_syntax(&self, language: LapceLanguage) {
+        reset_highlight_configs();
+        for (_, doc) in self.main_split.docs.get_untracked() {
+            if doc.syntax.with_untracked(|s| s.language) == language {
+                doc.syntax.update(|syntax| {
+                    *syntax = Syntax::from_language(language);
+                });
+                doc.trigger_syntax_change(None);
+            }
+        }
+    }
+
+    /// Zoom in or out by one step, or reset the zoom with `None`, resizing
+    /// what the `zoom-scope` setting chooses.
+    fn zoom(&self, zoom_in: Option<bool>) {
+        let config = self.common.config.get_untracked();
+        match config.ui.zoom_scope {
+            ZoomScope::Window => {
+                let scale = match zoom_in {
+                    Some(zoom_in) => {
+                        let scale =
+                            self.common.window_common.window_scale.get_untracked();
+                        let step = if zoom_in { 0.1 } else { -0.1 };
+                        (scale + step).clamp(0.1, 4.0)
+                    }
+                    None => 1.0,
+                };
+                self.common.window_common.window_scale.set(scale);
+
+                LapceConfig::update_file(
+                    "ui",
+                    "scale",
+                    toml_edit::Value::from(scale),
+                );
+            }
+            ZoomScope::Editor => {
+                // Only the zoom is written, so that Reset Zoom goes back to
+                // the configured font size
+                let zoom = match zoom_in {
+                    Some(zoom_in) => font_size_zoom(
+                        config.editor.font_size(),
+                        config.editor.font_size_zoom,
+                        zoom_in,
+                    ),
+                    None => 0,
+                };
+                // The config file is watched
+                LapceConfig::update_file(
+                    "editor",
+                    "font-size-zoom",
+                    toml_edit::Value::from(zoom),
+                );
+            }
+            ZoomScope::Terminal => {
+                let zoom = match zoom_in {
+                    Some(zoom_in) => font_size_zoom(
+                        config.terminal_font_size(),
+                        config.terminal.font_size_zoom,
+                        zoom_in,
+                    ),
+                    None => 0,
+                };
+                LapceConfig::update_file(
+                    "terminal",
+                    "font-size-zoom",
+                    toml_edit::Value::from(zoom),
+                );
+            }
+        }
+    }
+
+    /// Remove a folder added to the workspace, and tell the language servers.
+    pub fn remove_workspace_folder(&self, path: &Path) {
+        self.workspace_folders
+            .update(|folders| folders.retain(|f| f != path));
+        self.common.proxy.update_workspace_folders(
+            self.workspace_folders.get_untracked().into_iter().collect(),
+        );
+    }
+
     pub fn workspace_info(&self) -> WorkspaceInfo {
         let main_split_data = self
             .main_split
@@ -2383,9 +3711,13 @@ pub fn workspace_info(&self) -> WorkspaceInfo {
             .get(&self.main_split.root_split)
             .cloned()
             .unwrap();
+        let mut panel = self.panel.panel_info();
+        if let Some(zen_mode) = self.zen_mode.get_untracked() {
+            panel.styles = zen_mode.panel_styles;
+        }
         WorkspaceInfo {
             split: main_split_data.get_untracked().split_info(self),
-            panel: self.panel.panel_info(),
+            panel,
             breakpoints: self
                 .terminal
                 .debug
@@ -2396,9 +3728,70 @@ pub fn workspace_info(&self) -> WorkspaceInfo {
                     (path, breakpoints.into_values().collect::<Vec<_>>())
                 })
                 .collect(),
+            bookmarks: self.common.bookmarks.get_untracked(),
+            folders: self.workspace_folders.get_untracked().into_iter().collect(),
+            watches: self
+                .terminal
+                .debug
+                .watches
+                .get_untracked()
+                .into_iter()
+                .collect(),
         }
     }
 
+    /// The title of the window while this workspace is the active one, rendered
+    /// from the `core.window-title` template.
+    pub fn window_title(&self) -> String {
+        let template = self
+            .common
+            .config
+            .with(|config| config.core.window_title.clone());
+        let (file, dirty) = match self.main_split.active_editor.get() {
+            Some(editor) => {
+                let doc = editor.doc_signal().get();
+                let file = doc.content.with(|content| match content {
+                    DocContent::File { path, .. } => path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    DocContent::Scratch { name, .. } => name.clone(),
+                    DocContent::Local | DocContent::History(_) => String::new(),
+                });
+                (file, !doc.buffer.with(|b| b.is_pristine()))
+            }
+            None => (String::new(), false),
+        };
+        let workspace = self
+            .workspace
+            .path
+            .as_ref()
+            .map(|path| {
+                path.file_name()
+                    .unwrap_or(path.as_os_str())
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .unwrap_or_default();
+        let remote = match &self.workspace.kind {
+            LapceWorkspaceType::Local => String::new(),
+            LapceWorkspaceType::RemoteSSH(remote) => {
+                format!(" [SSH: {}]", remote.host)
+            }
+            #[cfg(windows)]
+            LapceWorkspaceType::RemoteWSL(remote) => {
+                format!(" [WSL: {}]", remote.host)
+            }
+        };
+        render_window_title(
+            &template,
+            &workspace,
+            &remote,
+            &file,
+            if dirty { "\u{25CF} " } else { "" },
+        )
+    }
+
     pub fn hover_origin(&self) -> Option<Point> {
         if !self.common.hover.active.get_untracked() {
             return None;
@@ -2443,6 +3836,47 @@ pub fn hover_origin(&self) -> Option<Point> {
         Some(origin)
     }
 
+    /// Where to show the signature help, above the start of the arguments of
+    /// the call, or below it if there's no room above.
+    pub fn signature_origin(&self) -> Option<Point> {
+        let signature = &self.common.signature;
+        if !signature.is_shown() {
+            return None;
+        }
+        let editor_data =
+            self.main_split.editors.editor(signature.editor_id.get())?;
+        let (window_origin, viewport, editor) = (
+            editor_data.window_origin(),
+            editor_data.viewport(),
+            &editor_data.editor,
+        );
+        let (point_above, point_below) =
+            editor.points_of_offset(signature.offset.get(), CursorAffinity::Forward);
+
+        let window_origin =
+            window_origin.get() - self.common.window_origin.get().to_vec2();
+        let viewport = viewport.get();
+        let signature_size = signature.layout_rect.get().size();
+        let tab_size = self.layout_rect.get().size();
+
+        let mut origin = window_origin
+            + Vec2::new(
+                point_below.x - viewport.x0,
+                (point_above.y - viewport.y0) - signature_size.height,
+            );
+        if origin.y < 0.0 {
+            origin.y = window_origin.y + point_below.y - viewport.y0;
+        }
+        if origin.x + signature_size.width + 1.0 > tab_size.width {
+            origin.x = tab_size.width - signature_size.width - 1.0;
+        }
+        if origin.x <= 0.0 {
+            origin.x = 0.0;
+        }
+
+        Some(origin)
+    }
+
     pub fn completion_origin(&self) -> Point {
         let completion = self.common.completion.get();
         if completion.status == CompletionStatus::Inactive {
@@ -2629,21 +4063,25 @@ pub fn toggle_panel_visual(&self, kind: PanelKind) {
     /// Toggle a specific kind of panel.
     fn toggle_panel_focus(&self, kind: PanelKind) {
         let should_hide = match kind {
-            PanelKind::FileExplorer
-            | PanelKind::Plugin
-            | PanelKind::Problem
-            | PanelKind::Debug
+            PanelKind::Debug
             | PanelKind::CallHierarchy
-            | PanelKind::DocumentSymbol
             | PanelKind::References
-            | PanelKind::Implementation => {
+            | PanelKind::Implementation
+            | PanelKind::UndoTree
+            | PanelKind::Bookmarks
+            | PanelKind::Output
+            | PanelKind::Task => {
                 // Some panels don't accept focus (yet). Fall back to visibility check
                 // in those cases.
                 self.panel.is_panel_visible(&kind)
             }
-            PanelKind::Terminal | PanelKind::SourceControl | PanelKind::Search => {
-                self.is_panel_focused(kind)
-            }
+            PanelKind::Terminal
+            | PanelKind::SourceControl
+            | PanelKind::Search
+            | PanelKind::FileExplorer
+            | PanelKind::Plugin
+            | PanelKind::Problem
+            | PanelKind::DocumentSymbol => self.is_panel_focused(kind),
         };
         if should_hide {
             self.hide_panel(kind);
@@ -2689,6 +4127,42 @@ fn toggle_container_visual(&self, position: &PanelContainerPosition) {
         }
     }
 
+    /// Move the focus to the next (or previous) visible panel, going through
+    /// the editors after the last one.
+    fn cycle_panel_focus(&self, forward: bool) {
+        let mut stops = vec![Focus::Workbench];
+        for position in [
+            PanelPosition::LeftTop,
+            PanelPosition::LeftBottom,
+            PanelPosition::BottomLeft,
+            PanelPosition::BottomRight,
+            PanelPosition::RightTop,
+            PanelPosition::RightBottom,
+        ] {
+            if let Some((kind, true)) =
+                self.panel.active_panel_at_position(&position, false)
+            {
+                stops.push(Focus::Panel(kind));
+            }
+        }
+
+        let focus = self.common.focus.get_untracked();
+        let current = stops.iter().position(|f| *f == focus).unwrap_or(0);
+        let next = if forward {
+            (current + 1) % stops.len()
+        } else {
+            (current + stops.len() - 1) % stops.len()
+        };
+        self.common.focus.set(stops[next].clone());
+        // The panels get their keys through the app view, so take the
+        // keyboard back from any text input that had it
+        self.common
+            .window_common
+            .app_view_id
+            .get_untracked()
+            .request_focus();
+    }
+
     fn is_panel_focused(&self, kind: PanelKind) -> bool {
         // Moving between e.g. Search and Problems doesn't affect focus, so we need to also check
         // visibility.
@@ -2745,6 +4219,14 @@ fn run_and_debug(
             RunDebugMode::Debug => {
                 if config.prelaunch.is_some() {
                     self.run_in_terminal(cx, mode, config, false);
+                } else if config.request == DebugRequest::Attach {
+                    // The debugger won't ask for a terminal to run the program
+                    // in, so the session gets a shell of its own.
+                    self.run_in_terminal(cx, mode, config, true);
+                    self.common.proxy.dap_start(
+                        config.clone(),
+                        self.terminal.debug.source_breakpoints(),
+                    )
                 } else {
                     self.common.proxy.dap_start(
                         config.clone(),
@@ -2799,7 +4281,13 @@ fn run_in_terminal(
         self.terminal.debug.daps.update(|daps| {
             daps.insert(
                 config.dap_id,
-                DapData::new(cx, config.dap_id, term_id, self.common.clone()),
+                DapData::new(
+                    cx,
+                    config.dap_id,
+                    term_id,
+                    self.terminal.debug.watches,
+                    self.common.clone(),
+                ),
             );
         });
 
@@ -2856,42 +4344,76 @@ pub fn show_alert(&self, title: String, msg: String, buttons: Vec<AlertButton>)
         self.alert_data.active.set(true);
     }
 
-    fn update_progress(&self, progress: &ProgressParams) {
-        let token = progress.token.clone();
+    fn update_progress(
+        &self,
+        plugin_id: PluginId,
+        name: &str,
+        progress: &ProgressParams,
+    ) {
+        let token = (plugin_id, progress.token.clone());
         match &progress.value {
             lsp_types::ProgressParamsValue::WorkDone(progress) => match progress {
                 lsp_types::WorkDoneProgress::Begin(progress) => {
+                    let cancel = progress.cancellable.unwrap_or(false).then(|| {
+                        let proxy = self.common.proxy.clone();
+                        let token = token.1.clone();
+                        Rc::new(move || {
+                            proxy.lsp_cancel_progress(plugin_id, token.clone());
+                        }) as Rc<dyn Fn()>
+                    });
+                    let notification = self.common.notifications.start_progress(
+                        format!("{name}: {}", progress.title),
+                        progress.message.clone(),
+                        progress.percentage,
+                        cancel,
+                    );
                     let progress = WorkProgress {
-                        token: token.clone(),
+                        token: token.1.clone(),
+                        name: name.to_string(),
                         title: progress.title.clone(),
                         message: progress.message.clone(),
                         percentage: progress.percentage,
+                        notification,
                     };
                     self.progresses.update(|p| {
                         p.insert(token, progress);
                     });
                 }
                 lsp_types::WorkDoneProgress::Report(report) => {
+                    let mut notification = None;
                     self.progresses.update(|p| {
                         if let Some(progress) = p.get_mut(&token) {
                             progress.message.clone_from(&report.message);
                             progress.percentage = report.percentage;
+                            notification = Some(progress.notification);
                         }
-                    })
+                    });
+                    if let Some(notification) = notification {
+                        self.common.notifications.report_progress(
+                            notification,
+                            report.message.clone(),
+                            report.percentage,
+                        );
+                    }
                 }
-                lsp_types::WorkDoneProgress::End(_) => {
+                lsp_types::WorkDoneProgress::End(end) => {
+                    let mut ended = None;
                     self.progresses.update(|p| {
-                        p.swap_remove(&token);
+                        ended = p.swap_remove(&token);
                     });
+                    if let Some(progress) = ended {
+                        self.common.notifications.end_progress(
+                            progress.notification,
+                            end.message.clone(),
+                        );
+                    }
                 }
             },
         }
     }
 
     fn show_message(&self, title: &str, message: &ShowMessageParams) {
-        self.messages.update(|messages| {
-            messages.push((title.to_string(), message.clone()));
-        });
+        self.common.notifications.notify(title, message);
     }
 
     pub fn update_code_lens_id(&self, view_id: Option<ViewId>) {
@@ -2918,11 +4440,13 @@ pub fn show_code_lens(
             .send(InternalCommand::ShowCodeActions {
                 offset,
                 mouse_click,
-                plugin_id,
                 code_actions: lens
                     .into_iter()
                     .filter_map(|lens| {
-                        Some(CodeActionOrCommand::Command(lens.command?))
+                        Some((
+                            plugin_id,
+                            CodeActionOrCommand::Command(lens.command?),
+                        ))
                     })
                     .collect(),
             });
@@ -2993,3 +4517,69 @@ fn open_uri(path: &Path) {
         }
     }
 }
+
+/// List the variables of a terminal as `KEY=value` lines, the live ones of
+/// the foreground process after the ones the shell was spawned with.
+fn format_terminal_environment(
+    title: &str,
+    environment: &TerminalEnvironment,
+) -> String {
+    let mut text = format!("# Environment of {title}\n\n# Spawned with\n");
+    if !environment.from_process {
+        text.push_str(
+            "# (as given to the shell, without the variables set up for the \
+             pty, since the environment of processes is only read on Linux)\n",
+        );
+    }
+    for (key, value) in &environment.spawned {
+        text.push_str(&format!("{key}={value}\n"));
+    }
+    if let Some(foreground) = environment.foreground.as_ref() {
+        text.push_str(&format!(
+            "\n# Foreground process: {} ({})\n",
+            foreground.name, foreground.pid
+        ));
+        for (key, value) in &foreground.variables {
+            text.push_str(&format!("{key}={value}\n"));
+        }
+    }
+    text
+}
+
+/// Fill in the variables of a window title template, dropping the `" - "`
+/// separated segments that end up empty, such as the file when no editor is
+/// open.
+fn render_window_title(
+    template: &str,
+    workspace: &str,
+    remote: &str,
+    file: &str,
+    dirty: &str,
+) -> String {
+    let title = template
+        .replace("${workspace}", workspace)
+        .replace("${remote}", remote)
+        .replace("${file}", file)
+        .replace("${dirty}", dirty);
+    let title = title
+        .split(" - ")
+        .filter(|segment| !segment.trim().is_empty())
+        .join(" - ");
+    if title.is_empty() {
+        "Lapce".to_string()
+    } else {
+        title
+    }
+}
+
+/// The zoom that makes the font one step bigger or smaller than
+/// `font_size`, which is the configured size with `zoom` applied, within the
+/// sizes the editor and terminal fonts are clamped to.
+fn font_size_zoom(font_size: usize, zoom: i64, zoom_in: bool) -> i64 {
+    let zoomed = if zoom_in {
+        (font_size + 1).min(32)
+    } else {
+        font_size.saturating_sub(1).max(6)
+    };
+    zoom + zoomed as i64 - font_size as i64
+}
@@ -2,7 +2,10 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::{debug::LapceBreakpoint, main_split::SplitInfo, panel::data::PanelInfo};
+use crate::{
+    bookmark::Bookmarks, debug::LapceBreakpoint, main_split::SplitInfo,
+    panel::data::PanelInfo,
+};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct SshHost {
@@ -156,4 +159,12 @@ pub struct WorkspaceInfo {
     pub split: SplitInfo,
     pub panel: PanelInfo,
     pub breakpoints: HashMap<PathBuf, Vec<LapceBreakpoint>>,
+    #[serde(default)]
+    pub bookmarks: Bookmarks,
+    /// The folders added to the workspace besides its root.
+    #[serde(default)]
+    pub folders: Vec<PathBuf>,
+    /// The expressions of the watch view of the debug panel.
+    #[serde(default)]
+    pub watches: Vec<String>,
 }
@@ -23,12 +23,14 @@ fn main() -> Result<()> {
     let meta_file = Path::new(&env::var("OUT_DIR")?).join("meta.rs");
 
     let ReleaseInfo { version, branch } = release_info;
+    let target = env::var("TARGET")?;
 
     #[rustfmt::skip]
     let meta = format!(r#"
         pub const NAME: &str = "Lapce-{branch}";
         pub const VERSION: &str = "{version}";
         pub const RELEASE: ReleaseType = ReleaseType::{branch};
+        pub const TARGET: &str = "{target}";
     "#);
 
     fs::write(meta_file, meta)?;
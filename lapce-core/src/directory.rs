@@ -94,6 +94,84 @@ pub fn proxy_directory() -> Option<PathBuf> {
             None
         }
     }
+    /// Directory for user defined new file templates
+    pub fn templates_directory() -> Option<PathBuf> {
+        if let Some(dir) = Self::data_local_directory() {
+            let dir = dir.join("templates");
+            if !dir.exists() {
+                if let Err(err) = std::fs::create_dir(&dir) {
+                    tracing::error!("{:?}", err);
+                }
+            }
+            Some(dir)
+        } else {
+            None
+        }
+    }
+
+    /// Directory for user defined snippets
+    /// Snippets are stored within as json files named after their language
+    pub fn snippets_directory() -> Option<PathBuf> {
+        if let Some(dir) = Self::data_local_directory() {
+            let dir = dir.join("snippets");
+            if !dir.exists() {
+                if let Err(err) = std::fs::create_dir(&dir) {
+                    tracing::error!("{:?}", err);
+                }
+            }
+            Some(dir)
+        } else {
+            None
+        }
+    }
+
+    /// Directory for hunspell dictionaries used by the spell checker
+    /// Each dictionary is a pair of `.dic` and `.aff` files named after it
+    pub fn dictionaries_directory() -> Option<PathBuf> {
+        if let Some(dir) = Self::data_local_directory() {
+            let dir = dir.join("dictionaries");
+            if !dir.exists() {
+                if let Err(err) = std::fs::create_dir(&dir) {
+                    tracing::error!("{:?}", err);
+                }
+            }
+            Some(dir)
+        } else {
+            None
+        }
+    }
+
+    /// Directory for the saved versions of the files kept by the local history
+    pub fn local_history_directory() -> Option<PathBuf> {
+        if let Some(dir) = Self::data_local_directory() {
+            let dir = dir.join("local-history");
+            if !dir.exists() {
+                if let Err(err) = std::fs::create_dir(&dir) {
+                    tracing::error!("{:?}", err);
+                }
+            }
+            Some(dir)
+        } else {
+            None
+        }
+    }
+
+    /// Directory for the backups of the files deleted by workspace edits,
+    /// which undoing the edits restores
+    pub fn backups_directory() -> Option<PathBuf> {
+        if let Some(dir) = Self::data_local_directory() {
+            let dir = dir.join("backups");
+            if !dir.exists() {
+                if let Err(err) = std::fs::create_dir(&dir) {
+                    tracing::error!("{:?}", err);
+                }
+            }
+            Some(dir)
+        } else {
+            None
+        }
+    }
+
     /// Get the path to the themes folder
     /// Themes are stored within as individual toml files
     pub fn themes_directory() -> Option<PathBuf> {
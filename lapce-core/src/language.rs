@@ -1,14 +1,17 @@
 use std::{
     collections::{hash_map::Entry, HashMap, HashSet},
-    fmt::Write,
+    fmt::{self, Write},
     path::Path,
     str::FromStr,
+    sync::RwLock,
 };
 
+use globset::{Glob, GlobMatcher};
+use include_dir::{include_dir, Dir};
 use lapce_rpc::style::{LineStyle, Style};
 use once_cell::sync::Lazy;
 use regex::Regex;
-use strum_macros::{AsRefStr, Display, EnumMessage, EnumString, IntoStaticStr};
+use strum_macros::{EnumMessage, EnumString, IntoStaticStr};
 use tracing::{event, Level};
 use tree_sitter::{Point, TreeCursor};
 
@@ -47,6 +50,10 @@ const fn as_str(&self) -> &'static str {
     }
 }
 
+/// The queries shipped with Lapce, for what the grammars don't come with.
+const DEFAULT_QUERIES_DIR: Dir =
+    include_dir!("$CARGO_MANIFEST_DIR/../defaults/queries");
+
 const DEFAULT_CODE_GLANCE_LIST: &[&str] = &["source_file"];
 const DEFAULT_CODE_GLANCE_IGNORE_LIST: &[&str] = &["source_file"];
 
@@ -82,14 +89,14 @@ macro_rules! comment_properties {
             multi_line_prefix: None,
         }
     };
-    ($sl_s:expr, $sl_e:expr, $ml_s:expr, $ml_e:expr) => {
+    ($sl_s:expr, $ml_s:expr, $ml_e:expr) => {
         CommentProperties {
             single_line_start: Some($sl_s),
-            single_line_end: Some($sl_e),
+            single_line_end: None,
 
-            multi_line_start: Some($sl_s),
-            multi_line_end: None,
-            multi_line_prefix: Some($sl_e),
+            multi_line_start: Some($ml_s),
+            multi_line_end: Some($ml_e),
+            multi_line_prefix: None,
         }
     };
 }
@@ -115,6 +122,40 @@ pub struct SyntaxProperties {
     tree_sitter: TreeSitterProperties,
 }
 
+/// A glob pattern of the `file-associations` setting, and the language of the
+/// files it matches.
+struct FileAssociation {
+    glob: GlobMatcher,
+    /// Whether the pattern has a `/`, and is matched against the whole path
+    /// instead of the file name.
+    whole_path: bool,
+    language: LapceLanguage,
+}
+
+static FILE_ASSOCIATIONS: RwLock<Vec<FileAssociation>> = RwLock::new(Vec::new());
+
+/// A language that isn't built in, registered at runtime.
+struct CustomLanguage {
+    name: &'static str,
+    properties: SyntaxProperties,
+}
+
+/// The languages registered at runtime, which `LapceLanguage::Custom` indexes
+/// into. They are leaked as the languages are `Copy` and live as long as the
+/// editor, and are only replaced when their settings change.
+static CUSTOM_LANGUAGES: RwLock<Vec<&'static CustomLanguage>> =
+    RwLock::new(Vec::new());
+
+fn leak_str(string: &str) -> &'static str {
+    Box::leak(Box::<str>::from(string))
+}
+
+fn leak_strs(strings: &[String]) -> &'static [&'static str] {
+    let strings: Vec<&'static str> =
+        strings.iter().map(|string| leak_str(string)).collect();
+    Box::leak(strings.into_boxed_slice())
+}
+
 #[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, PartialOrd, Ord, Default)]
 struct TreeSitterProperties {
     /// the grammar name that's in the grammars folder
@@ -167,8 +208,9 @@ struct CommentProperties {
     multi_line_prefix: Option<&'static str>,
 }
 
-/// NOTE: Keep the enum variants "fieldless" so they can cast to usize as array
-/// indices into the LANGUAGES array.  See method `LapceLanguage::properties`.
+/// NOTE: Keep the enum variants "fieldless", but for `Custom`, and in the
+/// order of the LANGUAGES array, which is searched for their properties.  See
+/// method `LapceLanguage::properties`.
 ///
 /// Do not assign values to the variants because the number of variants and
 /// number of elements in the LANGUAGES array change as different features
@@ -182,8 +224,6 @@ struct CommentProperties {
     Clone,
     Copy,
     Debug,
-    Display,
-    AsRefStr,
     IntoStaticStr,
     EnumString,
     EnumMessage,
@@ -444,6 +484,26 @@ pub enum LapceLanguage {
     Yaml,
     #[strum(message = "Zig")]
     Zig,
+
+    /// A language that isn't built in, registered at runtime with
+    /// `LapceLanguage::register`.
+    #[remain::unsorted]
+    Custom(u16),
+}
+
+impl AsRef<str> for LapceLanguage {
+    fn as_ref(&self) -> &str {
+        match self {
+            LapceLanguage::Custom(_) => self.name(),
+            _ => self.into(),
+        }
+    }
+}
+
+impl fmt::Display for LapceLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_ref())
+    }
 }
 
 /// NOTE: Elements in the array must be in the same order as the enum variants of
@@ -544,7 +604,7 @@ pub enum LapceLanguage {
         indent: Indent::space(4),
         files: &[],
         extensions: &["c", "h"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: None,
             grammar_fn: None,
@@ -597,7 +657,7 @@ pub enum LapceLanguage {
         indent: Indent::space(4),
         files: &[],
         extensions: &["cpp", "cxx", "cc", "c++", "hpp", "hxx", "hh", "h++"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: None,
             grammar_fn: None,
@@ -757,23 +817,23 @@ pub enum LapceLanguage {
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
-        id: LapceLanguage::FSharp,
+        id: LapceLanguage::Fish,
         indent: Indent::tab(),
         files: &[],
-        extensions: &[],
+        extensions: &["fish"],
         comment: comment_properties!(),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
-        id: LapceLanguage::Fish,
+        id: LapceLanguage::Fluent,
         indent: Indent::tab(),
         files: &[],
-        extensions: &["fish"],
+        extensions: &[],
         comment: comment_properties!(),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
-        id: LapceLanguage::Fluent,
+        id: LapceLanguage::Forth,
         indent: Indent::tab(),
         files: &[],
         extensions: &[],
@@ -781,7 +841,7 @@ pub enum LapceLanguage {
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
-        id: LapceLanguage::Forth,
+        id: LapceLanguage::Fortran,
         indent: Indent::tab(),
         files: &[],
         extensions: &[],
@@ -789,7 +849,7 @@ pub enum LapceLanguage {
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
-        id: LapceLanguage::Fortran,
+        id: LapceLanguage::FSharp,
         indent: Indent::tab(),
         files: &[],
         extensions: &[],
@@ -854,7 +914,7 @@ pub enum LapceLanguage {
             "tese", "mesh", "task", "rgen", "rint", "rahit", "rchit", "rmiss",
             "rcall",
         ],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -870,7 +930,7 @@ pub enum LapceLanguage {
         indent: Indent::tab(),
         files: &[],
         extensions: &["go"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: None,
             grammar_fn: None,
@@ -941,7 +1001,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["hs"],
-        comment: comment_properties!("--"),
+        comment: comment_properties!("--", "{-", "-}"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -949,7 +1009,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["hx"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -957,7 +1017,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["hcl", "tf"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -989,7 +1049,7 @@ pub enum LapceLanguage {
         indent: Indent::space(4),
         files: &[],
         extensions: &["java"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -997,7 +1057,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["js", "cjs", "mjs"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: None,
             grammar_fn: None,
@@ -1043,7 +1103,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["jsx"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: Some("javascript"),
             grammar_fn: Some("javascript"),
@@ -1142,7 +1202,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["lua"],
-        comment: comment_properties!("--"),
+        comment: comment_properties!("--", "--[[", "]]"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -1283,7 +1343,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["php"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: None,
             grammar_fn: None,
@@ -1348,7 +1408,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["proto"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -1381,7 +1441,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["ql"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -1451,7 +1511,7 @@ pub enum LapceLanguage {
         indent: Indent::space(4),
         files: &[],
         extensions: &["rs"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: None,
             grammar_fn: None,
@@ -1489,7 +1549,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["scss"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -1513,7 +1573,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["sql"],
-        comment: comment_properties!("--"),
+        comment: comment_properties!("--", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -1553,7 +1613,7 @@ pub enum LapceLanguage {
         indent: Indent::space(2),
         files: &[],
         extensions: &["swift"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -1577,7 +1637,7 @@ pub enum LapceLanguage {
         indent: Indent::space(4),
         files: &[],
         extensions: &["tsx"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: Some("tsx"),
             grammar_fn: Some("tsx"),
@@ -1591,7 +1651,7 @@ pub enum LapceLanguage {
         indent: Indent::space(4),
         files: &[],
         extensions: &["ts", "cts", "mts"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties {
             grammar: Some("typescript"),
             grammar_fn: Some("typescript"),
@@ -1637,7 +1697,7 @@ pub enum LapceLanguage {
         indent: Indent::space(4),
         files: &[],
         extensions: &["wgsl"],
-        comment: comment_properties!("//"),
+        comment: comment_properties!("//", "/*", "*/"),
         tree_sitter: TreeSitterProperties::DEFAULT,
     },
     SyntaxProperties {
@@ -1675,21 +1735,32 @@ pub enum LapceLanguage {
 ];
 
 impl LapceLanguage {
-    const HIGHLIGHTS_INJECTIONS_FILE_NAME: &'static str = "injections.scm";
-    const HIGHLIGHTS_QUERIES_FILE_NAME: &'static str = "highlights.scm";
+    pub const HIGHLIGHTS_INJECTIONS_FILE_NAME: &'static str = "injections.scm";
+    pub const HIGHLIGHTS_QUERIES_FILE_NAME: &'static str = "highlights.scm";
+    pub const TEXTOBJECTS_QUERIES_FILE_NAME: &'static str = "textobjects.scm";
 
     pub fn from_path(path: &Path) -> LapceLanguage {
         Self::from_path_raw(path).unwrap_or(LapceLanguage::PlainText)
     }
 
     pub fn from_path_raw(path: &Path) -> Option<LapceLanguage> {
+        if let Some(language) = Self::from_file_association(path) {
+            return Some(language);
+        }
         let filename = path.file_name().and_then(|s| s.to_str());
         let extension = path
             .extension()
             .and_then(|s| s.to_str().map(|s| s.to_lowercase()));
+        // The languages registered take precedence over the built-in ones
+        // with the same extensions.
+        let custom = CUSTOM_LANGUAGES.read().ok();
+        let custom = custom
+            .iter()
+            .flat_map(|languages| languages.iter())
+            .map(|language| &language.properties);
         // NOTE: This is a linear search.  It is assumed that this function
         // isn't called in any tight loop.
-        for properties in LANGUAGES {
+        for properties in custom.chain(LANGUAGES) {
             if properties.files.iter().any(|f| Some(*f) == filename) {
                 return Some(properties.id);
             }
@@ -1707,12 +1778,196 @@ pub fn from_path_raw(path: &Path) -> Option<LapceLanguage> {
 
     pub fn from_name(name: &str) -> Option<LapceLanguage> {
         match LapceLanguage::from_str(name.to_lowercase().as_str()) {
+            Ok(LapceLanguage::Custom(_)) => Self::from_custom_name(name),
             Ok(v) => Some(v),
             Err(e) => {
-                event!(Level::DEBUG, "failed parsing `{name}` LapceLanguage: {e}");
-                None
+                let language = Self::from_custom_name(name);
+                if language.is_none() {
+                    event!(
+                        Level::DEBUG,
+                        "failed parsing `{name}` LapceLanguage: {e}"
+                    );
+                }
+                language
+            }
+        }
+    }
+
+    /// The language registered at runtime with the name `name`, in any case.
+    fn from_custom_name(name: &str) -> Option<LapceLanguage> {
+        let languages = CUSTOM_LANGUAGES.read().ok()?;
+        languages
+            .iter()
+            .find(|language| language.name.eq_ignore_ascii_case(name.trim()))
+            .map(|language| language.properties.id)
+    }
+
+    /// Register the language `name` that isn't built in, for the files with
+    /// the extensions `extensions` or the names `files`, with the single line
+    /// comment token `comment`. Its grammar and queries are loaded from the
+    /// grammars and queries directories, under its name in lowercase.  
+    /// Returns the built-in language when `name` is one, which can't be
+    /// redefined.
+    pub fn register(
+        name: &str,
+        extensions: &[String],
+        files: &[String],
+        comment: Option<&str>,
+    ) -> LapceLanguage {
+        if let Ok(language) = LapceLanguage::from_str(&name.to_lowercase()) {
+            if !matches!(language, LapceLanguage::Custom(_)) {
+                return language;
+            }
+        }
+
+        let Ok(mut languages) = CUSTOM_LANGUAGES.write() else {
+            return LapceLanguage::PlainText;
+        };
+        let index = languages
+            .iter()
+            .position(|language| language.name.eq_ignore_ascii_case(name))
+            .unwrap_or(languages.len());
+        let id = LapceLanguage::Custom(index as u16);
+        if let Some(language) = languages.get(index) {
+            let properties = &language.properties;
+            if properties
+                .extensions
+                .iter()
+                .copied()
+                .eq(extensions.iter().map(String::as_str))
+                && properties
+                    .files
+                    .iter()
+                    .copied()
+                    .eq(files.iter().map(String::as_str))
+                && properties.comment.single_line_start == comment
+            {
+                return id;
             }
         }
+
+        let lowercase = leak_str(&name.to_lowercase());
+        let language = Box::leak(Box::new(CustomLanguage {
+            name: leak_str(name),
+            properties: SyntaxProperties {
+                id,
+                comment: CommentProperties {
+                    single_line_start: comment.map(leak_str),
+                    ..comment_properties!()
+                },
+                indent: Indent::space(4),
+                files: leak_strs(files),
+                extensions: leak_strs(extensions),
+                tree_sitter: TreeSitterProperties {
+                    grammar: Some(lowercase),
+                    grammar_fn: Some(lowercase),
+                    query: Some(lowercase),
+                    ..TreeSitterProperties::DEFAULT
+                },
+            },
+        }));
+        if index < languages.len() {
+            languages[index] = language;
+        } else {
+            languages.push(language);
+        }
+        id
+    }
+
+    /// Sets the languages of the files matching the glob patterns of the
+    /// `file-associations` setting, e.g. `"*.inc" = "php"`, which take
+    /// precedence over their file name and extension.
+    pub fn set_file_associations(associations: &HashMap<String, String>) {
+        let mut file_associations = associations
+            .iter()
+            .filter_map(|(pattern, name)| {
+                let Some(language) = LapceLanguage::from_injection_name(name) else {
+                    event!(Level::WARN, "unknown language `{name}` for `{pattern}`");
+                    return None;
+                };
+                match Glob::new(pattern) {
+                    Ok(glob) => Some(FileAssociation {
+                        glob: glob.compile_matcher(),
+                        whole_path: pattern.contains('/'),
+                        language,
+                    }),
+                    Err(e) => {
+                        event!(
+                            Level::WARN,
+                            "invalid file association `{pattern}`: {e}"
+                        );
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        // The patterns on the whole path, then the longest ones, are the most
+        // specific, and win over the others matching the same file.
+        file_associations.sort_by_key(|association| {
+            (
+                !association.whole_path,
+                std::cmp::Reverse(association.glob.glob().glob().len()),
+            )
+        });
+        if let Ok(mut associations) = FILE_ASSOCIATIONS.write() {
+            *associations = file_associations;
+        }
+    }
+
+    /// The language set for the file at `path` by the `file-associations`
+    /// setting.
+    pub fn from_file_association(path: &Path) -> Option<LapceLanguage> {
+        let file_name = path.file_name()?;
+        let associations = FILE_ASSOCIATIONS.read().ok()?;
+        associations
+            .iter()
+            .find(|association| {
+                if association.whole_path {
+                    association.glob.is_match(path)
+                } else {
+                    association.glob.is_match(file_name)
+                }
+            })
+            .map(|association| association.language)
+    }
+
+    /// The language of an injection, as named in the info string of a code
+    /// block in markdown, or in the `injection.language` of a query, which is
+    /// often an extension or an alias rather than the name of the language.
+    pub fn from_injection_name(name: &str) -> Option<LapceLanguage> {
+        let name = name.trim().to_lowercase();
+        let alias = match name.as_str() {
+            "sh" | "shell" | "zsh" | "ksh" => Some(LapceLanguage::Bash),
+            "golang" => Some(LapceLanguage::Go),
+            "py3" | "python3" => Some(LapceLanguage::Python),
+            "node" | "nodejs" => Some(LapceLanguage::Javascript),
+            "patch" => Some(LapceLanguage::Diff),
+            _ => None,
+        };
+        if alias.is_some() {
+            return alias;
+        }
+        match LapceLanguage::from_str(&name) {
+            Ok(LapceLanguage::Custom(_)) => {}
+            Ok(language) => return Some(language),
+            Err(_) => {}
+        }
+        if let Some(language) = Self::from_custom_name(&name) {
+            return Some(language);
+        }
+        LANGUAGES
+            .iter()
+            .find(|properties| {
+                strum::EnumMessage::get_message(&properties.id)
+                    .map(|message| message.to_lowercase() == name)
+                    .unwrap_or(false)
+            })
+            .or_else(|| {
+                LANGUAGES.iter().find(|properties| {
+                    properties.extensions.contains(&name.as_str())
+                })
+            })
+            .map(|properties| properties.id)
     }
 
     pub fn languages() -> Vec<&'static str> {
@@ -1723,14 +1978,32 @@ pub fn languages() -> Vec<&'static str> {
                 langs.push(lang)
             }
         }
+        if let Ok(languages) = CUSTOM_LANGUAGES.read() {
+            langs.extend(languages.iter().map(|language| language.name));
+        }
         langs
     }
 
-    // NOTE: Instead of using `&LANGUAGES[*self as usize]` directly, the
-    // `debug_assertion` gives better feedback should something has gone wrong
-    // badly.
+    /// The language registered at runtime `self` is, if it's one.
+    fn custom(&self) -> Option<&'static CustomLanguage> {
+        let LapceLanguage::Custom(index) = self else {
+            return None;
+        };
+        CUSTOM_LANGUAGES.read().ok()?.get(*index as usize).copied()
+    }
+
+    // NOTE: The LANGUAGES array is in the order of the variants, which the
+    // `debug_assertion` checks, so it can be searched by id.
     fn properties(&self) -> &SyntaxProperties {
-        let i = *self as usize;
+        if let LapceLanguage::Custom(_) = self {
+            return match self.custom() {
+                Some(language) => &language.properties,
+                None => &LANGUAGES[0],
+            };
+        }
+        let i = LANGUAGES
+            .binary_search_by(|l| l.id.cmp(self))
+            .unwrap_or_default();
         let l = &LANGUAGES[i];
         debug_assert!(
             l.id == *self,
@@ -1742,9 +2015,17 @@ fn properties(&self) -> &SyntaxProperties {
     }
 
     pub fn name(&self) -> &'static str {
+        if let Some(language) = self.custom() {
+            return language.name;
+        }
         strum::EnumMessage::get_message(self).unwrap_or(self.into())
     }
 
+    /// The file name extensions of the language, as `["rs"]` for rust.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        self.properties().extensions
+    }
+
     pub fn sticky_header_tags(&self) -> &[&'static str] {
         self.properties().tree_sitter.sticky_headers
     }
@@ -1756,6 +2037,23 @@ pub fn comment_token(&self) -> &'static str {
             .unwrap_or_default()
     }
 
+    /// The tokens that open and close a block comment, if the language has them.
+    pub fn block_comment_tokens(&self) -> Option<(&'static str, &'static str)> {
+        match self.properties().comment {
+            CommentProperties {
+                multi_line_start: Some(start),
+                multi_line_end: Some(end),
+                ..
+            }
+            | CommentProperties {
+                single_line_start: Some(start),
+                single_line_end: Some(end),
+                ..
+            } => Some((start, end)),
+            _ => None,
+        }
+    }
+
     pub fn indent_unit(&self) -> &str {
         self.properties().indent
     }
@@ -1781,7 +2079,9 @@ fn get_grammar(&self) -> Option<tree_sitter::Language> {
         None
     }
 
-    fn query_name(&self) -> String {
+    /// The name of the folder of the queries of the language in the queries
+    /// directory.
+    pub fn query_name(&self) -> String {
         self.properties()
             .tree_sitter
             .query
@@ -1789,7 +2089,9 @@ fn query_name(&self) -> String {
             .to_lowercase()
     }
 
-    fn grammar_name(&self) -> String {
+    /// The name the grammar library of the language has in the grammars
+    /// directory, as `libtree-sitter-<name>`.
+    pub fn grammar_name(&self) -> String {
         self.properties()
             .tree_sitter
             .grammar
@@ -1827,6 +2129,30 @@ fn get_grammar_query(&self) -> (String, String) {
         ("".to_string(), "".to_string())
     }
 
+    /// The textobjects query of the language from the user config dir, or the
+    /// one shipped with Lapce, which is empty when there's none.
+    pub(crate) fn textobjects_query(&self) -> String {
+        let name = self.query_name();
+        if let Some(queries_dir) = Directory::queries_directory() {
+            if queries_dir
+                .join(&name)
+                .join(Self::TEXTOBJECTS_QUERIES_FILE_NAME)
+                .exists()
+            {
+                return read_grammar_query(
+                    &queries_dir,
+                    &name,
+                    Self::TEXTOBJECTS_QUERIES_FILE_NAME,
+                );
+            }
+        }
+        DEFAULT_QUERIES_DIR
+            .get_file(format!("{name}/{}", Self::TEXTOBJECTS_QUERIES_FILE_NAME))
+            .and_then(|file| file.contents_utf8())
+            .unwrap_or_default()
+            .to_string()
+    }
+
     pub(crate) fn new_highlight_config(
         &self,
     ) -> Result<HighlightConfiguration, HighlightIssue> {
@@ -1964,7 +2290,7 @@ pub(crate) fn walk_tree_bracket_ast(
     level: &mut usize,
     counter: &mut usize,
     bracket_pos: &mut HashMap<usize, Vec<LineStyle>>,
-    palette: &Vec<String>,
+    palette: &[String],
 ) {
     if cursor.node().kind().ends_with('(')
         || cursor.node().kind().ends_with('{')
@@ -2042,7 +2368,10 @@ fn read_grammar_query(queries_dir: &Path, name: &str, kind: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+    };
 
     use super::LapceLanguage;
 
@@ -2051,4 +2380,101 @@ fn test_lanaguage_from_path() {
         let l = LapceLanguage::from_path(&PathBuf::new().join("test.rs"));
         assert_eq!(l, LapceLanguage::Rust);
     }
+
+    #[test]
+    fn test_language_from_injection_name() {
+        for (name, language) in [
+            ("rust", LapceLanguage::Rust),
+            ("rs", LapceLanguage::Rust),
+            ("C++", LapceLanguage::Cpp),
+            ("C#", LapceLanguage::Csharp),
+            ("sh", LapceLanguage::Bash),
+            ("golang", LapceLanguage::Go),
+            ("js", LapceLanguage::Javascript),
+            ("py", LapceLanguage::Python),
+        ] {
+            assert_eq!(
+                LapceLanguage::from_injection_name(name),
+                Some(language),
+                "{name}"
+            );
+        }
+        assert_eq!(LapceLanguage::from_injection_name("not-a-language"), None);
+    }
+
+    #[test]
+    fn test_language_from_file_association() {
+        let associations = HashMap::from([
+            ("*.inc".to_string(), "php".to_string()),
+            ("Jenkinsfile".to_string(), "groovy".to_string()),
+            ("**/templates/*.inc".to_string(), "html".to_string()),
+        ]);
+        LapceLanguage::set_file_associations(&associations);
+        for (path, language) in [
+            ("/project/config.inc", Some(LapceLanguage::Php)),
+            ("/project/templates/page.inc", Some(LapceLanguage::Html)),
+            ("/project/Jenkinsfile", Some(LapceLanguage::Groovy)),
+            ("/project/main.rs", None),
+        ] {
+            assert_eq!(
+                LapceLanguage::from_file_association(Path::new(path)),
+                language,
+                "{path}"
+            );
+        }
+        LapceLanguage::set_file_associations(&HashMap::new());
+    }
+
+    #[test]
+    fn test_language_properties() {
+        for language in [
+            LapceLanguage::PlainText,
+            LapceLanguage::Fish,
+            LapceLanguage::FSharp,
+            LapceLanguage::Rust,
+            LapceLanguage::Zig,
+        ] {
+            assert_eq!(language.properties().id, language);
+        }
+        assert_eq!(LapceLanguage::Rust.as_ref(), "Rust");
+        assert_eq!(LapceLanguage::Rust.to_string(), "Rust");
+        assert_eq!(LapceLanguage::from_name("custom"), None);
+    }
+
+    #[test]
+    fn test_register_language() {
+        let extensions = ["lapcetest".to_string()];
+        let path = Path::new("/project/main.lapcetest");
+        assert_eq!(LapceLanguage::from_path_raw(path), None);
+
+        let language =
+            LapceLanguage::register("LapceTest", &extensions, &[], Some("%"));
+        assert!(matches!(language, LapceLanguage::Custom(_)));
+        assert_eq!(LapceLanguage::from_path(path), language);
+        assert_eq!(LapceLanguage::from_name("lapcetest"), Some(language));
+        assert_eq!(
+            LapceLanguage::from_injection_name("LapceTest"),
+            Some(language)
+        );
+        assert_eq!(language.name(), "LapceTest");
+        assert_eq!(language.as_ref(), "LapceTest");
+        assert_eq!(language.comment_token(), "%");
+        assert_eq!(language.grammar_name(), "lapcetest");
+        assert!(LapceLanguage::languages().contains(&"LapceTest"));
+
+        // Registering it again updates it in place.
+        assert_eq!(
+            LapceLanguage::register("lapcetest", &[], &[], None),
+            language
+        );
+        assert_eq!(LapceLanguage::from_path_raw(path), None);
+        assert_eq!(language.comment_token(), "");
+
+        // The built-in languages can't be redefined.
+        assert_eq!(
+            LapceLanguage::register("Rust", &extensions, &[], None),
+            LapceLanguage::Rust
+        );
+        assert_eq!(LapceLanguage::from_path_raw(path), None);
+    }
 }
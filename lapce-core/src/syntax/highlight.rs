@@ -25,7 +25,7 @@
     Language, Point, Query, QueryCaptures, QueryCursor, QueryMatch, Tree,
 };
 
-use super::{util::RopeProvider, PARSER};
+use super::{textobject::reset_textobject_queries, util::RopeProvider, PARSER};
 use crate::{language::LapceLanguage, style::SCOPES};
 
 thread_local! {
@@ -36,6 +36,8 @@ pub fn reset_highlight_configs() {
     HIGHLIGHT_CONFIGS.with_borrow_mut(|configs| {
         configs.clear();
     });
+    // The textobjects are read from the same queries directory
+    reset_textobject_queries();
 }
 
 pub(crate) fn get_highlight_config(
@@ -50,6 +52,18 @@ pub(crate) fn get_highlight_config(
     })
 }
 
+/// The language `config` was made for, as the config of an injected layer.
+pub(crate) fn language_of_config(
+    config: &Arc<HighlightConfiguration>,
+) -> Option<LapceLanguage> {
+    HIGHLIGHT_CONFIGS.with_borrow(|configs| {
+        configs
+            .iter()
+            .find(|(_, c)| c.as_ref().is_ok_and(|c| Arc::ptr_eq(c, config)))
+            .map(|(language, _)| *language)
+    })
+}
+
 /// Indicates which highlight should be applied to a region of source code.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Highlight(pub usize);
@@ -8,7 +8,7 @@
 
 use std::{
     cell::RefCell,
-    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     hash::{Hash, Hasher},
     mem,
     path::Path,
@@ -46,6 +46,7 @@
 };
 pub mod edit;
 pub mod highlight;
+pub mod textobject;
 pub mod util;
 
 const TREE_SITTER_MATCH_LIMIT: u32 = 256;
@@ -77,305 +78,122 @@ pub enum Error {
     Unknown,
 }
 
-#[derive(Clone, Debug)]
-pub enum NodeType {
-    LeftParen,
-    RightParen,
-    LeftBracket,
-    RightBracket,
-    LeftCurly,
-    RightCurly,
-    Pair,
-    Code,
-    Dummy,
-}
-
-#[derive(Clone, Debug)]
-pub enum BracketParserMode {
-    Parsing,
-    NoParsing,
-}
-
-#[derive(Clone, Debug)]
-pub struct ASTNode {
-    pub tt: NodeType,
-    pub len: usize,
-    pub children: Vec<ASTNode>,
-    pub level: usize,
-}
-
-impl ASTNode {
-    pub fn new() -> Self {
-        Self {
-            tt: NodeType::Dummy,
-            len: 0,
-            children: vec![],
-            level: 0,
-        }
-    }
-
-    pub fn new_with_type(tt: NodeType, len: usize) -> Self {
-        Self {
-            tt,
-            len,
-            children: vec![],
-            level: 0,
-        }
-    }
-}
-
-impl Default for ASTNode {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
+/// The theme colors of the brackets of a document, which rotate through a palette
+/// by nesting depth.
 #[derive(Clone, Debug)]
 pub struct BracketParser {
-    pub code: Vec<char>,
-    pub cur: usize,
-    pub ast: ASTNode,
-    bracket_set: HashMap<char, ASTNode>,
     pub bracket_pos: HashMap<usize, Vec<LineStyle>>,
-    mode: BracketParserMode,
-    noparsing_token: Vec<char>,
     pub active: bool,
     pub limit: u64,
 }
 
 impl BracketParser {
-    pub fn new(code: String, active: bool, limit: u64) -> Self {
+    pub fn new(active: bool, limit: u64) -> Self {
         Self {
-            code: code.chars().collect(),
-            cur: 0,
-            ast: ASTNode::new(),
-            bracket_set: HashMap::from([
-                ('(', ASTNode::new_with_type(NodeType::LeftParen, 1)),
-                (')', ASTNode::new_with_type(NodeType::RightParen, 1)),
-                ('{', ASTNode::new_with_type(NodeType::LeftCurly, 1)),
-                ('}', ASTNode::new_with_type(NodeType::RightCurly, 1)),
-                ('[', ASTNode::new_with_type(NodeType::LeftBracket, 1)),
-                (']', ASTNode::new_with_type(NodeType::RightBracket, 1)),
-            ]),
             bracket_pos: HashMap::new(),
-            mode: BracketParserMode::Parsing,
-            noparsing_token: vec!['\'', '"', '`'],
             active,
             limit,
         }
     }
 
-    /*pub fn enable(&self) {
-        *(self.active.borrow_mut()) = true;
-    }
-
-    pub fn disable(&self) {
-        *(self.active.borrow_mut()) = false;
-    }*/
-
+    /// Recompute the bracket colors from the syntax tree, or from a scan of the text
+    /// when there is no syntax support for the document.
     pub fn update_code(
         &mut self,
-        code: String,
         buffer: &Buffer,
         syntax: Option<&Syntax>,
+        palette: &[String],
     ) {
-        let palette = vec![
-            "bracket.color.1".to_string(),
-            "bracket.color.2".to_string(),
-            "bracket.color.3".to_string(),
-        ];
-        if self.active
-            && code
-                .chars()
-                .fold(0, |i, c| if c == '\n' { i + 1 } else { i })
-                < self.limit as usize
+        self.bracket_pos = HashMap::new();
+        if !self.active
+            || palette.is_empty()
+            || buffer.last_line() as u64 >= self.limit
         {
-            self.bracket_pos = HashMap::new();
-            if let Some(syntax) = syntax {
-                if let Some(layers) = &syntax.layers {
-                    if let Some(tree) = layers.try_tree() {
-                        let mut walk_cursor = tree.walk();
-                        let mut bracket_pos: HashMap<usize, Vec<LineStyle>> =
-                            HashMap::new();
-                        language::walk_tree_bracket_ast(
-                            &mut walk_cursor,
-                            &mut 0,
-                            &mut 0,
-                            &mut bracket_pos,
-                            &palette,
-                        );
-                        self.bracket_pos = bracket_pos;
-                    }
-                }
-            } else {
-                self.code = code.chars().collect();
-                self.cur = 0;
-                self.parse();
-                let mut pos_vec = vec![];
-                Self::highlight_pos(
-                    &self.ast,
-                    &mut pos_vec,
-                    &mut 0usize,
-                    &mut 0usize,
-                    &palette,
+            return;
+        }
+
+        if let Some(syntax) = syntax {
+            if let Some(tree) =
+                syntax.layers.as_ref().and_then(|layers| layers.try_tree())
+            {
+                let mut walk_cursor = tree.walk();
+                language::walk_tree_bracket_ast(
+                    &mut walk_cursor,
+                    &mut 0,
+                    &mut 0,
+                    &mut self.bracket_pos,
+                    palette,
                 );
-                if buffer.is_empty() {
-                    return;
-                }
-                for (offset, color) in pos_vec.iter() {
-                    let (line, col) = buffer.offset_to_line_col(*offset);
-                    let line_style = LineStyle {
-                        start: col,
-                        end: col + 1,
-                        style: Style {
-                            fg_color: Some(color.clone()),
-                        },
-                    };
-                    match self.bracket_pos.entry(line) {
-                        Entry::Vacant(v) => _ = v.insert(vec![line_style.clone()]),
-                        Entry::Occupied(mut o) => {
-                            o.get_mut().push(line_style.clone())
-                        }
-                    }
-                }
             }
         } else {
-            self.bracket_pos = HashMap::new();
-        }
-    }
-
-    fn is_left(c: &char) -> bool {
-        if *c == '(' || *c == '{' || *c == '[' {
-            return true;
+            let text = buffer.to_string();
+            for (offset, color) in scan_brackets(&text, palette) {
+                let (line, col) = buffer.offset_to_line_col(offset);
+                let line_style = LineStyle {
+                    start: col,
+                    end: col + 1,
+                    style: Style {
+                        fg_color: Some(color),
+                    },
+                };
+                self.bracket_pos.entry(line).or_default().push(line_style);
+            }
         }
-        false
-    }
-
-    fn parse(&mut self) {
-        let new_ast = &mut ASTNode::new();
-        self.parse_bracket(0, new_ast);
-        self.ast = new_ast.clone();
-        Self::patch_len(&mut self.ast);
-        self.cur = 0;
     }
+}
 
-    fn parse_bracket(&mut self, level: usize, parent_node: &mut ASTNode) {
-        let mut counter = 0usize;
-        while self.cur < self.code.len() {
-            if self.noparsing_token.contains(&self.code[self.cur]) {
-                if matches!(self.mode, BracketParserMode::Parsing) {
-                    self.mode = BracketParserMode::NoParsing;
-                } else {
-                    self.mode = BracketParserMode::Parsing;
-                }
+/// Find the brackets of `text` that are outside of string and character literals,
+/// with the color of their nesting depth, or `bracket.unpaired` for closing
+/// brackets without an opening one.
+fn scan_brackets(text: &str, palette: &[String]) -> Vec<(usize, String)> {
+    let mut brackets = Vec::new();
+    let mut level = 0usize;
+    let mut chars = text.char_indices();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '(' | '[' | '{' => {
+                brackets.push((offset, palette[level % palette.len()].clone()));
+                level += 1;
             }
-            if self.bracket_set.contains_key(&self.code[self.cur])
-                && matches!(self.mode, BracketParserMode::Parsing)
-            {
-                if Self::is_left(&self.code[self.cur]) {
-                    let code_node = ASTNode::new_with_type(NodeType::Code, counter);
-                    let left_node =
-                        self.bracket_set.get(&self.code[self.cur]).unwrap().clone();
-                    let mut pair_node =
-                        ASTNode::new_with_type(NodeType::Pair, counter + 1);
-                    pair_node.level = level;
-                    pair_node.children.push(code_node);
-                    pair_node.children.push(left_node);
-                    self.cur += 1;
-                    self.parse_bracket(level + 1, &mut pair_node);
-                    parent_node.children.push(pair_node.clone());
-                    counter = 0;
-                } else if level <= parent_node.level {
-                    let code_node = ASTNode::new_with_type(NodeType::Code, counter);
-                    let right_node =
-                        self.bracket_set.get(&self.code[self.cur]).unwrap().clone();
-                    parent_node.children.push(code_node);
-                    parent_node.children.push(right_node);
-                    let parent_len = parent_node.len;
-                    parent_node.len = parent_len + counter + 1;
-                    counter = 0;
-                    self.cur += 1;
+            ')' | ']' | '}' => {
+                if level == 0 {
+                    brackets.push((offset, "bracket.unpaired".to_string()));
                 } else {
-                    let code_node = ASTNode::new_with_type(NodeType::Code, counter);
-                    let right_node =
-                        self.bracket_set.get(&self.code[self.cur]).unwrap().clone();
-                    parent_node.children.push(code_node);
-                    parent_node.children.push(right_node);
-                    let parent_len = parent_node.len;
-                    parent_node.len = parent_len + counter + 1;
-                    self.cur += 1;
-                    return;
+                    level -= 1;
+                    brackets.push((offset, palette[level % palette.len()].clone()));
                 }
-            } else {
-                counter += self.code[self.cur].len_utf8();
-                self.cur += 1;
             }
-        }
-    }
-
-    fn patch_len(ast: &mut ASTNode) {
-        if !ast.children.is_empty() {
-            let mut len = 0usize;
-            for n in ast.children.iter_mut() {
-                match n.tt {
-                    NodeType::LeftCurly => len += 1,
-                    NodeType::RightCurly => len += 1,
-                    NodeType::LeftParen => len += 1,
-                    NodeType::RightParen => len += 1,
-                    NodeType::LeftBracket => len += 1,
-                    NodeType::RightBracket => len += 1,
-                    NodeType::Code => len += n.len,
-                    NodeType::Pair => {
-                        Self::patch_len(n);
-                        len += n.len;
+            '"' | '`' => skip_literal(&mut chars, c),
+            '\'' => {
+                // Only skip what looks like a character literal, so that lifetimes and
+                // apostrophes don't start one.
+                let mut rest = text[offset + 1..].chars();
+                match (rest.next(), rest.next()) {
+                    (Some('\\'), _) => skip_literal(&mut chars, c),
+                    (Some(_), Some('\'')) => {
+                        chars.next();
+                        chars.next();
                     }
-                    _ => break,
+                    _ => {}
                 }
             }
-            ast.len = len;
+            _ => {}
         }
     }
+    brackets
+}
 
-    fn highlight_pos(
-        ast: &ASTNode,
-        pos_vec: &mut Vec<(usize, String)>,
-        index: &mut usize,
-        level: &mut usize,
-        palette: &Vec<String>,
-    ) {
-        if !ast.children.is_empty() {
-            for n in ast.children.iter() {
-                match n.tt {
-                    NodeType::LeftCurly
-                    | NodeType::LeftParen
-                    | NodeType::LeftBracket => {
-                        pos_vec
-                            .push((*index, palette[*level % palette.len()].clone()));
-                        *level += 1;
-                        *index += 1;
-                    }
-                    NodeType::RightCurly
-                    | NodeType::RightParen
-                    | NodeType::RightBracket => {
-                        let (new_level, overflow) = (*level).overflowing_sub(1);
-                        if overflow {
-                            pos_vec.push((*index, "bracket.unpaired".to_string()));
-                        } else {
-                            *level = new_level;
-                            pos_vec.push((
-                                *index,
-                                palette[*level % palette.len()].clone(),
-                            ));
-                        }
-                        *index += 1;
-                    }
-                    NodeType::Code => *index += n.len,
-                    NodeType::Pair => {
-                        Self::highlight_pos(n, pos_vec, index, level, palette)
-                    }
-                    _ => break,
-                }
+/// Advance `chars` past the end of a literal opened by `quote`. Literals other than
+/// backtick ones end at the end of the line even when unterminated.
+fn skip_literal(chars: &mut impl Iterator<Item = (usize, char)>, quote: char) {
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
             }
+            '\n' if quote != '`' => return,
+            c if c == quote => return,
+            _ => {}
         }
     }
 }
@@ -513,12 +331,14 @@ pub fn update(
         let injection_callback = |language: &InjectionLanguageMarker| {
             let language = match language {
                 InjectionLanguageMarker::Name(name) => {
-                    LapceLanguage::from_name(name)
+                    LapceLanguage::from_injection_name(name)
                 }
                 InjectionLanguageMarker::Filename(path) => {
                     LapceLanguage::from_path_raw(path)
                 }
-                InjectionLanguageMarker::Shebang(id) => LapceLanguage::from_name(id),
+                InjectionLanguageMarker::Shebang(id) => {
+                    LapceLanguage::from_injection_name(id)
+                }
             };
             language
                 .map(get_highlight_config)
@@ -819,6 +639,21 @@ pub fn try_tree(&self) -> Option<&Tree> {
         self.layers[self.root].try_tree()
     }
 
+    /// The layers whose ranges hold `offset`, the most deeply injected first.
+    pub fn layers_at(&self, offset: usize) -> Vec<&LanguageLayer> {
+        let mut layers = self
+            .layers
+            .values()
+            .filter(|layer| {
+                layer.ranges.iter().any(|range| {
+                    range.start_byte <= offset && offset <= range.end_byte
+                })
+            })
+            .collect::<Vec<_>>();
+        layers.sort_by_key(|layer| std::cmp::Reverse(layer.depth));
+        layers
+    }
+
     /// Iterate over the highlighted regions for a given slice of source code.
     pub fn highlight_iter<'a>(
         &'a self,
@@ -895,6 +730,55 @@ pub fn highlight_iter<'a>(
     }
 }
 
+/// The kinds of definitions that can be navigated between, told apart by the
+/// names of the tree-sitter nodes, which follow similar conventions across
+/// the grammars.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefinitionKind {
+    /// Functions and methods.
+    Function,
+    /// Classes and the like, as structs, enums, interfaces and modules.
+    Class,
+}
+
+impl DefinitionKind {
+    pub fn matches(self, node_kind: &str) -> bool {
+        let names: &[&str] = match self {
+            DefinitionKind::Function => &["function", "method", "constructor"],
+            DefinitionKind::Class => &[
+                "class",
+                "struct",
+                "enum",
+                "interface",
+                "trait",
+                "impl",
+                "module",
+                "mod",
+                "namespace",
+                "record",
+            ],
+        };
+        // As in `function_definition`, `method_declaration`, `struct_item`,
+        // or `method` and `class` for ruby.
+        let name = ["_definition", "_declaration", "_item"]
+            .iter()
+            .find_map(|suffix| node_kind.strip_suffix(suffix))
+            .unwrap_or(node_kind);
+        names
+            .iter()
+            .any(|n| name == *n || name.ends_with(&format!("_{n}")))
+    }
+}
+
+/// The lines of a node of the syntax tree, or of a run of comments, spanning
+/// several lines, which can be folded up to its last line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyntaxFoldingRange {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub is_comment: bool,
+}
+
 #[derive(Clone)]
 pub struct Syntax {
     pub rev: u64,
@@ -1088,6 +972,57 @@ pub fn parent_offset(&self, offset: usize) -> Option<usize> {
         Some(parent.start_byte())
     }
 
+    /// The range of the smallest node enclosing the range from `start` to `end`
+    /// that is larger than it, for expanding a selection.
+    pub fn expand_range(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        let tree = self.layers.as_ref()?.try_tree()?;
+        let mut node = tree.root_node().descendant_for_byte_range(start, end)?;
+        while node.start_byte() >= start && node.end_byte() <= end {
+            node = node.parent()?;
+        }
+        Some((node.start_byte(), node.end_byte()))
+    }
+
+    /// The start of the first definition of `kind` after `offset`, or of the
+    /// last one before it when not `forward`.
+    pub fn find_definition(
+        &self,
+        offset: usize,
+        kind: DefinitionKind,
+        forward: bool,
+    ) -> Option<usize> {
+        let tree = self.layers.as_ref()?.try_tree()?;
+        let mut cursor = tree.walk();
+        let mut found = None;
+        loop {
+            let node = cursor.node();
+            let start = node.start_byte();
+            if forward && start > offset {
+                if node.is_named() && kind.matches(node.kind()) {
+                    return Some(start);
+                }
+            } else if !forward && start < offset {
+                if node.is_named() && kind.matches(node.kind()) {
+                    found = Some(start);
+                }
+            } else if !forward {
+                // The nodes are visited in the order they start.
+                return found;
+            }
+
+            // The nodes ending before the offset hold nothing after it.
+            let skip_children = forward && node.end_byte() <= offset;
+            if !skip_children && cursor.goto_first_child() {
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return found;
+                }
+            }
+        }
+    }
+
     pub fn find_tag(
         &self,
         offset: usize,
@@ -1150,6 +1085,21 @@ fn find_tag_in_children(&self, node: Node, tag: &str) -> Option<usize> {
         None
     }
 
+    /// Whether JSX markup can be written at `offset`: among the children of a
+    /// JSX element, or starting an expression that is returned, the body of an
+    /// arrow function or in parentheses.
+    pub fn accepts_jsx(&self, offset: usize) -> bool {
+        self.layers
+            .as_ref()
+            .and_then(|layers| layers.try_tree())
+            .and_then(|tree| {
+                tree.root_node().descendant_for_byte_range(offset, offset)
+            })
+            .is_some_and(|node| {
+                is_jsx_children(node) || is_jsx_expression_start(node, offset)
+            })
+    }
+
     pub fn sticky_headers(&self, offset: usize) -> Option<Vec<usize>> {
         let tree = self.layers.as_ref()?.try_tree()?;
         let mut node = tree.root_node().descendant_for_byte_range(offset, offset)?;
@@ -1168,6 +1118,74 @@ pub fn sticky_headers(&self, offset: usize) -> Option<Vec<usize>> {
         Some(offsets)
     }
 
+    /// The ranges of the named nodes with lines to fold between their first
+    /// and last ones, outer nodes first. Comments on consecutive lines are
+    /// folded together.
+    pub fn folding_ranges(&self) -> Vec<SyntaxFoldingRange> {
+        let Some(tree) = self.layers.as_ref().and_then(|layers| layers.try_tree())
+        else {
+            return Vec::new();
+        };
+
+        let mut ranges = Vec::new();
+        let mut comments: Option<(usize, usize)> = None;
+        let mut push = |start_line: usize, end_line: usize, is_comment: bool| {
+            if end_line > start_line + 1 {
+                ranges.push(SyntaxFoldingRange {
+                    start_line,
+                    end_line,
+                    is_comment,
+                });
+            }
+        };
+
+        let mut cursor = tree.walk();
+        let mut visited = false;
+        loop {
+            if !visited {
+                let node = cursor.node();
+                let start = node.start_position().row;
+                // Line comments end at the start of the next line.
+                let end = node.end_position();
+                let end = if end.column == 0 && end.row > start {
+                    end.row - 1
+                } else {
+                    end.row
+                };
+                let is_comment = node.kind().contains("comment");
+                if is_comment {
+                    comments = match comments {
+                        Some((first, last)) if last + 1 == start => {
+                            Some((first, end))
+                        }
+                        Some((first, last)) => {
+                            push(first, last, true);
+                            Some((start, end))
+                        }
+                        None => Some((start, end)),
+                    };
+                } else if node.is_named() && node.parent().is_some() {
+                    push(start, end, false);
+                }
+                if !is_comment && cursor.goto_first_child() {
+                    continue;
+                }
+            }
+            if cursor.goto_next_sibling() {
+                visited = false;
+            } else if cursor.goto_parent() {
+                visited = true;
+            } else {
+                break;
+            }
+        }
+        if let Some((first, last)) = comments {
+            push(first, last, true);
+        }
+        ranges.sort_by_key(|range| range.start_line);
+        ranges
+    }
+
     pub fn find_enclosing_parentheses(
         &self,
         offset: usize,
@@ -1241,6 +1259,43 @@ pub fn find_enclosing_pair(&self, offset: usize) -> Option<(usize, usize)> {
     }
 }
 
+/// Whether `node` is, or is within, the children of a JSX element, rather
+/// than one of its tags or an expression embedded in it.
+fn is_jsx_children(node: Node) -> bool {
+    let mut node = Some(node);
+    while let Some(current) = node {
+        match current.kind() {
+            "jsx_element" | "jsx_fragment" => return true,
+            "jsx_opening_element"
+            | "jsx_closing_element"
+            | "jsx_self_closing_element"
+            | "jsx_expression" => return false,
+            _ => node = current.parent(),
+        }
+    }
+    false
+}
+
+/// Whether `node`, at `offset`, starts an expression where a JSX element is
+/// commonly written: the value returned, the body of an arrow function, or an
+/// expression in parentheses.
+fn is_jsx_expression_start(node: Node, offset: usize) -> bool {
+    let mut node = node;
+    while node.start_byte() == offset {
+        let Some(parent) = node.parent() else {
+            return false;
+        };
+        match parent.kind() {
+            "return_statement" | "parenthesized_expression" => return true,
+            "arrow_function" => {
+                return parent.child_by_field_name("body") == Some(node)
+            }
+            _ => node = parent,
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1277,4 +1332,127 @@ fn test_lens_iter() {
         assert_eq!(Some((90, 25)), iter.next());
         assert_eq!(None, iter.next());
     }
+
+    #[test]
+    fn test_definition_kind() {
+        let function = DefinitionKind::Function;
+        let class = DefinitionKind::Class;
+        for kind in [
+            "function_item",
+            "function_definition",
+            "method_declaration",
+            "generator_function_declaration",
+            "method",
+            "singleton_method",
+        ] {
+            assert!(function.matches(kind), "{kind}");
+            assert!(!class.matches(kind), "{kind}");
+        }
+        for kind in [
+            "struct_item",
+            "impl_item",
+            "class_definition",
+            "record_struct_declaration",
+            "module",
+        ] {
+            assert!(class.matches(kind), "{kind}");
+            assert!(!function.matches(kind), "{kind}");
+        }
+        for kind in [
+            "call_expression",
+            "function_type",
+            "class_body",
+            "identifier",
+        ] {
+            assert!(!function.matches(kind), "{kind}");
+            assert!(!class.matches(kind), "{kind}");
+        }
+    }
+
+    #[test]
+    fn test_scan_brackets() {
+        let palette = vec!["a".to_string(), "b".to_string()];
+        let colors = |text: &str| {
+            scan_brackets(text, &palette)
+                .into_iter()
+                .map(|(offset, color)| format!("{offset}:{color}"))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            colors("f(a[{}])"),
+            ["1:a", "3:b", "4:a", "5:a", "6:b", "7:a"]
+        );
+        assert_eq!(colors(")("), ["0:bracket.unpaired", "1:a"]);
+        // Brackets in literals are skipped, but lifetimes don't start one.
+        assert_eq!(colors(r#"("(" ')')"#), ["0:a", "8:a"]);
+        assert_eq!(colors("f<'a>(x: &'a u8)"), ["5:a", "15:a"]);
+        assert_eq!(colors(r#"("\"(")"#), ["0:a", "6:a"]);
+        // An unterminated string ends with its line.
+        assert_eq!(colors("\"(\n()"), ["3:a", "4:a"]);
+    }
+
+    #[test]
+    fn test_is_jsx_children() {
+        let source = "\
+const a = b;
+function App() {
+    return <div className=\"x\">
+        ul>li
+        {items.map((item) => item)}
+    </div>;
+}
+";
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_javascript::language())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let is_in_jsx_children = |text: &str| {
+            let offset = source.find(text).unwrap();
+            is_jsx_children(
+                tree.root_node()
+                    .descendant_for_byte_range(offset, offset)
+                    .unwrap(),
+            )
+        };
+
+        assert!(is_in_jsx_children("ul>li"));
+        assert!(!is_in_jsx_children("b;"));
+        assert!(!is_in_jsx_children("className"));
+        assert!(!is_in_jsx_children("items"));
+    }
+
+    #[test]
+    fn test_is_jsx_expression_start() {
+        let source = "\
+const a = b;
+const c = (ul>li);
+const List = () => ul>li*3;
+function App() {
+    return div.container;
+}
+";
+        let mut parser = Parser::new();
+        parser
+            .set_language(&tree_sitter_javascript::language())
+            .unwrap();
+        let tree = parser.parse(source, None).unwrap();
+        let is_jsx_expression_start = |text: &str| {
+            let offset = source.find(text).unwrap();
+            is_jsx_expression_start(
+                tree.root_node()
+                    .descendant_for_byte_range(offset, offset)
+                    .unwrap(),
+                offset,
+            )
+        };
+
+        assert!(is_jsx_expression_start("ul>li)"));
+        assert!(is_jsx_expression_start("ul>li*3"));
+        assert!(is_jsx_expression_start("div.container"));
+        assert!(!is_jsx_expression_start("b;"));
+        assert!(!is_jsx_expression_start("li)"));
+        assert!(!is_jsx_expression_start("container"));
+    }
 }
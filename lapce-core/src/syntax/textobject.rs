@@ -0,0 +1,573 @@
+//! The text objects of the syntax tree for modal editing, as the function or
+//! the parameter at the cursor, taken from the `textobjects.scm` query of the
+//! language when it has one, and guessed from the names of the nodes
+//! otherwise. The language injected at the cursor is looked at before the
+//! ones it's injected in.
+
+use std::{cell::RefCell, collections::HashMap, ops::Range, sync::Arc};
+
+use tree_sitter::{Language, Node, Query, QueryCursor, Tree};
+
+use super::{
+    highlight::language_of_config, util::RopeProvider, DefinitionKind, Syntax,
+};
+use crate::language::LapceLanguage;
+
+thread_local! {
+    static TEXTOBJECT_QUERIES: RefCell<HashMap<LapceLanguage, Option<Arc<Query>>>> = Default::default();
+}
+
+pub(crate) fn reset_textobject_queries() {
+    TEXTOBJECT_QUERIES.with_borrow_mut(|queries| {
+        queries.clear();
+    });
+}
+
+fn get_textobject_query(
+    language: LapceLanguage,
+    grammar: &Language,
+) -> Option<Arc<Query>> {
+    TEXTOBJECT_QUERIES.with_borrow_mut(|queries| {
+        queries
+            .entry(language)
+            .or_insert_with(|| {
+                let source = language.textobjects_query();
+                if source.trim().is_empty() {
+                    return None;
+                }
+                match Query::new(grammar, &source) {
+                    Ok(query) => Some(Arc::new(query)),
+                    Err(err) => {
+                        tracing::error!(
+                            "Failed to parse the textobjects of {}: {err:?}",
+                            language.name()
+                        );
+                        None
+                    }
+                }
+            })
+            .clone()
+    })
+}
+
+/// The tree of a layer of the syntax, with its language when it's known.
+struct LayerTree<'a> {
+    language: Option<LapceLanguage>,
+    grammar: &'a Language,
+    tree: &'a Tree,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextObject {
+    Function,
+    Class,
+    Parameter,
+    Comment,
+}
+
+impl TextObject {
+    fn name(self) -> &'static str {
+        match self {
+            TextObject::Function => "function",
+            TextObject::Class => "class",
+            TextObject::Parameter => "parameter",
+            TextObject::Comment => "comment",
+        }
+    }
+
+    /// Whether `name` is the capture of the text object in a textobjects
+    /// query, as `function.inner`, or `function.inside` as helix names it.
+    pub fn is_capture(self, name: &str, inside: bool) -> bool {
+        let Some(suffix) = name
+            .strip_prefix(self.name())
+            .and_then(|name| name.strip_prefix('.'))
+        else {
+            return false;
+        };
+        if inside {
+            suffix == "inner" || suffix == "inside"
+        } else {
+            suffix == "outer" || suffix == "around"
+        }
+    }
+
+    /// Whether the node is the text object, for the languages without a
+    /// textobjects query.
+    fn matches(self, node: &Node) -> bool {
+        if !node.is_named() {
+            return false;
+        }
+        match self {
+            TextObject::Function => DefinitionKind::Function.matches(node.kind()),
+            TextObject::Class => DefinitionKind::Class.matches(node.kind()),
+            TextObject::Parameter => node
+                .parent()
+                .map(|parent| is_parameter_list(parent.kind()))
+                .unwrap_or(false),
+            TextObject::Comment => node.kind().contains("comment"),
+        }
+    }
+}
+
+/// Whether the node of kind `kind` holds the parameters of a function, or the
+/// arguments of a call, as `formal_parameters` or `argument_list`.
+pub fn is_parameter_list(kind: &str) -> bool {
+    ["parameters", "arguments", "parameter_list", "argument_list"]
+        .iter()
+        .any(|name| kind == *name || kind.ends_with(&format!("_{name}")))
+}
+
+impl Syntax {
+    /// The trees of the layers holding `offset`, the most deeply injected
+    /// first.
+    fn layer_trees(&self, offset: usize) -> Vec<LayerTree<'_>> {
+        let Some(layers) = self.layers.as_ref() else {
+            return Vec::new();
+        };
+        layers
+            .layers_at(offset)
+            .into_iter()
+            .filter_map(|layer| {
+                let language = if layer.depth == 0 {
+                    Some(self.language)
+                } else {
+                    language_of_config(&layer.config)
+                };
+                Some(LayerTree {
+                    language,
+                    grammar: &layer.config.language,
+                    tree: layer.try_tree()?,
+                })
+            })
+            .collect()
+    }
+
+    /// The range of the text object around `offset`, or of what's inside of
+    /// it when `inside`, as `if` and `af` select in vim.
+    pub fn find_text_object(
+        &self,
+        offset: usize,
+        object: TextObject,
+        inside: bool,
+    ) -> Option<Range<usize>> {
+        self.layer_trees(offset).iter().find_map(|layer| {
+            let from_query = self
+                .query_text_object(layer, offset, object)
+                .into_iter()
+                .find_map(|(inside_node, around_node)| {
+                    if inside {
+                        inside_node
+                            .map(|node| self.inside_capture(node, object, layer))
+                    } else if let Some(node) = around_node {
+                        Some(node.byte_range())
+                    } else {
+                        // The queries only capture the parameters themselves
+                        inside_node
+                            .filter(|_| object == TextObject::Parameter)
+                            .map(|node| self.around(node, object))
+                    }
+                });
+            if from_query.is_some() {
+                return from_query;
+            }
+
+            let node = self.guess_text_object(layer, offset, object)?;
+            Some(if inside {
+                self.inside_of(node, object, layer)
+            } else {
+                self.around(node, object)
+            })
+        })
+    }
+
+    /// The nodes captured as the inside and as the whole of the text object
+    /// by the matches of the textobjects query holding `offset`, the smallest
+    /// first.
+    fn query_text_object<'a>(
+        &self,
+        layer: &LayerTree<'a>,
+        offset: usize,
+        object: TextObject,
+    ) -> Vec<(Option<Node<'a>>, Option<Node<'a>>)> {
+        let Some(query) = layer
+            .language
+            .and_then(|language| get_textobject_query(language, layer.grammar))
+        else {
+            return Vec::new();
+        };
+        let captures = |inside: bool| {
+            query
+                .capture_names()
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| object.is_capture(name, inside))
+                .map(|(i, _)| i as u32)
+                .collect::<Vec<_>>()
+        };
+        let (inside_captures, around_captures) = (captures(true), captures(false));
+        if inside_captures.is_empty() && around_captures.is_empty() {
+            return Vec::new();
+        }
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(offset..offset + 1);
+        let mut matches = cursor
+            .matches(&query, layer.tree.root_node(), RopeProvider(&self.text))
+            .filter_map(|mat| {
+                let node = |captures: &[u32]| {
+                    mat.captures
+                        .iter()
+                        .find(|capture| captures.contains(&capture.index))
+                        .map(|capture| capture.node)
+                };
+                let (inside, around) =
+                    (node(&inside_captures), node(&around_captures));
+                // The match is at `offset` when the whole of the text object is
+                let range = around.or(inside)?.byte_range();
+                range
+                    .contains(&offset)
+                    .then_some((range.len(), inside, around))
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(len, _, _)| *len);
+        matches
+            .into_iter()
+            .map(|(_, inside, around)| (inside, around))
+            .collect()
+    }
+
+    /// The smallest node holding `offset` which looks like the text object
+    /// from its name, for the languages without a textobjects query.
+    fn guess_text_object<'a>(
+        &self,
+        layer: &LayerTree<'a>,
+        offset: usize,
+        object: TextObject,
+    ) -> Option<Node<'a>> {
+        let mut node = layer
+            .tree
+            .root_node()
+            .descendant_for_byte_range(offset, offset)?;
+        while !object.matches(&node) {
+            node = node.parent()?;
+        }
+        Some(node)
+    }
+
+    /// What's inside of the text object `node` guessed from its name, which
+    /// is in the body of a function or class.
+    fn inside_of(
+        &self,
+        node: Node,
+        object: TextObject,
+        layer: &LayerTree,
+    ) -> Range<usize> {
+        match object {
+            TextObject::Function | TextObject::Class => {
+                match node.child_by_field_name("body") {
+                    Some(body) => self.between_delimiters(body),
+                    None => node.byte_range(),
+                }
+            }
+            _ => self.inside_capture(node, object, layer),
+        }
+    }
+
+    /// What's between the braces of the body of a function or class, the
+    /// text of a comment without its markers, or the whole of a parameter.
+    fn inside_capture(
+        &self,
+        node: Node,
+        object: TextObject,
+        layer: &LayerTree,
+    ) -> Range<usize> {
+        match object {
+            TextObject::Function | TextObject::Class => {
+                self.between_delimiters(node)
+            }
+            TextObject::Parameter => node.byte_range(),
+            TextObject::Comment => {
+                let language = layer.language.unwrap_or(self.language);
+                let text = self.text.slice_to_cow(node.byte_range());
+                let mut start = 0;
+                let mut end = text.len();
+                let block = language.block_comment_tokens();
+                let line = language.comment_token();
+                if let Some((open, close)) = block.filter(|(open, close)| {
+                    text.starts_with(open) && text.ends_with(close)
+                }) {
+                    start = open.len();
+                    end = end.saturating_sub(close.len()).max(start);
+                } else if !line.is_empty() && text.starts_with(line) {
+                    start = line.len();
+                }
+                let start = node.start_byte() + start;
+                let end = node.start_byte() + end;
+                self.trim_whitespace(start..end)
+            }
+        }
+    }
+
+    /// The range of a block without the braces around it, when it has them.
+    fn between_delimiters(&self, body: Node) -> Range<usize> {
+        let count = body.child_count();
+        let first = body.child(0);
+        let last = count.checked_sub(1).and_then(|i| body.child(i));
+        let range = match (first, last) {
+            (Some(first), Some(last))
+                if count > 1 && !first.is_named() && !last.is_named() =>
+            {
+                first.end_byte()..last.start_byte()
+            }
+            _ => body.byte_range(),
+        };
+        self.trim_whitespace(range)
+    }
+
+    /// The range of the node, with the comma separating a parameter from the
+    /// next one, or from the previous one for the last parameter.
+    fn around(&self, node: Node, object: TextObject) -> Range<usize> {
+        let range = node.byte_range();
+        if object != TextObject::Parameter {
+            return range;
+        }
+        if let Some(comma) = node.next_sibling().filter(|n| n.kind() == ",") {
+            let end = node
+                .next_named_sibling()
+                .map(|next| next.start_byte())
+                .unwrap_or(comma.end_byte());
+            return range.start..end;
+        }
+        if let Some(comma) = node.prev_sibling().filter(|n| n.kind() == ",") {
+            let start = comma
+                .prev_named_sibling()
+                .map(|prev| prev.end_byte())
+                .unwrap_or(comma.start_byte());
+            return start..range.end;
+        }
+        range
+    }
+
+    fn trim_whitespace(&self, range: Range<usize>) -> Range<usize> {
+        let text = self.text.slice_to_cow(range.clone());
+        let start = range.start + (text.len() - text.trim_start().len());
+        let end = range.end - (text.len() - text.trim_end().len());
+        start..end.max(start)
+    }
+
+    /// The ranges of the parameter or argument at `offset`, and of the one
+    /// after it, or before it when not `forward`, for swapping them.
+    pub fn find_parameter_swap(
+        &self,
+        offset: usize,
+        forward: bool,
+    ) -> Option<(Range<usize>, Range<usize>)> {
+        self.layer_trees(offset).iter().find_map(|layer| {
+            let node = self
+                .query_text_object(layer, offset, TextObject::Parameter)
+                .into_iter()
+                .find_map(|(inside, _)| inside)
+                .or_else(|| {
+                    self.guess_text_object(layer, offset, TextObject::Parameter)
+                })?;
+            let mut sibling = node;
+            loop {
+                sibling = if forward {
+                    sibling.next_named_sibling()?
+                } else {
+                    sibling.prev_named_sibling()?
+                };
+                // Skipping the comments between the parameters
+                if !sibling.kind().contains("comment") {
+                    break;
+                }
+            }
+            Some((node.byte_range(), sibling.byte_range()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ops::Range,
+        sync::{atomic::AtomicUsize, Arc},
+    };
+
+    use lapce_xi_rope::Rope;
+
+    use super::{is_parameter_list, TextObject};
+    use crate::{
+        language::LapceLanguage,
+        syntax::{highlight::HighlightConfiguration, Syntax, SyntaxLayers},
+    };
+
+    const SOURCE: &str = "\
+// Adds them
+fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+struct Point {
+    x: f64,
+}
+
+fn main() {
+    add(1, add(2, 3));
+}
+";
+
+    /// The rust source parsed as `language`, whose textobjects query is used
+    /// when it has one.
+    fn syntax(language: LapceLanguage) -> Syntax {
+        let grammar = tree_sitter_rust::language();
+        let config = HighlightConfiguration::new(grammar, "", "", "").unwrap();
+        let mut syntax = Syntax {
+            rev: 0,
+            language,
+            text: Rope::from(""),
+            layers: Some(SyntaxLayers::new_empty(Arc::new(config))),
+            lens: Syntax::lens_from_normal_lines(0, 0, 0, &[]),
+            normal_lines: Vec::new(),
+            line_height: 0,
+            lens_height: 0,
+            styles: None,
+            cancel_flag: Arc::new(AtomicUsize::new(0)),
+        };
+        syntax.parse(1, Rope::from(SOURCE), None);
+        syntax
+    }
+
+    /// The offset of the first `text` in the source.
+    fn at(text: &str) -> usize {
+        SOURCE.find(text).unwrap()
+    }
+
+    fn text(range: Option<Range<usize>>) -> Option<&'static str> {
+        range.map(|range| &SOURCE[range])
+    }
+
+    fn find(
+        syntax: &Syntax,
+        at_text: &str,
+        object: TextObject,
+        inside: bool,
+    ) -> Option<&'static str> {
+        text(syntax.find_text_object(at(at_text), object, inside))
+    }
+
+    #[test]
+    fn test_find_text_object() {
+        // From the textobjects query shipped for rust, and from the names of
+        // the nodes for a language without one.
+        for syntax in [
+            syntax(LapceLanguage::Rust),
+            syntax(LapceLanguage::PlainText),
+        ] {
+            let syntax = &syntax;
+            assert_eq!(
+                find(syntax, "a + b", TextObject::Function, true),
+                Some("a + b")
+            );
+            assert_eq!(
+                find(syntax, "a + b", TextObject::Function, false),
+                Some("fn add(a: u32, b: u32) -> u32 {\n    a + b\n}")
+            );
+            assert_eq!(
+                find(syntax, "x: f64", TextObject::Class, true),
+                Some("x: f64,")
+            );
+            assert_eq!(
+                find(syntax, "Point", TextObject::Class, false),
+                Some("struct Point {\n    x: f64,\n}")
+            );
+            assert_eq!(find(syntax, "Point", TextObject::Function, false), None);
+
+            assert_eq!(
+                find(syntax, "b: u32", TextObject::Parameter, true),
+                Some("b: u32")
+            );
+            assert_eq!(
+                find(syntax, "a: u32", TextObject::Parameter, false),
+                Some("a: u32, ")
+            );
+            assert_eq!(
+                find(syntax, "b: u32", TextObject::Parameter, false),
+                Some(", b: u32")
+            );
+            // The innermost arguments
+            assert_eq!(
+                find(syntax, "2, 3", TextObject::Parameter, false),
+                Some("2, ")
+            );
+            assert_eq!(
+                find(syntax, "add(2", TextObject::Parameter, true),
+                Some("add(2, 3)")
+            );
+        }
+
+        let syntax = syntax(LapceLanguage::Rust);
+        // The body of the function whose signature the cursor is on
+        assert_eq!(
+            find(&syntax, "add(", TextObject::Function, true),
+            Some("a + b")
+        );
+        assert_eq!(
+            find(&syntax, "Adds", TextObject::Comment, true),
+            Some("Adds them")
+        );
+        assert_eq!(
+            find(&syntax, "Adds", TextObject::Comment, false).map(str::trim_end),
+            Some("// Adds them")
+        );
+    }
+
+    #[test]
+    fn test_find_parameter_swap() {
+        for syntax in [
+            syntax(LapceLanguage::Rust),
+            syntax(LapceLanguage::PlainText),
+        ] {
+            let swap = |at_text: &str, forward: bool| {
+                syntax
+                    .find_parameter_swap(at(at_text), forward)
+                    .map(|(a, b)| (&SOURCE[a], &SOURCE[b]))
+            };
+            assert_eq!(swap("a: u32", true), Some(("a: u32", "b: u32")));
+            assert_eq!(swap("b: u32", false), Some(("b: u32", "a: u32")));
+            assert_eq!(swap("a: u32", false), None);
+            assert_eq!(swap("3)", false), Some(("3", "2")));
+            assert_eq!(swap("1", true), Some(("1", "add(2, 3)")));
+        }
+    }
+
+    #[test]
+    fn test_is_capture() {
+        assert!(TextObject::Function.is_capture("function.inner", true));
+        assert!(TextObject::Function.is_capture("function.inside", true));
+        assert!(TextObject::Function.is_capture("function.outer", false));
+        assert!(TextObject::Function.is_capture("function.around", false));
+        assert!(!TextObject::Function.is_capture("function.outer", true));
+        assert!(!TextObject::Function.is_capture("functions.inner", true));
+        assert!(!TextObject::Class.is_capture("function.inner", true));
+        assert!(TextObject::Parameter.is_capture("parameter.inner", true));
+        assert!(TextObject::Comment.is_capture("comment.outer", false));
+    }
+
+    #[test]
+    fn test_is_parameter_list() {
+        for kind in [
+            "parameters",
+            "formal_parameters",
+            "arguments",
+            "argument_list",
+            "parameter_list",
+            "type_arguments",
+        ] {
+            assert!(is_parameter_list(kind), "{kind}");
+        }
+        for kind in ["parameter", "function_item", "call_expression"] {
+            assert!(!is_parameter_list(kind), "{kind}");
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! The backups of the files deleted by workspace edits, which undoing the
+//! edits restores.
+//!
+//! Each backup is in a directory named after the time it was made at in
+//! milliseconds, so that the backups of files with the same name don't
+//! collide, and the ones older than `MAX_AGE` are removed as new ones are
+//! made.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use lapce_core::directory::Directory;
+
+/// How long the backups are kept for.
+const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Copy the file or the directory at `path` to a new backup, returning the
+/// path of the copy.
+pub fn backup(path: &Path) -> Result<PathBuf> {
+    let dir = Directory::backups_directory()
+        .ok_or_else(|| anyhow!("no backups directory"))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    remove_older(&dir, now.saturating_sub(MAX_AGE).as_millis() as u64);
+    backup_in(&dir, path, now.as_millis() as u64)
+}
+
+fn backup_in(dir: &Path, path: &Path, now: u64) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("{} has no file name", path.display()))?;
+    // A backup made within the same millisecond still gets its own directory.
+    let mut timestamp = now;
+    while dir.join(timestamp.to_string()).exists() {
+        timestamp += 1;
+    }
+    let backup = dir.join(timestamp.to_string()).join(name);
+    fs::create_dir_all(dir.join(timestamp.to_string()))?;
+    copy(path, &backup)?;
+    Ok(backup)
+}
+
+fn remove_older(dir: &Path, timestamp: u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let is_older = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u64>().ok())
+            .is_some_and(|time| time < timestamp);
+        if is_older {
+            if let Err(err) = fs::remove_dir_all(entry.path()) {
+                tracing::error!("{:?}", err);
+            }
+        }
+    }
+}
+
+/// Move the file or the directory at `from` to `to`. Renaming across file
+/// systems fails, as when restoring a backup, so the path is copied and removed
+/// instead then, leaving it as it was if it can't be copied.
+pub fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(err) if crosses_devices(&err) => {}
+        result => return result,
+    }
+    if fs::symlink_metadata(to).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    if let Err(err) = copy(from, to) {
+        // Don't leave a partial copy behind.
+        let _ = remove(to);
+        return Err(err);
+    }
+    remove(from)
+}
+
+/// Whether `err` is the failure to rename a path to another file system.
+fn crosses_devices(err: &io::Error) -> bool {
+    // ERROR_NOT_SAME_DEVICE
+    #[cfg(windows)]
+    const CROSSES_DEVICES: i32 = 17;
+    #[cfg(not(windows))]
+    const CROSSES_DEVICES: i32 = libc::EXDEV;
+    err.raw_os_error() == Some(CROSSES_DEVICES)
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// Copy the file or the directory at `from`, with its content, to `to`. The
+/// symbolic links are copied as links rather than followed, so that they can't
+/// lead out of the directory or back into it.
+pub fn copy(from: &Path, to: &Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(from)?.file_type();
+    if file_type.is_symlink() {
+        copy_symlink(from, to)?;
+    } else if file_type.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    let target = fs::read_link(from)?;
+    // Links to directories are made differently, which the metadata of the
+    // link tells even when its target is missing.
+    if fs::metadata(from).is_ok_and(|metadata| metadata.is_dir()) {
+        std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        std::os::windows::fs::symlink_file(target, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{backup_in, copy, remove_older, rename};
+
+    #[test]
+    fn test_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let backups = dir.join("backups");
+        fs::create_dir_all(dir.join("src/nested")).unwrap();
+        fs::create_dir_all(&backups).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("src/nested/mod.rs"), "mod a;").unwrap();
+
+        let file = backup_in(&backups, &dir.join("src/main.rs"), 10).unwrap();
+        assert_eq!(file, backups.join("10/main.rs"));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "fn main() {}");
+
+        // A backup made within the same millisecond doesn't overwrite it.
+        let src = backup_in(&backups, &dir.join("src"), 10).unwrap();
+        assert_eq!(src, backups.join("11/src"));
+        assert_eq!(
+            fs::read_to_string(src.join("nested/mod.rs")).unwrap(),
+            "mod a;"
+        );
+
+        remove_older(&backups, 11);
+        assert!(!backups.join("10").exists());
+        assert!(backups.join("11").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("outside.rs"), "").unwrap();
+        // A link back to the directory would be followed forever.
+        std::os::unix::fs::symlink(dir.join("src"), dir.join("src/loop")).unwrap();
+        std::os::unix::fs::symlink("../outside.rs", dir.join("src/outside.rs"))
+            .unwrap();
+
+        copy(&dir.join("src"), &dir.join("copy")).unwrap();
+        assert_eq!(
+            fs::read_link(dir.join("copy/loop")).unwrap(),
+            dir.join("src")
+        );
+        assert_eq!(
+            fs::read_link(dir.join("copy/outside.rs")).unwrap(),
+            Path::new("../outside.rs")
+        );
+    }
+
+    #[test]
+    fn test_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::write(dir.join("a.rs"), "a").unwrap();
+        fs::write(dir.join("b.rs"), "b").unwrap();
+
+        rename(&dir.join("a.rs"), &dir.join("c.rs")).unwrap();
+        assert!(!dir.join("a.rs").exists());
+        assert_eq!(fs::read_to_string(dir.join("c.rs")).unwrap(), "a");
+
+        // Failures other than crossing file systems leave the source.
+        assert!(rename(&dir.join("b.rs"), &dir.join("missing/b.rs")).is_err());
+        assert_eq!(fs::read_to_string(dir.join("b.rs")).unwrap(), "b");
+    }
+}
@@ -9,9 +9,10 @@
 };
 
 use anyhow::{anyhow, Result};
+use encoding_rs::Encoding;
 use floem_editor_core::buffer::rope_text::CharIndicesJoin;
-use lapce_core::encoding::offset_utf8_to_utf16;
-use lapce_rpc::buffer::BufferId;
+use lapce_core::{encoding::offset_utf8_to_utf16, language::LapceLanguage};
+use lapce_rpc::buffer::{BufferId, UTF8_BOM};
 use lapce_xi_rope::{interval::IntervalBounds, rope::Rope, RopeDelta};
 use lsp_types::*;
 
@@ -24,22 +25,27 @@ pub struct Buffer {
     pub path: PathBuf,
     pub rev: u64,
     pub mod_time: Option<SystemTime>,
+    /// The name of the encoding the file is read and saved with.
+    pub encoding: String,
 }
 
 impl Buffer {
     pub fn new(id: BufferId, path: PathBuf) -> Buffer {
-        let (s, read_only) = match load_file(&path) {
-            Ok(s) => (s, false),
-            Err(err) => match err.downcast_ref::<std::io::Error>() {
-                Some(err) => match err.kind() {
-                    std::io::ErrorKind::PermissionDenied => {
-                        ("Permission Denied".to_string(), true)
-                    }
-                    std::io::ErrorKind::NotFound => ("".to_string(), false),
-                    _ => ("Not Supported".to_string(), true),
-                },
-                None => ("Not Supported".to_string(), true),
-            },
+        let (s, read_only, encoding) = match load_file_with_encoding(&path, None) {
+            Ok((s, encoding)) => (s, false, encoding),
+            Err(err) => {
+                let (s, read_only) = match err.downcast_ref::<std::io::Error>() {
+                    Some(err) => match err.kind() {
+                        std::io::ErrorKind::PermissionDenied => {
+                            ("Permission Denied".to_string(), true)
+                        }
+                        std::io::ErrorKind::NotFound => ("".to_string(), false),
+                        _ => ("Not Supported".to_string(), true),
+                    },
+                    None => ("Not Supported".to_string(), true),
+                };
+                (s, read_only, UTF8.to_string())
+            }
         };
         let rope = Rope::from(s);
         let rev = u64::from(!rope.is_empty());
@@ -53,7 +59,19 @@ pub fn new(id: BufferId, path: PathBuf) -> Buffer {
             language_id,
             rev,
             mod_time,
+            encoding,
+        }
+    }
+
+    /// Change the encoding the buffer is saved with, checking that its content
+    /// can be represented in it.
+    pub fn set_encoding(&mut self, rev: u64, encoding: &str) -> Result<()> {
+        if self.rev != rev {
+            return Err(anyhow!("not the right rev"));
         }
+        encode(&self.get_document(), encoding)?;
+        self.encoding = encoding.to_string();
+        Ok(())
     }
 
     pub fn save(&mut self, rev: u64, create_parents: bool) -> Result<()> {
@@ -64,6 +82,13 @@ pub fn save(&mut self, rev: u64, create_parents: bool) -> Result<()> {
         if self.rev != rev {
             return Err(anyhow!("not the right rev"));
         }
+        // Encode before touching the file, so it's left alone if the content
+        // can't be represented in the encoding.
+        let encoded = if self.encoding == UTF8 {
+            None
+        } else {
+            Some(encode(&self.get_document(), &self.encoding)?)
+        };
         let bak_extension = self.path.extension().map_or_else(
             || OsString::from("bak"),
             |ext| {
@@ -95,8 +120,13 @@ pub fn save(&mut self, rev: u64, create_parents: bool) -> Result<()> {
             .write(true)
             .truncate(true)
             .open(&path)?;
-        for chunk in self.rope.iter_chunks(..self.rope.len()) {
-            f.write_all(chunk.as_bytes())?;
+        match encoded {
+            Some(bytes) => f.write_all(&bytes)?,
+            None => {
+                for chunk in self.rope.iter_chunks(..self.rope.len()) {
+                    f.write_all(chunk.as_bytes())?;
+                }
+            }
         }
 
         self.mod_time = get_mod_time(&path);
@@ -186,101 +216,138 @@ pub fn is_empty(&self) -> bool {
     }
 }
 
+const UTF8: &str = "UTF-8";
+
 pub fn load_file(path: &Path) -> Result<String> {
-    read_path_to_string(path)
+    load_file_with_encoding(path, None).map(|(content, _)| content)
 }
 
-pub fn read_path_to_string<P: AsRef<Path>>(path: P) -> Result<String> {
-    let path = path.as_ref();
-
+/// Read the file at `path`, decoding it with `encoding`, or with the encoding
+/// detected from its content if `None`. Returns the content and the name of the
+/// encoding.
+pub fn load_file_with_encoding(
+    path: &Path,
+    encoding: Option<&str>,
+) -> Result<(String, String)> {
     let mut file = File::open(path)?;
     // Read the file in as bytes
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer)?;
 
-    // Parse the file contents as utf8
-    let contents = String::from_utf8(buffer)?;
+    let encoding = match encoding {
+        Some(encoding) => encoding,
+        None => detect_encoding(&buffer)
+            .ok_or_else(|| anyhow!("the file isn't a text file"))?,
+    };
+    Ok((decode(&buffer, encoding)?, encoding.to_string()))
+}
+
+/// Guess the encoding of a file from its content: its byte order mark, if it
+/// looks like UTF-16, or if it's valid UTF-8. Other files are taken to be in
+/// windows-1252, as the most common legacy encoding, unless they contain null
+/// bytes in which case they're most likely binary and `None` is returned.
+fn detect_encoding(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Some(if encoding == encoding_rs::UTF_8 {
+            UTF8_BOM
+        } else {
+            encoding.name()
+        });
+    }
+    // UTF-16 of ASCII text is valid UTF-8 too, so it's checked first.
+    if let Some(encoding) = detect_utf16(bytes) {
+        return Some(encoding);
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return Some(UTF8);
+    }
+    if bytes.contains(&0) {
+        return None;
+    }
+    Some(encoding_rs::WINDOWS_1252.name())
+}
 
-    Ok(contents.to_string())
+/// Text in UTF-16 without a byte order mark is recognised by most of its
+/// characters being ASCII, so that every other byte is zero.
+fn detect_utf16(bytes: &[u8]) -> Option<&'static str> {
+    let sample = &bytes[..bytes.len().min(4096)];
+    if sample.len() < 2 || bytes.len() % 2 != 0 {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let (mut even_zeros, mut odd_zeros) = (0, 0);
+    for pair in sample.chunks_exact(2) {
+        if pair[0] == 0 {
+            even_zeros += 1;
+        }
+        if pair[1] == 0 {
+            odd_zeros += 1;
+        }
+    }
+    if odd_zeros * 10 >= pairs * 4 && even_zeros * 20 < pairs {
+        Some(encoding_rs::UTF_16LE.name())
+    } else if even_zeros * 10 >= pairs * 4 && odd_zeros * 20 < pairs {
+        Some(encoding_rs::UTF_16BE.name())
+    } else {
+        None
+    }
+}
+
+fn encoding_for_name(name: &str) -> Result<&'static Encoding> {
+    if name == UTF8_BOM {
+        return Ok(encoding_rs::UTF_8);
+    }
+    Encoding::for_label(name.as_bytes())
+        .ok_or_else(|| anyhow!("unknown encoding {name}"))
+}
+
+/// Decode `bytes` in the encoding `name`, dropping its byte order mark.
+/// Malformed sequences are replaced with the replacement character.
+fn decode(bytes: &[u8], name: &str) -> Result<String> {
+    let encoding = encoding_for_name(name)?;
+    let (content, _) = encoding.decode_with_bom_removal(bytes);
+    Ok(content.into_owned())
+}
+
+/// Encode `content` in the encoding `name`. UTF-16 is always written with a
+/// byte order mark. Fails if a character can't be represented in the encoding.
+fn encode(content: &str, name: &str) -> Result<Vec<u8>> {
+    let encoding = encoding_for_name(name)?;
+    let bytes = if name == UTF8_BOM {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice(content.as_bytes());
+        bytes
+    } else if encoding == encoding_rs::UTF_16LE {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    } else if encoding == encoding_rs::UTF_16BE {
+        let mut bytes = vec![0xFE, 0xFF];
+        bytes.extend(content.encode_utf16().flat_map(u16::to_be_bytes));
+        bytes
+    } else {
+        let (bytes, _, had_errors) = encoding.encode(content);
+        if had_errors {
+            return Err(anyhow!(
+                "the content has characters that can't be encoded in {name}"
+            ));
+        }
+        bytes.into_owned()
+    };
+    Ok(bytes)
 }
 
 pub fn language_id_from_path(path: &Path) -> Option<&'static str> {
-    // recommended language_id values
-    // https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocumentItem
+    // The files set to a language by the `file-associations` setting take the
+    // language id of its extensions
+    if let Some(language) = LapceLanguage::from_file_association(path) {
+        return language
+            .extensions()
+            .iter()
+            .find_map(|ext| language_id_from_extension(ext));
+    }
     Some(match path.extension() {
-        Some(ext) => {
-            match ext.to_str()? {
-                "C" | "H" => "cpp",
-                "M" => "objective-c",
-                // stop case-sensitive matching
-                ext => match ext.to_lowercase().as_str() {
-                    "bat" => "bat",
-                    "clj" | "cljs" | "cljc" | "edn" => "clojure",
-                    "coffee" => "coffeescript",
-                    "c" | "h" => "c",
-                    "cpp" | "hpp" | "cxx" | "hxx" | "c++" | "h++" | "cc" | "hh" => {
-                        "cpp"
-                    }
-                    "cs" | "csx" => "csharp",
-                    "css" => "css",
-                    "d" | "di" | "dlang" => "dlang",
-                    "diff" | "patch" => "diff",
-                    "dart" => "dart",
-                    "dockerfile" => "dockerfile",
-                    "elm" => "elm",
-                    "ex" | "exs" => "elixir",
-                    "erl" | "hrl" => "erlang",
-                    "fs" | "fsi" | "fsx" | "fsscript" => "fsharp",
-                    "git-commit" | "git-rebase" => "git",
-                    "go" => "go",
-                    "groovy" | "gvy" | "gy" | "gsh" => "groovy",
-                    "hbs" => "handlebars",
-                    "htm" | "html" | "xhtml" => "html",
-                    "ini" => "ini",
-                    "java" | "class" => "java",
-                    "js" => "javascript",
-                    "jsx" => "javascriptreact",
-                    "json" => "json",
-                    "jl" => "julia",
-                    "kt" | "kts" => "kotlin",
-                    "less" => "less",
-                    "lua" => "lua",
-                    "makefile" | "gnumakefile" => "makefile",
-                    "md" | "markdown" => "markdown",
-                    "m" => "objective-c",
-                    "mm" => "objective-cpp",
-                    "plx" | "pl" | "pm" | "xs" | "t" | "pod" | "cgi" => "perl",
-                    "p6" | "pm6" | "pod6" | "t6" | "raku" | "rakumod"
-                    | "rakudoc" | "rakutest" => "perl6",
-                    "php" | "phtml" | "pht" | "phps" => "php",
-                    "proto" => "proto",
-                    "ps1" | "ps1xml" | "psc1" | "psm1" | "psd1" | "pssc"
-                    | "psrc" => "powershell",
-                    "py" | "pyi" | "pyc" | "pyd" | "pyw" => "python",
-                    "r" => "r",
-                    "rb" => "ruby",
-                    "rs" => "rust",
-                    "scss" | "sass" => "scss",
-                    "sc" | "scala" => "scala",
-                    "sh" | "bash" | "zsh" => "shellscript",
-                    "sql" => "sql",
-                    "swift" => "swift",
-                    "svelte" => "svelte",
-                    "thrift" => "thrift",
-                    "toml" => "toml",
-                    "ts" => "typescript",
-                    "tsx" => "typescriptreact",
-                    "tex" => "tex",
-                    "vb" => "vb",
-                    "xml" | "csproj" => "xml",
-                    "xsl" => "xsl",
-                    "yml" | "yaml" => "yaml",
-                    "zig" => "zig",
-                    "vue" => "vue",
-                    _ => return None,
-                },
-            }
-        }
+        Some(ext) => language_id_from_extension(ext.to_str()?)?,
         // Handle paths without extension
         #[allow(clippy::match_single_binding)]
         None => match path.file_name()?.to_str()? {
@@ -294,6 +361,81 @@ pub fn language_id_from_path(path: &Path) -> Option<&'static str> {
     })
 }
 
+fn language_id_from_extension(ext: &str) -> Option<&'static str> {
+    // recommended language_id values
+    // https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocumentItem
+    Some(match ext {
+        "C" | "H" => "cpp",
+        "M" => "objective-c",
+        // stop case-sensitive matching
+        ext => match ext.to_lowercase().as_str() {
+            "bat" => "bat",
+            "clj" | "cljs" | "cljc" | "edn" => "clojure",
+            "coffee" => "coffeescript",
+            "c" | "h" => "c",
+            "cpp" | "hpp" | "cxx" | "hxx" | "c++" | "h++" | "cc" | "hh" => "cpp",
+            "cs" | "csx" => "csharp",
+            "css" => "css",
+            "d" | "di" | "dlang" => "dlang",
+            "diff" | "patch" => "diff",
+            "dart" => "dart",
+            "dockerfile" => "dockerfile",
+            "elm" => "elm",
+            "ex" | "exs" => "elixir",
+            "erl" | "hrl" => "erlang",
+            "fs" | "fsi" | "fsx" | "fsscript" => "fsharp",
+            "git-commit" | "git-rebase" => "git",
+            "go" => "go",
+            "groovy" | "gvy" | "gy" | "gsh" => "groovy",
+            "hbs" => "handlebars",
+            "htm" | "html" | "xhtml" => "html",
+            "ini" => "ini",
+            "java" | "class" => "java",
+            "js" => "javascript",
+            "jsx" => "javascriptreact",
+            "json" => "json",
+            "jl" => "julia",
+            "kt" | "kts" => "kotlin",
+            "less" => "less",
+            "lua" => "lua",
+            "makefile" | "gnumakefile" => "makefile",
+            "md" | "markdown" => "markdown",
+            "m" => "objective-c",
+            "mm" => "objective-cpp",
+            "plx" | "pl" | "pm" | "xs" | "t" | "pod" | "cgi" => "perl",
+            "p6" | "pm6" | "pod6" | "t6" | "raku" | "rakumod" | "rakudoc"
+            | "rakutest" => "perl6",
+            "php" | "phtml" | "pht" | "phps" => "php",
+            "proto" => "proto",
+            "ps1" | "ps1xml" | "psc1" | "psm1" | "psd1" | "pssc" | "psrc" => {
+                "powershell"
+            }
+            "py" | "pyi" | "pyc" | "pyd" | "pyw" => "python",
+            "r" => "r",
+            "rb" => "ruby",
+            "rs" => "rust",
+            "scss" | "sass" => "scss",
+            "sc" | "scala" => "scala",
+            "sh" | "bash" | "zsh" => "shellscript",
+            "sql" => "sql",
+            "swift" => "swift",
+            "svelte" => "svelte",
+            "thrift" => "thrift",
+            "toml" => "toml",
+            "ts" => "typescript",
+            "tsx" => "typescriptreact",
+            "tex" => "tex",
+            "vb" => "vb",
+            "xml" | "csproj" => "xml",
+            "xsl" => "xsl",
+            "yml" | "yaml" => "yaml",
+            "zig" => "zig",
+            "vue" => "vue",
+            _ => return None,
+        },
+    })
+}
+
 fn get_document_content_changes(
     delta: &RopeDelta,
     buffer: &Buffer,
@@ -341,3 +483,43 @@ pub fn get_mod_time<P: AsRef<Path>>(path: P) -> Option<SystemTime> {
         .and_then(|meta| meta.modified())
         .ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use lapce_rpc::buffer::UTF8_BOM;
+
+    use super::{decode, detect_encoding, encode};
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(detect_encoding(b"plain text"), Some("UTF-8"));
+        assert_eq!(detect_encoding("caf\u{e9}".as_bytes()), Some("UTF-8"));
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBFtext"), Some(UTF8_BOM));
+        assert_eq!(detect_encoding(b"\xFF\xFEt\0e\0"), Some("UTF-16LE"));
+        assert_eq!(detect_encoding(b"\0t\0e\0x\0t"), Some("UTF-16BE"));
+        assert_eq!(detect_encoding(b"t\0e\0x\0t\0"), Some("UTF-16LE"));
+        assert_eq!(detect_encoding(b"caf\xE9"), Some("windows-1252"));
+        assert_eq!(detect_encoding(b"\x7FELF\x02\x01\x01\0\0\xFF"), None);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let content = "caf\u{e9}\nna\u{ef}ve";
+        for encoding in [
+            "UTF-8",
+            UTF8_BOM,
+            "UTF-16LE",
+            "UTF-16BE",
+            "windows-1252",
+            "ISO-8859-15",
+        ] {
+            let bytes = encode(content, encoding).unwrap();
+            assert_eq!(decode(&bytes, encoding).unwrap(), content, "{encoding}");
+        }
+        assert_eq!(encode("caf\u{e9}", "windows-1252").unwrap(), b"caf\xE9");
+        assert_eq!(encode("a", UTF8_BOM).unwrap(), b"\xEF\xBB\xBFa");
+        assert_eq!(encode("a", "UTF-16BE").unwrap(), b"\xFE\xFF\0a");
+        assert!(encode("\u{65e5}\u{672c}", "windows-1252").is_err());
+        assert!(encode("a", "no such encoding").is_err());
+    }
+}
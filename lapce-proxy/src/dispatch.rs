@@ -3,7 +3,7 @@
     fs, io,
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
     },
     thread,
@@ -14,38 +14,55 @@
 use anyhow::{anyhow, Context, Result};
 use crossbeam_channel::Sender;
 use git2::{
-    build::CheckoutBuilder, DiffOptions, ErrorCode::NotFound, Oid, Repository,
+    build::CheckoutBuilder, DiffFormat, DiffOptions, ErrorCode::NotFound, Oid,
+    Repository,
 };
 use grep_matcher::Matcher;
 use grep_regex::RegexMatcherBuilder;
 use grep_searcher::{sinks::UTF8, SearcherBuilder};
+use ignore::{
+    overrides::{Override, OverrideBuilder},
+    WalkBuilder,
+};
 use indexmap::IndexMap;
+use lapce_core::language::LapceLanguage;
 use lapce_rpc::{
     buffer::BufferId,
     core::{CoreNotification, CoreRpcHandler, FileChanged},
     file::FileNodeItem,
     file_line::FileLine,
+    plugin::PluginId,
     proxy::{
-        ProxyHandler, ProxyNotification, ProxyRequest, ProxyResponse,
-        ProxyRpcHandler, SearchMatch,
+        ExternalFormatterMode, ProxyHandler, ProxyNotification, ProxyRequest,
+        ProxyResponse, ProxyRpcHandler, SearchMatch,
     },
     source_control::{DiffInfo, FileDiff},
     style::{LineStyle, SemanticStyles},
     terminal::TermId,
     RequestId, RpcError,
 };
-use lapce_xi_rope::Rope;
+use lapce_xi_rope::{find::is_multiline_regex, Rope};
 use lsp_types::{
-    notification::{Cancel, Notification},
-    CancelParams, MessageType, NumberOrString, Position, Range, ShowMessageParams,
-    TextDocumentItem, Url,
+    notification::{Cancel, Notification, WorkDoneProgressCancel},
+    CancelParams, CodeActionResponse, MessageType, NumberOrString, Position, Range,
+    SemanticTokens, SemanticTokensFullDeltaResult, ShowMessageParams,
+    TextDocumentItem, Url, WorkDoneProgressCancelParams,
 };
 use parking_lot::Mutex;
 
 use crate::{
-    buffer::{get_mod_time, load_file, Buffer},
-    plugin::{catalog::PluginCatalog, PluginCatalogRpcHandler},
-    terminal::{Terminal, TerminalSender},
+    backup,
+    buffer::{get_mod_time, load_file, load_file_with_encoding, Buffer},
+    formatter::{apply_text_edits, minimal_text_edits, run_external_formatter},
+    local_history,
+    plugin::{
+        catalog::PluginCatalog, psp::apply_semantic_tokens_edits,
+        PluginCatalogRpcHandler,
+    },
+    task::{run_task, stop_task, workspace_tasks, RunningTasks},
+    terminal::{
+        foreground_environment, process_environment, Terminal, TerminalSender,
+    },
     watcher::{FileWatcher, Notify, WatchToken},
 };
 
@@ -54,11 +71,27 @@
 
 pub struct Dispatcher {
     workspace: Option<PathBuf>,
+    /// The folders added to the workspace besides its root, shared with the
+    /// file watcher.
+    workspace_folders: Arc<Mutex<Vec<PathBuf>>>,
     pub proxy_rpc: ProxyRpcHandler,
     core_rpc: CoreRpcHandler,
     catalog_rpc: PluginCatalogRpcHandler,
     buffers: HashMap<PathBuf, Buffer>,
+    /// The last semantic tokens of each buffer, with the plugin they're from,
+    /// for the plugin to send only the changes to them the next time.
+    semantic_tokens: Arc<Mutex<HashMap<PathBuf, (PluginId, SemanticTokens)>>>,
     terminals: HashMap<TermId, TerminalSender>,
+    /// The pid of the shell of each terminal, and the variables it was
+    /// spawned with.
+    terminal_environments: HashMap<TermId, (Option<u32>, Vec<(String, String)>)>,
+    /// Shared with the terminals, telling them to report their process usage.
+    sample_terminal_usage: Arc<AtomicBool>,
+    /// The terminals whose process hasn't exited yet, which keep the proxy
+    /// running while the connection to the UI is lost.
+    live_terminals: Arc<Mutex<HashSet<TermId>>>,
+    /// The processes of the tasks still running.
+    tasks: RunningTasks,
     file_watcher: FileWatcher,
     window_id: usize,
     tab_id: usize,
@@ -70,23 +103,27 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
         match rpc {
             Initialize {
                 workspace,
+                workspace_folders,
                 disabled_volts,
                 extra_plugin_paths,
                 plugin_configurations,
+                file_associations,
                 window_id,
                 tab_id,
             } => {
+                LapceLanguage::set_file_associations(&file_associations);
                 self.window_id = window_id;
                 self.tab_id = tab_id;
                 self.workspace = workspace;
+                *self.workspace_folders.lock() = workspace_folders.clone();
                 self.file_watcher.notify(FileWatchNotifier::new(
                     self.workspace.clone(),
+                    self.workspace_folders.clone(),
                     self.core_rpc.clone(),
                     self.proxy_rpc.clone(),
                 ));
-                if let Some(workspace) = self.workspace.as_ref() {
-                    self.file_watcher
-                        .watch(workspace, true, WORKSPACE_EVENT_TOKEN);
+                for root in self.roots() {
+                    self.file_watcher.watch(&root, true, WORKSPACE_EVENT_TOKEN);
                 }
 
                 let plugin_rpc = self.catalog_rpc.clone();
@@ -94,6 +131,7 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 thread::spawn(move || {
                     let mut plugin = PluginCatalog::new(
                         workspace,
+                        workspace_folders,
                         disabled_volts,
                         extra_plugin_paths,
                         plugin_configurations,
@@ -122,8 +160,11 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                         if get_mod_time(&buffer.path) == buffer.mod_time {
                             return;
                         }
-                        match load_file(&buffer.path) {
-                            Ok(content) => {
+                        match load_file_with_encoding(
+                            &buffer.path,
+                            Some(&buffer.encoding),
+                        ) {
+                            Ok((content, _)) => {
                                 self.core_rpc.open_file_changed(
                                     path,
                                     FileChanged::Change(content),
@@ -136,6 +177,7 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     }
                 } else {
                     self.buffers.remove(&path);
+                    self.semantic_tokens.lock().remove(&path);
                     self.core_rpc.open_file_changed(path, FileChanged::Delete);
                 }
             }
@@ -160,6 +202,10 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 for (_, sender) in self.terminals.iter() {
                     sender.send(Msg::Shutdown);
                 }
+                let task_ids = self.tasks.lock().keys().copied().collect::<Vec<_>>();
+                for task_id in task_ids {
+                    stop_task(task_id, &self.tasks);
+                }
                 self.proxy_rpc.shutdown();
             }
             Update { path, delta, rev } => {
@@ -179,6 +225,40 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     tracing::error!("{:?}", err);
                 }
             }
+            UpdateFileAssociations { associations } => {
+                LapceLanguage::set_file_associations(&associations);
+            }
+            UpdateWorkspaceFolders { folders } => {
+                let previous = std::mem::replace(
+                    &mut *self.workspace_folders.lock(),
+                    folders.clone(),
+                );
+                for folder in previous.iter().filter(|f| !folders.contains(f)) {
+                    self.file_watcher.unwatch(folder, WORKSPACE_EVENT_TOKEN);
+                }
+                for folder in folders.iter().filter(|f| !previous.contains(f)) {
+                    self.file_watcher.watch(folder, true, WORKSPACE_EVENT_TOKEN);
+                }
+
+                // The changes of the repositories of the folders
+                let roots = self.roots();
+                let core_rpc = self.core_rpc.clone();
+                thread::spawn(move || {
+                    if let Some(diff) = git_diff_roots(&roots) {
+                        core_rpc.diff_info(diff);
+                    }
+                });
+
+                if let Err(err) = self.catalog_rpc.update_workspace_folders(folders)
+                {
+                    tracing::error!("{:?}", err);
+                }
+            }
+            PluginEvent { event } => {
+                if let Err(err) = self.catalog_rpc.plugin_event(event) {
+                    tracing::error!("{:?}", err);
+                }
+            }
             NewTerminal { term_id, profile } => {
                 let mut terminal = match Terminal::new(term_id, profile, 50, 10) {
                     Ok(terminal) => terminal,
@@ -187,6 +267,7 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                         return;
                     }
                 };
+                terminal.sample_usage = self.sample_terminal_usage.clone();
 
                 #[allow(unused)]
                 let mut child_id = None;
@@ -201,13 +282,32 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 }
 
                 self.core_rpc.terminal_process_id(term_id, child_id);
+                self.terminal_environments
+                    .insert(term_id, (child_id, terminal.environment.clone()));
                 let tx = terminal.tx.clone();
                 let poller = terminal.poller.clone();
                 let sender = TerminalSender::new(tx, poller);
                 self.terminals.insert(term_id, sender);
+                if let Err(err) = self.catalog_rpc.plugin_event(
+                    lapce_rpc::plugin::PluginEvent::TerminalCreated { term_id },
+                ) {
+                    tracing::error!("{:?}", err);
+                }
+                self.live_terminals.lock().insert(term_id);
                 let rpc = self.core_rpc.clone();
+                let catalog_rpc = self.catalog_rpc.clone();
+                let live_terminals = self.live_terminals.clone();
                 thread::spawn(move || {
-                    terminal.run(rpc);
+                    let exit_code = terminal.run(rpc);
+                    live_terminals.lock().remove(&term_id);
+                    if let Err(err) = catalog_rpc.plugin_event(
+                        lapce_rpc::plugin::PluginEvent::TerminalExited {
+                            term_id,
+                            exit_code,
+                        },
+                    ) {
+                        tracing::error!("{:?}", err);
+                    }
                 });
             }
             TerminalWrite { term_id, content } => {
@@ -232,10 +332,14 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 }
             }
             TerminalClose { term_id } => {
+                self.terminal_environments.remove(&term_id);
                 if let Some(tx) = self.terminals.remove(&term_id) {
                     tx.send(Msg::Shutdown);
                 }
             }
+            TerminalSampleUsage { enabled } => {
+                self.sample_terminal_usage.store(enabled, Ordering::Relaxed);
+            }
             DapStart {
                 config,
                 breakpoints,
@@ -310,6 +414,21 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     tracing::error!("{:?}", err);
                 }
             }
+            RunTask { task_id, task } => {
+                if let Err(err) = run_task(
+                    task_id,
+                    task,
+                    self.workspace.as_deref(),
+                    self.tasks.clone(),
+                    self.core_rpc.clone(),
+                ) {
+                    self.core_rpc
+                        .task_exited(task_id, None, Some(err.to_string()));
+                }
+            }
+            StopTask { task_id } => {
+                stop_task(task_id, &self.tasks);
+            }
             InstallVolt { volt } => {
                 let catalog_rpc = self.catalog_rpc.clone();
                 if let Err(err) = catalog_rpc.install_volt(volt) {
@@ -321,6 +440,13 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     tracing::error!("{:?}", err);
                 }
             }
+            StopLanguageServers { path, restart } => {
+                if let Err(err) =
+                    self.catalog_rpc.stop_language_servers(path, restart)
+                {
+                    tracing::error!("{:?}", err);
+                }
+            }
             RemoveVolt { volt } => {
                 self.catalog_rpc.remove_volt(volt);
             }
@@ -333,18 +459,45 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 }
             }
             GitCommit { message, diffs } => {
-                if let Some(workspace) = self.workspace.as_ref() {
-                    match git_commit(workspace, &message, diffs) {
-                        Ok(()) => (),
-                        Err(e) => {
-                            self.core_rpc.show_message(
-                                "Git Commit failure".to_owned(),
-                                ShowMessageParams {
-                                    typ: MessageType::ERROR,
-                                    message: e.to_string(),
-                                },
-                            );
-                        }
+                // A commit in the repository of each root with changes
+                let roots = self.roots();
+                let mut root_diffs: Vec<(&Path, Vec<FileDiff>)> = Vec::new();
+                let mut skipped = Vec::new();
+                for diff in diffs {
+                    let Some(root) = root_of(&roots, diff.path()) else {
+                        skipped.push(diff.path().clone());
+                        continue;
+                    };
+                    match root_diffs.iter_mut().find(|(r, _)| *r == root) {
+                        Some((_, diffs)) => diffs.push(diff),
+                        None => root_diffs.push((root, vec![diff])),
+                    }
+                }
+                if !skipped.is_empty() {
+                    self.core_rpc.show_message(
+                        "Git Commit".to_owned(),
+                        ShowMessageParams {
+                            typ: MessageType::WARNING,
+                            message: format!(
+                                "Left out the changes outside the workspace: {}",
+                                skipped
+                                    .iter()
+                                    .map(|path| path.display().to_string())
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            ),
+                        },
+                    );
+                }
+                for (root, diffs) in root_diffs {
+                    if let Err(e) = git_commit(root, &message, diffs) {
+                        self.core_rpc.show_message(
+                            "Git Commit failure".to_owned(),
+                            ShowMessageParams {
+                                typ: MessageType::ERROR,
+                                message: e.to_string(),
+                            },
+                        );
                     }
                 }
             }
@@ -357,11 +510,17 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                 }
             }
             GitDiscardFilesChanges { files } => {
-                if let Some(workspace) = self.workspace.as_ref() {
-                    match git_discard_files_changes(
-                        workspace,
-                        files.iter().map(AsRef::as_ref),
-                    ) {
+                let roots = self.roots();
+                for root in &roots {
+                    let mut files = files
+                        .iter()
+                        .filter(|file| root_of(&roots, file) == Some(root))
+                        .map(AsRef::as_ref)
+                        .peekable();
+                    if files.peek().is_none() {
+                        continue;
+                    }
+                    match git_discard_files_changes(root, files) {
                         Ok(()) => (),
                         Err(e) => eprintln!("{e:?}"),
                     }
@@ -395,6 +554,16 @@ fn handle_notification(&mut self, rpc: ProxyNotification) {
                     false,
                 );
             }
+            LspCancelProgress { plugin_id, token } => {
+                self.catalog_rpc.send_notification(
+                    Some(plugin_id),
+                    WorkDoneProgressCancel::METHOD,
+                    WorkDoneProgressCancelParams { token },
+                    None,
+                    None,
+                    false,
+                );
+            }
         }
     }
 
@@ -405,6 +574,7 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 let buffer = Buffer::new(buffer_id, path.clone());
                 let content = buffer.rope.to_string();
                 let read_only = buffer.read_only;
+                let encoding = buffer.encoding.clone();
                 self.catalog_rpc.did_open_document(
                     &path,
                     buffer.language_id.to_string(),
@@ -415,12 +585,54 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 self.buffers.insert(path, buffer);
                 self.respond_rpc(
                     id,
-                    Ok(ProxyResponse::NewBufferResponse { content, read_only }),
+                    Ok(ProxyResponse::NewBufferResponse {
+                        content,
+                        read_only,
+                        encoding,
+                    }),
                 );
             }
+            ReopenBuffer { path, encoding } => {
+                // The buffer itself is updated by the edit of the document the
+                // content is reloaded into.
+                let result = match self.buffers.get_mut(&path) {
+                    Some(buffer) => load_file_with_encoding(&path, Some(&encoding))
+                        .map(|(content, encoding)| {
+                            buffer.encoding.clone_from(&encoding);
+                            ProxyResponse::NewBufferResponse {
+                                content,
+                                read_only: buffer.read_only,
+                                encoding,
+                            }
+                        }),
+                    None => Err(anyhow!("the file isn't open")),
+                }
+                .map_err(|e| RpcError {
+                    code: 0,
+                    message: e.to_string(),
+                });
+                self.respond_rpc(id, result);
+            }
+            SetBufferEncoding {
+                path,
+                rev,
+                encoding,
+            } => {
+                let result = match self.buffers.get_mut(&path) {
+                    Some(buffer) => buffer
+                        .set_encoding(rev, &encoding)
+                        .map(|_| ProxyResponse::Success {}),
+                    None => Err(anyhow!("the file isn't open")),
+                }
+                .map_err(|e| RpcError {
+                    code: 0,
+                    message: e.to_string(),
+                });
+                self.respond_rpc(id, result);
+            }
             BufferHead { path } => {
-                let result = if let Some(workspace) = self.workspace.as_ref() {
-                    let result = file_get_head(workspace, &path);
+                let result = if let Some(workspace) = self.repository_root(&path) {
+                    let result = file_get_head(&workspace, &path);
                     if let Ok((_blob_id, content)) = result {
                         Ok(ProxyResponse::BufferHeadResponse {
                             version: "head".to_string(),
@@ -440,16 +652,93 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 };
                 self.respond_rpc(id, result);
             }
+            BufferIndex { path } => {
+                let result = if let Some(workspace) = self.repository_root(&path) {
+                    let result = file_get_index(&workspace, &path);
+                    if let Ok(content) = result {
+                        Ok(ProxyResponse::BufferHeadResponse {
+                            version: "index".to_string(),
+                            content,
+                        })
+                    } else {
+                        Err(RpcError {
+                            code: 0,
+                            message: "can't get file index".to_string(),
+                        })
+                    }
+                } else {
+                    Err(RpcError {
+                        code: 0,
+                        message: "no workspace set".to_string(),
+                    })
+                };
+                self.respond_rpc(id, result);
+            }
+            GitStageHunk { path, hunk } => {
+                let result = match self.repository_root(&path) {
+                    Some(workspace) => git_stage_hunk(&workspace, &path, &hunk),
+                    None => Err(anyhow!("no workspace set")),
+                }
+                .map(|_| ProxyResponse::Success {})
+                .map_err(|e| {
+                    tracing::error!("{e:?}");
+                    RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    }
+                });
+                self.respond_rpc(id, result);
+            }
             GlobalSearch {
                 pattern,
                 case_sensitive,
                 whole_word,
                 is_regex,
+                include,
+                exclude,
             } => {
                 static WORKER_ID: AtomicU64 = AtomicU64::new(0);
                 let our_id = WORKER_ID.fetch_add(1, Ordering::SeqCst) + 1;
 
-                let workspace = self.workspace.clone();
+                let all_roots = self.roots();
+                // The roots the include has globs for, with those globs
+                let (roots, includes): (Vec<_>, Vec<_>) = all_roots
+                    .iter()
+                    .filter_map(|root| match include.as_deref() {
+                        Some(include) => {
+                            Some((root.clone(), Some(root_include(root, include)?)))
+                        }
+                        None => Some((root.clone(), None)),
+                    })
+                    .unzip();
+                let overrides = match roots
+                    .iter()
+                    .zip(includes.iter())
+                    .map(|(root, include)| {
+                        if include.is_none() && exclude.is_none() {
+                            return Ok(None);
+                        }
+                        search_overrides(
+                            root,
+                            include.as_deref(),
+                            exclude.as_deref(),
+                        )
+                        .map(Some)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                {
+                    Ok(overrides) => overrides,
+                    Err(err) => {
+                        self.respond_rpc(
+                            id,
+                            Err(RpcError {
+                                code: 0,
+                                message: err.to_string(),
+                            }),
+                        );
+                        return;
+                    }
+                };
                 let buffers = self
                     .buffers
                     .iter()
@@ -465,13 +754,42 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         search_in_path(
                             our_id,
                             &WORKER_ID,
-                            workspace
+                            roots
                                 .iter()
-                                .flat_map(|w| ignore::Walk::new(w).flatten())
+                                .zip(overrides.iter())
+                                .flat_map(|(root, overrides)| {
+                                    let mut walk = WalkBuilder::new(root);
+                                    if let Some(overrides) = overrides {
+                                        walk.overrides(overrides.clone());
+                                    }
+                                    walk.build().flatten()
+                                })
                                 .chain(
-                                    buffers.iter().flat_map(|p| {
-                                        ignore::Walk::new(p).flatten()
-                                    }),
+                                    buffers
+                                        .iter()
+                                        .filter(|p| {
+                                            // The open files are searched with
+                                            // the globs of their root, unless
+                                            // it's left out
+                                            let Some(root) = root_of(&all_roots, p)
+                                            else {
+                                                return true;
+                                            };
+                                            let Some(index) =
+                                                roots.iter().position(|r| r == root)
+                                            else {
+                                                return false;
+                                            };
+                                            match &overrides[index] {
+                                                Some(overrides) => !overrides
+                                                    .matched(p, false)
+                                                    .is_ignore(),
+                                                None => true,
+                                            }
+                                        })
+                                        .flat_map(|p| {
+                                            ignore::Walk::new(p).flatten()
+                                        }),
                                 )
                                 .map(|p| p.into_path()),
                             &pattern,
@@ -506,7 +824,7 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 position,
             } => {
                 let proxy_rpc = self.proxy_rpc.clone();
-                self.catalog_rpc.hover(&path, position, move |_, result| {
+                self.catalog_rpc.hover(&path, position, move |result| {
                     let result = result.map(|hover| ProxyResponse::HoverResponse {
                         request_id,
                         hover,
@@ -529,8 +847,8 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 );
             }
             GitGetRemoteFileUrl { file } => {
-                if let Some(workspace) = self.workspace.as_ref() {
-                    match git_get_remote_file_url(workspace, &file) {
+                if let Some(workspace) = self.repository_root(&file) {
+                    match git_get_remote_file_url(&workspace, &file) {
                         Ok(s) => self.proxy_rpc.handle_response(
                             id,
                             Ok(ProxyResponse::GitGetRemoteFileUrl { file_url: s }),
@@ -539,6 +857,35 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     }
                 }
             }
+            GitShowCommit { rev } => {
+                let result = if let Some(workspace) = self.workspace.as_ref() {
+                    git_show_commit(workspace, &rev)
+                        .map(|(id, content)| ProxyResponse::GitShowCommitResponse {
+                            id,
+                            content,
+                        })
+                        .map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        })
+                } else {
+                    Err(RpcError {
+                        code: 0,
+                        message: "no workspace set".to_string(),
+                    })
+                };
+                self.respond_rpc(id, result);
+            }
+            GitResolveCommit { rev } => {
+                let commit = self
+                    .workspace
+                    .as_ref()
+                    .and_then(|workspace| git_resolve_commit(workspace, &rev));
+                self.respond_rpc(
+                    id,
+                    Ok(ProxyResponse::GitResolveCommitResponse { id: commit }),
+                );
+            }
             GetDefinition {
                 request_id,
                 path,
@@ -632,7 +979,7 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     &path,
                     position,
                     trigger_kind,
-                    move |_, result| {
+                    move |result| {
                         let result = result.map(|completions| {
                             ProxyResponse::GetInlineCompletions { completions }
                         });
@@ -669,20 +1016,100 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         }
                     };
 
-                let proxy_rpc = self.proxy_rpc.clone();
-                self.catalog_rpc.get_semantic_tokens(
-                    &path,
-                    move |plugin_id, result| match result {
-                        Ok(result) => {
-                            catalog_rpc.format_semantic_tokens(
-                                plugin_id,
-                                result,
-                                text,
-                                Box::new(handle_tokens),
-                            );
-                        }
-                        Err(e) => {
-                            proxy_rpc.handle_response(id, Err(e));
+                let format_tokens = {
+                    let catalog_rpc = catalog_rpc.clone();
+                    let semantic_tokens = self.semantic_tokens.clone();
+                    let path = path.clone();
+                    move |plugin_id: PluginId, tokens: SemanticTokens| {
+                        semantic_tokens
+                            .lock()
+                            .insert(path, (plugin_id, tokens.clone()));
+                        catalog_rpc.format_semantic_tokens(
+                            plugin_id,
+                            tokens,
+                            text,
+                            Box::new(handle_tokens),
+                        );
+                    }
+                };
+
+                let get_full_tokens = {
+                    let proxy_rpc = self.proxy_rpc.clone();
+                    let catalog_rpc = catalog_rpc.clone();
+                    let path = path.clone();
+                    let format_tokens = format_tokens.clone();
+                    move || {
+                        catalog_rpc.get_semantic_tokens(
+                            &path,
+                            move |plugin_id, result| match result {
+                                Ok(tokens) => format_tokens(plugin_id, tokens),
+                                Err(e) => {
+                                    proxy_rpc.handle_response(id, Err(e));
+                                }
+                            },
+                        );
+                    }
+                };
+
+                // Ask the plugin the last tokens are from for only the changes
+                // to them, falling back to all the tokens when it can't give
+                // them.
+                let previous = self.semantic_tokens.lock().get(&path).and_then(
+                    |(plugin_id, tokens)| {
+                        Some((*plugin_id, tokens.result_id.clone()?))
+                    },
+                );
+                let Some((plugin_id, previous_result_id)) = previous else {
+                    get_full_tokens();
+                    return;
+                };
+                let semantic_tokens = self.semantic_tokens.clone();
+                let local_path = path.clone();
+                self.catalog_rpc.get_semantic_tokens_delta(
+                    &local_path,
+                    plugin_id,
+                    previous_result_id.clone(),
+                    move |result| {
+                        let tokens = match result {
+                            Ok(SemanticTokensFullDeltaResult::Tokens(tokens)) => {
+                                Some(tokens)
+                            }
+                            Ok(SemanticTokensFullDeltaResult::TokensDelta(
+                                delta,
+                            )) => {
+                                // The tokens may have been replaced by those
+                                // of another request in the meantime, which
+                                // the delta isn't relative to.
+                                semantic_tokens.lock().get(&path).and_then(
+                                    |(id, tokens)| {
+                                        if *id != plugin_id
+                                            || tokens.result_id.as_ref()
+                                                != Some(&previous_result_id)
+                                        {
+                                            return None;
+                                        }
+                                        let mut data = tokens.data.clone();
+                                        apply_semantic_tokens_edits(
+                                            &mut data,
+                                            delta.edits,
+                                        )
+                                        .then_some(SemanticTokens {
+                                            result_id: delta.result_id,
+                                            data,
+                                        })
+                                    },
+                                )
+                            }
+                            Ok(
+                                SemanticTokensFullDeltaResult::PartialTokensDelta {
+                                    ..
+                                },
+                            )
+                            | Err(_) => None,
+                        };
+                        match tokens {
+                            Some(tokens) => format_tokens(plugin_id, tokens),
+                            None => get_full_tokens(),
                         }
                     },
                 );
@@ -697,11 +1124,25 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     &path,
                     position,
                     diagnostics,
-                    move |plugin_id, result| {
-                        let result = result.map(|resp| {
-                            ProxyResponse::GetCodeActionsResponse { plugin_id, resp }
-                        });
-                        proxy_rpc.handle_response(id, result);
+                    move |responses| {
+                        proxy_rpc.handle_response(
+                            id,
+                            Ok(code_actions_response(responses)),
+                        );
+                    },
+                );
+            }
+            GetCodeActionsOfKind { path, range, kind } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.get_code_actions_of_kind(
+                    &path,
+                    range,
+                    kind,
+                    move |responses| {
+                        proxy_rpc.handle_response(
+                            id,
+                            Ok(code_actions_response(responses)),
+                        );
                     },
                 );
             }
@@ -724,7 +1165,10 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         proxy_rpc.handle_response(id, result);
                     });
             }
-            GetDocumentFormatting { path } => {
+            GetDocumentFormatting {
+                path,
+                formatter: None,
+            } => {
                 let proxy_rpc = self.proxy_rpc.clone();
                 self.catalog_rpc
                     .get_document_formatting(&path, move |_, result| {
@@ -734,6 +1178,76 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         proxy_rpc.handle_response(id, result);
                     });
             }
+            GetDocumentFormatting {
+                path,
+                formatter: Some(formatter),
+            } => {
+                let Some(rope) =
+                    self.buffers.get(&path).map(|buffer| buffer.rope.clone())
+                else {
+                    self.proxy_rpc.handle_response(
+                        id,
+                        Err(RpcError {
+                            code: 0,
+                            message: "buffer not found".to_string(),
+                        }),
+                    );
+                    return;
+                };
+                let mode = formatter.mode;
+                let text = rope.clone();
+                let request_path = path.clone();
+                let workspace = self.workspace.clone();
+                let proxy_rpc = self.proxy_rpc.clone();
+                let format = move |formatted: Rope| {
+                    thread::spawn(move || {
+                        let result = run_external_formatter(
+                            &formatter,
+                            &path,
+                            workspace.as_deref(),
+                            formatted.to_string(),
+                        )
+                        .map(|text| ProxyResponse::GetDocumentFormatting {
+                            edits: minimal_text_edits(&rope, &Rope::from(text)),
+                        })
+                        .map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        });
+                        proxy_rpc.handle_response(id, result);
+                    });
+                };
+                match mode {
+                    ExternalFormatterMode::Replace => format(text),
+                    ExternalFormatterMode::AfterLanguageServer => {
+                        self.catalog_rpc.get_document_formatting(
+                            &request_path,
+                            move |_, result| {
+                                // Without a language server formatting the
+                                // document, the command still does
+                                let formatted = match result {
+                                    Ok(edits) => apply_text_edits(&text, &edits),
+                                    Err(_) => text,
+                                };
+                                format(formatted);
+                            },
+                        );
+                    }
+                }
+            }
+            GetDocumentRangeFormatting { path, range } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.get_document_range_formatting(
+                    &path,
+                    range,
+                    move |_, result| {
+                        let result = result.map(|edits| {
+                            ProxyResponse::GetDocumentRangeFormatting { edits }
+                        });
+                        proxy_rpc.handle_response(id, result);
+                    },
+                );
+            }
             PrepareRename { path, position } => {
                 let proxy_rpc = self.proxy_rpc.clone();
                 self.catalog_rpc.prepare_rename(
@@ -764,43 +1278,39 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 );
             }
             GetFiles { .. } => {
-                let workspace = self.workspace.clone();
+                let roots = self.roots();
                 let proxy_rpc = self.proxy_rpc.clone();
                 thread::spawn(move || {
-                    let result = if let Some(workspace) = workspace {
+                    let mut items = Vec::new();
+                    for workspace in &roots {
                         let git_folder =
-                            ignore::overrides::OverrideBuilder::new(&workspace)
+                            ignore::overrides::OverrideBuilder::new(workspace)
                                 .add("!.git/")
                                 .map(|git_folder| git_folder.build());
 
-                        let walker = match git_folder {
-                            Ok(Ok(git_folder)) => {
-                                ignore::WalkBuilder::new(&workspace)
-                                    .hidden(false)
-                                    .parents(false)
-                                    .require_git(false)
-                                    .overrides(git_folder)
-                                    .build()
-                            }
-                            _ => ignore::WalkBuilder::new(&workspace)
-                                .parents(false)
-                                .require_git(false)
-                                .build(),
-                        };
+                        let mut walker = ignore::WalkBuilder::new(workspace);
+                        walker.parents(false).require_git(false);
+                        if let Ok(Ok(git_folder)) = git_folder {
+                            walker.hidden(false).overrides(git_folder);
+                        }
+                        // The roots nested in this one are walked on their own.
+                        let nested = nested_roots(&roots, workspace);
+                        walker.filter_entry(move |entry| {
+                            !nested.iter().any(|root| entry.path() == root)
+                        });
 
-                        let mut items = Vec::new();
-                        for path in walker.flatten() {
+                        for path in walker.build().flatten() {
                             if let Some(file_type) = path.file_type() {
                                 if file_type.is_file() {
                                     items.push(path.into_path());
                                 }
                             }
                         }
-                        Ok(ProxyResponse::GetFilesResponse { items })
-                    } else {
-                        Ok(ProxyResponse::GetFilesResponse { items: Vec::new() })
-                    };
-                    proxy_rpc.handle_response(id, result);
+                    }
+                    proxy_rpc.handle_response(
+                        id,
+                        Ok(ProxyResponse::GetFilesResponse { items }),
+                    );
                 });
             }
             GetOpenFilesContent {} => {
@@ -849,6 +1359,31 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     proxy_rpc.handle_response(id, result);
                 });
             }
+            ReadFile { path } => {
+                let result = load_file(&path)
+                    .map(|content| ProxyResponse::ReadFileResponse { content })
+                    .map_err(|e| RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
+            LocalHistory { path } => {
+                let timestamps = local_history::versions(&path);
+                self.respond_rpc(
+                    id,
+                    Ok(ProxyResponse::LocalHistoryResponse { timestamps }),
+                );
+            }
+            LocalHistoryContent { path, timestamp } => {
+                let result = local_history::content(&path, timestamp)
+                    .map(|content| ProxyResponse::ReadFileResponse { content })
+                    .map_err(|e| RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
             Save {
                 rev,
                 path,
@@ -858,8 +1393,20 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 let result = buffer
                     .save(rev, create_parents)
                     .map(|_r| {
+                        if let Err(err) =
+                            local_history::record(&path, &buffer.rope.to_string())
+                        {
+                            tracing::error!("{:?}", err);
+                        }
                         self.catalog_rpc
                             .did_save_text_document(&path, buffer.rope.clone());
+                        if let Err(err) = self.catalog_rpc.plugin_event(
+                            lapce_rpc::plugin::PluginEvent::DocumentSaved {
+                                path: path.clone(),
+                            },
+                        ) {
+                            tracing::error!("{:?}", err);
+                        }
                         ProxyResponse::SaveResponse {}
                     })
                     .map_err(|e| RpcError {
@@ -880,7 +1427,14 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 buffer.rev = rev;
                 let result = buffer
                     .save(rev, create_parents)
-                    .map(|_| ProxyResponse::Success {})
+                    .map(|_| {
+                        if let Err(err) =
+                            local_history::record(&path, &buffer.rope.to_string())
+                        {
+                            tracing::error!("{:?}", err);
+                        }
+                        ProxyResponse::Success {}
+                    })
                     .map_err(|e| RpcError {
                         code: 0,
                         message: e.to_string(),
@@ -923,6 +1477,18 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     });
                 self.respond_rpc(id, result);
             }
+            TrashPathWithBackup { path } => {
+                let result = backup::backup(&path)
+                    .and_then(|backup| {
+                        trash::delete(&path)?;
+                        Ok(ProxyResponse::CreatePathResponse { path: backup })
+                    })
+                    .map_err(|e| RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
             DuplicatePath {
                 existing_path,
                 new_path,
@@ -980,8 +1546,9 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     }
                 });
 
-                let result = result
-                    .and_then(|_| fs::rename(&from, &to).map_err(|e| e.to_string()));
+                let result = result.and_then(|_| {
+                    backup::rename(&from, &to).map_err(|e| e.to_string())
+                });
 
                 let result = result
                     .map(|_| {
@@ -1026,6 +1593,9 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                                 self.buffers.insert(to.clone(), buffer);
                             }
                         }
+                        self.semantic_tokens
+                            .lock()
+                            .retain(|path, _| !path.starts_with(&from));
 
                         ProxyResponse::CreatePathResponse { path: to }
                     })
@@ -1078,6 +1648,46 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                     },
                 );
             }
+            GetLinkedEditingRanges { path, position } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.get_linked_editing_ranges(
+                    path.as_path(),
+                    position,
+                    move |_, result| {
+                        let result = result.map(|ranges| {
+                            ProxyResponse::GetLinkedEditingRanges { ranges }
+                        });
+                        proxy_rpc.handle_response(id, result);
+                    },
+                );
+            }
+            GetDocumentLinks { path } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.get_document_links(
+                    path.as_path(),
+                    move |plugin_id, result| {
+                        let result = result.map(|links| {
+                            ProxyResponse::GetDocumentLinks { plugin_id, links }
+                        });
+                        proxy_rpc.handle_response(id, result);
+                    },
+                );
+            }
+            DocumentLinkResolve { plugin_id, link } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.document_link_resolve(
+                    *link,
+                    plugin_id,
+                    move |result| {
+                        let result = result.map(|link| {
+                            ProxyResponse::DocumentLinkResolveResponse {
+                                link: Box::new(link),
+                            }
+                        });
+                        proxy_rpc.handle_response(id, result);
+                    },
+                );
+            }
             CodeActionResolve {
                 action_item,
                 plugin_id,
@@ -1108,6 +1718,72 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                         );
                     });
             }
+            DapSetVariable {
+                dap_id,
+                reference,
+                name,
+                value,
+            } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.dap_set_variable(
+                    dap_id,
+                    reference,
+                    name,
+                    value,
+                    move |result| {
+                        proxy_rpc.handle_response(
+                            id,
+                            result.map(|response| {
+                                ProxyResponse::DapSetVariableResponse { response }
+                            }),
+                        );
+                    },
+                );
+            }
+            DapEvaluate {
+                dap_id,
+                expression,
+                frame_id,
+                context,
+            } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.dap_evaluate(
+                    dap_id,
+                    expression,
+                    frame_id,
+                    context,
+                    move |result| {
+                        proxy_rpc.handle_response(
+                            id,
+                            result.map(|response| {
+                                ProxyResponse::DapEvaluateResponse { response }
+                            }),
+                        );
+                    },
+                );
+            }
+            DapCompletions {
+                dap_id,
+                text,
+                column,
+                frame_id,
+            } => {
+                let proxy_rpc = self.proxy_rpc.clone();
+                self.catalog_rpc.dap_completions(
+                    dap_id,
+                    text,
+                    column,
+                    frame_id,
+                    move |result| {
+                        proxy_rpc.handle_response(
+                            id,
+                            result.map(|targets| {
+                                ProxyResponse::DapCompletionsResponse { targets }
+                            }),
+                        );
+                    },
+                );
+            }
             DapGetScopes { dap_id, frame_id } => {
                 let proxy_rpc = self.proxy_rpc.clone();
                 self.catalog_rpc
@@ -1201,6 +1877,49 @@ fn handle_request(&mut self, id: RequestId, rpc: ProxyRequest) {
                 let resp = ProxyResponse::ReferencesResolveResponse { items };
                 self.proxy_rpc.handle_response(id, Ok(resp));
             }
+            TerminalEnvironment { term_id } => {
+                let result = self
+                    .terminal_environments
+                    .get(&term_id)
+                    .map(|(pid, spawned)| {
+                        // What the shell was really spawned with, where it can
+                        // be read.
+                        let read = pid.and_then(process_environment);
+                        let environment = lapce_rpc::terminal::TerminalEnvironment {
+                            from_process: read.is_some(),
+                            spawned: read.unwrap_or_else(|| spawned.clone()),
+                            foreground: pid.and_then(foreground_environment),
+                        };
+                        ProxyResponse::TerminalEnvironmentResponse { environment }
+                    })
+                    .ok_or_else(|| RpcError {
+                        code: 0,
+                        message: "terminal not found".to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
+            GetTasks {} => {
+                let result = match self.workspace.as_deref() {
+                    Some(workspace) => workspace_tasks(workspace),
+                    None => Ok(Vec::new()),
+                };
+                let result = result
+                    .map(|tasks| ProxyResponse::GetTasksResponse { tasks })
+                    .map_err(|e| RpcError {
+                        code: 0,
+                        message: e.to_string(),
+                    });
+                self.respond_rpc(id, result);
+            }
+            ReattachTerminals { term_ids } => {
+                let live_terminals = self.live_terminals.lock();
+                let alive = term_ids
+                    .into_iter()
+                    .filter(|term_id| live_terminals.contains(term_id))
+                    .collect();
+                let resp = ProxyResponse::ReattachTerminalsResponse { alive };
+                self.proxy_rpc.handle_response(id, Ok(resp));
+            }
         }
     }
 }
@@ -1214,21 +1933,44 @@ pub fn new(core_rpc: CoreRpcHandler, proxy_rpc: ProxyRpcHandler) -> Self {
 
         Self {
             workspace: None,
+            workspace_folders: Arc::new(Mutex::new(Vec::new())),
             proxy_rpc,
             core_rpc,
             catalog_rpc: plugin_rpc,
             buffers: HashMap::new(),
+            semantic_tokens: Arc::new(Mutex::new(HashMap::new())),
             terminals: HashMap::new(),
+            terminal_environments: HashMap::new(),
+            sample_terminal_usage: Arc::new(AtomicBool::new(false)),
+            live_terminals: Arc::new(Mutex::new(HashSet::new())),
+            tasks: Arc::new(Mutex::new(HashMap::new())),
             file_watcher,
             window_id: 1,
             tab_id: 1,
         }
     }
 
+    pub fn live_terminals(&self) -> Arc<Mutex<HashSet<TermId>>> {
+        self.live_terminals.clone()
+    }
+
     fn respond_rpc(&self, id: RequestId, result: Result<ProxyResponse, RpcError>) {
         self.proxy_rpc.handle_response(id, result);
     }
 
+    /// The root of the workspace, followed by the folders added to it.
+    fn roots(&self) -> Vec<PathBuf> {
+        workspace_roots(self.workspace.as_deref(), &self.workspace_folders.lock())
+    }
+
+    /// The root whose repository `path` is in, the workspace's for a path
+    /// under no root.
+    fn repository_root(&self, path: &Path) -> Option<PathBuf> {
+        root_of(&self.roots(), path)
+            .map(Path::to_path_buf)
+            .or_else(|| self.workspace.clone())
+    }
+
     fn get_buffer_or_insert(&mut self, path: PathBuf) -> &mut Buffer {
         self.buffers
             .entry(path.clone())
@@ -1240,6 +1982,7 @@ struct FileWatchNotifier {
     core_rpc: CoreRpcHandler,
     proxy_rpc: ProxyRpcHandler,
     workspace: Option<PathBuf>,
+    workspace_folders: Arc<Mutex<Vec<PathBuf>>>,
     workspace_fs_change_handler: Arc<Mutex<Option<Sender<bool>>>>,
     last_diff: Arc<Mutex<DiffInfo>>,
 }
@@ -1253,22 +1996,25 @@ fn notify(&self, events: Vec<(WatchToken, notify::Event)>) {
 impl FileWatchNotifier {
     fn new(
         workspace: Option<PathBuf>,
+        workspace_folders: Arc<Mutex<Vec<PathBuf>>>,
         core_rpc: CoreRpcHandler,
         proxy_rpc: ProxyRpcHandler,
     ) -> Self {
         let notifier = Self {
             workspace,
+            workspace_folders,
             core_rpc,
             proxy_rpc,
             workspace_fs_change_handler: Arc::new(Mutex::new(None)),
             last_diff: Arc::new(Mutex::new(DiffInfo::default())),
         };
 
-        if let Some(workspace) = notifier.workspace.clone() {
+        let roots = notifier.roots();
+        if !roots.is_empty() {
             let core_rpc = notifier.core_rpc.clone();
             let last_diff = notifier.last_diff.clone();
             thread::spawn(move || {
-                if let Some(diff) = git_diff_new(&workspace) {
+                if let Some(diff) = git_diff_roots(&roots) {
                     core_rpc.diff_info(diff.clone());
                     *last_diff.lock() = diff;
                 }
@@ -1278,6 +2024,10 @@ fn new(
         notifier
     }
 
+    fn roots(&self) -> Vec<PathBuf> {
+        workspace_roots(self.workspace.as_deref(), &self.workspace_folders.lock())
+    }
+
     fn handle_fs_events(&self, events: Vec<(WatchToken, notify::Event)>) {
         for (token, event) in events {
             match token {
@@ -1337,7 +2087,7 @@ fn handle_workspace_fs_event(&self, event: notify::Event) {
 
         let local_handler = self.workspace_fs_change_handler.clone();
         let core_rpc = self.core_rpc.clone();
-        let workspace = self.workspace.clone().unwrap();
+        let roots = self.roots();
         let last_diff = self.last_diff.clone();
         thread::spawn(move || {
             thread::sleep(Duration::from_millis(500));
@@ -1356,7 +2106,7 @@ fn handle_workspace_fs_event(&self, event: notify::Event) {
             if explorer_change {
                 core_rpc.workspace_file_change();
             }
-            if let Some(diff) = git_diff_new(&workspace) {
+            if let Some(diff) = git_diff_roots(&roots) {
                 let mut last_diff = last_diff.lock();
                 if diff != *last_diff {
                     core_rpc.diff_info(diff.clone());
@@ -1377,6 +2127,55 @@ pub struct DiffHunk {
     pub header: String,
 }
 
+/// The root of the workspace, if there's one, followed by the folders added
+/// to it, each only once.
+fn workspace_roots(workspace: Option<&Path>, folders: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for root in workspace
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(folders.iter().cloned())
+    {
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    roots
+}
+
+/// The ones of `roots` inside `root`.
+fn nested_roots(roots: &[PathBuf], root: &Path) -> Vec<PathBuf> {
+    roots
+        .iter()
+        .filter(|other| *other != root && other.starts_with(root))
+        .cloned()
+        .collect()
+}
+
+/// The innermost of `roots` holding `path`.
+fn root_of<'a>(roots: &'a [PathBuf], path: &Path) -> Option<&'a Path> {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.as_os_str().len())
+        .map(PathBuf::as_path)
+}
+
+/// The state of the repository of the first of `roots` with one, with the
+/// changed files of the repositories of the others.
+fn git_diff_roots(roots: &[PathBuf]) -> Option<DiffInfo> {
+    let mut infos = roots.iter().filter_map(|root| git_diff_new(root));
+    let mut info = infos.next()?;
+    for other in infos {
+        for diff in other.diffs {
+            if !info.diffs.contains(&diff) {
+                info.diffs.push(diff);
+            }
+        }
+    }
+    Some(info)
+}
+
 fn git_init(workspace_path: &Path) -> Result<()> {
     if Repository::discover(workspace_path).is_err() {
         Repository::init(workspace_path)?;
@@ -1487,6 +2286,25 @@ fn git_discard_workspace_changes(workspace_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Apply `hunk`, a patch against the content of the file in the index, to the
+/// index.
+fn git_stage_hunk(workspace_path: &Path, path: &Path, hunk: &str) -> Result<()> {
+    let repo = Repository::discover(workspace_path)?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("repository has no working directory"))?;
+    let path = path
+        .strip_prefix(workdir)
+        .context("file is outside of the repository")?
+        .to_string_lossy()
+        .replace('\\', "/");
+    let patch =
+        format!("diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n{hunk}");
+    let diff = git2::Diff::from_buffer(patch.as_bytes())?;
+    repo.apply(&diff, git2::ApplyLocation::Index, None)?;
+    Ok(())
+}
+
 fn git_delta_format(
     workspace_path: &Path,
     delta: &git2::DiffDelta,
@@ -1626,6 +2444,64 @@ fn file_get_head(workspace_path: &Path, path: &Path) -> Result<(String, String)>
     Ok((id, content))
 }
 
+/// The content of the file as staged in the index.
+fn file_get_index(workspace_path: &Path, path: &Path) -> Result<String> {
+    let repo = Repository::discover(workspace_path)?;
+    let index = repo.index()?;
+    let entry = index
+        .get_path(path.strip_prefix(workspace_path)?, 0)
+        .ok_or_else(|| anyhow!("the file isn't in the index"))?;
+    let blob = repo.find_blob(entry.id)?;
+    let content = std::str::from_utf8(blob.content())
+        .with_context(|| "content bytes to string")?
+        .to_string();
+    Ok(content)
+}
+
+/// Describe the commit `rev` resolves to like `git show` does, returning its id
+/// and the description.
+fn git_show_commit(workspace_path: &Path, rev: &str) -> Result<(String, String)> {
+    let repo = Repository::discover(workspace_path)?;
+    // Like `git cat-file -e <rev>^{commit}`, anything but a commit is an error.
+    let commit = repo.revparse_single(rev)?.peel_to_commit()?;
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree()?),
+        Err(_) => None,
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+
+    let author = commit.author();
+    let mut content = format!(
+        "commit {}\nAuthor: {} <{}>\n\n",
+        commit.id(),
+        author.name().unwrap_or_default(),
+        author.email().unwrap_or_default()
+    );
+    for line in commit.message().unwrap_or_default().lines() {
+        content.push_str("    ");
+        content.push_str(line);
+        content.push('\n');
+    }
+    content.push('\n');
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            content.push(line.origin());
+        }
+        content.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })?;
+
+    Ok((commit.id().to_string(), content))
+}
+
+/// The id of the commit `rev` names, if it's one.
+fn git_resolve_commit(workspace_path: &Path, rev: &str) -> Option<String> {
+    let repo = Repository::discover(workspace_path).ok()?;
+    let commit = repo.revparse_single(rev).ok()?.peel_to_commit().ok()?;
+    Some(commit.id().to_string())
+}
+
 fn git_get_remote_file_url(workspace_path: &Path, file: &Path) -> Result<String> {
     let repo = Repository::discover(workspace_path)?;
     let head = repo.head()?;
@@ -1671,6 +2547,72 @@ fn git_get_remote_file_url(workspace_path: &Path, file: &Path) -> Result<String>
     Ok(url)
 }
 
+/// The code actions of all the language servers, each with the plugin to
+/// resolve it with.
+fn code_actions_response(
+    responses: Vec<(PluginId, CodeActionResponse)>,
+) -> ProxyResponse {
+    let actions = responses
+        .into_iter()
+        .flat_map(|(plugin_id, resp)| {
+            resp.into_iter().map(move |action| (plugin_id, action))
+        })
+        .collect();
+    ProxyResponse::GetCodeActionsResponse { actions }
+}
+
+/// The globs of `include` that apply to `root`. A glob starting with
+/// `./<name>/` is relative to the roots named `name`, and doesn't apply to the
+/// others, so that a search can be limited to a folder of one of the roots.
+/// `None` if all the globs are for other roots, leaving `root` out.
+fn root_include(root: &Path, include: &str) -> Option<String> {
+    let name = root.file_name().map(|name| name.to_string_lossy());
+    let mut globs = include
+        .split(',')
+        .map(str::trim)
+        .filter(|glob| !glob.is_empty())
+        .peekable();
+    if globs.peek().is_none() {
+        return Some(String::new());
+    }
+    let globs = globs
+        .filter_map(|glob| match glob.strip_prefix("./") {
+            Some(glob) => {
+                let (root_name, glob) = glob.split_once('/')?;
+                (Some(root_name) == name.as_deref()).then_some(glob)
+            }
+            None => Some(glob),
+        })
+        .collect::<Vec<_>>();
+    (!globs.is_empty()).then(|| globs.join(","))
+}
+
+/// The overrides keeping the walk of `workspace` to the files matching the
+/// comma separated `include` globs, and out of those matching `exclude`.
+fn search_overrides(
+    workspace: &Path,
+    include: Option<&str>,
+    exclude: Option<&str>,
+) -> Result<Override, ignore::Error> {
+    let globs = |globs: Option<&str>| {
+        globs
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|glob| !glob.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    let mut builder = OverrideBuilder::new(workspace);
+    for glob in globs(include) {
+        builder.add(&glob)?;
+    }
+    for glob in globs(exclude) {
+        builder.add(&format!("!{glob}"))?;
+    }
+    builder.build()
+}
+
 fn search_in_path(
     id: u64,
     current_id: &AtomicU64,
@@ -1692,7 +2634,9 @@ fn search_in_path(
         code: 0,
         message: "can't build matcher".to_string(),
     })?;
-    let mut searcher = SearcherBuilder::new().build();
+    let mut searcher = SearcherBuilder::new()
+        .multi_line(is_regex && is_multiline_regex(pattern))
+        .build();
 
     for path in paths {
         if current_id.load(Ordering::SeqCst) != id {
@@ -1707,40 +2651,60 @@ fn search_in_path(
             if let Err(err) = searcher.search_path(
                 &matcher,
                 path.clone(),
-                UTF8(|lnum, line| {
+                UTF8(|lnum, lines| {
                     if current_id.load(Ordering::SeqCst) != id {
                         return Ok(false);
                     }
 
-                    let mymatch = matcher.find(line.as_bytes())?.unwrap();
-                    let line = if line.len() > 200 {
-                        // Shorten the line to avoid sending over absurdly long-lines
-                        // (such as in minified javascript)
-                        // Note that the start/end are column based, not absolute from the
-                        // start of the file.
-                        let left_keep = line[..mymatch.start()]
-                            .chars()
-                            .rev()
-                            .take(100)
-                            .map(|c| c.len_utf8())
-                            .sum::<usize>();
-                        let right_keep = line[mymatch.end()..]
-                            .chars()
-                            .take(100)
-                            .map(|c| c.len_utf8())
-                            .sum::<usize>();
-                        let display_range =
-                            mymatch.start() - left_keep..mymatch.end() + right_keep;
-                        line[display_range].to_string()
-                    } else {
-                        line.to_string()
+                    let first = matcher.find(lines.as_bytes())?.unwrap();
+                    // The matches of a multi-line search are shown on the line
+                    // they start on
+                    let line = match lines.find('\n') {
+                        Some(i) if i < first.start() => lines,
+                        Some(i) => &lines[..i + 1],
+                        None => lines,
                     };
-                    line_matches.push(SearchMatch {
-                        line: lnum as usize,
-                        start: mymatch.start(),
-                        end: mymatch.end(),
-                        line_content: line,
-                    });
+                    let mut line_matches_found = Vec::new();
+                    matcher.find_iter(lines.as_bytes(), |mymatch| {
+                        if mymatch.start() >= line.len() {
+                            return false;
+                        }
+                        line_matches_found.push(grep_matcher::Match::new(
+                            mymatch.start(),
+                            mymatch.end().min(line.len()),
+                        ));
+                        true
+                    })?;
+                    for mymatch in line_matches_found {
+                        let line_content = if line.len() > 200 {
+                            // Shorten the line to avoid sending over absurdly long-lines
+                            // (such as in minified javascript)
+                            // Note that the start/end are column based, not absolute from the
+                            // start of the file.
+                            let left_keep = line[..mymatch.start()]
+                                .chars()
+                                .rev()
+                                .take(100)
+                                .map(|c| c.len_utf8())
+                                .sum::<usize>();
+                            let right_keep = line[mymatch.end()..]
+                                .chars()
+                                .take(100)
+                                .map(|c| c.len_utf8())
+                                .sum::<usize>();
+                            let display_range = mymatch.start() - left_keep
+                                ..mymatch.end() + right_keep;
+                            line[display_range].to_string()
+                        } else {
+                            line.to_string()
+                        };
+                        line_matches.push(SearchMatch {
+                            line: lnum as usize,
+                            start: mymatch.start(),
+                            end: mymatch.end(),
+                            line_content,
+                        });
+                    }
                     Ok(true)
                 }),
             ) {
@@ -1756,3 +2720,84 @@ fn search_in_path(
 
     Ok(ProxyResponse::GlobalSearchResponse { matches })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{
+        nested_roots, root_include, root_of, search_overrides, workspace_roots,
+    };
+
+    #[test]
+    fn test_search_overrides() {
+        let workspace = Path::new("/ws");
+
+        let overrides =
+            search_overrides(workspace, Some("*.rs, *.toml ,"), Some("target/**"))
+                .unwrap();
+        assert!(overrides.matched("/ws/src/main.rs", false).is_whitelist());
+        assert!(overrides.matched("/ws/Cargo.toml", false).is_whitelist());
+        assert!(overrides.matched("/ws/README.md", false).is_ignore());
+        assert!(overrides
+            .matched("/ws/target/debug/build.rs", false)
+            .is_ignore());
+
+        let overrides = search_overrides(workspace, None, Some("*.md")).unwrap();
+        assert!(overrides.matched("/ws/README.md", false).is_ignore());
+        assert!(overrides.matched("/ws/src/main.rs", false).is_none());
+
+        let overrides = search_overrides(workspace, Some(" , "), None).unwrap();
+        assert!(overrides.is_empty());
+        assert!(overrides.matched("/ws/src/main.rs", false).is_none());
+    }
+
+    #[test]
+    fn test_root_include() {
+        let root = Path::new("/ws/app");
+        assert_eq!(root_include(root, "*.rs, ").as_deref(), Some("*.rs"));
+        assert_eq!(
+            root_include(root, "./app/src/**,*.toml").as_deref(),
+            Some("src/**,*.toml")
+        );
+        assert_eq!(root_include(root, "./lib/src/**"), None);
+        assert_eq!(
+            root_include(root, "./lib/src/**, *.md").as_deref(),
+            Some("*.md")
+        );
+        assert_eq!(root_include(root, " , ").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_root_of() {
+        let roots = workspace_roots(
+            Some(Path::new("/ws")),
+            &[PathBuf::from("/other"), PathBuf::from("/ws/nested")],
+        );
+        assert_eq!(roots, ["/ws", "/other", "/ws/nested"].map(PathBuf::from));
+        assert!(workspace_roots(None, &[]).is_empty());
+        // A folder added twice, or as the workspace, is a single root.
+        assert_eq!(
+            workspace_roots(
+                Some(Path::new("/ws")),
+                &[
+                    PathBuf::from("/ws"),
+                    PathBuf::from("/a"),
+                    PathBuf::from("/a")
+                ],
+            ),
+            ["/ws", "/a"].map(PathBuf::from)
+        );
+        assert_eq!(
+            nested_roots(&roots, Path::new("/ws")),
+            [PathBuf::from("/ws/nested")]
+        );
+        assert!(nested_roots(&roots, Path::new("/other")).is_empty());
+
+        let root_of = |path: &str| root_of(&roots, Path::new(path));
+        assert_eq!(root_of("/ws/src/main.rs"), Some(Path::new("/ws")));
+        assert_eq!(root_of("/ws/nested/lib.rs"), Some(Path::new("/ws/nested")));
+        assert_eq!(root_of("/other"), Some(Path::new("/other")));
+        assert_eq!(root_of("/ws-other/main.rs"), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Running the external formatter commands configured for a language, and
+//! turning their output into the smallest edits to the document.
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::{
+    io::{Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+    sync::{atomic::AtomicU64, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use floem_editor_core::buffer::diff::{rope_diff, DiffLines};
+use lapce_core::encoding::{offset_utf16_to_utf8_str, offset_utf8_to_utf16_str};
+use lapce_rpc::proxy::ExternalFormatter;
+use lapce_xi_rope::Rope;
+use lsp_types::{Position, Range, TextEdit};
+
+/// The lines of the old text, and of the new text replacing them.
+type LineChange = (std::ops::Range<usize>, std::ops::Range<usize>);
+
+/// How long a formatter can run before it's killed, so one that hangs doesn't
+/// stay around after the editor gave up waiting for it.
+const FORMATTER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Run `formatter` with `text` on its stdin and return what it prints on its
+/// stdout. `{file}` and `{workspace}` in its arguments are replaced by the
+/// path of the document and of the workspace.
+///
+/// The formatter is killed if it doesn't exit within [`FORMATTER_TIMEOUT`].
+pub fn run_external_formatter(
+    formatter: &ExternalFormatter,
+    path: &Path,
+    workspace: Option<&Path>,
+    text: String,
+) -> Result<String> {
+    run_formatter(formatter, path, workspace, text, FORMATTER_TIMEOUT)
+}
+
+fn run_formatter(
+    formatter: &ExternalFormatter,
+    path: &Path,
+    workspace: Option<&Path>,
+    text: String,
+    timeout: Duration,
+) -> Result<String> {
+    let file = path.to_string_lossy();
+    let workspace_dir = workspace
+        .map(|workspace| workspace.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let args = formatter.args.iter().map(|arg| {
+        arg.replace("{file}", &file)
+            .replace("{workspace}", &workspace_dir)
+    });
+
+    let mut process = Command::new(&formatter.command);
+    process.args(args);
+    if let Some(dir) = workspace.or_else(|| path.parent()) {
+        process.current_dir(dir);
+    }
+    #[cfg(target_os = "windows")]
+    process.creation_flags(0x08000000);
+    let mut child = process
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("can't run {}: {e}", formatter.command))?;
+
+    // Write from another thread, so that a formatter printing before it has
+    // read all of its input can't block on a full pipe.
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("can't write to {}", formatter.command))?;
+    let writer = thread::spawn(move || stdin.write_all(text.as_bytes()));
+    let stdout = child.stdout.take().map(read_to_end);
+    let stderr = child.stderr.take().map(read_to_end);
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            // The pipes are closed once it's gone, which ends the threads.
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!(
+                "{} didn't finish within {}ms",
+                formatter.command,
+                timeout.as_millis()
+            ));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    let join = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+        reader
+            .and_then(|reader| reader.join().ok())
+            .unwrap_or_default()
+    };
+    let (stdout, stderr) = (join(stdout), join(stderr));
+    if let Ok(Err(err)) = writer.join() {
+        return Err(anyhow!("can't write to {}: {err}", formatter.command));
+    }
+    if !status.success() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            formatter.command,
+            String::from_utf8_lossy(&stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8(stdout)?)
+}
+
+/// Read all of `pipe` on another thread.
+fn read_to_end(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        buf
+    })
+}
+
+/// Apply the edits of a language server to `rope`.
+pub fn apply_text_edits(rope: &Rope, edits: &[TextEdit]) -> Rope {
+    let mut edits = edits
+        .iter()
+        .map(|edit| {
+            let start = offset_of_position(rope, &edit.range.start);
+            let end = offset_of_position(rope, &edit.range.end).max(start);
+            (start, end, edit.new_text.as_str())
+        })
+        .collect::<Vec<_>>();
+    // From the end, so the offsets of the edits still to apply don't move
+    edits.sort_by_key(|(start, end, _)| (*start, *end));
+
+    let mut rope = rope.clone();
+    for (start, end, text) in edits.into_iter().rev() {
+        rope.edit(start..end, text);
+    }
+    rope
+}
+
+/// The edits turning `old` into `new`, replacing only the lines that differ
+/// so that the cursors and folds elsewhere in the document stay put.
+pub fn minimal_text_edits(old: &Rope, new: &Rope) -> Vec<TextEdit> {
+    let Some(changes) = rope_diff(
+        old.clone(),
+        new.clone(),
+        0,
+        Arc::new(AtomicU64::new(0)),
+        None,
+    ) else {
+        return Vec::new();
+    };
+
+    let mut edits = Vec::new();
+    // The lines of `old` and of `new` replaced by the change being gathered
+    let mut pending: Option<LineChange> = None;
+    let mut flush = |pending: &mut Option<LineChange>| {
+        if let Some((old_lines, new_lines)) = pending.take() {
+            let start = old.offset_of_line(old_lines.start);
+            let end = old.offset_of_line(old_lines.end);
+            let new_text = new.slice_to_cow(
+                new.offset_of_line(new_lines.start)
+                    ..new.offset_of_line(new_lines.end),
+            );
+            edits.push(TextEdit {
+                range: Range {
+                    start: position_of_offset(old, start),
+                    end: position_of_offset(old, end),
+                },
+                new_text: new_text.to_string(),
+            });
+        }
+    };
+
+    let (mut old_line, mut new_line) = (0, 0);
+    for change in changes {
+        match change {
+            DiffLines::Both(info) => {
+                flush(&mut pending);
+                old_line = info.left.end;
+                new_line = info.right.end;
+            }
+            DiffLines::Left(range) => {
+                let (old_lines, _) = pending
+                    .get_or_insert((range.start..range.start, new_line..new_line));
+                old_lines.end = range.end;
+                old_line = range.end;
+            }
+            DiffLines::Right(range) => {
+                let (_, new_lines) = pending
+                    .get_or_insert((old_line..old_line, range.start..range.start));
+                new_lines.end = range.end;
+                new_line = range.end;
+            }
+        }
+    }
+    flush(&mut pending);
+    edits
+}
+
+fn offset_of_position(rope: &Rope, position: &Position) -> usize {
+    let last_line = rope.line_of_offset(rope.len());
+    let line = (position.line as usize).min(last_line);
+    let line_start = rope.offset_of_line(line);
+    let line_end = rope.offset_of_line(line + 1);
+    let line_text = rope.slice_to_cow(line_start..line_end);
+    line_start + offset_utf16_to_utf8_str(&line_text, position.character as usize)
+}
+
+fn position_of_offset(rope: &Rope, offset: usize) -> Position {
+    let line = rope.line_of_offset(offset);
+    let line_start = rope.offset_of_line(line);
+    let line_text = rope.slice_to_cow(line_start..offset);
+    Position {
+        line: line as u32,
+        character: offset_utf8_to_utf16_str(&line_text, offset - line_start) as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lapce_xi_rope::Rope;
+    use lsp_types::{Position, Range, TextEdit};
+
+    use super::{apply_text_edits, minimal_text_edits};
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_formatter() {
+        use std::{path::Path, time::Duration};
+
+        use lapce_rpc::proxy::{ExternalFormatter, ExternalFormatterMode};
+
+        use super::run_formatter;
+
+        let formatter = |script: &str| ExternalFormatter {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            mode: ExternalFormatterMode::Replace,
+        };
+        let run = |script: &str| {
+            run_formatter(
+                &formatter(script),
+                Path::new("/tmp/main.rs"),
+                None,
+                "a b\n".to_string(),
+                Duration::from_millis(500),
+            )
+        };
+
+        assert_eq!(run("tr ' ' '\n'").unwrap(), "a\nb\n");
+        assert!(run("echo broken >&2; exit 1")
+            .unwrap_err()
+            .to_string()
+            .contains("broken"));
+        // A formatter that hangs is killed.
+        assert!(run("sleep 5").unwrap_err().to_string().contains("500ms"));
+    }
+
+    #[test]
+    fn test_minimal_text_edits() {
+        let old = Rope::from("fn main() {\nlet a=1;\n}\n");
+        let new = Rope::from("fn main() {\n    let a = 1;\n}\n");
+        let edits = minimal_text_edits(&old, &new);
+        assert_eq!(
+            edits,
+            vec![TextEdit {
+                range: Range {
+                    start: Position::new(1, 0),
+                    end: Position::new(2, 0),
+                },
+                new_text: "    let a = 1;\n".to_string(),
+            }]
+        );
+        assert_eq!(apply_text_edits(&old, &edits).to_string(), new.to_string());
+
+        assert!(minimal_text_edits(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_apply_text_edits() {
+        let rope = Rope::from("a\nbc\n");
+        let edits = vec![
+            TextEdit {
+                range: Range {
+                    start: Position::new(1, 1),
+                    end: Position::new(1, 2),
+                },
+                new_text: "d".to_string(),
+            },
+            TextEdit {
+                range: Range {
+                    start: Position::new(0, 0),
+                    end: Position::new(0, 0),
+                },
+                new_text: "x".to_string(),
+            },
+        ];
+        assert_eq!(apply_text_edits(&rope, &edits).to_string(), "xa\nbd\n");
+    }
+}
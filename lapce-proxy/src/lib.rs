@@ -1,9 +1,15 @@
 #![allow(clippy::manual_clamp)]
 
+pub mod backup;
 pub mod buffer;
 pub mod cli;
 pub mod dispatch;
+pub mod formatter;
+pub mod local_history;
 pub mod plugin;
+#[cfg(unix)]
+pub mod session;
+pub mod task;
 pub mod terminal;
 pub mod watcher;
 
@@ -23,11 +29,15 @@
     core::{CoreRpc, CoreRpcHandler},
     file::PathObject,
     proxy::{ProxyMessage, ProxyNotification, ProxyRpcHandler},
-    stdio::stdio_transport,
+    stdio::{reader_transport, reattachable_writer_transport, ReattachableWriter},
     RpcMessage,
 };
 use tracing::error;
 
+/// Printed by `--reattach` once it's connected to the proxy kept alive for
+/// the session, before relaying the rpc messages.
+pub const REATTACHED_MESSAGE: &str = "lapce-proxy-reattached";
+
 #[derive(Parser)]
 #[clap(name = "Lapce-proxy")]
 #[clap(version = meta::VERSION)]
@@ -35,6 +45,15 @@ struct Cli {
     #[clap(short, long, action, hide = true)]
     proxy: bool,
 
+    /// The id the UI gave to this proxy, under which it can be reattached
+    /// to after the connection is lost
+    #[clap(long, hide = true)]
+    session: Option<String>,
+
+    /// Connect the stdio to the proxy kept alive for `--session`
+    #[clap(long, action, hide = true)]
+    reattach: bool,
+
     /// Paths to file(s) and/or folder(s) to open.
     /// When path is a file (that exists or not),
     /// it accepts `path:line:column` syntax
@@ -52,13 +71,55 @@ pub fn mainloop() {
         };
         exit(1);
     }
+    if cli.reattach {
+        reattach(cli.session.as_deref());
+    }
     let core_rpc = CoreRpcHandler::new();
     let proxy_rpc = ProxyRpcHandler::new();
     let mut dispatcher = Dispatcher::new(core_rpc.clone(), proxy_rpc.clone());
 
     let (writer_tx, writer_rx) = crossbeam_channel::unbounded();
     let (reader_tx, reader_rx) = crossbeam_channel::unbounded();
-    stdio_transport(stdout(), writer_rx, BufReader::new(stdin()), reader_tx);
+    let writer = Arc::new(ReattachableWriter::new());
+    if let Err(err) = writer.attach(stdout()) {
+        tracing::error!("{:?}", err);
+    }
+    reattachable_writer_transport(writer.clone(), writer_rx);
+    {
+        let session = cli.session.clone();
+        let live_terminals = dispatcher.live_terminals();
+        let proxy_rpc = proxy_rpc.clone();
+        let core_rpc = core_rpc.clone();
+        let local_reader_tx = reader_tx.clone();
+        reader_transport(BufReader::new(stdin()), reader_tx, move || {
+            // Keep the terminals running for the UI to reattach to, which
+            // holds on to the reader so the proxy isn't shut down below.
+            #[cfg(unix)]
+            if let Some(session) = session {
+                if !live_terminals.lock().is_empty() {
+                    if let Err(err) = session::keep_alive(
+                        &session,
+                        writer,
+                        local_reader_tx,
+                        live_terminals,
+                        proxy_rpc,
+                        core_rpc,
+                    ) {
+                        tracing::error!("{:?}", err);
+                    }
+                }
+            }
+            #[cfg(not(unix))]
+            let _ = (
+                session,
+                writer,
+                local_reader_tx,
+                live_terminals,
+                proxy_rpc,
+                core_rpc,
+            );
+        });
+    }
 
     let local_core_rpc = core_rpc.clone();
     let local_writer_tx = writer_tx.clone();
@@ -137,6 +198,25 @@ pub fn mainloop() {
     proxy_rpc.mainloop(&mut dispatcher);
 }
 
+#[cfg(unix)]
+fn reattach(session: Option<&str>) -> ! {
+    let Some(session) = session else {
+        error!("--reattach requires --session");
+        exit(1);
+    };
+    if let Err(err) = session::relay(session) {
+        error!("failed to reattach to the proxy: {err}");
+        exit(1);
+    }
+    exit(0);
+}
+
+#[cfg(not(unix))]
+fn reattach(_session: Option<&str>) -> ! {
+    error!("reattaching to a proxy is only supported on unix");
+    exit(1);
+}
+
 pub fn register_lapce_path() -> Result<()> {
     let path = std::env::current_exe()?;
 
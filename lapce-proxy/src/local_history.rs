@@ -0,0 +1,128 @@
+//! The local history, which keeps the recently saved versions of each file
+//! on disk, independently of the source control.
+//!
+//! The versions of a file are stored in a directory named after a hash of its
+//! path, each in a file named after the time it was saved at in milliseconds.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use lapce_core::directory::Directory;
+
+/// The number of versions kept for each file.
+const MAX_VERSIONS: usize = 50;
+/// Files larger than this aren't kept in the history.
+const MAX_FILE_SIZE: usize = 5 * 1024 * 1024;
+
+/// Keep `content` as the latest saved version of the file at `path`.
+pub fn record(path: &Path, content: &str) -> Result<()> {
+    if content.len() > MAX_FILE_SIZE {
+        return Ok(());
+    }
+    let dir = history_dir(path).ok_or_else(|| anyhow!("no history directory"))?;
+    fs::create_dir_all(&dir)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+    record_in(&dir, content, now, MAX_VERSIONS)
+}
+
+/// The times the kept versions of the file at `path` were saved at, newest
+/// first.
+pub fn versions(path: &Path) -> Vec<u64> {
+    history_dir(path)
+        .map(|dir| versions_in(&dir))
+        .unwrap_or_default()
+}
+
+/// The content of the version of the file at `path` saved at `timestamp`.
+pub fn content(path: &Path, timestamp: u64) -> Result<String> {
+    let dir = history_dir(path).ok_or_else(|| anyhow!("no history directory"))?;
+    Ok(fs::read_to_string(dir.join(timestamp.to_string()))?)
+}
+
+fn history_dir(path: &Path) -> Option<PathBuf> {
+    let dir = Directory::local_history_directory()?;
+    Some(dir.join(format!("{:016x}", path_hash(path))))
+}
+
+/// A hash of the path which, unlike the one of the standard library, stays the
+/// same across versions of the editor.
+fn path_hash(path: &Path) -> u64 {
+    // FNV-1a
+    path.to_string_lossy()
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+fn record_in(dir: &Path, content: &str, now: u64, max: usize) -> Result<()> {
+    let versions = versions_in(dir);
+    let timestamp = if let Some(latest) = versions.first() {
+        // Saving without changes doesn't make a new version.
+        if fs::read_to_string(dir.join(latest.to_string()))
+            .is_ok_and(|latest| latest == content)
+        {
+            return Ok(());
+        }
+        now.max(latest + 1)
+    } else {
+        now
+    };
+    fs::write(dir.join(timestamp.to_string()), content)?;
+
+    for old in versions.iter().skip(max.saturating_sub(1)) {
+        if let Err(err) = fs::remove_file(dir.join(old.to_string())) {
+            tracing::error!("{:?}", err);
+        }
+    }
+    Ok(())
+}
+
+fn versions_in(dir: &Path) -> Vec<u64> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut versions: Vec<u64> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+        .collect();
+    versions.sort_unstable_by(|a, b| b.cmp(a));
+    versions
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{path_hash, record_in, versions_in};
+
+    #[test]
+    fn test_path_hash() {
+        assert_eq!(path_hash("".as_ref()), 0xcbf29ce484222325);
+        assert_eq!(path_hash("a".as_ref()), 0xaf63dc4c8601ec8c);
+        assert_ne!(path_hash("/a/b.rs".as_ref()), path_hash("/a/c.rs".as_ref()));
+    }
+
+    #[test]
+    fn test_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+
+        record_in(dir, "one", 10, 3).unwrap();
+        record_in(dir, "one", 20, 3).unwrap();
+        assert_eq!(versions_in(dir), vec![10]);
+
+        // A version saved within the same millisecond still gets its own file.
+        record_in(dir, "two", 10, 3).unwrap();
+        record_in(dir, "three", 30, 3).unwrap();
+        assert_eq!(versions_in(dir), vec![30, 11, 10]);
+
+        record_in(dir, "four", 40, 3).unwrap();
+        assert_eq!(versions_in(dir), vec![40, 30, 11]);
+        assert_eq!(fs::read_to_string(dir.join("11")).unwrap(), "two");
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     borrow::Cow,
     collections::HashMap,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicUsize, Ordering},
         Arc,
@@ -18,9 +18,14 @@
 };
 use lapce_xi_rope::{Rope, RopeDelta};
 use lsp_types::{
-    notification::DidOpenTextDocument, request::Request, DidOpenTextDocumentParams,
-    MessageType, SemanticTokens, ShowMessageParams, TextDocumentIdentifier,
-    TextDocumentItem, VersionedTextDocumentIdentifier,
+    notification::{
+        DidChangeConfiguration, DidChangeWorkspaceFolders, DidOpenTextDocument,
+    },
+    request::Request,
+    DidChangeConfigurationParams, DidChangeWorkspaceFoldersParams,
+    DidOpenTextDocumentParams, MessageType, PublishDiagnosticsParams,
+    SemanticTokens, ShowMessageParams, TextDocumentIdentifier, TextDocumentItem,
+    Url, VersionedTextDocumentIdentifier, WorkspaceFoldersChangeEvent,
 };
 use parking_lot::Mutex;
 use psp_types::Notification;
@@ -29,40 +34,51 @@
 use super::{
     dap::{DapClient, DapRpcHandler, DebuggerData},
     psp::{ClonableCallback, PluginServerRpc, PluginServerRpcHandler, RpcCallback},
+    setting_json,
     wasi::{load_all_volts, start_volt},
-    PluginCatalogNotification, PluginCatalogRpcHandler,
+    workspace_folder, LspPriority, PluginCatalogNotification,
+    PluginCatalogRpcHandler,
 };
-use crate::plugin::{
-    install_volt, psp::PluginHandlerNotification, wasi::enable_volt,
+use crate::{
+    buffer::language_id_from_path,
+    plugin::{install_volt, psp::PluginHandlerNotification, wasi::enable_volt},
 };
 
 pub struct PluginCatalog {
     workspace: Option<PathBuf>,
+    /// The folders added to the workspace besides its root.
+    workspace_folders: Vec<PathBuf>,
     plugin_rpc: PluginCatalogRpcHandler,
     plugins: HashMap<PluginId, PluginServerRpcHandler>,
     daps: HashMap<DapId, DapRpcHandler>,
     debuggers: HashMap<String, DebuggerData>,
     plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
     unactivated_volts: HashMap<VoltID, VoltMetadata>,
+    activated_volts: HashMap<VoltID, VoltMetadata>,
     open_files: HashMap<PathBuf, String>,
 }
 
 impl PluginCatalog {
     pub fn new(
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
         plugin_rpc: PluginCatalogRpcHandler,
     ) -> Self {
+        plugin_rpc.set_plugin_configurations(&plugin_configurations);
+        plugin_rpc.set_workspace_folders(&workspace_folders);
         let plugin = Self {
             workspace,
+            workspace_folders,
             plugin_rpc: plugin_rpc.clone(),
             plugin_configurations,
             plugins: HashMap::new(),
             daps: HashMap::new(),
             debuggers: HashMap::new(),
             unactivated_volts: HashMap::new(),
+            activated_volts: HashMap::new(),
             open_files: HashMap::new(),
         };
 
@@ -201,10 +217,76 @@ pub fn shutdown_volt(
         }
     }
 
+    /// Stop the volts whose language servers may handle the file at `path`,
+    /// which are those activated by its language and those activated by the
+    /// files of the workspace, or all the volts without a path. They are
+    /// started again when `restart`.
+    fn stop_language_servers(&mut self, path: Option<&Path>, restart: bool) {
+        let language_id = path.and_then(language_id_from_path);
+        let volt_ids: Vec<VoltID> = self
+            .activated_volts
+            .iter()
+            .filter(|(_, meta)| {
+                let Some(language_id) = language_id else {
+                    return path.is_none();
+                };
+                meta.activation
+                    .as_ref()
+                    .and_then(|a| a.language.as_ref())
+                    .map(|l| l.iter().any(|l| l == language_id))
+                    .unwrap_or(true)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for volt_id in volt_ids {
+            let ids: Vec<PluginId> = self
+                .plugins
+                .iter()
+                .filter(|(_, plugin)| plugin.volt_id == volt_id)
+                .map(|(id, _)| *id)
+                .collect();
+            for id in ids {
+                if let Some(plugin) = self.plugins.remove(&id) {
+                    plugin.shutdown();
+                }
+                // Clear the diagnostics of the server, as it won't update them.
+                for path in self.open_files.keys() {
+                    if let Ok(uri) = Url::from_file_path(path) {
+                        self.plugin_rpc.core_rpc.publish_diagnostics(
+                            PublishDiagnosticsParams {
+                                uri,
+                                diagnostics: Vec::new(),
+                                version: None,
+                            },
+                            id,
+                        );
+                    }
+                }
+            }
+            if let Some(meta) = self.activated_volts.remove(&volt_id) {
+                self.plugin_rpc.core_rpc.language_server_output(
+                    volt_id.clone(),
+                    meta.display_name.clone(),
+                    if restart {
+                        "Restarting the language server".to_string()
+                    } else {
+                        "Stopped the language server".to_string()
+                    },
+                );
+                if restart {
+                    self.unactivated_volts.insert(volt_id.clone(), meta);
+                    self.start_unactivated_volts(vec![volt_id]);
+                }
+            }
+        }
+    }
+
     fn start_unactivated_volts(&mut self, to_be_activated: Vec<VoltID>) {
         for id in to_be_activated.iter() {
             let workspace = self.workspace.clone();
             if let Some(meta) = self.unactivated_volts.remove(id) {
+                self.activated_volts.insert(id.clone(), meta.clone());
                 let configurations =
                     self.plugin_configurations.get(&meta.name).cloned();
                 tracing::debug!("{:?} {:?}", id, configurations);
@@ -404,6 +486,81 @@ pub fn dap_variable(
         }
     }
 
+    pub fn dap_set_variable(
+        &self,
+        dap_id: DapId,
+        reference: usize,
+        name: String,
+        value: String,
+        f: Box<dyn RpcCallback<dap_types::SetVariableResponse, RpcError>>,
+    ) {
+        if let Some(dap) = self.daps.get(&dap_id) {
+            dap.set_variable_async(
+                reference,
+                name,
+                value,
+                |result: Result<dap_types::SetVariableResponse, RpcError>| {
+                    f.call(result)
+                },
+            );
+        } else {
+            f.call(Err(RpcError {
+                code: 0,
+                message: "plugin doesn't exist".to_string(),
+            }));
+        }
+    }
+
+    pub fn dap_evaluate(
+        &self,
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: &str,
+        f: Box<dyn RpcCallback<dap_types::EvaluateResponse, RpcError>>,
+    ) {
+        if let Some(dap) = self.daps.get(&dap_id) {
+            dap.evaluate_async(
+                expression,
+                frame_id,
+                context,
+                |result: Result<dap_types::EvaluateResponse, RpcError>| {
+                    f.call(result)
+                },
+            );
+        } else {
+            f.call(Err(RpcError {
+                code: 0,
+                message: "plugin doesn't exist".to_string(),
+            }));
+        }
+    }
+
+    pub fn dap_completions(
+        &self,
+        dap_id: DapId,
+        text: String,
+        column: usize,
+        frame_id: Option<usize>,
+        f: Box<dyn RpcCallback<Vec<dap_types::CompletionItem>, RpcError>>,
+    ) {
+        if let Some(dap) = self.daps.get(&dap_id) {
+            dap.completions_async(
+                text,
+                column,
+                frame_id,
+                |result: Result<dap_types::CompletionsResponse, RpcError>| {
+                    f.call(result.map(|resp| resp.targets))
+                },
+            );
+        } else {
+            f.call(Err(RpcError {
+                code: 0,
+                message: "plugin doesn't exist".to_string(),
+            }));
+        }
+    }
+
     pub fn dap_get_scopes(
         &self,
         dap_id: DapId,
@@ -490,7 +647,77 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
             }
             UpdatePluginConfigs(configs) => {
                 tracing::debug!("UpdatePluginConfigs {:?}", configs);
-                self.plugin_configurations = configs;
+                let old_configs =
+                    std::mem::replace(&mut self.plugin_configurations, configs);
+                self.plugin_rpc
+                    .set_plugin_configurations(&self.plugin_configurations);
+                let lsp_settings =
+                    |configs: &HashMap<String, HashMap<String, Value>>,
+                     volt_id: &VoltID| {
+                        configs
+                            .get(&volt_id.name)
+                            .and_then(|config| config.get("lsp-settings"))
+                            .map(setting_json)
+                    };
+                for plugin in self.plugins.values() {
+                    // Only the language servers get the settings, not the volts
+                    // starting them.
+                    if plugin.spawned_by.is_none() {
+                        continue;
+                    }
+                    let settings =
+                        lsp_settings(&self.plugin_configurations, &plugin.volt_id);
+                    if settings != lsp_settings(&old_configs, &plugin.volt_id) {
+                        plugin.server_notification(
+                            DidChangeConfiguration::METHOD,
+                            DidChangeConfigurationParams {
+                                settings: settings.unwrap_or(Value::Null),
+                            },
+                            None,
+                            None,
+                            false,
+                        );
+                    }
+                }
+                for (plugin_id, plugin) in self.plugins.iter() {
+                    self.plugin_rpc.set_lsp_priority(
+                        *plugin_id,
+                        LspPriority::from_config(
+                            self.plugin_configurations.get(&plugin.volt_id.name),
+                        ),
+                    );
+                }
+            }
+            UpdateWorkspaceFolders(folders) => {
+                tracing::debug!("UpdateWorkspaceFolders {:?}", folders);
+                let changed_folders = |a: &[PathBuf], b: &[PathBuf]| {
+                    a.iter()
+                        .filter(|path| {
+                            !b.contains(path)
+                                && self.workspace.as_ref() != Some(*path)
+                        })
+                        .filter_map(|path| workspace_folder(path))
+                        .collect::<Vec<_>>()
+                };
+                let event = WorkspaceFoldersChangeEvent {
+                    added: changed_folders(&folders, &self.workspace_folders),
+                    removed: changed_folders(&self.workspace_folders, &folders),
+                };
+                self.plugin_rpc.set_workspace_folders(&folders);
+                self.workspace_folders = folders;
+                if !event.added.is_empty() || !event.removed.is_empty() {
+                    for plugin in self.plugins.values() {
+                        plugin.server_notification(
+                            DidChangeWorkspaceFolders::METHOD,
+                            DidChangeWorkspaceFoldersParams {
+                                event: event.clone(),
+                            },
+                            None,
+                            None,
+                            true,
+                        );
+                    }
+                }
             }
             PluginServerLoaded(plugin) => {
                 // TODO: check if the server has did open registered
@@ -518,6 +745,12 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
 
                 let plugin_id = plugin.plugin_id;
                 let spawned_by = plugin.spawned_by;
+                self.plugin_rpc.set_lsp_priority(
+                    plugin_id,
+                    LspPriority::from_config(
+                        self.plugin_configurations.get(&plugin.volt_id.name),
+                    ),
+                );
 
                 self.plugins.insert(plugin.plugin_id, plugin);
 
@@ -563,6 +796,7 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
             StopVolt(volt) => {
                 tracing::debug!("StopVolt {:?}", volt);
                 let volt_id = volt.id();
+                self.activated_volts.remove(&volt_id);
                 let ids: Vec<PluginId> = self.plugins.keys().cloned().collect();
                 for id in ids {
                     if self.plugins.get(&id).unwrap().volt_id == volt_id {
@@ -571,6 +805,10 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                     }
                 }
             }
+            StopLanguageServers { path, restart } => {
+                tracing::debug!("StopLanguageServers {:?} {restart}", path);
+                self.stop_language_servers(path.as_deref(), restart);
+            }
             EnableVolt(volt) => {
                 tracing::debug!("EnableVolt {:?}", volt);
                 let volt_id = volt.id();
@@ -586,6 +824,11 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                     }
                 });
             }
+            PluginCatalogNotification::PluginEvent(event) => {
+                for (_, plugin) in self.plugins.iter() {
+                    plugin.handle_rpc(PluginServerRpc::PluginEvent(event.clone()));
+                }
+            }
             DapLoaded(dap_rpc) => {
                 self.daps.insert(dap_rpc.dap_id, dap_rpc);
             }
@@ -598,16 +841,22 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
             } => {
                 let workspace = self.workspace.clone();
                 let plugin_rpc = self.plugin_rpc.clone();
-                if let Some(debugger) = config
-                    .ty
-                    .as_ref()
-                    .and_then(|ty| self.debuggers.get(ty).cloned())
-                {
+                // The adapter of the config wins over the one of its type
+                let adapter = config
+                    .adapter
+                    .clone()
+                    .map(|adapter| (adapter.program, adapter.args))
+                    .or_else(|| {
+                        let ty = config.ty.as_ref()?;
+                        let debugger = self.debuggers.get(ty)?;
+                        Some((debugger.program.clone(), debugger.args.clone()))
+                    });
+                if let Some((program, args)) = adapter {
                     thread::spawn(move || {
                         match DapClient::start(
                             DapServer {
-                                program: debugger.program,
-                                args: debugger.args.unwrap_or_default(),
+                                program,
+                                args: args.unwrap_or_default(),
                                 cwd: workspace,
                             },
                             config.clone(),
@@ -621,7 +870,7 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                                     tracing::error!("{:?}", err);
                                 }
 
-                                if let Err(err) = dap_rpc.launch(&config) {
+                                if let Err(err) = dap_rpc.launch_or_attach(&config) {
                                     tracing::error!("{:?}", err);
                                 }
                             }
@@ -635,7 +884,7 @@ pub fn handle_notification(&mut self, notification: PluginCatalogNotification) {
                         "debug fail".to_owned(),
                         ShowMessageParams {
                             typ: MessageType::ERROR,
-                            message: "Debugger not found. Please install the appropriate plugin.".to_owned(),
+                            message: "Debugger not found. Please install the appropriate plugin, or set the adapter of the config.".to_owned(),
                         },
                     )
                 }
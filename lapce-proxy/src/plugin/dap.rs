@@ -14,13 +14,16 @@
 use crossbeam_channel::{Receiver, Sender};
 use lapce_rpc::{
     dap_types::{
-        self, ConfigurationDone, Continue, ContinueArguments, ContinueResponse,
-        DapEvent, DapId, DapPayload, DapRequest, DapResponse, DapServer,
-        DebuggerCapabilities, Disconnect, Initialize, Launch, Next, NextArguments,
-        Pause, PauseArguments, Request, RunDebugConfig, RunInTerminal,
+        self, Attach, Completions, CompletionsArguments, CompletionsResponse,
+        ConfigurationDone, Continue, ContinueArguments, ContinueResponse, DapEvent,
+        DapId, DapPayload, DapRequest, DapResponse, DapServer, DebugRequest,
+        DebuggerCapabilities, Disconnect, Evaluate, EvaluateArguments,
+        EvaluateResponse, Initialize, Launch, Next, NextArguments, Pause,
+        PauseArguments, Request, RunDebugConfig, RunInTerminal,
         RunInTerminalArguments, RunInTerminalResponse, Scope, Scopes,
         ScopesArguments, ScopesResponse, SetBreakpoints, SetBreakpointsArguments,
-        SetBreakpointsResponse, Source, SourceBreakpoint, StackTrace,
+        SetBreakpointsResponse, SetVariable, SetVariableArguments,
+        SetVariableResponse, Source, SourceBreakpoint, StackTrace,
         StackTraceArguments, StackTraceResponse, StepIn, StepInArguments, StepOut,
         StepOutArguments, Terminate, ThreadId, Threads, ThreadsResponse, Variable,
         Variables, VariablesArguments, VariablesResponse,
@@ -306,7 +309,14 @@ fn handle_host_event(&mut self, event: &DapEvent) -> Result<()> {
                 }
             }
             DapEvent::Thread { .. } => {}
-            DapEvent::Output(_) => {}
+            DapEvent::Output(output) => {
+                // Telemetry is for the adapter's authors, not for the console
+                if output.category.as_deref() != Some("telemetry") {
+                    self.plugin_rpc
+                        .core_rpc
+                        .dap_output(self.config.dap_id, output.clone());
+                }
+            }
             DapEvent::Breakpoint { .. } => {}
             DapEvent::Module { .. } => {}
             DapEvent::LoadedSource { .. } => {}
@@ -394,7 +404,7 @@ fn check_restart(&mut self) -> Result<()> {
         let dap_rpc = self.dap_rpc.clone();
         let config = self.config.clone();
         thread::spawn(move || {
-            if let Err(err) = dap_rpc.launch(&config) {
+            if let Err(err) = dap_rpc.launch_or_attach(&config) {
                 tracing::error!("{:?}", err);
             }
         });
@@ -618,17 +628,32 @@ pub fn handle_server_message(&self, message_str: &str) {
         }
     }
 
-    pub fn launch(&self, config: &RunDebugConfig) -> Result<()> {
-        let params = serde_json::json!({
-            "program": config.program,
-            "args": config.args,
-            "cwd": config.cwd,
-            "runInTerminal": true,
-            "env": config.env
-        });
-        let _resp = self
-            .request::<Launch>(params)
-            .map_err(|e| anyhow!(e.message))?;
+    /// Launch the program of the config, or attach to the one it names,
+    /// with the arguments of the config for the adapter.
+    pub fn launch_or_attach(&self, config: &RunDebugConfig) -> Result<()> {
+        let mut params = match config.request {
+            DebugRequest::Launch => serde_json::json!({
+                "program": config.program,
+                "args": config.args,
+                "cwd": config.cwd,
+                "runInTerminal": true,
+                "env": config.env
+            }),
+            DebugRequest::Attach => serde_json::json!({}),
+        };
+        if let Value::Object(params) = &mut params {
+            params.extend(
+                config
+                    .arguments
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            );
+        }
+        let _resp = match config.request {
+            DebugRequest::Launch => self.request::<Launch>(params),
+            DebugRequest::Attach => self.request::<Attach>(params),
+        }
+        .map_err(|e| anyhow!(e.message))?;
         Ok(())
     }
 
@@ -793,6 +818,57 @@ pub fn variables_async(
         self.request_async::<Variables>(args, f);
     }
 
+    pub fn set_variable_async(
+        &self,
+        variables_reference: usize,
+        name: String,
+        value: String,
+        f: impl RpcCallback<SetVariableResponse, RpcError> + 'static,
+    ) {
+        let args = SetVariableArguments {
+            variables_reference,
+            name,
+            value,
+            format: None,
+        };
+
+        self.request_async::<SetVariable>(args, f);
+    }
+
+    pub fn evaluate_async(
+        &self,
+        expression: String,
+        frame_id: Option<usize>,
+        context: &str,
+        f: impl RpcCallback<EvaluateResponse, RpcError> + 'static,
+    ) {
+        let args = EvaluateArguments {
+            expression,
+            frame_id,
+            context: Some(context.to_string()),
+            format: None,
+        };
+
+        self.request_async::<Evaluate>(args, f);
+    }
+
+    pub fn completions_async(
+        &self,
+        text: String,
+        column: usize,
+        frame_id: Option<usize>,
+        f: impl RpcCallback<CompletionsResponse, RpcError> + 'static,
+    ) {
+        let args = CompletionsArguments {
+            frame_id,
+            text,
+            column,
+            line: None,
+        };
+
+        self.request_async::<Completions>(args, f);
+    }
+
     pub fn next(&self, thread_id: ThreadId) {
         let args = NextArguments {
             thread_id,
@@ -12,13 +12,13 @@
 use jsonrpc_lite::{Id, Params};
 use lapce_core::meta;
 use lapce_rpc::{
-    plugin::{PluginId, VoltID},
+    plugin::{PluginEvent, PluginId, VoltID},
     style::LineStyle,
     RpcError,
 };
 use lapce_xi_rope::Rope;
 use lsp_types::{
-    notification::{Initialized, Notification},
+    notification::{DidChangeConfiguration, Initialized, Notification},
     request::{Initialize, Request},
     *,
 };
@@ -26,7 +26,7 @@
 use serde_json::Value;
 
 use super::{
-    client_capabilities,
+    client_capabilities, merge_json,
     psp::{
         handle_plugin_server_message, PluginHandlerNotification, PluginHostHandler,
         PluginServerHandler, PluginServerRpcHandler, ResponseSender, RpcCallback,
@@ -166,6 +166,14 @@ fn format_semantic_tokens(
     ) {
         self.host.format_semantic_tokens(tokens, text, f);
     }
+
+    fn handle_plugin_event(&self, event: PluginEvent) {
+        self.host.handle_plugin_event(event);
+    }
+
+    fn pull_diagnostics(&self, uri: Url) {
+        self.host.pull_diagnostics(uri);
+    }
 }
 
 impl LspClient {
@@ -213,6 +221,9 @@ fn new(
             plugin_id,
             io_tx.clone(),
         );
+        let trace_rpc = plugin_rpc.clone();
+        let volt_id_closure = volt_id.clone();
+        let name = volt_display_name.clone();
         thread::spawn(move || {
             for msg in io_rx {
                 if msg
@@ -224,6 +235,13 @@ fn new(
                 }
                 if let Ok(msg) = serde_json::to_string(&msg) {
                     tracing::debug!("write to lsp: {}", msg);
+                    if trace_rpc.lsp_trace(&volt_id_closure) {
+                        trace_rpc.core_rpc.language_server_output(
+                            volt_id_closure.clone(),
+                            name.clone(),
+                            format!("--> {msg}"),
+                        );
+                    }
                     let msg =
                         format!("Content-Length: {}\r\n\r\n{}", msg.len(), msg);
                     if let Err(err) = writer.write(msg.as_bytes()) {
@@ -237,6 +255,7 @@ fn new(
         });
 
         let local_server_rpc = server_rpc.clone();
+        let trace_rpc = plugin_rpc.clone();
         let core_rpc = plugin_rpc.core_rpc.clone();
         let volt_id_closure = volt_id.clone();
         let name = volt_display_name.clone();
@@ -248,6 +267,13 @@ fn new(
                         if !message_str.contains("$/progress") {
                             tracing::debug!("read from lsp: {}", message_str);
                         }
+                        if trace_rpc.lsp_trace(&volt_id_closure) {
+                            core_rpc.language_server_output(
+                                volt_id_closure.clone(),
+                                name.clone(),
+                                format!("<-- {message_str}"),
+                            );
+                        }
                         if let Some(resp) = handle_plugin_server_message(
                             &local_server_rpc,
                             &message_str,
@@ -267,6 +293,12 @@ fn new(
                                 volt_id_closure.author, volt_id_closure.name
                             )),
                         );
+                        core_rpc.language_server_output(
+                            volt_id_closure,
+                            name,
+                            format!("Language server {server} stopped"),
+                        );
+                        core_rpc.language_server_stopped(local_server_rpc.plugin_id);
                         return;
                     }
                 };
@@ -275,6 +307,7 @@ fn new(
 
         let core_rpc = plugin_rpc.core_rpc.clone();
         let volt_id_closure = volt_id.clone();
+        let name = volt_display_name.clone();
         thread::spawn(move || {
             let mut reader = Box::new(BufReader::new(stderr));
             loop {
@@ -292,6 +325,11 @@ fn new(
                                 volt_id_closure.author, volt_id_closure.name
                             )),
                         );
+                        core_rpc.language_server_output(
+                            volt_id_closure.clone(),
+                            name.clone(),
+                            line.trim_end().to_string(),
+                        );
                     }
                     Err(_) => {
                         return;
@@ -362,20 +400,30 @@ fn initialize(&mut self) {
             .workspace
             .clone()
             .map(|p| Url::from_directory_path(p).unwrap());
-        tracing::debug!("initialization_options {:?}", self.options);
+        // The options the user set override those the volt starts the server
+        // with.
+        let volt_id = self.server_rpc.volt_id.clone();
+        let mut options = self.options.clone();
+        if let Some(user_options) = self
+            .plugin_rpc
+            .lsp_setting(&volt_id, "lsp-initialization-options")
+        {
+            match options.as_mut() {
+                Some(options) => merge_json(options, user_options),
+                None => options = Some(user_options),
+            }
+        }
+        tracing::debug!("initialization_options {:?}", options);
         #[allow(deprecated)]
         let params = InitializeParams {
             process_id: Some(process::id()),
-            root_uri: root_uri.clone(),
-            initialization_options: self.options.clone(),
-            capabilities: client_capabilities(),
+            root_uri,
+            initialization_options: options,
+            capabilities: client_capabilities(true),
             trace: Some(TraceValue::Verbose),
-            workspace_folders: root_uri.map(|uri| {
-                vec![WorkspaceFolder {
-                    name: uri.as_str().to_string(),
-                    uri,
-                }]
-            }),
+            workspace_folders: self
+                .plugin_rpc
+                .lsp_workspace_folders(self.workspace.as_deref()),
             client_info: Some(ClientInfo {
                 name: meta::NAME.to_owned(),
                 version: Some(meta::VERSION.to_owned()),
@@ -402,6 +450,17 @@ fn initialize(&mut self) {
                     None,
                     false,
                 );
+                if let Some(settings) =
+                    self.plugin_rpc.lsp_setting(&volt_id, "lsp-settings")
+                {
+                    self.server_rpc.server_notification(
+                        DidChangeConfiguration::METHOD,
+                        DidChangeConfigurationParams { settings },
+                        None,
+                        None,
+                        false,
+                    );
+                }
                 if self
                     .plugin_rpc
                     .plugin_server_loaded(self.server_rpc.clone())
@@ -13,6 +13,7 @@
         atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
+    thread,
     time::Duration,
 };
 
@@ -24,7 +25,7 @@
 use lapce_rpc::{
     core::CoreRpcHandler,
     dap_types::{self, DapId, RunDebugConfig, SourceBreakpoint, ThreadId},
-    plugin::{PluginId, VoltInfo, VoltMetadata},
+    plugin::{PluginEvent, PluginId, VoltID, VoltInfo, VoltMetadata},
     proxy::ProxyRpcHandler,
     style::LineStyle,
     terminal::TermId,
@@ -35,11 +36,13 @@
     request::{
         CallHierarchyIncomingCalls, CallHierarchyPrepare, CodeActionRequest,
         CodeActionResolveRequest, CodeLensRequest, CodeLensResolve, Completion,
-        DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition,
-        GotoImplementation, GotoImplementationResponse, GotoTypeDefinition,
-        GotoTypeDefinitionParams, GotoTypeDefinitionResponse, HoverRequest,
-        InlayHintRequest, InlineCompletionRequest, PrepareRenameRequest, References,
-        Rename, Request, ResolveCompletionItem, SelectionRangeRequest,
+        DocumentLinkRequest, DocumentLinkResolve, DocumentSymbolRequest,
+        FoldingRangeRequest, Formatting, GotoDefinition, GotoImplementation,
+        GotoImplementationResponse, GotoTypeDefinition, GotoTypeDefinitionParams,
+        GotoTypeDefinitionResponse, HoverRequest, InlayHintRequest,
+        InlineCompletionRequest, LinkedEditingRange, PrepareRenameRequest,
+        RangeFormatting, References, Rename, Request, ResolveCompletionItem,
+        SelectionRangeRequest, SemanticTokensFullDeltaRequest,
         SemanticTokensFullRequest, SignatureHelpRequest, WorkspaceSymbolRequest,
     },
     CallHierarchyClientCapabilities, CallHierarchyIncomingCall,
@@ -47,21 +50,29 @@
     ClientCapabilities, CodeAction, CodeActionCapabilityResolveSupport,
     CodeActionClientCapabilities, CodeActionContext, CodeActionKind,
     CodeActionKindLiteralSupport, CodeActionLiteralSupport, CodeActionParams,
-    CodeActionResponse, CodeLens, CodeLensParams, CompletionClientCapabilities,
-    CompletionItem, CompletionItemCapability,
+    CodeActionResponse, CodeActionTriggerKind, CodeLens, CodeLensParams,
+    CompletionClientCapabilities, CompletionItem, CompletionItemCapability,
     CompletionItemCapabilityResolveSupport, CompletionParams, CompletionResponse,
-    Diagnostic, DocumentFormattingParams, DocumentSymbolClientCapabilities,
-    DocumentSymbolParams, DocumentSymbolResponse, FoldingRange,
-    FoldingRangeClientCapabilities, FoldingRangeParams, FormattingOptions,
-    GotoCapability, GotoDefinitionParams, GotoDefinitionResponse, Hover,
-    HoverClientCapabilities, HoverParams, InlayHint, InlayHintClientCapabilities,
-    InlayHintParams, InlineCompletionClientCapabilities, InlineCompletionParams,
-    InlineCompletionResponse, InlineCompletionTriggerKind, Location, MarkupKind,
+    Diagnostic, DiagnosticClientCapabilities, DiagnosticWorkspaceClientCapabilities,
+    DocumentFormattingParams, DocumentLink, DocumentLinkClientCapabilities,
+    DocumentLinkParams, DocumentRangeFormattingParams,
+    DocumentSymbolClientCapabilities, DocumentSymbolParams, DocumentSymbolResponse,
+    FailureHandlingKind, FoldingRange, FoldingRangeClientCapabilities,
+    FoldingRangeParams, FormattingOptions, GotoCapability, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverClientCapabilities, HoverContents,
+    HoverParams, InlayHint, InlayHintClientCapabilities, InlayHintParams,
+    InlineCompletionClientCapabilities, InlineCompletionItem,
+    InlineCompletionParams, InlineCompletionResponse, InlineCompletionTriggerKind,
+    LanguageString, LinkedEditingRangeClientCapabilities, LinkedEditingRangeParams,
+    LinkedEditingRanges, Location, MarkedString, MarkupContent, MarkupKind,
     MessageActionItemCapabilities, ParameterInformationSettings,
     PartialResultParams, Position, PrepareRenameResponse,
     PublishDiagnosticsClientCapabilities, Range, ReferenceContext, ReferenceParams,
-    RenameParams, SelectionRange, SelectionRangeParams, SemanticTokens,
-    SemanticTokensClientCapabilities, SemanticTokensParams,
+    RenameParams, ResourceOperationKind, SelectionRange,
+    SelectionRangeClientCapabilities, SelectionRangeParams, SemanticTokens,
+    SemanticTokensClientCapabilities, SemanticTokensClientCapabilitiesRequests,
+    SemanticTokensDeltaParams, SemanticTokensFullDeltaResult,
+    SemanticTokensFullOptions, SemanticTokensParams,
     ShowMessageRequestClientCapabilities, SignatureHelp,
     SignatureHelpClientCapabilities, SignatureHelpParams,
     SignatureInformationSettings, SymbolInformation, TextDocumentClientCapabilities,
@@ -69,6 +80,7 @@
     TextDocumentSyncClientCapabilities, TextEdit, Url,
     VersionedTextDocumentIdentifier, WindowClientCapabilities,
     WorkDoneProgressParams, WorkspaceClientCapabilities, WorkspaceEdit,
+    WorkspaceEditClientCapabilities, WorkspaceFolder,
     WorkspaceSymbolClientCapabilities, WorkspaceSymbolParams,
 };
 use parking_lot::Mutex;
@@ -87,6 +99,9 @@
 
 pub type PluginName = String;
 
+/// How long a request sent to all the plugins waits for their responses.
+const MERGED_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[allow(clippy::large_enum_variant)]
 pub enum PluginCatalogRpc {
     ServerRequest {
@@ -118,6 +133,27 @@ pub enum PluginCatalogRpc {
         reference: usize,
         f: Box<dyn RpcCallback<Vec<dap_types::Variable>, RpcError>>,
     },
+    DapSetVariable {
+        dap_id: DapId,
+        reference: usize,
+        name: String,
+        value: String,
+        f: Box<dyn RpcCallback<dap_types::SetVariableResponse, RpcError>>,
+    },
+    DapEvaluate {
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: String,
+        f: Box<dyn RpcCallback<dap_types::EvaluateResponse, RpcError>>,
+    },
+    DapCompletions {
+        dap_id: DapId,
+        text: String,
+        column: usize,
+        frame_id: Option<usize>,
+        f: Box<dyn RpcCallback<Vec<dap_types::CompletionItem>, RpcError>>,
+    },
     DapGetScopes {
         dap_id: DapId,
         frame_id: usize,
@@ -155,12 +191,17 @@ pub enum PluginCatalogRpc {
 #[allow(clippy::large_enum_variant)]
 pub enum PluginCatalogNotification {
     UpdatePluginConfigs(HashMap<String, HashMap<String, serde_json::Value>>),
+    UpdateWorkspaceFolders(Vec<PathBuf>),
     UnactivatedVolts(Vec<VoltMetadata>),
     PluginServerLoaded(PluginServerRpcHandler),
     InstallVolt(VoltInfo),
     StopVolt(VoltInfo),
     EnableVolt(VoltInfo),
     ReloadVolt(VoltMetadata),
+    StopLanguageServers {
+        path: Option<PathBuf>,
+        restart: bool,
+    },
     DapLoaded(DapRpcHandler),
     DapDisconnected(DapId),
     DapStart {
@@ -212,9 +253,61 @@ pub enum PluginCatalogNotification {
         program: String,
         args: Option<Vec<String>>,
     },
+    PluginEvent(PluginEvent),
     Shutdown,
 }
 
+/// The features of the language servers whose results are merged across the
+/// servers of a language.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LspCapability {
+    Completion,
+    Hover,
+    CodeAction,
+    Diagnostics,
+    InlineCompletion,
+}
+
+impl LspCapability {
+    fn key(self) -> &'static str {
+        match self {
+            LspCapability::Completion => "completion",
+            LspCapability::Hover => "hover",
+            LspCapability::CodeAction => "code-action",
+            LspCapability::Diagnostics => "diagnostics",
+            LspCapability::InlineCompletion => "inline-completion",
+        }
+    }
+}
+
+/// The priorities of the language servers of a plugin by capability, from the
+/// `lsp-priority` table of the plugin's settings, as in
+/// `[lapce-rust.lsp-priority]` with `hover = 1`. The results of the servers
+/// with higher priorities come first, and those of the servers with negative
+/// priorities are left out. A capability without a priority has priority 0.
+#[derive(Clone, Debug, Default)]
+pub struct LspPriority(HashMap<String, i64>);
+
+impl LspPriority {
+    pub fn from_config(config: Option<&HashMap<String, Value>>) -> Self {
+        let priorities = config
+            .and_then(|config| config.get("lsp-priority"))
+            .and_then(|priority| priority.as_object())
+            .map(|priority| {
+                priority
+                    .iter()
+                    .filter_map(|(key, value)| Some((key.clone(), value.as_i64()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self(priorities)
+    }
+
+    pub fn get(&self, capability: LspCapability) -> i64 {
+        self.0.get(capability.key()).copied().unwrap_or(0)
+    }
+}
+
 #[derive(Clone)]
 pub struct PluginCatalogRpcHandler {
     core_rpc: CoreRpcHandler,
@@ -225,6 +318,13 @@ pub struct PluginCatalogRpcHandler {
     id: Arc<AtomicU64>,
     #[allow(dead_code, clippy::type_complexity)]
     pending: Arc<Mutex<HashMap<u64, Sender<Result<Value, RpcError>>>>>,
+    /// The priorities of the plugins, kept up to date by the catalog.
+    lsp_priorities: Arc<Mutex<HashMap<PluginId, LspPriority>>>,
+    /// The settings of the plugins by volt name, for the settings of their
+    /// language servers.
+    plugin_configurations: Arc<Mutex<HashMap<String, HashMap<String, Value>>>>,
+    /// The folders added to the workspace besides its root.
+    workspace_folders: Arc<Mutex<Vec<PathBuf>>>,
 }
 
 impl PluginCatalogRpcHandler {
@@ -237,9 +337,81 @@ pub fn new(core_rpc: CoreRpcHandler, proxy_rpc: ProxyRpcHandler) -> Self {
             plugin_rx: Arc::new(Mutex::new(Some(plugin_rx))),
             id: Arc::new(AtomicU64::new(0)),
             pending: Arc::new(Mutex::new(HashMap::new())),
+            lsp_priorities: Arc::new(Mutex::new(HashMap::new())),
+            plugin_configurations: Arc::new(Mutex::new(HashMap::new())),
+            workspace_folders: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
+    pub fn set_lsp_priority(&self, plugin_id: PluginId, priority: LspPriority) {
+        self.lsp_priorities.lock().insert(plugin_id, priority);
+    }
+
+    pub fn set_plugin_configurations(
+        &self,
+        configurations: &HashMap<String, HashMap<String, Value>>,
+    ) {
+        *self.plugin_configurations.lock() = configurations.clone();
+    }
+
+    pub fn set_workspace_folders(&self, folders: &[PathBuf]) {
+        *self.workspace_folders.lock() = folders.to_vec();
+    }
+
+    /// The folders of the workspace advertised to the language servers, which
+    /// are its root followed by the folders added to it.
+    pub fn lsp_workspace_folders(
+        &self,
+        workspace: Option<&Path>,
+    ) -> Option<Vec<WorkspaceFolder>> {
+        let mut paths: Vec<PathBuf> =
+            workspace.map(Path::to_path_buf).into_iter().collect();
+        for folder in self.workspace_folders.lock().iter() {
+            if !paths.contains(folder) {
+                paths.push(folder.clone());
+            }
+        }
+        let folders: Vec<WorkspaceFolder> = paths
+            .iter()
+            .filter_map(|path| workspace_folder(path))
+            .collect();
+        if folders.is_empty() {
+            None
+        } else {
+            Some(folders)
+        }
+    }
+
+    /// The setting `key` of the language servers of the volt, as a JSON value.
+    /// The settings are tables, or strings holding JSON.
+    pub fn lsp_setting(&self, volt_id: &VoltID, key: &str) -> Option<Value> {
+        self.plugin_configurations
+            .lock()
+            .get(&volt_id.name)
+            .and_then(|config| config.get(key))
+            .map(setting_json)
+    }
+
+    /// Whether the messages exchanged with the language servers of the volt
+    /// are shown in their output, with `lsp-trace` set in its settings.
+    pub fn lsp_trace(&self, volt_id: &VoltID) -> bool {
+        self.lsp_setting(volt_id, "lsp-trace")
+            .and_then(|v| v.as_bool())
+            == Some(true)
+    }
+
+    pub fn lsp_priority(
+        &self,
+        plugin_id: PluginId,
+        capability: LspCapability,
+    ) -> i64 {
+        self.lsp_priorities
+            .lock()
+            .get(&plugin_id)
+            .map(|priority| priority.get(capability))
+            .unwrap_or(0)
+    }
+
     #[allow(dead_code)]
     fn handle_response(&self, id: RequestId, result: Result<Value, RpcError>) {
         if let Some(chan) = { self.pending.lock().remove(&id) } {
@@ -340,6 +512,33 @@ pub fn mainloop(&self, plugin: &mut PluginCatalog) {
                 } => {
                     plugin.dap_variable(dap_id, reference, f);
                 }
+                PluginCatalogRpc::DapSetVariable {
+                    dap_id,
+                    reference,
+                    name,
+                    value,
+                    f,
+                } => {
+                    plugin.dap_set_variable(dap_id, reference, name, value, f);
+                }
+                PluginCatalogRpc::DapEvaluate {
+                    dap_id,
+                    expression,
+                    frame_id,
+                    context,
+                    f,
+                } => {
+                    plugin.dap_evaluate(dap_id, expression, frame_id, &context, f);
+                }
+                PluginCatalogRpc::DapCompletions {
+                    dap_id,
+                    text,
+                    column,
+                    frame_id,
+                    f,
+                } => {
+                    plugin.dap_completions(dap_id, text, column, frame_id, f);
+                }
                 PluginCatalogRpc::DapGetScopes {
                     dap_id,
                     frame_id,
@@ -430,6 +629,66 @@ fn send_request_to_all_plugins<P, Resp>(
         );
     }
 
+    /// Send the request to all the plugins and call `cb` once they all responded,
+    /// with the results of those that succeeded ordered by their priorities for
+    /// `capability`, leaving out the plugins with negative priorities.
+    ///
+    /// The plugins that haven't responded within [`MERGED_REQUEST_TIMEOUT`]
+    /// are left out too, so a slow server doesn't hold up the others.
+    fn send_request_to_all_plugins_merged<P, Resp>(
+        &self,
+        method: &'static str,
+        params: P,
+        language_id: Option<String>,
+        path: Option<PathBuf>,
+        capability: LspCapability,
+        cb: impl FnOnce(Vec<(PluginId, Resp)>) + Clone + Send + 'static,
+    ) where
+        P: Serialize,
+        Resp: DeserializeOwned + Send + 'static,
+    {
+        let request_sent = Arc::new(AtomicUsize::new(0));
+        let received = Arc::new(AtomicUsize::new(0));
+        let results = Arc::new(Mutex::new(Vec::new()));
+        let (all_received_tx, all_received_rx) = crossbeam_channel::bounded(1);
+        {
+            let results = results.clone();
+            thread::spawn(move || {
+                let _ = all_received_rx.recv_timeout(MERGED_REQUEST_TIMEOUT);
+                let mut results = std::mem::take(&mut *results.lock());
+                results.sort_by_key(|(priority, _, _)| -priority);
+                cb(results
+                    .into_iter()
+                    .map(|(_, plugin_id, item)| (plugin_id, item))
+                    .collect());
+            });
+        }
+        let handler = self.clone();
+        self.send_request(
+            None,
+            Some(request_sent.clone()),
+            method,
+            params,
+            language_id,
+            path,
+            true,
+            move |plugin_id, result| {
+                let priority = handler.lsp_priority(plugin_id, capability);
+                if priority >= 0 {
+                    if let Some(item) =
+                        result.ok().and_then(|v| serde_json::from_value(v).ok())
+                    {
+                        results.lock().push((priority, plugin_id, item));
+                    }
+                }
+                let received = received.fetch_add(1, Ordering::AcqRel) + 1;
+                if request_sent.load(Ordering::Acquire) == received {
+                    let _ = all_received_tx.try_send(());
+                }
+            },
+        );
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn send_request<P: Serialize>(
         &self,
@@ -764,10 +1023,7 @@ pub fn get_code_actions(
         path: &Path,
         position: Position,
         diagnostics: Vec<Diagnostic>,
-        cb: impl FnOnce(PluginId, Result<CodeActionResponse, RpcError>)
-            + Clone
-            + Send
-            + 'static,
+        cb: impl FnOnce(Vec<(PluginId, CodeActionResponse)>) + Clone + Send + 'static,
     ) {
         let uri = Url::from_file_path(path).unwrap();
         let method = CodeActionRequest::METHOD;
@@ -787,11 +1043,44 @@ pub fn get_code_actions(
         };
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
-        self.send_request_to_all_plugins(
+        self.send_request_to_all_plugins_merged(
             method,
             params,
             language_id,
             Some(path.to_path_buf()),
+            LspCapability::CodeAction,
+            cb,
+        );
+    }
+
+    pub fn get_code_actions_of_kind(
+        &self,
+        path: &Path,
+        range: Range,
+        kind: CodeActionKind,
+        cb: impl FnOnce(Vec<(PluginId, CodeActionResponse)>) + Clone + Send + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = CodeActionRequest::METHOD;
+        let params = CodeActionParams {
+            text_document: TextDocumentIdentifier { uri },
+            range,
+            context: CodeActionContext {
+                diagnostics: Vec::new(),
+                only: Some(vec![kind]),
+                trigger_kind: Some(CodeActionTriggerKind::AUTOMATIC),
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request_to_all_plugins_merged(
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            LspCapability::CodeAction,
             cb,
         );
     }
@@ -875,7 +1164,7 @@ pub fn get_inline_completions(
         path: &Path,
         position: Position,
         trigger_kind: InlineCompletionTriggerKind,
-        cb: impl FnOnce(PluginId, Result<InlineCompletionResponse, RpcError>)
+        cb: impl FnOnce(Result<InlineCompletionResponse, RpcError>)
             + Clone
             + Send
             + 'static,
@@ -895,12 +1184,31 @@ pub fn get_inline_completions(
         };
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
-        self.send_request_to_all_plugins(
+        // Every plugin providing inline completions is a source of them, with
+        // the items of the ones of higher priority offered first.
+        self.send_request_to_all_plugins_merged(
             method,
             params,
             language_id,
             Some(path.to_path_buf()),
-            cb,
+            LspCapability::InlineCompletion,
+            move |responses: Vec<(PluginId, InlineCompletionResponse)>| {
+                let items: Vec<InlineCompletionItem> = responses
+                    .into_iter()
+                    .flat_map(|(_, response)| match response {
+                        InlineCompletionResponse::Array(items) => items,
+                        InlineCompletionResponse::List(list) => list.items,
+                    })
+                    .collect();
+                if items.is_empty() {
+                    cb(Err(RpcError {
+                        code: 0,
+                        message: "no inline completions".to_string(),
+                    }));
+                } else {
+                    cb(Ok(InlineCompletionResponse::Array(items)));
+                }
+            },
         );
     }
 
@@ -977,6 +1285,93 @@ pub fn get_document_formatting(
         );
     }
 
+    pub fn get_document_range_formatting(
+        &self,
+        path: &Path,
+        range: Range,
+        cb: impl FnOnce(PluginId, Result<Vec<TextEdit>, RpcError>)
+            + Clone
+            + Send
+            + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = RangeFormatting::METHOD;
+        let params = DocumentRangeFormattingParams {
+            text_document: TextDocumentIdentifier { uri },
+            range,
+            options: FormattingOptions {
+                tab_size: 4,
+                insert_spaces: true,
+                ..Default::default()
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request_to_all_plugins(
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            cb,
+        );
+    }
+
+    pub fn get_linked_editing_ranges(
+        &self,
+        path: &Path,
+        position: Position,
+        cb: impl FnOnce(PluginId, Result<LinkedEditingRanges, RpcError>)
+            + Clone
+            + Send
+            + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = LinkedEditingRange::METHOD;
+        let params = LinkedEditingRangeParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request_to_all_plugins(
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            cb,
+        );
+    }
+
+    pub fn get_document_links(
+        &self,
+        path: &Path,
+        cb: impl FnOnce(PluginId, Result<Vec<DocumentLink>, RpcError>)
+            + Clone
+            + Send
+            + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = DocumentLinkRequest::METHOD;
+        let params = DocumentLinkParams {
+            text_document: TextDocumentIdentifier { uri },
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request_to_all_plugins(
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            cb,
+        );
+    }
+
     pub fn prepare_rename(
         &self,
         path: &Path,
@@ -1060,6 +1455,53 @@ pub fn get_semantic_tokens(
         );
     }
 
+    /// Get the changes to the semantic tokens with `previous_result_id` from
+    /// the plugin they're from.
+    pub fn get_semantic_tokens_delta(
+        &self,
+        path: &Path,
+        plugin_id: PluginId,
+        previous_result_id: String,
+        cb: impl FnOnce(Result<SemanticTokensFullDeltaResult, RpcError>)
+            + Clone
+            + Send
+            + 'static,
+    ) {
+        let uri = Url::from_file_path(path).unwrap();
+        let method = SemanticTokensFullDeltaRequest::METHOD;
+        let params = SemanticTokensDeltaParams {
+            text_document: TextDocumentIdentifier { uri },
+            previous_result_id,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        };
+        let language_id =
+            Some(language_id_from_path(path).unwrap_or("").to_string());
+        self.send_request(
+            Some(plugin_id),
+            None,
+            method,
+            params,
+            language_id,
+            Some(path.to_path_buf()),
+            true,
+            move |_, result| {
+                let result = match result {
+                    Ok(value) => serde_json::from_value::<
+                        SemanticTokensFullDeltaResult,
+                    >(value)
+                    .map_err(|_| RpcError {
+                        code: 0,
+                        message: "semantic tokens delta deserialize error"
+                            .to_string(),
+                    }),
+                    Err(e) => Err(e),
+                };
+                cb(result)
+            },
+        );
+    }
+
     pub fn get_selection_range(
         &self,
         path: &Path,
@@ -1092,7 +1534,7 @@ pub fn hover(
         &self,
         path: &Path,
         position: Position,
-        cb: impl FnOnce(PluginId, Result<Hover, RpcError>) + Clone + Send + 'static,
+        cb: impl FnOnce(Result<Hover, RpcError>) + Clone + Send + 'static,
     ) {
         let uri = Url::from_file_path(path).unwrap();
         let method = HoverRequest::METHOD;
@@ -1106,12 +1548,19 @@ pub fn hover(
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
 
-        self.send_request_to_all_plugins(
+        self.send_request_to_all_plugins_merged(
             method,
             params,
             language_id,
             Some(path.to_path_buf()),
-            cb,
+            LspCapability::Hover,
+            move |hovers: Vec<(PluginId, Hover)>| {
+                cb(merge_hovers(hovers.into_iter().map(|(_, hover)| hover))
+                    .ok_or_else(|| RpcError {
+                        code: 0,
+                        message: "no hover".to_string(),
+                    }))
+            },
         );
     }
 
@@ -1135,25 +1584,39 @@ pub fn completion(
         };
 
         let core_rpc = self.core_rpc.clone();
+        let handler = self.clone();
         let language_id =
             Some(language_id_from_path(path).unwrap_or("").to_string());
 
-        self.send_request_to_all_plugins(
+        // The items of each server are sent as soon as it responds, so that a
+        // slow server doesn't hold back those of the others.
+        self.send_request(
+            None,
+            None,
             method,
             params,
             language_id,
             Some(path.to_path_buf()),
-            move |plugin_id, result| match result {
-                Ok(value) => {
-                    if let Ok(resp) =
-                        serde_json::from_value::<CompletionResponse>(value)
-                    {
-                        core_rpc
-                            .completion_response(request_id, input, resp, plugin_id);
-                    }
+            true,
+            move |plugin_id, result| {
+                let priority =
+                    handler.lsp_priority(plugin_id, LspCapability::Completion);
+                if priority < 0 {
+                    return;
                 }
-                Err(err) => {
-                    tracing::error!("{:?}", err);
+                match result {
+                    Ok(value) => {
+                        if let Ok(resp) =
+                            serde_json::from_value::<CompletionResponse>(value)
+                        {
+                            core_rpc.completion_response(
+                                request_id, input, resp, plugin_id, priority,
+                            );
+                        }
+                    }
+                    Err(err) => {
+                        tracing::error!("{:?}", err);
+                    }
                 }
             },
         );
@@ -1276,6 +1739,35 @@ pub fn action_resolve(
         );
     }
 
+    pub fn document_link_resolve(
+        &self,
+        link: DocumentLink,
+        plugin_id: PluginId,
+        cb: impl FnOnce(Result<DocumentLink, RpcError>) + Send + Clone + 'static,
+    ) {
+        let method = DocumentLinkResolve::METHOD;
+        self.send_request(
+            Some(plugin_id),
+            None,
+            method,
+            link,
+            None,
+            None,
+            true,
+            move |_, result| {
+                let result = match result {
+                    Ok(value) => serde_json::from_value::<DocumentLink>(value)
+                        .map_err(|_| RpcError {
+                            code: 0,
+                            message: "document link deserialize error".to_string(),
+                        }),
+                    Err(e) => Err(e),
+                };
+                cb(result)
+            },
+        );
+    }
+
     pub fn did_open_document(
         &self,
         path: &Path,
@@ -1317,6 +1809,12 @@ pub fn plugin_server_loaded(
         ))
     }
 
+    pub fn update_workspace_folders(&self, folders: Vec<PathBuf>) -> Result<()> {
+        self.catalog_notification(PluginCatalogNotification::UpdateWorkspaceFolders(
+            folders,
+        ))
+    }
+
     pub fn update_plugin_configs(
         &self,
         configs: HashMap<String, HashMap<String, serde_json::Value>>,
@@ -1326,6 +1824,10 @@ pub fn update_plugin_configs(
         ))
     }
 
+    pub fn plugin_event(&self, event: PluginEvent) -> Result<()> {
+        self.catalog_notification(PluginCatalogNotification::PluginEvent(event))
+    }
+
     pub fn install_volt(&self, volt: VoltInfo) -> Result<()> {
         self.catalog_notification(PluginCatalogNotification::InstallVolt(volt))
     }
@@ -1368,6 +1870,17 @@ pub fn reload_volt(&self, volt: VoltMetadata) -> Result<()> {
         self.catalog_notification(PluginCatalogNotification::ReloadVolt(volt))
     }
 
+    pub fn stop_language_servers(
+        &self,
+        path: Option<PathBuf>,
+        restart: bool,
+    ) -> Result<()> {
+        self.catalog_notification(PluginCatalogNotification::StopLanguageServers {
+            path,
+            restart,
+        })
+    }
+
     pub fn enable_volt(&self, volt: VoltInfo) -> Result<()> {
         self.catalog_notification(PluginCatalogNotification::EnableVolt(volt))
     }
@@ -1488,6 +2001,63 @@ pub fn dap_variable(
         }
     }
 
+    pub fn dap_set_variable(
+        &self,
+        dap_id: DapId,
+        reference: usize,
+        name: String,
+        value: String,
+        f: impl FnOnce(Result<dap_types::SetVariableResponse, RpcError>) + Send + 'static,
+    ) {
+        if let Err(err) = self.plugin_tx.send(PluginCatalogRpc::DapSetVariable {
+            dap_id,
+            reference,
+            name,
+            value,
+            f: Box::new(f),
+        }) {
+            tracing::error!("{:?}", err);
+        }
+    }
+
+    pub fn dap_evaluate(
+        &self,
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: String,
+        f: impl FnOnce(Result<dap_types::EvaluateResponse, RpcError>) + Send + 'static,
+    ) {
+        if let Err(err) = self.plugin_tx.send(PluginCatalogRpc::DapEvaluate {
+            dap_id,
+            expression,
+            frame_id,
+            context,
+            f: Box::new(f),
+        }) {
+            tracing::error!("{:?}", err);
+        }
+    }
+
+    pub fn dap_completions(
+        &self,
+        dap_id: DapId,
+        text: String,
+        column: usize,
+        frame_id: Option<usize>,
+        f: impl FnOnce(Result<Vec<dap_types::CompletionItem>, RpcError>) + Send + 'static,
+    ) {
+        if let Err(err) = self.plugin_tx.send(PluginCatalogRpc::DapCompletions {
+            dap_id,
+            text,
+            column,
+            frame_id,
+            f: Box::new(f),
+        }) {
+            tracing::error!("{:?}", err);
+        }
+    }
+
     pub fn dap_get_scopes(
         &self,
         dap_id: DapId,
@@ -1663,7 +2233,112 @@ pub fn remove_volt(
     Ok(())
 }
 
-fn client_capabilities() -> ClientCapabilities {
+/// The workspace folder of `path`, named after its last component.
+pub fn workspace_folder(path: &Path) -> Option<WorkspaceFolder> {
+    let uri = Url::from_directory_path(path).ok()?;
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| uri.as_str().to_string());
+    Some(WorkspaceFolder { uri, name })
+}
+
+/// A setting holding JSON, which is either a value of the settings file or a
+/// string with the JSON in it.
+pub fn setting_json(value: &Value) -> Value {
+    value
+        .as_str()
+        .and_then(|s| serde_json::from_str::<Value>(s).ok())
+        .filter(|v| v.is_object() || v.is_array())
+        .unwrap_or_else(|| value.clone())
+}
+
+/// Merge `overrides` into `value`, going into the objects both have and
+/// replacing everything else.
+pub fn merge_json(value: &mut Value, overrides: Value) {
+    match (value, overrides) {
+        (Value::Object(value), Value::Object(overrides)) => {
+            for (key, v) in overrides {
+                match value.get_mut(&key) {
+                    Some(existing) => merge_json(existing, v),
+                    None => {
+                        value.insert(key, v);
+                    }
+                }
+            }
+        }
+        (value, overrides) => *value = overrides,
+    }
+}
+
+/// The part of `settings` under the dot separated `section`, as asked for by
+/// the language servers with `workspace/configuration`. A dotted key of the
+/// settings, such as `"rust-analyzer.check"`, is found too.
+pub fn configuration_section(settings: &Value, section: Option<&str>) -> Value {
+    let Some(section) = section.filter(|s| !s.is_empty()) else {
+        return settings.clone();
+    };
+    if let Some(value) = settings.get(section) {
+        return value.clone();
+    }
+    section
+        .match_indices('.')
+        .filter_map(|(i, _)| {
+            let value = settings.get(&section[..i])?;
+            Some(configuration_section(value, Some(&section[i + 1..])))
+        })
+        .find(|value| !value.is_null())
+        .unwrap_or(Value::Null)
+}
+
+/// Join the contents of the hovers from several language servers into one
+/// markdown hover, with the range of the first of them.
+fn merge_hovers(hovers: impl IntoIterator<Item = Hover>) -> Option<Hover> {
+    let mut hovers = hovers.into_iter();
+    let first = hovers.next()?;
+    let rest: Vec<Hover> = hovers.collect();
+    if rest.is_empty() {
+        return Some(first);
+    }
+
+    let markdown = |contents: HoverContents| match contents {
+        HoverContents::Scalar(string) => marked_string_markdown(string),
+        HoverContents::Array(strings) => strings
+            .into_iter()
+            .map(marked_string_markdown)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        HoverContents::Markup(content) => content.value,
+    };
+    let range = first.range;
+    let value = std::iter::once(first)
+        .chain(rest)
+        .map(|hover| markdown(hover.contents))
+        .filter(|value| !value.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n");
+    Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range,
+    })
+}
+
+fn marked_string_markdown(string: MarkedString) -> String {
+    match string {
+        MarkedString::String(string) => string,
+        MarkedString::LanguageString(LanguageString { language, value }) => {
+            format!("```{language}\n{value}\n```")
+        }
+    }
+}
+
+/// The capabilities of the client, with `configuration` for whether the
+/// settings are served to the server with `workspace/configuration`, which
+/// only the language servers get.
+fn client_capabilities(configuration: bool) -> ClientCapabilities {
     // https://github.com/rust-lang/rust-analyzer/blob/master/docs/dev/lsp-extensions.md#server-status
     let mut experimental = Map::new();
     experimental.insert("serverStatusNotification".into(), true.into());
@@ -1677,6 +2352,8 @@ fn client_capabilities() -> ClientCapabilities {
             completion: Some(CompletionClientCapabilities {
                 completion_item: Some(CompletionItemCapability {
                     snippet_support: Some(true),
+                    commit_characters_support: Some(true),
+                    insert_replace_support: Some(true),
                     resolve_support: Some(CompletionItemCapabilityResolveSupport {
                         properties: vec!["additionalTextEdits".to_string()],
                     }),
@@ -1733,6 +2410,12 @@ fn client_capabilities() -> ClientCapabilities {
                 ..Default::default()
             }),
             semantic_tokens: Some(SemanticTokensClientCapabilities {
+                requests: SemanticTokensClientCapabilitiesRequests {
+                    range: None,
+                    full: Some(SemanticTokensFullOptions::Delta {
+                        delta: Some(true),
+                    }),
+                },
                 ..Default::default()
             }),
             type_definition: Some(GotoCapability {
@@ -1747,6 +2430,10 @@ fn client_capabilities() -> ClientCapabilities {
             publish_diagnostics: Some(PublishDiagnosticsClientCapabilities {
                 ..Default::default()
             }),
+            diagnostic: Some(DiagnosticClientCapabilities {
+                dynamic_registration: Some(false),
+                related_document_support: Some(true),
+            }),
             inline_completion: Some(InlineCompletionClientCapabilities {
                 ..Default::default()
             }),
@@ -1757,6 +2444,16 @@ fn client_capabilities() -> ClientCapabilities {
                 hierarchical_document_symbol_support: Some(true),
                 ..Default::default()
             }),
+            linked_editing_range: Some(LinkedEditingRangeClientCapabilities {
+                dynamic_registration: Some(false),
+            }),
+            selection_range: Some(SelectionRangeClientCapabilities {
+                dynamic_registration: Some(false),
+            }),
+            document_link: Some(DocumentLinkClientCapabilities {
+                dynamic_registration: Some(false),
+                tooltip_support: Some(true),
+            }),
             folding_range: Some(FoldingRangeClientCapabilities {
                 dynamic_registration: Some(false),
                 range_limit: None,
@@ -1779,11 +2476,169 @@ fn client_capabilities() -> ClientCapabilities {
             symbol: Some(WorkspaceSymbolClientCapabilities {
                 ..Default::default()
             }),
-            configuration: Some(false),
+            configuration: Some(configuration),
             workspace_folders: Some(true),
+            diagnostic: Some(DiagnosticWorkspaceClientCapabilities {
+                refresh_support: Some(true),
+            }),
+            workspace_edit: Some(WorkspaceEditClientCapabilities {
+                document_changes: Some(true),
+                resource_operations: Some(vec![
+                    ResourceOperationKind::Create,
+                    ResourceOperationKind::Rename,
+                    ResourceOperationKind::Delete,
+                ]),
+                failure_handling: Some(FailureHandlingKind::Abort),
+                ..Default::default()
+            }),
             ..Default::default()
         }),
         experimental: Some(experimental.into()),
         ..Default::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use lsp_types::{
+        Hover, HoverContents, LanguageString, MarkedString, MarkupContent,
+        MarkupKind,
+    };
+    use serde_json::json;
+
+    use super::{
+        client_capabilities, configuration_section, merge_hovers, merge_json,
+        setting_json, LspCapability, LspPriority,
+    };
+
+    #[test]
+    fn test_lsp_priority() {
+        let config: HashMap<String, serde_json::Value> = HashMap::from([(
+            "lsp-priority".to_string(),
+            json!({ "completion": 2, "hover": -1, "code-action": "high" }),
+        )]);
+        let priority = LspPriority::from_config(Some(&config));
+        assert_eq!(priority.get(LspCapability::Completion), 2);
+        assert_eq!(priority.get(LspCapability::Hover), -1);
+        assert_eq!(priority.get(LspCapability::CodeAction), 0);
+        assert_eq!(priority.get(LspCapability::Diagnostics), 0);
+
+        let priority = LspPriority::from_config(None);
+        assert_eq!(priority.get(LspCapability::Completion), 0);
+    }
+
+    #[test]
+    fn test_merge_hovers() {
+        assert!(merge_hovers(Vec::new()).is_none());
+
+        let hover = |contents| Hover {
+            contents,
+            range: None,
+        };
+        let first = hover(HoverContents::Scalar(MarkedString::LanguageString(
+            LanguageString {
+                language: "rust".to_string(),
+                value: "fn main()".to_string(),
+            },
+        )));
+        assert_eq!(merge_hovers(vec![first.clone()]), Some(first.clone()));
+
+        let merged = merge_hovers(vec![
+            first,
+            hover(HoverContents::Array(Vec::new())),
+            hover(HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::PlainText,
+                value: "The entry point".to_string(),
+            })),
+        ]);
+        assert_eq!(
+            merged,
+            Some(hover(HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: "```rust\nfn main()\n```\n\n---\n\nThe entry point"
+                    .to_string(),
+            })))
+        );
+    }
+
+    #[test]
+    fn test_client_capabilities_configuration() {
+        let configuration = |capabilities: lsp_types::ClientCapabilities| {
+            capabilities.workspace.and_then(|w| w.configuration)
+        };
+        assert_eq!(configuration(client_capabilities(true)), Some(true));
+        assert_eq!(configuration(client_capabilities(false)), Some(false));
+    }
+
+    #[test]
+    fn test_setting_json() {
+        let table = json!({ "check": { "command": "clippy" } });
+        assert_eq!(setting_json(&table), table);
+        assert_eq!(
+            setting_json(&json!(r#"{ "check": { "command": "clippy" } }"#)),
+            table
+        );
+        assert_eq!(setting_json(&json!("clippy")), json!("clippy"));
+        assert_eq!(setting_json(&json!("42")), json!("42"));
+        assert_eq!(setting_json(&json!(true)), json!(true));
+    }
+
+    #[test]
+    fn test_merge_json() {
+        let mut value = json!({
+            "check": { "command": "check", "allTargets": true },
+            "cargo": { "features": ["a"] },
+        });
+        merge_json(
+            &mut value,
+            json!({
+                "check": { "command": "clippy" },
+                "cargo": { "features": ["b"] },
+                "procMacro": { "enable": false },
+            }),
+        );
+        assert_eq!(
+            value,
+            json!({
+                "check": { "command": "clippy", "allTargets": true },
+                "cargo": { "features": ["b"] },
+                "procMacro": { "enable": false },
+            })
+        );
+
+        let mut value = json!({ "check": true });
+        merge_json(&mut value, json!(null));
+        assert_eq!(value, json!(null));
+    }
+
+    #[test]
+    fn test_configuration_section() {
+        let settings = json!({
+            "rust-analyzer": { "check": { "command": "clippy" } },
+            "python.analysis": { "typeCheckingMode": "strict" },
+        });
+        assert_eq!(configuration_section(&settings, None), settings);
+        assert_eq!(configuration_section(&settings, Some("")), settings);
+        assert_eq!(
+            configuration_section(&settings, Some("rust-analyzer.check")),
+            json!({ "command": "clippy" })
+        );
+        assert_eq!(
+            configuration_section(&settings, Some("python.analysis")),
+            json!({ "typeCheckingMode": "strict" })
+        );
+        assert_eq!(
+            configuration_section(
+                &settings,
+                Some("python.analysis.typeCheckingMode")
+            ),
+            json!("strict")
+        );
+        assert_eq!(
+            configuration_section(&settings, Some("rust-analyzer.cargo")),
+            json!(null)
+        );
+    }
+}
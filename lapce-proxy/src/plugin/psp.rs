@@ -1,12 +1,13 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
     thread,
+    time::Duration,
 };
 
 use anyhow::{anyhow, Result};
@@ -17,37 +18,48 @@
 use lapce_core::{encoding::offset_utf16_to_utf8, rope_text_pos::RopeTextPosition};
 use lapce_rpc::{
     core::{CoreRpcHandler, ServerStatusParams},
-    plugin::{PluginId, VoltID},
+    plugin::{
+        OpenDiffParams, PluginEvent, PluginEventKind, PluginId,
+        SubscribeEventsParams, VoltID,
+    },
     style::{LineStyle, Style},
     RpcError,
 };
 use lapce_xi_rope::{Rope, RopeDelta};
 use lsp_types::{
     notification::{
-        Cancel, DidChangeTextDocument, DidOpenTextDocument, DidSaveTextDocument,
-        Initialized, LogMessage, Notification, Progress, PublishDiagnostics,
-        ShowMessage,
+        Cancel, DidChangeTextDocument, DidChangeWorkspaceFolders,
+        DidOpenTextDocument, DidSaveTextDocument, Initialized, LogMessage,
+        Notification, Progress, PublishDiagnostics, ShowMessage,
     },
     request::{
         CallHierarchyIncomingCalls, CallHierarchyPrepare, CodeActionRequest,
         CodeActionResolveRequest, CodeLensRequest, CodeLensResolve, Completion,
+        DocumentDiagnosticRequest, DocumentLinkRequest, DocumentLinkResolve,
         DocumentSymbolRequest, FoldingRangeRequest, Formatting, GotoDefinition,
         GotoImplementation, GotoTypeDefinition, HoverRequest, Initialize,
-        InlayHintRequest, InlineCompletionRequest, PrepareRenameRequest, References,
-        RegisterCapability, Rename, ResolveCompletionItem, SelectionRangeRequest,
-        SemanticTokensFullRequest, SignatureHelpRequest, WorkDoneProgressCreate,
-        WorkspaceSymbolRequest,
+        InlayHintRequest, InlineCompletionRequest, LinkedEditingRange,
+        PrepareRenameRequest, RangeFormatting, References, RegisterCapability,
+        Rename, ResolveCompletionItem, SelectionRangeRequest,
+        SemanticTokensFullDeltaRequest, SemanticTokensFullRequest,
+        SignatureHelpRequest, WorkDoneProgressCreate, WorkspaceConfiguration,
+        WorkspaceDiagnosticRefresh, WorkspaceFoldersRequest, WorkspaceSymbolRequest,
     },
-    CancelParams, CodeActionProviderCapability, DidChangeTextDocumentParams,
-    DidSaveTextDocumentParams, DocumentSelector, FoldingRangeProviderCapability,
-    HoverProviderCapability, ImplementationProviderCapability, InitializeResult,
-    LogMessageParams, MessageType, OneOf, ProgressParams, PublishDiagnosticsParams,
-    Range, Registration, RegistrationParams, SemanticTokens, SemanticTokensLegend,
-    SemanticTokensServerCapabilities, ServerCapabilities, ShowMessageParams,
-    TextDocumentContentChangeEvent, TextDocumentIdentifier,
-    TextDocumentSaveRegistrationOptions, TextDocumentSyncCapability,
-    TextDocumentSyncKind, TextDocumentSyncSaveOptions,
-    VersionedTextDocumentIdentifier,
+    CancelParams, CodeActionOptions, CodeActionProviderCapability,
+    ConfigurationParams, DiagnosticServerCapabilities, DidChangeTextDocumentParams,
+    DidOpenTextDocumentParams, DidSaveTextDocumentParams, DocumentDiagnosticParams,
+    DocumentDiagnosticReport, DocumentDiagnosticReportKind,
+    DocumentDiagnosticReportResult, DocumentSelector,
+    FoldingRangeProviderCapability, HoverProviderCapability,
+    ImplementationProviderCapability, InitializeResult, LogMessageParams,
+    MessageType, OneOf, PartialResultParams, ProgressParams,
+    PublishDiagnosticsParams, Range, Registration, RegistrationParams,
+    SemanticToken, SemanticTokens, SemanticTokensEdit, SemanticTokensFullOptions,
+    SemanticTokensLegend, SemanticTokensOptions, SemanticTokensServerCapabilities,
+    ServerCapabilities, ShowMessageParams, TextDocumentContentChangeEvent,
+    TextDocumentIdentifier, TextDocumentSaveRegistrationOptions,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncSaveOptions,
+    Url, VersionedTextDocumentIdentifier, WorkDoneProgressParams,
 };
 use parking_lot::Mutex;
 use psp_types::{
@@ -61,8 +73,9 @@
 use serde_json::Value;
 
 use super::{
+    configuration_section,
     lsp::{DocumentFilter, LspClient},
-    PluginCatalogRpcHandler,
+    LspCapability, PluginCatalogRpcHandler,
 };
 
 pub enum ResponseHandler<Resp, Error> {
@@ -165,6 +178,8 @@ pub enum PluginServerRpc {
         text: Rope,
         f: Box<dyn RpcCallback<Vec<LineStyle>, RpcError>>,
     },
+    PluginEvent(PluginEvent),
+    PullDiagnostics(Url),
 }
 
 #[derive(Clone)]
@@ -265,6 +280,8 @@ fn format_semantic_tokens(
         text: Rope,
         f: Box<dyn RpcCallback<Vec<LineStyle>, RpcError>>,
     );
+    fn handle_plugin_event(&self, event: PluginEvent);
+    fn pull_diagnostics(&self, uri: Url);
 }
 
 impl PluginServerRpcHandler {
@@ -484,7 +501,20 @@ pub fn mainloop<H>(&self, handler: &mut H)
                         .document_supported(language_id.as_deref(), path.as_deref())
                         && handler.method_registered(&method)
                     {
+                        let opened = if method == DidOpenTextDocument::METHOD {
+                            serde_json::to_value(&params).ok().and_then(|params| {
+                                serde_json::from_value::<DidOpenTextDocumentParams>(
+                                    params,
+                                )
+                                .ok()
+                            })
+                        } else {
+                            None
+                        };
                         self.send_server_notification(&method, params);
+                        if let Some(opened) = opened {
+                            handler.pull_diagnostics(opened.text_document.uri);
+                        }
                     }
                 }
                 PluginServerRpc::HostRequest {
@@ -535,6 +565,12 @@ pub fn mainloop<H>(&self, handler: &mut H)
                 PluginServerRpc::FormatSemanticTokens { tokens, text, f } => {
                     handler.format_semantic_tokens(tokens, text, f);
                 }
+                PluginServerRpc::PluginEvent(event) => {
+                    handler.handle_plugin_event(event);
+                }
+                PluginServerRpc::PullDiagnostics(uri) => {
+                    handler.pull_diagnostics(uri);
+                }
                 PluginServerRpc::Handler(notification) => {
                     handler.handle_handler_notification(notification)
                 }
@@ -613,6 +649,23 @@ struct SaveRegistration {
     filters: Vec<DocumentFilter>,
 }
 
+/// How long a document has to go unchanged before its diagnostics are pulled.
+const PULL_DIAGNOSTICS_DELAY: Duration = Duration::from_millis(300);
+
+/// The diagnostics last pulled for a document from a server.
+#[derive(Default)]
+struct PulledDiagnostics {
+    /// The id of the last request, so that the responses to older ones are
+    /// dropped.
+    request: u64,
+    /// The id the server gave to its result, sent back to let it answer that
+    /// nothing changed.
+    result_id: Option<String>,
+    /// The number of changes made to the document, so that the diagnostics
+    /// are only pulled once the changes pause.
+    changes: u64,
+}
+
 #[derive(Default)]
 struct ServerRegistrations {
     save: Option<SaveRegistration>,
@@ -625,7 +678,7 @@ pub struct PluginHostHandler {
     pub(crate) workspace: Option<PathBuf>,
     document_selector: Vec<DocumentFilter>,
     core_rpc: CoreRpcHandler,
-    catalog_rpc: PluginCatalogRpcHandler,
+    pub(crate) catalog_rpc: PluginCatalogRpcHandler,
     pub server_rpc: PluginServerRpcHandler,
     pub server_capabilities: ServerCapabilities,
     server_registrations: ServerRegistrations,
@@ -633,6 +686,13 @@ pub struct PluginHostHandler {
     /// Language servers that this plugin has spawned.  
     /// Note that these plugin ids could be 'dead' if the LSP died/exited.  
     spawned_lsp: HashMap<PluginId, SpawnedLspInfo>,
+
+    /// The editor events the plugin has subscribed to.
+    subscribed_events: HashSet<PluginEventKind>,
+
+    /// The documents the diagnostics were pulled for, to pull them again when
+    /// the server asks for a refresh.
+    pulled_diagnostics: Arc<Mutex<HashMap<Url, PulledDiagnostics>>>,
 }
 
 impl PluginHostHandler {
@@ -663,6 +723,8 @@ pub fn new(
             server_capabilities: ServerCapabilities::default(),
             server_registrations: ServerRegistrations::default(),
             spawned_lsp: HashMap::new(),
+            subscribed_events: HashSet::new(),
+            pulled_diagnostics: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -815,9 +877,29 @@ pub fn method_registered(&mut self, method: &str) -> bool {
                     OneOf::Right(_) => true,
                 })
                 .unwrap_or(false),
+            RangeFormatting::METHOD => self
+                .server_capabilities
+                .document_range_formatting_provider
+                .as_ref()
+                .map(|f| match f {
+                    OneOf::Left(is_capable) => *is_capable,
+                    OneOf::Right(_) => true,
+                })
+                .unwrap_or(false),
             SemanticTokensFullRequest::METHOD => {
                 self.server_capabilities.semantic_tokens_provider.is_some()
             }
+            SemanticTokensFullDeltaRequest::METHOD => self
+                .server_capabilities
+                .semantic_tokens_provider
+                .as_ref()
+                .map(|provider| {
+                    matches!(
+                        semantic_tokens_options(provider).full,
+                        Some(SemanticTokensFullOptions::Delta { delta: Some(true) })
+                    )
+                })
+                .unwrap_or(false),
             InlayHintRequest::METHOD => {
                 self.server_capabilities.inlay_hint_provider.is_some()
             }
@@ -839,11 +921,26 @@ pub fn method_registered(&mut self, method: &str) -> bool {
                 self.server_capabilities.selection_range_provider.is_some()
             }
             CodeActionResolveRequest::METHOD => {
-                self.server_capabilities.code_action_provider.is_some()
+                matches!(
+                    self.server_capabilities.code_action_provider,
+                    Some(CodeActionProviderCapability::Options(CodeActionOptions {
+                        resolve_provider: Some(true),
+                        ..
+                    }))
+                )
             }
             CodeLensRequest::METHOD => {
                 self.server_capabilities.code_lens_provider.is_some()
             }
+            DocumentLinkRequest::METHOD => {
+                self.server_capabilities.document_link_provider.is_some()
+            }
+            DocumentLinkResolve::METHOD => self
+                .server_capabilities
+                .document_link_provider
+                .as_ref()
+                .and_then(|x| x.resolve_provider)
+                .unwrap_or(false),
             CodeLensResolve::METHOD => self
                 .server_capabilities
                 .code_lens_provider
@@ -856,6 +953,17 @@ pub fn method_registered(&mut self, method: &str) -> bool {
             CallHierarchyIncomingCalls::METHOD => {
                 self.server_capabilities.call_hierarchy_provider.is_some()
             }
+            LinkedEditingRange::METHOD => self
+                .server_capabilities
+                .linked_editing_range_provider
+                .is_some(),
+            DidChangeWorkspaceFolders::METHOD => self
+                .server_capabilities
+                .workspace
+                .as_ref()
+                .and_then(|w| w.workspace_folders.as_ref())
+                .and_then(|f| f.supported)
+                .unwrap_or(false),
             _ => false,
         }
     }
@@ -959,6 +1067,36 @@ pub fn process_request(
             WorkDoneProgressCreate::METHOD => {
                 resp.send_null();
             }
+            WorkspaceConfiguration::METHOD => {
+                let params: ConfigurationParams =
+                    serde_json::from_value(serde_json::to_value(params)?)?;
+                let settings = self
+                    .catalog_rpc
+                    .lsp_setting(&self.volt_id, "lsp-settings")
+                    .unwrap_or(Value::Null);
+                let items: Vec<Value> = params
+                    .items
+                    .iter()
+                    .map(|item| {
+                        configuration_section(&settings, item.section.as_deref())
+                    })
+                    .collect();
+                resp.send(items);
+            }
+            WorkspaceDiagnosticRefresh::METHOD => {
+                resp.send_null();
+                let uris: Vec<Url> =
+                    self.pulled_diagnostics.lock().keys().cloned().collect();
+                for uri in uris {
+                    self.pull_diagnostics(uri);
+                }
+            }
+            WorkspaceFoldersRequest::METHOD => {
+                resp.send(
+                    self.catalog_rpc
+                        .lsp_workspace_folders(self.workspace.as_deref()),
+                );
+            }
             RegisterCapability::METHOD => {
                 let params: RegistrationParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
@@ -1128,15 +1266,48 @@ pub fn handle_notification(
                     }
                 });
             }
+            PluginEvent::SUBSCRIBE_METHOD => {
+                let params: SubscribeEventsParams =
+                    serde_json::from_value(serde_json::to_value(params)?)?;
+                self.subscribed_events.extend(params.events);
+                if self
+                    .subscribed_events
+                    .contains(&PluginEventKind::WorkspaceOpened)
+                {
+                    self.handle_plugin_event(PluginEvent::WorkspaceOpened {
+                        path: self.workspace.clone(),
+                    });
+                }
+            }
+            OpenDiffParams::METHOD => {
+                let params: OpenDiffParams =
+                    serde_json::from_value(serde_json::to_value(params)?)?;
+                self.catalog_rpc
+                    .core_rpc
+                    .open_diff_files(params.left, params.right);
+            }
             PublishDiagnostics::METHOD => {
                 let diagnostics: PublishDiagnosticsParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
-                self.catalog_rpc.core_rpc.publish_diagnostics(diagnostics);
+                let plugin_id = self.server_rpc.plugin_id;
+                if self
+                    .catalog_rpc
+                    .lsp_priority(plugin_id, LspCapability::Diagnostics)
+                    >= 0
+                {
+                    self.catalog_rpc
+                        .core_rpc
+                        .publish_diagnostics(diagnostics, plugin_id);
+                }
             }
             Progress::METHOD => {
                 let progress: ProgressParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
-                self.catalog_rpc.core_rpc.work_done_progress(progress);
+                self.catalog_rpc.core_rpc.work_done_progress(
+                    self.server_rpc.plugin_id,
+                    self.volt_display_name.clone(),
+                    progress,
+                );
             }
             ShowMessage::METHOD => {
                 let message: ShowMessageParams =
@@ -1147,6 +1318,11 @@ pub fn handle_notification(
             LogMessage::METHOD => {
                 let message: LogMessageParams =
                     serde_json::from_value(serde_json::to_value(params)?)?;
+                self.catalog_rpc.core_rpc.language_server_output(
+                    self.volt_id.clone(),
+                    self.volt_display_name.clone(),
+                    message.message.clone(),
+                );
                 self.catalog_rpc.core_rpc.log_message(
                     message,
                     format!(
@@ -1190,6 +1366,127 @@ pub fn handle_notification(
         Ok(())
     }
 
+    pub fn handle_plugin_event(&self, event: PluginEvent) {
+        if let PluginEvent::DocumentClosed { path } = &event {
+            if let Ok(uri) = Url::from_file_path(path) {
+                self.pulled_diagnostics.lock().remove(&uri);
+            }
+        }
+        if self.subscribed_events.contains(&event.kind()) {
+            self.server_rpc.server_notification(
+                PluginEvent::METHOD,
+                event,
+                None,
+                None,
+                false,
+            );
+        }
+    }
+
+    /// Request the diagnostics of the document from a server using the pull
+    /// model, and publish them as if the server had pushed them.
+    pub fn pull_diagnostics(&self, uri: Url) {
+        let identifier = match &self.server_capabilities.diagnostic_provider {
+            Some(DiagnosticServerCapabilities::Options(options)) => {
+                options.identifier.clone()
+            }
+            Some(DiagnosticServerCapabilities::RegistrationOptions(options)) => {
+                options.diagnostic_options.identifier.clone()
+            }
+            None => return,
+        };
+        let plugin_id = self.server_rpc.plugin_id;
+        if self
+            .catalog_rpc
+            .lsp_priority(plugin_id, LspCapability::Diagnostics)
+            < 0
+        {
+            return;
+        }
+
+        let (request, previous_result_id) = {
+            let mut pulled = self.pulled_diagnostics.lock();
+            let pulled = pulled.entry(uri.clone()).or_default();
+            pulled.request += 1;
+            (pulled.request, pulled.result_id.clone())
+        };
+        let pulled_diagnostics = self.pulled_diagnostics.clone();
+        let core_rpc = self.core_rpc.clone();
+        let path = uri.to_file_path().ok();
+        self.server_rpc.server_request_async(
+            DocumentDiagnosticRequest::METHOD,
+            DocumentDiagnosticParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                identifier,
+                previous_result_id,
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+            },
+            None,
+            path,
+            false,
+            move |result: Result<Value, RpcError>| {
+                let Ok(DocumentDiagnosticReportResult::Report(report)) = result
+                    .and_then(|value| {
+                        serde_json::from_value(value).map_err(|e| RpcError {
+                            code: 0,
+                            message: e.to_string(),
+                        })
+                    })
+                else {
+                    return;
+                };
+                let (report, related_documents) = match report {
+                    DocumentDiagnosticReport::Full(report) => (
+                        DocumentDiagnosticReportKind::Full(
+                            report.full_document_diagnostic_report,
+                        ),
+                        report.related_documents,
+                    ),
+                    DocumentDiagnosticReport::Unchanged(report) => (
+                        DocumentDiagnosticReportKind::Unchanged(
+                            report.unchanged_document_diagnostic_report,
+                        ),
+                        report.related_documents,
+                    ),
+                };
+
+                {
+                    let mut pulled = pulled_diagnostics.lock();
+                    let Some(pulled) = pulled.get_mut(&uri) else {
+                        return;
+                    };
+                    if pulled.request != request {
+                        return;
+                    }
+                    pulled.result_id = match &report {
+                        DocumentDiagnosticReportKind::Full(report) => {
+                            report.result_id.clone()
+                        }
+                        DocumentDiagnosticReportKind::Unchanged(report) => {
+                            Some(report.result_id.clone())
+                        }
+                    };
+                }
+
+                for (uri, report) in std::iter::once((uri, report))
+                    .chain(related_documents.into_iter().flatten())
+                {
+                    if let DocumentDiagnosticReportKind::Full(report) = report {
+                        core_rpc.publish_diagnostics(
+                            PublishDiagnosticsParams {
+                                uri,
+                                diagnostics: report.items,
+                                version: None,
+                            },
+                            plugin_id,
+                        );
+                    }
+                }
+            },
+        );
+    }
+
     pub fn handle_did_save_text_document(
         &self,
         language_id: String,
@@ -1202,6 +1499,7 @@ pub fn handle_did_save_text_document(
         if !should_send {
             return;
         }
+        let text_document_uri = text_document.uri.clone();
         let params = DidSaveTextDocumentParams {
             text_document,
             text: if include_text {
@@ -1217,6 +1515,7 @@ pub fn handle_did_save_text_document(
             Some(path),
             false,
         );
+        self.pull_diagnostics(text_document_uri);
     }
 
     pub fn handle_did_change_text_document(
@@ -1275,6 +1574,7 @@ pub fn handle_did_change_text_document(
         };
 
         let path = document.uri.to_file_path().ok();
+        let uri = document.uri.clone();
 
         let params = DidChangeTextDocumentParams {
             text_document: document,
@@ -1288,6 +1588,34 @@ pub fn handle_did_change_text_document(
             path,
             false,
         );
+        self.pull_diagnostics_after_changes(uri);
+    }
+
+    /// Pull the diagnostics of the document once it hasn't changed for a
+    /// while, rather than after every change.
+    fn pull_diagnostics_after_changes(&self, uri: Url) {
+        if self.server_capabilities.diagnostic_provider.is_none() {
+            return;
+        }
+        let changes = {
+            let mut pulled = self.pulled_diagnostics.lock();
+            let pulled = pulled.entry(uri.clone()).or_default();
+            pulled.changes += 1;
+            pulled.changes
+        };
+        let pulled_diagnostics = self.pulled_diagnostics.clone();
+        let server_rpc = self.server_rpc.clone();
+        thread::spawn(move || {
+            thread::sleep(PULL_DIAGNOSTICS_DELAY);
+            let changed = pulled_diagnostics
+                .lock()
+                .get(&uri)
+                .map(|pulled| pulled.changes)
+                != Some(changes);
+            if !changed {
+                server_rpc.handle_rpc(PluginServerRpc::PullDiagnostics(uri));
+            }
+        });
     }
 
     pub fn format_semantic_tokens(
@@ -1427,12 +1755,100 @@ fn format_semantic_styles(
 fn semantic_tokens_legend(
     semantic_tokens_provider: &SemanticTokensServerCapabilities,
 ) -> &SemanticTokensLegend {
+    &semantic_tokens_options(semantic_tokens_provider).legend
+}
+
+fn semantic_tokens_options(
+    semantic_tokens_provider: &SemanticTokensServerCapabilities,
+) -> &SemanticTokensOptions {
     match semantic_tokens_provider {
-        SemanticTokensServerCapabilities::SemanticTokensOptions(options) => {
-            &options.legend
-        }
+        SemanticTokensServerCapabilities::SemanticTokensOptions(options) => options,
         SemanticTokensServerCapabilities::SemanticTokensRegistrationOptions(
             options,
-        ) => &options.semantic_tokens_options.legend,
+        ) => &options.semantic_tokens_options,
+    }
+}
+
+/// Apply the edits of a semantic tokens delta to the tokens it's relative to.
+/// The edits index into the integers the tokens are encoded as, five to a
+/// token, so this returns false, leaving `data` unchanged, when they don't
+/// start and end on token boundaries or go out of bounds.
+pub fn apply_semantic_tokens_edits(
+    data: &mut Vec<SemanticToken>,
+    edits: Vec<SemanticTokensEdit>,
+) -> bool {
+    const TOKEN_LEN: usize = 5;
+
+    let mut ranges = Vec::with_capacity(edits.len());
+    for edit in &edits {
+        let (start, delete_count) =
+            (edit.start as usize, edit.delete_count as usize);
+        if start % TOKEN_LEN != 0
+            || delete_count % TOKEN_LEN != 0
+            || (start + delete_count) / TOKEN_LEN > data.len()
+        {
+            return false;
+        }
+        ranges.push((start / TOKEN_LEN, (start + delete_count) / TOKEN_LEN));
+    }
+
+    // The edits are relative to the tokens before any of them, so apply them
+    // from the last one backwards to keep the earlier indices valid.
+    let mut edits: Vec<_> = ranges.into_iter().zip(edits).collect();
+    edits.sort_by_key(|((start, _), _)| std::cmp::Reverse(*start));
+    for ((start, end), edit) in edits {
+        data.splice(start..end, edit.data.unwrap_or_default());
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{SemanticToken, SemanticTokensEdit};
+
+    use super::apply_semantic_tokens_edits;
+
+    fn token(delta_line: u32) -> SemanticToken {
+        SemanticToken {
+            delta_line,
+            delta_start: 0,
+            length: 1,
+            token_type: 0,
+            token_modifiers_bitset: 0,
+        }
+    }
+
+    fn edit(start: u32, delete_count: u32, data: &[u32]) -> SemanticTokensEdit {
+        SemanticTokensEdit {
+            start,
+            delete_count,
+            data: Some(data.iter().copied().map(token).collect()),
+        }
+    }
+
+    #[test]
+    fn test_apply_semantic_tokens_edits() {
+        let mut data: Vec<_> = (0..4).map(token).collect();
+
+        // The edits are all relative to the tokens before any of them.
+        assert!(apply_semantic_tokens_edits(
+            &mut data,
+            vec![edit(0, 5, &[10]), edit(15, 5, &[]), edit(20, 0, &[11, 12])],
+        ));
+        assert_eq!(data, [10, 1, 2, 11, 12].map(token));
+
+        assert!(apply_semantic_tokens_edits(&mut data, Vec::new()));
+        assert_eq!(data, [10, 1, 2, 11, 12].map(token));
+
+        // Edits out of bounds or not on token boundaries leave the tokens as
+        // they were.
+        for edits in [
+            vec![edit(0, 30, &[])],
+            vec![edit(3, 5, &[])],
+            vec![edit(0, 5, &[1]), edit(5, 4, &[])],
+        ] {
+            assert!(!apply_semantic_tokens_edits(&mut data, edits));
+            assert_eq!(data, [10, 1, 2, 11, 12].map(token));
+        }
     }
 }
@@ -15,7 +15,7 @@
 use jsonrpc_lite::{Id, Params};
 use lapce_core::directory::Directory;
 use lapce_rpc::{
-    plugin::{PluginId, VoltID, VoltInfo, VoltMetadata},
+    plugin::{PluginEvent, PluginId, VoltID, VoltInfo, VoltMetadata},
     style::LineStyle,
     RpcError,
 };
@@ -24,7 +24,7 @@
     notification::Initialized, request::Initialize, DocumentFilter,
     InitializeParams, InitializedParams, TextDocumentContentChangeEvent,
     TextDocumentIdentifier, Url, VersionedTextDocumentIdentifier,
-    WorkDoneProgressParams, WorkspaceFolder,
+    WorkDoneProgressParams,
 };
 use parking_lot::Mutex;
 use psp_types::{Notification, Request};
@@ -191,13 +191,23 @@ fn format_semantic_tokens(
     ) {
         self.host.format_semantic_tokens(tokens, text, f);
     }
+
+    fn handle_plugin_event(&self, event: PluginEvent) {
+        self.host.handle_plugin_event(event);
+    }
+
+    fn pull_diagnostics(&self, uri: Url) {
+        self.host.pull_diagnostics(uri);
+    }
 }
 
 impl Plugin {
     fn initialize(&mut self) {
         let workspace = self.host.workspace.clone();
         let configurations = self.configurations.as_ref().map(unflatten_map);
-        let root_uri = workspace.map(|p| Url::from_directory_path(p).unwrap());
+        let root_uri = workspace
+            .as_ref()
+            .map(|p| Url::from_directory_path(p).unwrap());
         let server_rpc = self.host.server_rpc.clone();
         self.host.server_rpc.server_request_async(
             Initialize::METHOD,
@@ -205,18 +215,16 @@ fn initialize(&mut self) {
             InitializeParams {
                 process_id: Some(process::id()),
                 root_path: None,
-                root_uri: root_uri.clone(),
-                capabilities: client_capabilities(),
+                root_uri,
+                capabilities: client_capabilities(false),
                 trace: None,
                 client_info: None,
                 locale: None,
                 initialization_options: configurations,
-                workspace_folders: root_uri.map(|uri| {
-                    vec![WorkspaceFolder {
-                        name: uri.as_str().to_string(),
-                        uri,
-                    }]
-                }),
+                workspace_folders: self
+                    .host
+                    .catalog_rpc
+                    .lsp_workspace_folders(workspace.as_deref()),
                 work_done_progress_params: WorkDoneProgressParams::default(),
             },
             None,
@@ -362,6 +370,7 @@ pub fn find_all_volts(extra_plugin_paths: &[PathBuf]) -> Vec<VoltMetadata> {
 ///         wasm: None,
 ///         color_themes: None,
 ///         icon_themes: None,
+///         file_templates: None,
 ///         dir: parent_path.canonicalize().ok(),
 ///         activation: None,
 ///         config: None
@@ -400,6 +409,14 @@ pub fn load_volt(path: &Path) -> Result<VoltMetadata> {
             })
             .collect()
     });
+    meta.file_templates = meta.file_templates.as_ref().map(|templates| {
+        templates
+            .iter()
+            .filter_map(|file| {
+                Some(path.join(file).canonicalize().ok()?.to_str()?.to_string())
+            })
+            .collect()
+    });
 
     Ok(meta)
 }
@@ -518,9 +535,15 @@ pub fn start_volt(
         }
     })?;
     let plugin_meta = meta.clone();
+    let core_rpc = plugin_rpc.core_rpc.clone();
     linker.func_wrap("lapce", "host_handle_stderr", move || {
         if let Ok(msg) = wasi_read_string(&stderr) {
             tracing_log::log::log!(target: &format!("lapce_proxy::plugin::wasi::{}::{}", plugin_meta.author, plugin_meta.name), tracing_log::log::Level::Debug, "{msg}");
+            core_rpc.language_server_output(
+                plugin_meta.id(),
+                plugin_meta.display_name.clone(),
+                msg.trim_end().to_string(),
+            );
         }
     })?;
     linker.module(&mut store, "", &module)?;
@@ -143,6 +143,7 @@ fn test_load_volt() {
             wasm: wasm_path,
             color_themes: Some(color_themes_pathes),
             icon_themes: Some(icon_themes_pathes),
+            file_templates: None,
             dir: parent_path.canonicalize().ok(),
             activation: None,
             config: None
@@ -203,6 +204,7 @@ fn test_load_volt() {
             wasm: wasm_path,
             color_themes: Some(color_themes_pathes),
             icon_themes: Some(icon_themes_pathes),
+            file_templates: None,
             dir: parent_path.canonicalize().ok(),
             activation: None,
             config: None
@@ -229,6 +231,7 @@ fn test_load_volt() {
             wasm: None,
             color_themes: Some(Vec::new()),
             icon_themes: Some(Vec::new()),
+            file_templates: None,
             dir: parent_path.canonicalize().ok(),
             activation: None,
             config: None
@@ -0,0 +1,173 @@
+//! Keeping a remote proxy running after its connection to the UI is lost, so
+//! the terminals it runs survive e.g. a dropped SSH connection.
+//!
+//! The proxy then listens on a socket named after the session id the UI gave
+//! it, and a later `lapce --proxy --session <id> --reattach` relays its stdio
+//! to that socket, which becomes the proxy's new connection to the UI.
+
+use std::{
+    collections::HashSet,
+    fs::{DirBuilder, Permissions},
+    io::{self, BufReader, Write},
+    net::Shutdown,
+    os::unix::{
+        fs::{DirBuilderExt, PermissionsExt},
+        net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use crossbeam_channel::Sender;
+use lapce_core::directory::Directory;
+use lapce_rpc::{
+    core::CoreRpcHandler,
+    proxy::{ProxyMessage, ProxyRpcHandler},
+    stdio::{reader_transport, ReattachableWriter, MAX_BACKLOG_SIZE},
+    terminal::TermId,
+};
+use lsp_types::{MessageType, ShowMessageParams};
+use parking_lot::Mutex;
+
+use crate::REATTACHED_MESSAGE;
+
+/// How long the proxy keeps running without a connection before giving up
+/// on the UI coming back.
+const DETACHED_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+fn socket_path(session: &str) -> Result<PathBuf> {
+    let dir = Directory::proxy_directory()
+        .ok_or_else(|| anyhow!("can't get proxy directory"))?
+        .join("sessions");
+    if !dir.exists() {
+        DirBuilder::new().recursive(true).mode(0o700).create(&dir)?;
+    }
+    Ok(dir.join(format!("{session}.sock")))
+}
+
+/// Relay the stdio of this process to the proxy kept alive for `session`,
+/// until either side is closed.
+pub fn relay(session: &str) -> Result<()> {
+    let stream = UnixStream::connect(socket_path(session)?)?;
+    {
+        let mut stdout = io::stdout();
+        writeln!(stdout, "{REATTACHED_MESSAGE}")?;
+        stdout.flush()?;
+    }
+    let mut socket_reader = stream.try_clone()?;
+    let mut socket_writer = stream.try_clone()?;
+    thread::spawn(move || {
+        let _ = io::copy(&mut io::stdin(), &mut socket_writer);
+        let _ = socket_writer.shutdown(Shutdown::Both);
+    });
+    io::copy(&mut socket_reader, &mut io::stdout())?;
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(())
+}
+
+/// Keep the proxy running after the connection to the UI was lost, attaching
+/// `writer` and `reader_tx` to whatever connects to the session's socket.
+///
+/// While detached, the proxy is shut down once no terminal is left running,
+/// or after [`DETACHED_TIMEOUT`].
+pub fn keep_alive(
+    session: &str,
+    writer: Arc<ReattachableWriter>,
+    reader_tx: Sender<ProxyMessage>,
+    live_terminals: Arc<Mutex<HashSet<TermId>>>,
+    proxy_rpc: ProxyRpcHandler,
+    core_rpc: CoreRpcHandler,
+) -> Result<()> {
+    writer.detach();
+
+    let path = socket_path(session)?;
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    // Whoever connects takes over the proxy, so only the user may
+    std::fs::set_permissions(&path, Permissions::from_mode(0o600))?;
+
+    // The session has no terminal to hang up anymore, and the shells in the
+    // terminals run in their own sessions.
+    unsafe {
+        libc::signal(libc::SIGHUP, libc::SIG_IGN);
+    }
+
+    let detached_since = Arc::new(Mutex::new(Some(Instant::now())));
+    // Each connection bumps the generation, so a connection closing after it
+    // was replaced doesn't detach the new one.
+    let generation = Arc::new(AtomicU64::new(0));
+
+    {
+        let detached_since = detached_since.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let Ok(write_stream) = stream.try_clone() else {
+                    continue;
+                };
+                match writer.attach(write_stream) {
+                    Ok(0) => {}
+                    Ok(dropped) => {
+                        core_rpc.show_message(
+                            "Remote Proxy".to_string(),
+                            ShowMessageParams {
+                                typ: MessageType::WARNING,
+                                message: format!(
+                                    "{dropped} messages from the proxy were lost \
+                                     while disconnected, as more than {} MB of \
+                                     them were waiting to be sent. The output \
+                                     of the terminals may be incomplete.",
+                                    MAX_BACKLOG_SIZE / 1024 / 1024
+                                ),
+                            },
+                        );
+                    }
+                    Err(err) => {
+                        tracing::error!("{:?}", err);
+                        continue;
+                    }
+                }
+                *detached_since.lock() = None;
+
+                let current = generation.fetch_add(1, Ordering::SeqCst) + 1;
+                let writer = writer.clone();
+                let detached_since = detached_since.clone();
+                let generation = generation.clone();
+                reader_transport(
+                    BufReader::new(stream),
+                    reader_tx.clone(),
+                    move || {
+                        if generation.load(Ordering::SeqCst) == current {
+                            writer.detach();
+                            *detached_since.lock() = Some(Instant::now());
+                        }
+                    },
+                );
+            }
+        });
+    }
+
+    let session = session.to_string();
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(5));
+        let Some(since) = *detached_since.lock() else {
+            continue;
+        };
+        if since.elapsed() > DETACHED_TIMEOUT || live_terminals.lock().is_empty() {
+            if let Ok(path) = socket_path(&session) {
+                let _ = std::fs::remove_file(path);
+            }
+            proxy_rpc.shutdown();
+            return;
+        }
+    });
+
+    Ok(())
+}
@@ -0,0 +1,640 @@
+//! Running the tasks of the workspace, listed in `.lapce/tasks.toml` or
+//! detected from its build files, and turning the problems they print into
+//! diagnostics with problem matchers.
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+use lapce_rpc::{
+    core::CoreRpcHandler,
+    task::{
+        ProblemMatcher, ProblemPattern, TaskConfig, TaskConfigs, TaskId,
+        TaskOutputLink, TaskSource,
+    },
+};
+use lsp_types::{Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range};
+use parking_lot::Mutex;
+use regex::Regex;
+
+pub const TASKS_TOML: &str = "tasks.toml";
+
+/// The processes of the tasks still running.
+pub type RunningTasks = Arc<Mutex<HashMap<TaskId, Child>>>;
+
+/// The tasks of the `.lapce/tasks.toml` file of the workspace, followed by
+/// those detected from its `Cargo.toml` and `package.json`. A task is known by
+/// its name, so the detected tasks named as one of the file are left out.
+pub fn workspace_tasks(workspace: &Path) -> Result<Vec<TaskConfig>> {
+    let mut tasks: Vec<TaskConfig> = Vec::new();
+    let path = workspace.join(".lapce").join(TASKS_TOML);
+    if let Ok(content) = std::fs::read_to_string(&path) {
+        let configs: TaskConfigs = toml::from_str(&content)
+            .map_err(|e| anyhow!("can't parse {}: {e}", path.display()))?;
+        for task in configs.tasks {
+            if tasks.iter().any(|t| t.name == task.name) {
+                return Err(anyhow!(
+                    "{} has more than one task named {}",
+                    path.display(),
+                    task.name
+                ));
+            }
+            tasks.push(task);
+        }
+    }
+    for task in detected_tasks(workspace) {
+        if !tasks.iter().any(|t| t.name == task.name) {
+            tasks.push(task);
+        }
+    }
+    Ok(tasks)
+}
+
+fn detected_tasks(workspace: &Path) -> Vec<TaskConfig> {
+    let mut tasks = Vec::new();
+    if workspace.join("Cargo.toml").exists() {
+        for subcommand in ["build", "test", "run"] {
+            tasks.push(TaskConfig {
+                name: format!("cargo {subcommand}"),
+                command: "cargo".to_string(),
+                args: vec![subcommand.to_string()],
+                cwd: None,
+                env: None,
+                problem_matcher: Some(ProblemMatcher::Builtin("rustc".to_string())),
+                source: TaskSource::Cargo,
+            });
+        }
+    }
+
+    let scripts = std::fs::read_to_string(workspace.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|package| package.get("scripts")?.as_object().cloned());
+    for (script, _) in scripts.into_iter().flatten() {
+        tasks.push(TaskConfig {
+            name: format!("npm: {script}"),
+            // npm is a batch script on Windows, which isn't found without its
+            // extension.
+            command: if cfg!(windows) { "npm.cmd" } else { "npm" }.to_string(),
+            args: vec!["run".to_string(), script],
+            cwd: None,
+            env: None,
+            problem_matcher: None,
+            source: TaskSource::Npm,
+        });
+    }
+    tasks
+}
+
+/// Start the process of the task, sending the lines it prints, the problems
+/// found in them and its exit code to the UI.
+pub fn run_task(
+    task_id: TaskId,
+    task: TaskConfig,
+    workspace: Option<&Path>,
+    running: RunningTasks,
+    core_rpc: CoreRpcHandler,
+) -> Result<()> {
+    let workspace_dir = workspace
+        .map(|workspace| workspace.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let cwd = task_cwd(&task, workspace);
+    let matcher = task
+        .problem_matcher
+        .as_ref()
+        .map(|matcher| LineMatcher::new(matcher, &task.name, &cwd))
+        .transpose()?;
+
+    let mut process = Command::new(&task.command);
+    process
+        .args(
+            task.args
+                .iter()
+                .map(|arg| arg.replace("${workspace}", &workspace_dir)),
+        )
+        .current_dir(&cwd)
+        .envs(task.env.iter().flatten())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // In a process group of its own, to stop the programs the task starts
+    // with it
+    #[cfg(unix)]
+    process.process_group(0);
+    #[cfg(target_os = "windows")]
+    process.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    let mut child = process
+        .spawn()
+        .map_err(|e| anyhow!("can't run {}: {e}", task.command))?;
+
+    let next_line = Arc::new(AtomicUsize::new(0));
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|reader| {
+        let core_rpc = core_rpc.clone();
+        let next_line = next_line.clone();
+        // Each stream with a matcher of its own, since a problem spanning
+        // several lines is printed on one of them
+        let mut matcher = matcher.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut buf = Vec::new();
+            while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+                if n == 0 {
+                    break;
+                }
+                let line = String::from_utf8_lossy(&buf)
+                    .trim_end_matches(['\r', '\n'])
+                    .to_string();
+                buf.clear();
+                // The lines of both streams are numbered in the order they're
+                // read, for the problems to link to them
+                let id = next_line.fetch_add(1, Ordering::Relaxed);
+                if let Some((path, mut diagnostic, lines)) =
+                    matcher.as_mut().and_then(|m| m.match_line(id, &line))
+                {
+                    TaskOutputLink { task_id, lines }.set(&mut diagnostic);
+                    core_rpc.task_diagnostic(task_id, path, diagnostic);
+                }
+                core_rpc.task_output(task_id, id, line);
+            }
+        })
+    })
+    .collect::<Vec<_>>();
+    running.lock().insert(task_id, child);
+
+    thread::spawn(move || {
+        for reader in readers {
+            let _ = reader.join();
+        }
+        // The output is closed once the process exits
+        let child = running.lock().remove(&task_id);
+        let exit_code = child
+            .and_then(|mut child| child.wait().ok())
+            .and_then(|status| status.code());
+        core_rpc.task_exited(task_id, exit_code, None);
+    });
+    Ok(())
+}
+
+/// The directory the task runs in, its `cwd` relative to the workspace, or
+/// the workspace itself.
+fn task_cwd(task: &TaskConfig, workspace: Option<&Path>) -> PathBuf {
+    let workspace_dir = workspace
+        .map(|workspace| workspace.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let workspace = workspace.unwrap_or(Path::new("."));
+    match task.cwd.as_ref() {
+        Some(cwd) => workspace.join(cwd.replace("${workspace}", &workspace_dir)),
+        None => workspace.to_path_buf(),
+    }
+}
+
+/// Stop the task, with the programs it started.
+pub fn stop_task(task_id: TaskId, running: &RunningTasks) {
+    let mut running = running.lock();
+    let Some(child) = running.get_mut(&task_id) else {
+        return;
+    };
+    #[cfg(unix)]
+    {
+        // The process group of the task has the id of its process
+        unsafe {
+            libc::kill(-(child.id() as i32), libc::SIGTERM);
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        // Without process groups to signal, the tree of processes of the task
+        // is ended by `taskkill`
+        let killed = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &child.id().to_string()])
+            .creation_flags(0x08000000) // CREATE_NO_WINDOW
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success());
+        if !killed {
+            let _ = child.kill();
+        }
+    }
+    #[cfg(not(any(unix, target_os = "windows")))]
+    {
+        let _ = child.kill();
+    }
+}
+
+/// Finds the problems in the lines printed by a task.
+#[derive(Clone)]
+pub struct LineMatcher {
+    kind: MatcherKind,
+    /// The name of the task, as the source of the diagnostics.
+    source: String,
+    /// The directory the paths printed by the task are relative to.
+    cwd: PathBuf,
+    /// The severity, code and message of a `rustc` problem whose location is
+    /// on the next lines, with the id of its first line.
+    pending: Option<(DiagnosticSeverity, Option<String>, String, usize)>,
+}
+
+#[derive(Clone)]
+enum MatcherKind {
+    Rustc { header: Regex, location: Regex },
+    Pattern(Regex, ProblemPattern),
+}
+
+impl LineMatcher {
+    pub fn new(matcher: &ProblemMatcher, source: &str, cwd: &Path) -> Result<Self> {
+        let kind = match matcher {
+            ProblemMatcher::Builtin(name) => match name.as_str() {
+                "rustc" => MatcherKind::Rustc {
+                    header: Regex::new(r"^(error|warning)(?:\[(\w+)\])?: (.*)$")
+                        .unwrap(),
+                    location: Regex::new(r"^\s*--> (.+):(\d+):(\d+)$").unwrap(),
+                },
+                "gcc" => builtin_pattern(
+                    r"^(.+?):(\d+):(\d+): (?:fatal )?(error|warning|note): (.*)$",
+                ),
+                "tsc" => builtin_pattern(
+                    r"^(.+?)\((\d+),(\d+)\): (error|warning|info) (.*)$",
+                ),
+                _ => return Err(anyhow!("unknown problem matcher {name}")),
+            },
+            ProblemMatcher::Pattern(pattern) => MatcherKind::Pattern(
+                Regex::new(&pattern.regexp)
+                    .map_err(|e| anyhow!("invalid problem matcher regexp: {e}"))?,
+                pattern.clone(),
+            ),
+        };
+        Ok(Self {
+            kind,
+            source: source.to_string(),
+            cwd: cwd.to_path_buf(),
+            pending: None,
+        })
+    }
+
+    /// The file and the diagnostic of the problem ending with `line`, with the
+    /// ids of the lines it's printed on, `id` being the one of `line`.
+    pub fn match_line(
+        &mut self,
+        id: usize,
+        line: &str,
+    ) -> Option<(PathBuf, Diagnostic, std::ops::Range<usize>)> {
+        match &self.kind {
+            // `rustc` prints the message of a problem, as `error[E0308]:
+            // mismatched types`, with its location on one of the next lines,
+            // as `  --> src/main.rs:2:5`
+            MatcherKind::Rustc { header, location } => {
+                if let Some(captures) = header.captures(line) {
+                    self.pending = Some((
+                        severity_of(&captures[1]),
+                        captures.get(2).map(|code| code.as_str().to_string()),
+                        captures[3].to_string(),
+                        id,
+                    ));
+                    return None;
+                }
+                let captures = location.captures(line)?;
+                let (severity, code, message, start) = self.pending.take()?;
+                let (path, diagnostic) = self.diagnostic(
+                    &captures[1],
+                    captures[2].parse().ok()?,
+                    captures[3].parse().ok()?,
+                    severity,
+                    code,
+                    message,
+                );
+                Some((path, diagnostic, start..id + 1))
+            }
+            MatcherKind::Pattern(regex, pattern) => {
+                let captures = regex.captures(line)?;
+                let group = |i: usize| captures.get(i).map(|m| m.as_str());
+                let file = group(pattern.file)?;
+                let line = group(pattern.line)?.parse().ok()?;
+                let column = pattern
+                    .column
+                    .and_then(group)
+                    .and_then(|column| column.parse().ok())
+                    .unwrap_or(1);
+                let severity = pattern
+                    .severity
+                    .and_then(group)
+                    .map(severity_of)
+                    .unwrap_or(DiagnosticSeverity::ERROR);
+                let message = group(pattern.message)?.to_string();
+                let (path, diagnostic) =
+                    self.diagnostic(file, line, column, severity, None, message);
+                Some((path, diagnostic, id..id + 1))
+            }
+        }
+    }
+
+    /// The diagnostic at the 1-based `line` and `column` of `file`.
+    fn diagnostic(
+        &self,
+        file: &str,
+        line: u32,
+        column: u32,
+        severity: DiagnosticSeverity,
+        code: Option<String>,
+        message: String,
+    ) -> (PathBuf, Diagnostic) {
+        let position =
+            Position::new(line.saturating_sub(1), column.saturating_sub(1));
+        let diagnostic = Diagnostic {
+            range: Range::new(position, position),
+            severity: Some(severity),
+            code: code.map(NumberOrString::String),
+            source: Some(self.source.clone()),
+            message,
+            ..Default::default()
+        };
+        (self.cwd.join(file), diagnostic)
+    }
+}
+
+fn builtin_pattern(regexp: &str) -> MatcherKind {
+    MatcherKind::Pattern(
+        Regex::new(regexp).unwrap(),
+        ProblemPattern {
+            regexp: regexp.to_string(),
+            file: 1,
+            line: 2,
+            column: Some(3),
+            severity: Some(4),
+            message: 5,
+        },
+    )
+}
+
+fn severity_of(severity: &str) -> DiagnosticSeverity {
+    match severity.to_lowercase().as_str() {
+        "warning" | "warn" => DiagnosticSeverity::WARNING,
+        "note" | "info" | "information" => DiagnosticSeverity::INFORMATION,
+        "hint" => DiagnosticSeverity::HINT,
+        _ => DiagnosticSeverity::ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use lapce_rpc::task::{ProblemMatcher, ProblemPattern, TaskConfig, TaskSource};
+    use lsp_types::{DiagnosticSeverity, NumberOrString, Position};
+
+    use super::{detected_tasks, task_cwd, workspace_tasks, LineMatcher};
+
+    fn task(cwd: Option<&str>) -> TaskConfig {
+        TaskConfig {
+            name: "build".to_string(),
+            command: "make".to_string(),
+            args: Vec::new(),
+            cwd: cwd.map(str::to_string),
+            env: None,
+            problem_matcher: None,
+            source: TaskSource::Config,
+        }
+    }
+
+    #[test]
+    fn test_task_cwd() {
+        let workspace = Some(Path::new("/ws"));
+        assert_eq!(task_cwd(&task(None), workspace), PathBuf::from("/ws"));
+        assert_eq!(
+            task_cwd(&task(Some("crates/app")), workspace),
+            PathBuf::from("/ws/crates/app")
+        );
+        assert_eq!(
+            task_cwd(&task(Some("${workspace}/app")), workspace),
+            PathBuf::from("/ws/app")
+        );
+        assert_eq!(
+            task_cwd(&task(Some("/tmp/out")), workspace),
+            PathBuf::from("/tmp/out")
+        );
+        assert_eq!(task_cwd(&task(None), None), PathBuf::from("."));
+    }
+
+    #[test]
+    fn test_workspace_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        fs::create_dir_all(dir.join(".lapce")).unwrap();
+
+        assert!(workspace_tasks(dir).unwrap().is_empty());
+
+        fs::write(dir.join("Cargo.toml"), "[package]").unwrap();
+        fs::write(
+            dir.join("package.json"),
+            r#"{ "scripts": { "lint": "eslint .", "test": "jest" } }"#,
+        )
+        .unwrap();
+        let detected = detected_tasks(dir);
+        let names: Vec<&str> =
+            detected.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "cargo build",
+                "cargo test",
+                "cargo run",
+                "npm: lint",
+                "npm: test"
+            ]
+        );
+        assert_eq!(detected[0].args, ["build"]);
+        assert_eq!(detected[0].source, TaskSource::Cargo);
+        assert_eq!(
+            detected[0].problem_matcher,
+            Some(ProblemMatcher::Builtin("rustc".to_string()))
+        );
+        assert_eq!(detected[4].args, ["run", "test"]);
+        assert_eq!(detected[4].source, TaskSource::Npm);
+
+        fs::write(
+            dir.join(".lapce").join("tasks.toml"),
+            r#"
+[[tasks]]
+name = "lint"
+command = "shellcheck"
+args = ["run.sh"]
+cwd = "scripts"
+env = { LANG = "C" }
+
+[tasks.problem-matcher]
+regexp = '^(.+) line (\d+): (.*)$'
+file = 1
+line = 2
+message = 3
+
+[[tasks]]
+name = "check"
+command = "cargo"
+problem-matcher = "rustc"
+"#,
+        )
+        .unwrap();
+        let tasks = workspace_tasks(dir).unwrap();
+        assert_eq!(tasks.len(), 7);
+        assert_eq!(tasks[0].name, "lint");
+        assert_eq!(tasks[0].args, ["run.sh"]);
+        assert_eq!(tasks[0].cwd.as_deref(), Some("scripts"));
+        assert_eq!(
+            tasks[0].env.as_ref().and_then(|env| env.get("LANG")),
+            Some(&"C".to_string())
+        );
+        assert_eq!(tasks[0].source, TaskSource::Config);
+        assert!(matches!(
+            &tasks[0].problem_matcher,
+            Some(ProblemMatcher::Pattern(ProblemPattern {
+                file: 1,
+                line: 2,
+                column: None,
+                ..
+            }))
+        ));
+        assert_eq!(
+            tasks[1].problem_matcher,
+            Some(ProblemMatcher::Builtin("rustc".to_string()))
+        );
+        assert_eq!(tasks[2].name, "cargo build");
+
+        // A task of the file replaces the detected one of the same name
+        fs::write(
+            dir.join(".lapce").join("tasks.toml"),
+            "[[tasks]]\nname = \"cargo test\"\ncommand = \"cargo\"\nargs = [\"nextest\"]",
+        )
+        .unwrap();
+        let tasks = workspace_tasks(dir).unwrap();
+        assert_eq!(tasks.len(), 5);
+        assert_eq!(tasks[0].args, ["nextest"]);
+        assert_eq!(
+            tasks
+                .iter()
+                .filter(|task| task.name == "cargo test")
+                .count(),
+            1
+        );
+
+        fs::write(
+            dir.join(".lapce").join("tasks.toml"),
+            "[[tasks]]\nname = \"a\"\ncommand = \"a\"\n[[tasks]]\nname = \"a\"\ncommand = \"b\"",
+        )
+        .unwrap();
+        assert!(workspace_tasks(dir).is_err());
+
+        fs::write(dir.join(".lapce").join("tasks.toml"), "[[tasks]]\nname = 1")
+            .unwrap();
+        assert!(workspace_tasks(dir).is_err());
+    }
+
+    #[test]
+    fn test_rustc_matcher() {
+        let mut matcher = LineMatcher::new(
+            &ProblemMatcher::Builtin("rustc".to_string()),
+            "cargo build",
+            Path::new("/ws"),
+        )
+        .unwrap();
+        assert!(matcher
+            .match_line(0, "error[E0308]: mismatched types")
+            .is_none());
+        let (path, diagnostic, lines) =
+            matcher.match_line(1, "  --> src/main.rs:2:18").unwrap();
+        assert_eq!(lines, 0..2);
+        assert_eq!(path, PathBuf::from("/ws/src/main.rs"));
+        assert_eq!(diagnostic.range.start, Position::new(1, 17));
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(
+            diagnostic.code,
+            Some(NumberOrString::String("E0308".to_string()))
+        );
+        assert_eq!(diagnostic.message, "mismatched types");
+        assert_eq!(diagnostic.source.as_deref(), Some("cargo build"));
+
+        // The location of a problem already matched
+        assert!(matcher.match_line(2, "  --> src/main.rs:3:1").is_none());
+
+        // With the lines of the other stream of the task in between
+        matcher.match_line(3, "warning: unused variable: `a`");
+        let (_, diagnostic, lines) =
+            matcher.match_line(6, " --> src/lib.rs:10:9").unwrap();
+        assert_eq!(lines, 3..7);
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostic.code, None);
+    }
+
+    #[test]
+    fn test_pattern_matcher() {
+        let mut gcc = LineMatcher::new(
+            &ProblemMatcher::Builtin("gcc".to_string()),
+            "make",
+            Path::new("/ws"),
+        )
+        .unwrap();
+        let (path, diagnostic, lines) = gcc
+            .match_line(4, "main.c:4:12: warning: unused variable 'x'")
+            .unwrap();
+        assert_eq!(lines, 4..5);
+        assert_eq!(path, PathBuf::from("/ws/main.c"));
+        assert_eq!(diagnostic.range.start, Position::new(3, 11));
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostic.message, "unused variable 'x'");
+        assert!(gcc.match_line(5, "make: *** [all] Error 1").is_none());
+
+        let mut custom = LineMatcher::new(
+            &ProblemMatcher::Pattern(ProblemPattern {
+                regexp: r"^(.+) line (\d+): (.*)$".to_string(),
+                file: 1,
+                line: 2,
+                column: None,
+                severity: None,
+                message: 3,
+            }),
+            "lint",
+            Path::new("/ws"),
+        )
+        .unwrap();
+        let (path, diagnostic, _) = custom
+            .match_line(0, "/abs/file.sh line 7: missing quote")
+            .unwrap();
+        assert_eq!(path, PathBuf::from("/abs/file.sh"));
+        assert_eq!(diagnostic.range.start, Position::new(6, 0));
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::ERROR));
+
+        assert!(LineMatcher::new(
+            &ProblemMatcher::Builtin("unknown".to_string()),
+            "lint",
+            Path::new("/ws"),
+        )
+        .is_err());
+    }
+}
@@ -1,11 +1,14 @@
 use std::{
     borrow::Cow,
-    collections::VecDeque,
+    collections::{BTreeMap, VecDeque},
     io::{self, ErrorKind, Read, Write},
     num::NonZeroUsize,
     path::PathBuf,
-    sync::Arc,
-    time::Duration,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 use alacritty_terminal::{
@@ -18,12 +21,20 @@
 use directories::BaseDirs;
 use lapce_rpc::{
     core::CoreRpcHandler,
-    terminal::{TermId, TerminalProfile},
+    terminal::{
+        TermId, TerminalProcessEnvironment, TerminalProcessUsage, TerminalProfile,
+    },
 };
 use polling::PollMode;
 
 const READ_BUFFER_SIZE: usize = 0x10_0000;
 
+/// How often the usage of the foreground process is sampled, when enabled.
+const USAGE_SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long the event loop waits for an event otherwise.
+const POLL_TIMEOUT: Duration = Duration::from_secs(6);
+
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 const PTY_READ_WRITE_TOKEN: usize = 0;
 #[cfg(any(target_os = "linux", target_os = "macos"))]
@@ -56,10 +67,27 @@ pub fn send(&self, msg: Msg) {
 
 pub struct Terminal {
     term_id: TermId,
+    cwd: Option<PathBuf>,
+    /// Whether the shell reports its working directory through OSC 7, which
+    /// is then trusted over the one of the process.
+    reports_cwd: bool,
+    /// The end of the last output, which may be the start of an OSC 7
+    /// sequence finished by the next one.
+    osc7_tail: Vec<u8>,
+    /// When the working directory of the process was last read.
+    cwd_checked: Option<Instant>,
     pub(crate) poller: Arc<polling::Poller>,
     pub(crate) pty: alacritty_terminal::tty::Pty,
     rx: Receiver<Msg>,
     pub tx: Sender<Msg>,
+    /// Whether to report the usage of the foreground process.
+    pub(crate) sample_usage: Arc<AtomicBool>,
+    usage: Option<TerminalProcessUsage>,
+    /// The variables the shell was spawned with.
+    pub(crate) environment: Vec<(String, String)>,
+    /// The pid, the CPU time in clock ticks and the time of the last sample,
+    /// to compute the CPU usage from.
+    last_usage_sample: Option<(u32, u64, Instant)>,
 }
 
 impl Terminal {
@@ -83,6 +111,17 @@ pub fn new(
         #[cfg(target_os = "macos")]
         set_locale_environment();
 
+        // The shell inherits the environment of the proxy and the type of
+        // the terminal the pty sets, on top of which the variables of the
+        // profile are set.
+        let mut environment: BTreeMap<String, String> = std::env::vars().collect();
+        environment
+            .entry("TERM".to_string())
+            .or_insert_with(|| "xterm-256color".to_string());
+        environment.insert("COLORTERM".to_string(), "truecolor".to_string());
+        environment.extend(options.env.clone());
+        let environment: Vec<(String, String)> = environment.into_iter().collect();
+
         let size = WindowSize {
             num_lines: height as u16,
             num_cols: width as u16,
@@ -95,14 +134,24 @@ pub fn new(
 
         Ok(Terminal {
             term_id,
+            cwd: None,
+            reports_cwd: false,
+            osc7_tail: Vec::new(),
+            cwd_checked: None,
             poller: poll,
             pty,
             tx,
             rx,
+            sample_usage: Arc::new(AtomicBool::new(false)),
+            usage: None,
+            environment,
+            last_usage_sample: None,
         })
     }
 
-    pub fn run(&mut self, core_rpc: CoreRpcHandler) {
+    /// Run the event loop of the terminal until its process exits, returning
+    /// the exit code.
+    pub fn run(&mut self, core_rpc: CoreRpcHandler) -> Option<i32> {
         let mut state = State::default();
         let mut buf = [0u8; READ_BUFFER_SIZE];
 
@@ -119,11 +168,18 @@ pub fn run(&mut self, core_rpc: CoreRpcHandler) {
         let mut events =
             polling::Events::with_capacity(NonZeroUsize::new(1024).unwrap());
 
-        let timeout = Some(Duration::from_secs(6));
+        let mut last_usage_update = Instant::now();
         let mut exit_code = None;
         'event_loop: loop {
             events.clear();
-            if let Err(err) = self.poller.wait(&mut events, timeout) {
+            // Only wake up to sample the usage when it's enabled, which is
+            // picked up after the next event or timeout.
+            let timeout = if self.samples_usage() {
+                USAGE_SAMPLE_INTERVAL
+            } else {
+                POLL_TIMEOUT
+            };
+            if let Err(err) = self.poller.wait(&mut events, Some(timeout)) {
                 match err.kind() {
                     ErrorKind::Interrupted => continue,
                     _ => panic!("EventLoop polling error: {err:?}"),
@@ -189,6 +245,11 @@ pub fn run(&mut self, core_rpc: CoreRpcHandler) {
                 }
             }
 
+            if last_usage_update.elapsed() >= USAGE_SAMPLE_INTERVAL {
+                last_usage_update = Instant::now();
+                self.update_usage(&core_rpc);
+            }
+
             // Register write interest if necessary.
             let needs_write = state.needs_write();
             if needs_write != interest.writable {
@@ -204,6 +265,7 @@ pub fn run(&mut self, core_rpc: CoreRpcHandler) {
         if let Err(err) = self.pty.deregister(&self.poller) {
             tracing::error!("{:?}", err);
         }
+        exit_code
     }
 
     /// Drain the channel.
@@ -232,6 +294,7 @@ fn pty_read(
                 Ok(0) => break,
                 Ok(n) => {
                     core_rpc.update_terminal(self.term_id, buf[..n].to_vec());
+                    self.update_cwd(core_rpc, &buf[..n]);
                 }
                 Err(err) => match err.kind() {
                     ErrorKind::Interrupted | ErrorKind::WouldBlock => {
@@ -244,6 +307,131 @@ fn pty_read(
         Ok(())
     }
 
+    /// Track the working directory of the shell, preferring the path reported
+    /// by the shell through OSC 7 and falling back to the process itself
+    /// while the shell has never reported it.
+    fn update_cwd(&mut self, core_rpc: &CoreRpcHandler, content: &[u8]) {
+        let mut output = std::mem::take(&mut self.osc7_tail);
+        output.extend_from_slice(content);
+        self.osc7_tail = osc7_tail(&output).to_vec();
+
+        let cwd = match osc7_cwd(&output) {
+            Some(cwd) => {
+                self.reports_cwd = true;
+                Some(cwd)
+            }
+            None if self.reports_cwd => None,
+            // Reading the directory of the process is throttled, as there may
+            // be output many times a second.
+            None if self
+                .cwd_checked
+                .is_some_and(|checked| checked.elapsed() < CWD_CHECK_INTERVAL) =>
+            {
+                None
+            }
+            None => {
+                self.cwd_checked = Some(Instant::now());
+                self.process_cwd()
+            }
+        };
+        let Some(cwd) = cwd else {
+            return;
+        };
+        if self.cwd.as_ref() != Some(&cwd) {
+            self.cwd = Some(cwd.clone());
+            core_rpc.terminal_cwd(self.term_id, cwd);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn process_cwd(&self) -> Option<PathBuf> {
+        std::fs::read_link(format!("/proc/{}/cwd", self.pty.child().id())).ok()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn process_cwd(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Whether the usage of the foreground process is sampled, which is only
+    /// supported on Linux.
+    fn samples_usage(&self) -> bool {
+        cfg!(target_os = "linux") && self.sample_usage.load(Ordering::Relaxed)
+    }
+
+    /// Sample the usage of the foreground process if enabled, reporting it
+    /// when it changed.
+    fn update_usage(&mut self, core_rpc: &CoreRpcHandler) {
+        let usage = if self.samples_usage() {
+            self.process_usage()
+        } else {
+            self.last_usage_sample = None;
+            None
+        };
+        if usage != self.usage {
+            self.usage = usage.clone();
+            core_rpc.terminal_process_usage(self.term_id, usage);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn process_usage(&mut self) -> Option<TerminalProcessUsage> {
+        use std::os::fd::AsRawFd;
+
+        // The foreground process group of the pty is the job the shell waits
+        // on, or the shell itself while it's at the prompt.
+        let pgid = unsafe { libc::tcgetpgrp(self.pty.file().as_raw_fd()) };
+        let pid = if pgid > 0 {
+            pgid as u32
+        } else {
+            self.pty.child().id()
+        };
+
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // The name is in parentheses and can contain spaces itself, so the
+        // other fields are split from after it, starting with the third.
+        let name_start = stat.find('(')? + 1;
+        let name_end = stat.rfind(')')?;
+        let name = stat.get(name_start..name_end)?.to_string();
+        let fields: Vec<&str> =
+            stat.get(name_end + 1..)?.split_whitespace().collect();
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+        let rss: u64 = fields.get(21)?.parse().ok()?;
+        let ticks = utime + stime;
+
+        let now = Instant::now();
+        let cpu = match self.last_usage_sample {
+            Some((last_pid, last_ticks, last_time)) if last_pid == pid => {
+                let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+                let elapsed = now.duration_since(last_time).as_secs_f32();
+                if ticks_per_second > 0 && elapsed > 0.0 {
+                    ticks.saturating_sub(last_ticks) as f32
+                        / ticks_per_second as f32
+                        / elapsed
+                        * 100.0
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        };
+        self.last_usage_sample = Some((pid, ticks, now));
+
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(0) as u64;
+        Some(TerminalProcessUsage {
+            pid,
+            name,
+            cpu,
+            memory: rss * page_size,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn process_usage(&mut self) -> Option<TerminalProcessUsage> {
+        None
+    }
+
     #[inline]
     fn pty_write(&mut self, state: &mut State) -> io::Result<()> {
         state.ensure_next();
@@ -308,6 +496,56 @@ fn program(profile: &TerminalProfile) -> Option<Shell> {
     }
 }
 
+/// The variables the foreground process of the terminal whose shell is
+/// `shell_pid` was started with.
+#[cfg(target_os = "linux")]
+pub(crate) fn foreground_environment(
+    shell_pid: u32,
+) -> Option<TerminalProcessEnvironment> {
+    // The foreground process group of the controlling terminal is the sixth
+    // field after the name, which is in parentheses and can contain spaces.
+    let stat = std::fs::read_to_string(format!("/proc/{shell_pid}/stat")).ok()?;
+    let name_end = stat.rfind(')')?;
+    let fields: Vec<&str> = stat.get(name_end + 1..)?.split_whitespace().collect();
+    let pgid: i32 = fields.get(5)?.parse().ok()?;
+    let pid = if pgid > 0 { pgid as u32 } else { shell_pid };
+
+    let name = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    Some(TerminalProcessEnvironment {
+        pid,
+        name: name.trim().to_string(),
+        variables: process_environment(pid)?,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn foreground_environment(
+    _shell_pid: u32,
+) -> Option<TerminalProcessEnvironment> {
+    None
+}
+
+/// The variables the process `pid` was started with, sorted.
+#[cfg(target_os = "linux")]
+pub(crate) fn process_environment(pid: u32) -> Option<Vec<(String, String)>> {
+    let environ = std::fs::read(format!("/proc/{pid}/environ")).ok()?;
+    let mut variables: Vec<(String, String)> = environ
+        .split(|b| *b == 0)
+        .filter_map(|var| {
+            let var = String::from_utf8_lossy(var);
+            let (key, value) = var.split_once('=')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect();
+    variables.sort();
+    Some(variables)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn process_environment(_pid: u32) -> Option<Vec<(String, String)>> {
+    None
+}
+
 struct Writing {
     source: Cow<'static, [u8]>,
     written: usize,
@@ -373,6 +611,64 @@ fn set_current(&mut self, new: Option<Writing>) {
     }
 }
 
+/// The start of the OSC 7 sequence a shell reports its working directory
+/// with.
+const OSC7: &[u8] = b"\x1b]7;";
+
+/// The longest unfinished OSC 7 sequence kept to be finished by the next
+/// output.
+const MAX_OSC7_TAIL: usize = 4096;
+
+/// How often the working directory of the process is read, at most.
+const CWD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Extract the last working directory reported with an OSC 7 sequence
+/// (`ESC ] 7 ; file://host/path BEL`) in the given output, ignoring a sequence
+/// that isn't finished in it.
+fn osc7_cwd(content: &[u8]) -> Option<PathBuf> {
+    let url = content
+        .windows(OSC7.len())
+        .enumerate()
+        .filter(|(_, w)| *w == OSC7)
+        .filter_map(|(start, _)| {
+            let rest = &content[start + OSC7.len()..];
+            let end = rest.iter().position(|b| *b == b'\x07' || *b == b'\x1b')?;
+            Some(&rest[..end])
+        })
+        .last()?;
+    let url = url::Url::parse(std::str::from_utf8(url).ok()?).ok()?;
+    if url.scheme() != "file" {
+        return None;
+    }
+    // The host is the machine the shell runs on, which is always ours, so the
+    // path is read without it.
+    url::Url::parse(&format!("file://{}", url.path()))
+        .ok()?
+        .to_file_path()
+        .ok()
+}
+
+/// The end of `content` that may be the start of an OSC 7 sequence, which is
+/// finished by the next output.
+fn osc7_tail(content: &[u8]) -> &[u8] {
+    if let Some(start) = content.windows(OSC7.len()).rposition(|w| w == OSC7) {
+        let rest = &content[start + OSC7.len()..];
+        if !rest.iter().any(|b| *b == b'\x07' || *b == b'\x1b') {
+            return if content.len() - start <= MAX_OSC7_TAIL {
+                &content[start..]
+            } else {
+                &[]
+            };
+        }
+    }
+    // The output may end in the middle of the start of the sequence.
+    let len = (1..OSC7.len())
+        .rev()
+        .find(|len| content.ends_with(&OSC7[..*len]))
+        .unwrap_or(0);
+    &content[content.len() - len..]
+}
+
 #[cfg(target_os = "macos")]
 fn set_locale_environment() {
     let locale = locale_config::Locale::global_default()
@@ -380,3 +676,50 @@ fn set_locale_environment() {
         .replace('-', "_");
     std::env::set_var("LC_ALL", locale + ".UTF-8");
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{osc7_cwd, osc7_tail};
+
+    #[test]
+    fn test_osc7_cwd() {
+        assert_eq!(
+            osc7_cwd(b"ls\r\n\x1b]7;file://host/home/user\x07$ "),
+            Some(PathBuf::from("/home/user"))
+        );
+        // The last sequence wins, whichever terminator it has.
+        assert_eq!(
+            osc7_cwd(b"\x1b]7;file:///tmp\x07\x1b]7;file:///home/a%20b\x1b\\"),
+            Some(PathBuf::from("/home/a b"))
+        );
+        // An unfinished sequence is ignored.
+        assert_eq!(
+            osc7_cwd(b"\x1b]7;file:///tmp\x07\x1b]7;file:///ho"),
+            Some(PathBuf::from("/tmp"))
+        );
+        assert_eq!(osc7_cwd(b"\x1b]7;file:///ho"), None);
+        assert_eq!(osc7_cwd(b"\x1b]7;https://host/path\x07"), None);
+        assert_eq!(osc7_cwd(b"plain output"), None);
+    }
+
+    #[test]
+    fn test_osc7_split_across_reads() {
+        let chunks: [&[u8]; 3] = [b"output\x1b]", b"7;file:///ho", b"me/user\x07$ "];
+        let mut tail = Vec::new();
+        let mut cwd = None;
+        for chunk in chunks {
+            let mut output = std::mem::take(&mut tail);
+            output.extend_from_slice(chunk);
+            tail = osc7_tail(&output).to_vec();
+            cwd = cwd.or(osc7_cwd(&output));
+        }
+        assert_eq!(cwd, Some(PathBuf::from("/home/user")));
+        assert!(tail.is_empty());
+
+        assert_eq!(osc7_tail(b"output\x1b"), b"\x1b");
+        assert_eq!(osc7_tail(b"\x1b]7;file:///tmp\x07"), b"");
+        assert_eq!(osc7_tail(b"output"), b"");
+    }
+}
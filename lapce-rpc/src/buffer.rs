@@ -12,6 +12,32 @@ pub fn next() -> Self {
     }
 }
 
+/// The name of UTF-8 with a byte order mark, which files are saved with when
+/// they had one.
+pub const UTF8_BOM: &str = "UTF-8 with BOM";
+
+/// The encodings a file can be reopened or saved with, by the names shown to
+/// the user. Apart from [`UTF8_BOM`] these are the names of the WHATWG
+/// encoding standard.
+pub const FILE_ENCODINGS: &[&str] = &[
+    "UTF-8",
+    UTF8_BOM,
+    "UTF-16LE",
+    "UTF-16BE",
+    "windows-1252",
+    "ISO-8859-2",
+    "ISO-8859-15",
+    "windows-1250",
+    "windows-1251",
+    "KOI8-R",
+    "Shift_JIS",
+    "EUC-JP",
+    "GBK",
+    "gb18030",
+    "Big5",
+    "EUC-KR",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewBufferResponse {
     pub content: String,
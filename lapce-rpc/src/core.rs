@@ -9,7 +9,7 @@
 
 use crossbeam_channel::{Receiver, Sender};
 use lsp_types::{
-    CancelParams, CompletionResponse, LogMessageParams, ProgressParams,
+    CancelParams, CompletionResponse, Diagnostic, LogMessageParams, ProgressParams,
     PublishDiagnosticsParams, ShowMessageParams, SignatureHelp,
 };
 use parking_lot::Mutex;
@@ -20,10 +20,11 @@
         self, DapId, RunDebugConfig, Scope, StackFrame, Stopped, ThreadId, Variable,
     },
     file::PathObject,
-    plugin::{PluginId, VoltInfo, VoltMetadata},
+    plugin::{PluginId, VoltID, VoltInfo, VoltMetadata},
     proxy::ProxyStatus,
     source_control::DiffInfo,
-    terminal::TermId,
+    task::TaskId,
+    terminal::{TermId, TerminalProcessUsage},
     RequestId, RpcError, RpcMessage,
 };
 
@@ -56,6 +57,9 @@ pub enum CoreNotification {
         input: String,
         resp: CompletionResponse,
         plugin_id: PluginId,
+        /// The priority of the server for completion, its items coming before
+        /// those of the servers with lower priorities.
+        priority: i64,
     },
     SignatureHelpResponse {
         request_id: usize,
@@ -65,16 +69,33 @@ pub enum CoreNotification {
     OpenPaths {
         paths: Vec<PathObject>,
     },
+    OpenDiffFiles {
+        left_path: PathBuf,
+        right_path: PathBuf,
+    },
     WorkspaceFileChange,
     PublishDiagnostics {
         diagnostics: PublishDiagnosticsParams,
+        /// The plugin of the language server the diagnostics are from, as the
+        /// servers of a language each publish their own.
+        plugin_id: PluginId,
     },
     ServerStatus {
         params: ServerStatusParams,
     },
     WorkDoneProgress {
+        /// The plugin of the language server reporting the progress, as the
+        /// tokens are only unique for a server.
+        plugin_id: PluginId,
+        /// The name of the volt of the language server.
+        name: String,
         progress: ProgressParams,
     },
+    /// The process of a language server exited, ending the work it was
+    /// reporting the progress of.
+    LanguageServerStopped {
+        plugin_id: PluginId,
+    },
     ShowMessage {
         title: String,
         message: ShowMessageParams,
@@ -83,6 +104,13 @@ pub enum CoreNotification {
         message: LogMessageParams,
         target: String,
     },
+    /// Output of the language servers of a volt: what they write to stderr,
+    /// their log messages and, when traced, the messages exchanged with them.
+    LanguageServerOutput {
+        volt_id: VoltID,
+        name: String,
+        message: String,
+    },
     LspCancel {
         params: CancelParams,
     },
@@ -124,6 +152,36 @@ pub enum CoreNotification {
         term_id: TermId,
         exit_code: Option<i32>,
     },
+    TerminalCwd {
+        term_id: TermId,
+        cwd: PathBuf,
+    },
+    /// The usage of the foreground process, sampled while enabled with
+    /// `ProxyNotification::TerminalSampleUsage`.
+    TerminalProcessUsage {
+        term_id: TermId,
+        usage: Option<TerminalProcessUsage>,
+    },
+    /// A line printed by a task on its stdout, or its stderr, with an id
+    /// increasing with each line of the task.
+    TaskOutput {
+        task_id: TaskId,
+        id: usize,
+        line: String,
+    },
+    /// A problem found in the output of a task by its problem matcher.
+    TaskDiagnostic {
+        task_id: TaskId,
+        path: PathBuf,
+        diagnostic: Diagnostic,
+    },
+    /// The task exited, with no exit code when it failed to start or was
+    /// killed by a signal.
+    TaskExited {
+        task_id: TaskId,
+        exit_code: Option<i32>,
+        error: Option<String>,
+    },
     RunInTerminal {
         config: RunDebugConfig,
     },
@@ -141,6 +199,10 @@ pub enum CoreNotification {
     DapContinued {
         dap_id: DapId,
     },
+    DapOutput {
+        dap_id: DapId,
+        output: dap_types::Output,
+    },
     DapBreakpointsResp {
         dap_id: DapId,
         path: PathBuf,
@@ -250,6 +312,13 @@ pub fn notification(&self, notification: CoreNotification) {
         }
     }
 
+    pub fn open_diff_files(&self, left_path: PathBuf, right_path: PathBuf) {
+        self.notification(CoreNotification::OpenDiffFiles {
+            left_path,
+            right_path,
+        });
+    }
+
     pub fn workspace_file_change(&self) {
         self.notification(CoreNotification::WorkspaceFileChange);
     }
@@ -268,12 +337,14 @@ pub fn completion_response(
         input: String,
         resp: CompletionResponse,
         plugin_id: PluginId,
+        priority: i64,
     ) {
         self.notification(CoreNotification::CompletionResponse {
             request_id,
             input,
             resp,
             plugin_id,
+            priority,
         });
     }
 
@@ -321,16 +392,36 @@ pub fn log(&self, level: LogLevel, message: String, target: Option<String>) {
         });
     }
 
-    pub fn publish_diagnostics(&self, diagnostics: PublishDiagnosticsParams) {
-        self.notification(CoreNotification::PublishDiagnostics { diagnostics });
+    pub fn publish_diagnostics(
+        &self,
+        diagnostics: PublishDiagnosticsParams,
+        plugin_id: PluginId,
+    ) {
+        self.notification(CoreNotification::PublishDiagnostics {
+            diagnostics,
+            plugin_id,
+        });
     }
 
     pub fn server_status(&self, params: ServerStatusParams) {
         self.notification(CoreNotification::ServerStatus { params });
     }
 
-    pub fn work_done_progress(&self, progress: ProgressParams) {
-        self.notification(CoreNotification::WorkDoneProgress { progress });
+    pub fn work_done_progress(
+        &self,
+        plugin_id: PluginId,
+        name: String,
+        progress: ProgressParams,
+    ) {
+        self.notification(CoreNotification::WorkDoneProgress {
+            plugin_id,
+            name,
+            progress,
+        });
+    }
+
+    pub fn language_server_stopped(&self, plugin_id: PluginId) {
+        self.notification(CoreNotification::LanguageServerStopped { plugin_id });
     }
 
     pub fn show_message(&self, title: String, message: ShowMessageParams) {
@@ -341,6 +432,19 @@ pub fn log_message(&self, message: LogMessageParams, target: String) {
         self.notification(CoreNotification::LogMessage { message, target });
     }
 
+    pub fn language_server_output(
+        &self,
+        volt_id: VoltID,
+        name: String,
+        message: String,
+    ) {
+        self.notification(CoreNotification::LanguageServerOutput {
+            volt_id,
+            name,
+            message,
+        });
+    }
+
     pub fn cancel(&self, params: CancelParams) {
         self.notification(CoreNotification::LspCancel { params });
     }
@@ -359,6 +463,48 @@ pub fn terminal_process_stopped(&self, term_id: TermId, exit_code: Option<i32>)
         });
     }
 
+    pub fn task_output(&self, task_id: TaskId, id: usize, line: String) {
+        self.notification(CoreNotification::TaskOutput { task_id, id, line });
+    }
+
+    pub fn task_diagnostic(
+        &self,
+        task_id: TaskId,
+        path: PathBuf,
+        diagnostic: Diagnostic,
+    ) {
+        self.notification(CoreNotification::TaskDiagnostic {
+            task_id,
+            path,
+            diagnostic,
+        });
+    }
+
+    pub fn task_exited(
+        &self,
+        task_id: TaskId,
+        exit_code: Option<i32>,
+        error: Option<String>,
+    ) {
+        self.notification(CoreNotification::TaskExited {
+            task_id,
+            exit_code,
+            error,
+        });
+    }
+
+    pub fn terminal_cwd(&self, term_id: TermId, cwd: PathBuf) {
+        self.notification(CoreNotification::TerminalCwd { term_id, cwd });
+    }
+
+    pub fn terminal_process_usage(
+        &self,
+        term_id: TermId,
+        usage: Option<TerminalProcessUsage>,
+    ) {
+        self.notification(CoreNotification::TerminalProcessUsage { term_id, usage });
+    }
+
     pub fn terminal_launch_failed(&self, term_id: TermId, error: String) {
         self.notification(CoreNotification::TerminalLaunchFailed { term_id, error });
     }
@@ -386,6 +532,10 @@ pub fn dap_continued(&self, dap_id: DapId) {
         self.notification(CoreNotification::DapContinued { dap_id });
     }
 
+    pub fn dap_output(&self, dap_id: DapId, output: dap_types::Output) {
+        self.notification(CoreNotification::DapOutput { dap_id, output });
+    }
+
     pub fn dap_breakpoints_resp(
         &self,
         dap_id: DapId,
@@ -48,11 +48,24 @@ pub struct RunDebugConfig {
     #[serde(rename = "type")]
     pub ty: Option<String>,
     pub name: String,
+    /// The program to run, which a config attaching a debugger can leave out.
+    #[serde(default)]
     pub program: String,
     pub args: Option<Vec<String>>,
     pub cwd: Option<String>,
     pub env: Option<HashMap<String, String>>,
     pub prelaunch: Option<RunDebugProgram>,
+    /// Whether the debugger launches the program or attaches to one already
+    /// running.
+    #[serde(default)]
+    pub request: DebugRequest,
+    /// The debug adapter to start for this config, in place of the one
+    /// registered by a plugin for its `type`.
+    pub adapter: Option<RunDebugProgram>,
+    /// The arguments specific to the debug adapter, passed along in the
+    /// launch or attach request, e.g. `pid` to attach to.
+    #[serde(default)]
+    pub arguments: HashMap<String, Value>,
     #[serde(skip)]
     pub debug_command: Option<Vec<String>>,
     #[serde(skip)]
@@ -63,6 +76,22 @@ pub struct RunDebugConfig {
     pub config_source: ConfigSource,
 }
 
+impl RunDebugConfig {
+    /// Whether the config can be debugged, with a debug adapter given by a
+    /// plugin for its type, or by the config itself.
+    pub fn is_debuggable(&self) -> bool {
+        self.ty.is_some() || self.adapter.is_some()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugRequest {
+    #[default]
+    Launch,
+    Attach,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
 pub enum ConfigSource {
     #[default]
@@ -500,6 +529,14 @@ impl Request for Launch {
     const COMMAND: &'static str = "launch";
 }
 
+pub enum Attach {}
+
+impl Request for Attach {
+    type Arguments = Value;
+    type Result = Value;
+    const COMMAND: &'static str = "attach";
+}
+
 #[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RunInTerminalResponse {
@@ -821,6 +858,125 @@ impl Request for Variables {
     const COMMAND: &'static str = "variables";
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetVariableArguments {
+    pub variables_reference: usize,
+    pub name: String,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ValueFormat>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetVariableResponse {
+    pub value: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variables_reference: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub named_variables: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_variables: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum SetVariable {}
+
+impl Request for SetVariable {
+    type Arguments = SetVariableArguments;
+    type Result = SetVariableResponse;
+    const COMMAND: &'static str = "setVariable";
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateArguments {
+    pub expression: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_id: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ValueFormat>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateResponse {
+    pub result: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presentation_hint: Option<VariablePresentationHint>,
+    #[serde(default)]
+    pub variables_reference: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub named_variables: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_variables: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_reference: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum Evaluate {}
+
+impl Request for Evaluate {
+    type Arguments = EvaluateArguments;
+    type Result = EvaluateResponse;
+    const COMMAND: &'static str = "evaluate";
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionsArguments {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_id: Option<usize>,
+    pub text: String,
+    /// The column of the cursor in `text`, counted from 1.
+    pub column: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionItem {
+    pub label: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    /// Where the text replacing what's typed starts, counted like the column
+    /// of the request, and the length of what it replaces.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionsResponse {
+    pub targets: Vec<CompletionItem>,
+}
+
+#[derive(Debug)]
+pub enum Completions {}
+
+impl Request for Completions {
+    type Arguments = CompletionsArguments;
+    type Result = CompletionsResponse;
+    const COMMAND: &'static str = "completions";
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct NextArguments {
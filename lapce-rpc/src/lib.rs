@@ -12,6 +12,7 @@
 pub mod source_control;
 pub mod stdio;
 pub mod style;
+pub mod task;
 pub mod terminal;
 
 pub use parse::{Call, RequestId, RpcObject};
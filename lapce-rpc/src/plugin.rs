@@ -1,10 +1,11 @@
 use core::fmt;
 use std::{collections::HashMap, path::PathBuf};
 
+use lsp_types::Range;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::counter::Counter;
+use crate::{counter::Counter, terminal::TermId};
 
 #[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct PluginId(pub u64);
@@ -68,6 +69,7 @@ pub struct VoltMetadata {
     pub wasm: Option<String>,
     pub color_themes: Option<Vec<String>>,
     pub icon_themes: Option<Vec<String>>,
+    pub file_templates: Option<Vec<String>>,
     pub dir: Option<PathBuf>,
     pub activation: Option<VoltActivation>,
     pub config: Option<HashMap<String, VoltConfig>>,
@@ -140,9 +142,104 @@ fn from(volt: &VoltInfo) -> Self {
     }
 }
 
+/// The kinds of [`PluginEvent`] a plugin can subscribe to.
+#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PluginEventKind {
+    DocumentOpened,
+    DocumentSaved,
+    DocumentClosed,
+    SelectionChanged,
+    TerminalCreated,
+    TerminalExited,
+    WorkspaceOpened,
+}
+
+/// Editor lifecycle events sent to the plugins that subscribed to them with
+/// [`PluginEvent::SUBSCRIBE_METHOD`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "kind", content = "params")]
+pub enum PluginEvent {
+    DocumentOpened {
+        path: PathBuf,
+    },
+    DocumentSaved {
+        path: PathBuf,
+    },
+    /// The last editor showing the document was closed.
+    DocumentClosed {
+        path: PathBuf,
+    },
+    /// Sent once the selection has stopped changing for a moment.
+    SelectionChanged {
+        path: PathBuf,
+        selections: Vec<Range>,
+    },
+    #[serde(rename_all = "camelCase")]
+    TerminalCreated {
+        term_id: TermId,
+    },
+    #[serde(rename_all = "camelCase")]
+    TerminalExited {
+        term_id: TermId,
+        exit_code: Option<i32>,
+    },
+    /// Sent as soon as the plugin subscribes, as the workspace is opened
+    /// before any plugin is running.
+    WorkspaceOpened {
+        path: Option<PathBuf>,
+    },
+}
+
+impl PluginEvent {
+    /// The notification plugins receive events with.
+    pub const METHOD: &'static str = "lapce/event";
+    /// The notification plugins subscribe to events with, taking
+    /// [`SubscribeEventsParams`].
+    pub const SUBSCRIBE_METHOD: &'static str = "lapce/subscribeEvents";
+
+    pub fn kind(&self) -> PluginEventKind {
+        match self {
+            PluginEvent::DocumentOpened { .. } => PluginEventKind::DocumentOpened,
+            PluginEvent::DocumentSaved { .. } => PluginEventKind::DocumentSaved,
+            PluginEvent::DocumentClosed { .. } => PluginEventKind::DocumentClosed,
+            PluginEvent::SelectionChanged { .. } => {
+                PluginEventKind::SelectionChanged
+            }
+            PluginEvent::TerminalCreated { .. } => PluginEventKind::TerminalCreated,
+            PluginEvent::TerminalExited { .. } => PluginEventKind::TerminalExited,
+            PluginEvent::WorkspaceOpened { .. } => PluginEventKind::WorkspaceOpened,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscribeEventsParams {
+    pub events: Vec<PluginEventKind>,
+}
+
+/// The files a plugin asks to compare in a diff editor, with `left` as the
+/// original and `right` as the file that can be edited.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenDiffParams {
+    pub left: PathBuf,
+    pub right: PathBuf,
+}
+
+impl OpenDiffParams {
+    /// The notification plugins open a diff editor with.
+    pub const METHOD: &'static str = "lapce/openDiff";
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{VoltID, VoltInfo, VoltMetadata};
+    use std::path::PathBuf;
+
+    use super::{
+        OpenDiffParams, PluginEvent, PluginEventKind, SubscribeEventsParams, VoltID,
+        VoltInfo, VoltMetadata,
+    };
+    use crate::terminal::TermId;
 
     #[test]
     fn test_volt_metadata_id() {
@@ -157,6 +254,7 @@ fn test_volt_metadata_id() {
             wasm: None,
             color_themes: None,
             icon_themes: None,
+            file_templates: None,
             dir: std::env::current_dir().unwrap().canonicalize().ok(),
             activation: None,
             config: None,
@@ -195,6 +293,7 @@ fn test_volt_metadata_info() {
             wasm: None,
             color_themes: None,
             icon_themes: None,
+            file_templates: None,
             dir: std::env::current_dir().unwrap().canonicalize().ok(),
             activation: None,
             config: None,
@@ -234,4 +333,59 @@ fn test_volt_info_id() {
         assert_eq!(<&VoltInfo as Into<VoltID>>::into(&volt_info), volt_id);
         assert_eq!(<VoltInfo as Into<VoltID>>::into(volt_info), volt_id);
     }
+
+    #[test]
+    fn test_plugin_event_serialization() {
+        let event = PluginEvent::DocumentSaved {
+            path: PathBuf::from("/tmp/main.rs"),
+        };
+        assert_eq!(event.kind(), PluginEventKind::DocumentSaved);
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "kind": "documentSaved",
+                "params": { "path": "/tmp/main.rs" },
+            })
+        );
+
+        let event = PluginEvent::TerminalExited {
+            term_id: TermId(3),
+            exit_code: Some(1),
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "kind": "terminalExited",
+                "params": { "termId": 3, "exitCode": 1 },
+            })
+        );
+
+        let params: SubscribeEventsParams = serde_json::from_value(
+            serde_json::json!({ "events": ["selectionChanged", "terminalExited"] }),
+        )
+        .unwrap();
+        assert_eq!(
+            params.events,
+            vec![
+                PluginEventKind::SelectionChanged,
+                PluginEventKind::TerminalExited
+            ]
+        );
+    }
+
+    #[test]
+    fn test_open_diff_params() {
+        let params: OpenDiffParams = serde_json::from_value(serde_json::json!({
+            "left": "/tmp/old.rs",
+            "right": "/tmp/new.rs",
+        }))
+        .unwrap();
+        assert_eq!(
+            params,
+            OpenDiffParams {
+                left: PathBuf::from("/tmp/old.rs"),
+                right: PathBuf::from("/tmp/new.rs"),
+            }
+        );
+    }
 }
@@ -1,10 +1,11 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     path::PathBuf,
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
+    time::{Duration, Instant},
 };
 
 use crossbeam_channel::{Receiver, Sender};
@@ -12,11 +13,12 @@
 use lapce_xi_rope::RopeDelta;
 use lsp_types::{
     request::{GotoImplementationResponse, GotoTypeDefinitionResponse},
-    CallHierarchyIncomingCall, CallHierarchyItem, CodeAction, CodeActionResponse,
-    CodeLens, CompletionItem, Diagnostic, DocumentSymbolResponse, FoldingRange,
-    GotoDefinitionResponse, Hover, InlayHint, InlineCompletionResponse,
-    InlineCompletionTriggerKind, Location, Position, PrepareRenameResponse,
-    SelectionRange, SymbolInformation, TextDocumentItem, TextEdit, WorkspaceEdit,
+    CallHierarchyIncomingCall, CallHierarchyItem, CodeAction, CodeActionKind,
+    CodeActionOrCommand, CodeLens, CompletionItem, Diagnostic, DocumentLink,
+    DocumentSymbolResponse, FoldingRange, GotoDefinitionResponse, Hover, InlayHint,
+    InlineCompletionResponse, InlineCompletionTriggerKind, LinkedEditingRanges,
+    Location, Position, PrepareRenameResponse, ProgressToken, Range, SelectionRange,
+    SymbolInformation, TextDocumentItem, TextEdit, WorkspaceEdit,
 };
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
@@ -27,10 +29,11 @@
     dap_types::{self, DapId, RunDebugConfig, SourceBreakpoint, ThreadId},
     file::{FileNodeItem, PathObject},
     file_line::FileLine,
-    plugin::{PluginId, VoltInfo, VoltMetadata},
+    plugin::{PluginEvent, PluginId, VoltInfo, VoltMetadata},
     source_control::FileDiff,
     style::SemanticStyles,
-    terminal::{TermId, TerminalProfile},
+    task::{TaskConfig, TaskId},
+    terminal::{TermId, TerminalEnvironment, TerminalProfile},
     RequestId, RpcError, RpcMessage,
 };
 
@@ -56,6 +59,30 @@ pub struct SearchMatch {
     pub line_content: String,
 }
 
+/// A command formatting the documents of a language, given their text on its
+/// stdin and printing the formatted text on its stdout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExternalFormatter {
+    pub command: String,
+    /// The arguments of the command, where `{file}` is replaced by the path
+    /// of the document and `{workspace}` by the path of the workspace.
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub mode: ExternalFormatterMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExternalFormatterMode {
+    /// Format with the command instead of the language servers.
+    #[default]
+    Replace,
+    /// Format with the language servers first, and then with the command.
+    AfterLanguageServer,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "method", content = "params")]
@@ -64,14 +91,42 @@ pub enum ProxyRequest {
         buffer_id: BufferId,
         path: PathBuf,
     },
+    /// Read an open file again, decoding it with `encoding`.
+    ReopenBuffer {
+        path: PathBuf,
+        encoding: String,
+    },
+    /// Change the encoding an open file is saved with. Fails if its content
+    /// can't be represented in `encoding`.
+    SetBufferEncoding {
+        path: PathBuf,
+        rev: u64,
+        encoding: String,
+    },
     BufferHead {
         path: PathBuf,
     },
+    /// The content of the file as staged in the git index.
+    BufferIndex {
+        path: PathBuf,
+    },
+    /// Stage a single hunk of the changes to a file, given as the lines of a
+    /// unified diff against the index starting at its `@@` header.
+    GitStageHunk {
+        path: PathBuf,
+        hunk: String,
+    },
     GlobalSearch {
         pattern: String,
         case_sensitive: bool,
         whole_word: bool,
         is_regex: bool,
+        /// The globs, separated by commas and relative to the workspace, of the
+        /// files searched.
+        include: Option<String>,
+        /// The globs of the files left out of the search.
+        #[serde(default)]
+        exclude: Option<String>,
     },
     CompletionResolve {
         plugin_id: PluginId,
@@ -94,9 +149,26 @@ pub enum ProxyRequest {
         path: PathBuf,
         positions: Vec<Position>,
     },
+    GetLinkedEditingRanges {
+        path: PathBuf,
+        position: Position,
+    },
+    GetDocumentLinks {
+        path: PathBuf,
+    },
+    DocumentLinkResolve {
+        plugin_id: PluginId,
+        link: Box<DocumentLink>,
+    },
     GitGetRemoteFileUrl {
         file: PathBuf,
     },
+    GitShowCommit {
+        rev: String,
+    },
+    GitResolveCommit {
+        rev: String,
+    },
     GetReferences {
         path: PathBuf,
         position: Position,
@@ -151,6 +223,13 @@ pub enum ProxyRequest {
         position: Position,
         diagnostics: Vec<Diagnostic>,
     },
+    /// The code actions of `kind` for `range`, such as the
+    /// `source.organizeImports` action for the whole document.
+    GetCodeActionsOfKind {
+        path: PathBuf,
+        range: Range,
+        kind: CodeActionKind,
+    },
     GetCodeLens {
         path: PathBuf,
     },
@@ -167,6 +246,13 @@ pub enum ProxyRequest {
     },
     GetDocumentFormatting {
         path: PathBuf,
+        /// The command formatting the document in place of, or after, the
+        /// language servers.
+        formatter: Option<ExternalFormatter>,
+    },
+    GetDocumentRangeFormatting {
+        path: PathBuf,
+        range: Range,
     },
     GetOpenFilesContent {},
     GetFiles {
@@ -175,6 +261,17 @@ pub enum ProxyRequest {
     ReadDir {
         path: PathBuf,
     },
+    ReadFile {
+        path: PathBuf,
+    },
+    /// The saved versions of the file kept by the local history.
+    LocalHistory {
+        path: PathBuf,
+    },
+    LocalHistoryContent {
+        path: PathBuf,
+        timestamp: u64,
+    },
     Save {
         rev: u64,
         path: PathBuf,
@@ -198,6 +295,11 @@ pub enum ProxyRequest {
     TrashPath {
         path: PathBuf,
     },
+    /// Move the path to the trash, keeping a copy of it which it can be
+    /// restored from.
+    TrashPathWithBackup {
+        path: PathBuf,
+    },
     DuplicatePath {
         existing_path: PathBuf,
         new_path: PathBuf,
@@ -217,9 +319,38 @@ pub enum ProxyRequest {
         dap_id: DapId,
         frame_id: usize,
     },
+    DapSetVariable {
+        dap_id: DapId,
+        reference: usize,
+        name: String,
+        value: String,
+    },
+    DapEvaluate {
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: String,
+    },
+    DapCompletions {
+        dap_id: DapId,
+        text: String,
+        column: usize,
+        frame_id: Option<usize>,
+    },
     ReferencesResolve {
         items: Vec<Location>,
     },
+    /// Sent after reconnecting to a proxy that kept running, to find out
+    /// which of the terminals are still alive.
+    ReattachTerminals {
+        term_ids: Vec<TermId>,
+    },
+    TerminalEnvironment {
+        term_id: TermId,
+    },
+    /// The tasks of `.lapce/tasks.toml`, and those detected from the build
+    /// files of the workspace.
+    GetTasks {},
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -228,10 +359,16 @@ pub enum ProxyRequest {
 pub enum ProxyNotification {
     Initialize {
         workspace: Option<PathBuf>,
+        /// The folders added to the workspace besides its root.
+        #[serde(default)]
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         /// Paths to extra plugins that should be loaded
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+        /// The languages of the files matching glob patterns.
+        #[serde(default)]
+        file_associations: HashMap<String, String>,
         window_id: usize,
         tab_id: usize,
     },
@@ -261,6 +398,17 @@ pub enum ProxyNotification {
     UpdatePluginConfigs {
         configs: HashMap<String, HashMap<String, serde_json::Value>>,
     },
+    /// Set the languages of the files matching glob patterns.
+    UpdateFileAssociations {
+        associations: HashMap<String, String>,
+    },
+    /// Set the folders added to the workspace besides its root.
+    UpdateWorkspaceFolders {
+        folders: Vec<PathBuf>,
+    },
+    PluginEvent {
+        event: PluginEvent,
+    },
     NewTerminal {
         term_id: TermId,
         profile: TerminalProfile,
@@ -274,6 +422,12 @@ pub enum ProxyNotification {
     ReloadVolt {
         volt: VoltMetadata,
     },
+    /// Stop the language servers of the file at `path`, or all of them, and
+    /// start them again when `restart`.
+    StopLanguageServers {
+        path: Option<PathBuf>,
+        restart: bool,
+    },
     DisableVolt {
         volt: VoltInfo,
     },
@@ -295,6 +449,11 @@ pub enum ProxyNotification {
     LspCancel {
         id: i32,
     },
+    /// Ask a language server to cancel the work it reported the progress of.
+    LspCancelProgress {
+        plugin_id: PluginId,
+        token: ProgressToken,
+    },
     TerminalWrite {
         term_id: TermId,
         content: String,
@@ -307,6 +466,10 @@ pub enum ProxyNotification {
     TerminalClose {
         term_id: TermId,
     },
+    /// Whether to sample the resource usage of the terminal processes.
+    TerminalSampleUsage {
+        enabled: bool,
+    },
     DapStart {
         config: RunDebugConfig,
         breakpoints: HashMap<PathBuf, Vec<SourceBreakpoint>>,
@@ -351,6 +514,13 @@ pub enum ProxyNotification {
         path: PathBuf,
         breakpoints: Vec<SourceBreakpoint>,
     },
+    RunTask {
+        task_id: TaskId,
+        task: TaskConfig,
+    },
+    StopTask {
+        task_id: TaskId,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -360,9 +530,19 @@ pub enum ProxyResponse {
     GitGetRemoteFileUrl {
         file_url: String,
     },
+    GitShowCommitResponse {
+        id: String,
+        content: String,
+    },
+    GitResolveCommitResponse {
+        /// The id of the commit, or `None` if `rev` isn't one.
+        id: Option<String>,
+    },
     NewBufferResponse {
         content: String,
         read_only: bool,
+        /// The encoding the file was decoded with.
+        encoding: String,
     },
     BufferHeadResponse {
         version: String,
@@ -371,6 +551,14 @@ pub enum ProxyResponse {
     ReadDirResponse {
         items: Vec<FileNodeItem>,
     },
+    ReadFileResponse {
+        content: String,
+    },
+    LocalHistoryResponse {
+        /// When the versions were saved, in milliseconds since the epoch,
+        /// newest first.
+        timestamps: Vec<u64>,
+    },
     CompletionResolveResponse {
         item: Box<CompletionItem>,
     },
@@ -398,9 +586,10 @@ pub enum ProxyResponse {
     GetReferencesResponse {
         references: Vec<Location>,
     },
+    /// The code actions from all the language servers, with the plugins they
+    /// are from, in the order of the priorities of the servers.
     GetCodeActionsResponse {
-        plugin_id: PluginId,
-        resp: CodeActionResponse,
+        actions: Vec<(PluginId, CodeActionOrCommand)>,
     },
     LspFoldingRangeResponse {
         plugin_id: PluginId,
@@ -424,6 +613,9 @@ pub enum ProxyResponse {
     GetDocumentFormatting {
         edits: Vec<TextEdit>,
     },
+    GetDocumentRangeFormatting {
+        edits: Vec<TextEdit>,
+    },
     GetDocumentSymbols {
         resp: DocumentSymbolResponse,
     },
@@ -433,6 +625,16 @@ pub enum ProxyResponse {
     GetSelectionRange {
         ranges: Vec<SelectionRange>,
     },
+    GetLinkedEditingRanges {
+        ranges: LinkedEditingRanges,
+    },
+    GetDocumentLinks {
+        plugin_id: PluginId,
+        links: Vec<DocumentLink>,
+    },
+    DocumentLinkResolveResponse {
+        link: Box<DocumentLink>,
+    },
     GetInlayHints {
         hints: Vec<InlayHint>,
     },
@@ -460,6 +662,18 @@ pub enum ProxyResponse {
     DapGetScopesResponse {
         scopes: Vec<(dap_types::Scope, Vec<dap_types::Variable>)>,
     },
+    DapSetVariableResponse {
+        response: dap_types::SetVariableResponse,
+    },
+    DapEvaluateResponse {
+        response: dap_types::EvaluateResponse,
+    },
+    DapCompletionsResponse {
+        targets: Vec<dap_types::CompletionItem>,
+    },
+    GetTasksResponse {
+        tasks: Vec<TaskConfig>,
+    },
     CreatePathResponse {
         path: PathBuf,
     },
@@ -468,6 +682,12 @@ pub enum ProxyResponse {
     ReferencesResolveResponse {
         items: Vec<FileLine>,
     },
+    ReattachTerminalsResponse {
+        alive: Vec<TermId>,
+    },
+    TerminalEnvironmentResponse {
+        environment: TerminalEnvironment,
+    },
 }
 
 pub type ProxyMessage = RpcMessage<ProxyRequest, ProxyNotification, ProxyResponse>;
@@ -509,9 +729,13 @@ pub struct ProxyRpcHandler {
     tx: Sender<ProxyRpc>,
     rx: Receiver<ProxyRpc>,
     id: Arc<AtomicU64>,
-    pending: Arc<Mutex<HashMap<u64, ResponseHandler>>>,
+    pending: Arc<Mutex<HashMap<u64, (Instant, ResponseHandler)>>>,
+    latencies: Arc<Mutex<VecDeque<Duration>>>,
 }
 
+/// The number of round trips kept for [`ProxyRpcHandler::recent_latencies`].
+const LATENCY_HISTORY: usize = 120;
+
 impl ProxyRpcHandler {
     pub fn new() -> Self {
         let (tx, rx) = crossbeam_channel::unbounded();
@@ -520,6 +744,7 @@ pub fn new() -> Self {
             rx,
             id: Arc::new(AtomicU64::new(0)),
             pending: Arc::new(Mutex::new(HashMap::new())),
+            latencies: Arc::new(Mutex::new(VecDeque::new())),
         }
     }
 
@@ -550,7 +775,7 @@ pub fn mainloop<H>(&self, handler: &mut H)
     fn request_common(&self, request: ProxyRequest, rh: ResponseHandler) {
         let id = self.id.fetch_add(1, Ordering::Relaxed);
 
-        self.pending.lock().insert(id, rh);
+        self.pending.lock().insert(id, (Instant::now(), rh));
 
         if let Err(err) = self.tx.send(ProxyRpc::Request(id, request)) {
             tracing::error!("{:?}", err);
@@ -582,11 +807,24 @@ pub fn handle_response(
         result: Result<ProxyResponse, RpcError>,
     ) {
         let handler = { self.pending.lock().remove(&id) };
-        if let Some(handler) = handler {
+        if let Some((sent, handler)) = handler {
+            {
+                let mut latencies = self.latencies.lock();
+                if latencies.len() == LATENCY_HISTORY {
+                    latencies.pop_front();
+                }
+                latencies.push_back(sent.elapsed());
+            }
             handler.invoke(result);
         }
     }
 
+    /// The round trip times of the most recently answered requests, oldest
+    /// first.
+    pub fn recent_latencies(&self) -> Vec<Duration> {
+        self.latencies.lock().iter().copied().collect()
+    }
+
     pub fn notification(&self, notification: ProxyNotification) {
         if let Err(err) = self.tx.send(ProxyRpc::Notification(notification)) {
             tracing::error!("{:?}", err);
@@ -597,6 +835,10 @@ pub fn lsp_cancel(&self, id: i32) {
         self.notification(ProxyNotification::LspCancel { id });
     }
 
+    pub fn lsp_cancel_progress(&self, plugin_id: PluginId, token: ProgressToken) {
+        self.notification(ProxyNotification::LspCancelProgress { plugin_id, token });
+    }
+
     pub fn git_init(&self) {
         self.notification(ProxyNotification::GitInit {});
     }
@@ -617,6 +859,10 @@ pub fn reload_volt(&self, volt: VoltMetadata) {
         self.notification(ProxyNotification::ReloadVolt { volt });
     }
 
+    pub fn stop_language_servers(&self, path: Option<PathBuf>, restart: bool) {
+        self.notification(ProxyNotification::StopLanguageServers { path, restart });
+    }
+
     pub fn remove_volt(&self, volt: VoltMetadata) {
         self.notification(ProxyNotification::RemoveVolt { volt });
     }
@@ -636,20 +882,25 @@ pub fn shutdown(&self) {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         &self,
         workspace: Option<PathBuf>,
+        workspace_folders: Vec<PathBuf>,
         disabled_volts: Vec<VoltID>,
         extra_plugin_paths: Vec<PathBuf>,
         plugin_configurations: HashMap<String, HashMap<String, serde_json::Value>>,
+        file_associations: HashMap<String, String>,
         window_id: usize,
         tab_id: usize,
     ) {
         self.notification(ProxyNotification::Initialize {
             workspace,
+            workspace_folders,
             disabled_volts,
             extra_plugin_paths,
             plugin_configurations,
+            file_associations,
             window_id,
             tab_id,
         });
@@ -703,6 +954,22 @@ pub fn terminal_write(&self, term_id: TermId, content: String) {
         self.notification(ProxyNotification::TerminalWrite { term_id, content });
     }
 
+    pub fn terminal_sample_usage(&self, enabled: bool) {
+        self.notification(ProxyNotification::TerminalSampleUsage { enabled });
+    }
+
+    pub fn get_tasks(&self, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::GetTasks {}, f);
+    }
+
+    pub fn run_task(&self, task_id: TaskId, task: TaskConfig) {
+        self.notification(ProxyNotification::RunTask { task_id, task });
+    }
+
+    pub fn stop_task(&self, task_id: TaskId) {
+        self.notification(ProxyNotification::StopTask { task_id });
+    }
+
     pub fn new_buffer(
         &self,
         buffer_id: BufferId,
@@ -712,10 +979,40 @@ pub fn new_buffer(
         self.request_async(ProxyRequest::NewBuffer { buffer_id, path }, f);
     }
 
+    pub fn reopen_buffer(
+        &self,
+        path: PathBuf,
+        encoding: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::ReopenBuffer { path, encoding }, f);
+    }
+
+    pub fn set_buffer_encoding(
+        &self,
+        path: PathBuf,
+        rev: u64,
+        encoding: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::SetBufferEncoding {
+                path,
+                rev,
+                encoding,
+            },
+            f,
+        );
+    }
+
     pub fn get_buffer_head(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
         self.request_async(ProxyRequest::BufferHead { path }, f);
     }
 
+    pub fn get_buffer_index(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::BufferIndex { path }, f);
+    }
+
     pub fn create_file(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
         self.request_async(ProxyRequest::CreateFile { path }, f);
     }
@@ -728,6 +1025,16 @@ pub fn trash_path(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
         self.request_async(ProxyRequest::TrashPath { path }, f);
     }
 
+    /// Move the path to the trash, responding with the path of a copy of it
+    /// which it can be restored from.
+    pub fn trash_path_with_backup(
+        &self,
+        path: PathBuf,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::TrashPathWithBackup { path }, f);
+    }
+
     pub fn duplicate_path(
         &self,
         existing_path: PathBuf,
@@ -787,6 +1094,8 @@ pub fn global_search(
         case_sensitive: bool,
         whole_word: bool,
         is_regex: bool,
+        include: Option<String>,
+        exclude: Option<String>,
         f: impl ProxyCallback + 'static,
     ) {
         self.request_async(
@@ -795,6 +1104,8 @@ pub fn global_search(
                 case_sensitive,
                 whole_word,
                 is_regex,
+                include,
+                exclude,
             },
             f,
         );
@@ -834,6 +1145,26 @@ pub fn read_dir(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
         self.request_async(ProxyRequest::ReadDir { path }, f);
     }
 
+    /// Read the content of the file at `path` as it currently is on disk.
+    pub fn read_file(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::ReadFile { path }, f);
+    }
+
+    pub fn local_history(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::LocalHistory { path }, f);
+    }
+
+    /// Read the version of the file at `path` the local history kept when it
+    /// was saved at `timestamp`.
+    pub fn local_history_content(
+        &self,
+        path: PathBuf,
+        timestamp: u64,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::LocalHistoryContent { path, timestamp }, f);
+    }
+
     pub fn completion_resolve(
         &self,
         plugin_id: PluginId,
@@ -964,6 +1295,22 @@ pub fn references_resolve(
         self.request_async(ProxyRequest::ReferencesResolve { items }, f);
     }
 
+    pub fn reattach_terminals(
+        &self,
+        term_ids: Vec<TermId>,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::ReattachTerminals { term_ids }, f);
+    }
+
+    pub fn terminal_environment(
+        &self,
+        term_id: TermId,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::TerminalEnvironment { term_id }, f);
+    }
+
     pub fn go_to_implementation(
         &self,
         path: PathBuf,
@@ -990,6 +1337,19 @@ pub fn get_code_actions(
         );
     }
 
+    pub fn get_code_actions_of_kind(
+        &self,
+        path: PathBuf,
+        range: Range,
+        kind: CodeActionKind,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::GetCodeActionsOfKind { path, range, kind },
+            f,
+        );
+    }
+
     pub fn get_code_lens(&self, path: PathBuf, f: impl ProxyCallback + 'static) {
         self.request_async(ProxyRequest::GetCodeLens { path }, f);
     }
@@ -1006,9 +1366,25 @@ pub fn get_code_lens_resolve(
     pub fn get_document_formatting(
         &self,
         path: PathBuf,
+        formatter: Option<ExternalFormatter>,
         f: impl ProxyCallback + 'static,
     ) {
-        self.request_async(ProxyRequest::GetDocumentFormatting { path }, f);
+        self.request_async(
+            ProxyRequest::GetDocumentFormatting { path, formatter },
+            f,
+        );
+    }
+
+    pub fn get_document_range_formatting(
+        &self,
+        path: PathBuf,
+        range: Range,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::GetDocumentRangeFormatting { path, range },
+            f,
+        );
     }
 
     pub fn get_semantic_tokens(
@@ -1052,6 +1428,16 @@ pub fn git_get_remote_file_url(
         self.request_async(ProxyRequest::GitGetRemoteFileUrl { file }, f);
     }
 
+    /// Get the message and the patch of the commit `rev` resolves to, failing if
+    /// it isn't a commit of the workspace repository.
+    pub fn git_show_commit(&self, rev: String, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::GitShowCommit { rev }, f);
+    }
+
+    pub fn git_resolve_commit(&self, rev: String, f: impl ProxyCallback + 'static) {
+        self.request_async(ProxyRequest::GitResolveCommit { rev }, f);
+    }
+
     pub fn rename(
         &self,
         path: PathBuf,
@@ -1101,6 +1487,21 @@ pub fn update_plugin_configs(
         self.notification(ProxyNotification::UpdatePluginConfigs { configs });
     }
 
+    pub fn update_file_associations(&self, associations: HashMap<String, String>) {
+        self.notification(ProxyNotification::UpdateFileAssociations {
+            associations,
+        });
+    }
+
+    pub fn update_workspace_folders(&self, folders: Vec<PathBuf>) {
+        self.notification(ProxyNotification::UpdateWorkspaceFolders { folders });
+    }
+
+    /// Pass an event that happened in the editor on to the subscribed plugins.
+    pub fn plugin_event(&self, event: PluginEvent) {
+        self.notification(ProxyNotification::PluginEvent { event });
+    }
+
     pub fn git_discard_files_changes(&self, files: Vec<PathBuf>) {
         self.notification(ProxyNotification::GitDiscardFilesChanges { files });
     }
@@ -1109,6 +1510,15 @@ pub fn git_discard_workspace_changes(&self) {
         self.notification(ProxyNotification::GitDiscardWorkspaceChanges {});
     }
 
+    pub fn git_stage_hunk(
+        &self,
+        path: PathBuf,
+        hunk: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::GitStageHunk { path, hunk }, f);
+    }
+
     pub fn get_selection_range(
         &self,
         path: PathBuf,
@@ -1118,6 +1528,45 @@ pub fn get_selection_range(
         self.request_async(ProxyRequest::GetSelectionRange { path, positions }, f);
     }
 
+    /// The ranges of the document which have the same content as the one at
+    /// `position` and are edited along with it, like the tags of an element.
+    pub fn get_linked_editing_ranges(
+        &self,
+        path: PathBuf,
+        position: Position,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::GetLinkedEditingRanges { path, position },
+            f,
+        );
+    }
+
+    /// The links of the document, as to URLs and to the files of imported
+    /// modules, some of which may need resolving to get their targets.
+    pub fn get_document_links(
+        &self,
+        path: PathBuf,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(ProxyRequest::GetDocumentLinks { path }, f);
+    }
+
+    pub fn document_link_resolve(
+        &self,
+        plugin_id: PluginId,
+        link: DocumentLink,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::DocumentLinkResolve {
+                plugin_id,
+                link: Box::new(link),
+            },
+            f,
+        );
+    }
+
     pub fn dap_start(
         &self,
         config: RunDebugConfig,
@@ -1203,6 +1652,67 @@ pub fn dap_variable(
         self.request_async(ProxyRequest::DapVariable { dap_id, reference }, f);
     }
 
+    /// Set the variable `name` of the variables of `reference` to the value of
+    /// the expression `value`.
+    pub fn dap_set_variable(
+        &self,
+        dap_id: DapId,
+        reference: usize,
+        name: String,
+        value: String,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::DapSetVariable {
+                dap_id,
+                reference,
+                name,
+                value,
+            },
+            f,
+        );
+    }
+
+    /// Evaluate `expression` in the frame `frame_id`, or globally, where
+    /// `context` is why it's evaluated, as `repl` or `watch`.
+    pub fn dap_evaluate(
+        &self,
+        dap_id: DapId,
+        expression: String,
+        frame_id: Option<usize>,
+        context: &str,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::DapEvaluate {
+                dap_id,
+                expression,
+                frame_id,
+                context: context.to_string(),
+            },
+            f,
+        );
+    }
+
+    pub fn dap_completions(
+        &self,
+        dap_id: DapId,
+        text: String,
+        column: usize,
+        frame_id: Option<usize>,
+        f: impl ProxyCallback + 'static,
+    ) {
+        self.request_async(
+            ProxyRequest::DapCompletions {
+                dap_id,
+                text,
+                column,
+                frame_id,
+            },
+            f,
+        );
+    }
+
     pub fn dap_get_scopes(
         &self,
         dap_id: DapId,
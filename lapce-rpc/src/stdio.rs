@@ -1,10 +1,13 @@
 use std::{
+    collections::VecDeque,
     io::{self, BufRead, Write},
+    sync::Arc,
     thread,
 };
 
 use anyhow::Result;
 use crossbeam_channel::{Receiver, Sender};
+use parking_lot::Mutex;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{json, Value};
 
@@ -41,6 +44,192 @@ pub fn stdio_transport<W, R, Req1, Notif1, Resp1, Req2, Notif2, Resp2>(
     });
 }
 
+/// The most bytes of messages kept for a [`ReattachableWriter`] while it's
+/// detached, after which the oldest messages are dropped.
+pub const MAX_BACKLOG_SIZE: usize = 64 * 1024 * 1024;
+
+/// A writer of rpc messages whose connection can go away and be replaced by
+/// a new one, e.g. when an SSH connection drops and is established again.
+/// The messages written while there is no connection are kept and sent once
+/// the next one is attached.
+#[derive(Default)]
+pub struct ReattachableWriter {
+    inner: Mutex<ReattachableWriterInner>,
+}
+
+/// A connection of a [`ReattachableWriter`], locked on its own so that a write
+/// blocking on it doesn't keep it from being replaced.
+type Connection = Arc<Mutex<Box<dyn Write + Send>>>;
+
+#[derive(Default)]
+struct ReattachableWriterInner {
+    writer: Option<Connection>,
+    backlog: VecDeque<String>,
+    backlog_size: usize,
+    /// The number of messages dropped from the backlog since the last attach.
+    dropped: usize,
+}
+
+impl ReattachableWriterInner {
+    fn push_backlog(&mut self, msg: String) {
+        self.backlog_size += msg.len();
+        self.backlog.push_back(msg);
+        while self.backlog_size > MAX_BACKLOG_SIZE {
+            let Some(dropped) = self.backlog.pop_front() else {
+                break;
+            };
+            self.backlog_size -= dropped.len();
+            self.dropped += 1;
+        }
+    }
+}
+
+impl ReattachableWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write the messages kept while detached to `writer`, and use it for
+    /// the following messages. Returns the number of messages that were
+    /// dropped instead for going over [`MAX_BACKLOG_SIZE`].
+    pub fn attach(&self, writer: impl Write + Send + 'static) -> io::Result<usize> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+        let dropped = {
+            let mut inner = self.inner.lock();
+            // The messages written meanwhile are kept to follow the backlog
+            inner.writer = None;
+            std::mem::take(&mut inner.dropped)
+        };
+        loop {
+            let mut backlog = {
+                let mut inner = self.inner.lock();
+                if inner.backlog.is_empty() {
+                    inner.writer = Some(Arc::new(Mutex::new(writer)));
+                    return Ok(dropped);
+                }
+                inner.backlog_size = 0;
+                std::mem::take(&mut inner.backlog)
+            };
+            // Written without holding the lock, which the messages written
+            // meanwhile would wait on
+            let mut result = Ok(());
+            while let Some(msg) = backlog.front() {
+                result = writer.write_all(msg.as_bytes());
+                if result.is_err() {
+                    break;
+                }
+                backlog.pop_front();
+            }
+            if let Err(err) = result.and_then(|_| writer.flush()) {
+                let mut inner = self.inner.lock();
+                for msg in backlog.into_iter().rev() {
+                    inner.backlog_size += msg.len();
+                    inner.backlog.push_front(msg);
+                }
+                inner.dropped += dropped;
+                return Err(err);
+            }
+        }
+    }
+
+    /// Drop the current connection, keeping the following messages until
+    /// another one is attached.
+    pub fn detach(&self) {
+        self.inner.lock().writer = None;
+    }
+
+    pub fn write<Req, Notif, Resp>(&self, msg: RpcMessage<Req, Notif, Resp>)
+    where
+        Req: Serialize,
+        Notif: Serialize,
+        Resp: Serialize,
+    {
+        let msg = match encode_msg(msg) {
+            Ok(msg) => msg,
+            Err(err) => {
+                tracing::error!("{:?}", err);
+                return;
+            }
+        };
+        let mut failed: Option<Connection> = None;
+        loop {
+            let connection = {
+                let mut inner = self.inner.lock();
+                match inner.writer.clone() {
+                    Some(connection)
+                        if !failed
+                            .as_ref()
+                            .is_some_and(|f| Arc::ptr_eq(f, &connection)) =>
+                    {
+                        connection
+                    }
+                    current => {
+                        if current.is_some() {
+                            inner.writer = None;
+                        }
+                        inner.push_backlog(msg);
+                        return;
+                    }
+                }
+            };
+            // Written without holding the lock, so that a connection which
+            // stopped reading can still be detached or replaced
+            let result = {
+                let mut writer = connection.lock();
+                writer
+                    .write_all(msg.as_bytes())
+                    .and_then(|_| writer.flush())
+            };
+            if result.is_ok() {
+                return;
+            }
+            // Sent with the connection attached since, if there is one
+            failed = Some(connection);
+        }
+    }
+}
+
+/// Write the messages received on `writer_receiver` with `writer`, until all
+/// their senders are gone.
+pub fn reattachable_writer_transport<Req, Notif, Resp>(
+    writer: Arc<ReattachableWriter>,
+    writer_receiver: Receiver<RpcMessage<Req, Notif, Resp>>,
+) where
+    Req: 'static + Serialize + Send,
+    Notif: 'static + Serialize + Send,
+    Resp: 'static + Serialize + Send,
+{
+    thread::spawn(move || {
+        for msg in writer_receiver {
+            writer.write(msg);
+        }
+    });
+}
+
+/// Read the messages from `reader` into `reader_sender` until the connection
+/// is closed, then call `on_close`.
+pub fn reader_transport<R, Req, Notif, Resp>(
+    mut reader: R,
+    reader_sender: Sender<RpcMessage<Req, Notif, Resp>>,
+    on_close: impl FnOnce() + Send + 'static,
+) where
+    R: 'static + BufRead + Send,
+    Req: 'static + DeserializeOwned + Send,
+    Notif: 'static + DeserializeOwned + Send,
+    Resp: 'static + DeserializeOwned + Send,
+{
+    thread::spawn(move || {
+        while let Ok(msg) = read_msg(&mut reader) {
+            if let Some(msg) = msg {
+                if reader_sender.send(msg).is_err() {
+                    break;
+                }
+            }
+        }
+        on_close();
+    });
+}
+
 pub fn write_msg<W, Req, Notif, Resp>(
     out: &mut W,
     msg: RpcMessage<Req, Notif, Resp>,
@@ -50,6 +239,20 @@ pub fn write_msg<W, Req, Notif, Resp>(
     Req: Serialize,
     Notif: Serialize,
     Resp: Serialize,
+{
+    let msg = encode_msg(msg)?;
+    out.write_all(msg.as_bytes())?;
+    out.flush()?;
+    Ok(())
+}
+
+fn encode_msg<Req, Notif, Resp>(
+    msg: RpcMessage<Req, Notif, Resp>,
+) -> io::Result<String>
+where
+    Req: Serialize,
+    Notif: Serialize,
+    Resp: Serialize,
 {
     let value = match msg {
         RpcMessage::Request(id, req) => {
@@ -73,10 +276,7 @@ pub fn write_msg<W, Req, Notif, Resp>(
             })
         }
     };
-    let msg = format!("{}\n", serde_json::to_string(&value)?);
-    out.write_all(msg.as_bytes())?;
-    out.flush()?;
-    Ok(())
+    Ok(format!("{}\n", serde_json::to_string(&value)?))
 }
 
 pub fn read_msg<R, Req, Notif, Resp>(
@@ -0,0 +1,117 @@
+use std::{collections::HashMap, ops::Range};
+
+use lsp_types::Diagnostic;
+use serde::{Deserialize, Serialize};
+
+use crate::counter::Counter;
+
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct TaskId(pub u64);
+
+impl TaskId {
+    pub fn next() -> Self {
+        static TASK_ID_COUNTER: Counter = Counter::new();
+        Self(TASK_ID_COUNTER.next())
+    }
+}
+
+/// The lines of the output of a task run a problem was found in, kept in the
+/// `data` of the diagnostic of the problem.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct TaskOutputLink {
+    pub task_id: TaskId,
+    /// The ids of the lines, as sent with `CoreNotification::TaskOutput`.
+    pub lines: Range<usize>,
+}
+
+impl TaskOutputLink {
+    /// The link of a diagnostic found by a problem matcher.
+    pub fn of(diagnostic: &Diagnostic) -> Option<Self> {
+        serde_json::from_value(diagnostic.data.clone()?).ok()
+    }
+
+    pub fn set(&self, diagnostic: &mut Diagnostic) {
+        diagnostic.data = serde_json::to_value(self).ok();
+    }
+}
+
+/// The tasks listed in the `.lapce/tasks.toml` file of the workspace.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct TaskConfigs {
+    #[serde(default)]
+    pub tasks: Vec<TaskConfig>,
+}
+
+/// A command to run in the workspace, whose output can be turned into
+/// diagnostics by a problem matcher.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct TaskConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub cwd: Option<String>,
+    pub env: Option<HashMap<String, String>>,
+    pub problem_matcher: Option<ProblemMatcher>,
+    #[serde(default)]
+    pub source: TaskSource,
+}
+
+impl TaskConfig {
+    /// The command line of the task, as shown to the user.
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.command.as_str())
+            .chain(self.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Where a task comes from.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSource {
+    /// The `.lapce/tasks.toml` file of the workspace.
+    #[default]
+    Config,
+    /// Detected from the `Cargo.toml` of the workspace.
+    Cargo,
+    /// A script of the `package.json` of the workspace.
+    Npm,
+}
+
+/// How the lines printed by a task are turned into diagnostics, by the name
+/// of a built-in matcher, as `rustc`, `gcc` or `tsc`, or by a pattern.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ProblemMatcher {
+    Builtin(String),
+    Pattern(ProblemPattern),
+}
+
+/// A regex matching a whole problem on one line, with the indices of the
+/// groups holding each part of it.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProblemPattern {
+    pub regexp: String,
+    #[serde(default = "default_file_group")]
+    pub file: usize,
+    #[serde(default = "default_line_group")]
+    pub line: usize,
+    pub column: Option<usize>,
+    /// The group holding e.g. `error` or `warning`, the problem being an
+    /// error without one.
+    pub severity: Option<usize>,
+    pub message: usize,
+}
+
+fn default_file_group() -> usize {
+    1
+}
+
+fn default_line_group() -> usize {
+    2
+}
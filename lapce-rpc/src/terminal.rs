@@ -24,3 +24,39 @@ pub struct TerminalProfile {
 }
 
 impl TerminalProfile {}
+
+/// The resource usage of the foreground process of a terminal.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct TerminalProcessUsage {
+    pub pid: u32,
+    /// The name of the executable.
+    pub name: String,
+    /// The CPU usage since the previous sample, in percent of a single core.
+    pub cpu: f32,
+    /// The resident set size, in bytes.
+    pub memory: u64,
+}
+
+/// The environment of a terminal, to inspect e.g. which `PATH` its programs
+/// run with.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct TerminalEnvironment {
+    /// The variables the shell of the terminal was spawned with.
+    pub spawned: Vec<(String, String)>,
+    /// Whether `spawned` was read from the shell process, which is only
+    /// supported on Linux, rather than being the variables it was given,
+    /// which lack the ones set up for the pty.
+    pub from_process: bool,
+    /// The variables of the foreground process, where they can be read.
+    pub foreground: Option<TerminalProcessEnvironment>,
+}
+
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct TerminalProcessEnvironment {
+    pub pid: u32,
+    /// The name of the executable.
+    pub name: String,
+    /// The variables the process was started with, which doesn't include the
+    /// ones it changed itself since.
+    pub variables: Vec<(String, String)>,
+}